@@ -45,6 +45,7 @@ dummyCredential gc addr key pValidTo pCreatedAt = NormalAC (CredentialDeployment
       cdvArData = OrdMap.empty,
       cdvPolicy = Policy {
         pItems = OrdMap.empty,
+        pStatements = [],
         ..
         },
       ..
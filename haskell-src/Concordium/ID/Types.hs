@@ -9,6 +9,7 @@
 module Concordium.ID.Types where
 
 import Data.Word
+import Data.Bits((.&.), (.|.), complement)
 import Data.Data(Data, Typeable)
 import Data.ByteString(ByteString)
 import Data.ByteString.Short(ShortByteString)
@@ -41,6 +42,7 @@ import Concordium.Crypto.ByteStringHelpers
 import Concordium.Crypto.FFIDataTypes
 import Concordium.ID.Parameters
 import Concordium.Common.Time
+import Concordium.Common.Version
 import qualified Concordium.Crypto.SHA256 as SHA256
 
 accountAddressSize :: Int
@@ -375,27 +377,178 @@ instance FromJSON AttributeTag where
 instance ToJSON AttributeTag where
   toJSON tag = maybe "UNKNOWN" toJSON $ Map.lookup tag invMapping
 
+-- |Proof of an attribute statement, either a range proof or a proof of a
+-- set (non-)membership circuit. Opaque from the Haskell side, but parsed
+-- structurally in the binary serialization since it is not length-prefixed.
+newtype AttributeStatementProof = AttributeStatementProof ShortByteString
+    deriving(Eq)
+    deriving(Show) via ByteStringHex
+    deriving(ToJSON) via ByteStringHex
+    deriving(FromJSON) via ByteStringHex
+
+putAttributeStatementProof :: Putter AttributeStatementProof
+putAttributeStatementProof (AttributeStatementProof bs) = putShortByteString bs
+
+-- |Parse a proof that consists of the given number of group elements and
+-- scalars, followed by an inner product proof. Group elements are 48 bytes and
+-- scalars 32 bytes. The inner product proof is a length-prefixed list of pairs
+-- of group elements followed by two scalars.
+getProofWithInnerProduct :: Int -> Int -> Get AttributeStatementProof
+getProofWithInnerProduct numPoints numScalars = do
+  prefix <- getByteString (48 * numPoints + 32 * numScalars)
+  l <- getWord32be
+  ipProof <- getByteString (2 * 48 * fromIntegral l + 2 * 32)
+  return $! AttributeStatementProof (BSS.toShort (prefix <> runPut (putWord32be l) <> ipProof))
+
+-- |Parse a range proof.
+getRangeProof :: Get AttributeStatementProof
+getRangeProof = getProofWithInnerProduct 4 3
+
+-- |Parse a proof of a set (non-)membership circuit.
+getR1CSProof :: Get AttributeStatementProof
+getR1CSProof = getProofWithInnerProduct 8 3
+
+-- |A statement about an attribute that is not revealed, together with a proof
+-- with respect to the commitment to the attribute in the credential.
+-- NB: This must be kept consistent with AttributeStatementWithProof in id/types.rs.
+data AttributeStatementWithProof =
+  -- |The attribute lies in the interval [lower, upper).
+  AttributeInRange {
+    asAttributeTag :: !AttributeTag,
+    asLower :: !AttributeValue,
+    asUpper :: !AttributeValue,
+    asProof :: !AttributeStatementProof
+    }
+  -- |The attribute is one of the values in the set.
+  | AttributeInSet {
+    asAttributeTag :: !AttributeTag,
+    asSet :: ![AttributeValue],
+    asProof :: !AttributeStatementProof
+    }
+  -- |The attribute is none of the values in the set.
+  | AttributeNotInSet {
+    asAttributeTag :: !AttributeTag,
+    asSet :: ![AttributeValue],
+    asProof :: !AttributeStatementProof
+    }
+  deriving(Eq, Show)
+
+instance Serialize AttributeStatementWithProof where
+  put AttributeInRange{..} =
+    putWord8 0 <>
+    put asAttributeTag <>
+    put asLower <>
+    put asUpper <>
+    putAttributeStatementProof asProof
+  put AttributeInSet{..} =
+    putWord8 1 <>
+    put asAttributeTag <>
+    putWord16be (fromIntegral (length asSet)) <>
+    mapM_ put asSet <>
+    putAttributeStatementProof asProof
+  put AttributeNotInSet{..} =
+    putWord8 2 <>
+    put asAttributeTag <>
+    putWord16be (fromIntegral (length asSet)) <>
+    mapM_ put asSet <>
+    putAttributeStatementProof asProof
+
+  get = getWord8 >>= \case
+    0 -> AttributeInRange <$> get <*> get <*> get <*> getRangeProof
+    1 -> AttributeInSet <$> get <*> getSet <*> getR1CSProof
+    2 -> AttributeNotInSet <$> get <*> getSet <*> getR1CSProof
+    n -> fail $ "Unsupported attribute statement tag " ++ show n
+    where getSet = do
+            l <- fromIntegral <$> getWord16be
+            replicateM l get
+
+instance ToJSON AttributeStatementWithProof where
+  toJSON AttributeInRange{..} = object [
+    "type" .= ("attributeInRange" :: Text.Text),
+    "contents" .= object [
+        "attributeTag" .= asAttributeTag,
+        "lower" .= asLower,
+        "upper" .= asUpper,
+        "proof" .= asProof
+        ]
+    ]
+  toJSON AttributeInSet{..} = object [
+    "type" .= ("attributeInSet" :: Text.Text),
+    "contents" .= object [
+        "attributeTag" .= asAttributeTag,
+        "set" .= asSet,
+        "proof" .= asProof
+        ]
+    ]
+  toJSON AttributeNotInSet{..} = object [
+    "type" .= ("attributeNotInSet" :: Text.Text),
+    "contents" .= object [
+        "attributeTag" .= asAttributeTag,
+        "set" .= asSet,
+        "proof" .= asProof
+        ]
+    ]
+
+instance FromJSON AttributeStatementWithProof where
+  parseJSON = withObject "AttributeStatementWithProof" $ \v -> do
+    ty <- v .: "type"
+    c <- v .: "contents"
+    -- The proofs are checked to be well-formed, so that the binary
+    -- serialization of the statement can be parsed again.
+    let proof getProof = do
+          AttributeStatementProof bs <- c .: "proof"
+          case runGet (getProof <* checkEmpty) (BSS.fromShort bs) of
+            Left err -> fail $ "Malformed attribute statement proof: " ++ err
+            Right p -> return p
+        checkEmpty = isEmpty >>= flip unless (fail "Trailing bytes.")
+    case (ty :: Text.Text) of
+      "attributeInRange" ->
+        AttributeInRange <$> c .: "attributeTag" <*> c .: "lower" <*> c .: "upper" <*> proof getRangeProof
+      "attributeInSet" ->
+        AttributeInSet <$> c .: "attributeTag" <*> c .: "set" <*> proof getR1CSProof
+      "attributeNotInSet" ->
+        AttributeNotInSet <$> c .: "attributeTag" <*> c .: "set" <*> proof getR1CSProof
+      _ -> fail $ "Unsupported attribute statement type " ++ Text.unpack ty
+
+-- |Version of the format of attribute statements in policies. It is part of
+-- both the binary and the JSON serialization of policies with statements.
+attributeStatementsVersion :: Version
+attributeStatementsVersion = 1
+
+-- |Bit set in the number of revealed attributes in the binary serialization of
+-- a policy to indicate that it is followed by attribute statements.
+policyStatementsFlag :: Word16
+policyStatementsFlag = 0x8000
+
 data Policy = Policy {
   -- |Validity of this credential.
   pValidTo :: CredentialValidTo,
   -- |Creation of this credential
   pCreatedAt :: CredentialCreatedAt,
   -- |List of items in this attribute list.
-  pItems :: Map.Map AttributeTag AttributeValue
+  pItems :: Map.Map AttributeTag AttributeValue,
+  -- |Statements, with proofs, about attributes that are not revealed.
+  pStatements :: [AttributeStatementWithProof]
   } deriving(Eq, Show)
 
 instance ToJSON Policy where
-  toJSON Policy{..} = object [
+  toJSON Policy{..} = object $ [
     "validTo" .= pValidTo,
     "createdAt" .= pCreatedAt,
     "revealedAttributes" .= pItems
-    ]
+    ] ++ ["attributeStatements" .= Versioned attributeStatementsVersion pStatements | not (null pStatements)]
 
 instance FromJSON Policy where
   parseJSON = withObject "Policy" $ \v -> do
     pValidTo <- v .: "validTo"
     pCreatedAt <- v .: "createdAt"
     pItems <- v .: "revealedAttributes"
+    pStatements <- v .:? "attributeStatements" >>= \case
+      Nothing -> return []
+      Just (Versioned version statements) -> do
+        unless (version == attributeStatementsVersion) $
+          fail $ "Unsupported version of attribute statements " ++ show version
+        return statements
     return Policy{..}
 
 -- |Unique identifier of the anonymity revoker.
@@ -631,21 +784,42 @@ instance FromJSON CredentialDeploymentValues where
     cdvPolicy <- v .: "policy"
     return CredentialDeploymentValues{..}
 
+-- |Parse a policy. If the 'policyStatementsFlag' is set in the number of
+-- revealed attributes, they are followed by the version of the statements and
+-- a non-empty list of statements.
 getPolicy :: Get Policy
 getPolicy = do
   pValidTo <- get
   pCreatedAt <- get
-  l <- fromIntegral <$> getWord16be
-  pItems <- safeFromAscList =<< replicateM l (getTwoOf get get)
+  l <- getWord16be
+  pItems <- safeFromAscList =<< replicateM (fromIntegral (l .&. complement policyStatementsFlag)) (getTwoOf get get)
+  pStatements <- if l .&. policyStatementsFlag == 0 then return [] else do
+    version <- getVersion
+    unless (version == attributeStatementsVersion) $
+      fail $ "Unsupported version of attribute statements " ++ show version
+    n <- getWord16be
+    -- The serialization must be canonical, so an empty list of statements
+    -- must use the original format.
+    when (n == 0) $ fail "Policy has no attribute statements."
+    replicateM (fromIntegral n) get
   return Policy{..}
 
+-- |Serialize a policy. Policies without statements are serialized in the
+-- original format.
 putPolicy :: Putter Policy
 putPolicy Policy{..} =
-  let l = length pItems
+  let l = fromIntegral (length pItems)
+      items = mapM_ (putTwoOf put put) (Map.toAscList pItems)
   in put pValidTo <>
      put pCreatedAt <>
-     putWord16be (fromIntegral l) <>
-     mapM_ (putTwoOf put put) (Map.toAscList pItems)
+     if null pStatements then
+       putWord16be l <> items
+     else
+       putWord16be (l .|. policyStatementsFlag) <>
+       items <>
+       putVersion attributeStatementsVersion <>
+       putWord16be (fromIntegral (length pStatements)) <>
+       mapM_ put pStatements
 
 instance Serialize CredentialDeploymentValues where
   get = do
@@ -1,6 +1,7 @@
 {-# LANGUAGE ScopedTypeVariables #-}
 module ConcordiumTests.ID.Types where
 
+import Concordium.Common.Time
 import Concordium.Common.Version
 import Concordium.ID.Types
 import qualified Data.FixedByteString as FBS
@@ -64,6 +65,28 @@ checkInitialCDICompatibility filename referenceFile = do
       -- we do unpack for better error reporting, easier to compare lists of word8s
       assertEqual "Incompatible binary serializations." (BS.unpack referenceOutput) (BS.unpack (S.encode input))
 
+-- Check that a credential with attribute statements in its policy round trips through the binary
+-- serialization, and that its policy round trips through the JSON serialization.
+checkCDIStatementsRoundTrip :: FilePath -> FilePath -> Expectation
+checkCDIStatementsRoundTrip filename referenceFile = do
+  eitherDecodeFileStrict filename >>= \case
+    Left err -> assertFailure err
+    Right (input :: Versioned CredentialDeploymentInformation) -> do
+      referenceOutput <- BS.readFile referenceFile
+      let policy = cdvPolicy (cdiValues (vValue input))
+      assertBool "The policy has no attribute statements." (not (null (pStatements policy)))
+      assertEqual "Binary serialization does not round trip." (Right input) (S.decode referenceOutput)
+      assertEqual "JSON serialization of the policy does not round trip." (Right policy) (eitherDecode (encode policy))
+
+-- Check that a policy with the flag for attribute statements, but no statements, is rejected since
+-- the serialization must be canonical.
+checkEmptyStatementsRejected :: Expectation
+checkEmptyStatementsRejected = do
+  let ym = S.encode (YearMonth 2022 5)
+  case S.runGet getPolicy (ym <> ym <> BS.pack [0x80, 0, 1, 0, 0]) of
+    Left _ -> return ()
+    Right p -> assertFailure $ "Parsed a policy without attribute statements: " ++ show p
+
 tests :: Spec
 tests = describe "Concordium.ID" $ do
   specify "account address JSON" $ withMaxSuccess 100000 testJSON
@@ -71,3 +94,6 @@ tests = describe "Concordium.ID" $ do
   specify "JSON/binary CDI serialization check" $ checkCDICompatibility "testdata/cdi.json" "testdata/cdi.bin"
   specify "JSON/binary CDI Commitments check" $ checkCDICommitmentsCompatibility "testdata/cdi.json" "testdata/cdi-coms.bin"
   specify "JSON/binary Initial CDI serialization check" $ checkInitialCDICompatibility "testdata/icdi.json" "testdata/icdi.bin"
+  specify "JSON/binary CDI with attribute statements serialization check" $ checkCDICompatibility "testdata/cdi-statements.json" "testdata/cdi-statements.bin"
+  specify "CDI with attribute statements round trip" $ checkCDIStatementsRoundTrip "testdata/cdi-statements.json" "testdata/cdi-statements.bin"
+  specify "Policy with empty attribute statements is rejected" checkEmptyStatementsRejected
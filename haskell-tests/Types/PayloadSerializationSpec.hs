@@ -236,6 +236,7 @@ genCredentialDeploymentInformation = do
     pValidTo <- ym
     pCreatedAt <- ym
     let pItems = Map.empty
+    let pStatements = []
     return Policy{..}
   cdiProofs <- do l <- choose (0, 10000)
                   Proofs . BSS.pack <$> vector l
@@ -86,6 +86,7 @@ genInitialCredentialDeploymentInformation = do
     pValidTo <- ym
     pCreatedAt <- ym
     let pItems = Map.empty
+    let pStatements = []
     return Policy{..}
   let icdiValues = InitialCredentialDeploymentValues{..}
   icdiSig <- IpCdiSignature . BSS.pack <$> vector 64
@@ -1,31 +1,51 @@
 use anyhow::{bail, ensure};
 use crypto_common::{
-    base16_encode_string, types::TransactionTime, SerdeDeserialize, SerdeSerialize, Versioned,
-    VERSION_0,
+    base16_decode_string, base16_encode_string,
+    encryption::Password,
+    types::{Timestamp, TransactionTime},
+    SerdeDeserialize, SerdeSerialize, Versioned, VERSION_0,
 };
-use ed25519_dalek::{ExpandedSecretKey, PublicKey};
 use id::{
     constants::{ArCurve, AttributeKind, IpPairing},
     identity_provider::{
-        create_initial_cdi, sign_identity_object, validate_request as ip_validate_request,
+        create_initial_cdi_with_signer, renew_identity_object_with_signer,
+        sign_identity_object_with_signer, validate_request as ip_validate_request, Reason,
+        SigningError,
     },
+    revocation::{sign_status_list, SignedStatusList, StatusList},
     types::*,
 };
+use identity_provider_service::{
+    metrics::Metrics,
+    signer::{self, IdentityProviderSigner},
+    storage::{
+        import_file_database, Cancellation, CancellationKind, FileStorage, PendingEntry,
+        PendingStatus, SqliteStorage, Storage,
+    },
+    submission_queue::{self, Enqueued, RetryPolicy, SubmissionQueue},
+    verifier::{
+        self, AttributeListPolicy, JwtConfig, JwtVerifier, OidcConfig, OidcVerifier, Parameters,
+        RedirectVerifier, VerificationError, Verifier,
+    },
+};
 use log::{error, info, warn};
 use reqwest::Client;
-use serde_json::{from_str, json, to_value};
+use serde_json::{from_str, from_value, json, to_value};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     convert::Infallible,
     fs,
+    net::IpAddr,
     path::PathBuf,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use structopt::StructOpt;
-use url::Url;
-use warp::{http::StatusCode, hyper::header::LOCATION, Filter, Rejection, Reply};
-
-type ExampleAttributeList = AttributeList<id::constants::BaseField, AttributeKind>;
+use warp::{
+    http::StatusCode,
+    hyper::header::{LOCATION, SET_COOKIE},
+    Filter, Rejection, Reply,
+};
 
 /// Structure used to receive the correct command line arguments.
 #[derive(Debug, StructOpt)]
@@ -44,6 +64,53 @@ struct IdentityProviderServiceConfiguration {
         env = "IDENTITY_PROVIDER"
     )]
     identity_provider_file: PathBuf,
+    #[structopt(
+        long = "identity-provider-password",
+        help = "Password with which the identity provider file is encrypted. If not given the \
+                file is expected to be unencrypted.",
+        env = "IDENTITY_PROVIDER_PASSWORD",
+        hide_env_values = true
+    )]
+    identity_provider_password: Option<String>,
+    #[structopt(
+        long = "signer",
+        help = "Where the ed25519 key of the identity provider, used for signing initial \
+                accounts, is kept. Either `software`, in which case it is read from the \
+                identity provider file, or `pkcs11`, in which case it is kept in a PKCS#11 token.",
+        default_value = "software",
+        possible_values = &["software", "pkcs11"],
+        env = "SIGNER"
+    )]
+    signer: String,
+    #[cfg(feature = "pkcs11")]
+    #[structopt(
+        long = "pkcs11-module",
+        help = "Path of the PKCS#11 module, e.g., `/usr/lib/softhsm/libsofthsm2.so`.",
+        env = "PKCS11_MODULE"
+    )]
+    pkcs11_module: Option<PathBuf>,
+    #[cfg(feature = "pkcs11")]
+    #[structopt(
+        long = "pkcs11-token-label",
+        help = "Label of the PKCS#11 token containing the key.",
+        env = "PKCS11_TOKEN_LABEL"
+    )]
+    pkcs11_token_label: Option<String>,
+    #[cfg(feature = "pkcs11")]
+    #[structopt(
+        long = "pkcs11-pin",
+        help = "User PIN of the PKCS#11 token.",
+        env = "PKCS11_PIN",
+        hide_env_values = true
+    )]
+    pkcs11_pin: Option<String>,
+    #[cfg(feature = "pkcs11")]
+    #[structopt(
+        long = "pkcs11-key-label",
+        help = "Label of the ed25519 private key in the PKCS#11 token.",
+        env = "PKCS11_KEY_LABEL"
+    )]
+    pkcs11_key_label: Option<String>,
     #[structopt(
         long = "anonymity-revokers",
         help = "File with the list of anonymity revokers as JSON.",
@@ -64,6 +131,24 @@ struct IdentityProviderServiceConfiguration {
         env = "RETRIEVE_BASE"
     )]
     retrieve_url: url::Url,
+    #[structopt(
+        long = "verifier",
+        help = "How users are verified. Either `redirect`, where the user is redirected to the \
+                identity verifier at `id-verification-url`, `oidc`, where the user logs in with \
+                an OpenID Connect provider, or `jwt`, where the user brings back an attestation \
+                signed by a trusted attester. The `oidc` and `jwt` verifiers are configured by \
+                `verifier-config`.",
+        default_value = "redirect",
+        possible_values = &["redirect", "oidc", "jwt"],
+        env = "VERIFIER"
+    )]
+    verifier: String,
+    #[structopt(
+        long = "verifier-config",
+        help = "File with the configuration of the `oidc` or `jwt` verifier as JSON.",
+        env = "VERIFIER_CONFIG"
+    )]
+    verifier_config: Option<PathBuf>,
     #[structopt(
         long = "id-verification-url",
         help = "URL of the identity verifier. This is the URL where the user will be redirected \
@@ -88,6 +173,103 @@ struct IdentityProviderServiceConfiguration {
         env = "WALLET_PROXY_BASE"
     )]
     wallet_proxy_base: url::Url,
+    #[structopt(
+        long = "storage-backend",
+        help = "Where to store the data of the service. Either `files` or `sqlite`. The `files` \
+                backend stores every entry in a separate file in the `database` directory. The \
+                `sqlite` backend stores everything in the database given by `--database`, into \
+                which an existing `files` database can be imported with \
+                `--import-file-database`.",
+        default_value = "files",
+        possible_values = &["sqlite", "files"],
+        env = "STORAGE_BACKEND"
+    )]
+    storage_backend: String,
+    #[structopt(
+        long = "database",
+        help = "Location of the SQLite database. It is created if it does not exist.",
+        default_value = "database.sqlite",
+        env = "DATABASE"
+    )]
+    database: PathBuf,
+    #[structopt(
+        long = "import-file-database",
+        help = "Directory of a database written by the `files` backend. If given, its entries are \
+                imported into the SQLite database on startup. Entries that already exist in the \
+                SQLite database are not overwritten.",
+        env = "IMPORT_FILE_DATABASE"
+    )]
+    import_file_database: Option<PathBuf>,
+    #[structopt(
+        long = "import-file-database-backup",
+        help = "Directory of the deleted entries of the database given by `import-file-database`.",
+        default_value = "database-deleted",
+        env = "IMPORT_FILE_DATABASE_BACKUP"
+    )]
+    import_file_database_backup: PathBuf,
+    #[structopt(
+        long = "admin-port",
+        default_value = "8102",
+        help = "Port on which the admin API and the metrics will listen on.",
+        env = "IDENTITY_PROVIDER_SERVICE_ADMIN_PORT"
+    )]
+    admin_port: u16,
+    #[structopt(
+        long = "admin-address",
+        default_value = "127.0.0.1",
+        help = "Address on which the admin API and the metrics will listen on.",
+        env = "IDENTITY_PROVIDER_SERVICE_ADMIN_ADDRESS"
+    )]
+    admin_address: IpAddr,
+    #[structopt(
+        long = "admin-token",
+        help = "Token that authenticates requests to the admin API. It must be given as a bearer \
+                token in the `Authorization` header. If not given the admin API is disabled, and \
+                only the metrics are served.",
+        env = "ADMIN_TOKEN",
+        hide_env_values = true
+    )]
+    admin_token: Option<String>,
+    #[structopt(
+        long = "submission-retry-delay",
+        default_value = "5",
+        help = "Delay in seconds before retrying a failed initial account submission. The delay \
+                doubles with each failed attempt. This is also the delay between queries of the \
+                status of a submitted transaction.",
+        env = "SUBMISSION_RETRY_DELAY"
+    )]
+    submission_retry_delay: u64,
+    #[structopt(
+        long = "submission-max-retry-delay",
+        default_value = "600",
+        help = "Maximum delay in seconds between retries of a failed initial account submission.",
+        env = "SUBMISSION_MAX_RETRY_DELAY"
+    )]
+    submission_max_retry_delay: u64,
+    #[structopt(
+        long = "submission-max-attempts",
+        default_value = "10",
+        help = "Number of failed attempts after which an initial account submission is given up \
+                on. It can then be re-driven via the admin API.",
+        env = "SUBMISSION_MAX_ATTEMPTS"
+    )]
+    submission_max_attempts: u32,
+    #[structopt(
+        long = "submission-poll-interval",
+        default_value = "5",
+        help = "Interval in seconds at which pending initial account submissions are processed.",
+        env = "SUBMISSION_POLL_INTERVAL"
+    )]
+    submission_poll_interval: u64,
+    #[structopt(
+        long = "status-list-interval",
+        default_value = "3600",
+        help = "Interval in seconds at which the signed status list of revoked identities is \
+                re-published. It is in addition published at startup and whenever an identity is \
+                revoked.",
+        env = "STATUS_LIST_INTERVAL"
+    )]
+    status_list_interval: u64,
 }
 
 #[derive(SerdeSerialize, SerdeDeserialize)]
@@ -126,75 +308,41 @@ struct IdentityTokenContainer {
 }
 
 /// The state the server maintains in-between the requests, consisting of
-/// the resolved configuration. In particular in this prototype the private keys
-/// are maintain in-memory.
+/// the resolved configuration. The private keys of the identity provider are
+/// only accessed via the signer.
 struct ServerConfig {
-    ip_data: IpData<IpPairing>,
-    global: GlobalContext<ArCurve>,
-    ars: ArInfos<ArCurve>,
-    id_verification_url: url::Url,
-    id_verification_query_url: url::Url,
-    retrieve_url: url::Url,
+    ip_info:               IpInfo<IpPairing>,
+    signer:                Arc<dyn IdentityProviderSigner>,
+    global:                GlobalContext<ArCurve>,
+    ars:                   ArInfos<ArCurve>,
+    verifier:              Arc<dyn Verifier>,
+    attribute_list_policy: AttributeListPolicy,
+    retrieve_url:          url::Url,
     submit_credential_url: url::Url,
 }
 
-/// A mockup of a database to store all the data.
-/// In production this would be a real database, here we store everything as
-/// files on disk and synchronize access to disk via a lock. On deletion files
-/// are moved into a 'backup_root' folder.
+/// The database of the service. This is a thin wrapper around the configured
+/// [Storage] which takes care of converting to and from the JSON format in
+/// which entries are stored.
 #[derive(Clone)]
 struct DB {
-    /// Root directory where all the data is stored.
-    root:        std::path::PathBuf,
-    /// Root of the backup directory where we store "deleted" files.
-    backup_root: std::path::PathBuf,
-    /// And a hashmap of pending entries. Pending entries are also stored in the
-    /// filesystem, but we cache them here since they have to be accessed
-    /// often. We put it behind a mutex to sync all accesses, to the hashmap
-    /// as well as to the filesystem, which is implicit. In a real database
-    /// this would be done differently.
-    pending:     Arc<Mutex<HashMap<String, PendingEntry>>>,
-}
-
-#[derive(SerdeSerialize, SerdeDeserialize, Clone)]
-#[serde(rename_all = "lowercase")]
-/// When the initial account transaction is submitted we use this type to keep
-/// track of its status.
-enum PendingStatus {
-    /// The transaction was submitted, and is currently in the state indicated
-    /// by the submission status.
-    Submitted {
-        submission_id: String,
-        status:        SubmissionStatus,
-    },
-    /// The transaction could not be submitted due to, most likely, network
-    /// issues. It should be retried.
-    CouldNotSubmit,
-}
-
-#[derive(SerdeDeserialize, SerdeSerialize)]
-#[serde(rename_all = "camelCase")]
-/// Successful response from the wallet proxy.
-/// It contains a JSON body with a single field `submissionId`.
-struct InitialAccountReponse {
-    submission_id: String,
-}
-
-///
-#[derive(SerdeSerialize, SerdeDeserialize, Clone)]
-struct PendingEntry {
-    pub status: PendingStatus,
-    pub value:  serde_json::Value,
+    storage:          Arc<dyn Storage>,
+    /// Held while a status list is built and written, so that a list built
+    /// from an older set of revocations never overwrites a newer one.
+    status_list_lock: Arc<Mutex<()>>,
 }
 
 impl ServerConfig {
     /// Resolve the configuration from the command-line arguments, checking that
-    /// all values have the correct formats.
-    pub fn from_opts(config: &IdentityProviderServiceConfiguration) -> anyhow::Result<Self> {
-        let ip_data_contents = fs::read_to_string(&config.identity_provider_file)?;
+    /// all values have the correct formats. The client is used by the verifier.
+    pub fn from_opts(
+        config: &IdentityProviderServiceConfiguration,
+        client: &Client,
+    ) -> anyhow::Result<Self> {
+        let (ip_info, signer) = open_signer(config)?;
+        signer::check_signer(signer.as_ref(), &ip_info)?;
         let ar_info_contents = fs::read_to_string(&config.anonymity_revokers_file)?;
         let global_context_contents = fs::read_to_string(&config.global_context_file)?;
-        let ip_data = from_str(&ip_data_contents)?;
         let versioned_global = from_str::<Versioned<_>>(&global_context_contents)?;
         let versioned_ar_infos = from_str::<Versioned<_>>(&ar_info_contents)?;
         ensure!(
@@ -208,52 +356,161 @@ impl ServerConfig {
         let mut submit_credential_url = config.wallet_proxy_base.clone();
         submit_credential_url.set_path("v0/submitCredential/");
         Ok(ServerConfig {
-            ip_data,
+            ip_info,
+            signer,
             global: versioned_global.value,
             ars: versioned_ar_infos.value,
-            id_verification_url: config.id_verification_url.clone(),
-            id_verification_query_url: config
+            verifier: open_verifier(config, client)?,
+            attribute_list_policy: AttributeListPolicy::default(),
+            retrieve_url: config.retrieve_url.clone(),
+            submit_credential_url,
+        })
+    }
+}
+
+/// Make the verifier configured by the command line arguments.
+fn open_verifier(
+    config: &IdentityProviderServiceConfiguration,
+    client: &Client,
+) -> anyhow::Result<Arc<dyn Verifier>> {
+    let read_config = || -> anyhow::Result<String> {
+        match &config.verifier_config {
+            Some(path) => Ok(fs::read_to_string(path)?),
+            None => bail!("The {} verifier requires verifier-config.", config.verifier),
+        }
+    };
+    match config.verifier.as_str() {
+        "redirect" => Ok(Arc::new(RedirectVerifier::new(
+            client.clone(),
+            config.id_verification_url.clone(),
+            config
                 .id_verification_query_url
                 .as_ref()
                 .cloned()
                 .unwrap_or_else(|| config.id_verification_url.clone()),
-            retrieve_url: config.retrieve_url.clone(),
-            submit_credential_url,
-        })
+        ))),
+        "oidc" => {
+            let oidc_config: OidcConfig = from_str(&read_config()?)?;
+            let mut redirect_url = config.retrieve_url.clone();
+            redirect_url.set_path("api/identity/callback");
+            Ok(Arc::new(OidcVerifier::new(
+                client.clone(),
+                oidc_config,
+                redirect_url,
+            )))
+        }
+        "jwt" => {
+            let jwt_config: JwtConfig = from_str(&read_config()?)?;
+            Ok(Arc::new(JwtVerifier::new(
+                jwt_config,
+                config.retrieve_url.clone(),
+            )))
+        }
+        other => bail!("Unsupported verifier {}.", other),
     }
 }
 
-impl DB {
-    /// Create a new database using the given root and backup_root paths.
-    /// The 'backup_root' path is used to place deleted entries.
-    ///
-    /// This function will attempt to reconstruct the in-memory pending table if
-    /// it finds any pending entries.
-    pub fn new(root: std::path::PathBuf, backup_root: std::path::PathBuf) -> anyhow::Result<Self> {
-        // Create the 'database' directories for storing IdentityObjects and
-        // AnonymityRevocationRecords.
-        fs::create_dir_all(root.join("revocation"))?;
-        fs::create_dir_all(root.join("identity"))?;
-        fs::create_dir_all(root.join("pending"))?;
-        fs::create_dir_all(root.join("requests"))?;
-        let mut hm = HashMap::new();
-        for file in fs::read_dir(root.join("pending"))?.flatten() {
-            if file.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
-                let name = file
-                    .file_name()
-                    .into_string()
-                    .expect("Base16 strings are valid strings.");
-                let contents = fs::read_to_string(file.path())?;
-                let entry = from_str::<PendingEntry>(&contents)?;
-                hm.insert(name, entry);
+/// Make the signer configured by the command line arguments, returning it
+/// together with the public data of the identity provider.
+fn open_signer(
+    config: &IdentityProviderServiceConfiguration,
+) -> anyhow::Result<(IpInfo<IpPairing>, Arc<dyn IdentityProviderSigner>)> {
+    let password = config
+        .identity_provider_password
+        .as_ref()
+        .map(|password| Password::from(password.clone()));
+    match config.signer.as_str() {
+        "software" => {
+            let (ip_info, signer) =
+                signer::read_software_signer(&config.identity_provider_file, password.as_ref())?;
+            Ok((ip_info, Arc::new(signer)))
+        }
+        "pkcs11" => open_pkcs11_signer(config, password.as_ref()),
+        other => bail!("Unsupported signer {}.", other),
+    }
+}
+
+#[cfg(feature = "pkcs11")]
+fn open_pkcs11_signer(
+    config: &IdentityProviderServiceConfiguration,
+    password: Option<&Password>,
+) -> anyhow::Result<(IpInfo<IpPairing>, Arc<dyn IdentityProviderSigner>)> {
+    let (module, token_label, pin, key_label) = match (
+        &config.pkcs11_module,
+        &config.pkcs11_token_label,
+        &config.pkcs11_pin,
+        &config.pkcs11_key_label,
+    ) {
+        (Some(module), Some(token_label), Some(pin), Some(key_label)) => {
+            (module, token_label, pin, key_label)
+        }
+        _ => bail!(
+            "The pkcs11 signer requires pkcs11-module, pkcs11-token-label, pkcs11-pin and \
+             pkcs11-key-label."
+        ),
+    };
+    let cdi_signer =
+        signer::Pkcs11CdiSigner::new(module, token_label, pin.clone(), key_label.clone())?;
+    let (ip_info, signer) =
+        signer::read_signer_with(&config.identity_provider_file, password, cdi_signer)?;
+    Ok((ip_info, Arc::new(signer)))
+}
+
+#[cfg(not(feature = "pkcs11"))]
+fn open_pkcs11_signer(
+    _config: &IdentityProviderServiceConfiguration,
+    _password: Option<&Password>,
+) -> anyhow::Result<(IpInfo<IpPairing>, Arc<dyn IdentityProviderSigner>)> {
+    bail!("The service was built without support for PKCS#11. Build it with the `pkcs11` feature.")
+}
+
+/// Open the storage configured by the command line arguments, importing the
+/// file database first if requested.
+fn open_storage(config: &IdentityProviderServiceConfiguration) -> anyhow::Result<Arc<dyn Storage>> {
+    match config.storage_backend.as_str() {
+        "files" => {
+            ensure!(
+                config.import_file_database.is_none(),
+                "Importing a file database is only supported by the SQLite backend."
+            );
+            // Create the 'database' directories for storing IdentityObjects and
+            // AnonymityRevocationRecords.
+            Ok(Arc::new(FileStorage::new(
+                PathBuf::from("database"),
+                PathBuf::from("database-deleted"),
+            )?))
+        }
+        "sqlite" => {
+            let storage = SqliteStorage::open(&config.database)?;
+            if let Some(root) = &config.import_file_database {
+                let summary =
+                    import_file_database(root, &config.import_file_database_backup, &storage)?;
+                info!("Imported the file database: {:?}", summary);
             }
+            Ok(Arc::new(storage))
         }
-        let pending = Arc::new(Mutex::new(hm));
-        Ok(Self {
-            root,
-            backup_root,
-            pending,
-        })
+        other => bail!("Unsupported storage backend {}.", other),
+    }
+}
+
+impl DB {
+    /// Create a database backed by the given storage.
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            status_list_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Run the given operations on the database on the thread pool for blocking
+    /// tasks. The storage does blocking I/O, which must not hold up the
+    /// threads that run the request handlers.
+    pub async fn run<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&DB) -> anyhow::Result<R> + Send + 'static,
+    ) -> anyhow::Result<R> {
+        let db = self.clone();
+        tokio::task::spawn_blocking(move || f(&db)).await?
     }
 
     /// Write the validated request, so that it can be retrieved and used to
@@ -264,165 +521,202 @@ impl DB {
         key: &str,
         identity_object_request: &IdentityObjectRequest,
     ) -> anyhow::Result<()> {
-        let _lock = self
-            .pending
-            .lock()
-            .expect("Cannot acquire a lock, which means something is very wrong.");
-        {
-            let file = std::fs::File::create(self.root.join("requests").join(key))?;
-            serde_json::to_writer(file, identity_object_request)?;
-        }
-        Ok(())
+        self.storage
+            .write_request_record(key, &to_value(identity_object_request)?)
     }
 
     /// Read a validated request under the given key.
     pub fn read_request_record(&self, key: &str) -> anyhow::Result<IdentityObjectRequest> {
-        let contents = {
-            let _lock = self
-                .pending
-                .lock()
-                .expect("Cannot acquire a lock, which means something is very wrong.");
-            fs::read_to_string(self.root.join("requests").join(key))?
-        }; // drop the lock at this point
-           // It is more efficient to read the whole thing, and then deserialize
-        Ok(from_str::<IdentityObjectRequest>(&contents)?)
+        Ok(from_value(self.storage.read_request_record(key)?)?)
     }
 
     /// Write the anonymity revocation record under the given key.
-    /// The key should be a valid filename.
     pub fn write_revocation_record(
         &self,
         key: &str,
         record: AnonymityRevocationRecord<ArCurve>,
     ) -> anyhow::Result<()> {
-        let _lock = self
-            .pending
-            .lock()
-            .expect("Cannot acquire a lock, which means something is very wrong.");
-        {
-            // FIXME: We should be careful to not overwrite here.
-            let file = std::fs::File::create(self.root.join("revocation").join(key))?;
-            serde_json::to_writer(file, &Versioned {
+        self.storage.write_revocation_record(
+            key,
+            &to_value(&Versioned {
                 version: VERSION_0,
                 value:   record,
-            })?;
-        } // close the file
-          // and now drop the lock as well.
-        Ok(())
+            })?,
+        )
     }
 
-    /// Write the identity object under the given key. The key should be
-    /// a valid filename.
+    /// Write the identity object under the given key.
     pub fn write_identity_object(
         &self,
         key: &str,
         obj: &Versioned<IdentityObject<IpPairing, ArCurve, AttributeKind>>,
         init_credential: &Versioned<AccountCredentialMessage<IpPairing, ArCurve, AttributeKind>>,
-    ) -> anyhow::Result<()> {
-        let _lock = self
-            .pending
-            .lock()
-            .expect("Cannot acquire a lock, which means something is very wrong.");
-        {
-            let file = std::fs::File::create(self.root.join("identity").join(key))?;
-            let stored_obj = json!({
-                "identityObject": obj,
-                "accountAddress": AccountAddress::new(&obj.value.pre_identity_object.pub_info_for_ip.reg_id),
-                "credential": init_credential
-            });
-            serde_json::to_writer(file, &stored_obj)?;
-        }
-        Ok(())
+        entry: &PendingEntry,
+    ) -> anyhow::Result<bool> {
+        let stored_obj = json!({
+            "identityObject": obj,
+            "accountAddress": AccountAddress::new(&obj.value.pre_identity_object.pub_info_for_ip.reg_id),
+            "credential": init_credential
+        });
+        self.storage
+            .write_identity_object_with_pending(key, &stored_obj, entry)
     }
 
     /// Try to read the identity object under the given key, if it exists.
     pub fn read_identity_object(&self, key: &str) -> anyhow::Result<serde_json::Value> {
-        // ensure the key is valid base16 characters, which also ensures we are only
-        // reading in the subdirectory FIXME: This is an inefficient way of
-        // doing it.
-        if hex::decode(key).is_err() {
-            bail!("Invalid key.")
-        }
-
-        let contents = {
-            let _lock = self
-                .pending
-                .lock()
-                .expect("Cannot acquire a lock, which means something is very wrong.");
-            fs::read_to_string(self.root.join("identity").join(key))?
-        }; // drop the lock at this point
-           // It is more efficient to read the whole thing, and then deserialize
-        Ok(from_str::<serde_json::Value>(&contents)?)
+        self.storage.read_identity_object(key)
     }
 
-    /// Store the pending entry. This is only used in case of server-restart to
-    /// pupulate the pending table.
-    pub fn write_pending(
+    /// Replace the identity object under the given key by the renewed one,
+    /// keeping the initial account data, and return the updated entry. The
+    /// previous entry is the one the renewal was made from. Nothing is
+    /// written, and `None` returned, if the entry has changed since, or the
+    /// identity was cancelled.
+    pub fn write_renewed_identity_object(
         &self,
         key: &str,
-        status: PendingStatus,
-        value: serde_json::Value,
-    ) -> anyhow::Result<()> {
-        let mut lock = self
-            .pending
-            .lock()
-            .expect("Cannot acquire a lock, which means something is very wrong.");
+        previous: &serde_json::Value,
+        obj: &Versioned<IdentityObject<IpPairing, ArCurve, AttributeKind>>,
+    ) -> anyhow::Result<Option<serde_json::Value>> {
+        let mut stored_obj = previous.clone();
+        stored_obj["identityObject"] = to_value(obj)?;
+        if self
+            .storage
+            .replace_identity_object(key, previous, &stored_obj)?
         {
-            let file = std::fs::File::create(self.root.join("pending").join(key))?;
-            let value = PendingEntry { status, value };
-            serde_json::to_writer(file, &value)?;
-            lock.insert(key.to_string(), value);
+            Ok(Some(stored_obj))
+        } else {
+            Ok(None)
         }
-        Ok(())
     }
 
-    pub fn mark_finalized(&self, key: &str) {
-        let mut lock = self.pending.lock().unwrap();
-        lock.remove(key);
-        let pending_path = self.root.join("pending").join(key);
-        std::fs::remove_file(pending_path).unwrap();
+    /// Write the signed status list of the revoked identities.
+    pub fn write_status_list(&self, list: &SignedStatusList<ArCurve>) -> anyhow::Result<()> {
+        self.storage.write_status_list(&to_value(&Versioned {
+            version: VERSION_0,
+            value:   list,
+        })?)
     }
 
-    pub fn delete_all(&self, key: &str) {
-        let mut lock = self.pending.lock().unwrap();
-        let ar_record_path = self.root.join("revocation").join(key);
-        let id_path = self.root.join("identity").join(key);
-        let pending_path = self.root.join("pending").join(key);
+    /// Read the signed status list of the revoked identities, if it has been
+    /// published.
+    pub fn read_status_list(&self) -> anyhow::Result<Option<serde_json::Value>> {
+        self.storage.read_status_list()
+    }
 
-        std::fs::rename(
-            ar_record_path,
-            self.backup_root.join("revocation").join(key),
-        )
-        .unwrap();
-        std::fs::rename(id_path, self.backup_root.join("identity").join(key)).unwrap();
-        std::fs::remove_file(pending_path).unwrap();
-        lock.remove(key);
+    /// The `IdCredPub` of all revoked identities, in the order they were
+    /// revoked.
+    pub fn list_revoked(&self) -> anyhow::Result<Vec<ArCurve>> {
+        let mut revoked = self
+            .storage
+            .list_cancellations()?
+            .into_iter()
+            .filter(|(_, cancellation)| cancellation.kind == CancellationKind::Revoked)
+            .collect::<Vec<_>>();
+        revoked.sort_by(|a, b| (a.1.time, &a.0).cmp(&(b.1.time, &b.0)));
+        revoked
+            .into_iter()
+            .map(|(key, _)| base16_decode_string(&key))
+            .collect()
+    }
+
+    pub fn is_pending(&self, key: &str) -> anyhow::Result<bool> { self.storage.is_pending(key) }
+
+    /// Whether the initial account creation was given up on.
+    pub fn is_dead_letter(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(self.storage.read_dead_letter(key)?.is_some())
     }
 
-    pub fn is_pending(&self, key: &str) -> bool { self.pending.lock().unwrap().get(key).is_some() }
+    /// The status of the request under the given key, or `None` if there is no
+    /// such request.
+    pub fn request_status(&self, key: &str) -> anyhow::Result<Option<RequestStatus>> {
+        if self.storage.read_request_record(key).is_err() {
+            return Ok(None);
+        }
+        Ok(Some(RequestStatus::new(
+            self.storage.read_cancellation(key)?.as_ref(),
+            self.is_dead_letter(key)?,
+            self.is_pending(key)?,
+            self.storage.read_identity_object(key).is_ok(),
+        )))
+    }
+
+    /// All requests together with their status.
+    pub fn list_requests(&self) -> anyhow::Result<Vec<(String, RequestStatus)>> {
+        let cancellations: HashMap<_, _> = self.storage.list_cancellations()?.into_iter().collect();
+        let failed: HashSet<_> = self
+            .storage
+            .list_dead_letters()?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        let pending: HashSet<_> = self
+            .storage
+            .list_pending()?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        let issued: HashSet<_> = self
+            .storage
+            .list_identity_object_keys()?
+            .into_iter()
+            .collect();
+        Ok(self
+            .storage
+            .list_request_keys()?
+            .into_iter()
+            .map(|key| {
+                let status = RequestStatus::new(
+                    cancellations.get(&key),
+                    failed.contains(&key),
+                    pending.contains(&key),
+                    issued.contains(&key),
+                );
+                (key, status)
+            })
+            .collect())
+    }
 }
 
-#[derive(SerdeSerialize, SerdeDeserialize, Clone)]
+/// Status of an identity request, as seen by the admin API.
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
-/// Status of a submission as returned by the wallet-proxy.
-enum SubmissionStatus {
-    /// Submission is absent, most likely it was invalid.
-    Absent,
-    /// Submission is received, but not yet committed to any blocks.
-    Received,
-    /// Submission is committed to one or more blocks.
-    Committed,
-    /// Submission is finalized in a block.
-    Finalized,
+enum RequestStatus {
+    /// The request was validated, but no identity object has been created yet,
+    /// e.g., because the user has not completed the identity verification.
+    Validated,
+    /// The identity object was created, and the initial account creation is
+    /// pending.
+    Pending,
+    /// The identity object was created, but the initial account creation was
+    /// given up on.
+    Failed,
+    /// The identity object was created, and the initial account creation has
+    /// finalized.
+    Done,
+    /// The identity was rejected by an administrator before it was issued.
+    Rejected,
+    /// The identity was revoked by an administrator.
+    Revoked,
 }
 
-#[derive(SerdeSerialize, SerdeDeserialize)]
-#[serde(rename_all = "camelCase")]
-/// The part of the response we care about. Since the transaction
-/// will either be in a block, or not, and if it is, then the account will have
-/// been created.
-struct SubmissionStatusResponse {
-    status: SubmissionStatus,
+impl RequestStatus {
+    fn new(cancellation: Option<&Cancellation>, failed: bool, pending: bool, issued: bool) -> Self {
+        match cancellation {
+            Some(Cancellation {
+                kind: CancellationKind::Rejected,
+                ..
+            }) => RequestStatus::Rejected,
+            Some(Cancellation {
+                kind: CancellationKind::Revoked,
+                ..
+            }) => RequestStatus::Revoked,
+            None if failed => RequestStatus::Failed,
+            None if pending => RequestStatus::Pending,
+            None if issued => RequestStatus::Done,
+            None => RequestStatus::Validated,
+        }
+    }
 }
 
 /// Parameters of the get request.
@@ -434,108 +728,62 @@ struct GetParameters {
     redirect_uri: String,
 }
 
-/// Query the status of the transaction and update the status in the database if
-/// finalized, or if unable to submit the transaction successfully.
-async fn followup(
-    client: Client,
-    db: DB,
-    submission_url: url::Url,
-    mut query_url_base: url::Url,
-    key: String,
-) {
-    let v = {
-        let hm = db.pending.lock().unwrap();
-        hm.get(&key).cloned()
-    }; // release lock
-    if let Some(v) = v {
-        match &v.status {
-            PendingStatus::CouldNotSubmit => {
-                match submit_account_creation(&client, submission_url.clone(), &v.value).await {
-                    Ok(new_status) => {
-                        let mut hm = db.pending.lock().unwrap();
-                        if let Some(point) = hm.get_mut(&key) {
-                            point.status = new_status;
-                        }
-                    }
-                    Err(_) => {
-                        db.delete_all(&key);
-                        warn!("Account creation transaction rejected.");
-                    }
-                }
-            }
-            PendingStatus::Submitted { submission_id, .. } => {
-                query_url_base.set_path(&format!("v0/submissionStatus/{}", submission_id));
-                match client.get(query_url_base.clone()).send().await {
-                    Ok(response) => {
-                        match response.status() {
-                            StatusCode::OK => {
-                                match response.json::<SubmissionStatusResponse>().await {
-                                    Ok(ss) => {
-                                        match ss.status {
-                                            SubmissionStatus::Finalized => {
-                                                db.mark_finalized(&key);
-                                                info!("Account creation transaction finalized.");
-                                            }
-                                            SubmissionStatus::Absent => error!(
-                                                "An account creation transaction has gone \
-                                                 missing. This indicates a configuration error."
-                                            ),
-                                            // do nothing, wait for the next call
-                                            SubmissionStatus::Received => {}
-                                            SubmissionStatus::Committed => {}
-                                        }
-                                    }
-                                    Err(e) => error!(
-                                        "Received unexpected response when querying submission \
-                                         status: {}.",
-                                        e
-                                    ),
-                                }
-                            }
-                            other => error!(
-                                "Received unexpected response when querying submission status: {}.",
-                                other
-                            ),
-                        }
-                    }
-                    Err(e) => {
-                        error!(
-                            "Could not query submission status for {} due to: {}.",
-                            key, e
-                        );
-                        // and do nothing
-                    }
-                }
-            }
-        }
-    }
-}
-
 /// Checks the status of an initial account creation. A pending token is
 /// returned if the account transaction has not finalized yet. If the
 /// transaction is finalized then the identity object is returned.
 async fn get_identity_token(
-    server_config: Arc<ServerConfig>,
     retrieval_db: DB,
-    client: Client,
+    queue: Arc<SubmissionQueue>,
     id_cred_pub: String,
 ) -> Result<impl Reply, Rejection> {
-    // Check status of initial account creation transaction and update the file
-    // database accordingly.
-    let query_url_base = server_config.submit_credential_url.clone();
-    followup(
-        client,
-        retrieval_db.clone(),
-        server_config.submit_credential_url.clone(),
-        query_url_base,
-        id_cred_pub.clone(),
-    )
-    .await;
+    let key = id_cred_pub.clone();
+    match retrieval_db
+        .run(move |db| db.storage.read_cancellation(&key))
+        .await
+    {
+        Ok(None) => {}
+        Ok(Some(cancellation)) => {
+            info!("Identity object was {:?}.", cancellation.kind);
+            let error_identity_token_container = IdentityTokenContainer {
+                status: IdentityStatus::Error,
+                detail: match cancellation.kind {
+                    CancellationKind::Rejected => "The identity was rejected.".to_string(),
+                    CancellationKind::Revoked => "The identity was revoked.".to_string(),
+                },
+                token:  serde_json::Value::Null,
+            };
+            return Ok(warp::reply::json(&error_identity_token_container));
+        }
+        Err(e) => {
+            error!("Could not read the cancellation of {}: {}", id_cred_pub, e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    }
+    // Check status of initial account creation transaction, if it is due, and
+    // update the database accordingly.
+    if let Err(e) = queue.process(&id_cred_pub, submission_queue::now()).await {
+        error!(
+            "Could not follow up on the initial account creation of {}: {}",
+            id_cred_pub, e
+        );
+    }
 
     // If the initial account creation transaction is still not finalized, then we
     // return a pending object to the caller to indicate that the identity is
     // not ready yet.
-    if retrieval_db.is_pending(&id_cred_pub) {
+    let key = id_cred_pub.clone();
+    let is_pending = match retrieval_db.run(move |db| db.is_pending(&key)).await {
+        Ok(is_pending) => is_pending,
+        Err(e) => {
+            error!(
+                "Could not read the pending status of {}: {}",
+                id_cred_pub, e
+            );
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
+    let key = id_cred_pub.clone();
+    if is_pending {
         info!("Identity object is pending.");
         let identity_token_container = IdentityTokenContainer {
             status: IdentityStatus::Pending,
@@ -543,8 +791,25 @@ async fn get_identity_token(
             token:  serde_json::Value::Null,
         };
         Ok(warp::reply::json(&identity_token_container))
+    } else if let Ok(true) = retrieval_db
+        .run({
+            let key = key.clone();
+            move |db| db.is_dead_letter(&key)
+        })
+        .await
+    {
+        info!("Initial account creation failed.");
+        let error_identity_token_container = IdentityTokenContainer {
+            status: IdentityStatus::Error,
+            detail: "Initial account creation failed.".to_string(),
+            token:  serde_json::Value::Null,
+        };
+        Ok(warp::reply::json(&error_identity_token_container))
     } else {
-        match retrieval_db.read_identity_object(&id_cred_pub) {
+        match retrieval_db
+            .run(move |db| db.read_identity_object(&key))
+            .await
+        {
             Ok(identity_object) => {
                 info!("Identity object found");
 
@@ -579,33 +844,57 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Reading the provided IP, AR and global context configurations.");
 
-    let server_config = Arc::new(ServerConfig::from_opts(&opt)?);
-
     // Client used to make HTTP requests to both the id verifier,
     // as well as to submit the initial account creation.
     // We reuse it between requests since it is expensive to create.
     let client = Client::new();
-    let followup_client = client.clone();
-
-    // Create the 'database' directories for storing IdentityObjects and
-    // AnonymityRevocationRecords.
-    let db = DB::new(
-        std::path::Path::new("database").to_path_buf(),
-        std::path::Path::new("database-deleted").to_path_buf(),
-    )?;
+
+    let server_config = Arc::new(ServerConfig::from_opts(&opt, &client)?);
+
+    let db = DB::new(open_storage(&opt)?);
+    let metrics = Metrics::new()?;
     info!("Configurations have been loaded successfully.");
 
+    // The queue of initial account creations. Pending submissions, also those
+    // from before a restart, are followed up on in the background.
+    let queue = Arc::new(
+        SubmissionQueue::new(
+            Arc::clone(&db.storage),
+            client.clone(),
+            server_config.submit_credential_url.clone(),
+            RetryPolicy {
+                initial_delay: Duration::from_secs(opt.submission_retry_delay),
+                max_delay:     Duration::from_secs(opt.submission_max_retry_delay),
+                max_attempts:  opt.submission_max_attempts,
+            },
+        )
+        .with_metrics(metrics.clone()),
+    );
+    Arc::clone(&queue).spawn(Duration::from_secs(opt.submission_poll_interval));
+
+    // The status list is re-published periodically, so that its issuance time
+    // stays recent, and a publication that failed when revoking an identity is
+    // made up for.
+    if let Err(e) = publish_status_list(&server_config, &db) {
+        error!("Could not publish the status list: {}", e);
+    }
+    spawn_status_list_publisher(
+        Arc::clone(&server_config),
+        db.clone(),
+        Duration::from_secs(opt.status_list_interval),
+    );
+
     let retrieval_db = db.clone();
-    let server_config_retrieve = Arc::clone(&server_config);
+    let retrieval_queue = Arc::clone(&queue);
 
     // The endpoint for querying the identity object.
     let retrieve_identity = warp::get()
         .and(warp::path!("api" / "identity" / String))
+        .and(count_request(metrics.clone(), "retrieve"))
         .and_then(move |id_cred_pub: String| {
             get_identity_token(
-                server_config_retrieve.clone(),
                 retrieval_db.clone(),
-                followup_client.clone(),
+                Arc::clone(&retrieval_queue),
                 id_cred_pub,
             )
         });
@@ -614,6 +903,17 @@ async fn main() -> anyhow::Result<()> {
     let server_config_validate_query = Arc::clone(&server_config);
     let server_config_forward = Arc::clone(&server_config);
 
+    // The signed status list of the revoked identities, against which holders
+    // of credentials prove that their identity is not revoked.
+    let status_list_db = db.clone();
+    let status_list = warp::get()
+        .and(warp::path!("api" / "status-list"))
+        .and(count_request(metrics.clone(), "status-list"))
+        .and_then(move || get_status_list(status_list_db.clone()));
+
+    let admin_db = db.clone();
+    let admin_queue = Arc::clone(&queue);
+    let revoke_server_config = Arc::clone(&server_config);
     let db_arc = Arc::new(db);
     let verify_db = Arc::clone(&db_arc);
     let create_db = Arc::clone(&db_arc);
@@ -623,10 +923,14 @@ async fn main() -> anyhow::Result<()> {
     let verify_request = warp::post()
         .and(warp::filters::body::content_length_limit(50 * 1024))
         .and(warp::path!("api" / "identity"))
+        .and(count_request(metrics.clone(), "verify"))
         .and(extract_and_validate_request(server_config_validate))
-        .or(warp::get().and(warp::path!("api" / "identity")).and(
-            extract_and_validate_request_query(server_config_validate_query),
-        ))
+        .or(warp::get()
+            .and(warp::path!("api" / "identity"))
+            .and(count_request(metrics.clone(), "verify"))
+            .and(extract_and_validate_request_query(
+                server_config_validate_query,
+            )))
         .unify()
         .and_then(move |idi| {
             save_validated_request(Arc::clone(&verify_db), idi, server_config_forward.clone())
@@ -635,26 +939,545 @@ async fn main() -> anyhow::Result<()> {
     // Endpoint for creating identities. The identity verification service will
     // forward the user to this endpoint after they have created a list of
     // verified attributes.
+    let create_server_config = Arc::clone(&server_config);
+    let create_queue = Arc::clone(&queue);
     let create_identity = warp::get()
         .and(warp::path!("api" / "identity" / "create" / String))
-        .and_then(move |id_cred_pub: String| {
-            create_signed_identity_object(
-                Arc::clone(&server_config),
-                Arc::clone(&create_db),
-                client.clone(),
-                id_cred_pub,
+        .and(count_request(metrics.clone(), "create"))
+        .and(query_parameters())
+        .and(warp::cookie::optional(VERIFICATION_SESSION_COOKIE))
+        .and_then(
+            move |id_cred_pub: String, parameters: Parameters, session: Option<String>| {
+                create_signed_identity_object(
+                    Arc::clone(&create_server_config),
+                    Arc::clone(&create_db),
+                    Arc::clone(&create_queue),
+                    id_cred_pub,
+                    parameters,
+                    session,
+                )
+            },
+        );
+
+    // Endpoint for renewing an issued identity object. The wallet proves that
+    // it holds the identity object, and gets it back with an extended validity.
+    let renew_server_config = Arc::clone(&server_config);
+    let renew_db = Arc::clone(&db_arc);
+    let renew_identity = warp::post()
+        .and(warp::filters::body::content_length_limit(50 * 1024))
+        .and(warp::path!("api" / "identity" / "renew"))
+        .and(count_request(metrics.clone(), "renew"))
+        .and(warp::body::json())
+        .and_then(move |request: Versioned<IdentityRenewalRequest<ArCurve>>| {
+            renew_identity_object(
+                Arc::clone(&renew_server_config),
+                Arc::clone(&renew_db),
+                request,
             )
         });
 
+    // Endpoint the OpenID Connect provider redirects the user to after they
+    // logged in. The verifier identifies the request by the `state`.
+    let callback_db = Arc::clone(&db_arc);
+    let callback_identity = warp::get()
+        .and(warp::path!("api" / "identity" / "callback"))
+        .and(count_request(metrics.clone(), "create"))
+        .and(query_parameters())
+        .and(warp::cookie::optional(VERIFICATION_SESSION_COOKIE))
+        .and_then(move |parameters: Parameters, session: Option<String>| {
+            let server_config = Arc::clone(&server_config);
+            let db = Arc::clone(&callback_db);
+            let queue = Arc::clone(&queue);
+            async move {
+                match server_config.verifier.request_key(&parameters) {
+                    Some(id_cred_pub) => {
+                        create_signed_identity_object(
+                            server_config,
+                            db,
+                            queue,
+                            id_cred_pub,
+                            parameters,
+                            session,
+                        )
+                        .await
+                    }
+                    None => {
+                        warn!("The user was sent back with an unknown or expired state.");
+                        Err(warp::reject::custom(IdRequestRejection::IdVerifierFailure))
+                    }
+                }
+            }
+        });
+
+    // Endpoints of the admin API. All of them require the admin token.
+    if opt.admin_token.is_none() {
+        warn!("No admin token given, the admin API is disabled.");
+    }
+    let auth = admin_auth(opt.admin_token.clone());
+    let with_db = warp::any().map(move || admin_db.clone());
+    // Inspecting the submissions of initial account creations, and re-driving
+    // the ones that are stuck.
+    let list_submissions_route = warp::get()
+        .and(warp::path!("api" / "admin" / "submissions"))
+        .and(auth.clone())
+        .and(with_db.clone())
+        .and_then(list_submissions);
+    let redrive_route = warp::post()
+        .and(warp::path!(
+            "api" / "admin" / "submissions" / String / "redrive"
+        ))
+        .and(auth.clone())
+        .and_then(move |id_cred_pub: String| {
+            redrive_submission(Arc::clone(&admin_queue), id_cred_pub)
+        });
+    // Inspecting requests, and rejecting or revoking identities.
+    let list_requests_route = warp::get()
+        .and(warp::path!("api" / "admin" / "requests"))
+        .and(auth.clone())
+        .and(with_db.clone())
+        .and(warp::query())
+        .and_then(list_requests);
+    let get_request_route = warp::get()
+        .and(warp::path!("api" / "admin" / "requests" / String))
+        .and(auth.clone())
+        .and(with_db.clone())
+        .and_then(|id_cred_pub: String, db: DB| get_request(db, id_cred_pub));
+    let reject_route = warp::post()
+        .and(warp::path!(
+            "api" / "admin" / "requests" / String / "reject"
+        ))
+        .and(auth.clone())
+        .and(with_db.clone())
+        .and(warp::body::json())
+        .and_then(|id_cred_pub: String, db: DB, body: CancelRequest| {
+            cancel_identity(db, id_cred_pub, CancellationKind::Rejected, body)
+        });
+    let revoke_route = warp::post()
+        .and(warp::path!(
+            "api" / "admin" / "requests" / String / "revoke"
+        ))
+        .and(auth.clone())
+        .and(with_db.clone())
+        .and(warp::body::json())
+        .and_then(move |id_cred_pub: String, db: DB, body: CancelRequest| {
+            revoke_identity(Arc::clone(&revoke_server_config), db, id_cred_pub, body)
+        });
+    let revocation_record_route = warp::get()
+        .and(warp::path!("api" / "admin" / "revocation-records" / String))
+        .and(auth)
+        .and(with_db)
+        .and_then(|id_cred_pub: String, db: DB| get_revocation_record(db, id_cred_pub));
+    // The metrics do not contain personal data, so they are served without
+    // authentication.
+    let admin_metrics = metrics.clone();
+    let metrics_route = warp::get()
+        .and(warp::path!("metrics"))
+        .and_then(move || get_metrics(admin_metrics.clone()));
+    let admin = list_submissions_route
+        .or(redrive_route)
+        .or(list_requests_route)
+        .or(get_request_route)
+        .or(reject_route)
+        .or(revoke_route)
+        .or(revocation_record_route)
+        .or(metrics_route)
+        .recover(handle_rejection);
+    info!(
+        "Booting up admin HTTP server. Listening on {}:{}.",
+        opt.admin_address, opt.admin_port
+    );
+    tokio::spawn(warp::serve(admin).run((opt.admin_address, opt.admin_port)));
+
     info!("Booting up HTTP server. Listening on port {}.", opt.port);
     let server = verify_request
+        .or(callback_identity)
+        .or(renew_identity)
+        .or(status_list)
         .or(retrieve_identity)
         .or(create_identity)
-        .recover(handle_rejection);
+        .recover(move |err: Rejection| {
+            record_failure(&metrics, &err);
+            handle_rejection(err)
+        });
     warp::serve(server).run(([0, 0, 0, 0], opt.port)).await;
     Ok(())
 }
 
+/// A pending initial account creation as listed by the admin API.
+#[derive(SerdeSerialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingSubmission {
+    id_cred_pub:  String,
+    status:       PendingStatus,
+    attempts:     u32,
+    next_attempt: i64,
+    last_error:   Option<String>,
+}
+
+/// An initial account creation that was given up on, as listed by the admin
+/// API.
+#[derive(SerdeSerialize)]
+#[serde(rename_all = "camelCase")]
+struct FailedSubmission {
+    id_cred_pub: String,
+    status:      PendingStatus,
+    attempts:    u32,
+    reason:      String,
+    time:        i64,
+}
+
+#[derive(SerdeSerialize)]
+#[serde(rename_all = "camelCase")]
+struct SubmissionsResponse {
+    pending:      Vec<PendingSubmission>,
+    dead_letters: Vec<FailedSubmission>,
+}
+
+/// List the pending and the failed initial account creations.
+async fn list_submissions(db: DB) -> Result<impl Reply, Rejection> {
+    let submissions = db
+        .run(|db| Ok((db.storage.list_pending()?, db.storage.list_dead_letters()?)))
+        .await;
+    let (pending, dead_letters) = match submissions {
+        Ok(submissions) => submissions,
+        Err(e) => {
+            error!("Could not list the submissions: {}", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
+    let response = SubmissionsResponse {
+        pending:      pending
+            .into_iter()
+            .map(|(id_cred_pub, entry)| PendingSubmission {
+                id_cred_pub,
+                status: entry.status,
+                attempts: entry.attempts,
+                next_attempt: entry.next_attempt,
+                last_error: entry.last_error,
+            })
+            .collect(),
+        dead_letters: dead_letters
+            .into_iter()
+            .map(|(id_cred_pub, letter)| FailedSubmission {
+                id_cred_pub,
+                status: letter.entry.status,
+                attempts: letter.entry.attempts,
+                reason: letter.reason,
+                time: letter.time,
+            })
+            .collect(),
+    };
+    Ok(warp::reply::json(&response))
+}
+
+/// Retry the initial account creation immediately, also if it was given up on.
+async fn redrive_submission(
+    queue: Arc<SubmissionQueue>,
+    id_cred_pub: String,
+) -> Result<impl Reply, Rejection> {
+    match queue.redrive(&id_cred_pub, submission_queue::now()).await {
+        Ok(true) => Ok(mk_reply("Submission re-driven.", StatusCode::OK)),
+        Ok(false) => Err(warp::reject::not_found()),
+        Err(e) => {
+            error!("Could not re-drive the submission {}: {}", id_cred_pub, e);
+            Err(warp::reject::custom(IdRequestRejection::InternalError))
+        }
+    }
+}
+
+/// Query parameters of the listing of requests.
+#[derive(SerdeDeserialize)]
+struct ListRequestsQuery {
+    /// Only list the requests with this status.
+    status: Option<RequestStatus>,
+}
+
+/// A request as listed by the admin API.
+#[derive(SerdeSerialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestSummary {
+    id_cred_pub: String,
+    status:      RequestStatus,
+}
+
+/// List the requests, optionally only those with the given status.
+async fn list_requests(db: DB, query: ListRequestsQuery) -> Result<impl Reply, Rejection> {
+    let requests = match db.run(|db| db.list_requests()).await {
+        Ok(requests) => requests,
+        Err(e) => {
+            error!("Could not list the requests: {}", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
+    let response: Vec<_> = requests
+        .into_iter()
+        .filter(|(_, status)| match query.status {
+            Some(wanted) => wanted == *status,
+            None => true,
+        })
+        .map(|(id_cred_pub, status)| RequestSummary {
+            id_cred_pub,
+            status,
+        })
+        .collect();
+    Ok(warp::reply::json(&response))
+}
+
+/// A stored request as returned by the admin API.
+#[derive(SerdeSerialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestDetails {
+    id_cred_pub:  String,
+    status:       RequestStatus,
+    request:      IdentityObjectRequest,
+    cancellation: Option<Cancellation>,
+}
+
+/// Return the stored request with the given key, together with its status.
+async fn get_request(db: DB, id_cred_pub: String) -> Result<impl Reply, Rejection> {
+    let key = id_cred_pub.clone();
+    let details = db
+        .run(move |db| {
+            let status = match db.request_status(&key)? {
+                Some(status) => status,
+                None => return Ok(None),
+            };
+            Ok(Some(RequestDetails {
+                request: db.read_request_record(&key)?,
+                cancellation: db.storage.read_cancellation(&key)?,
+                id_cred_pub: key,
+                status,
+            }))
+        })
+        .await;
+    match details {
+        Ok(Some(details)) => Ok(warp::reply::json(&details)),
+        Ok(None) => Err(warp::reject::not_found()),
+        Err(e) => {
+            error!("Could not read the request {}: {}", id_cred_pub, e);
+            Err(warp::reject::custom(IdRequestRejection::InternalError))
+        }
+    }
+}
+
+/// The body of a request to reject or revoke an identity.
+#[derive(SerdeDeserialize)]
+struct CancelRequest {
+    reason: String,
+}
+
+/// Reject an identity that has not been issued yet, or revoke one that has.
+/// The identity is then no longer handed out to the user, and a pending
+/// initial account creation is no longer followed up on. Note that this does
+/// not undo an initial account creation that was already submitted.
+async fn cancel_identity(
+    db: DB,
+    id_cred_pub: String,
+    kind: CancellationKind,
+    body: CancelRequest,
+) -> Result<impl Reply, Rejection> {
+    let key = id_cred_pub.clone();
+    let status = match db.run(move |db| db.request_status(&key)).await {
+        Ok(Some(status)) => status,
+        Ok(None) => return Err(warp::reject::not_found()),
+        Err(e) => {
+            error!("Could not read the request {}: {}", id_cred_pub, e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
+    let allowed = match kind {
+        CancellationKind::Rejected => status == RequestStatus::Validated,
+        CancellationKind::Revoked => matches!(
+            status,
+            RequestStatus::Pending | RequestStatus::Failed | RequestStatus::Done
+        ),
+    };
+    if !allowed {
+        return Err(warp::reject::custom(IdRequestRejection::InvalidState));
+    }
+    let cancellation = Cancellation {
+        kind,
+        reason: body.reason,
+        time: submission_queue::now(),
+    };
+    let key = id_cred_pub.clone();
+    let stored = cancellation.clone();
+    if let Err(e) = db.run(move |db| db.storage.cancel(&key, &stored)).await {
+        error!("Could not cancel the identity {}: {}", id_cred_pub, e);
+        return Err(warp::reject::custom(IdRequestRejection::InternalError));
+    }
+    info!(
+        "Identity {} {:?}: {}",
+        id_cred_pub, kind, cancellation.reason
+    );
+    Ok(mk_reply(
+        match kind {
+            CancellationKind::Rejected => "Identity rejected.",
+            CancellationKind::Revoked => "Identity revoked.",
+        },
+        StatusCode::OK,
+    ))
+}
+
+/// Revoke an identity as [cancel_identity] does, and publish the status list
+/// including it. If the publication fails the identity is still revoked, and
+/// it is included in the next periodic publication.
+async fn revoke_identity(
+    server_config: Arc<ServerConfig>,
+    db: DB,
+    id_cred_pub: String,
+    body: CancelRequest,
+) -> Result<impl Reply, Rejection> {
+    let reply = cancel_identity(db.clone(), id_cred_pub, CancellationKind::Revoked, body).await?;
+    if let Err(e) = db
+        .run(move |db| publish_status_list(&server_config, db))
+        .await
+    {
+        error!("Could not publish the status list: {}", e);
+    }
+    Ok(reply)
+}
+
+/// Sign and store the status list of the identities revoked so far. The list
+/// is rebuilt from the cancellations, so its version is the number of revoked
+/// identities. Publications are serialized, since the revocation handler and
+/// the periodic publisher may publish concurrently.
+fn publish_status_list(server_config: &ServerConfig, db: &DB) -> anyhow::Result<()> {
+    let _lock = db
+        .status_list_lock
+        .lock()
+        .expect("Cannot acquire a lock, which means something is very wrong.");
+    let issued_at = Timestamp::from(submission_queue::now() as u64 * 1000);
+    let mut list = StatusList::new(server_config.ip_info.ip_identity, issued_at);
+    for id_cred_pub in db.list_revoked()? {
+        list.revoke(id_cred_pub);
+    }
+    let signed = sign_status_list(list, server_config.signer.as_ref())?;
+    db.write_status_list(&signed)?;
+    info!(
+        "Published status list version {}.",
+        signed.status_list.version
+    );
+    Ok(())
+}
+
+/// Re-publish the status list at the given interval.
+fn spawn_status_list_publisher(
+    server_config: Arc<ServerConfig>,
+    db: DB,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::delay_for(interval).await;
+            let server_config = Arc::clone(&server_config);
+            if let Err(e) = db
+                .run(move |db| publish_status_list(&server_config, db))
+                .await
+            {
+                error!("Could not publish the status list: {}", e);
+            }
+        }
+    })
+}
+
+/// Return the signed status list of the revoked identities.
+async fn get_status_list(db: DB) -> Result<impl Reply, Rejection> {
+    match db.run(|db| db.read_status_list()).await {
+        Ok(Some(list)) => Ok(warp::reply::json(&list)),
+        Ok(None) => Err(warp::reject::not_found()),
+        Err(e) => {
+            error!("Could not read the status list: {}", e);
+            Err(warp::reject::custom(IdRequestRejection::InternalError))
+        }
+    }
+}
+
+/// Return the anonymity revocation record of the identity with the given key.
+async fn get_revocation_record(db: DB, id_cred_pub: String) -> Result<impl Reply, Rejection> {
+    match db
+        .run(move |db| db.storage.read_revocation_record(&id_cred_pub))
+        .await
+    {
+        Ok(record) => Ok(warp::reply::json(&record)),
+        Err(_) => Err(warp::reject::not_found()),
+    }
+}
+
+/// Return the metrics in the Prometheus text format.
+async fn get_metrics(metrics: Metrics) -> Result<impl Reply, Rejection> {
+    match metrics.encode() {
+        Ok(text) => Ok(warp::reply::with_header(
+            text,
+            "Content-Type",
+            "text/plain; version=0.0.4",
+        )),
+        Err(e) => {
+            error!("Could not encode the metrics: {}", e);
+            Err(warp::reject::custom(IdRequestRejection::InternalError))
+        }
+    }
+}
+
+/// Compare the tokens in time independent of where they differ.
+fn tokens_equal(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// A filter that only lets through requests with the given bearer token. If
+/// there is no token all requests are rejected.
+fn admin_auth(token: Option<String>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    let expected = Arc::new(token.map(|token| format!("Bearer {}", token)));
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let expected = Arc::clone(&expected);
+            async move {
+                match (header, expected.as_ref()) {
+                    (Some(header), Some(expected))
+                        if tokens_equal(header.as_bytes(), expected.as_bytes()) =>
+                    {
+                        Ok(())
+                    }
+                    _ => Err(warp::reject::custom(IdRequestRejection::Unauthorized)),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// A filter that counts the requests to the given endpoint.
+fn count_request(
+    metrics: Metrics,
+    endpoint: &'static str,
+) -> impl Filter<Extract = (), Error = Infallible> + Clone {
+    warp::any()
+        .map(move || metrics.requests.with_label_values(&[endpoint]).inc())
+        .untuple_one()
+}
+
+/// A filter extracting the query parameters of the request, which are empty if
+/// there is no query.
+fn query_parameters() -> impl Filter<Extract = (Parameters,), Error = Infallible> + Clone {
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .map(|query: String| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect()
+        })
+}
+
+/// Count the failed request in the metrics, by the reason it failed.
+fn record_failure(metrics: &Metrics, err: &Rejection) {
+    if let Some(rejection) = err.find::<IdRequestRejection>() {
+        metrics
+            .failures
+            .with_label_values(&[rejection.label()])
+            .inc();
+    }
+}
+
 /// A helper macro to check whether the expression is an error, an in that case
 /// fail with internal server error.
 macro_rules! ok_or_500 (
@@ -683,77 +1506,70 @@ async fn save_validated_request(
 
     // Sign the id_cred_pub so that the identity verifier can verify that the given
     // id_cred_pub matches a valid identity creation request.
-    let public_key: PublicKey = server_config.ip_data.public_ip_info.ip_cdi_verify_key;
-    let expanded_secret_key: ExpandedSecretKey =
-        ExpandedSecretKey::from(&server_config.ip_data.ip_cdi_secret_key);
     let message = hex::decode(&base_16_encoded_id_cred_pub).unwrap();
-    let signature_on_id_cred_pub = expanded_secret_key.sign(message.as_slice(), &public_key);
+    let signature_on_id_cred_pub = match server_config.signer.sign(message.as_slice()) {
+        Ok(signature) => signature,
+        Err(e) => {
+            error!("Could not sign the idCredPub {}.", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
     let serialized_signature = base16_encode_string(&signature_on_id_cred_pub);
 
+    let key = base_16_encoded_id_cred_pub.clone();
     ok_or_500!(
-        db.write_request_record(&base_16_encoded_id_cred_pub, &identity_object_request),
+        db.run(move |db| db.write_request_record(&key, &identity_object_request))
+            .await,
         "Could not write the valid request to database."
     );
 
-    let attribute_form_url = format!(
-        "{}/{}/{}",
-        server_config.id_verification_url.to_string(),
-        base_16_encoded_id_cred_pub,
-        serialized_signature
-    );
-    Ok(warp::reply::with_status(
-        warp::reply::with_header(warp::reply(), LOCATION, attribute_form_url),
-        StatusCode::FOUND,
-    ))
-}
-
-/// Submit an account creation transaction. Return Ok if either the submission
-/// was successful or if it failed due to reasons unrelated to the request
-/// itself, e.g., we could not reach the server. Return Err(_) if the submission
-/// is malformed for some reason.
-async fn submit_account_creation(
-    client: &Client,
-    url: url::Url,
-    submission: &serde_json::Value,
-) -> Result<PendingStatus, String> {
-    // Submit and wait for the submission ID.
-    match client.put(url).json(submission).send().await {
-        Ok(response) => {
-            match response.status() {
-                StatusCode::BAD_GATEWAY => {
-                    // internal server error, retry later.
-                    Ok(PendingStatus::CouldNotSubmit)
-                }
-                StatusCode::BAD_REQUEST => {
-                    Err("Failed validation of the reuse of malformed initial account.".to_string())
-                }
-                StatusCode::OK => match response.json::<InitialAccountReponse>().await {
-                    Ok(v) => {
-                        let initial_status = PendingStatus::Submitted {
-                            submission_id: v.submission_id,
-                            status:        SubmissionStatus::Received,
-                        };
-                        Ok(initial_status)
-                    }
-                    Err(_) => Ok(PendingStatus::CouldNotSubmit),
-                },
-                other => {
-                    error!("Unexpected response from the Wallet Proxy: {}", other);
-                    // internal server error, retry later.
-                    Ok(PendingStatus::CouldNotSubmit)
-                }
-            }
+    let start = match server_config
+        .verifier
+        .start(&base_16_encoded_id_cred_pub, &serialized_signature)
+    {
+        Ok(start) => start,
+        Err(e) => {
+            error!("Could not make the verification URL: {}", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
         }
+    };
+    let mut response = warp::http::Response::builder()
+        .status(StatusCode::FOUND)
+        .header(LOCATION, start.url.to_string());
+    if let Some(session) = start.session {
+        response = response.header(
+            SET_COOKIE,
+            session_cookie(&server_config.retrieve_url, &session),
+        );
+    }
+    match response.body("") {
+        Ok(response) => Ok(response),
         Err(e) => {
-            // This almost certainly means we could not reach the server, or the server is
-            // configured wrong. This should be considered an internal error and
-            // we must retry.
-            error!("Could not reach the wallet proxy due to: {}", e);
-            Ok(PendingStatus::CouldNotSubmit)
+            error!("Could not make the verification response: {}", e);
+            Err(warp::reject::custom(IdRequestRejection::InternalError))
         }
     }
 }
 
+/// The name of the cookie that keeps the verification session in the browser
+/// of the user, see [verifier::VerificationStart].
+const VERIFICATION_SESSION_COOKIE: &str = "verification_session";
+
+/// The `Set-Cookie` header value keeping the verification session. The cookie
+/// is only sent back to the endpoints the verifier sends the user back to.
+fn session_cookie(base_url: &url::Url, session: &str) -> String {
+    format!(
+        "{}={}; Path=/api/identity; HttpOnly; SameSite=Lax{}",
+        VERIFICATION_SESSION_COOKIE,
+        session,
+        if base_url.scheme() == "https" {
+            "; Secure"
+        } else {
+            ""
+        }
+    )
+}
+
 #[derive(Debug)]
 /// An internal error type used by this server to manage error handling.
 enum IdRequestRejection {
@@ -772,10 +1588,39 @@ enum IdRequestRejection {
     Malformed,
     /// Missing validated request for the given id_cred_pub
     NoValidRequest,
+    /// The identity was rejected or revoked by an administrator.
+    Cancelled,
+    /// The request to the admin API did not have the right token.
+    Unauthorized,
+    /// The admin action is not possible in the current state of the identity,
+    /// e.g., rejecting an identity that was already issued.
+    InvalidState,
+    /// The renewal does not extend the validity of the identity object, or
+    /// extends it beyond what the identity provider allows.
+    InvalidRenewal,
 }
 
 impl warp::reject::Reject for IdRequestRejection {}
 
+impl IdRequestRejection {
+    /// Name of the rejection, used to label the failure metrics.
+    fn label(&self) -> &'static str {
+        match self {
+            IdRequestRejection::UnsupportedVersion => "unsupported_version",
+            IdRequestRejection::InvalidProofs => "invalid_proofs",
+            IdRequestRejection::IdVerifierFailure => "id_verifier_failure",
+            IdRequestRejection::InternalError => "internal_error",
+            IdRequestRejection::ReuseOfRegId => "reuse_of_reg_id",
+            IdRequestRejection::Malformed => "malformed",
+            IdRequestRejection::NoValidRequest => "no_valid_request",
+            IdRequestRejection::Cancelled => "cancelled",
+            IdRequestRejection::Unauthorized => "unauthorized",
+            IdRequestRejection::InvalidState => "invalid_state",
+            IdRequestRejection::InvalidRenewal => "invalid_renewal",
+        }
+    }
+}
+
 #[derive(SerdeSerialize)]
 /// Response in case of an error. This is going to be encoded as a JSON body
 /// with fields 'code' and 'message'.
@@ -826,6 +1671,22 @@ async fn handle_rejection(err: Rejection) -> Result<impl warp::Reply, Infallible
         let code = StatusCode::BAD_REQUEST;
         let message = "No validated request was found for the given id_cred_pub.";
         Ok(mk_reply(message, code))
+    } else if let Some(IdRequestRejection::Cancelled) = err.find() {
+        let code = StatusCode::FORBIDDEN;
+        let message = "The identity was rejected or revoked.";
+        Ok(mk_reply(message, code))
+    } else if let Some(IdRequestRejection::Unauthorized) = err.find() {
+        let code = StatusCode::UNAUTHORIZED;
+        let message = "Unauthorized.";
+        Ok(mk_reply(message, code))
+    } else if let Some(IdRequestRejection::InvalidState) = err.find() {
+        let code = StatusCode::CONFLICT;
+        let message = "Not possible in the current state of the identity.";
+        Ok(mk_reply(message, code))
+    } else if let Some(IdRequestRejection::InvalidRenewal) = err.find() {
+        let code = StatusCode::BAD_REQUEST;
+        let message = "Invalid validity of the renewed identity object.";
+        Ok(mk_reply(message, code))
     } else if err
         .find::<warp::filters::body::BodyDeserializeError>()
         .is_some()
@@ -840,19 +1701,175 @@ async fn handle_rejection(err: Rejection) -> Result<impl warp::Reply, Infallible
     }
 }
 
+/// Renew an issued identity object. The request must prove knowledge of the
+/// secrets of the identity object, and may extend its validity by at most
+/// the validity of a new identity object. The identity object is signed again
+/// with the new validity and stored in place of the original, so that it is
+/// also returned when retrieving the identity. The anonymity revocation record
+/// stays the same, since the renewed identity object has the same anonymity
+/// revocation data.
+async fn renew_identity_object(
+    server_config: Arc<ServerConfig>,
+    db: Arc<DB>,
+    request: Versioned<IdentityRenewalRequest<ArCurve>>,
+) -> Result<impl Reply, Rejection> {
+    if request.version != VERSION_0 {
+        return Err(warp::reject::custom(IdRequestRejection::UnsupportedVersion));
+    }
+    let request = request.value;
+    let base16_encoded_id_cred_pub = base16_encode_string(&request.id_cred_pub);
+    let key = base16_encoded_id_cred_pub.clone();
+    match db.run(move |db| db.request_status(&key)).await {
+        Ok(Some(RequestStatus::Done)) => {}
+        Ok(Some(RequestStatus::Rejected)) | Ok(Some(RequestStatus::Revoked)) => {
+            return Err(warp::reject::custom(IdRequestRejection::Cancelled));
+        }
+        Ok(Some(_)) => return Err(warp::reject::custom(IdRequestRejection::InvalidState)),
+        Ok(None) => return Err(warp::reject::custom(IdRequestRejection::NoValidRequest)),
+        Err(e) => {
+            error!(
+                "Could not read the status of {}: {}",
+                base16_encoded_id_cred_pub, e
+            );
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    }
+
+    if request.valid_to
+        > server_config
+            .attribute_list_policy
+            .valid_to(YearMonth::now())
+    {
+        warn!(
+            "Renewal of {} beyond the allowed validity.",
+            base16_encoded_id_cred_pub
+        );
+        return Err(warp::reject::custom(IdRequestRejection::InvalidRenewal));
+    }
+
+    let key = base16_encoded_id_cred_pub.clone();
+    let previous = match db.run(move |db| db.read_identity_object(&key)).await {
+        Ok(previous) => previous,
+        Err(e) => {
+            error!(
+                "Could not read the identity object of {}: {}",
+                base16_encoded_id_cred_pub, e
+            );
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
+    let id_object = match from_value::<Versioned<_>>(previous["identityObject"].clone()) {
+        Ok(id_object) => id_object.value,
+        Err(e) => {
+            error!(
+                "Could not read the identity object of {}: {}",
+                base16_encoded_id_cred_pub, e
+            );
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
+
+    let renewed = match renew_identity_object_with_signer(
+        &request,
+        id_object,
+        &server_config.global,
+        &server_config.ip_info,
+        server_config.signer.as_ref(),
+    ) {
+        Ok(renewed) => renewed,
+        Err(SigningError::Invalid(Reason::InvalidRenewal)) => {
+            warn!(
+                "Renewal of {} does not extend the validity.",
+                base16_encoded_id_cred_pub
+            );
+            return Err(warp::reject::custom(IdRequestRejection::InvalidRenewal));
+        }
+        Err(SigningError::Invalid(reason)) => {
+            warn!("Renewal request is invalid {}.", reason);
+            return Err(warp::reject::custom(IdRequestRejection::InvalidProofs));
+        }
+        Err(SigningError::Signer(e)) => {
+            error!("Could not sign the renewed identity object {}.", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
+
+    // The identity may have been rejected or revoked, or renewed by another
+    // request, since it was read. The renewal is only stored if neither is
+    // the case, which the storage checks together with the write.
+    let key = base16_encoded_id_cred_pub.clone();
+    let stored_obj = match db
+        .run(move |db| {
+            db.write_renewed_identity_object(&key, &previous, &Versioned::new(VERSION_0, renewed))
+        })
+        .await
+    {
+        Ok(Some(stored_obj)) => stored_obj,
+        Ok(None) => {
+            warn!(
+                "The identity object of {} was cancelled or changed during its renewal.",
+                base16_encoded_id_cred_pub
+            );
+            let key = base16_encoded_id_cred_pub.clone();
+            return match db.run(move |db| db.request_status(&key)).await {
+                Ok(Some(RequestStatus::Rejected)) | Ok(Some(RequestStatus::Revoked)) => {
+                    Err(warp::reject::custom(IdRequestRejection::Cancelled))
+                }
+                _ => Err(warp::reject::custom(IdRequestRejection::InvalidState)),
+            };
+        }
+        Err(e) => {
+            error!("Could not write the renewed identity object: {}", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
+    info!(
+        "Renewed the identity object of {}.",
+        base16_encoded_id_cred_pub
+    );
+    Ok(warp::reply::json(&IdentityTokenContainer {
+        status: IdentityStatus::Done,
+        token:  stored_obj,
+        detail: "".to_string(),
+    }))
+}
+
 /// Checks for a validated request and checks with the identity verifier if
 /// there is a verified attribute list for this person. If there is an attribute
 /// list, then it is used to create the identity object that is then signed and
 /// saved. If successful a re-direct to the URL where the identity object is
-/// available is returned.
+/// available is returned. The parameters are the query parameters the user was
+/// sent back with by the verifier, and the session is the verification session
+/// kept in their browser, if any.
 async fn create_signed_identity_object(
     server_config: Arc<ServerConfig>,
     db: Arc<DB>,
-    client: Client,
+    queue: Arc<SubmissionQueue>,
     id_cred_pub_input: String,
+    parameters: Parameters,
+    session: Option<String>,
 ) -> Result<impl Reply, Rejection> {
+    let key = id_cred_pub_input.clone();
+    match db.run(move |db| db.storage.read_cancellation(&key)).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            warn!(
+                "Refusing to create the identity {}, it was cancelled.",
+                id_cred_pub_input
+            );
+            return Err(warp::reject::custom(IdRequestRejection::Cancelled));
+        }
+        Err(e) => {
+            error!(
+                "Could not read the cancellation of {}: {}",
+                id_cred_pub_input, e
+            );
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    }
     // Read the validated request from the database.
-    let identity_object_input = match db.read_request_record(&id_cred_pub_input) {
+    let key = id_cred_pub_input.clone();
+    let identity_object_input = match db.run(move |db| db.read_request_record(&key)).await {
         Ok(request) => request,
         Err(e) => {
             error!(
@@ -873,29 +1890,27 @@ async fn create_signed_identity_object(
     let request = identity_object_input.id_object_request.value;
 
     // Identity verification process between the identity provider and the identity
-    // verifier. In this example the identity verifier is queried and will
-    // return the attribute list that the user submitted to the identity verifier.
-    // If there is no attribute list, then it corresponds to the user not having
-    // been verified, and the request will fail.
-    let attribute_list_url = format!(
-        "{}{}{}",
-        server_config.id_verification_query_url.clone(),
-        "/attributes/",
-        base16_encoded_id_cred_pub
-    );
-    let attribute_list = match client
-        .get(Url::parse(&attribute_list_url).unwrap())
-        .send()
-        .await
+    // verifier. The verifier returns the attributes of the user if they have been
+    // verified, and otherwise the request fails. The attribute list of the identity
+    // object is made from these attributes.
+    let alist = match verifier::verified_attribute_list(
+        server_config.verifier.as_ref(),
+        &server_config.attribute_list_policy,
+        &base16_encoded_id_cred_pub,
+        &parameters,
+        session.as_deref(),
+    )
+    .await
     {
-        Ok(attribute_list) => match attribute_list.json().await {
-            Ok(attribute_list) => attribute_list,
-            Err(e) => {
-                error!("Could not deserialize response from the verifier {}.", e);
-                return Err(warp::reject::custom(IdRequestRejection::IdVerifierFailure));
-            }
-        },
-        Err(e) => {
+        Ok(alist) => alist,
+        Err(VerificationError::NotVerified(e)) => {
+            warn!(
+                "The user {} was not verified: {}",
+                base16_encoded_id_cred_pub, e
+            );
+            return Err(warp::reject::custom(IdRequestRejection::IdVerifierFailure));
+        }
+        Err(VerificationError::Internal(e)) => {
             error!(
                 "Could not retrieve attribute list from the verifier: {}.",
                 e
@@ -909,28 +1924,11 @@ async fn create_signed_identity_object(
     // record and the identity object are persisted, so that they can be
     // retrieved when needed. The constructed response contains a redirect to a
     // webservice that returns the identity object constructed here.
-
-    // This is hardcoded for the proof-of-concept.
-    // Expiry is a year from now.
-    let now = YearMonth::now();
-    let valid_to_next_year = YearMonth {
-        year:  now.year + 1,
-        month: now.month,
-    };
-
-    let alist = ExampleAttributeList {
-        valid_to:     valid_to_next_year,
-        created_at:   now,
-        alist:        attribute_list,
-        max_accounts: 200,
-        _phantom:     Default::default(),
-    };
-
-    let signature = match sign_identity_object(
+    let signature = match sign_identity_object_with_signer(
         &request,
-        &server_config.ip_data.public_ip_info,
+        &server_config.ip_info,
         &alist,
-        &server_config.ip_data.ip_secret_key,
+        server_config.signer.as_ref(),
     ) {
         Ok(signature) => signature,
         Err(e) => {
@@ -941,8 +1939,11 @@ async fn create_signed_identity_object(
 
     let base16_encoded_id_cred_pub = base16_encode_string(&request.pub_info_for_ip.id_cred_pub);
 
+    let key = base16_encoded_id_cred_pub.clone();
+    let ar_record = revocation_record(&request, &alist);
     ok_or_500!(
-        save_revocation_record(&db, &request, &alist),
+        db.run(move |db| db.write_revocation_record(&key, ar_record))
+            .await,
         "Could not write the revocation record to database."
     );
 
@@ -962,8 +1963,8 @@ async fn create_signed_identity_object(
     // TODO: We should check beforehand that the regid is fresh and that
     // no account with this regid already exists, since that will lead to failure of
     // account creation.
-    let initial_cdi = create_initial_cdi(
-        &server_config.ip_data.public_ip_info,
+    let initial_cdi = match create_initial_cdi_with_signer(
+        &server_config.ip_info,
         versioned_id
             .value
             .pre_identity_object
@@ -971,8 +1972,14 @@ async fn create_signed_identity_object(
             .clone(),
         &versioned_id.value.alist,
         message_expiry,
-        &server_config.ip_data.ip_cdi_secret_key,
-    );
+        server_config.signer.as_ref(),
+    ) {
+        Ok(initial_cdi) => initial_cdi,
+        Err(e) => {
+            error!("Could not sign the initial account {}.", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    };
 
     let versioned_credential =
         Versioned::new(VERSION_0, AccountCredential::<IpPairing, _, _>::Initial {
@@ -986,34 +1993,48 @@ async fn create_signed_identity_object(
     // The proxy expects a versioned submission, so that is what we construct.
     let versioned_submission = Versioned::new(VERSION_0, submission);
 
-    // Store the created IdentityObject.
+    let submission_value = to_value(&versioned_submission).unwrap();
+    let now = submission_queue::now();
+    let entry = queue.new_entry(submission_value, now);
+
+    // Store the created IdentityObject, together with the pending initial
+    // account creation, so that it is submitted even if we fail to do it now.
     // This is stored so it can later be retrieved by querying via the idCredPub.
-    ok_or_500!(
-        db.write_identity_object(
-            &base16_encoded_id_cred_pub,
-            &versioned_id,
-            &versioned_submission
-        ),
-        "Could not write to database."
-    );
+    let key = base16_encoded_id_cred_pub.clone();
+    let stored_entry = entry.clone();
+    match db
+        .run(move |db| {
+            db.write_identity_object(&key, &versioned_id, &versioned_submission, &stored_entry)
+        })
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            // The identity was cancelled since we checked above.
+            warn!(
+                "Refusing to create the identity {}, it was cancelled.",
+                base16_encoded_id_cred_pub
+            );
+            return Err(warp::reject::custom(IdRequestRejection::Cancelled));
+        }
+        Err(e) => {
+            error!("Could not write to database: {}", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
+        }
+    }
 
     // Submit and wait for the submission ID.
-    let submission_value = to_value(versioned_submission).unwrap();
 
-    match submit_account_creation(
-        &client,
-        server_config.submit_credential_url.clone(),
-        &submission_value,
-    )
-    .await
-    {
-        Ok(status) => {
-            ok_or_500!(
-                db.write_pending(&base16_encoded_id_cred_pub, status, submission_value),
-                "Could not write submission status."
-            );
+    match queue.enqueue(&base16_encoded_id_cred_pub, entry, now).await {
+        Ok(Enqueued::Rejected(e)) => {
+            warn!("Initial account creation rejected: {}", e);
+            return Err(warp::reject::custom(IdRequestRejection::ReuseOfRegId));
+        }
+        Ok(Enqueued::Submitted) | Ok(Enqueued::Deferred) => {}
+        Err(e) => {
+            error!("Could not write submission status: {}", e);
+            return Err(warp::reject::custom(IdRequestRejection::InternalError));
         }
-        Err(_) => return Err(warp::reject::custom(IdRequestRejection::ReuseOfRegId)),
     };
     // If we reached here it means we at least have a pending request. We respond
     // with a URL where they will be able to retrieve the ID object.
@@ -1043,7 +2064,7 @@ fn validate_worker(
     }
     let request = &input.id_object_request.value;
     let context = IpContext {
-        ip_info:        &server_config.ip_data.public_ip_info,
+        ip_info:        &server_config.ip_info,
         ars_infos:      &server_config.ars.anonymity_revokers,
         global_context: &server_config.global,
     };
@@ -1136,21 +2157,17 @@ fn extract_and_validate_request_query(
     })
 }
 
-/// Creates and saves the revocation record to the file system (which should be
-/// a database, but for the proof-of-concept we use the file system).
-fn save_revocation_record<A: Attribute<id::constants::BaseField>>(
-    db: &DB,
+/// Creates the revocation record to save to the database.
+fn revocation_record<A: Attribute<id::constants::BaseField>>(
     pre_identity_object: &PreIdentityObject<IpPairing, ArCurve>,
     alist: &AttributeList<id::constants::BaseField, A>,
-) -> anyhow::Result<()> {
-    let ar_record = AnonymityRevocationRecord {
+) -> AnonymityRevocationRecord<ArCurve> {
+    AnonymityRevocationRecord {
         id_cred_pub:  pre_identity_object.pub_info_for_ip.id_cred_pub,
         ar_data:      pre_identity_object.ip_ar_data.clone(),
         max_accounts: alist.max_accounts,
         threshold:    pre_identity_object.choice_ar_parameters.threshold,
-    };
-    let base16_id_cred_pub = base16_encode_string(&ar_record.id_cred_pub);
-    db.write_revocation_record(&base16_id_cred_pub, ar_record)
+    }
 }
 
 #[cfg(test)]
@@ -1178,12 +2195,17 @@ mod tests {
         let global = global_context.value;
 
         let id_url = url::Url::parse("http://localhost/verify").unwrap();
+        let keypair = ed25519_dalek::Keypair {
+            secret: ip_data.ip_cdi_secret_key,
+            public: ip_data.public_ip_info.ip_cdi_verify_key,
+        };
         let server_config = Arc::new(ServerConfig {
-            ip_data,
+            ip_info: ip_data.public_ip_info,
+            signer: Arc::new(signer::KeySigner::new(ip_data.ip_secret_key, keypair)),
             global,
             ars,
-            id_verification_url: id_url.clone(),
-            id_verification_query_url: id_url,
+            verifier: Arc::new(RedirectVerifier::new(Client::new(), id_url.clone(), id_url)),
+            attribute_list_policy: AttributeListPolicy::default(),
             retrieve_url: url::Url::parse("http://localhost/retrieve").unwrap(),
             submit_credential_url: url::Url::parse("http://localhost/submitCredential").unwrap(),
         });
@@ -1220,12 +2242,17 @@ mod tests {
         let global = global_context.value;
 
         let id_url = url::Url::parse("http://localhost/verify").unwrap();
+        let keypair = ed25519_dalek::Keypair {
+            secret: ip_data.ip_cdi_secret_key,
+            public: ip_data.public_ip_info.ip_cdi_verify_key,
+        };
         let server_config = Arc::new(ServerConfig {
-            ip_data,
+            ip_info: ip_data.public_ip_info,
+            signer: Arc::new(signer::KeySigner::new(ip_data.ip_secret_key, keypair)),
             global,
             ars,
-            id_verification_url: id_url.clone(),
-            id_verification_query_url: id_url,
+            verifier: Arc::new(RedirectVerifier::new(Client::new(), id_url.clone(), id_url)),
+            attribute_list_policy: AttributeListPolicy::default(),
             retrieve_url: url::Url::parse("http://localhost/retrieve").unwrap(),
             submit_credential_url: url::Url::parse("http://localhost/submitCredential").unwrap(),
         });
@@ -1247,4 +2274,60 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn test_admin_auth() {
+        tokio_test::block_on(async {
+            let auth = admin_auth(Some("secret".to_string()));
+            assert!(
+                test::request()
+                    .header("authorization", "Bearer secret")
+                    .matches(&auth)
+                    .await
+            );
+            assert!(
+                !test::request()
+                    .header("authorization", "Bearer secreT")
+                    .matches(&auth)
+                    .await
+            );
+            assert!(!test::request().matches(&auth).await);
+            // Without a token the admin API is disabled.
+            assert!(
+                !test::request()
+                    .header("authorization", "Bearer secret")
+                    .matches(&admin_auth(None))
+                    .await
+            );
+        });
+    }
+
+    #[test]
+    fn test_request_status() {
+        let revoked = Cancellation {
+            kind:   CancellationKind::Revoked,
+            reason: "Fraud.".into(),
+            time:   0,
+        };
+        assert_eq!(
+            RequestStatus::new(Some(&revoked), true, false, true),
+            RequestStatus::Revoked
+        );
+        assert_eq!(
+            RequestStatus::new(None, true, false, true),
+            RequestStatus::Failed
+        );
+        assert_eq!(
+            RequestStatus::new(None, false, true, true),
+            RequestStatus::Pending
+        );
+        assert_eq!(
+            RequestStatus::new(None, false, false, true),
+            RequestStatus::Done
+        );
+        assert_eq!(
+            RequestStatus::new(None, false, false, false),
+            RequestStatus::Validated
+        );
+    }
 }
@@ -0,0 +1,6 @@
+//! Shared components of the identity provider service.
+pub mod metrics;
+pub mod signer;
+pub mod storage;
+pub mod submission_queue;
+pub mod verifier;
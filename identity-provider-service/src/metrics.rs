@@ -0,0 +1,110 @@
+//! Prometheus metrics of the identity provider service.
+//!
+//! All metrics are kept in their own [Registry], rather than the global
+//! default one, so that independent instances, e.g., in tests, do not
+//! interfere with each other.
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+/// The metrics of the service.
+#[derive(Clone)]
+pub struct Metrics {
+    registry:                  Registry,
+    /// Requests handled by the public API, labelled by `endpoint`.
+    pub requests:              IntCounterVec,
+    /// Requests of the public API that failed, labelled by the `reason` of the
+    /// failure.
+    pub failures:              IntCounterVec,
+    /// Outcomes of initial account submissions, labelled by `outcome`.
+    pub submissions:           IntCounterVec,
+    /// Duration of the calls to the wallet-proxy, labelled by `operation`,
+    /// which is either `submit` or `status`.
+    pub wallet_proxy_duration: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new() -> anyhow::Result<Self> {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "identity_provider_requests_total",
+                "Requests handled by the identity provider service.",
+            ),
+            &["endpoint"],
+        )?;
+        let failures = IntCounterVec::new(
+            Opts::new(
+                "identity_provider_request_failures_total",
+                "Requests that failed, by the reason of the failure.",
+            ),
+            &["reason"],
+        )?;
+        let submissions = IntCounterVec::new(
+            Opts::new(
+                "identity_provider_submissions_total",
+                "Outcomes of initial account submissions.",
+            ),
+            &["outcome"],
+        )?;
+        let wallet_proxy_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "identity_provider_wallet_proxy_duration_seconds",
+                "Duration of the calls to the wallet-proxy, by operation.",
+            ),
+            &["operation"],
+        )?;
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(failures.clone()))?;
+        registry.register(Box::new(submissions.clone()))?;
+        registry.register(Box::new(wallet_proxy_duration.clone()))?;
+        Ok(Self {
+            registry,
+            requests,
+            failures,
+            submissions,
+            wallet_proxy_duration,
+        })
+    }
+
+    /// The current values of all metrics in the Prometheus text format.
+    pub fn encode(&self) -> anyhow::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let metrics = Metrics::new().unwrap();
+        metrics.requests.with_label_values(&["create"]).inc();
+        metrics.requests.with_label_values(&["create"]).inc();
+        metrics
+            .failures
+            .with_label_values(&["invalid_proofs"])
+            .inc();
+        metrics
+            .wallet_proxy_duration
+            .with_label_values(&["submit"])
+            .observe(0.5);
+        let text = metrics.encode().unwrap();
+        assert!(text.contains("identity_provider_requests_total{endpoint=\"create\"} 2"));
+        assert!(
+            text.contains("identity_provider_request_failures_total{reason=\"invalid_proofs\"} 1")
+        );
+        assert!(text.contains(
+            "identity_provider_wallet_proxy_duration_seconds_count{operation=\"submit\"} 1"
+        ));
+        // Metrics of other instances are not included.
+        assert!(!Metrics::new()
+            .unwrap()
+            .encode()
+            .unwrap()
+            .contains("endpoint=\"create\""));
+    }
+}
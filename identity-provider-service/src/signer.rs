@@ -0,0 +1,363 @@
+//! Signing with the keys of the identity provider.
+//!
+//! The service does not need the secret keys of the identity provider, only
+//! the ability to sign with them. This is captured by the
+//! [IdentityProviderSigner] trait, which combines signing identity objects
+//! ([IpSigner]) with signing initial credentials ([IpCdiSigner]).
+//!
+//! The PS key, used for signing identity objects, is always kept in memory. It
+//! is read from the identity provider's private file, which can be encrypted
+//! with a password in the format of `crypto_common::encryption`, e.g., with the
+//! `utils encrypt` tool. The ed25519 key, used for signing initial
+//! credentials, can either be read from the same file, or, with the `pkcs11`
+//! feature, be kept in a PKCS#11 token such as SoftHSM.
+use anyhow::{anyhow, ensure, Context};
+use crypto_common::{
+    base16_decode,
+    encryption::{decrypt, EncryptedData, Password},
+    SerdeDeserialize,
+};
+use ed25519_dalek::Verifier;
+use id::{
+    constants::IpPairing,
+    identity_provider::{IpCdiSigner, IpSigner},
+    types::{IpData, IpInfo},
+};
+use std::{fs, path::Path};
+
+/// Signing with both keys of the identity provider.
+pub trait IdentityProviderSigner:
+    IpSigner<IpPairing, Error = anyhow::Error> + IpCdiSigner<Error = anyhow::Error> + Send + Sync {
+}
+
+impl<S> IdentityProviderSigner for S where
+    S: IpSigner<IpPairing, Error = anyhow::Error>
+        + IpCdiSigner<Error = anyhow::Error>
+        + Send
+        + Sync
+{
+}
+
+/// A signer with the PS key in memory, and the ed25519 key in the given
+/// signer.
+pub struct KeySigner<Cdi> {
+    ps_key: ps_sig::SecretKey<IpPairing>,
+    cdi:    Cdi,
+}
+
+impl<Cdi> KeySigner<Cdi> {
+    pub fn new(ps_key: ps_sig::SecretKey<IpPairing>, cdi: Cdi) -> Self { Self { ps_key, cdi } }
+}
+
+impl<Cdi> IpSigner<IpPairing> for KeySigner<Cdi> {
+    type Error = anyhow::Error;
+
+    fn sign_unknown_message(
+        &self,
+        message: &ps_sig::UnknownMessage<IpPairing>,
+    ) -> anyhow::Result<ps_sig::Signature<IpPairing>> {
+        match IpSigner::sign_unknown_message(&self.ps_key, message) {
+            Ok(sig) => Ok(sig),
+            Err(e) => match e {},
+        }
+    }
+}
+
+impl<Cdi: IpCdiSigner> IpCdiSigner for KeySigner<Cdi> {
+    type Error = anyhow::Error;
+
+    fn sign(&self, message: &[u8]) -> anyhow::Result<ed25519_dalek::Signature> {
+        self.cdi.sign(message).map_err(|e| anyhow!("{}", e))
+    }
+}
+
+/// A signer with both keys in memory.
+pub type SoftwareSigner = KeySigner<ed25519_dalek::Keypair>;
+
+/// The public data of the identity provider together with its PS secret key.
+/// This is the format of the identity provider's private file, without the
+/// ed25519 secret key, which might be kept elsewhere.
+#[derive(SerdeDeserialize)]
+struct IpPsKeys {
+    #[serde(rename = "ipInfo")]
+    ip_info:       IpInfo<IpPairing>,
+    #[serde(rename = "ipSecretKey", deserialize_with = "base16_decode")]
+    ip_secret_key: ps_sig::SecretKey<IpPairing>,
+}
+
+/// Read the key file, decrypting it if a password is given.
+fn read_key_file(path: &Path, password: Option<&Password>) -> anyhow::Result<Vec<u8>> {
+    let contents = fs::read(path).with_context(|| format!("Could not read {}.", path.display()))?;
+    match password {
+        None => Ok(contents),
+        Some(password) => {
+            let data: EncryptedData = serde_json::from_slice(&contents)
+                .with_context(|| format!("{} is not an encrypted file.", path.display()))?;
+            decrypt(password, &data)
+                .map_err(|e| anyhow!("Could not decrypt {}: {}", path.display(), e))
+        }
+    }
+}
+
+/// Read the identity provider's private file, in the format of
+/// [IpData], and make a signer with both keys in memory.
+pub fn read_software_signer(
+    path: &Path,
+    password: Option<&Password>,
+) -> anyhow::Result<(IpInfo<IpPairing>, SoftwareSigner)> {
+    let ip_data: IpData<IpPairing> = serde_json::from_slice(&read_key_file(path, password)?)
+        .with_context(|| {
+            format!(
+                "{} does not contain identity provider keys.",
+                path.display()
+            )
+        })?;
+    let keypair = ed25519_dalek::Keypair {
+        secret: ip_data.ip_cdi_secret_key,
+        public: ip_data.public_ip_info.ip_cdi_verify_key,
+    };
+    Ok((
+        ip_data.public_ip_info,
+        KeySigner::new(ip_data.ip_secret_key, keypair),
+    ))
+}
+
+/// Read the identity provider's public data and PS key from its private file,
+/// and make a signer using the given signer for the ed25519 key. The ed25519
+/// key in the file, if any, is ignored.
+pub fn read_signer_with<Cdi>(
+    path: &Path,
+    password: Option<&Password>,
+    cdi: Cdi,
+) -> anyhow::Result<(IpInfo<IpPairing>, KeySigner<Cdi>)> {
+    let keys: IpPsKeys =
+        serde_json::from_slice(&read_key_file(path, password)?).with_context(|| {
+            format!(
+                "{} does not contain identity provider keys.",
+                path.display()
+            )
+        })?;
+    Ok((keys.ip_info, KeySigner::new(keys.ip_secret_key, cdi)))
+}
+
+/// Check that the signer signs initial credentials with the key of the
+/// identity provider, so that misconfiguration is caught on startup rather
+/// than when the first account is created.
+pub fn check_signer(
+    signer: &dyn IdentityProviderSigner,
+    ip_info: &IpInfo<IpPairing>,
+) -> anyhow::Result<()> {
+    let message = b"identity provider signer check";
+    let signature = signer.sign(message)?;
+    ensure!(
+        ip_info
+            .ip_cdi_verify_key
+            .verify(message, &signature)
+            .is_ok(),
+        "The ed25519 key of the signer does not match the identity provider's public key."
+    );
+    Ok(())
+}
+
+#[cfg(feature = "pkcs11")]
+pub use self::pkcs11::Pkcs11CdiSigner;
+
+#[cfg(feature = "pkcs11")]
+mod pkcs11 {
+    use anyhow::{anyhow, bail, Context};
+    use cryptoki::{
+        context::{CInitializeArgs, Pkcs11},
+        mechanism::Mechanism,
+        object::{Attribute, ObjectClass, ObjectHandle},
+        session::{Session, UserType},
+        slot::Slot,
+        types::AuthPin,
+    };
+    use id::identity_provider::IpCdiSigner;
+    use std::{convert::TryFrom, path::Path, sync::Mutex};
+
+    /// Signs initial credentials with an ed25519 key in a PKCS#11 token, e.g.,
+    /// SoftHSM. The key is found by its label.
+    pub struct Pkcs11CdiSigner {
+        /// The module. Signing happens under the lock, which ensures that only
+        /// one session is logged in at a time.
+        pkcs11:    Mutex<Pkcs11>,
+        slot:      Slot,
+        pin:       String,
+        key_label: String,
+    }
+
+    impl Pkcs11CdiSigner {
+        /// Load the PKCS#11 module from the given path, and use the key with
+        /// the given label in the token with the given label.
+        pub fn new(
+            module: &Path,
+            token_label: &str,
+            pin: String,
+            key_label: String,
+        ) -> anyhow::Result<Self> {
+            let pkcs11 = Pkcs11::new(module)
+                .with_context(|| format!("Could not load PKCS#11 module {}.", module.display()))?;
+            pkcs11.initialize(CInitializeArgs::OsThreads)?;
+            let mut slot = None;
+            for candidate in pkcs11.get_slots_with_token()? {
+                if pkcs11.get_token_info(candidate)?.label().trim() == token_label {
+                    slot = Some(candidate);
+                    break;
+                }
+            }
+            let slot = match slot {
+                Some(slot) => slot,
+                None => bail!("No PKCS#11 token with label {}.", token_label),
+            };
+            let signer = Self {
+                pkcs11: Mutex::new(pkcs11),
+                slot,
+                pin,
+                key_label,
+            };
+            // Check that we can log in and find the key.
+            signer.with_key(|_, _| Ok(()))?;
+            Ok(signer)
+        }
+
+        /// Open a logged in session, and run the function with the session and
+        /// the key.
+        fn with_key<A>(
+            &self,
+            f: impl FnOnce(&Session, ObjectHandle) -> anyhow::Result<A>,
+        ) -> anyhow::Result<A> {
+            let pkcs11 = self
+                .pkcs11
+                .lock()
+                .map_err(|_| anyhow!("PKCS#11 lock poisoned."))?;
+            let session = pkcs11.open_ro_session(self.slot)?;
+            session.login(UserType::User, Some(&AuthPin::new(self.pin.clone())))?;
+            let keys = session.find_objects(&[
+                Attribute::Class(ObjectClass::PRIVATE_KEY),
+                Attribute::Label(self.key_label.as_bytes().to_vec()),
+            ])?;
+            let result = match keys.as_slice() {
+                [key] => f(&session, *key),
+                [] => Err(anyhow!("No private key with label {}.", self.key_label)),
+                _ => Err(anyhow!(
+                    "More than one private key with label {}.",
+                    self.key_label
+                )),
+            };
+            session.logout()?;
+            result
+        }
+    }
+
+    impl IpCdiSigner for Pkcs11CdiSigner {
+        type Error = anyhow::Error;
+
+        fn sign(&self, message: &[u8]) -> anyhow::Result<ed25519_dalek::Signature> {
+            let signature =
+                self.with_key(|session, key| Ok(session.sign(&Mechanism::Eddsa, key, message)?))?;
+            Ok(ed25519_dalek::Signature::try_from(signature.as_slice())?)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        //! These tests need SoftHSM, or another PKCS#11 module, with an
+        //! initialized token, e.g.,
+        //!
+        //! ```console
+        //! softhsm2-util --init-token --free --label idp-test --pin 1234 --so-pin 1234
+        //! PKCS11_MODULE=/usr/lib/softhsm/libsofthsm2.so cargo test --features pkcs11
+        //! ```
+        //!
+        //! They are skipped if `PKCS11_MODULE` is not set.
+        use super::*;
+        use cryptoki::object::KeyType;
+        use ed25519_dalek::Verifier;
+
+        /// DER encoding of the OID of edwards25519, 1.3.101.112.
+        const ED25519_PARAMS: [u8; 5] = [0x06, 0x03, 0x2b, 0x65, 0x70];
+
+        /// Import the key into the token, and return the signer using it.
+        fn import_key(keypair: &ed25519_dalek::Keypair, label: &str) -> Option<Pkcs11CdiSigner> {
+            let module = std::env::var("PKCS11_MODULE").ok()?;
+            let token = std::env::var("PKCS11_TOKEN").unwrap_or_else(|_| "idp-test".into());
+            let pin = std::env::var("PKCS11_PIN").unwrap_or_else(|_| "1234".into());
+            {
+                let pkcs11 = Pkcs11::new(&module).expect("Cannot load the module.");
+                pkcs11.initialize(CInitializeArgs::OsThreads).unwrap();
+                let slot = pkcs11
+                    .get_slots_with_token()
+                    .unwrap()
+                    .into_iter()
+                    .find(|slot| pkcs11.get_token_info(*slot).unwrap().label().trim() == token)
+                    .expect("The test token should exist.");
+                let session = pkcs11.open_rw_session(slot).unwrap();
+                session
+                    .login(UserType::User, Some(&AuthPin::new(pin.clone())))
+                    .unwrap();
+                session
+                    .create_object(&[
+                        Attribute::Class(ObjectClass::PRIVATE_KEY),
+                        Attribute::KeyType(KeyType::EC_EDWARDS),
+                        Attribute::EcParams(ED25519_PARAMS.to_vec()),
+                        Attribute::Value(keypair.secret.as_bytes().to_vec()),
+                        Attribute::Label(label.as_bytes().to_vec()),
+                        Attribute::Token(true),
+                        Attribute::Sign(true),
+                    ])
+                    .expect("Cannot import the key.");
+                session.logout().unwrap();
+            }
+            Some(
+                Pkcs11CdiSigner::new(Path::new(&module), &token, pin, label.into())
+                    .expect("Cannot create the signer."),
+            )
+        }
+
+        #[test]
+        fn test_pkcs11_signer() {
+            let keypair = ed25519_dalek::Keypair::generate(&mut rand::thread_rng());
+            let label = format!("ip-cdi-{}", uuid::Uuid::new_v4());
+            let signer = match import_key(&keypair, &label) {
+                Some(signer) => signer,
+                None => return,
+            };
+            let message = b"initial credential";
+            let signature = signer.sign(message).expect("Signing should succeed.");
+            assert!(keypair.public.verify(message, &signature).is_ok());
+            // Signing is deterministic, so the signature agrees with the one made
+            // in memory.
+            assert_eq!(signature, IpCdiSigner::sign(&keypair, message).unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto_common::encryption::encrypt;
+
+    #[test]
+    fn test_encrypted_key_file() {
+        let dir = std::env::temp_dir().join(format!("ip-signer-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let keys = include_bytes!("../data/identity_provider.json");
+        let password: Password = "secret".parse().unwrap();
+        let encrypted = encrypt(&password, &keys.to_vec(), &mut rand::thread_rng());
+        let path = dir.join("identity_provider.json.encrypted");
+        fs::write(&path, serde_json::to_vec(&encrypted).unwrap()).unwrap();
+
+        let (ip_info, signer) = read_software_signer(&path, Some(&password)).unwrap();
+        check_signer(&signer, &ip_info).unwrap();
+        // The PS key can be used with another ed25519 key, which does not match.
+        let other = ed25519_dalek::Keypair::generate(&mut rand::thread_rng());
+        let (ip_info, signer) = read_signer_with(&path, Some(&password), other).unwrap();
+        assert!(check_signer(&signer, &ip_info).is_err());
+
+        let wrong: Password = "wrong".parse().unwrap();
+        assert!(read_software_signer(&path, Some(&wrong)).is_err());
+        assert!(read_software_signer(&path, None).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
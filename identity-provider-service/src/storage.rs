@@ -0,0 +1,1713 @@
+//! Persistent storage of the identity provider service.
+//!
+//! The service stores validated requests, anonymity revocation records,
+//! identity objects, the status of pending initial account creations, the
+//! identities that were rejected or revoked by an administrator, and the
+//! signed status list of the revoked identities. The
+//! [Storage] trait abstracts over where this data lives. There are two
+//! implementations
+//!
+//! - [SqliteStorage] which keeps everything in a single SQLite database. This
+//!   is the backend intended for production use. Every operation uses its own
+//!   connection, and updates that touch more than one table are done in a
+//!   transaction, so concurrent requests do not need to be serialized by the
+//!   service.
+//! - [FileStorage] which keeps each entry in a separate JSON file. This is the
+//!   original storage of the service. It serializes all accesses via a global
+//!   lock. Data stored by it can be imported into an SQLite database via
+//!   [import_file_database].
+use anyhow::{bail, ensure, Context};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
+use serde_json::{from_str, Value};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+/// Status of a submission as returned by the wallet-proxy.
+pub enum SubmissionStatus {
+    /// Submission is absent, most likely it was invalid.
+    Absent,
+    /// Submission is received, but not yet committed to any blocks.
+    Received,
+    /// Submission is committed to one or more blocks.
+    Committed,
+    /// Submission is finalized in a block.
+    Finalized,
+}
+
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+/// When the initial account transaction is submitted we use this type to keep
+/// track of its status.
+pub enum PendingStatus {
+    /// The transaction was submitted, and is currently in the state indicated
+    /// by the submission status.
+    Submitted {
+        submission_id: String,
+        status:        SubmissionStatus,
+    },
+    /// The transaction could not be submitted due to, most likely, network
+    /// issues. It should be retried.
+    CouldNotSubmit,
+}
+
+/// An initial account creation that has not yet been finalized, together with
+/// the submission that was, or should be, sent to the wallet-proxy.
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Debug, PartialEq)]
+pub struct PendingEntry {
+    pub status:       PendingStatus,
+    pub value:        Value,
+    /// The number of consecutive failed attempts at submitting the
+    /// transaction or following up on it.
+    #[serde(default)]
+    pub attempts:     u32,
+    /// Time, in seconds since the unix epoch, before which the entry should
+    /// not be processed again.
+    #[serde(default)]
+    pub next_attempt: i64,
+    /// The reason the last attempt failed, if it did.
+    #[serde(default)]
+    pub last_error:   Option<String>,
+}
+
+impl PendingEntry {
+    /// A fresh entry that can be processed immediately.
+    pub fn new(status: PendingStatus, value: Value) -> Self {
+        Self {
+            status,
+            value,
+            attempts: 0,
+            next_attempt: 0,
+            last_error: None,
+        }
+    }
+}
+
+/// An initial account creation that was given up on, either because the
+/// wallet-proxy rejected it, or because it failed too many times. It is kept
+/// so that it can be inspected and re-driven.
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Debug, PartialEq)]
+pub struct DeadLetter {
+    /// The pending entry at the time it was given up on.
+    pub entry:  PendingEntry,
+    /// Why the entry was given up on.
+    pub reason: String,
+    /// Time, in seconds since the unix epoch, when the entry was given up on.
+    pub time:   i64,
+}
+
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+/// The kind of decision an administrator made about an identity.
+pub enum CancellationKind {
+    /// The identity was rejected before it was issued.
+    Rejected,
+    /// The identity was revoked after it was issued.
+    Revoked,
+}
+
+/// A decision by an administrator that an identity should not be, or no
+/// longer be, handed out to the user. The request, the anonymity revocation
+/// record and the identity object, if any, are kept.
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Debug, PartialEq)]
+pub struct Cancellation {
+    pub kind:   CancellationKind,
+    /// Why the identity was rejected or revoked.
+    pub reason: String,
+    /// Time, in seconds since the unix epoch, of the decision.
+    pub time:   i64,
+}
+
+/// Storage of all the data the identity provider service needs to keep. All
+/// entries are indexed by the base16 encoding of the `idCredPub` of the
+/// identity. Requests, revocation records and identity objects are stored as
+/// JSON values, the service is responsible for their format.
+///
+/// Implementations must be safe to use from concurrent requests.
+pub trait Storage: Send + Sync {
+    /// Write the validated request, so that it can be retrieved and used to
+    /// create the identity object when the identity verifier calls with an
+    /// attribute list and a verification result.
+    fn write_request_record(&self, key: &str, request: &Value) -> anyhow::Result<()>;
+
+    /// Read a validated request under the given key. Fails if there is none.
+    fn read_request_record(&self, key: &str) -> anyhow::Result<Value>;
+
+    /// The keys of all validated requests.
+    fn list_request_keys(&self) -> anyhow::Result<Vec<String>>;
+
+    /// Write the anonymity revocation record under the given key.
+    fn write_revocation_record(&self, key: &str, record: &Value) -> anyhow::Result<()>;
+
+    /// Read the anonymity revocation record under the given key. Fails if there
+    /// is none.
+    fn read_revocation_record(&self, key: &str) -> anyhow::Result<Value>;
+
+    /// Write the identity object, together with the initial account and its
+    /// credential, under the given key.
+    fn write_identity_object(&self, key: &str, obj: &Value) -> anyhow::Result<()>;
+
+    /// Write the identity object like [Storage::write_identity_object],
+    /// together with the pending entry of its initial account creation. The
+    /// entry is stored before the transaction is submitted, so that the
+    /// submission is retried if the service stops before its outcome is
+    /// recorded. Nothing is written, and `false` returned, if the identity was
+    /// cancelled.
+    fn write_identity_object_with_pending(
+        &self,
+        key: &str,
+        obj: &Value,
+        entry: &PendingEntry,
+    ) -> anyhow::Result<bool>;
+
+    /// Replace the identity object under the given key, if it is still the
+    /// given previous one and the identity was not cancelled. Returns whether
+    /// the identity object was replaced, so that concurrent updates do not
+    /// overwrite each other and an identity cannot be updated after it was
+    /// rejected or revoked.
+    fn replace_identity_object(
+        &self,
+        key: &str,
+        previous: &Value,
+        obj: &Value,
+    ) -> anyhow::Result<bool>;
+
+    /// Read the identity object under the given key. Fails if there is none.
+    fn read_identity_object(&self, key: &str) -> anyhow::Result<Value>;
+
+    /// The keys of all identity objects.
+    fn list_identity_object_keys(&self) -> anyhow::Result<Vec<String>>;
+
+    /// Store or update the pending entry under the given key, unless the
+    /// identity was cancelled. Checking this together with the write ensures
+    /// that a submission in progress cannot bring back an entry removed by
+    /// [Storage::cancel].
+    fn write_pending(&self, key: &str, entry: &PendingEntry) -> anyhow::Result<()>;
+
+    /// Read the pending entry under the given key, if it exists.
+    fn read_pending(&self, key: &str) -> anyhow::Result<Option<PendingEntry>>;
+
+    /// All pending entries.
+    fn list_pending(&self) -> anyhow::Result<Vec<(String, PendingEntry)>>;
+
+    /// Remove the pending entry, since the initial account creation has
+    /// finalized.
+    fn mark_finalized(&self, key: &str) -> anyhow::Result<()>;
+
+    /// Delete the identity, which happens if the initial account creation
+    /// failed. The revocation record and the identity object are kept as
+    /// deleted entries, and the pending entry is removed.
+    fn delete_all(&self, key: &str) -> anyhow::Result<()>;
+
+    /// Remove the pending entry under the given key and store the dead letter
+    /// instead, unless the identity was cancelled.
+    fn dead_letter(&self, key: &str, letter: &DeadLetter) -> anyhow::Result<()>;
+
+    /// Read the dead letter under the given key, if it exists.
+    fn read_dead_letter(&self, key: &str) -> anyhow::Result<Option<DeadLetter>>;
+
+    /// All dead letters.
+    fn list_dead_letters(&self) -> anyhow::Result<Vec<(String, DeadLetter)>>;
+
+    /// Queue the entry under the given key for processing at the given time,
+    /// resetting its attempts. A dead letter is moved back to the pending
+    /// entries. Returns whether there was an entry with the given key.
+    fn redrive(&self, key: &str, time: i64) -> anyhow::Result<bool>;
+
+    /// Record the cancellation of the identity under the given key, and remove
+    /// its pending entry or dead letter, so that the initial account creation
+    /// is no longer followed up on.
+    fn cancel(&self, key: &str, cancellation: &Cancellation) -> anyhow::Result<()>;
+
+    /// Read the cancellation of the identity under the given key, if it exists.
+    fn read_cancellation(&self, key: &str) -> anyhow::Result<Option<Cancellation>>;
+
+    /// All cancellations.
+    fn list_cancellations(&self) -> anyhow::Result<Vec<(String, Cancellation)>>;
+
+    /// Write the signed status list of the revoked identities, replacing the
+    /// previous one.
+    fn write_status_list(&self, list: &Value) -> anyhow::Result<()>;
+
+    /// Read the signed status list, if one has been written.
+    fn read_status_list(&self) -> anyhow::Result<Option<Value>>;
+
+    /// Whether the initial account creation is still pending.
+    fn is_pending(&self, key: &str) -> anyhow::Result<bool> {
+        Ok(self.read_pending(key)?.is_some())
+    }
+}
+
+/// Tables of the SQLite database as created by the first version of the
+/// service that supported it. The `deleted_` tables play the role of the
+/// backup folder of the file storage.
+const INITIAL_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS requests (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS revocation_records (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS identity_objects (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS pending (
+    key TEXT PRIMARY KEY NOT NULL,
+    status TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS deleted_revocation_records (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS deleted_identity_objects (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+";
+
+fn migrate_initial(conn: &Connection) -> rusqlite::Result<()> { conn.execute_batch(INITIAL_SCHEMA) }
+
+/// Add the retry state of pending entries, and the dead letters.
+fn migrate_retries(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "pending", "attempts", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(
+        conn,
+        "pending",
+        "next_attempt",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column(conn, "pending", "last_error", "TEXT")?;
+    conn.execute_batch(
+        "
+CREATE TABLE IF NOT EXISTS dead_letters (
+    key TEXT PRIMARY KEY NOT NULL,
+    entry TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    time INTEGER NOT NULL
+);
+",
+    )
+}
+
+/// Add the identities rejected or revoked by an administrator.
+fn migrate_cancellations(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "
+CREATE TABLE IF NOT EXISTS cancellations (
+    key TEXT PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    time INTEGER NOT NULL
+);
+",
+    )
+}
+
+/// Add the signed status list of the revoked identities.
+fn migrate_status_list(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "
+CREATE TABLE IF NOT EXISTS status_list (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+",
+    )
+}
+
+/// Add the column to the table unless it already has it. Databases written
+/// before the schema version was recorded can have some of the columns of
+/// later migrations.
+fn add_column(conn: &Connection, table: &str, column: &str, def: &str) -> rusqlite::Result<()> {
+    let exists: bool = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ),
+        params![column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, def
+        ))?;
+    }
+    Ok(())
+}
+
+/// The migrations of the SQLite database, in order. The database records the
+/// number of migrations that were applied to it in its `user_version`, and
+/// [SqliteStorage::open] applies the remaining ones. New tables and columns
+/// are added by appending a migration, never by changing an existing one.
+const MIGRATIONS: &[fn(&Connection) -> rusqlite::Result<()>] = &[
+    migrate_initial,
+    migrate_retries,
+    migrate_cancellations,
+    migrate_status_list,
+];
+
+/// Apply the migrations the database is missing, in a single transaction.
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: u32 = tx.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+    let version = version as usize;
+    ensure!(
+        version <= MIGRATIONS.len(),
+        "The database has schema version {}, but this version of the service only supports up to \
+         {}.",
+        version,
+        MIGRATIONS.len()
+    );
+    for migration in &MIGRATIONS[version..] {
+        migration(&tx)?;
+    }
+    tx.execute_batch(&format!("PRAGMA user_version = {}", MIGRATIONS.len()))?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// The key of the only row of the `status_list` table.
+const STATUS_LIST_KEY: &str = "latest";
+
+/// How long to wait for other connections to release their locks before
+/// failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Storage in an SQLite database.
+#[derive(Clone, Debug)]
+pub struct SqliteStorage {
+    /// Path of the database file.
+    path: PathBuf,
+}
+
+impl SqliteStorage {
+    /// Open the database at the given path, creating it if it does not exist,
+    /// and bring its tables up to date, see [MIGRATIONS].
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let storage = SqliteStorage {
+            path: path.as_ref().to_path_buf(),
+        };
+        let mut conn = storage.connection()?;
+        // Write-ahead logging allows readers to proceed concurrently with a
+        // writer. The setting is persistent.
+        conn.query_row("PRAGMA journal_mode=WAL", params![], |_| Ok(()))?;
+        migrate(&mut conn)?;
+        Ok(storage)
+    }
+
+    fn connection(&self) -> anyhow::Result<Connection> {
+        let conn = Connection::open(&self.path)
+            .with_context(|| format!("Could not open database {}.", self.path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(conn)
+    }
+
+    fn write_value(&self, table: &str, key: &str, value: &Value) -> anyhow::Result<()> {
+        self.connection()?.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} (key, value) VALUES (?1, ?2)",
+                table
+            ),
+            params![key, value.to_string()],
+        )?;
+        Ok(())
+    }
+
+    fn read_value(&self, table: &str, key: &str) -> anyhow::Result<Value> {
+        let contents: Option<String> = self
+            .connection()?
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", table),
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match contents {
+            Some(contents) => Ok(from_str(&contents)?),
+            None => bail!("No entry with key {} in {}.", key, table),
+        }
+    }
+
+    fn list_keys(&self, table: &str) -> anyhow::Result<Vec<String>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!("SELECT key FROM {} ORDER BY key", table))?;
+        let keys = stmt
+            .query_map(params![], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(keys)
+    }
+}
+
+/// Insert the cancellation, replacing an existing one if `replace` is set and
+/// otherwise ignoring the new one. Returns the number of inserted rows.
+fn insert_cancellation(
+    conn: &Connection,
+    key: &str,
+    cancellation: &Cancellation,
+    replace: bool,
+) -> anyhow::Result<usize> {
+    Ok(conn.execute(
+        &format!(
+            "INSERT OR {} INTO cancellations (key, kind, reason, time) VALUES (?1, ?2, ?3, ?4)",
+            if replace { "REPLACE" } else { "IGNORE" }
+        ),
+        params![
+            key,
+            serde_json::to_string(&cancellation.kind)?,
+            cancellation.reason,
+            cancellation.time
+        ],
+    )?)
+}
+
+/// The columns of the pending table, apart from the key, in the order expected
+/// by [pending_from_row].
+const PENDING_COLUMNS: &str = "status, value, attempts, next_attempt, last_error";
+
+/// A row of the pending table, as read from the database.
+type PendingRow = (String, String, u32, i64, Option<String>);
+
+fn read_pending_row(row: &rusqlite::Row, offset: usize) -> rusqlite::Result<PendingRow> {
+    Ok((
+        row.get(offset)?,
+        row.get(offset + 1)?,
+        row.get(offset + 2)?,
+        row.get(offset + 3)?,
+        row.get(offset + 4)?,
+    ))
+}
+
+fn pending_from_row(
+    (status, value, attempts, next_attempt, last_error): PendingRow,
+) -> anyhow::Result<PendingEntry> {
+    Ok(PendingEntry {
+        status: from_str(&status)?,
+        value: from_str(&value)?,
+        attempts,
+        next_attempt,
+        last_error,
+    })
+}
+
+/// Insert the pending entry, replacing an existing entry if `replace` is set
+/// and otherwise ignoring the new one. Nothing is inserted if the identity was
+/// cancelled. Returns the number of inserted rows.
+fn insert_pending(
+    conn: &Connection,
+    key: &str,
+    entry: &PendingEntry,
+    replace: bool,
+) -> anyhow::Result<usize> {
+    Ok(conn.execute(
+        &format!(
+            "INSERT OR {} INTO pending (key, {}) SELECT ?1, ?2, ?3, ?4, ?5, ?6 WHERE NOT EXISTS \
+             (SELECT 1 FROM cancellations WHERE key = ?1)",
+            if replace { "REPLACE" } else { "IGNORE" },
+            PENDING_COLUMNS
+        ),
+        params![
+            key,
+            serde_json::to_string(&entry.status)?,
+            entry.value.to_string(),
+            entry.attempts,
+            entry.next_attempt,
+            entry.last_error
+        ],
+    )?)
+}
+
+impl Storage for SqliteStorage {
+    fn write_request_record(&self, key: &str, request: &Value) -> anyhow::Result<()> {
+        self.write_value("requests", key, request)
+    }
+
+    fn read_request_record(&self, key: &str) -> anyhow::Result<Value> {
+        self.read_value("requests", key)
+    }
+
+    fn list_request_keys(&self) -> anyhow::Result<Vec<String>> { self.list_keys("requests") }
+
+    fn write_revocation_record(&self, key: &str, record: &Value) -> anyhow::Result<()> {
+        self.write_value("revocation_records", key, record)
+    }
+
+    fn read_revocation_record(&self, key: &str) -> anyhow::Result<Value> {
+        self.read_value("revocation_records", key)
+    }
+
+    fn write_identity_object(&self, key: &str, obj: &Value) -> anyhow::Result<()> {
+        self.write_value("identity_objects", key, obj)
+    }
+
+    fn write_identity_object_with_pending(
+        &self,
+        key: &str,
+        obj: &Value,
+        entry: &PendingEntry,
+    ) -> anyhow::Result<bool> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if insert_pending(&tx, key, entry, true)? == 0 {
+            return Ok(false);
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO identity_objects (key, value) VALUES (?1, ?2)",
+            params![key, obj.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    fn replace_identity_object(
+        &self,
+        key: &str,
+        previous: &Value,
+        obj: &Value,
+    ) -> anyhow::Result<bool> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let current: Option<String> = tx
+            .query_row(
+                "SELECT value FROM identity_objects WHERE key = ?1 AND NOT EXISTS (SELECT 1 FROM \
+                 cancellations WHERE key = ?1)",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match current {
+            Some(current) if from_str::<Value>(&current)? == *previous => {}
+            _ => return Ok(false),
+        }
+        tx.execute(
+            "UPDATE identity_objects SET value = ?2 WHERE key = ?1",
+            params![key, obj.to_string()],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    fn read_identity_object(&self, key: &str) -> anyhow::Result<Value> {
+        self.read_value("identity_objects", key)
+    }
+
+    fn list_identity_object_keys(&self) -> anyhow::Result<Vec<String>> {
+        self.list_keys("identity_objects")
+    }
+
+    fn write_pending(&self, key: &str, entry: &PendingEntry) -> anyhow::Result<()> {
+        insert_pending(&self.connection()?, key, entry, true)?;
+        Ok(())
+    }
+
+    fn read_pending(&self, key: &str) -> anyhow::Result<Option<PendingEntry>> {
+        let row = self
+            .connection()?
+            .query_row(
+                &format!("SELECT {} FROM pending WHERE key = ?1", PENDING_COLUMNS),
+                params![key],
+                |row| read_pending_row(row, 0),
+            )
+            .optional()?;
+        row.map(pending_from_row).transpose()
+    }
+
+    fn list_pending(&self) -> anyhow::Result<Vec<(String, PendingEntry)>> {
+        let conn = self.connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key, {} FROM pending ORDER BY key",
+            PENDING_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok((row.get::<_, String>(0)?, read_pending_row(row, 1)?))
+        })?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (key, row) = row?;
+            out.push((key, pending_from_row(row)?));
+        }
+        Ok(out)
+    }
+
+    fn mark_finalized(&self, key: &str) -> anyhow::Result<()> {
+        self.connection()?
+            .execute("DELETE FROM pending WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    fn delete_all(&self, key: &str) -> anyhow::Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for table in &["revocation_records", "identity_objects"] {
+            tx.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO deleted_{0} (key, value) SELECT key, value FROM {0} \
+                     WHERE key = ?1",
+                    table
+                ),
+                params![key],
+            )?;
+            tx.execute(&format!("DELETE FROM {} WHERE key = ?1", table), params![
+                key
+            ])?;
+        }
+        tx.execute("DELETE FROM pending WHERE key = ?1", params![key])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn dead_letter(&self, key: &str, letter: &DeadLetter) -> anyhow::Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute("DELETE FROM pending WHERE key = ?1", params![key])?;
+        tx.execute(
+            "INSERT OR REPLACE INTO dead_letters (key, entry, reason, time) SELECT ?1, ?2, ?3, ?4 \
+             WHERE NOT EXISTS (SELECT 1 FROM cancellations WHERE key = ?1)",
+            params![
+                key,
+                serde_json::to_string(&letter.entry)?,
+                letter.reason,
+                letter.time
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn read_dead_letter(&self, key: &str) -> anyhow::Result<Option<DeadLetter>> {
+        let row: Option<(String, String, i64)> = self
+            .connection()?
+            .query_row(
+                "SELECT entry, reason, time FROM dead_letters WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        row.map(|(entry, reason, time)| {
+            Ok(DeadLetter {
+                entry: from_str(&entry)?,
+                reason,
+                time,
+            })
+        })
+        .transpose()
+    }
+
+    fn list_dead_letters(&self) -> anyhow::Result<Vec<(String, DeadLetter)>> {
+        let conn = self.connection()?;
+        let mut stmt =
+            conn.prepare("SELECT key, entry, reason, time FROM dead_letters ORDER BY key")?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (key, entry, reason, time) = row?;
+            out.push((key, DeadLetter {
+                entry: from_str(&entry)?,
+                reason,
+                time,
+            }));
+        }
+        Ok(out)
+    }
+
+    fn redrive(&self, key: &str, time: i64) -> anyhow::Result<bool> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let letter: Option<String> = tx
+            .query_row(
+                "SELECT entry FROM dead_letters WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(entry) = letter {
+            let entry: PendingEntry = from_str(&entry)?;
+            tx.execute("DELETE FROM dead_letters WHERE key = ?1", params![key])?;
+            insert_pending(&tx, key, &redriven(entry, time), true)?;
+        }
+        let updated = tx.execute(
+            "UPDATE pending SET attempts = 0, next_attempt = ?2 WHERE key = ?1",
+            params![key, time],
+        )?;
+        tx.commit()?;
+        Ok(updated > 0)
+    }
+
+    fn cancel(&self, key: &str, cancellation: &Cancellation) -> anyhow::Result<()> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute("DELETE FROM pending WHERE key = ?1", params![key])?;
+        tx.execute("DELETE FROM dead_letters WHERE key = ?1", params![key])?;
+        insert_cancellation(&tx, key, cancellation, true)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn read_cancellation(&self, key: &str) -> anyhow::Result<Option<Cancellation>> {
+        let row: Option<(String, String, i64)> = self
+            .connection()?
+            .query_row(
+                "SELECT kind, reason, time FROM cancellations WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        row.map(|(kind, reason, time)| {
+            Ok(Cancellation {
+                kind: from_str(&kind)?,
+                reason,
+                time,
+            })
+        })
+        .transpose()
+    }
+
+    fn list_cancellations(&self) -> anyhow::Result<Vec<(String, Cancellation)>> {
+        let conn = self.connection()?;
+        let mut stmt =
+            conn.prepare("SELECT key, kind, reason, time FROM cancellations ORDER BY key")?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (key, kind, reason, time) = row?;
+            out.push((key, Cancellation {
+                kind: from_str(&kind)?,
+                reason,
+                time,
+            }));
+        }
+        Ok(out)
+    }
+
+    fn write_status_list(&self, list: &Value) -> anyhow::Result<()> {
+        self.write_value("status_list", STATUS_LIST_KEY, list)
+    }
+
+    fn read_status_list(&self) -> anyhow::Result<Option<Value>> {
+        let contents: Option<String> = self
+            .connection()?
+            .query_row(
+                "SELECT value FROM status_list WHERE key = ?1",
+                params![STATUS_LIST_KEY],
+                |row| row.get(0),
+            )
+            .optional()?;
+        contents
+            .map(|contents| Ok(from_str(&contents)?))
+            .transpose()
+    }
+}
+
+/// The entry with its attempts reset so that it is processed at the given time.
+fn redriven(entry: PendingEntry, time: i64) -> PendingEntry {
+    PendingEntry {
+        attempts: 0,
+        next_attempt: time,
+        ..entry
+    }
+}
+
+/// A mockup of a database to store all the data.
+/// Here we store everything as files on disk and synchronize access to disk
+/// via a lock. On deletion files are moved into a 'backup_root' folder.
+#[derive(Clone)]
+pub struct FileStorage {
+    /// Root directory where all the data is stored.
+    root:        PathBuf,
+    /// Root of the backup directory where we store "deleted" files.
+    backup_root: PathBuf,
+    /// And a hashmap of pending entries. Pending entries are also stored in the
+    /// filesystem, but we cache them here since they have to be accessed
+    /// often. We put it behind a mutex to sync all accesses, to the hashmap
+    /// as well as to the filesystem, which is implicit.
+    pending:     Arc<Mutex<HashMap<String, PendingEntry>>>,
+}
+
+impl FileStorage {
+    /// Create a new database using the given root and backup_root paths.
+    /// The 'backup_root' path is used to place deleted entries.
+    ///
+    /// This function will attempt to reconstruct the in-memory pending table if
+    /// it finds any pending entries.
+    pub fn new(root: PathBuf, backup_root: PathBuf) -> anyhow::Result<Self> {
+        for dir in &[
+            "revocation",
+            "identity",
+            "pending",
+            "requests",
+            "dead-letter",
+            "cancelled",
+        ] {
+            fs::create_dir_all(root.join(dir))?;
+        }
+        fs::create_dir_all(backup_root.join("revocation"))?;
+        fs::create_dir_all(backup_root.join("identity"))?;
+        let pending = read_dir_entries(&root.join("pending"))?
+            .into_iter()
+            .map(|(key, contents)| Ok((key, from_str::<PendingEntry>(&contents)?)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        Ok(Self {
+            root,
+            backup_root,
+            pending: Arc::new(Mutex::new(pending)),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, PendingEntry>> {
+        self.pending
+            .lock()
+            .expect("Cannot acquire a lock, which means something is very wrong.")
+    }
+
+    fn write_file(&self, dir: &str, key: &str, value: &Value) -> anyhow::Result<()> {
+        let _lock = self.lock();
+        let file = fs::File::create(self.root.join(dir).join(key))?;
+        serde_json::to_writer(file, value)?;
+        Ok(())
+    }
+
+    /// Whether the identity was cancelled. This must be called with the lock
+    /// held.
+    fn is_cancelled(&self, key: &str) -> bool { self.root.join("cancelled").join(key).exists() }
+
+    /// Write the pending entry unless the identity was cancelled. This must be
+    /// called with the lock held.
+    fn write_pending_locked(
+        &self,
+        pending: &mut HashMap<String, PendingEntry>,
+        key: &str,
+        entry: &PendingEntry,
+    ) -> anyhow::Result<bool> {
+        if self.is_cancelled(key) {
+            return Ok(false);
+        }
+        let file = fs::File::create(self.root.join("pending").join(key))?;
+        serde_json::to_writer(file, entry)?;
+        pending.insert(key.to_string(), entry.clone());
+        Ok(true)
+    }
+
+    fn read_file(&self, dir: &str, key: &str) -> anyhow::Result<Value> {
+        // ensure the key is valid base16 characters, which also ensures we are only
+        // reading in the subdirectory.
+        if hex::decode(key).is_err() {
+            bail!("Invalid key.")
+        }
+        let contents = {
+            let _lock = self.lock();
+            fs::read_to_string(self.root.join(dir).join(key))?
+        }; // drop the lock at this point
+           // It is more efficient to read the whole thing, and then deserialize
+        Ok(from_str(&contents)?)
+    }
+
+    fn list_files(&self, dir: &str) -> anyhow::Result<Vec<String>> {
+        let _lock = self.lock();
+        let mut keys = read_dir_keys(&self.root.join(dir))?;
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+impl Storage for FileStorage {
+    fn write_request_record(&self, key: &str, request: &Value) -> anyhow::Result<()> {
+        self.write_file("requests", key, request)
+    }
+
+    fn read_request_record(&self, key: &str) -> anyhow::Result<Value> {
+        self.read_file("requests", key)
+    }
+
+    fn list_request_keys(&self) -> anyhow::Result<Vec<String>> { self.list_files("requests") }
+
+    fn write_revocation_record(&self, key: &str, record: &Value) -> anyhow::Result<()> {
+        self.write_file("revocation", key, record)
+    }
+
+    fn read_revocation_record(&self, key: &str) -> anyhow::Result<Value> {
+        self.read_file("revocation", key)
+    }
+
+    fn write_identity_object(&self, key: &str, obj: &Value) -> anyhow::Result<()> {
+        self.write_file("identity", key, obj)
+    }
+
+    fn write_identity_object_with_pending(
+        &self,
+        key: &str,
+        obj: &Value,
+        entry: &PendingEntry,
+    ) -> anyhow::Result<bool> {
+        let mut lock = self.lock();
+        // The files cannot be written atomically. The pending entry is written
+        // first, so that the identity object is never reported as done before
+        // its initial account is submitted.
+        if !self.write_pending_locked(&mut lock, key, entry)? {
+            return Ok(false);
+        }
+        let file = fs::File::create(self.root.join("identity").join(key))?;
+        serde_json::to_writer(file, obj)?;
+        Ok(true)
+    }
+
+    fn replace_identity_object(
+        &self,
+        key: &str,
+        previous: &Value,
+        obj: &Value,
+    ) -> anyhow::Result<bool> {
+        if hex::decode(key).is_err() {
+            bail!("Invalid key.")
+        }
+        let _lock = self.lock();
+        let path = self.root.join("identity").join(key);
+        if self.is_cancelled(key) || !path.exists() {
+            return Ok(false);
+        }
+        if from_str::<Value>(&fs::read_to_string(&path)?)? != *previous {
+            return Ok(false);
+        }
+        let file = fs::File::create(path)?;
+        serde_json::to_writer(file, obj)?;
+        Ok(true)
+    }
+
+    fn read_identity_object(&self, key: &str) -> anyhow::Result<Value> {
+        self.read_file("identity", key)
+    }
+
+    fn list_identity_object_keys(&self) -> anyhow::Result<Vec<String>> {
+        self.list_files("identity")
+    }
+
+    fn write_pending(&self, key: &str, entry: &PendingEntry) -> anyhow::Result<()> {
+        let mut lock = self.lock();
+        self.write_pending_locked(&mut lock, key, entry)?;
+        Ok(())
+    }
+
+    fn read_pending(&self, key: &str) -> anyhow::Result<Option<PendingEntry>> {
+        Ok(self.lock().get(key).cloned())
+    }
+
+    fn list_pending(&self) -> anyhow::Result<Vec<(String, PendingEntry)>> {
+        let mut entries: Vec<_> = self
+            .lock()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(entries)
+    }
+
+    fn mark_finalized(&self, key: &str) -> anyhow::Result<()> {
+        let mut lock = self.lock();
+        lock.remove(key);
+        fs::remove_file(self.root.join("pending").join(key))?;
+        Ok(())
+    }
+
+    fn delete_all(&self, key: &str) -> anyhow::Result<()> {
+        let mut lock = self.lock();
+        for dir in &["revocation", "identity"] {
+            let path = self.root.join(dir).join(key);
+            if path.exists() {
+                fs::rename(path, self.backup_root.join(dir).join(key))?;
+            }
+        }
+        let pending_path = self.root.join("pending").join(key);
+        if pending_path.exists() {
+            fs::remove_file(pending_path)?;
+        }
+        lock.remove(key);
+        Ok(())
+    }
+
+    fn dead_letter(&self, key: &str, letter: &DeadLetter) -> anyhow::Result<()> {
+        let mut lock = self.lock();
+        if self.is_cancelled(key) {
+            return Ok(());
+        }
+        let file = fs::File::create(self.root.join("dead-letter").join(key))?;
+        serde_json::to_writer(file, letter)?;
+        let pending_path = self.root.join("pending").join(key);
+        if pending_path.exists() {
+            fs::remove_file(pending_path)?;
+        }
+        lock.remove(key);
+        Ok(())
+    }
+
+    fn read_dead_letter(&self, key: &str) -> anyhow::Result<Option<DeadLetter>> {
+        if hex::decode(key).is_err() {
+            bail!("Invalid key.")
+        }
+        let path = self.root.join("dead-letter").join(key);
+        let _lock = self.lock();
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(from_str(&fs::read_to_string(path)?)?))
+    }
+
+    fn list_dead_letters(&self) -> anyhow::Result<Vec<(String, DeadLetter)>> {
+        let _lock = self.lock();
+        let mut entries = read_dir_entries(&self.root.join("dead-letter"))?
+            .into_iter()
+            .map(|(key, contents)| Ok((key, from_str(&contents)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(entries)
+    }
+
+    fn redrive(&self, key: &str, time: i64) -> anyhow::Result<bool> {
+        if hex::decode(key).is_err() {
+            bail!("Invalid key.")
+        }
+        let mut lock = self.lock();
+        let letter_path = self.root.join("dead-letter").join(key);
+        let entry = if letter_path.exists() {
+            let letter: DeadLetter = from_str(&fs::read_to_string(&letter_path)?)?;
+            Some(letter.entry)
+        } else {
+            lock.get(key).cloned()
+        };
+        match entry {
+            Some(entry) => {
+                let entry = redriven(entry, time);
+                let file = fs::File::create(self.root.join("pending").join(key))?;
+                serde_json::to_writer(file, &entry)?;
+                if letter_path.exists() {
+                    fs::remove_file(letter_path)?;
+                }
+                lock.insert(key.to_string(), entry);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn cancel(&self, key: &str, cancellation: &Cancellation) -> anyhow::Result<()> {
+        if hex::decode(key).is_err() {
+            bail!("Invalid key.")
+        }
+        let mut lock = self.lock();
+        let file = fs::File::create(self.root.join("cancelled").join(key))?;
+        serde_json::to_writer(file, cancellation)?;
+        for dir in &["pending", "dead-letter"] {
+            let path = self.root.join(dir).join(key);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        lock.remove(key);
+        Ok(())
+    }
+
+    fn read_cancellation(&self, key: &str) -> anyhow::Result<Option<Cancellation>> {
+        if hex::decode(key).is_err() {
+            bail!("Invalid key.")
+        }
+        let path = self.root.join("cancelled").join(key);
+        let _lock = self.lock();
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(from_str(&fs::read_to_string(path)?)?))
+    }
+
+    fn list_cancellations(&self) -> anyhow::Result<Vec<(String, Cancellation)>> {
+        let _lock = self.lock();
+        let mut entries = read_dir_entries(&self.root.join("cancelled"))?
+            .into_iter()
+            .map(|(key, contents)| Ok((key, from_str(&contents)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(entries)
+    }
+
+    fn write_status_list(&self, list: &Value) -> anyhow::Result<()> {
+        let _lock = self.lock();
+        let file = fs::File::create(self.root.join("status-list.json"))?;
+        serde_json::to_writer(file, list)?;
+        Ok(())
+    }
+
+    fn read_status_list(&self) -> anyhow::Result<Option<Value>> {
+        let path = self.root.join("status-list.json");
+        let _lock = self.lock();
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(from_str(&fs::read_to_string(path)?)?))
+    }
+}
+
+/// The names of all files in the directory. A missing directory has no
+/// entries.
+fn read_dir_keys(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut out = Vec::new();
+    if !dir.exists() {
+        return Ok(out);
+    }
+    for file in fs::read_dir(dir)?.flatten() {
+        if file.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+            match file.file_name().into_string() {
+                Ok(name) => out.push(name),
+                Err(name) => bail!("Invalid file name {:?} in {}.", name, dir.display()),
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Read all files in the directory, returning their names and contents.
+/// A missing directory has no entries.
+fn read_dir_entries(dir: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let mut out = Vec::new();
+    if !dir.exists() {
+        return Ok(out);
+    }
+    for file in fs::read_dir(dir)?.flatten() {
+        if file.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+            let name = match file.file_name().into_string() {
+                Ok(name) => name,
+                Err(name) => bail!("Invalid file name {:?} in {}.", name, dir.display()),
+            };
+            out.push((name, fs::read_to_string(file.path())?));
+        }
+    }
+    Ok(out)
+}
+
+/// The number of entries of each kind imported by [import_file_database].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub requests:                   usize,
+    pub revocation_records:         usize,
+    pub identity_objects:           usize,
+    pub pending:                    usize,
+    pub deleted_revocation_records: usize,
+    pub deleted_identity_objects:   usize,
+    pub dead_letters:               usize,
+    pub cancellations:              usize,
+}
+
+/// Import the data written by [FileStorage] with the given root and backup
+/// root into the SQLite database. The import happens in a single transaction,
+/// so either all or none of the data is imported. Entries that already exist
+/// in the database are not overwritten, so it is safe to run the import
+/// again, e.g., if it was interrupted.
+pub fn import_file_database(
+    root: &Path,
+    backup_root: &Path,
+    target: &SqliteStorage,
+) -> anyhow::Result<ImportSummary> {
+    let mut conn = target.connection()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let import = |dir: &Path, table: &str| -> anyhow::Result<usize> {
+        let mut count = 0;
+        for (key, contents) in read_dir_entries(dir)? {
+            // Check that the contents are valid JSON, and normalize them.
+            let value: Value = from_str(&contents)
+                .with_context(|| format!("Invalid entry {} in {}.", key, dir.display()))?;
+            count += tx.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO {} (key, value) VALUES (?1, ?2)",
+                    table
+                ),
+                params![key, value.to_string()],
+            )?;
+        }
+        Ok(count)
+    };
+    let mut summary = ImportSummary {
+        requests:                   import(&root.join("requests"), "requests")?,
+        revocation_records:         import(&root.join("revocation"), "revocation_records")?,
+        identity_objects:           import(&root.join("identity"), "identity_objects")?,
+        deleted_revocation_records: import(
+            &backup_root.join("revocation"),
+            "deleted_revocation_records",
+        )?,
+        deleted_identity_objects:   import(
+            &backup_root.join("identity"),
+            "deleted_identity_objects",
+        )?,
+        pending:                    0,
+        dead_letters:               0,
+        cancellations:              0,
+    };
+    for (key, contents) in read_dir_entries(&root.join("pending"))? {
+        let entry: PendingEntry =
+            from_str(&contents).with_context(|| format!("Invalid pending entry {}.", key))?;
+        summary.pending += insert_pending(&tx, &key, &entry, false)?;
+    }
+    for (key, contents) in read_dir_entries(&root.join("dead-letter"))? {
+        let letter: DeadLetter =
+            from_str(&contents).with_context(|| format!("Invalid dead letter {}.", key))?;
+        summary.dead_letters += tx.execute(
+            "INSERT OR IGNORE INTO dead_letters (key, entry, reason, time) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                key,
+                serde_json::to_string(&letter.entry)?,
+                letter.reason,
+                letter.time
+            ],
+        )?;
+    }
+    for (key, contents) in read_dir_entries(&root.join("cancelled"))? {
+        let cancellation: Cancellation =
+            from_str(&contents).with_context(|| format!("Invalid cancellation {}.", key))?;
+        summary.cancellations += insert_cancellation(&tx, &key, &cancellation, false)?;
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A fresh directory for a test.
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ip-storage-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).expect("Cannot create test directory.");
+        dir
+    }
+
+    fn pending_entry(n: u64) -> PendingEntry {
+        PendingEntry::new(
+            PendingStatus::Submitted {
+                submission_id: format!("submission-{}", n),
+                status:        SubmissionStatus::Received,
+            },
+            json!({ "credential": n }),
+        )
+    }
+
+    /// Run the same checks on both backends.
+    fn check_storage(storage: &dyn Storage) {
+        let key = "aabb";
+        assert!(storage.read_request_record(key).is_err());
+        storage
+            .write_request_record(key, &json!({"request": 1}))
+            .unwrap();
+        assert_eq!(
+            storage.read_request_record(key).unwrap(),
+            json!({"request": 1})
+        );
+        storage
+            .write_revocation_record(key, &json!({"record": 1}))
+            .unwrap();
+        storage
+            .write_identity_object(key, &json!({"identityObject": 1}))
+            .unwrap();
+        assert_eq!(
+            storage.read_identity_object(key).unwrap(),
+            json!({"identityObject": 1})
+        );
+
+        assert!(!storage.is_pending(key).unwrap());
+        storage.write_pending(key, &pending_entry(1)).unwrap();
+        storage.write_pending("ccdd", &pending_entry(2)).unwrap();
+        assert_eq!(storage.read_pending(key).unwrap(), Some(pending_entry(1)));
+        assert_eq!(storage.list_pending().unwrap(), vec![
+            (key.to_string(), pending_entry(1)),
+            ("ccdd".to_string(), pending_entry(2))
+        ]);
+        storage.mark_finalized("ccdd").unwrap();
+        assert!(!storage.is_pending("ccdd").unwrap());
+
+        storage.delete_all(key).unwrap();
+        assert!(!storage.is_pending(key).unwrap());
+        assert!(storage.read_identity_object(key).is_err());
+        assert!(storage.list_pending().unwrap().is_empty());
+
+        let failed = PendingEntry {
+            attempts: 5,
+            next_attempt: 100,
+            ..pending_entry(3)
+        };
+        storage.write_pending("eeff", &failed).unwrap();
+        let letter = DeadLetter {
+            entry:  failed.clone(),
+            reason: "Too many attempts.".into(),
+            time:   100,
+        };
+        storage.dead_letter("eeff", &letter).unwrap();
+        assert!(!storage.is_pending("eeff").unwrap());
+        assert_eq!(
+            storage.read_dead_letter("eeff").unwrap(),
+            Some(letter.clone())
+        );
+        assert_eq!(storage.list_dead_letters().unwrap(), vec![(
+            "eeff".to_string(),
+            letter
+        )]);
+        assert!(storage.redrive("eeff", 200).unwrap());
+        assert_eq!(storage.read_dead_letter("eeff").unwrap(), None);
+        assert_eq!(
+            storage.read_pending("eeff").unwrap(),
+            Some(PendingEntry {
+                attempts: 0,
+                next_attempt: 200,
+                ..failed
+            })
+        );
+        assert!(!storage.redrive("0000", 200).unwrap());
+
+        assert_eq!(storage.list_request_keys().unwrap(), vec![key.to_string()]);
+        assert!(storage.list_identity_object_keys().unwrap().is_empty());
+        storage
+            .write_revocation_record("eeff", &json!({"record": 2}))
+            .unwrap();
+        assert_eq!(
+            storage.read_revocation_record("eeff").unwrap(),
+            json!({"record": 2})
+        );
+        storage
+            .write_identity_object("eeff", &json!({"identityObject": 2}))
+            .unwrap();
+        assert_eq!(storage.list_identity_object_keys().unwrap(), vec![
+            "eeff".to_string()
+        ]);
+        assert_eq!(storage.read_cancellation("eeff").unwrap(), None);
+        storage
+            .dead_letter("eeff", &DeadLetter {
+                entry:  pending_entry(3),
+                reason: "Rejected.".into(),
+                time:   250,
+            })
+            .unwrap();
+        let cancellation = Cancellation {
+            kind:   CancellationKind::Revoked,
+            reason: "Fraudulent documents.".into(),
+            time:   300,
+        };
+        storage.cancel("eeff", &cancellation).unwrap();
+        assert!(!storage.is_pending("eeff").unwrap());
+        assert_eq!(storage.read_dead_letter("eeff").unwrap(), None);
+        assert_eq!(
+            storage.read_cancellation("eeff").unwrap(),
+            Some(cancellation.clone())
+        );
+        assert_eq!(storage.list_cancellations().unwrap(), vec![(
+            "eeff".to_string(),
+            cancellation
+        )]);
+        // The identity object and the revocation record are kept.
+        assert!(storage.read_identity_object("eeff").is_ok());
+        assert!(storage.read_revocation_record("eeff").is_ok());
+        // A submission in progress cannot bring back the entry.
+        storage.write_pending("eeff", &pending_entry(3)).unwrap();
+        assert!(!storage.is_pending("eeff").unwrap());
+        storage
+            .dead_letter("eeff", &DeadLetter {
+                entry:  pending_entry(3),
+                reason: "Rejected.".into(),
+                time:   350,
+            })
+            .unwrap();
+        assert_eq!(storage.read_dead_letter("eeff").unwrap(), None);
+        assert!(!storage
+            .write_identity_object_with_pending(
+                "eeff",
+                &json!({"identityObject": 4}),
+                &pending_entry(3)
+            )
+            .unwrap());
+        assert!(!storage.is_pending("eeff").unwrap());
+        assert_eq!(
+            storage.read_identity_object("eeff").unwrap(),
+            json!({"identityObject": 2})
+        );
+        // Nor can a cancelled identity be renewed.
+        assert!(!storage
+            .replace_identity_object(
+                "eeff",
+                &json!({"identityObject": 2}),
+                &json!({"identityObject": 5})
+            )
+            .unwrap());
+        assert_eq!(
+            storage.read_identity_object("eeff").unwrap(),
+            json!({"identityObject": 2})
+        );
+
+        assert_eq!(storage.read_status_list().unwrap(), None);
+        storage.write_status_list(&json!({"version": 1})).unwrap();
+        storage.write_status_list(&json!({"version": 2})).unwrap();
+        assert_eq!(
+            storage.read_status_list().unwrap(),
+            Some(json!({"version": 2}))
+        );
+
+        assert!(storage
+            .write_identity_object_with_pending(
+                "0011",
+                &json!({"identityObject": 3}),
+                &pending_entry(4),
+            )
+            .unwrap());
+        assert_eq!(
+            storage.read_identity_object("0011").unwrap(),
+            json!({"identityObject": 3})
+        );
+        assert_eq!(
+            storage.read_pending("0011").unwrap(),
+            Some(pending_entry(4))
+        );
+        // The identity object is only replaced if it has not changed since it
+        // was read.
+        assert!(storage
+            .replace_identity_object(
+                "0011",
+                &json!({"identityObject": 3}),
+                &json!({"identityObject": 6})
+            )
+            .unwrap());
+        assert!(!storage
+            .replace_identity_object(
+                "0011",
+                &json!({"identityObject": 3}),
+                &json!({"identityObject": 7})
+            )
+            .unwrap());
+        assert_eq!(
+            storage.read_identity_object("0011").unwrap(),
+            json!({"identityObject": 6})
+        );
+        assert!(!storage
+            .replace_identity_object("2233", &json!(null), &json!({"identityObject": 8}))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_storage() {
+        let dir = test_dir();
+        let storage = SqliteStorage::open(dir.join("db.sqlite")).unwrap();
+        check_storage(&storage);
+        // deleted entries are kept
+        let conn = storage.connection().unwrap();
+        let deleted: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM deleted_identity_objects",
+                params![],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(deleted, 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_storage() {
+        let dir = test_dir();
+        let storage = FileStorage::new(dir.join("database"), dir.join("database-deleted")).unwrap();
+        check_storage(&storage);
+        assert!(dir.join("database-deleted/identity/aabb").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_pending_survives_restart() {
+        let dir = test_dir();
+        let path = dir.join("db.sqlite");
+        {
+            let storage = SqliteStorage::open(&path).unwrap();
+            storage.write_pending("aa", &pending_entry(1)).unwrap();
+            storage
+                .write_pending("bb", &PendingEntry {
+                    attempts: 3,
+                    next_attempt: 1000,
+                    last_error: Some("Unreachable.".into()),
+                    ..PendingEntry::new(PendingStatus::CouldNotSubmit, json!({"credential": 2}))
+                })
+                .unwrap();
+        }
+        let storage = SqliteStorage::open(&path).unwrap();
+        let pending = storage.list_pending().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0], ("aa".to_string(), pending_entry(1)));
+        assert_eq!(pending[1].1.status, PendingStatus::CouldNotSubmit);
+        assert_eq!(pending[1].1.attempts, 3);
+        assert_eq!(pending[1].1.next_attempt, 1000);
+        assert_eq!(pending[1].1.last_error.as_deref(), Some("Unreachable."));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_migrations() {
+        let dir = test_dir();
+        let path = dir.join("db.sqlite");
+        let entry = PendingEntry::new(PendingStatus::CouldNotSubmit, json!({"credential": 1}));
+        {
+            // A database of the first version, without a schema version.
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(INITIAL_SCHEMA).unwrap();
+            conn.execute(
+                "INSERT INTO pending (key, status, value) VALUES (?1, ?2, ?3)",
+                params![
+                    "aa",
+                    serde_json::to_string(&entry.status).unwrap(),
+                    entry.value.to_string()
+                ],
+            )
+            .unwrap();
+        }
+        let storage = SqliteStorage::open(&path).unwrap();
+        assert_eq!(storage.read_pending("aa").unwrap(), Some(entry));
+        storage.write_pending("bb", &pending_entry(2)).unwrap();
+        storage
+            .cancel("bb", &Cancellation {
+                kind:   CancellationKind::Rejected,
+                reason: "Test.".into(),
+                time:   1,
+            })
+            .unwrap();
+        assert!(!storage.is_pending("bb").unwrap());
+        storage.write_status_list(&json!({"list": 1})).unwrap();
+        // Opening again applies nothing.
+        SqliteStorage::open(&path).unwrap();
+        let version: u32 = Connection::open(&path)
+            .unwrap()
+            .query_row("PRAGMA user_version", params![], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+
+        // A database that already has the columns of later migrations, but no
+        // schema version.
+        let path = dir.join("unversioned.sqlite");
+        {
+            let conn = Connection::open(&path).unwrap();
+            migrate_initial(&conn).unwrap();
+            migrate_retries(&conn).unwrap();
+        }
+        let storage = SqliteStorage::open(&path).unwrap();
+        storage.write_pending("aa", &pending_entry(1)).unwrap();
+        assert_eq!(storage.read_pending("aa").unwrap(), Some(pending_entry(1)));
+
+        // A database of a later version of the service is not opened.
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(&format!("PRAGMA user_version = {}", MIGRATIONS.len() + 1))
+            .unwrap();
+        assert!(SqliteStorage::open(&path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_concurrent_writes() {
+        let dir = test_dir();
+        let storage = Arc::new(SqliteStorage::open(dir.join("db.sqlite")).unwrap());
+        let handles: Vec<_> = (0..8u64)
+            .map(|t| {
+                let storage = Arc::clone(&storage);
+                std::thread::spawn(move || {
+                    for i in 0..20u64 {
+                        let key = format!("{:02x}{:02x}", t, i);
+                        storage
+                            .write_request_record(&key, &json!({ "n": i }))
+                            .unwrap();
+                        storage.write_pending(&key, &pending_entry(i)).unwrap();
+                        assert_eq!(storage.read_pending(&key).unwrap(), Some(pending_entry(i)));
+                        if i % 2 == 0 {
+                            storage.mark_finalized(&key).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().expect("Thread should not panic.");
+        }
+        assert_eq!(storage.list_pending().unwrap().len(), 8 * 10);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_file_database() {
+        let dir = test_dir();
+        let root = dir.join("database");
+        let backup_root = dir.join("database-deleted");
+        {
+            let files = FileStorage::new(root.clone(), backup_root.clone()).unwrap();
+            for key in &["01", "02", "03"] {
+                files
+                    .write_request_record(key, &json!({ "request": key }))
+                    .unwrap();
+                files
+                    .write_revocation_record(key, &json!({ "record": key }))
+                    .unwrap();
+                files
+                    .write_identity_object(key, &json!({ "identityObject": key }))
+                    .unwrap();
+                files.write_pending(key, &pending_entry(1)).unwrap();
+            }
+            files.mark_finalized("01").unwrap();
+            files.delete_all("02").unwrap();
+            files
+                .cancel("01", &Cancellation {
+                    kind:   CancellationKind::Revoked,
+                    reason: "Fraud.".into(),
+                    time:   10,
+                })
+                .unwrap();
+        }
+        let storage = SqliteStorage::open(dir.join("db.sqlite")).unwrap();
+        let summary = import_file_database(&root, &backup_root, &storage).unwrap();
+        assert_eq!(summary, ImportSummary {
+            requests:                   3,
+            revocation_records:         2,
+            identity_objects:           2,
+            pending:                    1,
+            deleted_revocation_records: 1,
+            deleted_identity_objects:   1,
+            dead_letters:               0,
+            cancellations:              1,
+        });
+        assert_eq!(
+            storage.read_identity_object("01").unwrap(),
+            json!({"identityObject": "01"})
+        );
+        assert!(storage.read_identity_object("02").is_err());
+        assert_eq!(
+            storage.read_request_record("02").unwrap(),
+            json!({"request": "02"})
+        );
+        assert_eq!(storage.list_pending().unwrap(), vec![(
+            "03".to_string(),
+            pending_entry(1)
+        )]);
+        // Importing again does not duplicate or overwrite anything.
+        let again = import_file_database(&root, &backup_root, &storage).unwrap();
+        assert_eq!(again, ImportSummary::default());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
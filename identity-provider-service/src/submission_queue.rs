@@ -0,0 +1,744 @@
+//! A durable queue of initial account creation transactions.
+//!
+//! When an identity object is created the identity provider submits the
+//! initial account creation transaction to the wallet-proxy. The submission is
+//! stored as a [PendingEntry], together with the identity object, before it is
+//! submitted, and followed up on until the transaction is finalized. Failures,
+//! e.g., the wallet-proxy being unreachable, or the transaction going missing,
+//! are retried with exponential backoff as given by the [RetryPolicy]. An entry
+//! that is rejected by the wallet-proxy, or that has failed too many times, is
+//! moved to the dead letters, from where it can be re-driven via
+//! [SubmissionQueue::redrive].
+//!
+//! Since the queue is kept in the [Storage] it survives restarts of the
+//! service.
+use crate::{
+    metrics::Metrics,
+    storage::{DeadLetter, PendingEntry, PendingStatus, Storage, SubmissionStatus},
+};
+use log::{error, info, warn};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use url::Url;
+
+/// When to retry failed submissions, and when to give up on them.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Delay before the first retry. This is also the delay between queries of
+    /// the status of a submitted transaction.
+    pub initial_delay: Duration,
+    /// The delay doubles with each failed attempt, up to this value.
+    pub max_delay:     Duration,
+    /// The number of failed attempts after which the entry is moved to the
+    /// dead letters.
+    pub max_attempts:  u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(5),
+            max_delay:     Duration::from_secs(600),
+            max_attempts:  10,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before the next attempt after the given number of failed
+    /// attempts.
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempts.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        match self.initial_delay.checked_mul(factor) {
+            Some(delay) if delay < self.max_delay => delay,
+            _ => self.max_delay,
+        }
+    }
+}
+
+#[derive(SerdeDeserialize, SerdeSerialize)]
+#[serde(rename_all = "camelCase")]
+/// Successful response from the wallet proxy.
+/// It contains a JSON body with a single field `submissionId`.
+struct InitialAccountReponse {
+    submission_id: String,
+}
+
+#[derive(SerdeSerialize, SerdeDeserialize)]
+#[serde(rename_all = "camelCase")]
+/// The part of the response we care about. Since the transaction
+/// will either be in a block, or not, and if it is, then the account will have
+/// been created.
+struct SubmissionStatusResponse {
+    status: SubmissionStatus,
+}
+
+/// The result of a submission to the wallet-proxy.
+enum SubmitOutcome {
+    /// The transaction was submitted with the given submission id.
+    Submitted(String),
+    /// The submission failed due to reasons unrelated to the transaction
+    /// itself, e.g., we could not reach the server. It should be retried.
+    Failed(String),
+    /// The transaction was rejected by the wallet-proxy.
+    Rejected(String),
+}
+
+/// The result of submitting a new entry of the queue.
+#[derive(Debug, PartialEq)]
+pub enum Enqueued {
+    /// The transaction was submitted.
+    Submitted,
+    /// The transaction could not be submitted yet and will be retried.
+    Deferred,
+    /// The transaction was rejected by the wallet-proxy. The entry is moved
+    /// to the dead letters.
+    Rejected(String),
+}
+
+/// Current time in seconds since the unix epoch.
+pub fn now() -> i64 { chrono::Utc::now().timestamp() }
+
+fn after(now: i64, delay: Duration) -> i64 { now + delay.as_secs() as i64 }
+
+/// The queue of submissions of initial account creation transactions.
+pub struct SubmissionQueue {
+    storage:        Arc<dyn Storage>,
+    client:         Client,
+    /// The URL where transactions are submitted. Its base is also used to
+    /// query the status of submissions.
+    submission_url: Url,
+    policy:         RetryPolicy,
+    /// Keys of the entries that are currently being processed, so that the
+    /// background task and concurrent requests do not submit the same
+    /// transaction twice.
+    in_flight:      Mutex<HashSet<String>>,
+    /// Where to record the outcomes of submissions and the durations of the
+    /// calls to the wallet-proxy, if anywhere.
+    metrics:        Option<Metrics>,
+}
+
+/// Marks a key as being processed, and removes the mark when dropped.
+struct InFlight<'a> {
+    queue: &'a SubmissionQueue,
+    key:   String,
+}
+
+impl<'a> Drop for InFlight<'a> {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.queue.in_flight.lock() {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+impl SubmissionQueue {
+    pub fn new(
+        storage: Arc<dyn Storage>,
+        client: Client,
+        submission_url: Url,
+        policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            storage,
+            client,
+            submission_url,
+            policy,
+            in_flight: Mutex::new(HashSet::new()),
+            metrics: None,
+        }
+    }
+
+    /// Record the outcomes of submissions and the durations of the calls to the
+    /// wallet-proxy in the given metrics.
+    pub fn with_metrics(self, metrics: Metrics) -> Self {
+        Self {
+            metrics: Some(metrics),
+            ..self
+        }
+    }
+
+    fn count(&self, outcome: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.submissions.with_label_values(&[outcome]).inc();
+        }
+    }
+
+    fn observe(&self, operation: &str, start: Instant) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .wallet_proxy_duration
+                .with_label_values(&[operation])
+                .observe(start.elapsed().as_secs_f64());
+        }
+    }
+
+    /// Run the given operations on the storage on the thread pool for blocking
+    /// tasks, so that they do not hold up the tasks of the runtime.
+    async fn storage<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&dyn Storage) -> anyhow::Result<R> + Send + 'static,
+    ) -> anyhow::Result<R> {
+        let storage = Arc::clone(&self.storage);
+        tokio::task::spawn_blocking(move || f(storage.as_ref())).await?
+    }
+
+    fn start(&self, key: &str) -> Option<InFlight<'_>> {
+        let mut in_flight = self
+            .in_flight
+            .lock()
+            .expect("Cannot acquire a lock, which means something is very wrong.");
+        if in_flight.insert(key.to_string()) {
+            Some(InFlight {
+                queue: self,
+                key:   key.to_string(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The entry of a new submission. It must be stored, e.g., with
+    /// [Storage::write_identity_object_with_pending], before it is submitted
+    /// with [SubmissionQueue::enqueue]. It is not due before the initial
+    /// delay, so the queue only submits it if `enqueue` did not record an
+    /// outcome by then.
+    pub fn new_entry(&self, submission: Value, now: i64) -> PendingEntry {
+        let mut entry = PendingEntry::new(PendingStatus::CouldNotSubmit, submission);
+        entry.next_attempt = after(now, self.policy.initial_delay);
+        entry
+    }
+
+    /// Submit the initial account creation transaction of the stored entry,
+    /// and record the outcome. If the entry is already being processed the
+    /// submission is left to that, and the entry reported as deferred.
+    pub async fn enqueue(
+        &self,
+        key: &str,
+        mut entry: PendingEntry,
+        now: i64,
+    ) -> anyhow::Result<Enqueued> {
+        let _guard = match self.start(key) {
+            Some(guard) => guard,
+            None => {
+                info!(
+                    "The account creation transaction {} is already being processed.",
+                    key
+                );
+                return Ok(Enqueued::Deferred);
+            }
+        };
+        match self.submit(&entry.value).await {
+            SubmitOutcome::Submitted(submission_id) => {
+                entry.status = PendingStatus::Submitted {
+                    submission_id,
+                    status: SubmissionStatus::Received,
+                };
+                self.succeeded(key, entry, now).await?;
+                Ok(Enqueued::Submitted)
+            }
+            SubmitOutcome::Failed(e) => {
+                self.failed(key, entry, e, now).await?;
+                Ok(Enqueued::Deferred)
+            }
+            SubmitOutcome::Rejected(e) => {
+                self.give_up(key, entry, e.clone(), now).await?;
+                Ok(Enqueued::Rejected(e))
+            }
+        }
+    }
+
+    /// Process the entry with the given key if it is due, i.e., submit the
+    /// transaction if it could not be submitted before, or query its status
+    /// otherwise.
+    pub async fn process(&self, key: &str, now: i64) -> anyhow::Result<()> {
+        let _guard = match self.start(key) {
+            Some(guard) => guard,
+            // Somebody else is processing the entry at the moment.
+            None => return Ok(()),
+        };
+        let owned_key = key.to_string();
+        let mut entry = match self
+            .storage(move |storage| storage.read_pending(&owned_key))
+            .await?
+        {
+            Some(entry) if entry.next_attempt <= now => entry,
+            _ => return Ok(()),
+        };
+        match entry.status.clone() {
+            PendingStatus::CouldNotSubmit => match self.submit(&entry.value).await {
+                SubmitOutcome::Submitted(submission_id) => {
+                    entry.status = PendingStatus::Submitted {
+                        submission_id,
+                        status: SubmissionStatus::Received,
+                    };
+                    self.succeeded(key, entry, now).await
+                }
+                SubmitOutcome::Failed(e) => self.failed(key, entry, e, now).await,
+                SubmitOutcome::Rejected(e) => {
+                    warn!("Account creation transaction {} rejected: {}", key, e);
+                    self.give_up(key, entry, e, now).await
+                }
+            },
+            PendingStatus::Submitted { submission_id, .. } => {
+                match self.query_status(&submission_id).await {
+                    Ok(SubmissionStatus::Finalized) => {
+                        info!("Account creation transaction {} finalized.", key);
+                        self.count("finalized");
+                        let key = key.to_string();
+                        self.storage(move |storage| storage.mark_finalized(&key))
+                            .await
+                    }
+                    Ok(SubmissionStatus::Absent) => {
+                        // The transaction has gone missing, so we submit it again.
+                        entry.status = PendingStatus::CouldNotSubmit;
+                        self.failed(
+                            key,
+                            entry,
+                            "The transaction has gone missing.".to_string(),
+                            now,
+                        )
+                        .await
+                    }
+                    Ok(status) => {
+                        entry.status = PendingStatus::Submitted {
+                            submission_id,
+                            status,
+                        };
+                        self.succeeded(key, entry, now).await
+                    }
+                    Err(e) => self.failed(key, entry, e, now).await,
+                }
+            }
+        }
+    }
+
+    /// Process all entries that are due.
+    pub async fn process_due(&self, now: i64) -> anyhow::Result<()> {
+        for (key, entry) in self.storage(|storage| storage.list_pending()).await? {
+            if entry.next_attempt <= now {
+                if let Err(e) = self.process(&key, now).await {
+                    error!("Could not process the pending entry {}: {}", key, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the dead letter, or the pending entry, with the given key to the
+    /// front of the queue and process it. Returns whether there was an entry.
+    pub async fn redrive(&self, key: &str, now: i64) -> anyhow::Result<bool> {
+        let owned_key = key.to_string();
+        if !self
+            .storage(move |storage| storage.redrive(&owned_key, now))
+            .await?
+        {
+            return Ok(false);
+        }
+        info!("Re-driving the account creation transaction {}.", key);
+        self.process(key, now).await?;
+        Ok(true)
+    }
+
+    /// Spawn a task that processes the due entries at the given interval.
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::delay_for(interval).await;
+                if let Err(e) = self.process_due(now()).await {
+                    error!("Could not process the submission queue: {}", e);
+                }
+            }
+        })
+    }
+
+    /// The attempt succeeded, follow up on the transaction after the initial
+    /// delay.
+    async fn succeeded(&self, key: &str, mut entry: PendingEntry, now: i64) -> anyhow::Result<()> {
+        entry.attempts = 0;
+        entry.last_error = None;
+        entry.next_attempt = after(now, self.policy.initial_delay);
+        let key = key.to_string();
+        self.storage(move |storage| storage.write_pending(&key, &entry))
+            .await
+    }
+
+    /// The attempt failed, retry later unless there have been too many
+    /// attempts.
+    async fn failed(
+        &self,
+        key: &str,
+        mut entry: PendingEntry,
+        reason: String,
+        now: i64,
+    ) -> anyhow::Result<()> {
+        entry.attempts += 1;
+        if entry.attempts >= self.policy.max_attempts {
+            error!(
+                "Giving up on the account creation transaction {} after {} attempts: {}",
+                key, entry.attempts, reason
+            );
+            self.give_up(key, entry, reason, now).await
+        } else {
+            warn!(
+                "Attempt {} for the account creation transaction {} failed: {}",
+                entry.attempts, key, reason
+            );
+            entry.next_attempt = after(now, self.policy.delay(entry.attempts));
+            entry.last_error = Some(reason);
+            let key = key.to_string();
+            self.storage(move |storage| storage.write_pending(&key, &entry))
+                .await
+        }
+    }
+
+    async fn give_up(
+        &self,
+        key: &str,
+        mut entry: PendingEntry,
+        reason: String,
+        now: i64,
+    ) -> anyhow::Result<()> {
+        self.count("given_up");
+        entry.last_error = Some(reason.clone());
+        let key = key.to_string();
+        self.storage(move |storage| {
+            storage.dead_letter(&key, &DeadLetter {
+                entry,
+                reason,
+                time: now,
+            })
+        })
+        .await
+    }
+
+    /// Submit an account creation transaction.
+    async fn submit(&self, submission: &Value) -> SubmitOutcome {
+        let start = Instant::now();
+        let outcome = self.submit_once(submission).await;
+        self.observe("submit", start);
+        self.count(match outcome {
+            SubmitOutcome::Submitted(_) => "submitted",
+            SubmitOutcome::Failed(_) => "failed",
+            SubmitOutcome::Rejected(_) => "rejected",
+        });
+        outcome
+    }
+
+    async fn submit_once(&self, submission: &Value) -> SubmitOutcome {
+        // Submit and wait for the submission ID.
+        match self
+            .client
+            .put(self.submission_url.clone())
+            .json(submission)
+            .send()
+            .await
+        {
+            Ok(response) => match response.status() {
+                StatusCode::BAD_REQUEST => SubmitOutcome::Rejected(
+                    "Failed validation of the reuse of malformed initial account.".to_string(),
+                ),
+                StatusCode::OK => match response.json::<InitialAccountReponse>().await {
+                    Ok(v) => SubmitOutcome::Submitted(v.submission_id),
+                    Err(e) => SubmitOutcome::Failed(format!(
+                        "Unexpected response from the wallet proxy: {}",
+                        e
+                    )),
+                },
+                other => SubmitOutcome::Failed(format!(
+                    "Unexpected response from the wallet proxy: {}",
+                    other
+                )),
+            },
+            // This almost certainly means we could not reach the server, or the server is
+            // configured wrong. This should be considered an internal error and
+            // we must retry.
+            Err(e) => SubmitOutcome::Failed(format!("Could not reach the wallet proxy: {}", e)),
+        }
+    }
+
+    /// Query the status of the submission with the given id.
+    async fn query_status(&self, submission_id: &str) -> Result<SubmissionStatus, String> {
+        let start = Instant::now();
+        let status = self.query_status_once(submission_id).await;
+        self.observe("status", start);
+        status
+    }
+
+    async fn query_status_once(&self, submission_id: &str) -> Result<SubmissionStatus, String> {
+        let mut url = self.submission_url.clone();
+        url.set_path(&format!("v0/submissionStatus/{}", submission_id));
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| format!("Could not query the submission status: {}", e))?;
+        match response.status() {
+            StatusCode::OK => match response.json::<SubmissionStatusResponse>().await {
+                Ok(ss) => Ok(ss.status),
+                Err(e) => Err(format!(
+                    "Unexpected response when querying the submission status: {}",
+                    e
+                )),
+            },
+            other => Err(format!(
+                "Unexpected response when querying the submission status: {}",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use serde_json::json;
+    use std::{fs, path::PathBuf};
+    use warp::Filter;
+
+    /// A stand-in for the wallet-proxy. Submissions are answered with the
+    /// given status codes in order, and with OK once they are used up. The
+    /// submission status is always the given status.
+    #[derive(Clone)]
+    struct Stub {
+        submit_responses: Arc<Mutex<Vec<u16>>>,
+        status:           Arc<Mutex<&'static str>>,
+        submissions:      Arc<Mutex<u32>>,
+    }
+
+    impl Stub {
+        fn new(submit_responses: Vec<u16>, status: &'static str) -> Self {
+            Stub {
+                submit_responses: Arc::new(Mutex::new(submit_responses)),
+                status:           Arc::new(Mutex::new(status)),
+                submissions:      Arc::new(Mutex::new(0)),
+            }
+        }
+
+        fn submissions(&self) -> u32 { *self.submissions.lock().unwrap() }
+
+        fn set_status(&self, status: &'static str) { *self.status.lock().unwrap() = status; }
+
+        /// Start the stub on a free port and return the submission URL.
+        fn serve(&self) -> Url {
+            let submit_stub = self.clone();
+            let submit = warp::put()
+                .and(warp::path!("v0" / "submitCredential"))
+                .map(move || {
+                    *submit_stub.submissions.lock().unwrap() += 1;
+                    let mut responses = submit_stub.submit_responses.lock().unwrap();
+                    let code = if responses.is_empty() {
+                        200
+                    } else {
+                        responses.remove(0)
+                    };
+                    warp::reply::with_status(
+                        warp::reply::json(&json!({"submissionId": "abcd"})),
+                        warp::http::StatusCode::from_u16(code).unwrap(),
+                    )
+                });
+            let status_stub = self.clone();
+            let status = warp::get()
+                .and(warp::path!("v0" / "submissionStatus" / String))
+                .map(move |id: String| {
+                    assert_eq!(id, "abcd");
+                    warp::reply::json(&json!({ "status": *status_stub.status.lock().unwrap() }))
+                });
+            let (addr, server) = warp::serve(submit.or(status)).bind_ephemeral(([127, 0, 0, 1], 0));
+            tokio::spawn(server);
+            Url::parse(&format!("http://{}/v0/submitCredential", addr)).unwrap()
+        }
+    }
+
+    fn test_storage() -> (PathBuf, Arc<SqliteStorage>) {
+        let dir = std::env::temp_dir().join(format!("ip-queue-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).expect("Cannot create test directory.");
+        let storage = Arc::new(SqliteStorage::open(dir.join("db.sqlite")).unwrap());
+        (dir, storage)
+    }
+
+    /// Store the entry of a new submission, as done together with the
+    /// identity object, and submit it.
+    async fn enqueue(
+        queue: &SubmissionQueue,
+        key: &str,
+        submission: Value,
+        now: i64,
+    ) -> anyhow::Result<Enqueued> {
+        let entry = queue.new_entry(submission, now);
+        queue.storage.write_pending(key, &entry)?;
+        queue.enqueue(key, entry, now).await
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_delay: Duration::from_secs(10),
+            max_delay:     Duration::from_secs(30),
+            max_attempts:  3,
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = policy();
+        assert_eq!(policy.delay(1), Duration::from_secs(10));
+        assert_eq!(policy.delay(2), Duration::from_secs(20));
+        assert_eq!(policy.delay(3), Duration::from_secs(30));
+        assert_eq!(policy.delay(100), Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_finalized() {
+        let stub = Stub::new(vec![502, 502], "received");
+        let (dir, storage) = test_storage();
+        let metrics = Metrics::new().unwrap();
+        let queue = SubmissionQueue::new(storage.clone(), Client::new(), stub.serve(), policy())
+            .with_metrics(metrics.clone());
+
+        let enqueued = enqueue(&queue, "aa", json!({"tx": 1}), 0).await.unwrap();
+        assert_eq!(enqueued, Enqueued::Deferred);
+        let entry = storage.read_pending("aa").unwrap().unwrap();
+        assert_eq!(entry.attempts, 1);
+        assert_eq!(entry.next_attempt, 10);
+
+        // Not due yet.
+        queue.process_due(5).await.unwrap();
+        assert_eq!(stub.submissions(), 1);
+
+        queue.process_due(10).await.unwrap();
+        let entry = storage.read_pending("aa").unwrap().unwrap();
+        assert_eq!(entry.status, PendingStatus::CouldNotSubmit);
+        assert_eq!(entry.attempts, 2);
+        assert_eq!(entry.next_attempt, 30);
+
+        queue.process_due(30).await.unwrap();
+        let entry = storage.read_pending("aa").unwrap().unwrap();
+        assert_eq!(entry.status, PendingStatus::Submitted {
+            submission_id: "abcd".into(),
+            status:        SubmissionStatus::Received,
+        });
+        assert_eq!(entry.attempts, 0);
+        assert_eq!(stub.submissions(), 3);
+
+        stub.set_status("committed");
+        queue.process_due(40).await.unwrap();
+        assert!(storage.is_pending("aa").unwrap());
+        stub.set_status("finalized");
+        queue.process_due(50).await.unwrap();
+        assert!(!storage.is_pending("aa").unwrap());
+        assert!(storage.list_dead_letters().unwrap().is_empty());
+
+        let count = |outcome| metrics.submissions.with_label_values(&[outcome]).get();
+        assert_eq!(count("failed"), 2);
+        assert_eq!(count("submitted"), 1);
+        assert_eq!(count("finalized"), 1);
+        let duration = |operation| {
+            metrics
+                .wallet_proxy_duration
+                .with_label_values(&[operation])
+                .get_sample_count()
+        };
+        assert_eq!(duration("submit"), 3);
+        assert_eq!(duration("status"), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_and_redrive() {
+        let stub = Stub::new(vec![502, 503, 504], "finalized");
+        let (dir, storage) = test_storage();
+        let queue = SubmissionQueue::new(storage.clone(), Client::new(), stub.serve(), policy());
+
+        enqueue(&queue, "aa", json!({"tx": 1}), 0).await.unwrap();
+        queue.process_due(100).await.unwrap();
+        queue.process_due(200).await.unwrap();
+        assert!(!storage.is_pending("aa").unwrap());
+        let letter = storage.read_dead_letter("aa").unwrap().unwrap();
+        assert_eq!(letter.entry.attempts, 3);
+        assert_eq!(letter.time, 200);
+
+        // The dead letter is not processed any more.
+        queue.process_due(300).await.unwrap();
+        assert_eq!(stub.submissions(), 3);
+
+        assert!(queue.redrive("aa", 400).await.unwrap());
+        assert_eq!(stub.submissions(), 4);
+        assert!(storage.read_dead_letter("aa").unwrap().is_none());
+        assert!(storage.is_pending("aa").unwrap());
+        queue.process_due(410).await.unwrap();
+        assert!(!storage.is_pending("aa").unwrap());
+
+        assert!(!queue.redrive("bb", 400).await.unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejection() {
+        let stub = Stub::new(vec![400, 502, 400], "absent");
+        let (dir, storage) = test_storage();
+        let queue = SubmissionQueue::new(storage.clone(), Client::new(), stub.serve(), policy());
+
+        // A rejected submission is given up on at once.
+        let enqueued = enqueue(&queue, "aa", json!({"tx": 1}), 0).await.unwrap();
+        assert!(matches!(enqueued, Enqueued::Rejected(_)));
+        assert!(!storage.is_pending("aa").unwrap());
+        assert!(storage.read_dead_letter("aa").unwrap().is_some());
+
+        // Rejection on a retry moves the entry to the dead letters at once.
+        enqueue(&queue, "bb", json!({"tx": 2}), 0).await.unwrap();
+        queue.process_due(10).await.unwrap();
+        assert!(!storage.is_pending("bb").unwrap());
+        let letter = storage.read_dead_letter("bb").unwrap().unwrap();
+        assert_eq!(letter.entry.attempts, 1);
+
+        // A transaction that goes missing is submitted again.
+        enqueue(&queue, "cc", json!({"tx": 3}), 0).await.unwrap();
+        queue.process_due(10).await.unwrap();
+        let entry = storage.read_pending("cc").unwrap().unwrap();
+        assert_eq!(entry.status, PendingStatus::CouldNotSubmit);
+        assert_eq!(entry.attempts, 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_submit_stored_entry() {
+        let stub = Stub::new(vec![], "finalized");
+        let (dir, storage) = test_storage();
+        let queue = SubmissionQueue::new(storage.clone(), Client::new(), stub.serve(), policy());
+
+        // The service stopped after storing the entry, before submitting it.
+        let entry = queue.new_entry(json!({"tx": 1}), 0);
+        storage.write_pending("aa", &entry).unwrap();
+        queue.process_due(5).await.unwrap();
+        assert_eq!(stub.submissions(), 0);
+        queue.process_due(10).await.unwrap();
+        assert_eq!(stub.submissions(), 1);
+        let entry = storage.read_pending("aa").unwrap().unwrap();
+        assert_eq!(entry.status, PendingStatus::Submitted {
+            submission_id: "abcd".into(),
+            status:        SubmissionStatus::Received,
+        });
+
+        // An entry that is being processed is not submitted twice.
+        let entry = queue.new_entry(json!({"tx": 2}), 0);
+        storage.write_pending("bb", &entry).unwrap();
+        let guard = queue.start("bb");
+        assert!(guard.is_some());
+        assert_eq!(
+            queue.enqueue("bb", entry, 0).await.unwrap(),
+            Enqueued::Deferred
+        );
+        assert_eq!(stub.submissions(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,951 @@
+//! Verification of the identity of users.
+//!
+//! After the identity provider has validated a request it sends the user to a
+//! verifier, which establishes who the user is, and then sends the user back
+//! to the identity provider. The identity provider then obtains the verified
+//! attributes from the verifier and uses them to create the identity object.
+//! The [Verifier] trait abstracts over how this happens. There are three
+//! implementations
+//!
+//! - [RedirectVerifier], where the user is redirected to a verifier service,
+//!   such as the `identity-verifier` binary, which is afterwards queried for
+//!   the attributes.
+//! - [OidcVerifier], where the user logs in with an OpenID Connect provider
+//!   using the authorization code flow with PKCE, and the claims of the user
+//!   are mapped to attributes.
+//! - [JwtVerifier], where the user is sent back with an attestation of the
+//!   attributes, which is a JWT signed by a trusted attester.
+//!
+//! Whichever verifier is used, [verified_attribute_list] produces the
+//! attribute list of the identity object.
+use async_trait::async_trait;
+use ed25519_dalek::Verifier as _;
+use id::{
+    constants::{AttributeKind, BaseField},
+    types::{AttributeList, AttributeTag, YearMonth},
+};
+use rand::RngCore;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
+use thiserror::Error;
+use url::Url;
+
+/// The verified attributes of a user.
+pub type Attributes = BTreeMap<AttributeTag, AttributeKind>;
+
+/// The query parameters the user was sent back to the identity provider with.
+pub type Parameters = HashMap<String, String>;
+
+#[derive(Debug, Error)]
+pub enum VerificationError {
+    /// The user could not be verified, e.g., because they did not complete
+    /// the verification, or the attestation is invalid.
+    #[error("The user was not verified: {0}")]
+    NotVerified(String),
+    /// Verification failed for reasons unrelated to the user, e.g., the
+    /// verifier could not be reached.
+    #[error("Verification failed: {0}")]
+    Internal(#[from] anyhow::Error),
+}
+
+fn not_verified<A>(reason: impl Into<String>) -> Result<A, VerificationError> {
+    Err(VerificationError::NotVerified(reason.into()))
+}
+
+/// Where to send the user to be verified.
+#[derive(Debug)]
+pub struct VerificationStart {
+    /// The URL the user is redirected to.
+    pub url:     Url,
+    /// A secret to keep in the browser of the user, in a cookie, and to pass
+    /// back to [Verifier::attributes]. It ensures that the user who comes back
+    /// is the one who was sent, and not somebody who was given the URL.
+    pub session: Option<String>,
+}
+
+/// A way of verifying the identity of users.
+#[async_trait]
+pub trait Verifier: Send + Sync {
+    /// Where to send the user after their request with the given `idCredPub`
+    /// was validated. The `signature` is the base16 encoded signature of the
+    /// identity provider on `idCredPub`, which allows the verifier to check
+    /// that the request was validated.
+    fn start(&self, id_cred_pub: &str, signature: &str) -> anyhow::Result<VerificationStart>;
+
+    /// The `idCredPub` of the request the user was sent back for, if the
+    /// verifier identifies it by the parameters, as [OidcVerifier] does by the
+    /// `state`. Other verifiers send the user back to a URL with the
+    /// `idCredPub`.
+    fn request_key(&self, _parameters: &Parameters) -> Option<String> { None }
+
+    /// The verified attributes of the user with the given `idCredPub`, given
+    /// the parameters the user was sent back to the identity provider with,
+    /// and the session kept in their browser, if any.
+    async fn attributes(
+        &self,
+        id_cred_pub: &str,
+        parameters: &Parameters,
+        session: Option<&str>,
+    ) -> Result<Attributes, VerificationError>;
+}
+
+/// How the identity provider constructs attribute lists from the verified
+/// attributes.
+#[derive(Clone, Copy, Debug)]
+pub struct AttributeListPolicy {
+    /// The number of months the identity object is valid for.
+    pub validity_months: u32,
+    /// The maximum number of accounts that can be created from the identity
+    /// object.
+    pub max_accounts:    u8,
+}
+
+impl Default for AttributeListPolicy {
+    fn default() -> Self {
+        Self {
+            validity_months: 12,
+            max_accounts:    200,
+        }
+    }
+}
+
+impl AttributeListPolicy {
+    /// The end of validity of an identity object created, or renewed, in the
+    /// given month.
+    pub fn valid_to(&self, from: YearMonth) -> YearMonth {
+        let months = u32::from(from.month) - 1 + self.validity_months;
+        YearMonth {
+            year:  from.year + (months / 12) as u16,
+            month: (months % 12 + 1) as u8,
+        }
+    }
+
+    /// The attribute list with the given attributes of an identity object
+    /// created in the given month.
+    pub fn attribute_list(
+        &self,
+        created_at: YearMonth,
+        alist: Attributes,
+    ) -> AttributeList<BaseField, AttributeKind> {
+        AttributeList {
+            valid_to: self.valid_to(created_at),
+            created_at,
+            max_accounts: self.max_accounts,
+            alist,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+/// Obtain the verified attributes of the user from the verifier, and make the
+/// attribute list of the identity object from them.
+pub async fn verified_attribute_list(
+    verifier: &dyn Verifier,
+    policy: &AttributeListPolicy,
+    id_cred_pub: &str,
+    parameters: &Parameters,
+    session: Option<&str>,
+) -> Result<AttributeList<BaseField, AttributeKind>, VerificationError> {
+    let attributes = verifier
+        .attributes(id_cred_pub, parameters, session)
+        .await?;
+    Ok(policy.attribute_list(YearMonth::now(), attributes))
+}
+
+/// Parse the attribute value for the given tag. Dates are accepted in the ISO
+/// 8601 format, e.g., `1990-12-31`, and converted to the `YYYYMMDD` format of
+/// attributes.
+fn attribute_value(tag: AttributeTag, value: &Value) -> Result<AttributeKind, String> {
+    let value = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => return Err(format!("Unsupported value of {}.", tag)),
+    };
+    let value = match tag.to_string().as_str() {
+        "dob" | "idDocIssuedAt" | "idDocExpiresAt" => value.replace('-', ""),
+        _ => value,
+    };
+    if value.len() > 31 {
+        return Err(format!("The value of {} is too long.", tag));
+    }
+    Ok(AttributeKind(value))
+}
+
+/// Verification by redirecting the user to a verifier service. The service is
+/// expected to redirect the user back to `api/identity/create/{idCredPub}`
+/// once it has verified them, and to serve the attributes at
+/// `{query_url}/attributes/{idCredPub}`.
+pub struct RedirectVerifier {
+    client:           Client,
+    /// Where the user is redirected to.
+    verification_url: Url,
+    /// Where the attributes are queried.
+    query_url:        Url,
+}
+
+impl RedirectVerifier {
+    pub fn new(client: Client, verification_url: Url, query_url: Url) -> Self {
+        Self {
+            client,
+            verification_url,
+            query_url,
+        }
+    }
+}
+
+#[async_trait]
+impl Verifier for RedirectVerifier {
+    fn start(&self, id_cred_pub: &str, signature: &str) -> anyhow::Result<VerificationStart> {
+        Ok(VerificationStart {
+            url:     Url::parse(&format!(
+                "{}/{}/{}",
+                self.verification_url, id_cred_pub, signature
+            ))?,
+            session: None,
+        })
+    }
+
+    async fn attributes(
+        &self,
+        id_cred_pub: &str,
+        _parameters: &Parameters,
+        _session: Option<&str>,
+    ) -> Result<Attributes, VerificationError> {
+        let url = Url::parse(&format!("{}/attributes/{}", self.query_url, id_cred_pub))
+            .map_err(anyhow::Error::from)?;
+        let response = self.client.get(url).send().await.map_err(|e| {
+            anyhow::anyhow!("Could not retrieve attribute list from the verifier: {}", e)
+        })?;
+        // If there is no attribute list the user has not been verified.
+        match response.json().await {
+            Ok(attributes) => Ok(attributes),
+            Err(e) => not_verified(format!(
+                "Could not deserialize response from the verifier: {}",
+                e
+            )),
+        }
+    }
+}
+
+/// Configuration of an OpenID Connect provider.
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcConfig {
+    /// The issuer identifier of the provider, which must be the `iss` of its
+    /// ID tokens.
+    pub issuer:                 String,
+    pub authorization_endpoint: Url,
+    pub token_endpoint:         Url,
+    pub userinfo_endpoint:      Url,
+    pub client_id:              String,
+    pub client_secret:          String,
+    /// The scopes to request. `openid` is always requested.
+    #[serde(default)]
+    pub scopes:                 Vec<String>,
+    /// Which claim gives the value of which attribute, e.g.,
+    /// `{"given_name": "firstName", "birthdate": "dob"}`. Claims that are not
+    /// listed are ignored.
+    pub claims:                 BTreeMap<String, AttributeTag>,
+}
+
+/// How long the user has to log in with the OpenID provider, in seconds.
+const OIDC_SESSION_LIFETIME: i64 = 600;
+
+/// A login with the OpenID provider that was started for a request.
+struct OidcSession {
+    id_cred_pub:   String,
+    /// The PKCE code verifier, whose hash was sent as the code challenge.
+    code_verifier: String,
+    /// The nonce the ID token must contain.
+    nonce:         String,
+    /// Time, in seconds since the unix epoch, after which the login is no
+    /// longer accepted.
+    expiry:        i64,
+}
+
+/// Verification by logging in with an OpenID Connect provider, using the
+/// authorization code flow with PKCE. A random `state` is passed to the
+/// provider, and kept both by the verifier, which maps it to the request, and
+/// in the browser of the user as the session. The provider redirects the user
+/// to the given redirect URL, which must be the `api/identity/callback`
+/// endpoint of the identity provider. The ID token is checked, and the claims
+/// of the user are then queried at the userinfo endpoint.
+///
+/// Logins that were started are kept in memory, so they have to be started
+/// again if the service restarts.
+pub struct OidcVerifier {
+    client:       Client,
+    config:       OidcConfig,
+    redirect_url: Url,
+    /// The logins that were started, by their `state`.
+    sessions:     Mutex<HashMap<String, OidcSession>>,
+}
+
+#[derive(SerdeDeserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token:     String,
+}
+
+/// The `aud` claim of a JWT, which is either a single audience or a list.
+#[derive(SerdeDeserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == audience,
+            Audience::Many(auds) => auds.iter().any(|aud| aud == audience),
+        }
+    }
+
+    fn is_single(&self) -> bool {
+        match self {
+            Audience::One(_) => true,
+            Audience::Many(auds) => auds.len() == 1,
+        }
+    }
+}
+
+#[derive(SerdeDeserialize)]
+struct IdTokenClaims {
+    iss:   String,
+    sub:   String,
+    aud:   Audience,
+    exp:   i64,
+    #[serde(default)]
+    azp:   Option<String>,
+    #[serde(default)]
+    nonce: Option<String>,
+}
+
+/// Decode a part of a JWT.
+fn jwt_part(part: &str) -> Result<Vec<u8>, String> {
+    base64::decode_config(part, base64::URL_SAFE_NO_PAD).map_err(|_| "Not a JWT.".to_string())
+}
+
+/// A fresh random token, base64url encoded.
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+impl OidcVerifier {
+    pub fn new(client: Client, config: OidcConfig, redirect_url: Url) -> Self {
+        Self {
+            client,
+            config,
+            redirect_url,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, OidcSession>> {
+        self.sessions
+            .lock()
+            .expect("Cannot acquire a lock, which means something is very wrong.")
+    }
+
+    /// Start a login for the given `idCredPub` at the given time, in seconds
+    /// since the unix epoch.
+    fn start_at(&self, id_cred_pub: &str, now: i64) -> VerificationStart {
+        let mut scopes = vec!["openid"];
+        scopes.extend(
+            self.config
+                .scopes
+                .iter()
+                .map(String::as_str)
+                .filter(|scope| *scope != "openid"),
+        );
+        let state = random_token();
+        let session = OidcSession {
+            id_cred_pub:   id_cred_pub.to_string(),
+            code_verifier: random_token(),
+            nonce:         random_token(),
+            expiry:        now + OIDC_SESSION_LIFETIME,
+        };
+        let code_challenge = base64::encode_config(
+            Sha256::digest(session.code_verifier.as_bytes()),
+            base64::URL_SAFE_NO_PAD,
+        );
+        let mut url = self.config.authorization_endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", self.redirect_url.as_str())
+            .append_pair("scope", &scopes.join(" "))
+            .append_pair("state", &state)
+            .append_pair("nonce", &session.nonce)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+        let mut sessions = self.lock();
+        sessions.retain(|_, session| session.expiry > now);
+        sessions.insert(state.clone(), session);
+        VerificationStart {
+            url,
+            session: Some(state),
+        }
+    }
+
+    /// Take the login the user was sent back for out of the started ones, so
+    /// that it is used at most once, and check that it belongs to the request
+    /// and to the browser of the user.
+    fn take_session(
+        &self,
+        id_cred_pub: &str,
+        parameters: &Parameters,
+        session: Option<&str>,
+        now: i64,
+    ) -> Result<OidcSession, String> {
+        let state = parameters.get("state").ok_or("No state.")?;
+        let oidc_session = self.lock().remove(state).ok_or("Unknown state.")?;
+        if session != Some(state.as_str()) {
+            return Err("The login was started in another browser.".into());
+        }
+        if oidc_session.id_cred_pub != id_cred_pub {
+            return Err("The state does not match the request.".into());
+        }
+        if oidc_session.expiry <= now {
+            return Err("The login has expired.".into());
+        }
+        Ok(oidc_session)
+    }
+
+    /// Check the claims of the ID token at the given time, and return the
+    /// subject. The signature of the token is not checked, since it is
+    /// received directly from the token endpoint over TLS, which authenticates
+    /// the provider, see section 3.1.3.7 of OpenID Connect Core 1.0.
+    fn check_id_token(&self, token: &str, nonce: &str, now: i64) -> Result<String, String> {
+        let payload = match token.split('.').collect::<Vec<_>>().as_slice() {
+            [_, payload, _] => jwt_part(payload)?,
+            _ => return Err("The ID token is not a JWT.".into()),
+        };
+        let claims: IdTokenClaims = serde_json::from_slice(&payload)
+            .map_err(|e| format!("Invalid ID token claims: {}", e))?;
+        if claims.iss != self.config.issuer {
+            return Err(format!("Unexpected issuer {}.", claims.iss));
+        }
+        if !claims.aud.contains(&self.config.client_id) {
+            return Err("The ID token is not for this client.".into());
+        }
+        if !claims.aud.is_single() && claims.azp.as_deref() != Some(&self.config.client_id) {
+            return Err("The ID token is not for this client.".into());
+        }
+        if claims.exp <= now {
+            return Err("The ID token has expired.".into());
+        }
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err("The nonce of the ID token does not match.".into());
+        }
+        Ok(claims.sub)
+    }
+
+    /// Map the claims of the user to attributes.
+    fn map_claims(&self, claims: &serde_json::Map<String, Value>) -> Result<Attributes, String> {
+        let mut attributes = Attributes::new();
+        for (claim, tag) in &self.config.claims {
+            if let Some(value) = claims.get(claim) {
+                attributes.insert(*tag, attribute_value(*tag, value)?);
+            }
+        }
+        Ok(attributes)
+    }
+}
+
+#[async_trait]
+impl Verifier for OidcVerifier {
+    fn start(&self, id_cred_pub: &str, _signature: &str) -> anyhow::Result<VerificationStart> {
+        Ok(self.start_at(id_cred_pub, chrono::Utc::now().timestamp()))
+    }
+
+    fn request_key(&self, parameters: &Parameters) -> Option<String> {
+        let state = parameters.get("state")?;
+        self.lock()
+            .get(state)
+            .map(|session| session.id_cred_pub.clone())
+    }
+
+    async fn attributes(
+        &self,
+        id_cred_pub: &str,
+        parameters: &Parameters,
+        session: Option<&str>,
+    ) -> Result<Attributes, VerificationError> {
+        if let Some(error) = parameters.get("error") {
+            return not_verified(format!("The OpenID provider returned {}.", error));
+        }
+        let now = chrono::Utc::now().timestamp();
+        let oidc_session = self
+            .take_session(id_cred_pub, parameters, session, now)
+            .map_err(VerificationError::NotVerified)?;
+        let code = match parameters.get("code") {
+            Some(code) => code,
+            None => return not_verified("No authorization code."),
+        };
+        let response = self
+            .client
+            .post(self.config.token_endpoint.clone())
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.redirect_url.as_str()),
+                ("client_id", &self.config.client_id),
+                ("client_secret", &self.config.client_secret),
+                ("code_verifier", &oidc_session.code_verifier),
+            ])
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Could not reach the token endpoint: {}", e))?;
+        if response.status() != StatusCode::OK {
+            // Most likely the code is invalid or was already used.
+            return not_verified(format!(
+                "The token endpoint responded with {}.",
+                response.status()
+            ));
+        }
+        let token: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| anyhow::anyhow!("Unexpected response from the token endpoint: {}", e))?;
+        let subject = self
+            .check_id_token(&token.id_token, &oidc_session.nonce, now)
+            .map_err(VerificationError::NotVerified)?;
+        let claims: serde_json::Map<String, Value> = self
+            .client
+            .get(self.config.userinfo_endpoint.clone())
+            .bearer_auth(&token.access_token)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow::anyhow!("Could not query the userinfo endpoint: {}", e))?
+            .json()
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!("Unexpected response from the userinfo endpoint: {}", e)
+            })?;
+        // The claims must be those of the user who logged in.
+        if claims.get("sub").and_then(Value::as_str) != Some(subject.as_str()) {
+            return not_verified("The userinfo is for another user.");
+        }
+        self.map_claims(&claims)
+            .map_err(VerificationError::NotVerified)
+    }
+}
+
+/// Configuration of the attester whose attestations are accepted.
+#[derive(SerdeSerialize, SerdeDeserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JwtConfig {
+    /// Where the user is sent to be verified. The `idCredPub`, the signature
+    /// of the identity provider on it, and the URL to which the user should be
+    /// sent back with the attestation are passed as query parameters.
+    pub verification_url: Url,
+    /// The expected issuer of attestations.
+    pub issuer:           String,
+    /// The identifier of this identity provider, which attestations must have
+    /// as their audience.
+    pub audience:         String,
+    /// The ed25519 key with which attestations are signed, base16 encoded.
+    #[serde(
+        serialize_with = "crypto_common::base16_encode",
+        deserialize_with = "crypto_common::base16_decode"
+    )]
+    pub public_key:       ed25519_dalek::PublicKey,
+}
+
+/// Verification by an attestation that the user brings back, which is a JWT
+/// signed with EdDSA by a trusted attester. The attester redirects the user to
+/// `api/identity/create/{idCredPub}?attestation={jwt}`. The claims of the JWT
+/// must include
+///
+/// - `iss`, the issuer, which must be the one configured,
+/// - `aud`, the audience, which must include the configured one,
+/// - `sub`, the base16 encoded `idCredPub`,
+/// - `exp`, the expiry of the attestation in seconds since the unix epoch,
+/// - `attributes`, an object mapping attribute tags, e.g., `firstName`, to
+///   values.
+pub struct JwtVerifier {
+    config:       JwtConfig,
+    /// The base URL of the identity provider, where the user is sent back to.
+    callback_url: Url,
+}
+
+#[derive(SerdeDeserialize)]
+struct JwtHeader {
+    alg: String,
+}
+
+#[derive(SerdeDeserialize)]
+struct AttestationClaims {
+    iss:        String,
+    aud:        Audience,
+    sub:        String,
+    exp:        i64,
+    attributes: serde_json::Map<String, Value>,
+}
+
+impl JwtVerifier {
+    /// A verifier using the given configuration. The `callback_url` is the base
+    /// URL of the identity provider.
+    pub fn new(config: JwtConfig, callback_url: Url) -> Self {
+        Self {
+            config,
+            callback_url,
+        }
+    }
+
+    /// Check the attestation for the given `idCredPub` at the given time, in
+    /// seconds since the unix epoch, and return the attested attributes.
+    pub fn check_attestation(
+        &self,
+        id_cred_pub: &str,
+        token: &str,
+        now: i64,
+    ) -> Result<Attributes, String> {
+        let parts: Vec<&str> = token.split('.').collect();
+        let (header, payload, signature) = match parts.as_slice() {
+            [header, payload, signature] => (header, payload, signature),
+            _ => return Err("The attestation is not a JWT.".into()),
+        };
+        let decode = |part: &str| {
+            base64::decode_config(part, base64::URL_SAFE_NO_PAD)
+                .map_err(|_| "The attestation is not a JWT.".to_string())
+        };
+        let header: JwtHeader = serde_json::from_slice(&decode(header)?)
+            .map_err(|e| format!("Invalid attestation header: {}", e))?;
+        if header.alg != "EdDSA" {
+            return Err(format!("Unsupported algorithm {}.", header.alg));
+        }
+        let signature = ed25519_dalek::Signature::try_from(decode(signature)?.as_slice())
+            .map_err(|_| "Invalid signature of the attestation.".to_string())?;
+        let signed = format!("{}.{}", parts[0], parts[1]);
+        if self
+            .config
+            .public_key
+            .verify(signed.as_bytes(), &signature)
+            .is_err()
+        {
+            return Err("Invalid signature of the attestation.".into());
+        }
+        let claims: AttestationClaims = serde_json::from_slice(&decode(payload)?)
+            .map_err(|e| format!("Invalid attestation claims: {}", e))?;
+        if claims.iss != self.config.issuer {
+            return Err(format!("Unexpected issuer {}.", claims.iss));
+        }
+        if !claims.aud.contains(&self.config.audience) {
+            return Err("The attestation is not for this identity provider.".into());
+        }
+        if claims.sub != id_cred_pub {
+            return Err("The attestation is for another request.".into());
+        }
+        if claims.exp <= now {
+            return Err("The attestation has expired.".into());
+        }
+        let mut attributes = Attributes::new();
+        for (name, value) in &claims.attributes {
+            let tag =
+                AttributeTag::from_str(name).map_err(|_| format!("Unknown attribute {}.", name))?;
+            attributes.insert(tag, attribute_value(tag, value)?);
+        }
+        Ok(attributes)
+    }
+}
+
+#[async_trait]
+impl Verifier for JwtVerifier {
+    fn start(&self, id_cred_pub: &str, signature: &str) -> anyhow::Result<VerificationStart> {
+        let mut callback = self.callback_url.clone();
+        callback.set_path(&format!("api/identity/create/{}", id_cred_pub));
+        let mut url = self.config.verification_url.clone();
+        url.query_pairs_mut()
+            .append_pair("id_cred_pub", id_cred_pub)
+            .append_pair("signature", signature)
+            .append_pair("redirect_uri", callback.as_str());
+        Ok(VerificationStart { url, session: None })
+    }
+
+    async fn attributes(
+        &self,
+        id_cred_pub: &str,
+        parameters: &Parameters,
+        _session: Option<&str>,
+    ) -> Result<Attributes, VerificationError> {
+        let token = match parameters.get("attestation") {
+            Some(token) => token,
+            None => return not_verified("No attestation."),
+        };
+        let now = chrono::Utc::now().timestamp();
+        self.check_attestation(id_cred_pub, token, now)
+            .map_err(VerificationError::NotVerified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Signer;
+    use serde_json::json;
+
+    fn encode(value: &Value) -> String {
+        base64::encode_config(value.to_string(), base64::URL_SAFE_NO_PAD)
+    }
+
+    fn sign(keypair: &ed25519_dalek::Keypair, header: &Value, claims: &Value) -> String {
+        let signed = format!("{}.{}", encode(header), encode(claims));
+        let signature = keypair.sign(signed.as_bytes());
+        format!(
+            "{}.{}",
+            signed,
+            base64::encode_config(signature.to_bytes().as_ref(), base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    fn tag(name: &str) -> AttributeTag { AttributeTag::from_str(name).unwrap() }
+
+    #[test]
+    fn test_jwt_attestation() {
+        let keypair = ed25519_dalek::Keypair::generate(&mut rand::thread_rng());
+        let verifier = JwtVerifier::new(
+            JwtConfig {
+                verification_url: Url::parse("https://attester.example/verify").unwrap(),
+                issuer:           "attester".into(),
+                audience:         "idp".into(),
+                public_key:       keypair.public,
+            },
+            Url::parse("https://idp.example").unwrap(),
+        );
+        let header = json!({"alg": "EdDSA", "typ": "JWT"});
+        let claims = json!({
+            "iss": "attester",
+            "aud": "idp",
+            "sub": "aabb",
+            "exp": 1000,
+            "attributes": {"firstName": "John", "dob": "1990-12-31"}
+        });
+        let token = sign(&keypair, &header, &claims);
+        let attributes = verifier.check_attestation("aabb", &token, 999).unwrap();
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes[&tag("firstName")], AttributeKind("John".into()));
+        assert_eq!(attributes[&tag("dob")], AttributeKind("19901231".into()));
+
+        // Expired, for another request, or with a bad signature.
+        assert!(verifier.check_attestation("aabb", &token, 1000).is_err());
+        assert!(verifier.check_attestation("ccdd", &token, 999).is_err());
+        let other = ed25519_dalek::Keypair::generate(&mut rand::thread_rng());
+        assert!(verifier
+            .check_attestation("aabb", &sign(&other, &header, &claims), 999)
+            .is_err());
+        let tampered = format!(
+            "{}.{}.{}",
+            encode(&header),
+            encode(
+                &json!({"iss": "attester", "aud": "idp", "sub": "aabb", "exp": 1000, "attributes": {}})
+            ),
+            token.rsplit('.').next().unwrap()
+        );
+        assert!(verifier.check_attestation("aabb", &tampered, 999).is_err());
+        // Only EdDSA is accepted.
+        let none = sign(&keypair, &json!({"alg": "none"}), &claims);
+        assert!(verifier.check_attestation("aabb", &none, 999).is_err());
+        // Attestations for other identity providers are not accepted.
+        let mut other_audience = claims.clone();
+        other_audience["aud"] = json!(["other-idp"]);
+        let token = sign(&keypair, &header, &other_audience);
+        assert!(verifier.check_attestation("aabb", &token, 999).is_err());
+        other_audience["aud"] = json!(["other-idp", "idp"]);
+        let token = sign(&keypair, &header, &other_audience);
+        assert!(verifier.check_attestation("aabb", &token, 999).is_ok());
+
+        let start = verifier.start("aabb", "0102").unwrap();
+        assert!(start.session.is_none());
+        let pairs: Parameters = start.url.query_pairs().into_owned().collect();
+        assert_eq!(
+            pairs["redirect_uri"],
+            "https://idp.example/api/identity/create/aabb"
+        );
+    }
+
+    #[test]
+    fn test_oidc_claims() {
+        let claims: BTreeMap<String, AttributeTag> = [
+            ("given_name".to_string(), tag("firstName")),
+            ("birthdate".to_string(), tag("dob")),
+            ("nationality".to_string(), tag("nationality")),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let verifier = OidcVerifier::new(
+            Client::new(),
+            OidcConfig {
+                issuer: "https://op.example".into(),
+                authorization_endpoint: Url::parse("https://op.example/authorize").unwrap(),
+                token_endpoint: Url::parse("https://op.example/token").unwrap(),
+                userinfo_endpoint: Url::parse("https://op.example/userinfo").unwrap(),
+                client_id: "idp".into(),
+                client_secret: "secret".into(),
+                scopes: vec!["profile".into()],
+                claims,
+            },
+            Url::parse("https://idp.example/api/identity/callback").unwrap(),
+        );
+        let userinfo = json!({"sub": "123", "given_name": "Jane", "birthdate": "1985-01-02"});
+        let attributes = verifier.map_claims(userinfo.as_object().unwrap()).unwrap();
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes[&tag("dob")], AttributeKind("19850102".into()));
+
+        let start = verifier.start_at("aabb", 1000);
+        let pairs: Parameters = start.url.query_pairs().into_owned().collect();
+        assert_eq!(pairs["scope"], "openid profile");
+        assert_eq!(pairs["client_id"], "idp");
+        assert_eq!(pairs["code_challenge_method"], "S256");
+        // The state is random, and is the session kept in the browser.
+        let state = pairs["state"].clone();
+        assert_ne!(state, "aabb");
+        assert_eq!(start.session.as_deref(), Some(state.as_str()));
+        let callback: Parameters = [("state".to_string(), state.clone())]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(verifier.request_key(&callback).as_deref(), Some("aabb"));
+
+        // The login must come back to the browser that started it, for the
+        // same request, and can only be used once.
+        assert!(verifier
+            .take_session("aabb", &callback, None, 1000)
+            .is_err());
+        assert!(verifier.request_key(&callback).is_none());
+        let state = verifier.start_at("aabb", 1000).session.unwrap();
+        let callback: Parameters = [("state".to_string(), state.clone())]
+            .iter()
+            .cloned()
+            .collect();
+        assert!(verifier
+            .take_session("ccdd", &callback, Some(&state), 1000)
+            .is_err());
+        let state = verifier.start_at("aabb", 1000).session.unwrap();
+        let callback: Parameters = [("state".to_string(), state.clone())]
+            .iter()
+            .cloned()
+            .collect();
+        assert!(verifier
+            .take_session(
+                "aabb",
+                &callback,
+                Some(&state),
+                1000 + OIDC_SESSION_LIFETIME
+            )
+            .is_err());
+        let start = verifier.start_at("aabb", 1000);
+        let state = start.session.unwrap();
+        let callback: Parameters = [("state".to_string(), state.clone())]
+            .iter()
+            .cloned()
+            .collect();
+        let session = verifier
+            .take_session("aabb", &callback, Some(&state), 1001)
+            .unwrap();
+        assert!(verifier
+            .take_session("aabb", &callback, Some(&state), 1001)
+            .is_err());
+        let pairs: Parameters = start.url.query_pairs().into_owned().collect();
+        assert_eq!(
+            pairs["code_challenge"],
+            base64::encode_config(
+                Sha256::digest(session.code_verifier.as_bytes()),
+                base64::URL_SAFE_NO_PAD
+            )
+        );
+        assert_eq!(pairs["nonce"], session.nonce);
+
+        // The ID token must be issued by the provider for this client and the
+        // login.
+        let id_token = |claims: &Value| {
+            format!(
+                "{}.{}.signature",
+                encode(&json!({"alg": "RS256"})),
+                encode(claims)
+            )
+        };
+        let claims = json!({
+            "iss": "https://op.example",
+            "sub": "123",
+            "aud": "idp",
+            "exp": 2000,
+            "nonce": session.nonce
+        });
+        assert_eq!(
+            verifier.check_id_token(&id_token(&claims), &session.nonce, 1001),
+            Ok("123".to_string())
+        );
+        assert!(verifier
+            .check_id_token(&id_token(&claims), &session.nonce, 2000)
+            .is_err());
+        assert!(verifier
+            .check_id_token(&id_token(&claims), "other", 1001)
+            .is_err());
+        for (claim, value) in &[
+            ("iss", json!("https://other.example")),
+            ("aud", json!("other")),
+            ("aud", json!(["idp", "other"])),
+        ] {
+            let mut claims = claims.clone();
+            claims[*claim] = value.clone();
+            assert!(verifier
+                .check_id_token(&id_token(&claims), &session.nonce, 1001)
+                .is_err());
+        }
+        let mut claims = claims;
+        claims["aud"] = json!(["idp", "other"]);
+        claims["azp"] = json!("idp");
+        assert!(verifier
+            .check_id_token(&id_token(&claims), &session.nonce, 1001)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_attribute_list_policy() {
+        let policy = AttributeListPolicy::default();
+        let alist = policy.attribute_list(
+            YearMonth {
+                year:  2021,
+                month: 12,
+            },
+            Attributes::new(),
+        );
+        assert_eq!(alist.valid_to, YearMonth {
+            year:  2022,
+            month: 12,
+        });
+        let alist = AttributeListPolicy {
+            validity_months: 1,
+            ..policy
+        }
+        .attribute_list(
+            YearMonth {
+                year:  2021,
+                month: 12,
+            },
+            Attributes::new(),
+        );
+        assert_eq!(alist.valid_to, YearMonth {
+            year:  2022,
+            month: 1,
+        });
+        assert_eq!(alist.max_accounts, 200);
+    }
+}
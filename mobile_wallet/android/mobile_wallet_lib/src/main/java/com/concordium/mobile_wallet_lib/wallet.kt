@@ -9,6 +9,7 @@ external fun create_pub_to_sec_transfer(input: String) : ReturnValue
 external fun create_sec_to_pub_transfer(input: String) : ReturnValue
 external fun combine_encrypted_amounts(input1: String, input2: String) : ReturnValue
 external fun decrypt_encrypted_amount(input: String) : ReturnValue
+external fun decrypt_encrypted_amounts_batch(input: String) : ReturnValue
 external fun check_account_address(input: String) : Boolean
 external fun link_check(input: String) : String
 
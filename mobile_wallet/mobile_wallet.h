@@ -124,6 +124,22 @@ char *create_transfer(const char *input_ptr, uint8_t *success);
  */
 uint64_t decrypt_encrypted_amount(const char *input_ptr, uint8_t *success);
 
+/**
+ * Take a pointer to a NUL-terminated UTF8-string and return a NUL-terminated
+ * UTF8-encoded string. The returned string must be freed by the caller by
+ * calling the function 'free_response_string'. In case of failure the function
+ * returns an error message as the response, and sets the 'success' flag to 0.
+ *
+ * The input string should encode a JSON object with fields "encryptedAmounts"
+ * and "encryptionSecretKey". The response is a JSON array with the decryption
+ * of each amount, or `null` if it cannot be decrypted.
+ *
+ * # Safety
+ * The input pointer must point to a null-terminated buffer, otherwise this
+ * function will fail in unspecified ways.
+ */
+char *decrypt_encrypted_amounts_batch(const char *input_ptr, uint8_t *success);
+
 /**
  * # Safety
  * This function is unsafe in the sense that if the argument pointer was not
@@ -20,7 +20,6 @@ use std::{
     collections::BTreeMap,
     convert::TryInto,
     ffi::{CStr, CString},
-    io::Cursor,
 };
 
 use crypto_common::types::KeyPair;
@@ -401,6 +400,13 @@ fn create_credential_aux(input: &str) -> anyhow::Result<String> {
 
     let acc_num: u8 = try_get(&v, "accountNumber")?;
 
+    // Statements about attributes that are not revealed, which will be proved
+    // as part of the credential.
+    let statements: Vec<AttributeStatement<AttributeKind>> = match v.get("attributeStatements") {
+        Some(s) => from_value(s.clone())?,
+        None => Vec::new(),
+    };
+
     // The mobile wallet for now only creates new accounts and does not support
     // adding credentials onto existing ones. Once that is supported the address
     // should be coming from the input data.
@@ -435,6 +441,7 @@ fn create_credential_aux(input: &str) -> anyhow::Result<String> {
         created_at: id_object.alist.created_at,
         policy_vec,
         _phantom: Default::default(),
+        statements: Vec::new(),
     };
 
     let context = IpContext::new(&ip_info, &ars_infos, &global_context);
@@ -445,8 +452,10 @@ fn create_credential_aux(input: &str) -> anyhow::Result<String> {
         &id_use_data,
         acc_num,
         policy,
+        &statements,
         &cred_data,
         &new_or_existing,
+        None,
     )?;
 
     let address = match new_or_existing {
@@ -514,10 +523,8 @@ fn generate_accounts_aux(input: &str) -> anyhow::Result<String> {
     Ok(to_string(&response)?)
 }
 
-/// Embed the precomputed table for decryption.
-/// It is unfortunate that this is pure bytes, but not enough of data is marked
-/// as const, and in any case a HashMap relies on an allocator, so will never be
-/// const.
+/// Embed the precomputed compact table for decryption. The table is searched
+/// directly in the embedded bytes, so it is never copied to the heap.
 static TABLE_BYTES: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/table_bytes.bin"));
 
 fn decrypt_encrypted_amount_aux(input: &str) -> anyhow::Result<Amount> {
@@ -525,14 +532,24 @@ fn decrypt_encrypted_amount_aux(input: &str) -> anyhow::Result<Amount> {
     let encrypted_amount = try_get(&v, "encryptedAmount")?;
     let secret = try_get(&v, "encryptionSecretKey")?;
 
-    let table = (&mut Cursor::new(TABLE_BYTES)).get()?;
-    Ok(
-        encrypted_transfers::decrypt_amount::<id::constants::ArCurve>(
-            &table,
-            &secret,
-            &encrypted_amount,
-        ),
-    )
+    let table =
+        elgamal::CompactBabyStepGiantStep::<id::constants::ArCurve, _>::from_bytes(TABLE_BYTES)?;
+    match encrypted_transfers::try_decrypt_amount(&table, &secret, &encrypted_amount) {
+        Some(amount) => Ok(amount),
+        None => bail!("Could not decrypt the encrypted amount."),
+    }
+}
+
+fn decrypt_encrypted_amounts_batch_aux(input: &str) -> anyhow::Result<String> {
+    let v: Value = from_str(input)?;
+    let encrypted_amounts: Vec<encrypted_transfers::types::EncryptedAmount<ExampleCurve>> =
+        try_get(&v, "encryptedAmounts")?;
+    let secret = try_get(&v, "encryptionSecretKey")?;
+
+    let table =
+        elgamal::CompactBabyStepGiantStep::<id::constants::ArCurve, _>::from_bytes(TABLE_BYTES)?;
+    let amounts = encrypted_transfers::decrypt_amounts_batch(&table, &secret, &encrypted_amounts);
+    Ok(to_string(&amounts)?)
 }
 
 /// Set the flag to 0, and return a newly allocated string containing
@@ -734,6 +751,21 @@ make_wrapper!(
     /// function will fail in unspecified ways.
     => generate_accounts -> generate_accounts_aux);
 
+make_wrapper!(
+    /// Take a pointer to a NUL-terminated UTF8-string and return a NUL-terminated
+    /// UTF8-encoded string. The returned string must be freed by the caller by
+    /// calling the function 'free_response_string'. In case of failure the function
+    /// returns an error message as the response, and sets the 'success' flag to 0.
+    ///
+    /// The input string should encode a JSON object with fields "encryptedAmounts"
+    /// and "encryptionSecretKey". The response is a JSON array with the decryption
+    /// of each amount, or `null` if it cannot be decrypted.
+    ///
+    /// # Safety
+    /// The input pointer must point to a null-terminated buffer, otherwise this
+    /// function will fail in unspecified ways.
+    => decrypt_encrypted_amounts_batch -> decrypt_encrypted_amounts_batch_aux);
+
 /// Take pointers to a NUL-terminated UTF8-string and return a u64.
 ///
 /// In case of failure to decode the input the function will
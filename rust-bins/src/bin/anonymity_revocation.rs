@@ -4,10 +4,12 @@ use crypto_common::*;
 use crypto_common_derive::*;
 use curve_arithmetic::{Curve, Value};
 use dodis_yampolskiy_prf as prf;
-use elgamal::{decrypt_from_chunks_given_generator, Message};
-use id::{anonymity_revoker::*, constants::ArCurve, types::*};
+use elgamal::{chunks_to_value, BabyStepGiantStep, Cipher, Message};
+use id::{anonymity_revoker::*, ar_committee::*, secret_sharing::Threshold, types::*};
+use serde::de::DeserializeOwned;
 use serde_json::json;
 use std::{
+    collections::{BTreeMap, BTreeSet},
     convert::TryFrom,
     path::{Path, PathBuf},
 };
@@ -53,14 +55,21 @@ struct CombinePrf {
         long = "ar-record",
         help = "File with the JSON encoded anonymity revocation record."
     )]
-    ar_record: PathBuf,
+    ar_record:          PathBuf,
+    #[structopt(long = "global-context", help = "File with global context.")]
+    global_context:     PathBuf,
+    #[structopt(
+        long = "anonymity-revokers",
+        help = "File with the public keys of the anonymity revokers."
+    )]
+    anonymity_revokers: PathBuf,
     #[structopt(
         long = "shares",
         help = "Files with the JSON encoded decrypted shares."
     )]
-    shares:    Vec<PathBuf>,
+    shares:             Vec<PathBuf>,
     #[structopt(long = "out", help = "File to output the decryption to.")]
-    out:       PathBuf,
+    out:                PathBuf,
 }
 
 #[derive(StructOpt)]
@@ -69,14 +78,19 @@ struct Combine {
         long = "credential",
         help = "File with the JSON encoded credential or credential values."
     )]
-    credential: PathBuf,
+    credential:         PathBuf,
+    #[structopt(
+        long = "anonymity-revokers",
+        help = "File with the public keys of the anonymity revokers."
+    )]
+    anonymity_revokers: PathBuf,
     #[structopt(
         long = "shares",
         help = "Files with the JSON encoded decrypted shares."
     )]
-    shares:     Vec<PathBuf>,
+    shares:             Vec<PathBuf>,
     #[structopt(long = "out", help = "File to output the decryption to.")]
-    out:        PathBuf,
+    out:                PathBuf,
 }
 
 #[derive(StructOpt)]
@@ -96,6 +110,156 @@ struct ComputeRegIds {
     no_secret:      bool,
 }
 
+#[derive(StructOpt)]
+struct CommitteeDeal {
+    #[structopt(long = "global-context", help = "File with global context.")]
+    global_context: PathBuf,
+    #[structopt(
+        long = "dealer",
+        help = "Identity of the committee member making the dealing."
+    )]
+    dealer:         ArIdentity,
+    #[structopt(long = "members", help = "Identities of all the committee members.")]
+    members:        Vec<ArIdentity>,
+    #[structopt(long = "threshold", help = "Number of members needed to decrypt.")]
+    threshold:      u8,
+    #[structopt(long = "out", help = "File to output the public dealing to.")]
+    out:            PathBuf,
+    #[structopt(
+        long = "out-shares",
+        help = "Directory to output the shares of the members to. Each share is encrypted with a \
+                password agreed with its member, and must only be sent to that member."
+    )]
+    out_shares:     PathBuf,
+}
+
+#[derive(StructOpt)]
+struct CommitteeKeygen {
+    #[structopt(long = "global-context", help = "File with global context.")]
+    global_context:    PathBuf,
+    #[structopt(
+        long = "ar-identity",
+        help = "The identity of the anonymity revoker that the committee acts as."
+    )]
+    ar_identity:       ArIdentity,
+    #[structopt(long = "member", help = "Identity of this committee member.")]
+    member:            ArIdentity,
+    #[structopt(long = "members", help = "Identities of all the committee members.")]
+    members:           Vec<ArIdentity>,
+    #[structopt(long = "threshold", help = "Number of members needed to decrypt.")]
+    threshold:         u8,
+    #[structopt(long = "dealings", help = "Files with the dealings of the members.")]
+    dealings:          Vec<PathBuf>,
+    #[structopt(
+        long = "shares",
+        help = "Files with the shares this member received, possibly encrypted."
+    )]
+    shares:            Vec<PathBuf>,
+    #[structopt(long = "name", help = "Name of the anonymity revoker.")]
+    name:              String,
+    #[structopt(long = "url", help = "URL of the anonymity revoker.")]
+    url:               String,
+    #[structopt(long = "description", help = "Description of the anonymity revoker.")]
+    description:       String,
+    #[structopt(
+        long = "out",
+        help = "File to output the private data of the member to. As plaintext or encrypted."
+    )]
+    out:               PathBuf,
+    #[structopt(
+        long = "out-pub",
+        help = "File to output the public anonymity revoker information to."
+    )]
+    out_pub:           PathBuf,
+    #[structopt(
+        long = "out-committee-key",
+        help = "File to output the public key of the committee to."
+    )]
+    out_committee_key: PathBuf,
+}
+
+#[derive(StructOpt)]
+struct CommitteeDecrypt {
+    #[structopt(
+        long = "credential",
+        help = "File with the JSON encoded credential or credential values."
+    )]
+    credential:     PathBuf,
+    #[structopt(
+        long = "member-private",
+        help = "File with the private data of the committee member. As plaintext or encrypted."
+    )]
+    member_private: PathBuf,
+    #[structopt(long = "out", help = "File to output the partial decryption to.")]
+    out:            PathBuf,
+}
+
+#[derive(StructOpt)]
+struct CommitteeDecryptPrf {
+    #[structopt(
+        long = "ar-record",
+        help = "File with the JSON encoded anonymity revocation record."
+    )]
+    ar_record:      PathBuf,
+    #[structopt(
+        long = "member-private",
+        help = "File with the private data of the committee member. As plaintext or encrypted."
+    )]
+    member_private: PathBuf,
+    #[structopt(long = "out", help = "File to output the partial decryption to.")]
+    out:            PathBuf,
+}
+
+#[derive(StructOpt)]
+struct CommitteeCombine {
+    #[structopt(
+        long = "credential",
+        help = "File with the JSON encoded credential or credential values."
+    )]
+    credential:    PathBuf,
+    #[structopt(
+        long = "committee-key",
+        help = "File with the public key of the committee."
+    )]
+    committee_key: PathBuf,
+    #[structopt(
+        long = "shares",
+        help = "Files with the JSON encoded partial decryptions of the members."
+    )]
+    shares:        Vec<PathBuf>,
+    #[structopt(
+        long = "out",
+        help = "File to output the decrypted share of the anonymity revoker to."
+    )]
+    out:           PathBuf,
+}
+
+#[derive(StructOpt)]
+struct CommitteeCombinePrf {
+    #[structopt(
+        long = "ar-record",
+        help = "File with the JSON encoded anonymity revocation record."
+    )]
+    ar_record:      PathBuf,
+    #[structopt(long = "global-context", help = "File with global context.")]
+    global_context: PathBuf,
+    #[structopt(
+        long = "committee-key",
+        help = "File with the public key of the committee."
+    )]
+    committee_key:  PathBuf,
+    #[structopt(
+        long = "shares",
+        help = "Files with the JSON encoded partial decryptions of the members."
+    )]
+    shares:         Vec<PathBuf>,
+    #[structopt(
+        long = "out",
+        help = "File to output the decrypted share of the anonymity revoker to."
+    )]
+    out:            PathBuf,
+}
+
 #[derive(StructOpt)]
 #[structopt(
     about = "Prototype tool showcasing anonymity revoker (inter)actions.",
@@ -129,6 +293,42 @@ enum AnonymityRevocation {
         about = "Computes all possible RegIds given a PRF key and the maximal number of accounts."
     )]
     ComputeRegIds(ComputeRegIds),
+    #[structopt(
+        name = "committee-deal",
+        about = "Make the dealing of a committee member in the generation of the key of an \
+                 anonymity revoker that is a committee."
+    )]
+    CommitteeDeal(CommitteeDeal),
+    #[structopt(
+        name = "committee-keygen",
+        about = "Check the dealings and shares received by a committee member, and compute the \
+                 key of the committee and the share of the member."
+    )]
+    CommitteeKeygen(CommitteeKeygen),
+    #[structopt(
+        name = "committee-decrypt",
+        about = "Take a deployed credential and let a committee member partially decrypt the \
+                 share of idCredPub of the committee."
+    )]
+    CommitteeDecrypt(CommitteeDecrypt),
+    #[structopt(
+        name = "committee-decrypt-prf",
+        about = "Take an anonymity revocation record and let a committee member partially decrypt \
+                 the share of the PRF key of the committee."
+    )]
+    CommitteeDecryptPrf(CommitteeDecryptPrf),
+    #[structopt(
+        name = "committee-combine",
+        about = "Combine partial decryptions of committee members to get the share of idCredPub \
+                 of the committee."
+    )]
+    CommitteeCombine(CommitteeCombine),
+    #[structopt(
+        name = "committee-combine-prf",
+        about = "Combine partial decryptions of committee members to get the share of the PRF key \
+                 of the committee."
+    )]
+    CommitteeCombinePrf(CommitteeCombinePrf),
 }
 
 #[derive(Debug, Serialize, SerdeSerialize, SerdeDeserialize)]
@@ -138,6 +338,52 @@ struct PrfWrapper<C: Curve> {
     pub prf_key: prf::SecretKey<C>,
 }
 
+/// Share of a committee member in a dealing, to be sent to the member only.
+#[derive(SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+struct CommitteeShare<C: Curve> {
+    #[serde(rename = "dealer")]
+    dealer: ArIdentity,
+    #[serde(rename = "member")]
+    member: ArIdentity,
+    #[serde(rename = "share")]
+    share:  Value<C>,
+}
+
+/// Public key of an anonymity revoker that is a committee.
+#[derive(SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+struct CommitteePublicData<C: Curve> {
+    #[serde(rename = "arIdentity")]
+    ar_identity:   ArIdentity,
+    #[serde(rename = "committeeKey")]
+    committee_key: CommitteeKey<C>,
+}
+
+/// Private data of a member of an anonymity revoker that is a committee.
+#[derive(SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+struct CommitteeMemberData<C: Curve> {
+    #[serde(rename = "member")]
+    member:            ArIdentity,
+    #[serde(rename = "committee")]
+    committee:         CommitteePublicData<C>,
+    #[serde(rename = "memberSecretKey")]
+    member_secret_key: elgamal::SecretKey<C>,
+}
+
+/// Partial decryptions of the ciphertexts of an anonymity revoker that is a
+/// committee by one of its members. There is one partial decryption for the
+/// share of idCredPub, and one for each chunk of the share of the PRF key.
+#[derive(SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+struct CommitteeDecryptedShare<C: Curve> {
+    #[serde(rename = "arIdentity")]
+    ar_identity:         ArIdentity,
+    #[serde(rename = "partialDecryptions")]
+    partial_decryptions: Vec<PartialDecryption<C>>,
+}
+
 fn main() {
     let app = AnonymityRevocation::clap()
         .setting(AppSettings::ArgRequiredElseHelp)
@@ -171,6 +417,36 @@ fn main() {
                 eprintln!("{}", e)
             }
         }
+        CommitteeDeal(cd) => {
+            if let Err(e) = handle_committee_deal(cd) {
+                eprintln!("{}", e)
+            }
+        }
+        CommitteeKeygen(ckg) => {
+            if let Err(e) = handle_committee_keygen(ckg) {
+                eprintln!("{}", e)
+            }
+        }
+        CommitteeDecrypt(dcr) => {
+            if let Err(e) = handle_committee_decrypt_id(dcr) {
+                eprintln!("{}", e)
+            }
+        }
+        CommitteeDecryptPrf(dcr) => {
+            if let Err(e) = handle_committee_decrypt_prf(dcr) {
+                eprintln!("{}", e)
+            }
+        }
+        CommitteeCombine(cmb) => {
+            if let Err(e) = handle_committee_combine_id(cmb) {
+                eprintln!("{}", e)
+            }
+        }
+        CommitteeCombinePrf(cmb) => {
+            if let Err(e) = handle_committee_combine_prf(cmb) {
+                eprintln!("{}", e)
+            }
+        }
     }
 }
 
@@ -253,8 +529,9 @@ fn handle_compute_regids(rid: ComputeRegIds) -> Result<(), String> {
     Ok(())
 }
 
-// Try to read ArData, either from encrypted or a plaintext file.
-fn decrypt_ar_data(fname: &Path) -> Result<ArData<ArCurve>, String> {
+// Try to read private data of an anonymity revoker, either from encrypted or a
+// plaintext file.
+fn decrypt_ar_data<T: DeserializeOwned>(fname: &Path) -> Result<T, String> {
     let data = succeed_or_die!(std::fs::read(fname), e => "Could not read anonymity revoker secret keys due to {}");
     match serde_json::from_slice(&data) {
         Ok(v) => Ok(v),
@@ -293,12 +570,16 @@ fn handle_decrypt_id(dcr: Decrypt) -> Result<(), String> {
         ar_data.get(&ar.public_ar_info.ar_identity),
         "Supplied AR is not part of the credential."
     );
-    let m = ar
-        .ar_secret_key
-        .decrypt(&single_ar_data.enc_id_cred_pub_share);
+    let (m, proof) = decrypt_with_proof(
+        &ar.ar_secret_key,
+        &single_ar_data.enc_id_cred_pub_share,
+        &mut rand::thread_rng(),
+    );
     let share = ChainArDecryptedData {
         ar_identity:       ar.public_ar_info.ar_identity,
         id_cred_pub_share: m,
+        proof:             Some(proof),
+        committee_key:     None,
     };
     match write_json_to_file(&dcr.out, &share) {
         Ok(_) => println!("Wrote decryption to {}", dcr.out.display()),
@@ -331,16 +612,18 @@ fn handle_decrypt_prf(dcr: DecryptPrf) -> Result<(), String> {
         ar_data.get(&ar.public_ar_info.ar_identity),
         "Given AR is not part of the credential."
     );
-    let m = decrypt_from_chunks_given_generator(
+    let table = BabyStepGiantStep::new(global_context.encryption_in_exponent_generator(), 1 << 16);
+    let (m, proofs) = decrypt_prf_key_share_with_proofs(
         &ar.ar_secret_key,
         &single_ar_data.enc_prf_key_share,
-        &global_context.encryption_in_exponent_generator(),
-        1 << 16,
-        CHUNK_SIZE,
+        &table,
+        &mut rand::thread_rng(),
     );
     let share = IpArDecryptedData {
-        ar_identity:   ar.public_ar_info.ar_identity,
+        ar_identity: ar.public_ar_info.ar_identity,
         prf_key_share: m,
+        proofs,
+        committee_key: None,
     };
     match write_json_to_file(&dcr.out, &share) {
         Ok(_) => println!("Wrote decryption to {}.", dcr.out.display()),
@@ -364,50 +647,43 @@ fn handle_combine_id(cmb: Combine) -> Result<(), String> {
         AccountCredentialValues::Normal { cdi } => cdi,
     };
     let revocation_threshold = credential.threshold;
+    let ars = succeed_or_die!(read_anonymity_revokers(&cmb.anonymity_revokers), e => "Could not read anonymity revokers due to {}");
 
-    let shares_values: Vec<_> = cmb.shares;
-
-    let number_of_ars = shares_values.len();
-    let number_of_ars =
-        u8::try_from(number_of_ars).expect("Number of anonymity revokers should not exceed 2^8-1");
-    if number_of_ars < revocation_threshold.into() {
-        return Err(format!(
-            "Insufficient number of anonymity revokers ({}). Threshold is {}.",
-            number_of_ars, revocation_threshold
-        ));
-    }
-
-    let mut ar_decrypted_data_vec: Vec<ChainArDecryptedData<ExampleCurve>> =
-        Vec::with_capacity(shares_values.len());
-    let mut shares: Vec<(ArIdentity, Message<ExampleCurve>)> =
-        Vec::with_capacity(shares_values.len());
-
-    for share_value in shares_values.iter() {
-        let decrypted = read_json_from_file(&share_value).map_err(|e| {
-            format!(
-                "Could not read from ar file {}, error: {}",
-                share_value.display(),
-                e
-            )
-        })?;
-        ar_decrypted_data_vec.push(decrypted);
-    }
-
-    let mut ar_identities = Vec::with_capacity(ar_decrypted_data_vec.len());
-
-    for ar_decrypted_data in ar_decrypted_data_vec {
-        let ar_id = ar_decrypted_data.ar_identity;
-        ar_identities.push(ar_id);
-        shares.push((ar_id, ar_decrypted_data.id_cred_pub_share));
-    }
-    ar_identities.sort();
-    ar_identities.dedup();
-    if ar_identities.len() < shares.len() {
-        return Err(
-            "No duplicates among the anonymity revokers identities nor share numbers are allowed."
-                .to_owned(),
-        );
+    let mut shares: Vec<(ArIdentity, Message<ExampleCurve>)> = Vec::with_capacity(cmb.shares.len());
+    let mut faulty = Vec::new();
+    for share_value in cmb.shares.iter() {
+        let decrypted: ChainArDecryptedData<ExampleCurve> = read_json_from_file(&share_value)
+            .map_err(|e| {
+                format!(
+                    "Could not read from ar file {}, error: {}",
+                    share_value.display(),
+                    e
+                )
+            })?;
+        let ar_id = decrypted.ar_identity;
+        let correct = match (
+            credential.ar_data.get(&ar_id),
+            ars.anonymity_revokers.get(&ar_id),
+        ) {
+            (Some(single_ar_data), Some(ar_info)) => match decrypted.proof {
+                Some(ref proof) => verify_decryption(
+                    &ar_info.ar_public_key,
+                    decrypted.committee_key.as_ref(),
+                    &single_ar_data.enc_id_cred_pub_share,
+                    &decrypted.id_cred_pub_share,
+                    proof,
+                ),
+                None => false,
+            },
+            _ => false,
+        };
+        if correct {
+            shares.push((ar_id, decrypted.id_cred_pub_share));
+        } else {
+            faulty.push((ar_id, share_value));
+        }
     }
+    check_combined_shares(&mut shares, &faulty, revocation_threshold)?;
 
     let id_cred_pub = reveal_id_cred_pub(&shares);
     let id_cred_pub_string = base16_encode_string(&id_cred_pub);
@@ -428,62 +704,465 @@ fn handle_combine_prf(cmb: CombinePrf) -> Result<(), String> {
     if ar_record.version != VERSION_0 {
         return Err("The version of the ArRecord should be 0.".to_owned());
     }
+    let ar_record = ar_record.value;
+    let revocation_threshold = ar_record.threshold;
+    let global_context: Versioned<GlobalContext<ExampleCurve>> = succeed_or_die!(read_json_from_file(cmb.global_context), e => "Could not read global context due to {}");
+    if global_context.version != VERSION_0 {
+        return Err("The version of the GlobalContext should be 0.".to_owned());
+    }
+    let global_context = global_context.value;
+    let ars = succeed_or_die!(read_anonymity_revokers(&cmb.anonymity_revokers), e => "Could not read anonymity revokers due to {}");
 
-    let revocation_threshold = ar_record.value.threshold;
+    let mut shares: Vec<(ArIdentity, Value<ExampleCurve>)> = Vec::with_capacity(cmb.shares.len());
+    let mut faulty = Vec::new();
+    for share_value in cmb.shares.iter() {
+        let decrypted: IpArDecryptedData<ExampleCurve> = read_json_from_file(&share_value)
+            .map_err(|e| {
+                format!(
+                    "Could not read from ar file {}, error: {}",
+                    share_value.display(),
+                    e
+                )
+            })?;
+        let ar_id = decrypted.ar_identity;
+        let correct = match (
+            ar_record.ar_data.get(&ar_id),
+            ars.anonymity_revokers.get(&ar_id),
+        ) {
+            (Some(single_ar_data), Some(ar_info)) => verify_prf_key_share_decryption(
+                &ar_info.ar_public_key,
+                decrypted.committee_key.as_ref(),
+                &single_ar_data.enc_prf_key_share,
+                global_context.encryption_in_exponent_generator(),
+                &decrypted.prf_key_share,
+                &decrypted.proofs,
+            ),
+            _ => false,
+        };
+        if correct {
+            shares.push((ar_id, decrypted.prf_key_share));
+        } else {
+            faulty.push((ar_id, share_value));
+        }
+    }
+    check_combined_shares(&mut shares, &faulty, revocation_threshold)?;
 
-    let shares_values: Vec<_> = cmb.shares;
+    let prf_key = reveal_prf_key(&shares);
+    let prf_key_string = base16_encode_string(&prf_key);
+    let json = json!({ "prfKey": prf_key_string });
+    match write_json_to_file(&cmb.out, &json) {
+        Ok(_) => println!("Wrote PRF key to {}.", cmb.out.display()),
+        Err(e) => {
+            println!("Could not write to file because {}", e);
+        }
+    }
+    Ok(())
+}
 
-    let number_of_ars = shares_values.len();
-    let number_of_ars =
-        u8::try_from(number_of_ars).expect("Number of anonymity revokers should not exceed 2^8-1");
-    if number_of_ars < revocation_threshold.into() {
+/// Report the shares whose proof of decryption is missing or not correct, or
+/// that are by anonymity revokers not involved in the ciphertexts, and check
+/// that the correct shares are by distinct anonymity revokers and suffice to
+/// reach the threshold.
+fn check_combined_shares<X>(
+    shares: &mut Vec<(ArIdentity, X)>,
+    faulty: &[(ArIdentity, &PathBuf)],
+    revocation_threshold: Threshold,
+) -> Result<(), String> {
+    for (ar_id, fname) in faulty.iter() {
+        eprintln!(
+            "The share of anonymity revoker {} in {} is faulty. It is ignored.",
+            ar_id,
+            fname.display()
+        );
+    }
+    shares.sort_by_key(|(ar_id, _)| *ar_id);
+    let number_of_shares = shares.len();
+    shares.dedup_by_key(|(ar_id, _)| *ar_id);
+    if shares.len() < number_of_shares {
+        return Err(
+            "No duplicates among the anonymity revokers identities nor share numbers are allowed."
+                .to_owned(),
+        );
+    }
+    if shares.len() < usize::from(revocation_threshold) {
         return Err(format!(
-            "Insufficient number of anonymity revokers ({}). Threshold is {}.",
-            number_of_ars, revocation_threshold
+            "Insufficient number of correct shares of anonymity revokers ({}). Threshold is {}.",
+            shares.len(),
+            revocation_threshold
         ));
     }
+    Ok(())
+}
 
-    let mut ar_decrypted_data_vec: Vec<IpArDecryptedData<ExampleCurve>> =
-        Vec::with_capacity(shares_values.len());
-    let mut shares: Vec<(ArIdentity, Value<ExampleCurve>)> =
-        Vec::with_capacity(shares_values.len());
+/// Check the committee members and threshold given on the command line.
+fn committee_parameters(
+    members: &[ArIdentity],
+    threshold: u8,
+) -> Result<(BTreeSet<ArIdentity>, Threshold), String> {
+    let member_set = members.iter().copied().collect::<BTreeSet<_>>();
+    if member_set.len() != members.len() {
+        return Err("The committee members must be distinct.".to_owned());
+    }
+    let threshold = succeed_or_die!(
+        Threshold::try_from(threshold).ok(),
+        "The threshold must be at least 1."
+    );
+    if usize::from(threshold) > member_set.len() {
+        return Err(format!(
+            "The threshold {} exceeds the number of committee members ({}).",
+            threshold,
+            member_set.len()
+        ));
+    }
+    Ok((member_set, threshold))
+}
 
-    for share_value in shares_values.iter() {
-        match read_json_from_file(&share_value) {
-            Err(y) => {
-                return Err(format!(
-                    "Could not read from ar file {}, error: {}",
-                    share_value.display(),
-                    y
-                ));
+fn handle_committee_deal(cd: CommitteeDeal) -> Result<(), String> {
+    let global_context = succeed_or_die!(
+        read_global_context(&cd.global_context),
+        "Could not read global context."
+    );
+    let (members, threshold) = committee_parameters(&cd.members, cd.threshold)?;
+    if !members.contains(&cd.dealer) {
+        return Err("The dealer must be a committee member.".to_owned());
+    }
+    let generator = global_context.on_chain_commitment_key.g;
+    let (dealing, shares) = deal(
+        &generator,
+        cd.dealer,
+        &members,
+        threshold,
+        &mut rand::thread_rng(),
+    );
+    succeed_or_die!(std::fs::create_dir_all(&cd.out_shares), e => "Could not create the directory for shares due to {}");
+    for (member, share) in shares {
+        let share = CommitteeShare {
+            dealer: cd.dealer,
+            member,
+            share,
+        };
+        let fname = cd
+            .out_shares
+            .join(format!("share-{}-to-{}.json", cd.dealer, member));
+        println!("Encrypting the share of member {}.", member);
+        succeed_or_die!(output_possibly_encrypted(&fname, &share), e => "Could not write share to file due to {}");
+        println!("Wrote share of member {} to {}.", member, fname.display());
+    }
+    succeed_or_die!(write_json_to_file(&cd.out, &dealing), e => "Could not write dealing to file due to {}");
+    println!("Wrote dealing to {}.", cd.out.display());
+    Ok(())
+}
+
+fn handle_committee_keygen(ckg: CommitteeKeygen) -> Result<(), String> {
+    let global_context = succeed_or_die!(
+        read_global_context(&ckg.global_context),
+        "Could not read global context."
+    );
+    let (members, threshold) = committee_parameters(&ckg.members, ckg.threshold)?;
+    if !members.contains(&ckg.member) {
+        return Err("The member must be a committee member.".to_owned());
+    }
+    let generator = global_context.on_chain_commitment_key.g;
+
+    let mut dealings = BTreeMap::new();
+    for fname in ckg.dealings.iter() {
+        let dealing: Dealing<ExampleCurve> =
+            succeed_or_die!(read_json_from_file(fname), e => "Could not read dealing due to {}");
+        if !members.contains(&dealing.dealer) {
+            return Err(format!(
+                "Dealer {} is not a committee member.",
+                dealing.dealer
+            ));
+        }
+        if dealings.insert(dealing.dealer, dealing).is_some() {
+            return Err("There can only be one dealing by each member.".to_owned());
+        }
+    }
+    let mut shares = BTreeMap::new();
+    for fname in ckg.shares.iter() {
+        let share: CommitteeShare<ExampleCurve> =
+            succeed_or_die!(decrypt_ar_data(fname), e => "Could not read share due to {}");
+        if share.member != ckg.member {
+            return Err(format!(
+                "The share in {} is for member {}.",
+                fname.display(),
+                share.member
+            ));
+        }
+        if shares.insert(share.dealer, share.share).is_some() {
+            return Err("There can only be one share from each dealer.".to_owned());
+        }
+    }
+
+    // Complain about all dealers whose dealing or share is missing or invalid.
+    let mut complaints = Vec::new();
+    for &dealer in members.iter() {
+        let valid = match (dealings.get(&dealer), shares.get(&dealer)) {
+            (Some(dealing), Some(share)) => {
+                verify_dealing(&generator, dealing, threshold)
+                    && verify_share(&generator, dealing, ckg.member, share)
             }
-            Ok(val) => ar_decrypted_data_vec.push(val),
+            _ => false,
+        };
+        if !valid {
+            complaints.push(dealer);
+        }
+    }
+    if !complaints.is_empty() {
+        for dealer in complaints.iter() {
+            eprintln!(
+                "Complaint: the dealing or share of member {} is missing or invalid.",
+                dealer
+            );
         }
+        return Err(
+            "The key generation must be restarted without the members complained about.".to_owned(),
+        );
     }
 
-    let mut ar_identities = Vec::with_capacity(ar_decrypted_data_vec.len());
+    let dealings = dealings.into_iter().map(|(_, d)| d).collect::<Vec<_>>();
+    let committee_key = succeed_or_die!(committee_key(&generator, threshold, &members, &dealings), e => "Could not compute the committee key due to {}");
+    let member_secret_key = member_secret_key(&generator, shares.values());
+    let public_ar_info = ArInfo {
+        ar_identity:    ckg.ar_identity,
+        ar_description: Description {
+            name:        ckg.name,
+            url:         ckg.url,
+            description: ckg.description,
+        },
+        ar_public_key:  committee_key.public_key,
+    };
+    let committee = CommitteePublicData {
+        ar_identity: ckg.ar_identity,
+        committee_key,
+    };
+    succeed_or_die!(write_json_to_file(&ckg.out_committee_key, &committee), e => "Could not write the committee key to file due to {}");
+    println!(
+        "Wrote the committee key to {}.",
+        ckg.out_committee_key.display()
+    );
+    let ver_public_ar_info = Versioned::new(VERSION_0, public_ar_info);
+    succeed_or_die!(write_json_to_file(&ckg.out_pub, &ver_public_ar_info), e => "Could not write public keys to file due to {}");
+    println!("Wrote public keys to {}.", ckg.out_pub.display());
+    let member_data = CommitteeMemberData {
+        member: ckg.member,
+        committee,
+        member_secret_key,
+    };
+    succeed_or_die!(output_possibly_encrypted(&ckg.out, &member_data), e => "Could not write the private data of the member to file due to {}");
+    println!("Wrote private data of the member to {}.", ckg.out.display());
+    Ok(())
+}
+
+/// Partially decrypt the ciphertexts with the key share of the member, and
+/// write the result to the given file.
+fn output_partial_decryptions(
+    member_data: &CommitteeMemberData<ExampleCurve>,
+    ciphers: &[Cipher<ExampleCurve>],
+    out: &Path,
+) -> Result<(), String> {
+    let mut csprng = rand::thread_rng();
+    let partial_decryptions = ciphers
+        .iter()
+        .map(|cipher| {
+            partial_decrypt(
+                member_data.member,
+                &member_data.member_secret_key,
+                cipher,
+                &mut csprng,
+            )
+        })
+        .collect();
+    let share = CommitteeDecryptedShare {
+        ar_identity: member_data.committee.ar_identity,
+        partial_decryptions,
+    };
+    succeed_or_die!(write_json_to_file(out, &share), e => "Could not write JSON to file due to {}");
+    println!("Wrote partial decryption to {}.", out.display());
+    Ok(())
+}
+
+/// Partially decrypt encIdCredPubShare of a committee.
+fn handle_committee_decrypt_id(dcr: CommitteeDecrypt) -> Result<(), String> {
+    let credential: Versioned<AccountCredentialValues<ExampleCurve, ExampleAttribute>> = succeed_or_die!(read_json_from_file(dcr.credential), e => "Could not read credential from provided file because {}");
 
-    for ar_decrypted_data in ar_decrypted_data_vec {
-        let ar_id = ar_decrypted_data.ar_identity;
-        ar_identities.push(ar_id);
-        shares.push((ar_id, ar_decrypted_data.prf_key_share));
+    if credential.version != VERSION_0 {
+        return Err("The version of the credential should be 0".to_owned());
     }
-    ar_identities.sort();
-    ar_identities.dedup();
-    if ar_identities.len() < shares.len() {
-        return Err(
-            "No duplicates among the anonymity revokers identities nor share numbers are allowed."
-                .to_owned(),
+    let credential = match credential.value {
+        AccountCredentialValues::Initial { .. } => {
+            return Err("Cannot decrypt data from initial account.".to_owned())
+        }
+        AccountCredentialValues::Normal { cdi } => cdi,
+    };
+    let member_data: CommitteeMemberData<ExampleCurve> = succeed_or_die!(decrypt_ar_data(&dcr.member_private), e => "Could not read the private data of the member due to {}");
+    let single_ar_data = succeed_or_die!(
+        credential.ar_data.get(&member_data.committee.ar_identity),
+        "The committee is not part of the credential."
+    );
+    output_partial_decryptions(
+        &member_data,
+        &[single_ar_data.enc_id_cred_pub_share],
+        &dcr.out,
+    )
+}
+
+/// Partially decrypt the chunks of encPrfKeyShare of a committee.
+fn handle_committee_decrypt_prf(dcr: CommitteeDecryptPrf) -> Result<(), String> {
+    let ar_record: Versioned<AnonymityRevocationRecord<ExampleCurve>> = succeed_or_die!(read_json_from_file(dcr.ar_record), e => "Could not read ArRecord due to {}");
+
+    if ar_record.version != VERSION_0 {
+        return Err("The version of the ArRecord should be 0.".to_owned());
+    }
+    let member_data: CommitteeMemberData<ExampleCurve> = succeed_or_die!(decrypt_ar_data(&dcr.member_private), e => "Could not read the private data of the member due to {}");
+    let single_ar_data = succeed_or_die!(
+        ar_record
+            .value
+            .ar_data
+            .get(&member_data.committee.ar_identity),
+        "The committee is not part of the anonymity revocation record."
+    );
+    output_partial_decryptions(&member_data, &single_ar_data.enc_prf_key_share, &dcr.out)
+}
+
+/// Read the partial decryptions of the ciphertexts by the committee members,
+/// and combine those that are correct. Members whose partial decryptions are
+/// not correct are reported, and there must be at least a threshold of correct
+/// ones. The correct partial decryptions are returned as the proof of the
+/// decryption of each ciphertext.
+fn combine_committee_shares(
+    committee: &CommitteePublicData<ExampleCurve>,
+    ciphers: &[Cipher<ExampleCurve>],
+    share_files: &[PathBuf],
+) -> Result<Vec<(Message<ExampleCurve>, DecryptionProof<ExampleCurve>)>, String> {
+    let mut valid = BTreeMap::new();
+    for fname in share_files.iter() {
+        let share: CommitteeDecryptedShare<ExampleCurve> = read_json_from_file(fname)
+            .map_err(|e| format!("Could not read from file {}, error: {}", fname.display(), e))?;
+        if share.ar_identity != committee.ar_identity {
+            return Err(format!(
+                "The partial decryption in {} is for anonymity revoker {}.",
+                fname.display(),
+                share.ar_identity
+            ));
+        }
+        let member = succeed_or_die!(
+            share.partial_decryptions.first().map(|pd| pd.member),
+            "Partial decryptions must not be empty."
         );
+        let correct = share.partial_decryptions.len() == ciphers.len()
+            && share
+                .partial_decryptions
+                .iter()
+                .zip(ciphers.iter())
+                .all(|(pd, cipher)| {
+                    pd.member == member
+                        && verify_partial_decryption(&committee.committee_key, cipher, pd)
+                });
+        if !correct {
+            eprintln!(
+                "The partial decryption of member {} in {} is not correct. It is ignored.",
+                member,
+                fname.display()
+            );
+        } else if valid.insert(member, share.partial_decryptions).is_some() {
+            return Err("No duplicates among the committee members are allowed.".to_owned());
+        }
+    }
+    let threshold = committee.committee_key.threshold;
+    if valid.len() < usize::from(threshold) {
+        return Err(format!(
+            "Insufficient number of correct partial decryptions ({}). Threshold is {}.",
+            valid.len(),
+            threshold
+        ));
     }
+    ciphers
+        .iter()
+        .enumerate()
+        .map(|(i, cipher)| {
+            let partial_decryptions = valid.values().map(|pds| pds[i].clone()).collect::<Vec<_>>();
+            let message = combine_partial_decryptions(threshold, cipher, &partial_decryptions)
+                .map_err(|e| format!("Could not combine the partial decryptions: {}", e))?;
+            let proof = DecryptionProof::Committee {
+                partial_decryptions,
+            };
+            Ok((message, proof))
+        })
+        .collect()
+}
 
-    let prf_key = reveal_prf_key(&shares);
-    let prf_key_string = base16_encode_string(&prf_key);
-    let json = json!({ "prfKey": prf_key_string });
-    match write_json_to_file(&cmb.out, &json) {
-        Ok(_) => println!("Wrote PRF key to {}.", cmb.out.display()),
+fn handle_committee_combine_id(cmb: CommitteeCombine) -> Result<(), String> {
+    let credential: Versioned<AccountCredentialValues<ExampleCurve, ExampleAttribute>> = succeed_or_die!(read_json_from_file(cmb.credential), e => "Could not read credential from provided file because {}");
+
+    if credential.version != VERSION_0 {
+        return Err("The version of the credential should be 0".to_owned());
+    }
+    let credential = match credential.value {
+        AccountCredentialValues::Initial { .. } => {
+            return Err("Cannot decrypt data from initial account.".to_owned())
+        }
+        AccountCredentialValues::Normal { cdi } => cdi,
+    };
+    let committee: CommitteePublicData<ExampleCurve> = succeed_or_die!(read_json_from_file(cmb.committee_key), e => "Could not read the committee key due to {}");
+    let single_ar_data = succeed_or_die!(
+        credential.ar_data.get(&committee.ar_identity),
+        "The committee is not part of the credential."
+    );
+    let (id_cred_pub_share, proof) = combine_committee_shares(
+        &committee,
+        &[single_ar_data.enc_id_cred_pub_share],
+        &cmb.shares,
+    )?
+    .remove(0);
+    let share = ChainArDecryptedData {
+        ar_identity: committee.ar_identity,
+        id_cred_pub_share,
+        proof: Some(proof),
+        committee_key: Some(committee.committee_key),
+    };
+    match write_json_to_file(&cmb.out, &share) {
+        Ok(_) => println!("Wrote decryption to {}", cmb.out.display()),
         Err(e) => {
-            println!("Could not write to file because {}", e);
+            eprintln!("Could not write JSON to file due to {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn handle_committee_combine_prf(cmb: CommitteeCombinePrf) -> Result<(), String> {
+    let ar_record: Versioned<AnonymityRevocationRecord<ExampleCurve>> = succeed_or_die!(read_json_from_file(cmb.ar_record), e => "Could not read ArRecord due to {}");
+
+    if ar_record.version != VERSION_0 {
+        return Err("The version of the ArRecord should be 0.".to_owned());
+    }
+    let global_context: Versioned<GlobalContext<ExampleCurve>> = succeed_or_die!(read_json_from_file(cmb.global_context), e => "Could not read global context due to {}");
+    if global_context.version != VERSION_0 {
+        return Err("The version of the GlobalContext should be 0.".to_owned());
+    }
+    let global_context = global_context.value;
+    let committee: CommitteePublicData<ExampleCurve> = succeed_or_die!(read_json_from_file(cmb.committee_key), e => "Could not read the committee key due to {}");
+    let single_ar_data = succeed_or_die!(
+        ar_record.value.ar_data.get(&committee.ar_identity),
+        "The committee is not part of the anonymity revocation record."
+    );
+    let messages =
+        combine_committee_shares(&committee, &single_ar_data.enc_prf_key_share, &cmb.shares)?;
+    let bsgs = BabyStepGiantStep::new(global_context.encryption_in_exponent_generator(), 1 << 16);
+    let (chunks, proofs): (Vec<_>, Vec<_>) = messages
+        .into_iter()
+        .map(|(m, proof)| (Value::from(bsgs.discrete_log(&m.value)), proof))
+        .unzip();
+    let share = IpArDecryptedData {
+        ar_identity: committee.ar_identity,
+        prf_key_share: chunks_to_value(&chunks, CHUNK_SIZE),
+        proofs,
+        committee_key: Some(committee.committee_key),
+    };
+    match write_json_to_file(&cmb.out, &share) {
+        Ok(_) => println!("Wrote decryption to {}.", cmb.out.display()),
+        Err(e) => {
+            eprintln!("Could not write JSON to file because {}", e);
         }
     }
     Ok(())
@@ -304,6 +304,11 @@ struct CreateCredential {
     anonymity_revokers: PathBuf,
     #[structopt(long = "index", help = "Index of the account to be created.")]
     index:              Option<u8>,
+    #[structopt(
+        long = "statements",
+        help = "File with a JSON encoded list of statements about unrevealed attributes to prove."
+    )]
+    statements:         Option<PathBuf>,
 }
 
 #[derive(StructOpt)]
@@ -771,6 +776,19 @@ fn handle_create_credential(cc: CreateCredential) {
         created_at: id_object.alist.created_at,
         policy_vec: revealed_attributes,
         _phantom:   Default::default(),
+        statements: Vec::new(),
+    };
+
+    // and the statements about the unrevealed attributes, if any.
+    let statements: Vec<AttributeStatement<ExampleAttribute>> = match cc.statements {
+        Some(ref file) => match read_json_from_file(file) {
+            Ok(statements) => statements,
+            Err(x) => {
+                eprintln!("Could not read attribute statements because {}", x);
+                return;
+            }
+        },
+        None => Vec::new(),
     };
 
     // finally we also read the credential holder information with secret keys
@@ -841,8 +859,10 @@ fn handle_create_credential(cc: CreateCredential) {
         &id_use_data,
         x,
         policy,
+        &statements,
         &acc_data,
         &new_or_existing,
+        None,
     );
 
     let (cdi, commitments_randomness) = match cdi {
@@ -177,6 +177,7 @@ fn main() {
             tree
         },
         _phantom: Default::default(),
+        statements: Vec::new(),
     };
     {
         // output testdata.bin for basic verification checking.
@@ -196,8 +197,10 @@ fn main() {
             &id_object_use_data,
             53,
             policy.clone(),
+            &[],
             &acc_data,
             &Left(EXPIRY),
+            None,
         )
         .expect("We should have generated valid data.");
 
@@ -220,8 +223,10 @@ fn main() {
             &id_object_use_data,
             53,
             policy.clone(),
+            &[],
             &acc_data_2,
             &Right(addr),
+            None,
         )
         .expect("We should have generated valid data.");
 
@@ -323,6 +328,60 @@ fn main() {
         } else {
             println!("Output binary file icdi.bin.");
         }
+
+        // Output a credential with attribute statements in its policy, to test
+        // compatibility of their serialization between haskell and rust.
+        // Attribute 0 has value 55 and is not revealed.
+        let statements = [
+            AttributeStatement::InRange {
+                attribute_tag: AttributeTag::from(0u8),
+                lower:         AttributeKind::from(50),
+                upper:         AttributeKind::from(60),
+            },
+            AttributeStatement::InSet {
+                attribute_tag: AttributeTag::from(0u8),
+                set:           vec![AttributeKind::from(54), AttributeKind::from(55)],
+            },
+            AttributeStatement::NotInSet {
+                attribute_tag: AttributeTag::from(0u8),
+                set:           vec![AttributeKind::from(31), AttributeKind::from(56)],
+            },
+        ];
+        let (cdi_3, _) = create_credential(
+            context,
+            &id_object,
+            &id_object_use_data,
+            54,
+            policy.clone(),
+            &statements,
+            &acc_data_2,
+            &Left(EXPIRY),
+            None,
+        )
+        .expect("We should have generated valid data.");
+
+        let ver_cdi_3 = Versioned::new(VERSION_0, cdi_3);
+        if let Err(err) = write_json_to_file("cdi-statements.json", &ver_cdi_3) {
+            eprintln!(
+                "Could not output JSON file cdi-statements.json, because {}.",
+                err
+            );
+        } else {
+            println!("Output cdi-statements.json.");
+        }
+
+        let cdi_statements_file = File::create("cdi-statements.bin");
+        if let Err(err) = cdi_statements_file
+            .unwrap()
+            .write_all(&to_bytes(&ver_cdi_3))
+        {
+            eprintln!(
+                "Could not output binary file cdi-statements.bin, because {}.",
+                err
+            );
+        } else {
+            println!("Output binary file cdi-statements.bin.");
+        }
     }
 
     // generate account credentials, parametrized
@@ -345,8 +404,10 @@ fn main() {
             &id_object_use_data,
             acc_num,
             policy.clone(),
+            &[],
             &acc_data,
             maybe_addr,
+            None,
         )
         .expect("We should have generated valid data.");
         let acc_addr = AccountAddress::new(&cdi.values.cred_id);
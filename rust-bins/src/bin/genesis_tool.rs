@@ -213,6 +213,7 @@ fn main() -> std::io::Result<()> {
             created_at: attributes.created_at,
             policy_vec: BTreeMap::<_, ExampleAttribute>::new(),
             _phantom:   Default::default(),
+            statements: Vec::new(),
         };
 
         let ar_data = {
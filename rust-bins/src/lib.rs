@@ -187,3 +187,24 @@ pub fn ask_for_password_confirm(
         return Ok(pass);
     }
 }
+
+/// Write the data as JSON to the given file, encrypted with a password if
+/// the user supplies one.
+pub fn output_possibly_encrypted<X: SerdeSerialize>(
+    fname: &Path,
+    data: &X,
+) -> Result<(), io::Error> {
+    let pass = ask_for_password_confirm(
+        "Enter password to encrypt credentials (leave empty for no encryption): ",
+        true,
+    )?;
+    if pass.is_empty() {
+        println!("No password supplied, so output will not be encrypted.");
+        write_json_to_file(fname, data)
+    } else {
+        let plaintext = serde_json::to_vec(data).expect("JSON serialization does not fail.");
+        let encrypted =
+            crypto_common::encryption::encrypt(&pass.into(), &plaintext, &mut rand::thread_rng());
+        write_json_to_file(fname, &encrypted)
+    }
+}
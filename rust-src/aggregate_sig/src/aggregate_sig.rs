@@ -16,16 +16,29 @@ pub const SECRET_KEY_SIZE: usize = 32;
 /// Size of the aggregate signature in bytes.
 pub const SIGNATURE_SIZE: usize = 48;
 
+/// Domain separation tag of the basic scheme of the standard BLS signature
+/// ciphersuite with signatures in G1, see
+/// <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-bls-signature-05#section-4.2.1>.
+/// Messages are hashed to G1 as specified in RFC 9380, so signatures produced
+/// with [SecretKey::sign_with_dst] and this tag can be checked by other
+/// implementations of the standard.
+pub const BLS_SIG_BASIC_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+
 /// A Secret Key is a scalar in the scalarfield of the pairing.
 ///
 /// EQUALITY IS NOT CONSTANT TIME!!! Do not use in production
 /// the trait is implemented only for testing purposes
 #[derive(Debug, Eq, Serialize)]
-pub struct SecretKey<P: Pairing>(P::ScalarField);
+pub struct SecretKey<P: Pairing>(pub(crate) P::ScalarField);
 
 impl<P: Pairing> SecretKey<P> {
     pub fn generate<R: Rng>(rng: &mut R) -> SecretKey<P> { SecretKey(P::generate_scalar(rng)) }
 
+    /// Construct a secret key from a scalar, for example one derived with
+    /// `keygen_bls`, which implements the key generation of the standard BLS
+    /// signature ciphersuite.
+    pub fn from_scalar(scalar: P::ScalarField) -> SecretKey<P> { SecretKey(scalar) }
+
     /// Sign a message using the SecretKey
     pub fn sign(&self, m: &[u8]) -> Signature<P> {
         let g1_hash = P::G1::hash_to_group(m);
@@ -33,6 +46,14 @@ impl<P: Pairing> SecretKey<P> {
         Signature(signature)
     }
 
+    /// Sign a message using the SecretKey, hashing the message to G1 with the
+    /// given domain separation tag. Using [BLS_SIG_BASIC_DST] gives signatures
+    /// of the standard BLS signature ciphersuite.
+    pub fn sign_with_dst(&self, m: &[u8], dst: &[u8]) -> Signature<P> {
+        let g1_hash = P::G1::hash_to_group_with_dst(m, dst);
+        Signature(g1_hash.mul_by_scalar(&self.0))
+    }
+
     /// Prove knowledge of the secret key with respect to the challenge given
     /// via the random oracle.
     pub fn prove<R: Rng>(&self, csprng: &mut R, ro: &mut RandomOracle) -> Proof<P> {
@@ -62,7 +83,7 @@ impl<P: Pairing> PartialEq for SecretKey<P> {
 
 /// A Public Key is a point on the second curve of the pairing
 #[derive(Debug, Eq, Serialize, SerdeBase16Serialize)]
-pub struct PublicKey<P: Pairing>(P::G2);
+pub struct PublicKey<P: Pairing>(pub(crate) P::G2);
 
 impl<P: Pairing> PublicKey<P> {
     /// Derived from a secret key sk by exponentiating the generator of G2 with
@@ -86,6 +107,14 @@ impl<P: Pairing> PublicKey<P> {
         P::check_pairing_eq(&signature.0, &P::G2::one_point(), &g1_hash, &self.0)
     }
 
+    /// Verifies a single message and signature pair, where the message is
+    /// hashed to G1 with the given domain separation tag. This is the dual of
+    /// [SecretKey::sign_with_dst].
+    pub fn verify_with_dst(&self, m: &[u8], dst: &[u8], signature: Signature<P>) -> bool {
+        let g1_hash = P::G1::hash_to_group_with_dst(m, dst);
+        P::check_pairing_eq(&signature.0, &P::G2::one_point(), &g1_hash, &self.0)
+    }
+
     /// Check proof of knowledge of the secret key with respect to the public
     /// key and the challenge which is given in terms of a random oracle.
     pub fn check_proof(&self, ro: &mut RandomOracle, proof: &Proof<P>) -> bool {
@@ -108,7 +137,7 @@ impl<P: Pairing> PartialEq for PublicKey<P> {
 }
 
 #[derive(Debug, Eq, Serialize)]
-pub struct Signature<P: Pairing>(P::G1);
+pub struct Signature<P: Pairing>(pub(crate) P::G1);
 
 impl<P: Pairing> Signature<P> {
     /// Aggregates this signatures with the given signature.
@@ -154,10 +183,21 @@ pub fn verify_aggregate_sig<P: Pairing>(
         return false;
     }
 
+    check_aggregate(m_pk_pairs, signature, P::G1::hash_to_group)
+}
+
+/// Checks `pairing(sig, g_2) == product_{i=0}^n ( pairing(hash(m_i), PK_i) )`
+/// where `hash` is the given function for hashing messages to G1. The checks on
+/// messages and keys that are needed for security are left to the caller.
+pub(crate) fn check_aggregate<P: Pairing, H: Fn(&[u8]) -> P::G1 + Sync>(
+    m_pk_pairs: &[(&[u8], PublicKey<P>)],
+    signature: Signature<P>,
+    hash: H,
+) -> bool {
     let product = m_pk_pairs
         .par_iter()
         .fold(<P::TargetField as Field>::one, |prod, (m, pk)| {
-            let g1_hash = P::G1::hash_to_group(m);
+            let g1_hash = hash(m);
             let paired = P::pair(&g1_hash, &pk.0);
             let mut p = prod;
             p.mul_assign(&paired);
@@ -210,7 +250,7 @@ pub fn verify_aggregate_sig_trusted_keys<P: Pairing>(
 // This is not very efficient - the sorting algorithm can exit as soon as it
 // encounters an equality and report that a duplicate indeed exists.
 // Consider building hashmap or Btree and exit as soon as a duplicate is seen
-fn has_duplicates<T>(messages: &[(&[u8], T)]) -> bool {
+pub(crate) fn has_duplicates<T>(messages: &[(&[u8], T)]) -> bool {
     let mut message_hashes: Vec<_> = messages.iter().map(|x| hash_message(x.0)).collect();
     message_hashes.sort_unstable();
     for i in 1..message_hashes.len() {
@@ -272,6 +312,53 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_sign_and_verify_with_dst() {
+        let mut rng: StdRng = SeedableRng::from_rng(thread_rng()).unwrap();
+
+        for _ in 0..TEST_ITERATIONS {
+            let sk = SecretKey::<Bls12>::generate(&mut rng);
+            let pk = PublicKey::from_secret(&sk);
+            let m = rng.gen::<[u8; 32]>();
+            let signature = sk.sign_with_dst(&m, BLS_SIG_BASIC_DST);
+            assert!(pk.verify_with_dst(&m, BLS_SIG_BASIC_DST, signature));
+            // The signature is bound to the domain separation tag.
+            assert!(!pk.verify(&m, signature));
+            assert!(!pk.verify_with_dst(&m, b"OTHER-DST", signature));
+        }
+    }
+
+    // Signatures of the basic scheme must agree with other implementations of
+    // the standard ciphersuite.
+    #[test]
+    fn test_sign_with_dst_known_answer() {
+        let sk: SecretKey<Bls12> = base16_decode_string(
+            "263dbd792f5b1be47ed85f8938c0f29586af0d3ac7b977f21c278fe1462040e3",
+        )
+        .expect("Valid secret key.");
+        let pk = PublicKey::from_secret(&sk);
+        assert_eq!(
+            base16_encode_string(&pk),
+            "ac400b70f6f8cd35648f5c126cce5417f3be4d8eefbd42ceb4286a14df7e03135313fe5845e3a575faab3\
+             e8b949d248814856c22d8cdb2967c720e963eedc999e738373b14172f06fc915769d3cc5ab7ae0a1b9c38\
+             f48b5585fb09d4bd2733bb"
+        );
+        let sig = sk.sign_with_dst(b"", BLS_SIG_BASIC_DST);
+        assert_eq!(
+            base16_encode_string(&sig),
+            "a822086b25eddc01d21b0f29c84779afdd736e29bac81970035edb1a07a13aa53b4704ab7abc0d9f90e8a\
+             ee19120affb"
+        );
+        assert!(pk.verify_with_dst(b"", BLS_SIG_BASIC_DST, sig));
+        let sig = sk.sign_with_dst(b"abc", BLS_SIG_BASIC_DST);
+        assert_eq!(
+            base16_encode_string(&sig),
+            "894868b11153b0352e9d3cea96a5b035a8780e4044d5538941ad27e40eb731b8a4a8fc8c4b36d67cd26f4\
+             e679ca914d6"
+        );
+        assert!(pk.verify_with_dst(b"abc", BLS_SIG_BASIC_DST, sig));
+    }
+
     macro_rules! aggregate_sigs {
         ($messages:expr, $sks:expr) => {{
             let mut sig = $sks[0].sign(&$messages[0]);
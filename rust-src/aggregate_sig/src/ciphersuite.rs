@@ -0,0 +1,378 @@
+//! The BLS signature schemes of
+//! <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-bls-signature-05>,
+//! in the minimal-signature-size variant, i.e., with signatures in G1 and
+//! public keys in G2. This is the same choice of groups as in the rest of this
+//! crate, so keys can be used with both. In particular, secret keys derived
+//! with `keygen_bls`, which implements the key generation of the standard, can
+//! be used via [SecretKey::from_scalar].
+//!
+//! The domain separation tags are those of the BLS12-381 ciphersuites, and
+//! messages are hashed to G1 as specified in RFC 9380.
+use crate::aggregate_sig::*;
+use crypto_common::*;
+use crypto_common_derive::*;
+use curve_arithmetic::{Curve, Pairing};
+
+/// Domain separation tag for signatures in the message augmentation scheme.
+pub const BLS_SIG_AUG_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_AUG_";
+/// Domain separation tag for signatures in the proof of possession scheme.
+pub const BLS_SIG_POP_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_POP_";
+/// Domain separation tag for proofs of possession.
+pub const BLS_POP_DST: &[u8] = b"BLS_POP_BLS12381G1_XMD:SHA-256_SSWU_RO_POP_";
+
+/// The three signature schemes of the standard. They differ in how they
+/// prevent rogue key attacks on aggregate signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// Aggregate signatures are only accepted on distinct messages.
+    Basic,
+    /// The public key of the signer is prepended to the message before
+    /// signing.
+    MessageAugmentation,
+    /// Signers must prove possession of their secret key, see [pop_prove].
+    /// This also allows for [fast_aggregate_verify].
+    ProofOfPossession,
+}
+
+impl Scheme {
+    /// The domain separation tag used when hashing messages in this scheme.
+    pub fn dst(self) -> &'static [u8] {
+        match self {
+            Scheme::Basic => BLS_SIG_BASIC_DST,
+            Scheme::MessageAugmentation => BLS_SIG_AUG_DST,
+            Scheme::ProofOfPossession => BLS_SIG_POP_DST,
+        }
+    }
+
+    /// Sign a message with the given secret key.
+    pub fn sign<P: Pairing>(self, sk: &SecretKey<P>, m: &[u8]) -> Signature<P> {
+        match self {
+            Scheme::MessageAugmentation => {
+                let pk = PublicKey::from_secret(sk);
+                sk.sign_with_dst(&augment(&pk, m), self.dst())
+            }
+            Scheme::Basic | Scheme::ProofOfPossession => sk.sign_with_dst(m, self.dst()),
+        }
+    }
+
+    /// Verify a signature on a single message. This fails if the public key
+    /// is not valid according to [key_validate].
+    pub fn verify<P: Pairing>(self, pk: &PublicKey<P>, m: &[u8], signature: Signature<P>) -> bool {
+        if !key_validate(pk) {
+            return false;
+        }
+        match self {
+            Scheme::MessageAugmentation => {
+                pk.verify_with_dst(&augment(pk, m), self.dst(), signature)
+            }
+            Scheme::Basic | Scheme::ProofOfPossession => {
+                pk.verify_with_dst(m, self.dst(), signature)
+            }
+        }
+    }
+
+    /// Verify an aggregate signature on pairs `(m_i, PK_i)`. This fails if
+    /// the list is empty or if any of the public keys is not valid. In the
+    /// basic scheme it also fails if the messages are not distinct.
+    pub fn aggregate_verify<P: Pairing>(
+        self,
+        m_pk_pairs: &[(&[u8], PublicKey<P>)],
+        signature: Signature<P>,
+    ) -> bool {
+        if m_pk_pairs.is_empty() || !m_pk_pairs.iter().all(|(_, pk)| key_validate(pk)) {
+            return false;
+        }
+        let dst = self.dst();
+        let hash = |m: &[u8]| P::G1::hash_to_group_with_dst(m, dst);
+        match self {
+            Scheme::Basic => {
+                !has_duplicates(m_pk_pairs) && check_aggregate(m_pk_pairs, signature, hash)
+            }
+            Scheme::MessageAugmentation => {
+                let augmented: Vec<(Vec<u8>, PublicKey<P>)> = m_pk_pairs
+                    .iter()
+                    .map(|(m, pk)| (augment(pk, m), *pk))
+                    .collect();
+                let pairs: Vec<(&[u8], PublicKey<P>)> =
+                    augmented.iter().map(|(m, pk)| (&m[..], *pk)).collect();
+                check_aggregate(&pairs, signature, hash)
+            }
+            Scheme::ProofOfPossession => check_aggregate(m_pk_pairs, signature, hash),
+        }
+    }
+}
+
+/// The KeyValidate procedure of the standard. Public keys are only ever
+/// constructed from secret keys or by deserialization, which checks that the
+/// point is in G2, so it only remains to check that the key is not the
+/// identity.
+pub fn key_validate<P: Pairing>(pk: &PublicKey<P>) -> bool { !pk.0.is_zero_point() }
+
+/// A proof of possession of a secret key as defined in the standard, i.e., a
+/// signature on the public key with a dedicated domain separation tag.
+#[derive(Debug, Eq, Serialize)]
+pub struct ProofOfPossession<P: Pairing>(P::G1);
+
+impl<P: Pairing> Clone for ProofOfPossession<P> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<P: Pairing> Copy for ProofOfPossession<P> {}
+
+impl<P: Pairing> PartialEq for ProofOfPossession<P> {
+    fn eq(&self, other: &Self) -> bool { self.0 == other.0 }
+}
+
+/// Produce a proof of possession of the given secret key.
+pub fn pop_prove<P: Pairing>(sk: &SecretKey<P>) -> ProofOfPossession<P> {
+    let pk = PublicKey::from_secret(sk);
+    let g1_hash = P::G1::hash_to_group_with_dst(&to_bytes(&pk), BLS_POP_DST);
+    ProofOfPossession(g1_hash.mul_by_scalar(&sk.0))
+}
+
+/// Check a proof of possession of the secret key of the given public key.
+pub fn pop_verify<P: Pairing>(pk: &PublicKey<P>, proof: &ProofOfPossession<P>) -> bool {
+    if !key_validate(pk) {
+        return false;
+    }
+    let g1_hash = P::G1::hash_to_group_with_dst(&to_bytes(pk), BLS_POP_DST);
+    P::check_pairing_eq(&proof.0, &P::G2::one_point(), &g1_hash, &pk.0)
+}
+
+/// Verify an aggregate signature on a single message in the proof of
+/// possession scheme. This is only secure if possession of the secret keys of
+/// all the public keys has been checked, either with [pop_verify] or with
+/// [PublicKey::check_proof]. Verification against an empty list of keys
+/// fails.
+pub fn fast_aggregate_verify<P: Pairing>(
+    pks: &[PublicKey<P>],
+    m: &[u8],
+    signature: Signature<P>,
+) -> bool {
+    if pks.is_empty() {
+        return false;
+    }
+    let sum = pks
+        .iter()
+        .fold(P::G2::zero_point(), |s, pk| s.plus_point(&pk.0));
+    Scheme::ProofOfPossession.verify(&PublicKey(sum), m, signature)
+}
+
+/// The message that is signed in the message augmentation scheme, i.e., the
+/// serialized public key followed by the message.
+fn augment<P: Pairing>(pk: &PublicKey<P>, m: &[u8]) -> Vec<u8> {
+    let mut bytes = to_bytes(pk);
+    bytes.extend_from_slice(m);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+    use keygen_bls::keygen_bls;
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+    const SCHEMES: [Scheme; 3] = [
+        Scheme::Basic,
+        Scheme::MessageAugmentation,
+        Scheme::ProofOfPossession,
+    ];
+
+    // The key derived by keygen_bls from the first seed in its test vectors.
+    fn test_key() -> SecretKey<Bls12> {
+        let ikm = [
+            0x09, 0xe7, 0x4a, 0xd3, 0xea, 0xd3, 0x73, 0x43, 0x93, 0x88, 0xbf, 0x7c, 0xfb, 0x52,
+            0xb1, 0x51, 0xc4, 0x50, 0x63, 0x2e, 0x67, 0xf3, 0xc8, 0x4e, 0x6e, 0xd7, 0x62, 0xbc,
+            0x09, 0x28, 0xd5, 0xeb,
+        ];
+        SecretKey::from_scalar(keygen_bls(&ikm, b"").expect("Key generation should succeed."))
+    }
+
+    // Known answers computed with the minimal-signature-size variant of blst
+    // 0.3.11 (`blst::min_sig`), an implementation independent of this crate,
+    // with the same ciphersuite IDs. The secret key is generated from the IKM
+    // of [test_key] with `SecretKey::key_gen_v4_5` and the salt
+    // "BLS-SIG-KEYGEN-SALT-", i.e., the KeyGen of draft 04 of the standard.
+    // The signatures in the message augmentation scheme are computed with the
+    // compressed public key as `aug`, and the proof of possession by signing
+    // the compressed public key with the tag `BLS_POP_DST`.
+    #[test]
+    fn test_known_answers() {
+        let sk = test_key();
+        assert_eq!(
+            base16_encode_string(&sk),
+            "57cb278c9deb055f12cc807c3068f2ce804654a54de54801f0cb6a774c211de2"
+        );
+        let pk = PublicKey::from_secret(&sk);
+        assert_eq!(
+            base16_encode_string(&pk),
+            "9393488163f304aa02b0d9220af7cf09a081860dfd3febdde87a031146bfb20af3785da9aa7d9f412e029\
+             b5ff0848c181119ca32cbebe030d5b887d1f2d235abfc934b82b283c3e295d597e5635c64935cf26044b0\
+             d5be42222350252b0af109"
+        );
+        let expected: [(Scheme, &[u8], &str); 6] = [
+            (
+                Scheme::Basic,
+                b"",
+                "8fb396d23ed8014a333d0271c735f3da73448625dda63620d590e314899ac7c1a7f31cc74b43355d9\
+                 ff0170d2fe1f4c9",
+            ),
+            (
+                Scheme::Basic,
+                b"abc",
+                "8be843f0b78509f6018b628f1713a6b3546ec9d5eb572bf90dce94f698473a8b20090cfd543de6ff6\
+                 2c66437190f1107",
+            ),
+            (
+                Scheme::MessageAugmentation,
+                b"",
+                "801140610af794317de6b77cf7a04776ba3ee805762b98086a988ccc4d2795aba5961e17b315aedbf\
+                 a4c2ed508c5f2bd",
+            ),
+            (
+                Scheme::MessageAugmentation,
+                b"abc",
+                "b25199bf6034431b33011d15d992a436719a24700c45939c858ab791c3cfa211c0ab3ed63d276a795\
+                 1734f0cf2d55958",
+            ),
+            (
+                Scheme::ProofOfPossession,
+                b"",
+                "a9d8e471a52cdbc78af18f629cb7449238400e22f85ab5fc0448b1ab8119f1deb31a6b752bd0d3dca\
+                 2643233f4adb54c",
+            ),
+            (
+                Scheme::ProofOfPossession,
+                b"abc",
+                "a705bbf1a10e4922c23c3bafa3f02941a15590dbd4c510ceb154d198251118454fa87875bc3e7c544\
+                 ff455b522bc6640",
+            ),
+        ];
+        for (scheme, m, sig_hex) in expected.iter() {
+            let sig = scheme.sign(&sk, m);
+            assert_eq!(base16_encode_string(&sig), *sig_hex, "{:?}", scheme);
+            let sig: Signature<Bls12> =
+                base16_decode_string(sig_hex).expect("Valid signature encoding.");
+            assert!(scheme.verify(&pk, m, sig), "{:?}", scheme);
+        }
+        let proof = pop_prove(&sk);
+        assert_eq!(
+            base16_encode_string(&proof),
+            "82297a04460642cad5c4b6cb4aba5b50c4d537457378c124eff37a846d0fd116d7302a7b75ec6c38d595c\
+             d5e45fd4cf6"
+        );
+        assert!(pop_verify(&pk, &proof));
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let mut rng: StdRng = SeedableRng::from_rng(thread_rng()).unwrap();
+        for scheme in SCHEMES.iter() {
+            let sk = SecretKey::<Bls12>::generate(&mut rng);
+            let pk = PublicKey::from_secret(&sk);
+            let m = rng.gen::<[u8; 32]>();
+            let sig = scheme.sign(&sk, &m);
+            assert!(scheme.verify(&pk, &m, sig));
+            // Signatures of one scheme are not valid in the others.
+            for other in SCHEMES.iter().filter(|other| *other != scheme) {
+                assert!(!other.verify(&pk, &m, sig));
+            }
+            let m2 = rng.gen::<[u8; 32]>();
+            assert!(!scheme.verify(&pk, &m2, sig));
+            let pk2 = PublicKey::from_secret(&SecretKey::<Bls12>::generate(&mut rng));
+            assert!(!scheme.verify(&pk2, &m, sig));
+        }
+    }
+
+    #[test]
+    fn test_aggregate_verify() {
+        let mut rng: StdRng = SeedableRng::from_rng(thread_rng()).unwrap();
+        for scheme in SCHEMES.iter() {
+            let sks: Vec<SecretKey<Bls12>> =
+                (0..10).map(|_| SecretKey::generate(&mut rng)).collect();
+            let mut ms: Vec<[u8; 32]> = (0..10).map(|_| rng.gen::<[u8; 32]>()).collect();
+            let pks: Vec<PublicKey<Bls12>> = sks.iter().map(PublicKey::from_secret).collect();
+            let sig = sks
+                .iter()
+                .zip(ms.iter())
+                .fold(Signature::empty(), |acc, (sk, m)| {
+                    acc.aggregate(scheme.sign(sk, m))
+                });
+            let pairs: Vec<(&[u8], PublicKey<Bls12>)> = ms
+                .iter()
+                .zip(pks.iter())
+                .map(|(m, pk)| (&m[..], *pk))
+                .collect();
+            assert!(scheme.aggregate_verify(&pairs, sig));
+            assert!(!scheme.aggregate_verify(&pairs[1..], sig));
+            assert!(!scheme.aggregate_verify(&[], sig));
+
+            // Repeated messages are only rejected in the basic scheme.
+            ms[1] = ms[0];
+            let sig = sks
+                .iter()
+                .zip(ms.iter())
+                .fold(Signature::empty(), |acc, (sk, m)| {
+                    acc.aggregate(scheme.sign(sk, m))
+                });
+            let pairs: Vec<(&[u8], PublicKey<Bls12>)> = ms
+                .iter()
+                .zip(pks.iter())
+                .map(|(m, pk)| (&m[..], *pk))
+                .collect();
+            assert_eq!(
+                scheme.aggregate_verify(&pairs, sig),
+                *scheme != Scheme::Basic
+            );
+        }
+    }
+
+    #[test]
+    fn test_fast_aggregate_verify() {
+        let mut rng: StdRng = SeedableRng::from_rng(thread_rng()).unwrap();
+        let sks: Vec<SecretKey<Bls12>> = (0..20).map(|_| SecretKey::generate(&mut rng)).collect();
+        let pks: Vec<PublicKey<Bls12>> = sks.iter().map(PublicKey::from_secret).collect();
+        for (sk, pk) in sks.iter().zip(pks.iter()) {
+            assert!(pop_verify(pk, &pop_prove(sk)));
+        }
+        let m = rng.gen::<[u8; 32]>();
+        let sig = sks.iter().fold(Signature::empty(), |acc, sk| {
+            acc.aggregate(Scheme::ProofOfPossession.sign(sk, &m))
+        });
+        assert!(fast_aggregate_verify(&pks, &m, sig));
+        assert!(!fast_aggregate_verify(&pks[1..], &m, sig));
+        assert!(!fast_aggregate_verify(&[], &m, sig));
+        let m2 = rng.gen::<[u8; 32]>();
+        assert!(!fast_aggregate_verify(&pks, &m2, sig));
+        // A proof of possession is not valid for another key.
+        assert!(!pop_verify(&pks[1], &pop_prove(&sks[0])));
+    }
+
+    #[test]
+    fn test_identity_key_is_rejected() {
+        let sk = SecretKey::<Bls12>::from_scalar(Fr::zero());
+        let pk = PublicKey::from_secret(&sk);
+        for scheme in SCHEMES.iter() {
+            // The signature is the identity, and the pairing equation holds.
+            let sig = scheme.sign(&sk, b"message");
+            assert!(!scheme.verify(&pk, b"message", sig));
+            assert!(!scheme.aggregate_verify(&[(&b"message"[..], pk)], sig));
+        }
+        assert!(!pop_verify(&pk, &pop_prove(&sk)));
+    }
+
+    #[test]
+    fn test_pop_serialization() {
+        let mut rng: StdRng = SeedableRng::from_rng(thread_rng()).unwrap();
+        let sk = SecretKey::<Bls12>::generate(&mut rng);
+        let proof = pop_prove(&sk);
+        let bytes = to_bytes(&proof);
+        assert_eq!(bytes.len(), SIGNATURE_SIZE);
+        assert_eq!(
+            serialize_deserialize(&proof).expect("Deserialization should succeed."),
+            proof
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! Implementation of aggregate signatures specified in <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-bls-signature-04>
+//! The standard ciphersuites are in the [ciphersuite] module.
 mod aggregate_sig;
+pub mod ciphersuite;
 mod ffi;
 
 pub use crate::aggregate_sig::*;
@@ -39,7 +39,7 @@ pub fn prove_verify_benchmarks(c: &mut Criterion) {
     }
     let B = SomeCurve::generate(rng);
     let B_tilde = SomeCurve::generate(rng);
-    let gens = Generators { G_H };
+    let gens = Generators::new(G_H);
     let keys = CommitmentKey { g: B, h: B_tilde };
 
     // Some numbers in [0, 2^n):
@@ -94,6 +94,9 @@ pub fn prove_verify_benchmarks(c: &mut Criterion) {
         &randomness,
     );
     let proof = proof.unwrap();
+    let commitments_b = commitments.clone();
+    let proof_b = proof.clone();
+    let gens_b = gens.clone();
     c.bench_function("Verifier.", move |b| {
         b.iter(|| {
             let mut transcript = RandomOracle::empty();
@@ -102,6 +105,22 @@ pub fn prove_verify_benchmarks(c: &mut Criterion) {
             );
         })
     });
+
+    // Verify 10 copies of the same proof as a batch.
+    c.bench_function("Verifier batch of 10.", move |b| {
+        let rng = &mut thread_rng();
+        b.iter(|| {
+            let instances: Vec<_> = (0..10)
+                .map(|_| RangeProofInstance {
+                    transcript: RandomOracle::empty(),
+                    n,
+                    commitments: &commitments_b,
+                    proof: &proof_b,
+                })
+                .collect();
+            assert!(verify_batch(rng, &instances, &gens_b, &keys).is_ok());
+        })
+    });
 }
 
 #[allow(non_snake_case)]
@@ -1,5 +1,7 @@
 //! Implementation of bulletproofs in the scope needed by Concordium.
 //!
-//! In particular this means range proofs for 64-bit unsigned integers.
+//! In particular this means range proofs for 64-bit unsigned integers, and
+//! proofs that committed values satisfy an arithmetic circuit.
 pub mod inner_product_proof;
+pub mod r1cs;
 pub mod range_proof;
@@ -0,0 +1,1030 @@
+//! Implementation of proofs for arithmetic circuits, i.e., rank-1 constraint
+//! systems, following Section 5 of the bulletproofs paper
+//! <https://eprint.iacr.org/2017/1066.pdf>.
+//!
+//! A constraint system consists of
+//! - values the prover has committed to using Pedersen commitments,
+//! - multiplication gates, each with a left input, a right input, and an output
+//!   which is the product of the inputs,
+//! - linear constraints, each requiring that a linear combination of the
+//!   committed values and the inputs and outputs of the gates is zero.
+//!
+//! Gadgets describing a statement are written once, generically over the
+//! [ConstraintSystem] trait, and then used by both the [Prover] and the
+//! [Verifier]. The prover additionally supplies the values of all the
+//! variables.
+use crate::{inner_product_proof::*, range_proof::Generators};
+use crypto_common::*;
+use crypto_common_derive::*;
+use curve_arithmetic::{multiexp, Curve};
+use ff::Field;
+use pedersen_scheme::*;
+use rand::*;
+use random_oracle::RandomOracle;
+use std::{
+    iter::once,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// A variable of a constraint system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variable {
+    /// A value the prover has committed to. The index is the position of the
+    /// commitment in the order the commitments were added.
+    Committed(usize),
+    /// Left input of the multiplication gate with the given index.
+    MultiplierLeft(usize),
+    /// Right input of the multiplication gate with the given index.
+    MultiplierRight(usize),
+    /// Output of the multiplication gate with the given index.
+    MultiplierOutput(usize),
+    /// The constant 1.
+    One,
+}
+
+/// A linear combination of variables with coefficients in the field F.
+#[derive(Clone, Debug)]
+pub struct LinearCombination<F> {
+    terms: Vec<(Variable, F)>,
+}
+
+impl<F: Field> LinearCombination<F> {
+    /// The empty linear combination, which is always zero.
+    pub fn zero() -> Self { Self { terms: Vec::new() } }
+
+    /// The linear combination that is the given constant.
+    pub fn constant(c: F) -> Self {
+        Self {
+            terms: vec![(Variable::One, c)],
+        }
+    }
+
+    /// The linear combination consisting of a single variable scaled by the
+    /// given coefficient.
+    pub fn term(var: Variable, coeff: F) -> Self {
+        Self {
+            terms: vec![(var, coeff)],
+        }
+    }
+
+    /// The terms of the linear combination. A variable may occur in more than
+    /// one term.
+    pub fn terms(&self) -> &[(Variable, F)] { &self.terms }
+}
+
+impl<F: Field> From<Variable> for LinearCombination<F> {
+    fn from(var: Variable) -> Self { Self::term(var, F::one()) }
+}
+
+impl<F: Field> Add for LinearCombination<F> {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self.terms.extend(other.terms);
+        self
+    }
+}
+
+impl<F: Field> Neg for LinearCombination<F> {
+    type Output = Self;
+
+    fn neg(mut self) -> Self {
+        for (_, coeff) in self.terms.iter_mut() {
+            coeff.negate();
+        }
+        self
+    }
+}
+
+impl<F: Field> Sub for LinearCombination<F> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self { self + (-other) }
+}
+
+impl<F: Field> Mul<F> for LinearCombination<F> {
+    type Output = Self;
+
+    fn mul(mut self, scalar: F) -> Self {
+        for (_, coeff) in self.terms.iter_mut() {
+            coeff.mul_assign(&scalar);
+        }
+        self
+    }
+}
+
+/// Reasons why proving or verifying a statement about a constraint system can
+/// fail.
+#[derive(Debug, PartialEq)]
+pub enum R1CSError {
+    /// The prover did not supply the value of a variable.
+    MissingAssignment,
+    /// There are fewer generators than the number of multiplication gates,
+    /// rounded up to the nearest power of two.
+    NotEnoughGenerators,
+    /// Choice of randomness led to a division by zero.
+    DivisionError,
+    /// The proof is not valid for the constraint system.
+    VerificationError,
+}
+
+/// The interface for building constraint systems that is shared by the prover
+/// and the verifier.
+///
+/// Variables passed to the methods must have been allocated by the same
+/// constraint system, otherwise the methods may panic.
+pub trait ConstraintSystem<F: Field> {
+    /// Allocate a multiplication gate and constrain its inputs to be equal to
+    /// the given linear combinations. The variables for the left input, the
+    /// right input and the output are returned, in that order.
+    fn multiply(
+        &mut self,
+        left: LinearCombination<F>,
+        right: LinearCombination<F>,
+    ) -> (Variable, Variable, Variable);
+
+    /// Allocate a multiplication gate without constraining its inputs. The
+    /// prover must supply the values of the inputs, the verifier supplies
+    /// `None`.
+    fn allocate_multiplier(
+        &mut self,
+        input_assignments: Option<(F, F)>,
+    ) -> Result<(Variable, Variable, Variable), R1CSError>;
+
+    /// Require that the given linear combination is zero.
+    fn constrain(&mut self, lc: LinearCombination<F>);
+
+    /// The number of multiplication gates allocated so far.
+    fn multipliers_len(&self) -> usize;
+}
+
+/// A proof that the values committed to satisfy a constraint system.
+#[derive(Clone, Serialize, SerdeBase16Serialize, Debug, PartialEq, Eq)]
+#[allow(non_snake_case)]
+pub struct R1CSProof<C: Curve> {
+    /// Commitment to the inputs of the multiplication gates.
+    A_I:          C,
+    /// Commitment to the outputs of the multiplication gates.
+    A_O:          C,
+    /// Commitment to the blinding factors.
+    S:            C,
+    T_1:          C,
+    T_3:          C,
+    T_4:          C,
+    T_5:          C,
+    T_6:          C,
+    t_x:          C::Scalar,
+    t_x_blinding: C::Scalar,
+    e_blinding:   C::Scalar,
+    ip_proof:     InnerProductProof<C>,
+}
+
+/// Compute the vector (1, x, x^2, ..., x^{n-1}).
+fn powers<F: Field>(x: F, n: usize) -> Vec<F> {
+    let mut xs = Vec::with_capacity(n);
+    let mut x_i = F::one();
+    for _ in 0..n {
+        xs.push(x_i);
+        x_i.mul_assign(&x);
+    }
+    xs
+}
+
+/// The weights of the variables in the linear constraints, combined into a
+/// single constraint using powers of a challenge.
+#[allow(non_snake_case)]
+struct FlattenedConstraints<F> {
+    w_L: Vec<F>,
+    w_R: Vec<F>,
+    w_O: Vec<F>,
+    w_V: Vec<F>,
+    w_c: F,
+}
+
+/// Combine the constraints into a single constraint, by scaling the q'th
+/// constraint by z^{q+1}. With this the constraints are
+/// <w_L, a_L> + <w_R, a_R> + <w_O, a_O> = <w_V, v> + w_c
+/// where n is the number of multiplication gates and m the number of committed
+/// values.
+#[allow(non_snake_case)]
+fn flatten_constraints<F: Field>(
+    constraints: &[LinearCombination<F>],
+    z: &F,
+    n: usize,
+    m: usize,
+) -> FlattenedConstraints<F> {
+    let mut w_L = vec![F::zero(); n];
+    let mut w_R = vec![F::zero(); n];
+    let mut w_O = vec![F::zero(); n];
+    let mut w_V = vec![F::zero(); m];
+    let mut w_c = F::zero();
+    let mut exp_z = *z;
+    for lc in constraints {
+        for (var, coeff) in lc.terms.iter() {
+            let mut c = *coeff;
+            c.mul_assign(&exp_z);
+            match var {
+                Variable::MultiplierLeft(i) => w_L[*i].add_assign(&c),
+                Variable::MultiplierRight(i) => w_R[*i].add_assign(&c),
+                Variable::MultiplierOutput(i) => w_O[*i].add_assign(&c),
+                Variable::Committed(i) => w_V[*i].sub_assign(&c),
+                Variable::One => w_c.sub_assign(&c),
+            }
+        }
+        exp_z.mul_assign(z);
+    }
+    FlattenedConstraints {
+        w_L,
+        w_R,
+        w_O,
+        w_V,
+        w_c,
+    }
+}
+
+/// The prover's side of a constraint system. The prover commits to values,
+/// builds the constraint system using gadgets, and then produces a proof.
+#[allow(non_snake_case)]
+pub struct Prover<'a, C: Curve> {
+    transcript:  &'a mut RandomOracle,
+    /// Commitment key used for the committed values, B and B_tilde.
+    key:         &'a CommitmentKey<C>,
+    constraints: Vec<LinearCombination<C::Scalar>>,
+    a_L:         Vec<C::Scalar>,
+    a_R:         Vec<C::Scalar>,
+    a_O:         Vec<C::Scalar>,
+    v:           Vec<C::Scalar>,
+    v_blinding:  Vec<C::Scalar>,
+}
+
+impl<'a, C: Curve> Prover<'a, C> {
+    /// Start a new constraint system. The transcript must be in the same state
+    /// when the verifier starts its constraint system.
+    pub fn new(key: &'a CommitmentKey<C>, transcript: &'a mut RandomOracle) -> Self {
+        Self {
+            transcript,
+            key,
+            constraints: Vec::new(),
+            a_L: Vec::new(),
+            a_R: Vec::new(),
+            a_O: Vec::new(),
+            v: Vec::new(),
+            v_blinding: Vec::new(),
+        }
+    }
+
+    /// Commit to the value with the given randomness, and add it to the
+    /// constraint system. The commitment is added to the transcript. The
+    /// verifier must add the commitments in the same order.
+    pub fn commit(
+        &mut self,
+        v: &Value<C>,
+        v_blinding: &Randomness<C>,
+    ) -> (Commitment<C>, Variable) {
+        let i = self.v.len();
+        let commitment = self.key.hide(v, v_blinding);
+        self.transcript.append_message(b"V", &commitment);
+        self.v.push(*v.as_ref());
+        self.v_blinding.push(*v_blinding.as_ref());
+        (commitment, Variable::Committed(i))
+    }
+
+    /// Evaluate a linear combination using the values of the variables known
+    /// to the prover.
+    fn eval(&self, lc: &LinearCombination<C::Scalar>) -> C::Scalar {
+        let mut sum = C::Scalar::zero();
+        for (var, coeff) in lc.terms.iter() {
+            let mut value = match var {
+                Variable::Committed(i) => self.v[*i],
+                Variable::MultiplierLeft(i) => self.a_L[*i],
+                Variable::MultiplierRight(i) => self.a_R[*i],
+                Variable::MultiplierOutput(i) => self.a_O[*i],
+                Variable::One => C::Scalar::one(),
+            };
+            value.mul_assign(coeff);
+            sum.add_assign(&value);
+        }
+        sum
+    }
+
+    /// Produce a proof that the committed values satisfy the constraint
+    /// system. The generators must contain at least as many elements as the
+    /// number of multiplication gates, rounded up to the nearest power of two.
+    #[allow(non_snake_case)]
+    #[allow(clippy::many_single_char_names)]
+    pub fn prove<R: Rng>(
+        self,
+        csprng: &mut R,
+        gens: &Generators<C>,
+    ) -> Result<R1CSProof<C>, R1CSError> {
+        let Prover {
+            transcript,
+            key,
+            constraints,
+            a_L,
+            a_R,
+            a_O,
+            v,
+            v_blinding,
+        } = self;
+        let n = a_L.len();
+        let m = v.len();
+        let padded_n = n.next_power_of_two();
+        if gens.G_H.len() < padded_n {
+            return Err(R1CSError::NotEnoughGenerators);
+        }
+        let (G, H): (Vec<_>, Vec<_>) = gens.G_H.iter().take(padded_n).cloned().unzip();
+        let B = key.g;
+        let B_tilde = key.h;
+        transcript.append_message(b"m", &(m as u64));
+        transcript.append_message(b"n", &(n as u64));
+
+        let i_blinding = C::generate_scalar(csprng);
+        let o_blinding = C::generate_scalar(csprng);
+        let s_blinding = C::generate_scalar(csprng);
+        let mut s_L = Vec::with_capacity(n);
+        let mut s_R = Vec::with_capacity(n);
+        for _ in 0..n {
+            s_L.push(C::generate_scalar(csprng));
+            s_R.push(C::generate_scalar(csprng));
+        }
+        let GH_B_tilde: Vec<C> = G[0..n]
+            .iter()
+            .chain(H[0..n].iter())
+            .copied()
+            .chain(once(B_tilde))
+            .collect();
+        let A_I_scalars: Vec<C::Scalar> = a_L
+            .iter()
+            .chain(a_R.iter())
+            .copied()
+            .chain(once(i_blinding))
+            .collect();
+        let A_I = multiexp(&GH_B_tilde, &A_I_scalars);
+        let G_B_tilde: Vec<C> = G[0..n].iter().copied().chain(once(B_tilde)).collect();
+        let A_O_scalars: Vec<C::Scalar> = a_O.iter().copied().chain(once(o_blinding)).collect();
+        let A_O = multiexp(&G_B_tilde, &A_O_scalars);
+        let S_scalars: Vec<C::Scalar> = s_L
+            .iter()
+            .chain(s_R.iter())
+            .copied()
+            .chain(once(s_blinding))
+            .collect();
+        let S = multiexp(&GH_B_tilde, &S_scalars);
+        transcript.append_message(b"A_I", &A_I);
+        transcript.append_message(b"A_O", &A_O);
+        transcript.append_message(b"S", &S);
+        let y: C::Scalar = transcript.challenge_scalar::<C, _>(b"y");
+        let z: C::Scalar = transcript.challenge_scalar::<C, _>(b"z");
+
+        let FlattenedConstraints {
+            w_L, w_R, w_O, w_V, ..
+        } = flatten_constraints(&constraints, &z, n, m);
+
+        let y_inv = match y.inverse() {
+            Some(inv) => inv,
+            None => return Err(R1CSError::DivisionError),
+        };
+        let y_n = powers(y, padded_n);
+        let y_inv_n = powers(y_inv, padded_n);
+
+        // The polynomials l(X) = l_1 X + l_2 X^2 + l_3 X^3 and
+        // r(X) = r_0 + r_1 X + r_3 X^3, with vector coefficients.
+        let mut l_1 = Vec::with_capacity(n);
+        let mut r_0 = Vec::with_capacity(n);
+        let mut r_1 = Vec::with_capacity(n);
+        let mut r_3 = Vec::with_capacity(n);
+        for i in 0..n {
+            let mut l_1_i = y_inv_n[i];
+            l_1_i.mul_assign(&w_R[i]);
+            l_1_i.add_assign(&a_L[i]);
+            l_1.push(l_1_i);
+
+            let mut r_0_i = w_O[i];
+            r_0_i.sub_assign(&y_n[i]);
+            r_0.push(r_0_i);
+
+            let mut r_1_i = y_n[i];
+            r_1_i.mul_assign(&a_R[i]);
+            r_1_i.add_assign(&w_L[i]);
+            r_1.push(r_1_i);
+
+            let mut r_3_i = y_n[i];
+            r_3_i.mul_assign(&s_R[i]);
+            r_3.push(r_3_i);
+        }
+        let l_2 = &a_O;
+        let l_3 = &s_L;
+
+        // Coefficients of t(X) = <l(X), r(X)>.
+        let t_1 = inner_product(&l_1, &r_0);
+        let mut t_2 = inner_product(&l_1, &r_1);
+        t_2.add_assign(&inner_product(l_2, &r_0));
+        let mut t_3 = inner_product(l_2, &r_1);
+        t_3.add_assign(&inner_product(l_3, &r_0));
+        let mut t_4 = inner_product(&l_1, &r_3);
+        t_4.add_assign(&inner_product(l_3, &r_1));
+        let t_5 = inner_product(l_2, &r_3);
+        let t_6 = inner_product(l_3, &r_3);
+
+        let t_1_blinding = C::generate_scalar(csprng);
+        let t_3_blinding = C::generate_scalar(csprng);
+        let t_4_blinding = C::generate_scalar(csprng);
+        let t_5_blinding = C::generate_scalar(csprng);
+        let t_6_blinding = C::generate_scalar(csprng);
+        let T_1 = key.hide_worker(&t_1, &t_1_blinding).0;
+        let T_3 = key.hide_worker(&t_3, &t_3_blinding).0;
+        let T_4 = key.hide_worker(&t_4, &t_4_blinding).0;
+        let T_5 = key.hide_worker(&t_5, &t_5_blinding).0;
+        let T_6 = key.hide_worker(&t_6, &t_6_blinding).0;
+        transcript.append_message(b"T1", &T_1);
+        transcript.append_message(b"T3", &T_3);
+        transcript.append_message(b"T4", &T_4);
+        transcript.append_message(b"T5", &T_5);
+        transcript.append_message(b"T6", &T_6);
+        let x: C::Scalar = transcript.challenge_scalar::<C, _>(b"x");
+
+        // The blinding of t_2 is determined by the blindings of the committed
+        // values.
+        let t_2_blinding = inner_product(&w_V, &v_blinding);
+
+        let x_powers = powers(x, 7);
+        let t_coeffs = [C::Scalar::zero(), t_1, t_2, t_3, t_4, t_5, t_6];
+        let t_blindings = [
+            C::Scalar::zero(),
+            t_1_blinding,
+            t_2_blinding,
+            t_3_blinding,
+            t_4_blinding,
+            t_5_blinding,
+            t_6_blinding,
+        ];
+        let t_x = inner_product(&t_coeffs, &x_powers);
+        let t_x_blinding = inner_product(&t_blindings, &x_powers);
+
+        let (x_1, x_2, x_3) = (x_powers[1], x_powers[2], x_powers[3]);
+        let mut l = Vec::with_capacity(padded_n);
+        let mut r = Vec::with_capacity(padded_n);
+        for i in 0..n {
+            let mut l_i = l_1[i];
+            l_i.mul_assign(&x_1);
+            let mut l_2_i = l_2[i];
+            l_2_i.mul_assign(&x_2);
+            l_i.add_assign(&l_2_i);
+            let mut l_3_i = l_3[i];
+            l_3_i.mul_assign(&x_3);
+            l_i.add_assign(&l_3_i);
+            l.push(l_i);
+
+            let mut r_i = r_1[i];
+            r_i.mul_assign(&x_1);
+            r_i.add_assign(&r_0[i]);
+            let mut r_3_i = r_3[i];
+            r_3_i.mul_assign(&x_3);
+            r_i.add_assign(&r_3_i);
+            r.push(r_i);
+        }
+        // Pad to a power of two. The padding corresponds to multiplication gates
+        // with all values zero, which are not part of any constraint, hence
+        // r_0 = -y^i for them.
+        for y_i in y_n.iter().skip(n) {
+            l.push(C::Scalar::zero());
+            let mut r_i = *y_i;
+            r_i.negate();
+            r.push(r_i);
+        }
+
+        let mut e_blinding = s_blinding;
+        e_blinding.mul_assign(&x);
+        e_blinding.add_assign(&o_blinding);
+        e_blinding.mul_assign(&x);
+        e_blinding.add_assign(&i_blinding);
+        e_blinding.mul_assign(&x);
+
+        transcript.append_message(b"t_x", &t_x);
+        transcript.append_message(b"t_x_blinding", &t_x_blinding);
+        transcript.append_message(b"e_blinding", &e_blinding);
+        let w: C::Scalar = transcript.challenge_scalar::<C, _>(b"w");
+        let Q = B.mul_by_scalar(&w);
+
+        let ip_proof =
+            match prove_inner_product_with_scalars(transcript, &G, &H, &y_inv_n, &Q, &l, &r) {
+                Some(ip_proof) => ip_proof,
+                None => return Err(R1CSError::DivisionError),
+            };
+        Ok(R1CSProof {
+            A_I,
+            A_O,
+            S,
+            T_1,
+            T_3,
+            T_4,
+            T_5,
+            T_6,
+            t_x,
+            t_x_blinding,
+            e_blinding,
+            ip_proof,
+        })
+    }
+}
+
+impl<'a, C: Curve> ConstraintSystem<C::Scalar> for Prover<'a, C> {
+    fn multiply(
+        &mut self,
+        left: LinearCombination<C::Scalar>,
+        right: LinearCombination<C::Scalar>,
+    ) -> (Variable, Variable, Variable) {
+        let l = self.eval(&left);
+        let r = self.eval(&right);
+        let (l_var, r_var, o_var) = self.allocate_multiplier_worker(l, r);
+        self.constrain(left - l_var.into());
+        self.constrain(right - r_var.into());
+        (l_var, r_var, o_var)
+    }
+
+    fn allocate_multiplier(
+        &mut self,
+        input_assignments: Option<(C::Scalar, C::Scalar)>,
+    ) -> Result<(Variable, Variable, Variable), R1CSError> {
+        match input_assignments {
+            Some((l, r)) => Ok(self.allocate_multiplier_worker(l, r)),
+            None => Err(R1CSError::MissingAssignment),
+        }
+    }
+
+    fn constrain(&mut self, lc: LinearCombination<C::Scalar>) { self.constraints.push(lc) }
+
+    fn multipliers_len(&self) -> usize { self.a_L.len() }
+}
+
+impl<'a, C: Curve> Prover<'a, C> {
+    fn allocate_multiplier_worker(
+        &mut self,
+        l: C::Scalar,
+        r: C::Scalar,
+    ) -> (Variable, Variable, Variable) {
+        let i = self.a_L.len();
+        let mut o = l;
+        o.mul_assign(&r);
+        self.a_L.push(l);
+        self.a_R.push(r);
+        self.a_O.push(o);
+        (
+            Variable::MultiplierLeft(i),
+            Variable::MultiplierRight(i),
+            Variable::MultiplierOutput(i),
+        )
+    }
+}
+
+/// The verifier's side of a constraint system. The verifier adds the
+/// commitments, builds the same constraint system as the prover using
+/// gadgets, and then checks a proof.
+#[allow(non_snake_case)]
+pub struct Verifier<'a, C: Curve> {
+    transcript:      &'a mut RandomOracle,
+    constraints:     Vec<LinearCombination<C::Scalar>>,
+    num_multipliers: usize,
+    V:               Vec<C>,
+}
+
+impl<'a, C: Curve> Verifier<'a, C> {
+    /// Start a new constraint system. The transcript must be in the same state
+    /// as when the prover started its constraint system.
+    pub fn new(transcript: &'a mut RandomOracle) -> Self {
+        Self {
+            transcript,
+            constraints: Vec::new(),
+            num_multipliers: 0,
+            V: Vec::new(),
+        }
+    }
+
+    /// Add a commitment to the constraint system, and to the transcript.
+    pub fn commit(&mut self, commitment: &Commitment<C>) -> Variable {
+        let i = self.V.len();
+        self.transcript.append_message(b"V", commitment);
+        self.V.push(commitment.0);
+        Variable::Committed(i)
+    }
+
+    /// Verify that the proof shows that the committed values satisfy the
+    /// constraint system. The generators and commitment key must be the same
+    /// as the ones used by the prover.
+    ///
+    /// The two checks the verifier has to do are combined using a random
+    /// weight, hence the need for the `csprng` argument.
+    #[allow(non_snake_case)]
+    #[allow(clippy::many_single_char_names)]
+    pub fn verify<R: Rng>(
+        self,
+        csprng: &mut R,
+        proof: &R1CSProof<C>,
+        gens: &Generators<C>,
+        key: &CommitmentKey<C>,
+    ) -> Result<(), R1CSError> {
+        let Verifier {
+            transcript,
+            constraints,
+            num_multipliers: n,
+            V,
+        } = self;
+        let m = V.len();
+        let padded_n = n.next_power_of_two();
+        if gens.G_H.len() < padded_n {
+            return Err(R1CSError::NotEnoughGenerators);
+        }
+        let (G, H): (Vec<_>, Vec<_>) = gens.G_H.iter().take(padded_n).cloned().unzip();
+        let B = key.g;
+        let B_tilde = key.h;
+        transcript.append_message(b"m", &(m as u64));
+        transcript.append_message(b"n", &(n as u64));
+        transcript.append_message(b"A_I", &proof.A_I);
+        transcript.append_message(b"A_O", &proof.A_O);
+        transcript.append_message(b"S", &proof.S);
+        let y: C::Scalar = transcript.challenge_scalar::<C, _>(b"y");
+        let z: C::Scalar = transcript.challenge_scalar::<C, _>(b"z");
+        transcript.append_message(b"T1", &proof.T_1);
+        transcript.append_message(b"T3", &proof.T_3);
+        transcript.append_message(b"T4", &proof.T_4);
+        transcript.append_message(b"T5", &proof.T_5);
+        transcript.append_message(b"T6", &proof.T_6);
+        let x: C::Scalar = transcript.challenge_scalar::<C, _>(b"x");
+        transcript.append_message(b"t_x", &proof.t_x);
+        transcript.append_message(b"t_x_blinding", &proof.t_x_blinding);
+        transcript.append_message(b"e_blinding", &proof.e_blinding);
+        let w: C::Scalar = transcript.challenge_scalar::<C, _>(b"w");
+
+        let FlattenedConstraints {
+            w_L,
+            w_R,
+            w_O,
+            w_V,
+            w_c,
+        } = flatten_constraints(&constraints, &z, n, m);
+
+        let ip_proof = &proof.ip_proof;
+        // The inner product proof must have exactly log_2(padded_n) rounds.
+        if 1usize.checked_shl(ip_proof.lr_vec.len() as u32) != Some(padded_n) {
+            return Err(R1CSError::VerificationError);
+        }
+        let verification_scalars = match verify_scalars(transcript, padded_n, ip_proof) {
+            Some(scalars) => scalars,
+            None => return Err(R1CSError::DivisionError),
+        };
+        let (u_sq, u_inv_sq, s) = (
+            verification_scalars.u_sq,
+            verification_scalars.u_inv_sq,
+            verification_scalars.s,
+        );
+        let a = ip_proof.a;
+        let b = ip_proof.b;
+
+        let y_inv = match y.inverse() {
+            Some(inv) => inv,
+            None => return Err(R1CSError::DivisionError),
+        };
+        let y_inv_n = powers(y_inv, padded_n);
+        // y^{-n} ∘ w_R
+        let y_inv_w_R: Vec<C::Scalar> = w_R
+            .iter()
+            .zip(y_inv_n.iter())
+            .map(|(w_R_i, y_inv_i)| {
+                let mut v = *w_R_i;
+                v.mul_assign(y_inv_i);
+                v
+            })
+            .collect();
+        let delta = inner_product(&y_inv_w_R, &w_L);
+
+        // Weight of the check of t(x) relative to the inner product check.
+        let r = C::generate_non_zero_scalar(csprng);
+        let x_powers = powers(x, 7);
+        let mut r_x_powers = x_powers.clone();
+        for r_x_i in r_x_powers.iter_mut() {
+            r_x_i.mul_assign(&r);
+        }
+
+        let mut points = Vec::with_capacity(2 * padded_n + m + 10 + 2 * ip_proof.lr_vec.len());
+        let mut scalars = Vec::with_capacity(points.capacity());
+
+        points.push(proof.A_I);
+        scalars.push(x_powers[1]);
+        points.push(proof.A_O);
+        scalars.push(x_powers[2]);
+        points.push(proof.S);
+        scalars.push(x_powers[3]);
+        for (V_j, w_V_j) in V.iter().zip(w_V.iter()) {
+            let mut scalar = *w_V_j;
+            scalar.mul_assign(&r_x_powers[2]);
+            points.push(*V_j);
+            scalars.push(scalar);
+        }
+        points.push(proof.T_1);
+        scalars.push(r_x_powers[1]);
+        points.push(proof.T_3);
+        scalars.push(r_x_powers[3]);
+        points.push(proof.T_4);
+        scalars.push(r_x_powers[4]);
+        points.push(proof.T_5);
+        scalars.push(r_x_powers[5]);
+        points.push(proof.T_6);
+        scalars.push(r_x_powers[6]);
+
+        // w(t_x - ab) + r(x^2 (w_c + delta) - t_x)
+        let mut B_scalar = a;
+        B_scalar.mul_assign(&b);
+        B_scalar.negate();
+        B_scalar.add_assign(&proof.t_x);
+        B_scalar.mul_assign(&w);
+        let mut wc_delta = w_c;
+        wc_delta.add_assign(&delta);
+        wc_delta.mul_assign(&r_x_powers[2]);
+        B_scalar.add_assign(&wc_delta);
+        let mut r_t_x = proof.t_x;
+        r_t_x.mul_assign(&r);
+        B_scalar.sub_assign(&r_t_x);
+        points.push(B);
+        scalars.push(B_scalar);
+
+        // -e_blinding - r t_x_blinding
+        let mut B_tilde_scalar = proof.t_x_blinding;
+        B_tilde_scalar.mul_assign(&r);
+        B_tilde_scalar.add_assign(&proof.e_blinding);
+        B_tilde_scalar.negate();
+        points.push(B_tilde);
+        scalars.push(B_tilde_scalar);
+
+        for i in 0..padded_n {
+            // x y^{-i} w_R_i - a s_i
+            let mut G_scalar = s[i];
+            G_scalar.mul_assign(&a);
+            G_scalar.negate();
+            if let Some(y_inv_w_R_i) = y_inv_w_R.get(i) {
+                let mut x_y_inv_w_R_i = *y_inv_w_R_i;
+                x_y_inv_w_R_i.mul_assign(&x);
+                G_scalar.add_assign(&x_y_inv_w_R_i);
+            }
+            points.push(G[i]);
+            scalars.push(G_scalar);
+
+            // y^{-i} (x w_L_i + w_O_i - b s_{n - 1 - i}) - 1
+            let mut H_scalar = s[padded_n - 1 - i];
+            H_scalar.mul_assign(&b);
+            H_scalar.negate();
+            if i < n {
+                let mut x_w_L_i = w_L[i];
+                x_w_L_i.mul_assign(&x);
+                H_scalar.add_assign(&x_w_L_i);
+                H_scalar.add_assign(&w_O[i]);
+            }
+            H_scalar.mul_assign(&y_inv_n[i]);
+            H_scalar.sub_assign(&C::Scalar::one());
+            points.push(H[i]);
+            scalars.push(H_scalar);
+        }
+        for ((L, R), (u_j_sq, u_j_inv_sq)) in
+            ip_proof.lr_vec.iter().zip(u_sq.iter().zip(u_inv_sq.iter()))
+        {
+            points.push(*L);
+            scalars.push(*u_j_sq);
+            points.push(*R);
+            scalars.push(*u_j_inv_sq);
+        }
+
+        if multiexp(&points, &scalars).is_zero_point() {
+            Ok(())
+        } else {
+            Err(R1CSError::VerificationError)
+        }
+    }
+}
+
+impl<'a, C: Curve> ConstraintSystem<C::Scalar> for Verifier<'a, C> {
+    fn multiply(
+        &mut self,
+        left: LinearCombination<C::Scalar>,
+        right: LinearCombination<C::Scalar>,
+    ) -> (Variable, Variable, Variable) {
+        let (l_var, r_var, o_var) = self.allocate_multiplier_worker();
+        self.constrain(left - l_var.into());
+        self.constrain(right - r_var.into());
+        (l_var, r_var, o_var)
+    }
+
+    fn allocate_multiplier(
+        &mut self,
+        _input_assignments: Option<(C::Scalar, C::Scalar)>,
+    ) -> Result<(Variable, Variable, Variable), R1CSError> {
+        Ok(self.allocate_multiplier_worker())
+    }
+
+    fn constrain(&mut self, lc: LinearCombination<C::Scalar>) { self.constraints.push(lc) }
+
+    fn multipliers_len(&self) -> usize { self.num_multipliers }
+}
+
+impl<'a, C: Curve> Verifier<'a, C> {
+    fn allocate_multiplier_worker(&mut self) -> (Variable, Variable, Variable) {
+        let i = self.num_multipliers;
+        self.num_multipliers += 1;
+        (
+            Variable::MultiplierLeft(i),
+            Variable::MultiplierRight(i),
+            Variable::MultiplierOutput(i),
+        )
+    }
+}
+
+/// Gadget constraining the variable `v` to be one of the values in `set`,
+/// by requiring that the product of `v - s` over all `s` in the set is zero.
+/// This uses one multiplication gate less than the size of the set.
+/// An empty set has no members, so in that case no proof will verify.
+pub fn set_membership_gadget<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: Variable,
+    set: &[F],
+) {
+    match set.split_first() {
+        None => cs.constrain(LinearCombination::constant(F::one())),
+        Some((first, rest)) => {
+            let mut product = LinearCombination::from(v) - LinearCombination::constant(*first);
+            for s in rest {
+                let (_, _, o) = cs.multiply(
+                    product,
+                    LinearCombination::from(v) - LinearCombination::constant(*s),
+                );
+                product = o.into();
+            }
+            cs.constrain(product);
+        }
+    }
+}
+
+/// Gadget constraining the variable `v` to be different from all the values
+/// in `set`, by requiring that the product of `v - s` over all `s` in the set
+/// has an inverse. The prover must supply the value of `v`, the verifier
+/// supplies `None`.
+pub fn set_non_membership_gadget<F: Field, CS: ConstraintSystem<F>>(
+    cs: &mut CS,
+    v: Variable,
+    v_assignment: Option<F>,
+    set: &[F],
+) -> Result<(), R1CSError> {
+    let mut product = LinearCombination::constant(F::one());
+    let mut product_assignment = Some(F::one());
+    for s in set {
+        let (_, _, o) = cs.multiply(
+            product,
+            LinearCombination::from(v) - LinearCombination::constant(*s),
+        );
+        product = o.into();
+        product_assignment = product_assignment.and_then(|mut p| {
+            let mut diff = v_assignment?;
+            diff.sub_assign(s);
+            p.mul_assign(&diff);
+            Some(p)
+        });
+    }
+    // The product times its inverse must be one. If the prover's value is in
+    // the set there is no inverse, and proving fails.
+    let inputs = match product_assignment {
+        Some(p) => Some((p, p.inverse().ok_or(R1CSError::MissingAssignment)?)),
+        None => None,
+    };
+    let (l, _, o) = cs.allocate_multiplier(inputs)?;
+    cs.constrain(product - l.into());
+    cs.constrain(LinearCombination::from(o) - LinearCombination::constant(F::one()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pairing::bls12_381::G1;
+
+    type SomeCurve = G1;
+
+    /// Prove that the committed values satisfy x * y = z.
+    fn mult_gadget<F: Field, CS: ConstraintSystem<F>>(
+        cs: &mut CS,
+        x: Variable,
+        y: Variable,
+        z: Variable,
+    ) {
+        let (_, _, o) = cs.multiply(x.into(), y.into());
+        cs.constrain(LinearCombination::from(o) - z.into());
+    }
+
+    fn prove_mult(
+        gens: &Generators<SomeCurve>,
+        key: &CommitmentKey<SomeCurve>,
+        values: [u64; 3],
+    ) -> (Vec<Commitment<SomeCurve>>, R1CSProof<SomeCurve>) {
+        let mut csprng = thread_rng();
+        let mut transcript = RandomOracle::domain("r1cs_test");
+        let mut prover = Prover::new(key, &mut transcript);
+        let mut commitments = Vec::new();
+        let mut vars = Vec::new();
+        for v in values.iter() {
+            let value = Value::<SomeCurve>::new(SomeCurve::scalar_from_u64(*v));
+            let (c, var) = prover.commit(&value, &Randomness::generate(&mut csprng));
+            commitments.push(c);
+            vars.push(var);
+        }
+        mult_gadget(&mut prover, vars[0], vars[1], vars[2]);
+        let proof = prover
+            .prove(&mut csprng, gens)
+            .expect("Proving should succeed.");
+        (commitments, proof)
+    }
+
+    fn verify_mult(
+        gens: &Generators<SomeCurve>,
+        key: &CommitmentKey<SomeCurve>,
+        commitments: &[Commitment<SomeCurve>],
+        proof: &R1CSProof<SomeCurve>,
+    ) -> Result<(), R1CSError> {
+        let mut csprng = thread_rng();
+        let mut transcript = RandomOracle::domain("r1cs_test");
+        let mut verifier = Verifier::new(&mut transcript);
+        let vars: Vec<Variable> = commitments.iter().map(|c| verifier.commit(c)).collect();
+        mult_gadget(&mut verifier, vars[0], vars[1], vars[2]);
+        verifier.verify(&mut csprng, proof, gens, key)
+    }
+
+    #[test]
+    fn test_mult() {
+        let mut csprng = thread_rng();
+        let gens = Generators::generate(8, &mut csprng);
+        let key = CommitmentKey::generate(&mut csprng);
+        let (commitments, proof) = prove_mult(&gens, &key, [3, 4, 12]);
+        assert_eq!(verify_mult(&gens, &key, &commitments, &proof), Ok(()));
+        let proof = serialize_deserialize(&proof).expect("Proof should deserialize.");
+        assert_eq!(verify_mult(&gens, &key, &commitments, &proof), Ok(()));
+
+        // The prover can produce a proof, but it must not verify.
+        let (commitments, proof) = prove_mult(&gens, &key, [3, 4, 13]);
+        assert_eq!(
+            verify_mult(&gens, &key, &commitments, &proof),
+            Err(R1CSError::VerificationError)
+        );
+    }
+
+    fn set_membership_test(v: u64, set: &[u64], member: bool) -> Result<(), R1CSError> {
+        let mut csprng = thread_rng();
+        let gens = Generators::generate(16, &mut csprng);
+        let key = CommitmentKey::<SomeCurve>::generate(&mut csprng);
+        let set: Vec<_> = set.iter().map(|s| SomeCurve::scalar_from_u64(*s)).collect();
+        let v_scalar = SomeCurve::scalar_from_u64(v);
+
+        let mut transcript = RandomOracle::domain("set_membership_test");
+        let mut prover = Prover::new(&key, &mut transcript);
+        let (commitment, var) =
+            prover.commit(&Value::new(v_scalar), &Randomness::generate(&mut csprng));
+        if member {
+            set_membership_gadget(&mut prover, var, &set);
+        } else {
+            set_non_membership_gadget(&mut prover, var, Some(v_scalar), &set)?;
+        }
+        let proof = prover.prove(&mut csprng, &gens)?;
+
+        let mut transcript = RandomOracle::domain("set_membership_test");
+        let mut verifier = Verifier::new(&mut transcript);
+        let var = verifier.commit(&commitment);
+        if member {
+            set_membership_gadget(&mut verifier, var, &set);
+        } else {
+            set_non_membership_gadget(&mut verifier, var, None, &set)?;
+        }
+        verifier.verify(&mut csprng, &proof, &gens, &key)
+    }
+
+    #[test]
+    fn test_set_membership() {
+        let set = [1, 5, 17, 33, 100];
+        assert_eq!(set_membership_test(17, &set, true), Ok(()));
+        assert_eq!(set_membership_test(1, &set, true), Ok(()));
+        assert_eq!(
+            set_membership_test(18, &set, true),
+            Err(R1CSError::VerificationError)
+        );
+        assert_eq!(
+            set_membership_test(2, &[], true),
+            Err(R1CSError::VerificationError)
+        );
+    }
+
+    #[test]
+    fn test_set_non_membership() {
+        let set = [1, 5, 17, 33, 100];
+        assert_eq!(set_membership_test(18, &set, false), Ok(()));
+        assert_eq!(set_membership_test(2, &[], false), Ok(()));
+        // The prover cannot even construct the proof for a member.
+        assert_eq!(
+            set_membership_test(17, &set, false),
+            Err(R1CSError::MissingAssignment)
+        );
+    }
+}
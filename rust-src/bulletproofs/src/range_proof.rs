@@ -1,14 +1,16 @@
 use crate::inner_product_proof::*;
 use crypto_common::*;
 use crypto_common_derive::*;
-use curve_arithmetic::{multiexp, multiexp_table, multiexp_worker_given_table, Curve, Value};
+use curve_arithmetic::{
+    multiexp, multiexp_table, multiexp_worker_given_table, Curve, TableCache, Value,
+};
 use ff::{Field, PrimeField};
 use pedersen_scheme::*;
 use rand::*;
 use random_oracle::RandomOracle;
-use std::iter::once;
+use std::{convert::TryFrom, iter::once, sync::Arc};
 
-#[derive(Clone, Serialize, SerdeBase16Serialize, Debug)]
+#[derive(Clone, Serialize, SerdeBase16Serialize, Debug, PartialEq, Eq)]
 #[allow(non_snake_case)]
 pub struct RangeProof<C: Curve> {
     A:        C,
@@ -79,13 +81,41 @@ fn z_vec<F: Field>(z: F, first_power: usize, n: usize) -> Vec<F> {
 
 /// Struct containing generators G and H needed for range proofs
 #[allow(non_snake_case)]
-#[derive(Clone, Serialize, SerdeBase16Serialize)]
+#[derive(Clone, SerdeBase16Serialize)]
 pub struct Generators<C: Curve> {
-    #[size_length = 4]
     pub G_H: Vec<(C, C)>,
+    /// Table of the generators, built when first needed. It is shared between
+    /// clones, and with the generators returned by [Generators::take].
+    table:   TableCache<GeneratorsTable<C>>,
 }
 
+/// The table is not part of the serialization.
+impl<C: Curve> Serial for Generators<C> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        out.put(&(self.G_H.len() as u32));
+        serial_vector_no_length(&self.G_H, out)
+    }
+}
+
+impl<C: Curve> Deserial for Generators<C> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let len: u32 = source.get()?;
+        Ok(Self::new(deserial_vector_no_length(
+            source,
+            usize::try_from(len)?,
+        )?))
+    }
+}
+
+#[allow(non_snake_case)]
 impl<C: Curve> Generators<C> {
+    pub fn new(G_H: Vec<(C, C)>) -> Self {
+        Self {
+            G_H,
+            table: TableCache::default(),
+        }
+    }
+
     /// Generate a list of generators of a given size.
     pub fn generate(n: usize, csprng: &mut impl Rng) -> Self {
         let mut gh = Vec::with_capacity(n);
@@ -94,14 +124,111 @@ impl<C: Curve> Generators<C> {
             let y = C::generate(csprng);
             gh.push((x, y));
         }
-        Self { G_H: gh }
+        Self::new(gh)
     }
 
+    /// The first `nm` generators. The table of all the generators is built if
+    /// needed, and shared with the result, so that it is only built once.
     pub fn take(&self, nm: usize) -> Self {
+        self.table();
         Self {
-            G_H: self.G_H[0..nm].to_vec(),
+            G_H:   self.G_H[0..nm].to_vec(),
+            table: self.table.clone(),
+        }
+    }
+
+    /// The table of the generators used when proving and verifying. It is
+    /// built the first time it is needed, unless it was given with
+    /// [Generators::set_table], and built again if the generators have
+    /// changed since.
+    pub fn table(&self) -> Arc<GeneratorsTable<C>> {
+        self.table
+            .get_or_build(|table| table.matches(self), || GeneratorsTable::new(self))
+    }
+
+    /// Use the given precomputed table, e.g., one that was shipped with the
+    /// application instead of building it. Returns `false`, and does not use
+    /// the table, if it is not a table of these generators.
+    pub fn set_table(&self, table: GeneratorsTable<C>) -> bool {
+        if !table.matches(self) {
+            return false;
+        }
+        self.table.set(table);
+        true
+    }
+}
+
+/// The window size of the tables of the generators.
+pub const GENERATORS_TABLE_WINDOW: usize = 4;
+
+/// Tables of the generators `G` and `H` for the wNAF multiexponentiation, see
+/// [multiexp_table], with window size [GENERATORS_TABLE_WINDOW].
+///
+/// Like [curve_arithmetic::FixedBaseTable] the table is serialized with
+/// uncompressed points that are not checked when deserializing, so it must
+/// only be deserialized from a trusted source.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratorsTable<C: Curve> {
+    G: Vec<Vec<C>>,
+    H: Vec<Vec<C>>,
+}
+
+#[allow(non_snake_case)]
+impl<C: Curve> GeneratorsTable<C> {
+    /// Build the table of the given generators.
+    pub fn new(gens: &Generators<C>) -> Self {
+        let (G, H): (Vec<_>, Vec<_>) = gens.G_H.iter().cloned().unzip();
+        GeneratorsTable {
+            G: multiexp_table(&G, GENERATORS_TABLE_WINDOW),
+            H: multiexp_table(&H, GENERATORS_TABLE_WINDOW),
         }
     }
+
+    /// Whether this is a table of the given generators, possibly together
+    /// with further generators following them.
+    pub fn matches(&self, gens: &Generators<C>) -> bool {
+        self.G.len() >= gens.G_H.len()
+            && gens
+                .G_H
+                .iter()
+                .zip(self.G.iter().zip(self.H.iter()))
+                .all(|((g, h), (g_row, h_row))| g_row[0] == *g && h_row[0] == *h)
+    }
+}
+
+impl<C: Curve> Serial for GeneratorsTable<C> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        out.put(&(self.G.len() as u32));
+        for row in self.G.iter().chain(self.H.iter()) {
+            for point in row.iter() {
+                point.curve_to_bytes_uncompressed(out);
+            }
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+impl<C: Curve> Deserial for GeneratorsTable<C> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let len: u32 = source.get()?;
+        let len = usize::try_from(len)?;
+        let row_len = 1 << (GENERATORS_TABLE_WINDOW - 1);
+        let mut read_rows = || -> ParseResult<Vec<Vec<C>>> {
+            let mut rows = safe_with_capacity(len);
+            for _ in 0..len {
+                let mut row = Vec::with_capacity(row_len);
+                for _ in 0..row_len {
+                    row.push(C::bytes_to_curve_uncompressed_unchecked(source)?);
+                }
+                rows.push(row);
+            }
+            Ok(rows)
+        };
+        let G = read_rows()?;
+        let H = read_rows()?;
+        Ok(GeneratorsTable { G, H })
+    }
 }
 
 /// This function produces a range proof given scalars in a prime field
@@ -215,16 +342,17 @@ pub fn prove<C: Curve, T: Rng>(
         .copied()
         .chain(once(s_tilde_sum))
         .collect();
-    let GH_B_tilde: Vec<C> = G
+    // The tables of G and H are precomputed, only the one of B_tilde is not.
+    let gens_table = gens.table();
+    let B_tilde_table = multiexp_table(&[B_tilde], GENERATORS_TABLE_WINDOW);
+    let table: Vec<&[C]> = gens_table.G[..nm]
         .iter()
-        .chain(H.iter())
-        .copied()
-        .chain(once(B_tilde))
+        .chain(gens_table.H[..nm].iter())
+        .chain(B_tilde_table.iter())
+        .map(Vec::as_slice)
         .collect();
-    let window_size = 4;
-    let table = multiexp_table(&GH_B_tilde, window_size);
-    let A = multiexp_worker_given_table(&A_scalars, &table, window_size);
-    let S = multiexp_worker_given_table(&S_scalars, &table, window_size);
+    let A = multiexp_worker_given_table(&A_scalars, &table, GENERATORS_TABLE_WINDOW);
+    let S = multiexp_worker_given_table(&S_scalars, &table, GENERATORS_TABLE_WINDOW);
     transcript.append_message(b"A", &A);
     transcript.append_message(b"S", &S);
     let y: C::Scalar = transcript.challenge_scalar::<C, _>(b"y");
@@ -458,7 +586,6 @@ pub fn verify_efficient<C: Curve>(
     if gens.G_H.len() < nm {
         return Err(VerificationError::NotEnoughGenerators);
     }
-    let (G, H): (Vec<_>, Vec<_>) = gens.G_H.iter().take(nm).cloned().unzip();
     let B = v_keys.g;
     let B_tilde = v_keys.h;
     for V in commitments {
@@ -492,7 +619,7 @@ pub fn verify_efficient<C: Curve>(
     // Calculate delta(x,y):
     let mut ip_1_y_nm = C::Scalar::zero();
     let mut yi = C::Scalar::one();
-    for _ in 0..G.len() {
+    for _ in 0..nm {
         ip_1_y_nm.add_assign(&yi);
         yi.mul_assign(&y);
     }
@@ -537,10 +664,15 @@ pub fn verify_efficient<C: Curve>(
     }
 
     let ip_proof = &proof.ip_proof;
-    let mut H_scalars: Vec<C::Scalar> = Vec::with_capacity(G.len());
+    // The inner product proof must have exactly log_2(nm) rounds, otherwise
+    // computing the verification scalars is not meaningful.
+    if 1usize.checked_shl(ip_proof.lr_vec.len() as u32) != Some(nm) {
+        return Err(VerificationError::Second);
+    }
+    let mut H_scalars: Vec<C::Scalar> = Vec::with_capacity(nm);
     let mut y_i = C::Scalar::one();
     let z_2_m = z_vec(z, 2, m);
-    let verification_scalars = verify_scalars(transcript, G.len(), &ip_proof);
+    let verification_scalars = verify_scalars(transcript, nm, ip_proof);
     if verification_scalars.is_none() {
         return Err(VerificationError::DivisionError);
     }
@@ -560,7 +692,7 @@ pub fn verify_efficient<C: Curve>(
         None => return Err(VerificationError::DivisionError),
     };
     let two_n: Vec<C::Scalar> = two_n_vec(n);
-    for i in 0..G.len() {
+    for i in 0..nm {
         let j = i / usize::from(n);
         let mut H_scalar = two_n[i % usize::from(n)];
         H_scalar.mul_assign(&z_2_m[j]);
@@ -574,7 +706,9 @@ pub fn verify_efficient<C: Curve>(
     }
     s_inv.reverse();
     let s = s_inv;
-    let H_term = multiexp(&H, &H_scalars); // Expensive!
+    let gens_table = gens.table();
+    let H_term =
+        multiexp_worker_given_table(&H_scalars, &gens_table.H[..nm], GENERATORS_TABLE_WINDOW); // Expensive!
     let A_term = A;
     let S_term = S.mul_by_scalar(&x);
     let mut B_scalar = tx;
@@ -587,7 +721,7 @@ pub fn verify_efficient<C: Curve>(
     minus_e_tilde.negate();
     let B_tilde_scalar = minus_e_tilde;
     let B_tilde_term = B_tilde.mul_by_scalar(&B_tilde_scalar);
-    let mut G_scalars = Vec::with_capacity(G.len());
+    let mut G_scalars = Vec::with_capacity(nm);
     for si in s {
         let mut G_scalar = z;
         G_scalar.negate();
@@ -596,7 +730,8 @@ pub fn verify_efficient<C: Curve>(
         G_scalar.sub_assign(&sa);
         G_scalars.push(G_scalar);
     }
-    let G_term = multiexp(&G, &G_scalars); // Expensive!
+    let G_term =
+        multiexp_worker_given_table(&G_scalars, &gens_table.G[..nm], GENERATORS_TABLE_WINDOW); // Expensive!
     let L_term = multiexp(&L, &u_sq); // Expensive!
     let R_term = multiexp(&R, &u_inv_sq); // Expensive!
 
@@ -666,6 +801,379 @@ pub fn verify_less_than_or_equal<C: Curve>(
     .is_ok()
 }
 
+/// The accumulated verification equation of a batch of range proofs that
+/// share the same generators and commitment keys. Each proof contributes both
+/// of its checks, scaled by verifier chosen random weights, so that a single
+/// multiexponentiation at the end checks all the proofs at once.
+#[allow(non_snake_case)]
+struct BatchTerms<C: Curve> {
+    /// Accumulated scalar of the commitment key generator B.
+    B_scalar:       C::Scalar,
+    /// Accumulated scalar of the commitment key generator B_tilde.
+    B_tilde_scalar: C::Scalar,
+    /// Accumulated scalars of the generators G. This has the length of the
+    /// generators that are available.
+    G_scalars:      Vec<C::Scalar>,
+    /// Accumulated scalars of the generators H. Same length as G_scalars.
+    H_scalars:      Vec<C::Scalar>,
+    /// The number of generators that are used by at least one proof.
+    num_used:       usize,
+    /// Points that are specific to individual proofs, i.e., commitments and
+    /// the prover's messages.
+    points:         Vec<C>,
+    /// Scalars corresponding to the `points`.
+    scalars:        Vec<C::Scalar>,
+}
+
+impl<C: Curve> BatchTerms<C> {
+    fn new(num_generators: usize) -> Self {
+        Self {
+            B_scalar:       C::Scalar::zero(),
+            B_tilde_scalar: C::Scalar::zero(),
+            G_scalars:      vec![C::Scalar::zero(); num_generators],
+            H_scalars:      vec![C::Scalar::zero(); num_generators],
+            num_used:       0,
+            points:         Vec::new(),
+            scalars:        Vec::new(),
+        }
+    }
+
+    /// Evaluate the accumulated equation. It holds if and only if the result
+    /// is the zero point.
+    #[allow(non_snake_case)]
+    fn check(self, gens: &Generators<C>, v_keys: &CommitmentKey<C>) -> bool {
+        let BatchTerms {
+            B_scalar,
+            B_tilde_scalar,
+            G_scalars,
+            H_scalars,
+            num_used,
+            mut points,
+            mut scalars,
+        } = self;
+        let (G, H): (Vec<_>, Vec<_>) = gens.G_H.iter().take(num_used).cloned().unzip();
+        points.push(v_keys.g);
+        scalars.push(B_scalar);
+        points.push(v_keys.h);
+        scalars.push(B_tilde_scalar);
+        points.extend_from_slice(&G);
+        scalars.extend_from_slice(&G_scalars[0..num_used]);
+        points.extend_from_slice(&H);
+        scalars.extend_from_slice(&H_scalars[0..num_used]);
+        multiexp(&points, &scalars).is_zero_point()
+    }
+}
+
+/// Add the verification equations of a single range proof to the batch.
+/// This does the same computations as `verify_efficient`, except that
+/// the two checks are combined using a random weight, and the result is
+/// multiplied by another random weight, before being added to the batch.
+///
+/// The arguments have the same meaning as in `verify_efficient`.
+#[allow(non_snake_case)]
+#[allow(clippy::many_single_char_names)]
+fn add_batch_terms<C: Curve, R: Rng>(
+    csprng: &mut R,
+    terms: &mut BatchTerms<C>,
+    transcript: &mut RandomOracle,
+    n: u8,
+    commitments: &[Commitment<C>],
+    proof: &RangeProof<C>,
+) -> Result<(), VerificationError> {
+    let m = commitments.len();
+    let nm = usize::from(n) * m;
+    if terms.G_scalars.len() < nm {
+        return Err(VerificationError::NotEnoughGenerators);
+    }
+    for V in commitments {
+        transcript.append_message(b"Vj", &V.0);
+    }
+    let A = proof.A;
+    let S = proof.S;
+    let T_1 = proof.T_1;
+    let T_2 = proof.T_2;
+    let tx = proof.tx;
+    let tx_tilde = proof.tx_tilde;
+    let e_tilde = proof.e_tilde;
+    transcript.append_message(b"A", &A);
+    transcript.append_message(b"S", &S);
+    let y: C::Scalar = transcript.challenge_scalar::<C, _>(b"y");
+    let z: C::Scalar = transcript.challenge_scalar::<C, _>(b"z");
+    let mut z2 = z;
+    z2.mul_assign(&z);
+    let mut z3 = z2;
+    z3.mul_assign(&z);
+    transcript.append_message(b"T1", &T_1);
+    transcript.append_message(b"T2", &T_2);
+    let x: C::Scalar = transcript.challenge_scalar::<C, _>(b"x");
+    let mut x2 = x;
+    x2.mul_assign(&x);
+    transcript.append_message(b"tx", &tx);
+    transcript.append_message(b"tx_tilde", &tx_tilde);
+    transcript.append_message(b"e_tilde", &e_tilde);
+    let w: C::Scalar = transcript.challenge_scalar::<C, _>(b"w");
+    // Calculate delta(x,y):
+    let mut ip_1_y_nm = C::Scalar::zero();
+    let mut yi = C::Scalar::one();
+    for _ in 0..nm {
+        ip_1_y_nm.add_assign(&yi);
+        yi.mul_assign(&y);
+    }
+    let mut ip_1_2_n = C::Scalar::zero();
+    let mut two_i = C::Scalar::one();
+    for _ in 0..usize::from(n) {
+        ip_1_2_n.add_assign(&two_i);
+        two_i.double();
+    }
+    let mut sum = C::Scalar::zero();
+    let mut zj3 = z3;
+    for _ in 0..m {
+        sum.add_assign(&zj3);
+        zj3.mul_assign(&z);
+    }
+    sum.mul_assign(&ip_1_2_n);
+    let mut delta_yz = z;
+    delta_yz.sub_assign(&z2);
+    delta_yz.mul_assign(&ip_1_y_nm);
+    delta_yz.sub_assign(&sum);
+
+    let ip_proof = &proof.ip_proof;
+    // The inner product proof must have exactly log_2(nm) rounds, otherwise
+    // computing the verification scalars is not meaningful.
+    if 1usize.checked_shl(ip_proof.lr_vec.len() as u32) != Some(nm) {
+        return Err(VerificationError::Second);
+    }
+    let verification_scalars = match verify_scalars(transcript, nm, ip_proof) {
+        Some(scalars) => scalars,
+        None => return Err(VerificationError::DivisionError),
+    };
+    let (u_sq, u_inv_sq, s) = (
+        verification_scalars.u_sq,
+        verification_scalars.u_inv_sq,
+        verification_scalars.s,
+    );
+    let a = ip_proof.a;
+    let b = ip_proof.b;
+    let y_inv = match y.inverse() {
+        Some(inv) => inv,
+        None => return Err(VerificationError::DivisionError),
+    };
+
+    // The weight of the first check relative to the second one.
+    let c = C::generate_non_zero_scalar(csprng);
+    // The weight of this proof in the batch.
+    let weight = C::generate_non_zero_scalar(csprng);
+    let scale = |mut v: C::Scalar| {
+        v.mul_assign(&weight);
+        v
+    };
+
+    let z_2_m = z_vec(z, 2, m);
+    let two_n: Vec<C::Scalar> = two_n_vec(n);
+    let mut y_i = C::Scalar::one();
+    for i in 0..nm {
+        let j = i / usize::from(n);
+        let mut H_scalar = two_n[i % usize::from(n)];
+        H_scalar.mul_assign(&z_2_m[j]);
+        let mut bs_inv = b;
+        bs_inv.mul_assign(&s[nm - 1 - i]);
+        H_scalar.sub_assign(&bs_inv);
+        H_scalar.mul_assign(&y_i);
+        y_i.mul_assign(&y_inv);
+        H_scalar.add_assign(&z);
+        terms.H_scalars[i].add_assign(&scale(H_scalar));
+
+        let mut G_scalar = z;
+        G_scalar.negate();
+        let mut sa = s[i];
+        sa.mul_assign(&a);
+        G_scalar.sub_assign(&sa);
+        terms.G_scalars[i].add_assign(&scale(G_scalar));
+    }
+    terms.num_used = std::cmp::max(terms.num_used, nm);
+
+    let mut B_scalar = tx;
+    let mut ab = a;
+    ab.mul_assign(&b);
+    B_scalar.sub_assign(&ab);
+    B_scalar.mul_assign(&w);
+    let mut c_delta_minus_tx = delta_yz;
+    c_delta_minus_tx.sub_assign(&tx);
+    c_delta_minus_tx.mul_assign(&c);
+    B_scalar.add_assign(&c_delta_minus_tx);
+    terms.B_scalar.add_assign(&scale(B_scalar));
+
+    let mut B_tilde_scalar = e_tilde;
+    B_tilde_scalar.negate();
+    let mut ctx_tilde = tx_tilde;
+    ctx_tilde.mul_assign(&c);
+    B_tilde_scalar.sub_assign(&ctx_tilde);
+    terms.B_tilde_scalar.add_assign(&scale(B_tilde_scalar));
+
+    let mut T_1_scalar = c;
+    T_1_scalar.mul_assign(&x);
+    let mut T_2_scalar = T_1_scalar;
+    T_2_scalar.mul_assign(&x);
+    terms.points.push(A);
+    terms.scalars.push(weight);
+    terms.points.push(S);
+    terms.scalars.push(scale(x));
+    terms.points.push(T_1);
+    terms.scalars.push(scale(T_1_scalar));
+    terms.points.push(T_2);
+    terms.scalars.push(scale(T_2_scalar));
+
+    let mut cz_j = c;
+    cz_j.mul_assign(&z2);
+    for V in commitments {
+        terms.points.push(V.0);
+        terms.scalars.push(scale(cz_j));
+        cz_j.mul_assign(&z);
+    }
+    for ((L, R), (u_j_sq, u_j_inv_sq)) in
+        ip_proof.lr_vec.iter().zip(u_sq.iter().zip(u_inv_sq.iter()))
+    {
+        terms.points.push(*L);
+        terms.scalars.push(scale(*u_j_sq));
+        terms.points.push(*R);
+        terms.scalars.push(scale(*u_j_inv_sq));
+    }
+    Ok(())
+}
+
+/// A statement that a <= b, together with its proof, to be verified as part
+/// of a batch by `verify_less_than_or_equal_batch`.
+pub struct LessThanOrEqualInstance<'a, C: Curve> {
+    /// The transcript in the state in which the proof was produced.
+    pub transcript:   RandomOracle,
+    /// The number n such that b is in [0, 2^n).
+    pub n:            u8,
+    /// Commitment to a.
+    pub commitment_a: &'a Commitment<C>,
+    /// Commitment to b.
+    pub commitment_b: &'a Commitment<C>,
+    /// The proof that a <= b.
+    pub proof:        &'a RangeProof<C>,
+}
+
+/// Verify many a <= b statements at once, with the same outcome as calling
+/// `verify_less_than_or_equal` on each of the instances, except with negligible
+/// probability. The proofs are combined and checked with `verify_batch`, so
+/// only a single multiexponentiation is performed if all of them are valid.
+///
+/// If the combined check fails, the indices of all the invalid proofs are
+/// returned, in increasing order, together with the reasons they failed.
+pub fn verify_less_than_or_equal_batch<C: Curve, R: Rng>(
+    csprng: &mut R,
+    instances: &[LessThanOrEqualInstance<C>],
+    gens: &Generators<C>,
+    key: &CommitmentKey<C>,
+) -> Result<(), Vec<(usize, VerificationError)>> {
+    let commitments = instances
+        .iter()
+        .map(|instance| {
+            [
+                Commitment(
+                    instance
+                        .commitment_b
+                        .0
+                        .minus_point(&instance.commitment_a.0),
+                ),
+                *instance.commitment_a,
+            ]
+        })
+        .collect::<Vec<_>>();
+    let instances = instances
+        .iter()
+        .zip(commitments.iter())
+        .map(|(instance, commitments)| RangeProofInstance {
+            transcript: instance.transcript.split(),
+            n: instance.n,
+            commitments,
+            proof: instance.proof,
+        })
+        .collect::<Vec<_>>();
+    verify_batch(csprng, &instances, gens, key)
+}
+
+/// A range proof together with the commitments it is about, to be verified as
+/// part of a batch by `verify_batch`.
+pub struct RangeProofInstance<'a, C: Curve> {
+    /// The transcript in the state in which the proof was produced.
+    pub transcript:  RandomOracle,
+    /// The number n such that each committed value is claimed to be in
+    /// [0, 2^n).
+    pub n:           u8,
+    /// Commitments to the values.
+    pub commitments: &'a [Commitment<C>],
+    /// The range proof.
+    pub proof:       &'a RangeProof<C>,
+}
+
+/// Verify many independent range proofs at once. The proofs must all use the
+/// same generators and commitment keys, but can otherwise differ in the number
+/// of values and the size of the range. The verification equations of all the
+/// proofs are combined using random weights, so that only a single
+/// multiexponentiation is performed if all the proofs are valid.
+///
+/// If the combined check fails, each proof is verified individually using
+/// `verify_efficient`, exactly once, and the indices of all the invalid proofs
+/// are returned, in increasing order, together with the reasons they failed.
+/// The cost of finding the invalid proofs is thus that of verifying each proof
+/// once, however many of them are invalid.
+pub fn verify_batch<C: Curve, R: Rng>(
+    csprng: &mut R,
+    instances: &[RangeProofInstance<C>],
+    gens: &Generators<C>,
+    v_keys: &CommitmentKey<C>,
+) -> Result<(), Vec<(usize, VerificationError)>> {
+    let mut terms = BatchTerms::new(gens.G_H.len());
+    let mut batch_ok = true;
+    for instance in instances.iter() {
+        let mut transcript = instance.transcript.split();
+        if add_batch_terms(
+            csprng,
+            &mut terms,
+            &mut transcript,
+            instance.n,
+            instance.commitments,
+            instance.proof,
+        )
+        .is_err()
+        {
+            batch_ok = false;
+            break;
+        }
+    }
+    if batch_ok && terms.check(gens, v_keys) {
+        return Ok(());
+    }
+    let failures = instances
+        .iter()
+        .enumerate()
+        .filter_map(|(i, instance)| {
+            let mut transcript = instance.transcript.split();
+            verify_efficient(
+                &mut transcript,
+                instance.n,
+                instance.commitments,
+                instance.proof,
+                gens,
+                v_keys,
+            )
+            .err()
+            .map(|e| (i, e))
+        })
+        .collect::<Vec<_>>();
+    // If all the proofs are valid individually, the combined check only failed
+    // due to an unlucky choice of weights.
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1162,7 +1670,7 @@ mod tests {
             G_H.push((g, h));
         }
 
-        let gens = Generators { G_H };
+        let gens = Generators::new(G_H);
         let B = SomeCurve::generate(rng);
         let B_tilde = SomeCurve::generate(rng);
         let keys = CommitmentKey { g: B, h: B_tilde };
@@ -1236,7 +1744,7 @@ mod tests {
             G_H.push((g, h));
         }
 
-        let gens = Generators { G_H };
+        let gens = Generators::new(G_H);
         let B = SomeCurve::generate(rng);
         let B_tilde = SomeCurve::generate(rng);
         let keys = CommitmentKey { g: B, h: B_tilde };
@@ -1308,7 +1816,7 @@ mod tests {
             G_H.push((g, h));
         }
 
-        let gens = Generators { G_H };
+        let gens = Generators::new(G_H);
         let B = SomeCurve::generate(rng);
         let B_tilde = SomeCurve::generate(rng);
         let key = CommitmentKey { g: B, h: B_tilde };
@@ -1338,6 +1846,77 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_verify_batch() {
+        let rng = &mut thread_rng();
+        let gens = Generators::generate(64, rng);
+        let keys = CommitmentKey::<SomeCurve>::generate(rng);
+        // (n, values) for each of the proofs.
+        let statements: Vec<(u8, Vec<u64>)> = vec![
+            (32, vec![7, 4294967295]),
+            (8, vec![255]),
+            (16, vec![0, 1, 2, 65535]),
+            (64, vec![u64::max_value()]),
+        ];
+        let mut commitments = Vec::new();
+        let mut proofs = Vec::new();
+        for (n, v_vec) in statements.iter() {
+            let randomness: Vec<_> = v_vec.iter().map(|_| Randomness::generate(rng)).collect();
+            let coms: Vec<_> = v_vec
+                .iter()
+                .zip(randomness.iter())
+                .map(|(v, r)| keys.hide_worker(&SomeCurve::scalar_from_u64(*v), r))
+                .collect();
+            let mut transcript = RandomOracle::domain("verify_batch_test");
+            let proof = prove(
+                &mut transcript,
+                rng,
+                *n,
+                v_vec.len() as u8,
+                v_vec,
+                &gens,
+                &keys,
+                &randomness,
+            )
+            .expect("Proving should succeed.");
+            commitments.push(coms);
+            proofs.push(proof);
+        }
+        let make_instances = || -> Vec<RangeProofInstance<SomeCurve>> {
+            statements
+                .iter()
+                .zip(commitments.iter().zip(proofs.iter()))
+                .map(|((n, _), (coms, proof))| RangeProofInstance {
+                    transcript: RandomOracle::domain("verify_batch_test"),
+                    n: *n,
+                    commitments: coms,
+                    proof,
+                })
+                .collect()
+        };
+        let instances = make_instances();
+        assert_eq!(verify_batch(rng, &instances, &gens, &keys), Ok(()));
+        assert_eq!(verify_batch(rng, &instances[..0], &gens, &keys), Ok(()));
+
+        // Use the wrong commitment in the third proof.
+        let mut bad_commitments = commitments[2].clone();
+        bad_commitments.swap(0, 1);
+        let mut bad_instances = make_instances();
+        bad_instances[2].commitments = &bad_commitments;
+        let failed_indices = |instances: &[RangeProofInstance<SomeCurve>]| {
+            verify_batch(&mut thread_rng(), instances, &gens, &keys)
+                .map_err(|failures| failures.into_iter().map(|(i, _)| i).collect::<Vec<_>>())
+        };
+        assert_eq!(failed_indices(&bad_instances), Err(vec![2]));
+
+        // Also claim a smaller range for the second proof. All the invalid
+        // proofs are reported.
+        bad_instances[1].n = 4;
+        assert_eq!(failed_indices(&bad_instances), Err(vec![1, 2]));
+        bad_instances[2].commitments = &commitments[2];
+        assert_eq!(failed_indices(&bad_instances), Err(vec![1]));
+    }
+
     #[allow(non_snake_case)]
     #[test]
     fn test_cheating_prover() {
@@ -1356,7 +1935,7 @@ mod tests {
             H.push(h);
             G_H.push((g, h));
         }
-        let gens = Generators { G_H };
+        let gens = Generators::new(G_H);
         let B = SomeCurve::generate(rng);
         let B_tilde = SomeCurve::generate(rng);
         let keys = CommitmentKey { g: B, h: B_tilde };
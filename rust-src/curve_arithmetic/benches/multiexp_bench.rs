@@ -40,5 +40,34 @@ pub fn bench_multiexp(c: &mut Criterion) {
     }
 }
 
-criterion_group!(multiexp_benchmarks, bench_multiexp);
+/// Compare the wNAF method with the previous default window size, Pippenger's
+/// method, and the automatically chosen strategy on the larger inputs that
+/// occur when verifying range proofs. Run with `--features parallel` to
+/// benchmark the multi-threaded version of Pippenger's method.
+pub fn bench_multiexp_large(c: &mut Criterion) {
+    let mut csprng = thread_rng();
+    let max = 4096;
+    let gs: Vec<G1> = (0..max).map(|_| G1::generate(&mut csprng)).collect();
+    let es: Vec<_> = (0..max).map(|_| G1::generate_scalar(&mut csprng)).collect();
+
+    for &i in [64, 256, 1024, 4096].iter() {
+        let mut group = c.benchmark_group(format!("Large({})", i));
+        group.sample_size(10);
+        let gsc = &gs[..i];
+        let esc = &es[..i];
+        group.bench_function("wnaf(4)", |b| b.iter(|| multiexp_worker(gsc, esc, 4)));
+        for w in (4..=12).step_by(2) {
+            group.bench_function(&format!("pippenger({})", w), |b| {
+                b.iter(|| multiexp_pippenger(gsc, esc, w))
+            });
+        }
+        group.bench_function(
+            &format!("multiexp({:?})", MultiexpStrategy::for_length::<G1>(i)),
+            |b| b.iter(|| multiexp(gsc, esc)),
+        );
+        group.finish();
+    }
+}
+
+criterion_group!(multiexp_benchmarks, bench_multiexp, bench_multiexp_large);
 criterion_main!(multiexp_benchmarks);
@@ -1,13 +1,14 @@
+use crate::expand_message::expand_message_xmd;
 use ff::{Field, PrimeField, SqrtField};
 use group::{CurveProjective, EncodedPoint};
 use pairing::bls12_381::{Fq, Fq2, FqRepr, G2Uncompressed, G2};
-use sha2::{Digest, Sha256};
+use sha2::Sha256;
 use std::{
     convert::TryInto,
     io::{Cursor, Write},
 };
 
-/// Implements https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#section-3
+/// Implements https://www.rfc-editor.org/rfc/rfc9380#section-3
 /// It follows the steps
 ///    1. u = hash_to_field(msg, 2)
 ///    2. Q0 = map_to_curve(u[0])
@@ -15,7 +16,9 @@ use std::{
 ///    4. R = Q0 + Q1              
 ///    5. P = clear_cofactor(R) = h_eff * R   # Clearing cofactor
 ///    6. return P,
-/// where the choices of hash_to_field, map_to_curve and h_eff are as described in https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#section-8.8.2.
+/// where the choices of hash_to_field, map_to_curve and h_eff are as described in https://www.rfc-editor.org/rfc/rfc9380#section-8.8.2.
+/// With the domain separation tag `dst` chosen by the caller this is the
+/// suite BLS12381G2_XMD:SHA-256_SSWU_RO_.
 pub fn hash_to_curve_g2(msg: &[u8], dst: &[u8]) -> G2 {
     let (u0, u1) = hash_to_field_fq2(msg, dst);
 
@@ -29,8 +32,8 @@ pub fn hash_to_curve_g2(msg: &[u8], dst: &[u8]) -> G2 {
 }
 
 /// This is an inefficient method for clearing the cofactor.
-/// Corresponds to multiplying by h_eff in https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#section-8.8.2
-/// A much faster equivalent implementation is available in https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#appendix-G.4
+/// Corresponds to multiplying by h_eff in https://www.rfc-editor.org/rfc/rfc9380#section-8.8.2
+/// A much faster equivalent implementation is available in https://www.rfc-editor.org/rfc/rfc9380#appendix-G.3
 fn clear_cofactor_g2(p: G2) -> G2 {
     // h_eff = 0xbc69f08f2ee75b3584c6a0ea91b352888e2a8e9145ad7689986ff031508ffe1329c2f178731db956d82bf015d1212b02ec0ec69d7477c1ae954cbc06689f6a359894c0adebbf6b4e8020005aaa95551
     // it is not possible to use the implementation of mul_assign for G2 directly
@@ -68,8 +71,8 @@ fn map_to_curve_g2(u: Fq2) -> G2 {
     from_coordinates_unchecked(x, y, z)
 }
 
-/// Implements https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#section-6.6.2
-/// This is not the optimized version described in https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#appendix-G.2.3
+/// Implements https://www.rfc-editor.org/rfc/rfc9380#section-6.6.2
+/// This is not the optimized version described in https://www.rfc-editor.org/rfc/rfc9380#appendix-F.2
 #[allow(clippy::many_single_char_names)]
 fn sswu(u: Fq2) -> (Fq2, Fq2) {
     let a = Fq2 {
@@ -163,7 +166,7 @@ fn sswu(u: Fq2) -> (Fq2, Fq2) {
     (x, y)
 }
 
-/// The function sgn0 given at https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#section-4.1
+/// The function sgn0 given at https://www.rfc-editor.org/rfc/rfc9380#section-4.1
 fn sgn0(x: Fq2) -> u64 {
     let sign_0 = x.c0.into_repr().0[0] % 2;
     let zero_0 = x.c0.is_zero();
@@ -171,72 +174,28 @@ fn sgn0(x: Fq2) -> u64 {
     sign_0 | (zero_0 as u64 & sign_1)
 }
 
-/// Implements https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#section-5.4.1
-/// len_in_bytes is fixed to 256
-/// Domain separation string (dst) should be at most 255 bytes
-fn expand_message_xmd(msg: &[u8], dst: &[u8]) -> [[u8; 32]; 8] {
-    // DST_prime = DST || I2OSP(len(DST), 1)
-    let mut dst_prime = dst.to_vec();
-    dst_prime.push(dst.len().try_into().unwrap()); // panics if dst is more than 255 bytes
-
-    // b_0 = H(msg_prime), msg_prime = Z_pad || msg || l_i_b_str || I2OSP(0, 1) ||
-    // DST_prime
-    let mut h = Sha256::new();
-    h.update(vec![0; 64]); // z_pad = I2OSP(0, 64), 64 is the input block size of Sha265
-    h.update(msg);
-    h.update(vec![1, 0]); // l_i_b_str = I2OSP(256, 2)
-    h.update([0u8]);
-    h.update(&dst_prime);
-    let mut b_0: [u8; 32] = [0u8; 32];
-    b_0.copy_from_slice(h.finalize().as_slice());
-
-    // b_1 = H(b_0 || I2OSP(1, 1) || DST_prime)
-    let mut h = Sha256::new();
-    h.update(b_0);
-    h.update([1u8]);
-    h.update(&dst_prime);
-
-    let mut b = [[0u8; 32]; 8]; // b[i] corresponds to b_i+1 in specification.
-    b[0].copy_from_slice(h.finalize().as_slice());
-
-    // compute remaining uniform bytes
-    for i in 1u8..8 {
-        // b_i = H(strxor(b_0, b_i-1)  || I2OSP(i, 1) || DST_prime)
-        let mut h = Sha256::new();
-        let xor: Vec<u8> = b_0
-            .iter()
-            .zip(b[i as usize - 1].iter())
-            .map(|(x, y)| x ^ y)
-            .collect();
-        h.update(xor);
-        h.update([i + 1]); // offset as standard drops b_0 and returns index b_1-b_8
-        h.update(&dst_prime);
-        b[i as usize].copy_from_slice(h.finalize().as_slice());
-    }
-
-    b
-}
-
-/// Implements https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#section-3
+/// Implements https://www.rfc-editor.org/rfc/rfc9380#section-5.2
 /// with the choice of expand_message being expand_message_xmd, as specified in
-/// https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#section-8.8.2.
+/// https://www.rfc-editor.org/rfc/rfc9380#section-8.8.2.
 fn hash_to_field_fq2(msg: &[u8], dst: &[u8]) -> (Fq2, Fq2) {
-    let b = expand_message_xmd(msg, dst);
+    // Two elements of Fq2, each consisting of two field elements of L = 64 bytes.
+    let b = expand_message_xmd::<Sha256>(msg, dst, 256);
     let u0 = Fq2 {
-        c0: fq_from_bytes(&b[0], &b[1]),
-        c1: fq_from_bytes(&b[2], &b[3]),
+        c0: fq_from_bytes(&b[..64]),
+        c1: fq_from_bytes(&b[64..128]),
     };
     let u1 = Fq2 {
-        c0: fq_from_bytes(&b[4], &b[5]),
-        c1: fq_from_bytes(&b[6], &b[7]),
+        c0: fq_from_bytes(&b[128..192]),
+        c1: fq_from_bytes(&b[192..]),
     };
     (u0, u1)
 }
 
-// Interpret input as integers (big endian)
-// Return (left*2^256 + right) as Fq
-fn fq_from_bytes(left_bytes: &[u8; 32], right_bytes: &[u8; 32]) -> Fq {
-    fn le_u64s_from_be_bytes(bytes: &[u8; 32]) -> Fq {
+// Interpret the 64 input bytes as an integer (big endian) and reduce it
+// modulo q. With left and right being the first and last 32 bytes, this is
+// (left*2^256 + right) as Fq.
+fn fq_from_bytes(bytes: &[u8]) -> Fq {
+    fn le_u64s_from_be_bytes(bytes: &[u8]) -> Fq {
         let mut digits = [0u64; 6];
 
         for (place, chunk) in digits.iter_mut().zip(bytes.chunks(8).rev()) {
@@ -246,6 +205,7 @@ fn fq_from_bytes(left_bytes: &[u8; 32], right_bytes: &[u8; 32]) -> Fq {
         Fq::from_repr(FqRepr(digits)).expect("Only the leading 4 u64s are initialized")
     }
 
+    let (left_bytes, right_bytes) = bytes.split_at(32);
     let two_to_256_fqrepr = [0u64, 0, 0, 0, 1, 0]; // 2^256
     let two_to_256_fq = Fq::from_repr(FqRepr(two_to_256_fqrepr)).expect("2^256 fits in modulus");
 
@@ -258,7 +218,7 @@ fn fq_from_bytes(left_bytes: &[u8; 32], right_bytes: &[u8; 32]) -> Fq {
 }
 
 /// Computes the 3-isogeny map for G2, specified in
-/// https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#appendix-E.3
+/// https://www.rfc-editor.org/rfc/rfc9380#appendix-E.3
 fn iso_map(x: Fq2, y: Fq2, z: Fq2) -> (Fq2, Fq2, Fq2) {
     // Compute Z^2i for i = 1,...,15
     let mut z_pow_2i: [Fq2; 15] = [z; 15];
@@ -571,7 +531,7 @@ mod tests {
 
     #[test]
     fn test_hash_to_field_fq2() {
-        // https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#appendix-J.10.1
+        // https://www.rfc-editor.org/rfc/rfc9380#appendix-J.10.1
         let dst = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
 
         {
@@ -629,7 +589,7 @@ mod tests {
 
     #[test]
     fn test_hash_to_curve_g2() {
-        // Test vectors are from https://tools.ietf.org/html/draft-irtf-cfrg-hash-to-curve-10#appendix-J.10.1
+        // Test vectors are from https://www.rfc-editor.org/rfc/rfc9380#appendix-J.10.1
         let dst = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
         {
             //    msg     =
@@ -1,17 +1,44 @@
 // Authors:
 
-use crate::{bls12_381_g1hash::*, bls12_381_g2hash::*, curve_arithmetic::*};
+use crate::{bls12_381_g1hash::*, bls12_381_g2hash::*, curve_arithmetic::*, secret_value::Secret};
 use byteorder::ReadBytesExt;
+use crypto_common::Buffer;
 use ff::{Field, PrimeField};
 use group::{CurveAffine, CurveProjective, EncodedPoint};
 use pairing::{
     bls12_381::{
-        Bls12, Fq, Fr, FrRepr, G1Affine, G1Compressed, G1Prepared, G2Affine, G2Compressed,
-        G2Prepared, G1, G2,
+        Bls12, Fq, Fr, FrRepr, G1Affine, G1Compressed, G1Prepared, G1Uncompressed, G2Affine,
+        G2Compressed, G2Prepared, G2Uncompressed, G1, G2,
     },
     Engine, PairingCurveAffine,
 };
 use rand::*;
+use subtle::{Choice, ConditionallySelectable};
+
+/// Return `b` if `choice` is set, and `a` otherwise. The coordinates of the
+/// projective points are private to the `pairing` library, so the points are
+/// selected through their affine encodings, see [select_affine]. This is only
+/// constant time if both points are normalized, since converting other points
+/// to affine coordinates takes an inversion, whose time depends on the point.
+fn select_projective<T: CurveProjective>(a: &T, b: &T, choice: Choice) -> T {
+    select_affine(&a.into_affine(), &b.into_affine(), choice).into_projective()
+}
+
+/// Return `b` if `choice` is set, and `a` otherwise, in constant time. The
+/// affine points contain a flag for the point at infinity, so they are
+/// selected through their uncompressed encodings. This is slow, but the
+/// constant time multiplication of affine points is done with projective
+/// points.
+fn select_affine<T: CurveAffine>(a: &T, b: &T, choice: Choice) -> T {
+    let mut result = a.into_uncompressed();
+    let b = b.into_uncompressed();
+    for (x, y) in result.as_mut().iter_mut().zip(b.as_ref().iter()) {
+        *x = u8::conditional_select(x, y, choice);
+    }
+    result
+        .into_affine_unchecked()
+        .expect("The encoding of one of the points is valid.")
+}
 
 const HASH_TO_GROUP_G1_DST: &[u8; 55] = b"CONCORDIUM-hashtoG1-with-BLS12381G1_XMD:SHA-256_SSWU_RO";
 const HASH_TO_GROUP_G2_DST: &[u8; 55] = b"CONCORDIUM-hashtoG2-with-BLS12381G2_XMD:SHA-256_SSWU_RO";
@@ -76,6 +103,12 @@ impl Curve for G2 {
         p
     }
 
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        select_projective(a, b, choice)
+    }
+
+    fn batch_normalize(points: &mut [Self]) { CurveProjective::batch_normalization(points) }
+
     fn compress(&self) -> Self::Compressed { self.into_affine().into_compressed() }
 
     fn decompress(c: &Self::Compressed) -> Result<G2, CurveDecodingError> {
@@ -108,11 +141,26 @@ impl Curve for G2 {
         Ok(g.into_affine_unchecked()?.into_projective())
     }
 
+    fn curve_to_bytes_uncompressed<B: Buffer>(&self, out: &mut B) {
+        out.write_all(self.into_affine().into_uncompressed().as_ref())
+            .expect("Writing to buffer should succeed.");
+    }
+
+    fn bytes_to_curve_uncompressed_unchecked<R: ReadBytesExt>(
+        bytes: &mut R,
+    ) -> anyhow::Result<Self> {
+        let mut g = G2Uncompressed::empty();
+        bytes.read_exact(g.as_mut())?;
+        Ok(g.into_affine_unchecked()?.into_projective())
+    }
+
     fn generate<T: Rng>(csprng: &mut T) -> Self { G2::random(csprng) }
 
     fn generate_scalar<T: Rng>(csprng: &mut T) -> Self::Scalar { Fr::random(csprng) }
 
     fn hash_to_group(b: &[u8]) -> Self { hash_to_curve_g2(b, HASH_TO_GROUP_G2_DST) }
+
+    fn hash_to_group_with_dst(b: &[u8], dst: &[u8]) -> Self { hash_to_curve_g2(b, dst) }
 }
 
 impl Curve for G1 {
@@ -160,6 +208,12 @@ impl Curve for G1 {
         p
     }
 
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        select_projective(a, b, choice)
+    }
+
+    fn batch_normalize(points: &mut [Self]) { CurveProjective::batch_normalization(points) }
+
     fn compress(&self) -> Self::Compressed { self.into_affine().into_compressed() }
 
     fn decompress(c: &Self::Compressed) -> Result<G1, CurveDecodingError> {
@@ -192,11 +246,26 @@ impl Curve for G1 {
         Ok(g.into_affine_unchecked()?.into_projective())
     }
 
+    fn curve_to_bytes_uncompressed<B: Buffer>(&self, out: &mut B) {
+        out.write_all(self.into_affine().into_uncompressed().as_ref())
+            .expect("Writing to buffer should succeed.");
+    }
+
+    fn bytes_to_curve_uncompressed_unchecked<R: ReadBytesExt>(
+        bytes: &mut R,
+    ) -> anyhow::Result<Self> {
+        let mut g = G1Uncompressed::empty();
+        bytes.read_exact(g.as_mut())?;
+        Ok(g.into_affine_unchecked()?.into_projective())
+    }
+
     fn generate<T: Rng>(csprng: &mut T) -> Self { G1::random(csprng) }
 
     fn generate_scalar<T: Rng>(csprng: &mut T) -> Self::Scalar { Fr::random(csprng) }
 
     fn hash_to_group(bytes: &[u8]) -> Self { hash_to_curve(bytes, HASH_TO_GROUP_G1_DST) }
+
+    fn hash_to_group_with_dst(bytes: &[u8], dst: &[u8]) -> Self { hash_to_curve(bytes, dst) }
 }
 
 impl Curve for G1Affine {
@@ -242,6 +311,14 @@ impl Curve for G1Affine {
         self.mul(s).into_affine()
     }
 
+    fn mul_by_secret(&self, scalar: &Secret<Self::Scalar>) -> Self {
+        self.into_projective().mul_by_secret(scalar).into_affine()
+    }
+
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        select_affine(a, b, choice)
+    }
+
     fn compress(&self) -> Self::Compressed { self.into_compressed() }
 
     fn decompress(c: &Self::Compressed) -> Result<G1Affine, CurveDecodingError> {
@@ -278,6 +355,8 @@ impl Curve for G1Affine {
     fn generate_scalar<T: Rng>(csprng: &mut T) -> Self::Scalar { Fr::random(csprng) }
 
     fn hash_to_group(b: &[u8]) -> Self { hash_to_curve(b, HASH_TO_GROUP_G1_DST).into_affine() }
+
+    fn hash_to_group_with_dst(b: &[u8], dst: &[u8]) -> Self { hash_to_curve(b, dst).into_affine() }
 }
 
 impl Curve for G2Affine {
@@ -323,6 +402,14 @@ impl Curve for G2Affine {
         self.mul(s).into_affine()
     }
 
+    fn mul_by_secret(&self, scalar: &Secret<Self::Scalar>) -> Self {
+        self.into_projective().mul_by_secret(scalar).into_affine()
+    }
+
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        select_affine(a, b, choice)
+    }
+
     fn compress(&self) -> Self::Compressed { self.into_compressed() }
 
     fn decompress(c: &Self::Compressed) -> Result<G2Affine, CurveDecodingError> {
@@ -359,6 +446,10 @@ impl Curve for G2Affine {
     fn generate_scalar<T: Rng>(csprng: &mut T) -> Self::Scalar { Fr::random(csprng) }
 
     fn hash_to_group(b: &[u8]) -> Self { hash_to_curve_g2(b, HASH_TO_GROUP_G2_DST).into_affine() }
+
+    fn hash_to_group_with_dst(b: &[u8], dst: &[u8]) -> Self {
+        hash_to_curve_g2(b, dst).into_affine()
+    }
 }
 
 impl Pairing for Bls12 {
@@ -464,6 +555,29 @@ mod tests {
         };
     }
 
+    // Normalization must not change the points or their serializations.
+    fn check_batch_normalize<C: Curve>() {
+        let mut csprng = thread_rng();
+        let g = C::generate(&mut csprng);
+        let mut points = vec![C::zero_point(), g];
+        for i in 2..10 {
+            let next = points[i - 1].plus_point(&g);
+            points.push(next);
+        }
+        let original = points.clone();
+        C::batch_normalize(&mut points);
+        assert_eq!(points, original, "Normalization changed the points.");
+        for (p, q) in points.iter().zip(original.iter()) {
+            assert_eq!(to_bytes(p), to_bytes(q));
+        }
+    }
+
+    #[test]
+    fn test_batch_normalize_g1() { check_batch_normalize::<G1>() }
+
+    #[test]
+    fn test_batch_normalize_g2() { check_batch_normalize::<G2>() }
+
     macro_test_scalar_byte_conversion!(sc_bytes_conv_g1, G1);
     macro_test_scalar_byte_conversion!(sc_bytes_conv_g2, G2);
     macro_test_scalar_byte_conversion!(sc_bytes_conv_g1_affine, G1Affine);
@@ -479,4 +593,39 @@ mod tests {
     macro_test_group_byte_conversion_unchecked!(u_curve_bytes_conv_g2, G2);
     macro_test_group_byte_conversion_unchecked!(u_curve_bytes_conv_g1_affine, G1Affine);
     macro_test_group_byte_conversion_unchecked!(u_curve_bytes_conv_g2_affine, G2Affine);
+
+    // Hashing with the test domain separation tags from RFC 9380 must agree
+    // with the test vectors in appendices J.9.1 and J.10.1.
+    #[test]
+    fn test_hash_to_group_with_dst() {
+        let dst_g1 = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let dst_g2 = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+        let expected_g1 = "83567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f6903";
+        let expected_g2 = "939cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4ca3a230ed250fbe3a2acf73a41177fd802c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbec7780ccc7954725f4168aff2787776e6";
+        assert_eq!(
+            base16_encode_string(&G1::hash_to_group_with_dst(b"abc", dst_g1)),
+            expected_g1
+        );
+        assert_eq!(
+            base16_encode_string(&G1Affine::hash_to_group_with_dst(b"abc", dst_g1)),
+            expected_g1
+        );
+        assert_eq!(
+            base16_encode_string(&G2::hash_to_group_with_dst(b"abc", dst_g2)),
+            expected_g2
+        );
+        assert_eq!(
+            base16_encode_string(&G2Affine::hash_to_group_with_dst(b"abc", dst_g2)),
+            expected_g2
+        );
+        // The default hashing is the same construction with a fixed tag.
+        assert_eq!(
+            G1::hash_to_group(b"abc"),
+            G1::hash_to_group_with_dst(b"abc", HASH_TO_GROUP_G1_DST)
+        );
+        assert_eq!(
+            G2::hash_to_group(b"abc"),
+            G2::hash_to_group_with_dst(b"abc", HASH_TO_GROUP_G2_DST)
+        );
+    }
 }
@@ -0,0 +1,279 @@
+//! Multiplication of points by secret scalars in constant time.
+//!
+//! [Curve::mul_by_scalar] and the multiexponentiation functions branch on the
+//! bits of the scalar, and the time they take hence leaks information about
+//! it. This is fine for public scalars, such as challenges, but not for
+//! secrets, such as secret keys. The function [mul_by_secret_scalar] performs
+//! the same sequence of group operations and memory accesses for all scalars.
+//! It relies on the group operations themselves not depending on the points
+//! except in exceptional cases, e.g., when adding the zero point, which only
+//! happen with negligible probability.
+use crate::curve_arithmetic::Curve;
+use ff::{PrimeField, PrimeFieldRepr};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// The window size of [mul_by_secret_scalar]. The table contains the
+/// `2^{CONSTANT_TIME_WINDOW - 1}` odd multiples of the point up to
+/// `2^CONSTANT_TIME_WINDOW - 1`.
+pub const CONSTANT_TIME_WINDOW: usize = 4;
+
+/// Multiply the point by the scalar in constant time. The result is the same
+/// as `point.mul_by_scalar(scalar)`.
+///
+/// The scalar is written in the regular signed representation of
+/// [odd_signed_digits], with `m` digits that are all odd and at most `2^w - 1`
+/// in absolute value, where `w` is [CONSTANT_TIME_WINDOW] and `m` only depends
+/// on the size of the scalar field. The multiplication then takes `w`
+/// doublings and one addition of a point looked up in the table of odd
+/// multiples for each digit. Every lookup reads the entire table. The table is
+/// normalized, see [Curve::batch_normalize], so that selecting its points is
+/// constant time for all curves, and only the points of the table are ever
+/// selected.
+pub fn mul_by_secret_scalar<C: Curve>(point: &C, scalar: &C::Scalar) -> C {
+    const W: usize = CONSTANT_TIME_WINDOW;
+
+    // The odd multiples point, 3 * point, ..., (2^W - 1) * point.
+    let double = point.double_point();
+    let mut table = Vec::with_capacity(1 << (W - 1));
+    table.push(*point);
+    for i in 1..(1 << (W - 1)) {
+        let next = table[i - 1].plus_point(&double);
+        table.push(next);
+    }
+    C::batch_normalize(&mut table);
+
+    let (digits, negate) = odd_signed_digits(scalar, W);
+    let mut digits = digits.iter().rev();
+    let top = digits.next().expect("There is at least one digit.");
+    let mut acc = lookup(table.iter(), *top, negate);
+    for &digit in digits {
+        for _ in 0..W {
+            acc = acc.double_point();
+        }
+        acc = acc.plus_point(&lookup(table.iter(), digit, negate));
+    }
+    acc
+}
+
+/// The regular signed representation of the scalar `k` with window size `w`.
+/// The digits are returned least significant first, and together with the
+/// returned choice `c` they satisfy
+/// `(-1)^c k = d_0 + d_1 2^w + ... + d_{m-1} 2^{w(m-1)}` modulo the group
+/// order, where `m` is the number of windows of size `w` that cover the
+/// scalars. All the digits are odd and at most `2^w - 1` in absolute value.
+///
+/// The representation only exists for odd integers, so if `k` is even the
+/// digits represent `n - k` instead, where `n` is the group order, which is
+/// odd. This also covers `k = 0`, which is represented by `n`. The digits are
+/// computed without branching on the scalar.
+///
+/// Assumes `w` is at least 1 and at most 8, and that the group order is less
+/// than `2^N - 2^{N - 2w + 1}`, where `N` is the number of bits of the
+/// scalars, so that the most significant digit is in range. This holds for
+/// the curves used.
+pub(crate) fn odd_signed_digits<F: PrimeField>(scalar: &F, w: usize) -> (Vec<i64>, Choice) {
+    let num_digits = (F::NUM_BITS as usize + w - 1) / w;
+    let repr = scalar.into_repr();
+    let mut negated_repr = F::char();
+    negated_repr.sub_noborrow(&repr);
+    let is_even = Choice::from(((repr.as_ref()[0] & 1) ^ 1) as u8);
+    let mut limbs: Vec<u64> = repr
+        .as_ref()
+        .iter()
+        .zip(negated_repr.as_ref().iter())
+        .map(|(k, neg_k)| u64::conditional_select(k, neg_k, is_even))
+        .collect();
+
+    let mut digits = Vec::with_capacity(num_digits);
+    for _ in 0..num_digits - 1 {
+        let low = (limbs[0] & ((1 << (w + 1)) - 1)) as i64;
+        digits.push(low - (1 << w));
+        shift_right(&mut limbs, w);
+        limbs[0] |= 1;
+    }
+    digits.push(limbs[0] as i64);
+    debug_assert!(digits[num_digits - 1] < 1 << w);
+    (digits, is_even)
+}
+
+/// Shift the little-endian integer given by its limbs right by `shift` bits,
+/// which must be less than 64.
+fn shift_right(limbs: &mut [u64], shift: usize) {
+    let n = limbs.len();
+    for i in 0..n {
+        let high = if i + 1 < n {
+            limbs[i + 1] << (64 - shift)
+        } else {
+            0
+        };
+        limbs[i] = (limbs[i] >> shift) | high;
+    }
+}
+
+/// The multiple of a point given by the odd digit, negated if `negate` is set,
+/// from the odd multiples `1, 3, 5, ...` of the point. All the multiples are
+/// read regardless of the digit. The multiples must be normalized, see
+/// [Curve::conditional_select].
+pub(crate) fn lookup<'a, C: Curve + 'a>(
+    mut odd_multiples: impl Iterator<Item = &'a C>,
+    digit: i64,
+    negate: Choice,
+) -> C {
+    let sign = digit >> 63;
+    let abs = ((digit ^ sign) - sign) as u64;
+    // The multiple 2 * i + 1 is the i-th one.
+    let index = abs >> 1;
+    let mut p = *odd_multiples
+        .next()
+        .expect("There is at least one multiple.");
+    for (i, q) in odd_multiples.enumerate() {
+        p = C::conditional_select(&p, q, (i as u64 + 1).ct_eq(&index));
+    }
+    let negate = Choice::from((sign & 1) as u8) ^ negate;
+    C::conditional_select(&p, &p.inverse_point(), negate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ristretto255, Secret};
+    use ff::Field;
+    use pairing::bls12_381::{G1Affine, G2Affine, G1, G2};
+    use rand::*;
+    use std::time::Instant;
+
+    fn check_mul<C: Curve>() {
+        let mut csprng = thread_rng();
+        let point = C::generate(&mut csprng);
+        let mut minus_one = C::scalar_from_u64(1);
+        minus_one.negate();
+        let special = [
+            C::Scalar::zero(),
+            C::scalar_from_u64(1),
+            C::scalar_from_u64(2),
+            C::scalar_from_u64(15),
+            C::scalar_from_u64(16),
+            minus_one,
+        ];
+        for scalar in special
+            .iter()
+            .copied()
+            .chain((0..20).map(|_| C::generate_scalar(&mut csprng)))
+        {
+            assert_eq!(
+                mul_by_secret_scalar(&point, &scalar),
+                point.mul_by_scalar(&scalar),
+                "Constant time multiplication by {:?} is incorrect.",
+                scalar
+            );
+            assert_eq!(
+                point.mul_by_secret(&Secret::new(scalar)),
+                point.mul_by_scalar(&scalar)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_mul_by_secret_scalar_g1() { check_mul::<G1>() }
+
+    #[test]
+    pub fn test_mul_by_secret_scalar_g2() { check_mul::<G2>() }
+
+    #[test]
+    pub fn test_mul_by_secret_scalar_g1_affine() { check_mul::<G1Affine>() }
+
+    #[test]
+    pub fn test_mul_by_secret_scalar_g2_affine() { check_mul::<G2Affine>() }
+
+    #[test]
+    pub fn test_mul_by_secret_scalar_ristretto255() { check_mul::<Ristretto255>() }
+
+    #[test]
+    pub fn test_conditional_select() {
+        let mut csprng = thread_rng();
+        let a = G1Affine::generate(&mut csprng);
+        let b = G1Affine::zero_point();
+        assert_eq!(G1Affine::conditional_select(&a, &b, Choice::from(0)), a);
+        assert_eq!(G1Affine::conditional_select(&a, &b, Choice::from(1)), b);
+        let a = G2::generate(&mut csprng);
+        let b = G2::generate(&mut csprng);
+        assert_eq!(G2::conditional_select(&a, &b, Choice::from(0)), a);
+        assert_eq!(G2::conditional_select(&a, &b, Choice::from(1)), b);
+    }
+
+    /// Welch's t-statistic of two samples.
+    fn t_statistic(xs: &[f64], ys: &[f64]) -> f64 {
+        let mean = |s: &[f64]| s.iter().sum::<f64>() / s.len() as f64;
+        let var = |s: &[f64], m: f64| {
+            s.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / (s.len() - 1) as f64
+        };
+        let (mx, my) = (mean(xs), mean(ys));
+        let (vx, vy) = (var(xs, mx), var(ys, my));
+        (mx - my) / (vx / xs.len() as f64 + vy / ys.len() as f64).sqrt()
+    }
+
+    /// A dudect-style test: time the function on inputs from two classes,
+    /// chosen at random for each measurement, and compare the distributions
+    /// of the timings with Welch's t-test. The measurements above a
+    /// percentile are discarded, for several percentiles, since the
+    /// distributions have long tails caused by interruptions. Returns the
+    /// largest absolute value of the t-statistic.
+    fn dudect<C: Curve>(
+        f: impl Fn(&C, &C::Scalar) -> C,
+        fixed: C::Scalar,
+        measurements: usize,
+    ) -> f64 {
+        let mut csprng = thread_rng();
+        let point = C::generate(&mut csprng);
+        let mut timings = [Vec::new(), Vec::new()];
+        for _ in 0..measurements {
+            let class = csprng.gen::<bool>() as usize;
+            let scalar = if class == 0 {
+                fixed
+            } else {
+                C::generate_scalar(&mut csprng)
+            };
+            let start = Instant::now();
+            let result = f(&point, &scalar);
+            let elapsed = start.elapsed().as_nanos() as f64;
+            // Make sure the result is used.
+            assert!(!result.is_zero_point() || scalar.is_zero());
+            timings[class].push(elapsed);
+        }
+        let mut all: Vec<f64> = timings.iter().flatten().copied().collect();
+        all.sort_by(|a, b| a.partial_cmp(b).expect("Timings are not NaN."));
+        [0.5, 0.75, 0.9, 0.99, 1.0]
+            .iter()
+            .map(|p| {
+                let index = ((all.len() - 1) as f64 * p) as usize;
+                let threshold = all[index];
+                let crop = |s: &[f64]| -> Vec<f64> {
+                    s.iter().copied().filter(|&t| t <= threshold).collect()
+                };
+                let xs = crop(&timings[0]);
+                let ys = crop(&timings[1]);
+                t_statistic(&xs, &ys).abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// The timing test is noisy, and only meaningful in release mode, so it is
+    /// not run by default. Run it with
+    /// `cargo test --release -p curve_arithmetic -- --ignored dudect`.
+    #[test]
+    #[ignore]
+    pub fn test_dudect_mul_by_secret_scalar() {
+        // A scalar with few bits set is the fastest for the variable time
+        // multiplication.
+        let fixed = <G1 as Curve>::scalar_from_u64(1);
+        let t = dudect::<G1>(mul_by_secret_scalar, fixed, 20000);
+        // The threshold commonly used by dudect for a leak.
+        assert!(
+            t < 4.5,
+            "Timing of multiplication by a secret depends on the scalar, t = {}.",
+            t
+        );
+        let t = dudect::<G1>(|p, s| p.mul_by_scalar(s), fixed, 20000);
+        println!("t-statistic of the variable time multiplication: {}", t);
+    }
+}
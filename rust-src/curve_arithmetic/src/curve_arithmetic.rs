@@ -1,11 +1,15 @@
+use crate::{
+    constant_time::mul_by_secret_scalar, pippenger::MultiexpStrategy, secret_value::Secret,
+};
 use byteorder::ReadBytesExt;
-use crypto_common::{Serial, Serialize};
+use crypto_common::{Buffer, Serial, Serialize};
 use ff::{Field, PrimeField};
 use rand::*;
 use std::{
     borrow::Borrow,
     fmt::{Debug, Display},
 };
+use subtle::Choice;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -51,9 +55,29 @@ pub trait Curve:
     fn minus_point(&self, other: &Self) -> Self;
     #[must_use]
     /// Exponentiation by a scalar, i.e., compute n * x for a group element x
-    /// and integer n.
+    /// and integer n. The time this takes depends on the scalar, so it must
+    /// not be used with secret scalars, see [Curve::mul_by_secret].
     fn mul_by_scalar(&self, scalar: &Self::Scalar) -> Self;
     #[must_use]
+    /// Exponentiation by a secret scalar in constant time. The result is the
+    /// same as that of [Curve::mul_by_scalar], but slower. The default
+    /// implementation uses [mul_by_secret_scalar](crate::mul_by_secret_scalar).
+    fn mul_by_secret(&self, scalar: &Secret<Self::Scalar>) -> Self {
+        mul_by_secret_scalar(self, scalar.as_ref())
+    }
+    #[must_use]
+    /// Return `b` if `choice` is set, and `a` otherwise, in constant time.
+    /// For some representations, e.g., the projective points of BLS12-381,
+    /// this is only constant time if both points are normalized, see
+    /// [Curve::batch_normalize].
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self;
+    /// Normalize the representations of the points without changing the
+    /// points themselves, so that they are cheaper to serialize or compare.
+    /// For projective points this converts them to affine coordinates, using
+    /// a single simultaneous inversion for all of them. The default
+    /// implementation does nothing.
+    fn batch_normalize(_points: &mut [Self]) {}
+    #[must_use]
     fn compress(&self) -> Self::Compressed;
     fn decompress(c: &Self::Compressed) -> Result<Self, CurveDecodingError>;
     fn decompress_unchecked(c: &Self::Compressed) -> Result<Self, CurveDecodingError>;
@@ -61,6 +85,18 @@ pub trait Curve:
     /// the group itself. This can be cheaper if the source of the value is
     /// trusted, but it must not be used on untrusted sources.
     fn bytes_to_curve_unchecked<R: ReadBytesExt>(b: &mut R) -> anyhow::Result<Self>;
+    /// Serialize the point without compression. This is larger than the
+    /// serialization given by [Serial], but cheaper to read back with
+    /// [Curve::bytes_to_curve_uncompressed_unchecked], which matters for large
+    /// precomputed tables. The default implementation uses the compressed
+    /// serialization.
+    fn curve_to_bytes_uncompressed<B: Buffer>(&self, out: &mut B) { self.serial(out) }
+    /// Deserialize a point written by [Curve::curve_to_bytes_uncompressed],
+    /// but do not check that it is in the group. This must only be used on
+    /// trusted sources.
+    fn bytes_to_curve_uncompressed_unchecked<R: ReadBytesExt>(b: &mut R) -> anyhow::Result<Self> {
+        Self::bytes_to_curve_unchecked(b)
+    }
     /// Generate a random group element, uniformly distributed.
     fn generate<R: Rng>(rng: &mut R) -> Self;
     /// Generate a random scalar value, uniformly distributed.
@@ -83,6 +119,12 @@ pub trait Curve:
     fn scalar_from_bytes<A: AsRef<[u8]>>(bs: A) -> Self::Scalar;
     /// Hash to a curve point from a seed. This is deterministic function.
     fn hash_to_group(m: &[u8]) -> Self;
+    /// Hash to a curve point using the given domain separation tag. For the
+    /// BLS12-381 groups this is the hash-to-curve suite from RFC 9380
+    /// (`BLS12381G1_XMD:SHA-256_SSWU_RO_` and
+    /// `BLS12381G2_XMD:SHA-256_SSWU_RO_`), so that the result agrees with
+    /// other implementations of the standard.
+    fn hash_to_group_with_dst(m: &[u8], dst: &[u8]) -> Self;
 }
 
 /// A pairing friendly curve is a collection of two groups and a pairing
@@ -174,12 +216,15 @@ pub trait Pairing: Sized + 'static + Clone {
     }
 }
 
-/// Like 'multiexp_worker', but computes a reasonable window size automatically.
+/// Compute the multiexponentiation `gs[0]^exps[0] * ... * gs[n-1]^exps[n-1]`
+/// with either the wNAF method of 'multiexp_worker' or Pippenger's method of
+/// 'multiexp_pippenger', and a window size, chosen automatically based on the
+/// number of terms. See [MultiexpStrategy::for_length].
+///
+/// Assumes the lengths of inputs are the same.
 #[inline(always)]
 pub fn multiexp<C: Curve, X: Borrow<C>>(gs: &[X], exps: &[C::Scalar]) -> C {
-    // This number is based on the benchmark in benches/multiexp_bench.rs
-    let window_size = 4;
-    multiexp_worker(gs, exps, window_size)
+    MultiexpStrategy::for_length::<C>(exps.len()).multiexp(gs, exps)
 }
 
 /// This implements the WNAF method from
@@ -212,9 +257,9 @@ pub fn multiexp_worker<C: Curve, X: Borrow<C>>(
 ///
 /// See <https://link.springer.com/content/pdf/10.1007%2F3-540-45537-X_13.pdf> for what it means
 /// for the table to be computed correctly.
-pub fn multiexp_worker_given_table<C: Curve>(
+pub fn multiexp_worker_given_table<C: Curve, T: AsRef<[C]>>(
     exps: &[C::Scalar],
-    table: &[Vec<C>],
+    table: &[T],
     window_size: usize,
 ) -> C {
     // Compute the wnaf
@@ -266,10 +311,10 @@ pub fn multiexp_worker_given_table<C: Curve>(
         for (wnaf_i, table_i) in wnaf.iter().zip(table.iter()) {
             match wnaf_i.get(j) {
                 Some(&ge) if ge > 0 => {
-                    a = a.plus_point(&table_i[(ge / 2) as usize]);
+                    a = a.plus_point(&table_i.as_ref()[(ge / 2) as usize]);
                 }
                 Some(&ge) if ge < 0 => {
-                    a = a.minus_point(&table_i[((-ge) / 2) as usize]);
+                    a = a.minus_point(&table_i.as_ref()[((-ge) / 2) as usize]);
                 }
                 _ => (),
             }
@@ -0,0 +1,236 @@
+//! Implementation of `expand_message_xmd` from
+//! <https://www.rfc-editor.org/rfc/rfc9380#section-5.3.1>. This is the
+//! function that all the hash-to-curve suites in this crate use to produce
+//! uniformly random bytes from a message and a domain separation tag.
+use sha2::digest::{generic_array::typenum::Unsigned, BlockInput, Digest};
+
+/// Prefix used when hashing domain separation tags longer than 255 bytes, see
+/// <https://www.rfc-editor.org/rfc/rfc9380#section-5.3.3>.
+const OVERSIZE_DST_PREFIX: &[u8] = b"H2C-OVERSIZE-DST-";
+
+/// Produce `len_in_bytes` uniformly random bytes from the message `msg` and
+/// domain separation tag `dst`, using the hash function `H`. Domain separation
+/// tags longer than 255 bytes are hashed first, as specified in the RFC.
+///
+/// # Panics
+/// The RFC only allows outputs of at most 65535 bytes and at most 255 times
+/// the digest size of `H`. This function panics if `len_in_bytes` exceeds
+/// these limits. All callers in this crate request fixed, small lengths.
+pub fn expand_message_xmd<H: Digest + BlockInput>(
+    msg: &[u8],
+    dst: &[u8],
+    len_in_bytes: usize,
+) -> Vec<u8> {
+    let b_in_bytes = H::output_size();
+    let r_in_bytes = <H::BlockSize as Unsigned>::USIZE;
+    // 1. ell = ceil(len_in_bytes / b_in_bytes)
+    let ell = (len_in_bytes + b_in_bytes - 1) / b_in_bytes;
+    // 2. ABORT if ell > 255 or len_in_bytes > 65535
+    assert!(
+        ell <= 255 && len_in_bytes <= 65535,
+        "expand_message_xmd: requested output is too long."
+    );
+    // 3. DST_prime = DST || I2OSP(len(DST), 1), where overly long tags are
+    // replaced by H("H2C-OVERSIZE-DST-" || DST).
+    let mut dst_prime = if dst.len() > 255 {
+        H::new()
+            .chain(OVERSIZE_DST_PREFIX)
+            .chain(dst)
+            .finalize()
+            .to_vec()
+    } else {
+        dst.to_vec()
+    };
+    dst_prime.push(dst_prime.len() as u8);
+
+    // 4.-7. b_0 = H(Z_pad || msg || l_i_b_str || I2OSP(0, 1) || DST_prime)
+    // where Z_pad = I2OSP(0, r_in_bytes) and l_i_b_str = I2OSP(len_in_bytes, 2)
+    let b_0 = H::new()
+        .chain(vec![0u8; r_in_bytes])
+        .chain(msg)
+        .chain((len_in_bytes as u16).to_be_bytes())
+        .chain([0u8])
+        .chain(&dst_prime)
+        .finalize();
+
+    // 8. b_1 = H(b_0 || I2OSP(1, 1) || DST_prime)
+    let mut b_i = H::new()
+        .chain(&b_0)
+        .chain([1u8])
+        .chain(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = Vec::with_capacity(ell * b_in_bytes);
+    uniform_bytes.extend_from_slice(&b_i);
+    for i in 2..=ell {
+        // 10. b_i = H(strxor(b_0, b_(i - 1)) || I2OSP(i, 1) || DST_prime)
+        let xor: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(x, y)| x ^ y).collect();
+        b_i = H::new()
+            .chain(xor)
+            .chain([i as u8])
+            .chain(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    // 11.-12. uniform_bytes = b_1 || ... || b_ell, truncated to len_in_bytes
+    uniform_bytes.truncate(len_in_bytes);
+    uniform_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Sha256, Sha512};
+
+    fn to_hex(bytes: &[u8]) -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() }
+
+    fn test_messages() -> Vec<Vec<u8>> {
+        let mut q128 = b"q128_".to_vec();
+        q128.extend_from_slice(&[b'q'; 128]);
+        let mut a512 = b"a512_".to_vec();
+        a512.extend_from_slice(&[b'a'; 512]);
+        vec![
+            b"".to_vec(),
+            b"abc".to_vec(),
+            b"abcdef0123456789".to_vec(),
+            q128,
+            a512,
+        ]
+    }
+
+    // Test vectors from https://www.rfc-editor.org/rfc/rfc9380#appendix-K.1
+    #[test]
+    fn test_expand_message_xmd_sha256() {
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        let expected_short = [
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235",
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615",
+            "eff31487c770a893cfb36f912fbfcbff40d5661771ca4b2cb4eafe524333f5c1",
+            "b23a1d2b4d97b2ef7785562a7e8bac7eed54ed6e97e29aa51bfe3f12ddad1ff9",
+            "4623227bcc01293b8c130bf771da8c298dede7383243dc0993d2d94823958c4c",
+        ];
+        let expected_long = [
+            "af84c27ccfd45d41914fdff5df25293e221afc53d8ad2ac06d5e3e29485dadbee0d121587713a3e0dd4d\
+             5e69e93eb7cd4f5df4cd103e188cf60cb02edc3edf18eda8576c412b18ffb658e3dd6ec849469b979d44\
+             4cf7b26911a08e63cf31f9dcc541708d3491184472c2c29bb749d4286b004ceb5ee6b9a7fa5b646c993f\
+             0ced",
+            "abba86a6129e366fc877aab32fc4ffc70120d8996c88aee2fe4b32d6c7b6437a647e6c3163d40b76a73c\
+             f6a5674ef1d890f95b664ee0afa5359a5c4e07985635bbecbac65d747d3d2da7ec2b8221b17b0ca9dc8a\
+             1ac1c07ea6a1e60583e2cb00058e77b7b72a298425cd1b941ad4ec65e8afc50303a22c0f99b0509b4c89\
+             5f40",
+            "ef904a29bffc4cf9ee82832451c946ac3c8f8058ae97d8d629831a74c6572bd9ebd0df635cd1f208e203\
+             8e760c4994984ce73f0d55ea9f22af83ba4734569d4bc95e18350f740c07eef653cbb9f87910d8337518\
+             25f0ebefa1abe5420bb52be14cf489b37fe1a72f7de2d10be453b2c9d9eb20c7e3f6edc5a60629178d94\
+             78df",
+            "80be107d0884f0d881bb460322f0443d38bd222db8bd0b0a5312a6fedb49c1bbd88fd75d8b9a09486c60\
+             123dfa1d73c1cc3169761b17476d3c6b7cbbd727acd0e2c942f4dd96ae3da5de368d26b32286e32de7e5\
+             a8cb2949f866a0b80c58116b29fa7fabb3ea7d520ee603e0c25bcaf0b9a5e92ec6a1fe4e0391d1cdbce8\
+             c68a",
+            "546aff5444b5b79aa6148bd81728704c32decb73a3ba76e9e75885cad9def1d06d6792f8a7d12794e90e\
+             fed817d96920d728896a4510864370c207f99bd4a608ea121700ef01ed879745ee3e4ceef777eda6d9e5\
+             e38b90c86ea6fb0b36504ba4a45d22e86f6db5dd43d98a294bebb9125d5b794e9d2a81181066eb954966\
+             a487",
+        ];
+        for ((msg, short), long) in test_messages()
+            .iter()
+            .zip(expected_short.iter())
+            .zip(expected_long.iter())
+        {
+            assert_eq!(
+                to_hex(&expand_message_xmd::<Sha256>(msg, dst, 0x20)),
+                *short
+            );
+            assert_eq!(to_hex(&expand_message_xmd::<Sha256>(msg, dst, 0x80)), *long);
+        }
+    }
+
+    // Test vectors from https://www.rfc-editor.org/rfc/rfc9380#appendix-K.2
+    // These exercise the hashing of domain separation tags that are longer
+    // than 255 bytes.
+    #[test]
+    fn test_expand_message_xmd_sha256_long_dst() {
+        let mut dst = b"QUUX-V01-CS02-with-expander-SHA256-128-long-DST-".to_vec();
+        dst.extend_from_slice(&[b'1'; 208]);
+        assert_eq!(dst.len(), 256);
+        let expected = [
+            "e8dc0c8b686b7ef2074086fbdd2f30e3f8bfbd3bdf177f73f04b97ce618a3ed3",
+            "52dbf4f36cf560fca57dedec2ad924ee9c266341d8f3d6afe5171733b16bbb12",
+            "35387dcf22618f3728e6c686490f8b431f76550b0b2c61cbc1ce7001536f4521",
+            "01b637612bb18e840028be900a833a74414140dde0c4754c198532c3a0ba42bc",
+            "20cce7033cabc5460743180be6fa8aac5a103f56d481cf369a8accc0c374431b",
+        ];
+        for (msg, expected) in test_messages().iter().zip(expected.iter()) {
+            assert_eq!(
+                to_hex(&expand_message_xmd::<Sha256>(msg, &dst, 0x20)),
+                *expected
+            );
+        }
+    }
+
+    // Test vectors from https://www.rfc-editor.org/rfc/rfc9380#appendix-K.3
+    #[test]
+    fn test_expand_message_xmd_sha512() {
+        let dst = b"QUUX-V01-CS02-with-expander-SHA512-256";
+        let expected_short = [
+            "6b9a7312411d92f921c6f68ca0b6380730a1a4d982c507211a90964c394179ba",
+            "0da749f12fbe5483eb066a5f595055679b976e93abe9be6f0f6318bce7aca8dc",
+            "087e45a86e2939ee8b91100af1583c4938e0f5fc6c9db4b107b83346bc967f58",
+            "7336234ee9983902440f6bc35b348352013becd88938d2afec44311caf8356b3",
+            "57b5f7e766d5be68a6bfe1768e3c2b7f1228b3e4b3134956dd73a59b954c66f4",
+        ];
+        let expected_long = [
+            "41b037d1734a5f8df225dd8c7de38f851efdb45c372887be655212d07251b921b052b62eaed99b46f72f\
+             2ef4cc96bfaf254ebbbec091e1a3b9e4fb5e5b619d2e0c5414800a1d882b62bb5cd1778f098b8eb6cb39\
+             9d5d9d18f5d5842cf5d13d7eb00a7cff859b605da678b318bd0e65ebff70bec88c753b159a805d2c89c5\
+             5961",
+            "7f1dddd13c08b543f2e2037b14cefb255b44c83cc397c1786d975653e36a6b11bdd7732d8b38adb4a0ed\
+             c26a0cef4bb45217135456e58fbca1703cd6032cb1347ee720b87972d63fbf232587043ed2901bce7f22\
+             610c0419751c065922b488431851041310ad659e4b23520e1772ab29dcdeb2002222a363f0c2b1c972b3\
+             efe1",
+            "3f721f208e6199fe903545abc26c837ce59ac6fa45733f1baaf0222f8b7acb0424814fcb5eecf6c1d38f\
+             06e9d0a6ccfbf85ae612ab8735dfdf9ce84c372a77c8f9e1c1e952c3a61b7567dd0693016af51d274582\
+             2663d0c2367e3f4f0bed827feecc2aaf98c949b5ed0d35c3f1023d64ad1407924288d366ea159f46287e\
+             61ac",
+            "b799b045a58c8d2b4334cf54b78260b45eec544f9f2fb5bd12fb603eaee70db7317bf807c406e2637392\
+             2b7b8920fa29142703dd52bdf280084fb7ef69da78afdf80b3586395b433dc66cde048a258e476a561e9\
+             deba7060af40adf30c64249ca7ddea79806ee5beb9a1422949471d267b21bc88e688e4014087a0b592b6\
+             95ed",
+            "05b0bfef265dcee87654372777b7c44177e2ae4c13a27f103340d9cd11c86cb2426ffcad5bd964080c2a\
+             ee97f03be1ca18e30a1f14e27bc11ebbd650f305269cc9fb1db08bf90bfc79b42a952b46daf810359e7b\
+             c36452684784a64952c343c52e5124cd1f71d474d5197fefc571a92929c9084ffe1112cf5eea5192ebff\
+             330b",
+        ];
+        for ((msg, short), long) in test_messages()
+            .iter()
+            .zip(expected_short.iter())
+            .zip(expected_long.iter())
+        {
+            assert_eq!(
+                to_hex(&expand_message_xmd::<Sha512>(msg, dst, 0x20)),
+                *short
+            );
+            assert_eq!(to_hex(&expand_message_xmd::<Sha512>(msg, dst, 0x80)), *long);
+        }
+    }
+
+    // Test vectors from draft 10 of the hash-to-curve specification, which
+    // used a different domain separation tag. The expansion itself has not
+    // changed since then.
+    #[test]
+    fn test_expand_message_xmd_draft10() {
+        let dst = b"QUUX-V01-CS02-with-expander";
+        assert_eq!(
+            to_hex(&expand_message_xmd::<Sha256>(b"", dst, 0x80)),
+            "8bcffd1a3cae24cf9cd7ab85628fd111bb17e3739d3b53f89580d217aa79526f1708354a76a402d3569d\
+             6a9d19ef3de4d0b991e4f54b9f20dcde9b95a66824cbdf6c1a963a1913d43fd7ac443a02fc5d9d8d77e2\
+             071b86ab114a9f34150954a7531da568a1ea8c760861c0cde2005afc2c114042ee7b5848f5303f0611cf\
+             297f"
+        );
+        assert_eq!(
+            to_hex(&expand_message_xmd::<Sha256>(b"abc", dst, 0x80)),
+            "fe994ec51bdaa821598047b3121c149b364b178606d5e72bfbb713933acc29c186f316baecf7ea22212f\
+             2496ef3f785a27e84a40d8b299cec56032763eceeff4c61bd1fe65ed81decafff4a31d0198619c0aa0c6\
+             c51fca15520789925e813dcfd318b542f8799441271f4db9ee3b8092a7a2e8d5b75b73e28fb1ab6b4573\
+             c192"
+        );
+    }
+}
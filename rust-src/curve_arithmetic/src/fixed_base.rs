@@ -0,0 +1,261 @@
+//! Precomputed tables for multiplying a fixed base point by scalars.
+use crate::{
+    constant_time::{lookup, odd_signed_digits},
+    curve_arithmetic::Curve,
+    pippenger::window_digit,
+    secret_value::Secret,
+};
+use anyhow::bail;
+use crypto_common::*;
+use ff::PrimeField;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// The largest window size supported by [FixedBaseTable]. The table has
+/// `2^window_size - 1` points for each window.
+pub const MAX_FIXED_BASE_WINDOW: usize = 8;
+
+/// The window size of the tables built for the generators that are used
+/// throughout, e.g., those of the global context. For scalars of 255 bits this
+/// is a table of 960 points, and a multiplication by a scalar takes at most 64
+/// additions and no doublings.
+pub const DEFAULT_FIXED_BASE_WINDOW: usize = 4;
+
+/// A table of multiples of a fixed base point. Scalars are split into windows
+/// of `window_size` bits, and the table contains, for each window `i` and each
+/// digit `d` in `1..2^window_size`, the point `d * 2^{i * window_size} *
+/// base`. A multiplication by a scalar then takes one addition for each
+/// non-zero digit, and no doublings. The points of the table are normalized,
+/// see [Curve::batch_normalize], so that they can be selected in constant time
+/// by [FixedBaseTable::mul_by_secret].
+///
+/// The table is serialized with uncompressed points, which are not checked to
+/// be in the group when deserializing, so that loading a table is cheaper than
+/// building it. A table must therefore only be deserialized from a trusted
+/// source, e.g., one shipped with the application.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedBaseTable<C: Curve> {
+    window_size: usize,
+    /// For each window the multiples of the base for the digits `1, 2, ...`.
+    table:       Vec<Vec<C>>,
+}
+
+/// The number of windows of the given size needed to cover the scalars.
+fn num_windows<C: Curve>(window_size: usize) -> usize {
+    (C::Scalar::NUM_BITS as usize + window_size - 1) / window_size
+}
+
+impl<C: Curve> FixedBaseTable<C> {
+    /// Build the table for the given base.
+    ///
+    /// Assumes the window size is at least 1 and at most
+    /// [MAX_FIXED_BASE_WINDOW].
+    pub fn new(base: &C, window_size: usize) -> Self {
+        assert!(window_size >= 1);
+        assert!(window_size <= MAX_FIXED_BASE_WINDOW);
+        let num_windows = num_windows::<C>(window_size);
+        let mut table = Vec::with_capacity(num_windows);
+        // The base of the current window, 2^{i * window_size} * base.
+        let mut window_base = *base;
+        for _ in 0..num_windows {
+            let mut row = Vec::with_capacity((1 << window_size) - 1);
+            let mut multiple = window_base;
+            row.push(multiple);
+            for _ in 2..(1 << window_size) {
+                multiple = multiple.plus_point(&window_base);
+                row.push(multiple);
+            }
+            window_base = multiple.plus_point(&window_base);
+            C::batch_normalize(&mut row);
+            table.push(row);
+        }
+        FixedBaseTable { window_size, table }
+    }
+
+    /// The base point the table was built for.
+    pub fn base(&self) -> &C { &self.table[0][0] }
+
+    /// The window size of the table.
+    pub fn window_size(&self) -> usize { self.window_size }
+
+    /// Multiply the base by the given scalar. The result is the same as
+    /// `self.base().mul_by_scalar(scalar)`.
+    pub fn mul_by_scalar(&self, scalar: &C::Scalar) -> C {
+        let repr = scalar.into_repr();
+        let mut acc = C::zero_point();
+        for (i, row) in self.table.iter().enumerate() {
+            let digit = window_digit(repr.as_ref(), i * self.window_size, self.window_size);
+            if digit != 0 {
+                acc = acc.plus_point(&row[digit - 1]);
+            }
+        }
+        acc
+    }
+
+    /// Multiply the base by the given secret scalar in constant time. The
+    /// result is the same as `self.base().mul_by_secret(scalar)`.
+    ///
+    /// The scalar is written with the odd signed digits of
+    /// [mul_by_secret_scalar](crate::mul_by_secret_scalar), which are in range
+    /// for the window size of the table. The odd multiples in each row are
+    /// then all read to look up the digit of that window, and the
+    /// multiplication takes one addition for each window.
+    pub fn mul_by_secret(&self, scalar: &Secret<C::Scalar>) -> C {
+        let (digits, negate) = odd_signed_digits(scalar.as_ref(), self.window_size);
+        let mut acc = lookup(self.table[0].iter().step_by(2), digits[0], negate);
+        for (row, &digit) in self.table.iter().zip(digits.iter()).skip(1) {
+            acc = acc.plus_point(&lookup(row.iter().step_by(2), digit, negate));
+        }
+        acc
+    }
+}
+
+impl<C: Curve> Serial for FixedBaseTable<C> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        out.put(&(self.window_size as u8));
+        for row in self.table.iter() {
+            for point in row.iter() {
+                point.curve_to_bytes_uncompressed(out);
+            }
+        }
+    }
+}
+
+impl<C: Curve> Deserial for FixedBaseTable<C> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let window_size: u8 = source.get()?;
+        let window_size = usize::from(window_size);
+        if !(1..=MAX_FIXED_BASE_WINDOW).contains(&window_size) {
+            bail!("Unsupported window size {}.", window_size)
+        }
+        let num_windows = num_windows::<C>(window_size);
+        let mut table = Vec::with_capacity(num_windows);
+        for _ in 0..num_windows {
+            let mut row = Vec::with_capacity((1 << window_size) - 1);
+            for _ in 1..(1 << window_size) {
+                row.push(C::bytes_to_curve_uncompressed_unchecked(source)?);
+            }
+            table.push(row);
+        }
+        Ok(FixedBaseTable { window_size, table })
+    }
+}
+
+/// A table built from some generators, e.g., a [FixedBaseTable], cached
+/// together with the generators and shared between their clones. The
+/// generators can change after the table is built, e.g., when they are public
+/// fields, so the table is checked against them whenever it is used, and
+/// rebuilt if it was built for other generators.
+pub struct TableCache<T>(Arc<RwLock<Option<Arc<T>>>>);
+
+impl<T> Clone for TableCache<T> {
+    fn clone(&self) -> Self { TableCache(Arc::clone(&self.0)) }
+}
+
+impl<T> Default for TableCache<T> {
+    fn default() -> Self { TableCache(Arc::default()) }
+}
+
+impl<T> TableCache<T> {
+    /// The cached table if `matches` holds for it, and otherwise the table
+    /// built by `build`, which replaces the cached one.
+    pub fn get_or_build(&self, matches: impl Fn(&T) -> bool, build: impl FnOnce() -> T) -> Arc<T> {
+        if let Some(table) = self
+            .0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .filter(|table| matches(table))
+        {
+            return Arc::clone(table);
+        }
+        let table = Arc::new(build());
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::clone(&table));
+        table
+    }
+
+    /// The cached table, if there is one.
+    pub fn get(&self) -> Option<Arc<T>> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Cache the given table, e.g., one that was shipped with the application
+    /// instead of building it, replacing the cached one.
+    pub fn set(&self, table: T) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(table));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ristretto255;
+    use ff::Field;
+    use pairing::bls12_381::{G1, G2};
+    use rand::*;
+
+    fn check_table<C: Curve>() {
+        let mut csprng = thread_rng();
+        let base = C::generate(&mut csprng);
+        for window_size in 1..=MAX_FIXED_BASE_WINDOW {
+            let table = FixedBaseTable::new(&base, window_size);
+            assert_eq!(*table.base(), base);
+            for _ in 0..10 {
+                let scalar = C::generate_scalar(&mut csprng);
+                assert_eq!(
+                    table.mul_by_scalar(&scalar),
+                    base.mul_by_scalar(&scalar),
+                    "Multiplication with a table of window size {} is incorrect.",
+                    window_size
+                );
+            }
+            assert!(table.mul_by_scalar(&C::scalar_from_u64(0)).is_zero_point());
+            let mut minus_one = C::scalar_from_u64(1);
+            minus_one.negate();
+            for scalar in [C::scalar_from_u64(0), C::scalar_from_u64(1), minus_one]
+                .iter()
+                .copied()
+                .chain((0..10).map(|_| C::generate_scalar(&mut csprng)))
+            {
+                assert_eq!(
+                    table.mul_by_secret(&Secret::new(scalar)),
+                    base.mul_by_scalar(&scalar),
+                    "Constant time multiplication with a table of window size {} is incorrect.",
+                    window_size
+                );
+            }
+        }
+        let table = FixedBaseTable::new(&base, DEFAULT_FIXED_BASE_WINDOW);
+        let table2 = serialize_deserialize(&table).expect("Deserialization should succeed.");
+        assert_eq!(table, table2);
+    }
+
+    #[test]
+    pub fn test_table_cache() {
+        let mut csprng = thread_rng();
+        let cache = TableCache::<FixedBaseTable<G1>>::default();
+        let base = G1::generate(&mut csprng);
+        let build = |base: &G1| FixedBaseTable::new(base, DEFAULT_FIXED_BASE_WINDOW);
+        let table = cache.get_or_build(|t| *t.base() == base, || build(&base));
+        // The table is shared between clones of the cache.
+        let clone = cache.clone();
+        let shared = clone.get_or_build(|t| *t.base() == base, || panic!("Should be cached."));
+        assert!(Arc::ptr_eq(&table, &shared));
+        // A table for other generators is rebuilt.
+        let other_base = G1::generate(&mut csprng);
+        let other = clone.get_or_build(|t| *t.base() == other_base, || build(&other_base));
+        assert_eq!(*other.base(), other_base);
+        assert!(Arc::ptr_eq(&cache.get().unwrap(), &other));
+    }
+
+    #[test]
+    pub fn test_fixed_base_g1() { check_table::<G1>() }
+
+    #[test]
+    pub fn test_fixed_base_g2() { check_table::<G2>() }
+
+    #[test]
+    pub fn test_fixed_base_ristretto255() { check_table::<Ristretto255>() }
+}
@@ -1,13 +1,28 @@
 //! Basic definitions of the curve and pairing abstractions, and implementations
-//! of these abstractions for the curves used on Concordium.
+//! of these abstractions for the curves used on Concordium, as well as for the
+//! ristretto255 group.
 mod bls12_381_g1hash;
 mod bls12_381_g2hash;
 mod bls12_381_instance;
+mod constant_time;
 mod curve_arithmetic;
+mod expand_message;
+mod fixed_base;
+mod pippenger;
+mod ristretto255_instance;
 pub use crate::curve_arithmetic::*;
+pub use constant_time::{mul_by_secret_scalar, CONSTANT_TIME_WINDOW};
+pub use expand_message::expand_message_xmd;
+pub use fixed_base::{FixedBaseTable, TableCache, DEFAULT_FIXED_BASE_WINDOW, MAX_FIXED_BASE_WINDOW};
+pub use pippenger::{
+    multiexp_pippenger, pippenger_cost, wnaf_cost, MultiexpStrategy, MAX_PIPPENGER_WINDOW,
+    MAX_WNAF_WINDOW,
+};
+pub use ristretto255_instance::{Ristretto255, Ristretto255Base, Ristretto255Scalar};
 
 pub mod secret_value;
 pub use secret_value::{Secret, Value};
+pub use subtle::Choice;
 
 #[macro_use]
 extern crate crypto_common_derive;
@@ -0,0 +1,246 @@
+//! Pippenger's bucket method for multiexponentiation, and the selection of
+//! the method and window size used by [multiexp](crate::multiexp).
+use crate::curve_arithmetic::{multiexp_worker, Curve};
+use ff::PrimeField;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::borrow::Borrow;
+
+/// The largest window size considered for the wNAF method. The table of
+/// precomputed points grows exponentially with the window size.
+pub const MAX_WNAF_WINDOW: usize = 8;
+
+/// The largest window size supported by [multiexp_pippenger]. Each window
+/// uses `2^window_size - 1` buckets.
+pub const MAX_PIPPENGER_WINDOW: usize = 20;
+
+/// The method used to compute a multiexponentiation, together with its window
+/// size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiexpStrategy {
+    /// The wNAF method, see [multiexp_worker].
+    Wnaf { window_size: usize },
+    /// Pippenger's bucket method, see [multiexp_pippenger].
+    Pippenger { window_size: usize },
+}
+
+impl MultiexpStrategy {
+    /// Choose the method and window size for a multiexponentiation with the
+    /// given number of terms. This minimizes an estimate of the number of
+    /// group operations of each method, see [wnaf_cost] and
+    /// [pippenger_cost]. The wNAF method is preferred if the estimates are
+    /// equal.
+    pub fn for_length<C: Curve>(n: usize) -> Self {
+        let num_bits = C::Scalar::NUM_BITS as usize;
+        let wnaf = (1..=MAX_WNAF_WINDOW).map(|window_size| {
+            (
+                wnaf_cost(n, num_bits, window_size),
+                MultiexpStrategy::Wnaf { window_size },
+            )
+        });
+        let pippenger = (1..=MAX_PIPPENGER_WINDOW).map(|window_size| {
+            (
+                pippenger_cost(n, num_bits, window_size),
+                MultiexpStrategy::Pippenger { window_size },
+            )
+        });
+        wnaf.chain(pippenger)
+            .min_by_key(|(cost, _)| *cost)
+            .map(|(_, strategy)| strategy)
+            .expect("There is at least one strategy.")
+    }
+
+    /// Compute the multiexponentiation with this strategy. The inputs must
+    /// have the same length.
+    pub fn multiexp<C: Curve, X: Borrow<C>>(self, gs: &[X], exps: &[C::Scalar]) -> C {
+        match self {
+            MultiexpStrategy::Wnaf { window_size } => multiexp_worker(gs, exps, window_size),
+            MultiexpStrategy::Pippenger { window_size } => {
+                multiexp_pippenger(gs, exps, window_size)
+            }
+        }
+    }
+}
+
+/// Estimate of the number of group operations of the wNAF method with `n`
+/// terms of `num_bits` bits. The table has `2^{w-1}` points for each term,
+/// about one in `w + 2` digits of the wNAF form is non-zero, and there is a
+/// doubling for each bit.
+pub fn wnaf_cost(n: usize, num_bits: usize, window_size: usize) -> usize {
+    n * (1 << (window_size - 1)) + n * num_bits / (window_size + 2) + num_bits
+}
+
+/// Estimate of the number of group operations of Pippenger's method with `n`
+/// terms of `num_bits` bits. Each window adds every term to a bucket, and then
+/// sums the buckets with two additions per bucket. There is a doubling for
+/// each bit. With the `parallel` feature the windows are processed in
+/// parallel, which divides the cost of the windows by the number of threads
+/// that are used.
+pub fn pippenger_cost(n: usize, num_bits: usize, window_size: usize) -> usize {
+    let num_windows = (num_bits + window_size - 1) / window_size;
+    let window_cost = n + 2 * ((1 << window_size) - 1);
+    #[cfg(feature = "parallel")]
+    let num_windows = {
+        let threads = std::cmp::min(rayon::current_num_threads(), num_windows);
+        (num_windows + threads - 1) / threads
+    };
+    num_windows * window_cost + num_bits
+}
+
+/// Compute the multiexponentiation `gs[0]^exps[0] * ... * gs[n-1]^exps[n-1]`
+/// with Pippenger's bucket method. The scalars are split into windows of
+/// `window_size` bits. For each window the points are added to the bucket
+/// given by the bits of their scalar in that window, and the buckets are
+/// summed weighted by their index. The sums of the windows are then combined
+/// with doublings. With the `parallel` feature the windows are processed in
+/// parallel.
+///
+/// Assumes:
+/// - the lengths of inputs are the same
+/// - window size at least 1
+/// - window size at most [MAX_PIPPENGER_WINDOW]
+pub fn multiexp_pippenger<C: Curve, X: Borrow<C>>(
+    gs: &[X],
+    exps: &[C::Scalar],
+    window_size: usize,
+) -> C {
+    assert_eq!(gs.len(), exps.len());
+    assert!(window_size >= 1);
+    assert!(window_size <= MAX_PIPPENGER_WINDOW);
+
+    let points: Vec<&C> = gs.iter().map(Borrow::borrow).collect();
+    let reprs: Vec<_> = exps.iter().map(PrimeField::into_repr).collect();
+    let num_bits = C::Scalar::NUM_BITS as usize;
+    let num_windows = (num_bits + window_size - 1) / window_size;
+    let window = |i: usize| window_sum(&points, &reprs, i * window_size, window_size);
+    #[cfg(feature = "parallel")]
+    let sums: Vec<C> = (0..num_windows).into_par_iter().map(window).collect();
+    #[cfg(not(feature = "parallel"))]
+    let sums: Vec<C> = (0..num_windows).map(window).collect();
+
+    // Combine the windows starting from the most significant one.
+    let mut acc = C::zero_point();
+    for sum in sums.iter().rev() {
+        for _ in 0..window_size {
+            acc = acc.double_point();
+        }
+        acc = acc.plus_point(sum);
+    }
+    acc
+}
+
+/// Compute the sum of the points weighted by the bits of their scalars in the
+/// window starting at bit `offset`.
+fn window_sum<C: Curve>(
+    points: &[&C],
+    reprs: &[<C::Scalar as PrimeField>::Repr],
+    offset: usize,
+    window_size: usize,
+) -> C {
+    // The bucket at index i is for the digit i + 1. Points with digit 0 do not
+    // contribute.
+    let mut buckets = vec![C::zero_point(); (1 << window_size) - 1];
+    for (g, repr) in points.iter().zip(reprs.iter()) {
+        let digit = window_digit(repr.as_ref(), offset, window_size);
+        if digit != 0 {
+            buckets[digit - 1] = buckets[digit - 1].plus_point(g);
+        }
+    }
+    // The sum of (i + 1) * buckets[i] is the sum of the running sums of the
+    // buckets, from the largest digit down.
+    let mut running = C::zero_point();
+    let mut sum = C::zero_point();
+    for bucket in buckets.iter().rev() {
+        running = running.plus_point(bucket);
+        sum = sum.plus_point(&running);
+    }
+    sum
+}
+
+/// The `window_size` bits starting at bit `offset` of the little-endian
+/// integer given by its 64-bit limbs. Bits beyond the last limb are 0.
+pub(crate) fn window_digit(limbs: &[u64], offset: usize, window_size: usize) -> usize {
+    let limb = offset / 64;
+    let shift = offset % 64;
+    if limb >= limbs.len() {
+        return 0;
+    }
+    let mut bits = limbs[limb] >> shift;
+    if shift + window_size > 64 && limb + 1 < limbs.len() {
+        bits |= limbs[limb + 1] << (64 - shift);
+    }
+    (bits & ((1 << window_size) - 1)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+    use pairing::bls12_381::G1;
+    use rand::*;
+
+    fn naive(gs: &[G1], es: &[<G1 as Curve>::Scalar]) -> G1 {
+        gs.iter()
+            .zip(es.iter())
+            .fold(G1::zero_point(), |acc, (g, e)| {
+                acc.plus_point(&g.mul_by_scalar(e))
+            })
+    }
+
+    #[test]
+    pub fn test_pippenger() {
+        let mut csprng = thread_rng();
+        for l in (0..50usize).chain([100, 300].iter().copied()) {
+            let gs: Vec<G1> = (0..l).map(|_| G1::generate(&mut csprng)).collect();
+            let mut es: Vec<_> = (0..l).map(|_| G1::generate_scalar(&mut csprng)).collect();
+            // Zero and small scalars leave most windows empty.
+            if l > 2 {
+                es[0] = <G1 as Curve>::Scalar::zero();
+                es[1] = G1::scalar_from_u64(3);
+            }
+            let goal = naive(&gs, &es);
+            // The windows of size 7 and 13 cross the limb boundaries.
+            for &window_size in [1, 4, 7, 8, 13].iter() {
+                assert_eq!(
+                    multiexp_pippenger(&gs, &es, window_size),
+                    goal,
+                    "Pippenger with window size {} produces a different answer than the naive \
+                     method for {} terms.",
+                    window_size,
+                    l
+                );
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_window_digit() {
+        let limbs = [0xf000_0000_0000_0001u64, 0x5];
+        assert_eq!(window_digit(&limbs, 0, 4), 1);
+        assert_eq!(window_digit(&limbs, 60, 4), 0xf);
+        assert_eq!(window_digit(&limbs, 60, 8), 0x5f);
+        assert_eq!(window_digit(&limbs, 64, 4), 0x5);
+        assert_eq!(window_digit(&limbs, 126, 4), 0);
+        assert_eq!(window_digit(&limbs, 128, 4), 0);
+    }
+
+    #[test]
+    pub fn test_strategy() {
+        // Few terms are computed with the wNAF method, with the window size
+        // that was previously fixed.
+        assert_eq!(
+            MultiexpStrategy::for_length::<G1>(1),
+            MultiexpStrategy::Wnaf { window_size: 4 }
+        );
+        match MultiexpStrategy::for_length::<G1>(1 << 14) {
+            MultiexpStrategy::Pippenger { window_size } => assert!(window_size > 4),
+            strategy => panic!("Many terms should use Pippenger, not {:?}.", strategy),
+        }
+        // The chosen strategy computes the right result.
+        let mut csprng = thread_rng();
+        let gs: Vec<G1> = (0..20).map(|_| G1::generate(&mut csprng)).collect();
+        let es: Vec<_> = (0..20).map(|_| G1::generate_scalar(&mut csprng)).collect();
+        let strategy = MultiexpStrategy::for_length::<G1>(gs.len());
+        assert_eq!(strategy.multiexp(&gs, &es), naive(&gs, &es));
+    }
+}
@@ -0,0 +1,290 @@
+//! Implementation of the [Curve] trait for the ristretto255 group, built on
+//! top of `curve25519-dalek`.
+//!
+//! The scalar field is implemented with the same `ff` machinery that is used
+//! for the BLS12-381 scalar field, so that everything that is generic over
+//! [Curve] works unchanged. Group operations are delegated to
+//! `curve25519-dalek`.
+use crate::{curve_arithmetic::*, expand_message::expand_message_xmd, secret_value::Secret};
+use byteorder::ReadBytesExt;
+use crypto_common::*;
+use curve25519_dalek::{
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar as DalekScalar,
+    traits::Identity,
+};
+use ff::{Field, PrimeField};
+use rand::*;
+use sha2::{Digest, Sha512};
+use std::fmt;
+use subtle::{Choice, ConditionallySelectable};
+
+const HASH_TO_GROUP_RISTRETTO255_DST: &[u8] = b"CONCORDIUM-hashtoRistretto255-with-SHA-512";
+
+// The fields are defined in separate modules since the derivation of
+// `PrimeField` defines constants at the level of the module. The derived
+// Montgomery reduction takes one argument per limb.
+#[allow(clippy::too_many_arguments)]
+mod scalar_field {
+    use ff::{Field, PrimeField, PrimeFieldDecodingError, PrimeFieldRepr};
+
+    /// The scalar field of ristretto255, i.e., integers modulo the prime order
+    /// of the group, 2^252 + 27742317777372353535851937790883648493.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "7237005577332262213973186563042994240857116359379907606001950938285454250989"]
+    #[PrimeFieldGenerator = "2"]
+    pub struct Ristretto255Scalar(Ristretto255ScalarRepr);
+}
+
+#[allow(clippy::too_many_arguments)]
+mod base_field {
+    use ff::{Field, PrimeField, PrimeFieldDecodingError, PrimeFieldRepr};
+
+    /// The field over which the curve underlying ristretto255 is defined, i.e.,
+    /// integers modulo 2^255 - 19.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "57896044618658097711785492504343953926634992332820282019728792003956564819949"]
+    #[PrimeFieldGenerator = "2"]
+    pub struct Ristretto255Base(Ristretto255BaseRepr);
+}
+
+pub use base_field::Ristretto255Base;
+pub use scalar_field::{Ristretto255Scalar, Ristretto255ScalarRepr};
+
+impl Deserial for Ristretto255Scalar {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let mut repr = Ristretto255ScalarRepr([0u64; 4]);
+        // Read the scalar in big endian, as for the BLS12-381 scalars.
+        for digit in repr.as_mut().iter_mut().rev() {
+            *digit = source.get()?;
+        }
+        Ok(Ristretto255Scalar::from_repr(repr)?)
+    }
+}
+
+impl Serial for Ristretto255Scalar {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        let repr = &self.into_repr();
+        for a in repr.as_ref().iter().rev() {
+            a.serial(out);
+        }
+    }
+}
+
+impl From<&Ristretto255Scalar> for DalekScalar {
+    fn from(s: &Ristretto255Scalar) -> Self {
+        let mut bytes = [0u8; 32];
+        for (chunk, digit) in bytes.chunks_mut(8).zip(s.into_repr().as_ref().iter()) {
+            chunk.copy_from_slice(&digit.to_le_bytes());
+        }
+        // The representation is always canonical, so no reduction happens here.
+        DalekScalar::from_bytes_mod_order(bytes)
+    }
+}
+
+/// The ristretto255 prime order group. This is a thin wrapper around the
+/// ristretto point from `curve25519-dalek`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Ristretto255(pub RistrettoPoint);
+
+impl fmt::Display for Ristretto255 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ristretto255({})", base16_encode_string(self))
+    }
+}
+
+impl Deserial for Ristretto255 {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let mut bytes = [0u8; 32];
+        source.read_exact(&mut bytes)?;
+        Ok(Ristretto255::decompress(&CompressedRistretto(bytes))?)
+    }
+}
+
+impl Serial for Ristretto255 {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        if let Err(e) = out.write_all(self.0.compress().as_bytes()) {
+            panic!(
+                "Precondition violated. Buffer should be safe to write {}.",
+                e
+            );
+        }
+    }
+}
+
+impl Curve for Ristretto255 {
+    type Base = Ristretto255Base;
+    type Compressed = CompressedRistretto;
+    type Scalar = Ristretto255Scalar;
+
+    const GROUP_ELEMENT_LENGTH: usize = 32;
+    const SCALAR_LENGTH: usize = 32;
+
+    fn zero_point() -> Self { Ristretto255(RistrettoPoint::identity()) }
+
+    fn one_point() -> Self { Ristretto255(curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT) }
+
+    fn inverse_point(&self) -> Self { Ristretto255(-self.0) }
+
+    fn is_zero_point(&self) -> bool { self.0 == RistrettoPoint::identity() }
+
+    fn double_point(&self) -> Self { Ristretto255(self.0 + self.0) }
+
+    fn plus_point(&self, other: &Self) -> Self { Ristretto255(self.0 + other.0) }
+
+    fn minus_point(&self, other: &Self) -> Self { Ristretto255(self.0 - other.0) }
+
+    fn mul_by_scalar(&self, scalar: &Self::Scalar) -> Self {
+        Ristretto255(self.0 * DalekScalar::from(scalar))
+    }
+
+    /// The multiplication of ristretto points by scalars is already constant
+    /// time.
+    fn mul_by_secret(&self, scalar: &Secret<Self::Scalar>) -> Self {
+        self.mul_by_scalar(scalar.as_ref())
+    }
+
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Ristretto255(RistrettoPoint::conditional_select(&a.0, &b.0, choice))
+    }
+
+    fn compress(&self) -> Self::Compressed { self.0.compress() }
+
+    fn decompress(c: &Self::Compressed) -> Result<Self, CurveDecodingError> {
+        match c.decompress() {
+            Some(p) => Ok(Ristretto255(p)),
+            None => Err(CurveDecodingError::NotOnCurve),
+        }
+    }
+
+    /// Every valid encoding of a ristretto point is an element of the group,
+    /// so there are no cheaper checks to skip.
+    fn decompress_unchecked(c: &Self::Compressed) -> Result<Self, CurveDecodingError> {
+        Self::decompress(c)
+    }
+
+    #[inline(always)]
+    fn scalar_from_u64(n: u64) -> Self::Scalar {
+        Ristretto255Scalar::from_repr(Ristretto255ScalarRepr::from(n))
+            .expect("Every u64 is representable.")
+    }
+
+    #[inline(always)]
+    fn scalar_from_bytes<A: AsRef<[u8]>>(bytes: A) -> Self::Scalar {
+        // Traverse at most 4 8-byte chunks, for a total of 256 bits.
+        // Only the lowest 252 bits are kept, i.e., the top 4 bits in the last
+        // chunk are set to 0.
+        let mut fr = [0u64; 4];
+        for (i, chunk) in bytes.as_ref().chunks(8).take(4).enumerate() {
+            let mut v = [0u8; 8];
+            v[..chunk.len()].copy_from_slice(chunk);
+            fr[i] = u64::from_le_bytes(v);
+        }
+        fr[3] &= (1u64 << 60) - 1;
+        Ristretto255Scalar::from_repr(Ristretto255ScalarRepr(fr))
+            .expect("The scalar with top four bits erased should be valid.")
+    }
+
+    fn bytes_to_curve_unchecked<R: ReadBytesExt>(bytes: &mut R) -> anyhow::Result<Self> {
+        let mut g = [0u8; 32];
+        bytes.read_exact(&mut g)?;
+        Ok(Self::decompress_unchecked(&CompressedRistretto(g))?)
+    }
+
+    fn generate<T: Rng>(csprng: &mut T) -> Self {
+        let mut bytes = [0u8; 64];
+        csprng.fill_bytes(&mut bytes);
+        Ristretto255(RistrettoPoint::from_uniform_bytes(&bytes))
+    }
+
+    fn generate_scalar<T: Rng>(csprng: &mut T) -> Self::Scalar {
+        Ristretto255Scalar::random(csprng)
+    }
+
+    fn hash_to_group(bytes: &[u8]) -> Self {
+        let hasher = Sha512::new()
+            .chain(HASH_TO_GROUP_RISTRETTO255_DST)
+            .chain(bytes);
+        Ristretto255(RistrettoPoint::from_hash(hasher))
+    }
+
+    /// This is hash_to_ristretto255 from
+    /// <https://www.rfc-editor.org/rfc/rfc9380#appendix-B>, i.e., the suite
+    /// ristretto255_XMD:SHA-512_R255MAP_RO_.
+    fn hash_to_group_with_dst(bytes: &[u8], dst: &[u8]) -> Self {
+        let mut uniform_bytes = [0u8; 64];
+        uniform_bytes.copy_from_slice(&expand_message_xmd::<Sha512>(bytes, dst, 64));
+        Ristretto255(RistrettoPoint::from_uniform_bytes(&uniform_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalar_arithmetic() {
+        let mut csprng = thread_rng();
+        for _ in 0..100 {
+            let a = Ristretto255::generate_scalar(&mut csprng);
+            let b = Ristretto255::generate_scalar(&mut csprng);
+            let mut ab = a;
+            ab.mul_assign(&b);
+            let mut a_plus_b = a;
+            a_plus_b.add_assign(&b);
+            // The field arithmetic agrees with the arithmetic in curve25519-dalek.
+            assert_eq!(
+                DalekScalar::from(&ab),
+                DalekScalar::from(&a) * DalekScalar::from(&b)
+            );
+            assert_eq!(
+                DalekScalar::from(&a_plus_b),
+                DalekScalar::from(&a) + DalekScalar::from(&b)
+            );
+            // And so does scalar multiplication.
+            let g = Ristretto255::generate(&mut csprng);
+            assert_eq!(
+                g.mul_by_scalar(&a).plus_point(&g.mul_by_scalar(&b)),
+                g.mul_by_scalar(&a_plus_b)
+            );
+            assert_eq!(g.mul_by_scalar(&a).mul_by_scalar(&b), g.mul_by_scalar(&ab));
+        }
+        // The group has the expected order.
+        let mut minus_one = Ristretto255Scalar::one();
+        minus_one.negate();
+        let g = Ristretto255::one_point();
+        assert!(g.mul_by_scalar(&minus_one).plus_point(&g).is_zero_point());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut csprng = thread_rng();
+        for _ in 0..100 {
+            let g = Ristretto255::generate(&mut csprng);
+            let s = Ristretto255::generate_scalar(&mut csprng);
+            assert_eq!(serialize_deserialize(&g).expect("Should deserialize."), g);
+            assert_eq!(serialize_deserialize(&s).expect("Should deserialize."), s);
+            assert_eq!(to_bytes(&g).len(), Ristretto255::GROUP_ELEMENT_LENGTH);
+            assert_eq!(to_bytes(&s).len(), Ristretto255::SCALAR_LENGTH);
+        }
+        // Not every 32 byte string is a valid encoding.
+        assert!(from_bytes::<Ristretto255, _>(&mut std::io::Cursor::new([0xffu8; 32])).is_err());
+    }
+
+    #[test]
+    fn test_hash_to_group() {
+        let p1 = Ristretto255::hash_to_group(b"message");
+        let p2 = Ristretto255::hash_to_group(b"message");
+        let p3 = Ristretto255::hash_to_group(b"other message");
+        assert_eq!(p1, p2);
+        assert_ne!(p1, p3);
+        assert!(!p1.is_zero_point());
+        let q1 = Ristretto255::hash_to_group_with_dst(b"message", b"DST-1");
+        let q2 = Ristretto255::hash_to_group_with_dst(b"message", b"DST-2");
+        assert_eq!(
+            q1,
+            Ristretto255::hash_to_group_with_dst(b"message", b"DST-1")
+        );
+        assert_ne!(q1, q2);
+    }
+}
@@ -6,15 +6,14 @@ use crate::curve_arithmetic::*;
 use crypto_common::*;
 use ff::Field;
 use rand::*;
-use std::{
-    ops::{Deref, Drop},
-    ptr,
-    rc::Rc,
-    sync::atomic,
-};
+use std::{ops::Drop, ptr, rc::Rc, sync::atomic};
 
 /// A generic wrapper for a secret that implements a zeroize on drop.
 /// Other types are expected to wrap this in more convenient interfaces.
+/// Points must be multiplied by secrets with [Curve::mul_by_secret], which
+/// takes constant time, instead of [Curve::mul_by_scalar]. Secrets do not
+/// dereference to scalars, so that using them as plain scalars, e.g., with
+/// [Curve::mul_by_scalar], requires an explicit [AsRef::as_ref].
 /// Ideally the constraint would be Default, but fields we have do not implement
 /// it, so we cannot use it at the moment. Hence the temporary hack of 'F:
 /// Field'.
@@ -32,12 +31,6 @@ impl<F: Field + Serialize> AsRef<F> for Secret<F> {
     fn as_ref(&self) -> &F { &self.secret }
 }
 
-impl<F: Field + Serialize> Deref for Secret<F> {
-    type Target = F;
-
-    fn deref(&self) -> &Self::Target { &self.secret }
-}
-
 // This works for our current fields since they are arrays
 // But in the future we need to revisit, especially if our
 // upstream dependencies decide to implement drop themselves.
@@ -59,15 +52,8 @@ pub struct Value<C: Curve> {
     pub value: Rc<Secret<C::Scalar>>,
 }
 
-/// This trait allows automatic conversion of &Value<C> to &C::Scalar.
-impl<C: Curve> Deref for Value<C> {
-    type Target = C::Scalar;
-
-    fn deref(&self) -> &C::Scalar { &self.value }
-}
-
 impl<C: Curve> AsRef<C::Scalar> for Value<C> {
-    fn as_ref(&self) -> &C::Scalar { &self.value }
+    fn as_ref(&self) -> &C::Scalar { &self.value.secret }
 }
 
 /// Any 64-bit value can be converted (by-value) to a scalar.
@@ -90,6 +76,10 @@ impl<C: Curve> Value<C> {
         Value::new(C::generate_non_zero_scalar(csprng))
     }
 
+    /// Multiply the point by the value in constant time, see
+    /// [Curve::mul_by_secret].
+    pub fn mul_point(&self, point: &C) -> C { point.mul_by_secret(&self.value) }
+
     /// View the value as a value in another group. This does not
     /// copy the secret value.
     #[inline]
@@ -15,11 +15,11 @@ pub struct SecretKey<C: Curve>(Rc<Secret<C::Scalar>>);
 impl<C: Curve> std::ops::Deref for SecretKey<C> {
     type Target = C::Scalar;
 
-    fn deref(&self) -> &C::Scalar { &self.0 }
+    fn deref(&self) -> &C::Scalar { (*self.0).as_ref() }
 }
 
 impl<C: Curve> AsRef<C::Scalar> for SecretKey<C> {
-    fn as_ref(&self) -> &C::Scalar { &self.0 }
+    fn as_ref(&self) -> &C::Scalar { (*self.0).as_ref() }
 }
 
 impl<C: Curve> SecretKey<C> {
@@ -60,8 +60,8 @@ impl<C: Curve> SecretKey<C> {
 
     /// Compute the PRF function given the base `g` and the counter.
     pub fn prf(&self, g: &C, n: u8) -> Result<C, PrfError> {
-        let y = self.prf_exponent(n)?;
-        Ok(g.mul_by_scalar(&y))
+        let y = Secret::new(self.prf_exponent(n)?);
+        Ok(g.mul_by_secret(&y))
     }
 
     /// Generate a `SecretKey` from a `csprng`.
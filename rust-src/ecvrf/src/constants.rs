@@ -24,12 +24,57 @@ const EXPANDED_SECRET_KEY_NONCE_LENGTH: usize = 32;
 pub const EXPANDED_SECRET_KEY_LENGTH: usize =
     EXPANDED_SECRET_KEY_KEY_LENGTH + EXPANDED_SECRET_KEY_NONCE_LENGTH;
 
-/// Suite string as defined by <https://tools.ietf.org/id/draft-irtf-cfrg-vrf-07.html#suites>
+/// Suite string of ECVRF-EDWARDS25519-SHA512-TAI as defined by <https://www.rfc-editor.org/rfc/rfc9381#section-5.5>
 pub const SUITE_STRING: [u8; 1] = [3u8];
 
+/// Suite string of ECVRF-EDWARDS25519-SHA512-ELL2 as defined by <https://www.rfc-editor.org/rfc/rfc9381#section-5.5>
+pub const ELL2_SUITE_STRING: [u8; 1] = [4u8];
+
+/// The domain separation tag used for hashing to the curve in the
+/// ECVRF-EDWARDS25519-SHA512-ELL2 suite, without the trailing suite string,
+/// c.f. <https://www.rfc-editor.org/rfc/rfc9381#section-5.4.1.2>
+pub const ELL2_DST_PREFIX: &[u8] = b"ECVRF_edwards25519_XMD:SHA-512_ELL2_NU_";
+
 /// Strings combined with inputs to the hash function for domain separation, as
-/// discussed in <https://tools.ietf.org/id/draft-irtf-cfrg-vrf-07.html#rfc.section.7.7>
+/// used throughout <https://www.rfc-editor.org/rfc/rfc9381#section-5>
 pub const ZERO_STRING: [u8; 1] = [0u8];
 pub const ONE_STRING: [u8; 1] = [1u8];
 pub const TWO_STRING: [u8; 1] = [2u8];
 pub const THREE_STRING: [u8; 1] = [3u8];
+
+/// The ECVRF ciphersuites supported by this crate. All of them are over
+/// edwards25519 with SHA-512. They differ in how inputs are hashed to the
+/// curve, and in whether the public key is part of the challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Suite {
+    /// ECVRF-EDWARDS25519-SHA512-TAI as in
+    /// <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-vrf-09>, where
+    /// the public key is not part of the challenge. This is the suite used by
+    /// the functions that do not take a suite, so that existing proofs
+    /// continue to verify.
+    Draft,
+    /// ECVRF-EDWARDS25519-SHA512-TAI of RFC 9381, hashing to the curve by
+    /// try-and-increment.
+    Tai,
+    /// ECVRF-EDWARDS25519-SHA512-ELL2 of RFC 9381, hashing to the curve with
+    /// Elligator 2 as specified in RFC 9380.
+    Ell2,
+}
+
+impl Default for Suite {
+    fn default() -> Self { Suite::Draft }
+}
+
+impl Suite {
+    /// The suite string that is used for domain separation of all hashes.
+    pub fn suite_string(self) -> [u8; 1] {
+        match self {
+            Suite::Draft | Suite::Tai => SUITE_STRING,
+            Suite::Ell2 => ELL2_SUITE_STRING,
+        }
+    }
+
+    /// Whether the public key is the first point in the challenge, as
+    /// required by RFC 9381.
+    pub fn challenge_includes_public_key(self) -> bool { self != Suite::Draft }
+}
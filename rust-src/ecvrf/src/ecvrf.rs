@@ -1,3 +1,4 @@
+use crate::constants::Suite;
 pub use crate::{errors::*, proof::*, public::*, secret::*};
 use crypto_common::{size_t, *};
 use ffi_helpers::*;
@@ -34,6 +35,14 @@ impl Keypair {
 
         expanded.prove(&self.public, &message)
     }
+
+    /// Construct a VRF proof with this keypair's secret key in the given
+    /// suite.
+    pub fn prove_with_suite(&self, suite: Suite, message: &[u8]) -> Proof {
+        let expanded: ExpandedSecretKey = (&self.secret).into();
+
+        expanded.prove_with_suite(suite, &self.public, message)
+    }
 }
 
 // foreign interface
@@ -436,4 +445,113 @@ mod tests {
             )
         }
     }
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("Valid hex."))
+            .collect()
+    }
+
+    /// Test against the examples in
+    /// <https://www.rfc-editor.org/rfc/rfc9381#appendix-B.3> and
+    /// <https://www.rfc-editor.org/rfc/rfc9381#appendix-B.4>. The keys and
+    /// inputs are the same as in the draft examples above.
+    #[test]
+    fn test_rfc9381_vectors() {
+        let examples = [
+            (
+                Suite::Tai,
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "",
+                "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b\
+                 190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805",
+                "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d0\
+                 3450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+            ),
+            (
+                Suite::Tai,
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "72",
+                "f3141cd382dc42909d19ec5110469e4feae18300e94f304590abdced48aed5933bf0864a62558b3ed7\
+                 f2fea45c92a465301b3bbf5e3e54ddf2d935be3b67926da3ef39226bbc355bdc9850112c8f4b02",
+                "eb4440665d3891d668e7e0fcaf587f1b4bd7fbfe99d0eb2211ccec90496310eb5e33821bc613efb94d\
+                 b5e5b54c70a848a0bef4553a41befc57663b56373a5031",
+            ),
+            (
+                Suite::Tai,
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "af82",
+                "9bc0f79119cc5604bf02d23b4caede71393cedfbb191434dd016d30177ccbf8096bb474e53895c362d\
+                 8628ee9f9ea3c0e52c7a5c691b6c18c9979866568add7a2d41b00b05081ed0f58ee5e31b3a970e",
+                "645427e5d00c62a23fb703732fa5d892940935942101e456ecca7bb217c61c452118fec1219202a0ed\
+                 cf038bb6373241578be7217ba85a2687f7a0310b2df19f",
+            ),
+            (
+                Suite::Ell2,
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "",
+                "7d9c633ffeee27349264cf5c667579fc583b4bda63ab71d001f89c10003ab46f14adf9a3cd8b8412d9\
+                 038531e865c341cafa73589b023d14311c331a9ad15ff2fb37831e00f0acaa6d73bc9997b06501",
+                "9d574bf9b8302ec0fc1e21c3ec5368269527b87b462ce36dab2d14ccf80c53cccf6758f058c5b1c856\
+                 b116388152bbe509ee3b9ecfe63d93c3b4346c1fbc6c54",
+            ),
+            (
+                Suite::Ell2,
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "72",
+                "47b327393ff2dd81336f8a2ef10339112401253b3c714eeda879f12c509072ef055b48372bb82efbdc\
+                 e8e10c8cb9a2f9d60e93908f93df1623ad78a86a028d6bc064dbfc75a6a57379ef855dc6733801",
+                "38561d6b77b71d30eb97a062168ae12b667ce5c28caccdf76bc88e093e4635987cd96814ce55b4689b\
+                 3dd2947f80e59aac7b7675f8083865b46c89b2ce9cc735",
+            ),
+            (
+                Suite::Ell2,
+                "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+                "af82",
+                "926e895d308f5e328e7aa159c06eddbe56d06846abf5d98c2512235eaa57fdce35b46edfc655bc828d\
+                 44ad09d1150f31374e7ef73027e14760d42e77341fe05467bb286cc2c9d7fde29120a0b2320d04",
+                "121b7f9b9aaaa29099fc04a94ba52784d44eac976dd1a3cca458733be5cd090a7b5fbd148444f17f8d\
+                 af1fb55cb04b1ae85a626e30a54b4b0f8abf4a43314a58",
+            ),
+        ];
+        for (suite, sk, alpha, pi, beta) in examples.iter() {
+            let sk = SecretKey::from_bytes(&from_hex(sk)).expect("Valid secret key.");
+            let pk = PublicKey::from(&sk);
+            let alpha = from_hex(alpha);
+            let proof = sk.prove_with_suite(*suite, &pk, &alpha);
+            assert_eq!(to_bytes(&proof), from_hex(pi), "{:?}", suite);
+            assert_eq!(proof.to_hash_with_suite(*suite).to_vec(), from_hex(beta));
+            assert!(pk.verify_with_suite(*suite, &proof, &alpha));
+            // The proof does not verify in the other suites.
+            for other in [Suite::Draft, Suite::Tai, Suite::Ell2].iter() {
+                if other != suite {
+                    assert!(!pk.verify_with_suite(*other, &proof, &alpha));
+                }
+            }
+        }
+    }
+
+    /// Proofs made without choosing a suite are in the draft suite, and only
+    /// verify there.
+    #[test]
+    fn test_default_suite() {
+        let mut csprng = thread_rng();
+        for _ in 0..20 {
+            let keypair = Keypair::generate(&mut csprng);
+            let message = csprng.gen::<[u8; 32]>();
+            let proof = keypair.prove(&message);
+            assert_eq!(proof, keypair.prove_with_suite(Suite::default(), &message));
+            assert!(keypair.public.verify(&proof, &message));
+            assert!(keypair
+                .public
+                .verify_with_suite(Suite::Draft, &proof, &message));
+            assert!(!keypair
+                .public
+                .verify_with_suite(Suite::Tai, &proof, &message));
+            assert_eq!(proof.to_hash(), proof.to_hash_with_suite(Suite::Tai));
+            let rfc_proof = keypair.prove_with_suite(Suite::Tai, &message);
+            assert!(!keypair.public.verify(&rfc_proof, &message));
+        }
+    }
 }
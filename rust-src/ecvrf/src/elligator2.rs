@@ -0,0 +1,195 @@
+//! Hashing to edwards25519 with the suite edwards25519_XMD:SHA-512_ELL2_NU_ of
+//! <https://www.rfc-editor.org/rfc/rfc9380#section-6.8.2>, which is used by
+//! the ECVRF-EDWARDS25519-SHA512-ELL2 suite.
+use crate::{expand_message::expand_message_xmd, field::Fe};
+use curve25519_dalek::{
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    traits::Identity,
+};
+
+/// (p - 1) / 2 for p = 2^255 - 19, as little endian limbs.
+const P_MINUS_ONE_OVER_TWO: [u64; 4] = [
+    0xffff_ffff_ffff_fff6,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x3fff_ffff_ffff_ffff,
+];
+
+/// (p + 3) / 8 for p = 2^255 - 19, as little endian limbs.
+const P_PLUS_THREE_OVER_EIGHT: [u64; 4] = [
+    0xffff_ffff_ffff_fffe,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x0fff_ffff_ffff_ffff,
+];
+
+/// (p - 1) / 4 for p = 2^255 - 19, as little endian limbs.
+const P_MINUS_ONE_OVER_FOUR: [u64; 4] = [
+    0xffff_ffff_ffff_fffb,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x1fff_ffff_ffff_ffff,
+];
+
+/// The Montgomery curve parameter J of curve25519.
+const J: u64 = 486_662;
+
+/// Hash the message to edwards25519 with the given domain separation tag.
+/// This is encode_to_curve of RFC 9380, so the result is in the prime order
+/// subgroup.
+pub(crate) fn encode_to_curve(msg: &[u8], dst: &[u8]) -> EdwardsPoint {
+    let uniform_bytes = expand_message_xmd(msg, dst, 48);
+    let u = fe_from_uniform_bytes(&uniform_bytes);
+    map_to_curve(&u).mul_by_cofactor()
+}
+
+/// Elligator 2 onto curve25519, followed by the rational map to
+/// edwards25519, c.f. <https://www.rfc-editor.org/rfc/rfc9380#appendix-D.1>.
+fn map_to_curve(u: &Fe) -> EdwardsPoint {
+    let (s, t) = map_to_curve_elligator2_curve25519(u);
+    // y = (s - 1) / (s + 1), x = sqrt(-486664) * s / t, with the exceptional
+    // cases mapped to the identity.
+    let (t_inv, y_den_inv) = match (t.inverse(), s.add(&Fe::ONE).inverse()) {
+        (Some(t_inv), Some(y_den_inv)) => (t_inv, y_den_inv),
+        _ => return EdwardsPoint::identity(),
+    };
+    let x = sqrt_minus_486664().mul(&s).mul(&t_inv);
+    let y = s.sub(&Fe::ONE).mul(&y_den_inv);
+    let mut bytes = y.to_le_bytes();
+    bytes[31] |= sgn0(&x) << 7;
+    CompressedEdwardsY(bytes)
+        .decompress()
+        .expect("The point is on the curve by construction.")
+}
+
+/// The generic Elligator 2 method of
+/// <https://www.rfc-editor.org/rfc/rfc9380#section-6.7.1> for curve25519,
+/// i.e., with J = 486662, K = 1 and Z = 2. Returns the coordinates of a point
+/// on the Montgomery curve.
+fn map_to_curve_elligator2_curve25519(u: &Fe) -> (Fe, Fe) {
+    let j = Fe::from_u64(J);
+    let minus_j = j.neg();
+    // x1 = -J / (1 + Z * u^2), or -J if the denominator is 0.
+    let u_square = u.square();
+    let den = u_square.add(&u_square).add(&Fe::ONE);
+    let x1 = match den.inverse() {
+        Some(den_inv) => minus_j.mul(&den_inv),
+        None => minus_j,
+    };
+    // x2 = -x1 - J
+    let x2 = minus_j.sub(&x1);
+    let gx1 = montgomery_rhs(&x1, &j);
+    if is_square(&gx1) {
+        let y = sqrt(&gx1).expect("gx1 is a square.");
+        (x1, with_sgn0(y, 1))
+    } else {
+        let gx2 = montgomery_rhs(&x2, &j);
+        let y = sqrt(&gx2).expect("gx2 is a square if gx1 is not.");
+        (x2, with_sgn0(y, 0))
+    }
+}
+
+/// Computes x^3 + J * x^2 + x.
+fn montgomery_rhs(x: &Fe, j: &Fe) -> Fe { x.add(j).mul(x).add(&Fe::ONE).mul(x) }
+
+/// The square root of -486664 with sgn0 equal to 0.
+fn sqrt_minus_486664() -> Fe {
+    let a = Fe::from_u64(486_664).neg();
+    with_sgn0(sqrt(&a).expect("-486664 is a square."), 0)
+}
+
+fn is_square(a: &Fe) -> bool { a.is_zero() || a.pow(&P_MINUS_ONE_OVER_TWO) == Fe::ONE }
+
+/// Square root for p = 5 mod 8, c.f.
+/// <https://www.rfc-editor.org/rfc/rfc9380#appendix-I.2>.
+fn sqrt(a: &Fe) -> Option<Fe> {
+    let r = a.pow(&P_PLUS_THREE_OVER_EIGHT);
+    if r.square() == *a {
+        return Some(r);
+    }
+    let r = r.mul(&Fe::from_u64(2).pow(&P_MINUS_ONE_OVER_FOUR));
+    if r.square() == *a {
+        Some(r)
+    } else {
+        None
+    }
+}
+
+/// The parity of the canonical representative.
+fn sgn0(a: &Fe) -> u8 { a.to_le_bytes()[0] & 1 }
+
+/// Return `a` or `-a`, whichever has the given sign.
+fn with_sgn0(a: Fe, sign: u8) -> Fe {
+    if sgn0(&a) == sign {
+        a
+    } else {
+        a.neg()
+    }
+}
+
+/// Interpret 24 big endian bytes as a field element.
+fn fe_from_be_bytes_24(bytes: &[u8]) -> Fe {
+    let mut le_bytes = [0u8; 32];
+    for (b, x) in le_bytes.iter_mut().zip(bytes.iter().rev()) {
+        *b = *x;
+    }
+    Fe::from_le_bytes(&le_bytes)
+}
+
+/// The hash_to_field step with L = 48, i.e., the 48 uniform bytes are
+/// interpreted as a big endian integer and reduced modulo p.
+fn fe_from_uniform_bytes(bytes: &[u8]) -> Fe {
+    let mut two_to_192 = [0u8; 32];
+    two_to_192[24] = 1;
+    fe_from_be_bytes_24(&bytes[..24])
+        .mul(&Fe::from_le_bytes(&two_to_192))
+        .add(&fe_from_be_bytes_24(&bytes[24..48]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).expect("Valid hex."))
+            .collect()
+    }
+
+    /// The values of H in the ECVRF-EDWARDS25519-SHA512-ELL2 examples of
+    /// <https://www.rfc-editor.org/rfc/rfc9381#appendix-B.4>. The input is the
+    /// public key followed by alpha.
+    #[test]
+    fn test_encode_to_curve() {
+        let dst = b"ECVRF_edwards25519_XMD:SHA-512_ELL2_NU_\x04";
+        let cases = [
+            (
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "b8066ebbb706c72b64390324e4a3276f129569eab100c26b9f05011200c1bad9",
+            ),
+            (
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c72",
+                "76ac3ccb86158a9104dff819b1ca293426d305fd76b39b13c9356d9b58c08e57",
+            ),
+            (
+                "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025af82",
+                "13d2a8b5ca32db7e98094a61f656a08c6c964344e058879a386a947a4e189ed1",
+            ),
+        ];
+        for (msg, h) in cases.iter() {
+            let p = encode_to_curve(&from_hex(msg), dst);
+            assert_eq!(p.compress().to_bytes().to_vec(), from_hex(h));
+        }
+    }
+
+    #[test]
+    fn test_uniform_bytes_are_reduced() {
+        // 2^384 - 1 = 19 * 2^129 - 1 mod p, using 2^255 = 19 mod p.
+        let r = fe_from_uniform_bytes(&[0xffu8; 48]);
+        let mut expected = [0u8; 32];
+        expected[..16].copy_from_slice(&[0xffu8; 16]);
+        expected[16] = 0x25;
+        assert_eq!(r.to_le_bytes(), expected);
+    }
+}
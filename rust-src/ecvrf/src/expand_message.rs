@@ -0,0 +1,140 @@
+//! Implementation of `expand_message_xmd` from
+//! <https://www.rfc-editor.org/rfc/rfc9380#section-5.3.1> with SHA-512, which
+//! the ECVRF-EDWARDS25519-SHA512-ELL2 suite uses to hash to the curve.
+use sha2::{Digest, Sha512};
+
+/// The output size of SHA-512, in bytes.
+const B_IN_BYTES: usize = 64;
+
+/// The input block size of SHA-512, in bytes.
+const R_IN_BYTES: usize = 128;
+
+/// Prefix used when hashing domain separation tags longer than 255 bytes, see
+/// <https://www.rfc-editor.org/rfc/rfc9380#section-5.3.3>.
+const OVERSIZE_DST_PREFIX: &[u8] = b"H2C-OVERSIZE-DST-";
+
+/// Produce `len_in_bytes` uniformly random bytes from the message `msg` and
+/// domain separation tag `dst`. Domain separation tags longer than 255 bytes
+/// are hashed first, as specified in the RFC.
+///
+/// # Panics
+/// The RFC only allows outputs of at most 65535 bytes and at most 255 times
+/// the digest size. This function panics if `len_in_bytes` exceeds these
+/// limits. All callers in this crate request fixed, small lengths.
+pub(crate) fn expand_message_xmd(msg: &[u8], dst: &[u8], len_in_bytes: usize) -> Vec<u8> {
+    // 1. ell = ceil(len_in_bytes / B_IN_BYTES)
+    let ell = (len_in_bytes + B_IN_BYTES - 1) / B_IN_BYTES;
+    // 2. ABORT if ell > 255 or len_in_bytes > 65535
+    assert!(
+        ell <= 255 && len_in_bytes <= 65535,
+        "expand_message_xmd: requested output is too long."
+    );
+    // 3. DST_prime = DST || I2OSP(len(DST), 1), where overly long tags are
+    // replaced by H("H2C-OVERSIZE-DST-" || DST).
+    let mut dst_prime = if dst.len() > 255 {
+        Sha512::new()
+            .chain(OVERSIZE_DST_PREFIX)
+            .chain(dst)
+            .finalize()
+            .to_vec()
+    } else {
+        dst.to_vec()
+    };
+    dst_prime.push(dst_prime.len() as u8);
+
+    // 4.-7. b_0 = H(Z_pad || msg || l_i_b_str || I2OSP(0, 1) || DST_prime)
+    // where Z_pad = I2OSP(0, R_IN_BYTES) and l_i_b_str = I2OSP(len_in_bytes, 2)
+    let b_0 = Sha512::new()
+        .chain(vec![0u8; R_IN_BYTES])
+        .chain(msg)
+        .chain((len_in_bytes as u16).to_be_bytes())
+        .chain([0u8])
+        .chain(&dst_prime)
+        .finalize();
+
+    // 8. b_1 = H(b_0 || I2OSP(1, 1) || DST_prime)
+    let mut b_i = Sha512::new()
+        .chain(&b_0)
+        .chain([1u8])
+        .chain(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = Vec::with_capacity(ell * B_IN_BYTES);
+    uniform_bytes.extend_from_slice(&b_i);
+    for i in 2..=ell {
+        // 10. b_i = H(strxor(b_0, b_(i - 1)) || I2OSP(i, 1) || DST_prime)
+        let xor: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(x, y)| x ^ y).collect();
+        b_i = Sha512::new()
+            .chain(xor)
+            .chain([i as u8])
+            .chain(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    // 11.-12. uniform_bytes = b_1 || ... || b_ell, truncated to len_in_bytes
+    uniform_bytes.truncate(len_in_bytes);
+    uniform_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_hex(bytes: &[u8]) -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() }
+
+    fn test_messages() -> Vec<Vec<u8>> {
+        let mut q128 = b"q128_".to_vec();
+        q128.extend_from_slice(&[b'q'; 128]);
+        let mut a512 = b"a512_".to_vec();
+        a512.extend_from_slice(&[b'a'; 512]);
+        vec![
+            b"".to_vec(),
+            b"abc".to_vec(),
+            b"abcdef0123456789".to_vec(),
+            q128,
+            a512,
+        ]
+    }
+
+    // Test vectors from https://www.rfc-editor.org/rfc/rfc9380#appendix-K.3
+    #[test]
+    fn test_expand_message_xmd_sha512() {
+        let dst = b"QUUX-V01-CS02-with-expander-SHA512-256";
+        let expected_short = [
+            "6b9a7312411d92f921c6f68ca0b6380730a1a4d982c507211a90964c394179ba",
+            "0da749f12fbe5483eb066a5f595055679b976e93abe9be6f0f6318bce7aca8dc",
+            "087e45a86e2939ee8b91100af1583c4938e0f5fc6c9db4b107b83346bc967f58",
+            "7336234ee9983902440f6bc35b348352013becd88938d2afec44311caf8356b3",
+            "57b5f7e766d5be68a6bfe1768e3c2b7f1228b3e4b3134956dd73a59b954c66f4",
+        ];
+        let expected_long = [
+            "41b037d1734a5f8df225dd8c7de38f851efdb45c372887be655212d07251b921b052b62eaed99b46f72f\
+             2ef4cc96bfaf254ebbbec091e1a3b9e4fb5e5b619d2e0c5414800a1d882b62bb5cd1778f098b8eb6cb39\
+             9d5d9d18f5d5842cf5d13d7eb00a7cff859b605da678b318bd0e65ebff70bec88c753b159a805d2c89c5\
+             5961",
+            "7f1dddd13c08b543f2e2037b14cefb255b44c83cc397c1786d975653e36a6b11bdd7732d8b38adb4a0ed\
+             c26a0cef4bb45217135456e58fbca1703cd6032cb1347ee720b87972d63fbf232587043ed2901bce7f22\
+             610c0419751c065922b488431851041310ad659e4b23520e1772ab29dcdeb2002222a363f0c2b1c972b3\
+             efe1",
+            "3f721f208e6199fe903545abc26c837ce59ac6fa45733f1baaf0222f8b7acb0424814fcb5eecf6c1d38f\
+             06e9d0a6ccfbf85ae612ab8735dfdf9ce84c372a77c8f9e1c1e952c3a61b7567dd0693016af51d274582\
+             2663d0c2367e3f4f0bed827feecc2aaf98c949b5ed0d35c3f1023d64ad1407924288d366ea159f46287e\
+             61ac",
+            "b799b045a58c8d2b4334cf54b78260b45eec544f9f2fb5bd12fb603eaee70db7317bf807c406e2637392\
+             2b7b8920fa29142703dd52bdf280084fb7ef69da78afdf80b3586395b433dc66cde048a258e476a561e9\
+             deba7060af40adf30c64249ca7ddea79806ee5beb9a1422949471d267b21bc88e688e4014087a0b592b6\
+             95ed",
+            "05b0bfef265dcee87654372777b7c44177e2ae4c13a27f103340d9cd11c86cb2426ffcad5bd964080c2a\
+             ee97f03be1ca18e30a1f14e27bc11ebbd650f305269cc9fb1db08bf90bfc79b42a952b46daf810359e7b\
+             c36452684784a64952c343c52e5124cd1f71d474d5197fefc571a92929c9084ffe1112cf5eea5192ebff\
+             330b",
+        ];
+        for ((msg, short), long) in test_messages()
+            .iter()
+            .zip(expected_short.iter())
+            .zip(expected_long.iter())
+        {
+            assert_eq!(to_hex(&expand_message_xmd(msg, dst, 0x20)), *short);
+            assert_eq!(to_hex(&expand_message_xmd(msg, dst, 0x80)), *long);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Arithmetic in the field of integers modulo p = 2^255 - 19, over which
+//! edwards25519 is defined. `curve25519-dalek` does not expose its field
+//! arithmetic, and only the few operations needed for hashing to the curve
+//! with Elligator 2 are implemented here.
+//!
+//! Elements are represented by five limbs of 51 bits, in little endian order,
+//! as in `curve25519-dalek`. The representation is not unique, but all limbs
+//! are kept below 2^52 after every operation. None of the operations are
+//! constant time, which is fine since only public values are hashed.
+
+const LOW_51_BIT_MASK: u64 = (1 << 51) - 1;
+
+/// p - 2 as little endian 64-bit words, the exponent used for inversion.
+const P_MINUS_TWO: [u64; 4] = [
+    0xffff_ffff_ffff_ffeb,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x7fff_ffff_ffff_ffff,
+];
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fe([u64; 5]);
+
+impl PartialEq for Fe {
+    fn eq(&self, other: &Self) -> bool { self.to_le_bytes() == other.to_le_bytes() }
+}
+
+impl Eq for Fe {}
+
+impl Fe {
+    pub(crate) const ONE: Fe = Fe([1, 0, 0, 0, 0]);
+    pub(crate) const ZERO: Fe = Fe([0, 0, 0, 0, 0]);
+
+    pub(crate) fn from_u64(n: u64) -> Fe { Fe([n & LOW_51_BIT_MASK, n >> 51, 0, 0, 0]) }
+
+    /// Interpret the bytes as a little endian integer, ignoring the most
+    /// significant bit, and reduce it modulo p.
+    pub(crate) fn from_le_bytes(bytes: &[u8; 32]) -> Fe {
+        let mut words = [0u64; 4];
+        for (word, chunk) in words.iter_mut().zip(bytes.chunks(8)) {
+            let mut v = [0u8; 8];
+            v.copy_from_slice(chunk);
+            *word = u64::from_le_bytes(v);
+        }
+        Fe([
+            words[0] & LOW_51_BIT_MASK,
+            ((words[0] >> 51) | (words[1] << 13)) & LOW_51_BIT_MASK,
+            ((words[1] >> 38) | (words[2] << 26)) & LOW_51_BIT_MASK,
+            ((words[2] >> 25) | (words[3] << 39)) & LOW_51_BIT_MASK,
+            (words[3] >> 12) & LOW_51_BIT_MASK,
+        ])
+    }
+
+    /// The canonical representative, as 32 little endian bytes.
+    pub(crate) fn to_le_bytes(&self) -> [u8; 32] {
+        let mut l = Fe::reduce(self.0).0;
+        // Compute the quotient of the value by p, which is either 0 or 1 since
+        // the value is below 2p, and subtract it times p, by adding 19 times
+        // it and dropping bit 255.
+        let mut q = (l[0] + 19) >> 51;
+        q = (l[1] + q) >> 51;
+        q = (l[2] + q) >> 51;
+        q = (l[3] + q) >> 51;
+        q = (l[4] + q) >> 51;
+        l[0] += 19 * q;
+        l[1] += l[0] >> 51;
+        l[0] &= LOW_51_BIT_MASK;
+        l[2] += l[1] >> 51;
+        l[1] &= LOW_51_BIT_MASK;
+        l[3] += l[2] >> 51;
+        l[2] &= LOW_51_BIT_MASK;
+        l[4] += l[3] >> 51;
+        l[3] &= LOW_51_BIT_MASK;
+        l[4] &= LOW_51_BIT_MASK;
+        let words = [
+            l[0] | (l[1] << 51),
+            (l[1] >> 13) | (l[2] << 38),
+            (l[2] >> 26) | (l[3] << 25),
+            (l[3] >> 39) | (l[4] << 12),
+        ];
+        let mut bytes = [0u8; 32];
+        for (chunk, word) in bytes.chunks_mut(8).zip(words.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Carry the bits above the lowest 51 of each limb to the next limb, with
+    /// the carry out of the last limb multiplied by 19 since 2^255 = 19 mod p.
+    fn reduce(mut l: [u64; 5]) -> Fe {
+        let c0 = l[0] >> 51;
+        let c1 = l[1] >> 51;
+        let c2 = l[2] >> 51;
+        let c3 = l[3] >> 51;
+        let c4 = l[4] >> 51;
+        for limb in l.iter_mut() {
+            *limb &= LOW_51_BIT_MASK;
+        }
+        l[0] += c4 * 19;
+        l[1] += c0;
+        l[2] += c1;
+        l[3] += c2;
+        l[4] += c3;
+        Fe(l)
+    }
+
+    pub(crate) fn is_zero(&self) -> bool { self.to_le_bytes() == [0u8; 32] }
+
+    pub(crate) fn add(&self, other: &Fe) -> Fe {
+        let mut l = self.0;
+        for (a, b) in l.iter_mut().zip(other.0.iter()) {
+            *a += b;
+        }
+        Fe::reduce(l)
+    }
+
+    pub(crate) fn sub(&self, other: &Fe) -> Fe {
+        // Add 16p first so that the limbs do not underflow.
+        Fe::reduce([
+            (self.0[0] + 36_028_797_018_963_664) - other.0[0],
+            (self.0[1] + 36_028_797_018_963_952) - other.0[1],
+            (self.0[2] + 36_028_797_018_963_952) - other.0[2],
+            (self.0[3] + 36_028_797_018_963_952) - other.0[3],
+            (self.0[4] + 36_028_797_018_963_952) - other.0[4],
+        ])
+    }
+
+    pub(crate) fn neg(&self) -> Fe { Fe::ZERO.sub(self) }
+
+    pub(crate) fn mul(&self, other: &Fe) -> Fe {
+        #[inline(always)]
+        fn m(x: u64, y: u64) -> u128 { u128::from(x) * u128::from(y) }
+        let a = &self.0;
+        let b = &other.0;
+        // The terms that wrap around 2^255 are multiplied by 19.
+        let b1_19 = b[1] * 19;
+        let b2_19 = b[2] * 19;
+        let b3_19 = b[3] * 19;
+        let b4_19 = b[4] * 19;
+        let c0 = m(a[0], b[0]) + m(a[4], b1_19) + m(a[3], b2_19) + m(a[2], b3_19) + m(a[1], b4_19);
+        let mut c1 =
+            m(a[1], b[0]) + m(a[0], b[1]) + m(a[4], b2_19) + m(a[3], b3_19) + m(a[2], b4_19);
+        let mut c2 =
+            m(a[2], b[0]) + m(a[1], b[1]) + m(a[0], b[2]) + m(a[4], b3_19) + m(a[3], b4_19);
+        let mut c3 = m(a[3], b[0]) + m(a[2], b[1]) + m(a[1], b[2]) + m(a[0], b[3]) + m(a[4], b4_19);
+        let mut c4 = m(a[4], b[0]) + m(a[3], b[1]) + m(a[2], b[2]) + m(a[1], b[3]) + m(a[0], b[4]);
+        c1 += c0 >> 51;
+        c2 += c1 >> 51;
+        c3 += c2 >> 51;
+        c4 += c3 >> 51;
+        let mask = u128::from(LOW_51_BIT_MASK);
+        let mut l = [
+            (c0 & mask) as u64,
+            (c1 & mask) as u64,
+            (c2 & mask) as u64,
+            (c3 & mask) as u64,
+            (c4 & mask) as u64,
+        ];
+        l[0] += (c4 >> 51) as u64 * 19;
+        l[1] += l[0] >> 51;
+        l[0] &= LOW_51_BIT_MASK;
+        Fe(l)
+    }
+
+    pub(crate) fn square(&self) -> Fe { self.mul(self) }
+
+    /// Raise to the power given as little endian 64-bit words.
+    pub(crate) fn pow(&self, exp: &[u64; 4]) -> Fe {
+        let mut r = Fe::ONE;
+        for word in exp.iter().rev() {
+            for i in (0..64).rev() {
+                r = r.square();
+                if (word >> i) & 1 == 1 {
+                    r = r.mul(self);
+                }
+            }
+        }
+        r
+    }
+
+    /// The multiplicative inverse, or `None` for zero.
+    pub(crate) fn inverse(&self) -> Option<Fe> {
+        if self.is_zero() {
+            None
+        } else {
+            Some(self.pow(&P_MINUS_TWO))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+
+    fn random_fe<R: Rng>(rng: &mut R) -> Fe {
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        Fe::from_le_bytes(&bytes)
+    }
+
+    #[test]
+    fn test_canonical_bytes() {
+        // p and p + 1 reduce to 0 and 1, and p - 1 is canonical.
+        let mut p = [0xffu8; 32];
+        p[0] = 0xed;
+        p[31] = 0x7f;
+        assert!(Fe::from_le_bytes(&p).is_zero());
+        p[0] = 0xee;
+        assert_eq!(Fe::from_le_bytes(&p), Fe::ONE);
+        p[0] = 0xec;
+        assert_eq!(Fe::from_le_bytes(&p).to_le_bytes(), p);
+        assert_eq!(Fe::ONE.neg().to_le_bytes(), p);
+    }
+
+    #[test]
+    fn test_field_laws() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let a = random_fe(&mut rng);
+            let b = random_fe(&mut rng);
+            let c = random_fe(&mut rng);
+            assert_eq!(a.add(&b).sub(&b), a);
+            assert_eq!(a.add(&a.neg()), Fe::ZERO);
+            assert_eq!(a.mul(&b), b.mul(&a));
+            assert_eq!(a.mul(&b.add(&c)), a.mul(&b).add(&a.mul(&c)));
+            assert_eq!(a.mul(&b).mul(&c), a.mul(&b.mul(&c)));
+            if let Some(a_inv) = a.inverse() {
+                assert_eq!(a.mul(&a_inv), Fe::ONE);
+            }
+        }
+        assert_eq!(Fe::ZERO.inverse(), None);
+    }
+}
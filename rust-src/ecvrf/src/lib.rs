@@ -1,8 +1,14 @@
-//! Implementation of the verifiable random function as specified in <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-vrf-09>.
+//! Implementation of the verifiable random function as specified in <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-vrf-09>,
+//! and of the ECVRF-EDWARDS25519-SHA512-TAI and ECVRF-EDWARDS25519-SHA512-ELL2
+//! suites of <https://www.rfc-editor.org/rfc/rfc9381>. The functions that do
+//! not take a [Suite] use the draft version.
 
 mod constants;
 mod ecvrf;
+mod elligator2;
 mod errors;
+mod expand_message;
+mod field;
 mod proof;
 mod public;
 mod secret;
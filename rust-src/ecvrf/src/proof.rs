@@ -10,9 +10,17 @@ use curve25519_dalek::{
 use sha2::*;
 
 /// Implements <https://tools.ietf.org/id/draft-irtf-cfrg-vrf-07.html#rfc.section.5.4.3>
+/// in the [Suite::Draft] suite.
 pub fn hash_points(pts: &[CompressedEdwardsY]) -> Scalar {
+    hash_points_with_suite(Suite::Draft, pts)
+}
+
+/// Implements <https://www.rfc-editor.org/rfc/rfc9381#section-5.4.3>. The
+/// caller is responsible for including the public key as the first point
+/// if the suite requires it.
+pub fn hash_points_with_suite(suite: Suite, pts: &[CompressedEdwardsY]) -> Scalar {
     let mut hash: Sha512 = Sha512::new();
-    hash.update(SUITE_STRING);
+    hash.update(suite.suite_string());
     hash.update(TWO_STRING);
     for p in pts {
         hash.update(p.to_bytes());
@@ -78,12 +86,17 @@ impl Debug for Proof {
     }
 }
 
-/// Implements <https://tools.ietf.org/id/draft-irtf-cfrg-vrf-07.html#rfc.section.5.2>
 impl Proof {
-    pub fn to_hash(&self) -> [u8; 64] {
+    /// Implements <https://tools.ietf.org/id/draft-irtf-cfrg-vrf-07.html#rfc.section.5.2>
+    /// in the [Suite::Draft] suite.
+    pub fn to_hash(&self) -> [u8; 64] { self.to_hash_with_suite(Suite::Draft) }
+
+    /// Implements <https://www.rfc-editor.org/rfc/rfc9381#section-5.2>.
+    /// The output is the same for [Suite::Draft] and [Suite::Tai].
+    pub fn to_hash_with_suite(&self, suite: Suite) -> [u8; 64] {
         let p = self.0.mul_by_cofactor();
         let hash: Sha512 = Sha512::new()
-            .chain(SUITE_STRING)
+            .chain(suite.suite_string())
             .chain(THREE_STRING)
             .chain(p.compress().to_bytes())
             .chain(ZERO_STRING);
@@ -10,7 +10,7 @@ use curve25519_dalek::{
 };
 use sha2::Sha512;
 
-use crate::{constants::*, errors::*, proof::*, secret::*};
+use crate::{constants::*, elligator2::encode_to_curve, errors::*, proof::*, secret::*};
 /// An ed25519-like public key. This has a bit stricter requirements than the
 /// signature scheme public keys, in particular points of small order are not
 /// allowed, and this is checked during serialization.
@@ -109,9 +109,36 @@ impl PublicKey {
     /// The failure should not happen in practice, expected number of iterations
     /// is 2.
     pub fn hash_to_curve(&self, message: &[u8]) -> Option<EdwardsPoint> {
+        self.encode_to_curve(Suite::Draft, message)
+    }
+
+    /// Implements <https://www.rfc-editor.org/rfc/rfc9381#section-5.4.1>
+    /// with the public key as the salt. Only the try-and-increment method of
+    /// [Suite::Draft] and [Suite::Tai] can fail, which should not happen in
+    /// practice.
+    pub fn encode_to_curve(&self, suite: Suite, message: &[u8]) -> Option<EdwardsPoint> {
+        match suite {
+            Suite::Draft | Suite::Tai => self.encode_to_curve_try_and_increment(suite, message),
+            Suite::Ell2 => {
+                let mut dst = ELL2_DST_PREFIX.to_vec();
+                dst.extend_from_slice(&suite.suite_string());
+                let mut msg = self.as_bytes().to_vec();
+                msg.extend_from_slice(message);
+                Some(encode_to_curve(&msg, &dst))
+            }
+        }
+    }
+
+    /// Implements <https://www.rfc-editor.org/rfc/rfc9381#section-5.4.1.1>.
+    /// The expected number of iterations is 2.
+    fn encode_to_curve_try_and_increment(
+        &self,
+        suite: Suite,
+        message: &[u8],
+    ) -> Option<EdwardsPoint> {
         let mut p_candidate_bytes = [0u8; 32];
         let mut h: Sha512 = Sha512::new();
-        h.update(SUITE_STRING);
+        h.update(suite.suite_string());
         h.update(ONE_STRING);
         h.update(&self.as_bytes()); // PK_string
         h.update(&message); // alpha_string
@@ -139,9 +166,16 @@ impl PublicKey {
     pub fn verify_key(&self) -> bool { !self.1.is_small_order() }
 
     /// Implements <https://tools.ietf.org/id/draft-irtf-cfrg-vrf-07.html#rfc.section.5.3>
-    #[allow(clippy::many_single_char_names)]
+    /// in the [Suite::Draft] suite.
     pub fn verify(&self, pi: &Proof, message: &[u8]) -> bool {
-        if let Some(h) = self.hash_to_curve(message) {
+        self.verify_with_suite(Suite::Draft, pi, message)
+    }
+
+    /// Implements <https://www.rfc-editor.org/rfc/rfc9381#section-5.3>. The
+    /// public key is validated when it is deserialized.
+    #[allow(clippy::many_single_char_names)]
+    pub fn verify_with_suite(&self, suite: Suite, pi: &Proof, message: &[u8]) -> bool {
+        if let Some(h) = self.encode_to_curve(suite, message) {
             let Proof(gamma, c, s) = pi; // s should be equal k + cx, where k is a deterministically
                                          // generated nonce and x is the secret key
                                          // self should be equal y=b^x
@@ -155,10 +189,25 @@ impl PublicKey {
             let v = h_to_s - gamma_to_c; // should equal h^k
 
             let derivable_c =
-                hash_points(&[h.compress(), gamma.compress(), u.compress(), v.compress()]);
+                hash_points_with_suite(suite, &self.challenge_points(suite, &[h, *gamma, u, v]));
             *c == derivable_c
         } else {
             false
         }
     }
+
+    /// The points that go into the challenge, i.e., the given points, preceded
+    /// by the public key if the suite requires it.
+    pub(crate) fn challenge_points(
+        &self,
+        suite: Suite,
+        points: &[EdwardsPoint],
+    ) -> Vec<CompressedEdwardsY> {
+        let mut compressed = Vec::with_capacity(points.len() + 1);
+        if suite.challenge_includes_public_key() {
+            compressed.push(self.0);
+        }
+        compressed.extend(points.iter().map(EdwardsPoint::compress));
+        compressed
+    }
 }
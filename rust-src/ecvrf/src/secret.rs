@@ -81,6 +81,11 @@ impl SecretKey {
         ExpandedSecretKey::from(self).prove(&public_key, &message)
     }
 
+    /// Construct a VRF proof seeded by the given message in the given suite.
+    pub fn prove_with_suite(&self, suite: Suite, public_key: &PublicKey, message: &[u8]) -> Proof {
+        ExpandedSecretKey::from(self).prove_with_suite(suite, public_key, message)
+    }
+
     /// Generate a `SecretKey` from a `csprng`.
     pub fn generate<T>(csprng: &mut T) -> SecretKey
     where
@@ -138,29 +143,39 @@ use crate::proof::*;
 impl ExpandedSecretKey {
     /// VRF proof with expanded secret key
     /// Implements <https://tools.ietf.org/id/draft-irtf-cfrg-vrf-07.html#rfc.section.5.1>
+    /// in the [Suite::Draft] suite.
     pub fn prove(&self, public_key: &PublicKey, alpha: &[u8]) -> Proof {
+        self.prove_with_suite(Suite::Draft, public_key, alpha)
+    }
+
+    /// VRF proof with expanded secret key
+    /// Implements <https://www.rfc-editor.org/rfc/rfc9381#section-5.1>
+    pub fn prove_with_suite(&self, suite: Suite, public_key: &PublicKey, alpha: &[u8]) -> Proof {
         let x = self.key;
         let h = public_key
-            .hash_to_curve(alpha)
+            .encode_to_curve(suite, alpha)
             .expect("Failure should not happen for non-maliciously crafted input.");
         let h_string = h.compress().to_bytes();
         let k = self.nonce_generation(&h_string);
 
         let gamma = x * h;
 
-        let c = hash_points(&[
-            h.compress(),
-            gamma.compress(),
-            (k * constants::ED25519_BASEPOINT_POINT).compress(), // b^k
-            (k * h).compress(),                                  // h^k
-        ]);
+        let c = hash_points_with_suite(
+            suite,
+            &public_key.challenge_points(suite, &[
+                h,
+                gamma,
+                k * constants::ED25519_BASEPOINT_POINT, // b^k
+                k * h,                                  // h^k
+            ]),
+        );
 
         let k_plus_cx = k + c * x;
 
         Proof(gamma, c, k_plus_cx)
     }
 
-    /// Implements <https://tools.ietf.org/id/draft-irtf-cfrg-vrf-07.html#rfc.section.5.4.2.2>
+    /// Implements <https://www.rfc-editor.org/rfc/rfc9381#section-5.4.2.2>
     fn nonce_generation(&self, h_string: &[u8]) -> Scalar {
         let h: Sha512 = Sha512::new().chain(self.nonce).chain(h_string);
         Scalar::from_hash(h)
@@ -21,14 +21,14 @@ pub struct Randomness<C: Curve> {
 }
 
 impl<C: Curve> AsRef<C::Scalar> for Randomness<C> {
-    fn as_ref(&self) -> &C::Scalar { &self.randomness }
+    fn as_ref(&self) -> &C::Scalar { (*self.randomness).as_ref() }
 }
 
 /// This trait allows automatic conversion of &Randomness<C> to &C::Scalar.
 impl<C: Curve> Deref for Randomness<C> {
     type Target = C::Scalar;
 
-    fn deref(&self) -> &C::Scalar { &self.randomness }
+    fn deref(&self) -> &C::Scalar { (*self.randomness).as_ref() }
 }
 
 impl<C: Curve> Randomness<C> {
@@ -115,6 +115,7 @@ pub fn multicombine<C: Curve>(ciphers: &[Cipher<C>], scalars: &[C::Scalar]) -> C
 #[cfg(test)]
 mod tests {
     use super::*;
+    use curve_arithmetic::Ristretto255;
     use pairing::bls12_381::{G1, G2};
 
     macro_rules! macro_test_cipher_to_byte_conversion {
@@ -134,4 +135,5 @@ mod tests {
 
     macro_test_cipher_to_byte_conversion!(key_to_cipher_conversion_g1, G1);
     macro_test_cipher_to_byte_conversion!(key_to_cipher_conversion_g2, G2);
+    macro_test_cipher_to_byte_conversion!(key_to_cipher_conversion_ristretto255, Ristretto255);
 }
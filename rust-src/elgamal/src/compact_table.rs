@@ -0,0 +1,452 @@
+//! A compact baby step giant step table that can be used directly from its
+//! serialized bytes.
+//!
+//! The table of [BabyStepGiantStep] maps the full serialization of each baby
+//! step to its index, and must be built or deserialized in full before use.
+//! The table [CompactBabyStepGiantStep] instead stores a sorted array of
+//! entries, each consisting of a truncated hash of the baby step and its
+//! index. The array is searched in place, so the table can be backed by a
+//! static byte array, or a memory mapped file, without copying it to the
+//! heap.
+//!
+//! Since the keys are truncated, different points can have the same key. Each
+//! candidate found by a lookup is hence checked by recomputing the point it
+//! corresponds to, and a lookup continues with the remaining entries with the
+//! same key if the check fails. A consequence is that a corrupted table can
+//! make the search fail, but never makes it return a wrong value.
+use crate::secret::BabyStepGiantStep;
+use anyhow::{ensure, Result};
+use crypto_common::*;
+use curve_arithmetic::Curve;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::{convert::TryInto, io::Cursor};
+
+/// The length of a table entry, a 4-byte key followed by a 4-byte index.
+const ENTRY_LENGTH: usize = 8;
+
+/// A table for computing small discrete logarithms with respect to a fixed
+/// base.
+pub trait DiscreteLog<C: Curve> {
+    /// Compute the discrete logarithm of `v`, if it is less than `bound`.
+    fn discrete_log_bounded(&self, v: &C, bound: u64) -> Option<u64>;
+
+    /// Compute the discrete logarithms of all the points, if they are less
+    /// than `bound`. The default implementation computes them one by one.
+    fn discrete_log_batch(&self, vs: &[C], bound: u64) -> Vec<Option<u64>> {
+        vs.iter()
+            .map(|v| self.discrete_log_bounded(v, bound))
+            .collect()
+    }
+}
+
+impl<C: Curve> DiscreteLog<C> for BabyStepGiantStep<C> {
+    fn discrete_log_bounded(&self, v: &C, bound: u64) -> Option<u64> {
+        let (m, inverse_point) = (self.m(), self.inverse_point());
+        let mut y = *v;
+        for i in 0..num_giant_steps(bound, m) {
+            if let Some(j) = self.lookup(&y) {
+                let x = i * m + j;
+                return if x < bound { Some(x) } else { None };
+            }
+            y = y.plus_point(&inverse_point);
+        }
+        None
+    }
+}
+
+/// The number of giant steps needed to find all values less than `bound`
+/// with a table of size `m`.
+fn num_giant_steps(bound: u64, m: u64) -> u64 { bound / m + u64::from(bound % m != 0) }
+
+/// The key of a point in the compact table, the first 4 bytes of the SHA256
+/// hash of its serialization.
+fn point_key<C: Curve>(point: &C) -> u32 {
+    let hash = Sha256::digest(&to_bytes(point));
+    u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+}
+
+/// The table for the baby step giant step algorithm in a compact format that
+/// is used directly from its serialization, see the module documentation.
+///
+/// The serialization consists of the size of the table `m` as a big endian
+/// `u64`, the serializations of the base and of the point `base^{-m}`, and
+/// `m` entries sorted by key. Each entry consists of the key, the first 4
+/// bytes of the SHA256 hash of the serialization of `base^j`, and the index
+/// `j`, both as big endian `u32`.
+pub struct CompactBabyStepGiantStep<C: Curve, B: AsRef<[u8]> = Vec<u8>> {
+    /// The serialized table.
+    bytes:         B,
+    /// The length of the header preceding the entries.
+    header_len:    usize,
+    /// The base of the discrete logarithms.
+    base:          C,
+    /// Point base^{-m}
+    inverse_point: C,
+    /// Size of the table.
+    m:             u64,
+}
+
+impl<C: Curve> CompactBabyStepGiantStep<C> {
+    /// Generate a new instance, precomputing the table. The size `m` must be
+    /// positive and at most `2^32`.
+    pub fn new(base: &C, m: u64) -> Self {
+        assert!(
+            m > 0 && m <= 1 << 32,
+            "The size of the table must be between 1 and 2^32."
+        );
+        let mut entries = Vec::with_capacity(m as usize);
+        let mut base_j = C::zero_point();
+        for j in 0..m {
+            entries.push((point_key(&base_j), j as u32));
+            base_j = base_j.plus_point(base);
+        }
+        entries.sort_unstable();
+        let inverse_point = base_j.inverse_point();
+
+        let mut bytes = Vec::new();
+        bytes.put(&m);
+        bytes.put(base);
+        bytes.put(&inverse_point);
+        let header_len = bytes.len();
+        bytes.reserve_exact(entries.len() * ENTRY_LENGTH);
+        for (key, j) in entries {
+            bytes.extend_from_slice(&key.to_be_bytes());
+            bytes.extend_from_slice(&j.to_be_bytes());
+        }
+        Self {
+            bytes,
+            header_len,
+            base: *base,
+            inverse_point,
+            m,
+        }
+    }
+}
+
+/// A compact table memory mapped from a file.
+#[cfg(feature = "mmap")]
+pub type MappedBabyStepGiantStep<C> = CompactBabyStepGiantStep<C, memmap2::Mmap>;
+
+#[cfg(feature = "mmap")]
+impl<C: Curve> MappedBabyStepGiantStep<C> {
+    /// Memory map a table from a file containing its serialization, see
+    /// [CompactBabyStepGiantStep::as_bytes]. The entries are only read from
+    /// the file as they are needed.
+    ///
+    /// The file must not be modified or truncated while the table is in use.
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // Safety: The table files are written once and then only read, see
+        // above.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Self::from_bytes(mmap)
+    }
+}
+
+impl<C: Curve, B: AsRef<[u8]>> CompactBabyStepGiantStep<C, B> {
+    /// Use the serialization of a table, see
+    /// [CompactBabyStepGiantStep::as_bytes]. Only the header is parsed, the
+    /// entries are read in place by the searches.
+    pub fn from_bytes(bytes: B) -> Result<Self> {
+        let mut source = Cursor::new(bytes.as_ref());
+        let m: u64 = source.get()?;
+        let base: C = source.get()?;
+        let inverse_point: C = source.get()?;
+        let header_len = source.position() as usize;
+        ensure!(
+            m > 0 && m <= 1 << 32,
+            "The size of the table must be between 1 and 2^32."
+        );
+        ensure!(
+            (bytes.as_ref().len() - header_len) as u64 == m * ENTRY_LENGTH as u64,
+            "The number of entries does not match the size of the table."
+        );
+        ensure!(
+            base.mul_by_scalar(&C::scalar_from_u64(m)).inverse_point() == inverse_point,
+            "The giant step does not match the base."
+        );
+        Ok(Self {
+            bytes,
+            header_len,
+            base,
+            inverse_point,
+            m,
+        })
+    }
+
+    /// The serialization of the table.
+    pub fn as_bytes(&self) -> &[u8] { self.bytes.as_ref() }
+
+    /// The base of the discrete logarithms computed with the table.
+    pub fn base(&self) -> &C { &self.base }
+
+    /// The size of the table.
+    pub fn m(&self) -> u64 { self.m }
+
+    /// The key and index of the `i`-th entry.
+    fn entry(&self, i: u64) -> (u32, u32) {
+        let start = self.header_len + i as usize * ENTRY_LENGTH;
+        let entry = &self.bytes.as_ref()[start..start + ENTRY_LENGTH];
+        let key = u32::from_be_bytes(entry[..4].try_into().expect("The key has 4 bytes."));
+        let j = u32::from_be_bytes(entry[4..].try_into().expect("The index has 4 bytes."));
+        (key, j)
+    }
+
+    /// The first entry whose key is not less than `key`.
+    fn lower_bound(&self, key: u32) -> u64 {
+        let (mut low, mut high) = (0, self.m);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.entry(mid).0 < key {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    /// Search the giant steps `start..end` for the discrete logarithm of `v`.
+    fn search(&self, v: &C, start: u64, end: u64, bound: u64) -> Option<u64> {
+        let mut y = v.plus_point(&self.inverse_point.mul_by_scalar(&C::scalar_from_u64(start)));
+        for i in start..end {
+            if let Some(x) = self.giant_step(v, &y, i, bound) {
+                return Some(x);
+            }
+            y = y.plus_point(&self.inverse_point);
+        }
+        None
+    }
+
+    /// Look up `y = v * base^{-i m}`, the `i`-th giant step from `v`, in the
+    /// table. Every candidate is checked against `v`, so that collisions of
+    /// the keys are handled.
+    fn giant_step(&self, v: &C, y: &C, i: u64, bound: u64) -> Option<u64> {
+        let key = point_key(y);
+        let mut index = self.lower_bound(key);
+        while index < self.m {
+            let (entry_key, j) = self.entry(index);
+            if entry_key != key {
+                break;
+            }
+            let x = i * self.m + u64::from(j);
+            if x < bound && self.base.mul_by_scalar(&C::scalar_from_u64(x)) == *v {
+                return Some(x);
+            }
+            index += 1;
+        }
+        None
+    }
+
+    /// Search for the discrete logarithms of all the points, taking the giant
+    /// steps of all the points that are not found yet together. The points
+    /// are normalized together before each lookup, which for projective
+    /// points replaces an inversion per point by a single one, see
+    /// [Curve::batch_normalize].
+    fn search_batch(&self, vs: &[C], bound: u64) -> Vec<Option<u64>> {
+        let mut results = vec![None; vs.len()];
+        // The indices of the points that are not found yet, and their current
+        // giant steps.
+        let mut pending: Vec<usize> = (0..vs.len()).collect();
+        let mut ys = vs.to_vec();
+        for i in 0..num_giant_steps(bound, self.m) {
+            if pending.is_empty() {
+                break;
+            }
+            C::batch_normalize(&mut ys);
+            let mut next_pending = Vec::with_capacity(pending.len());
+            let mut next_ys = Vec::with_capacity(ys.len());
+            for (&index, y) in pending.iter().zip(ys.iter()) {
+                match self.giant_step(&vs[index], y, i, bound) {
+                    Some(x) => results[index] = Some(x),
+                    None => {
+                        next_pending.push(index);
+                        next_ys.push(y.plus_point(&self.inverse_point));
+                    }
+                }
+            }
+            pending = next_pending;
+            ys = next_ys;
+        }
+        results
+    }
+}
+
+impl<C: Curve, B: AsRef<[u8]> + Sync> DiscreteLog<C> for CompactBabyStepGiantStep<C, B> {
+    /// Compute the discrete logarithm of `v`, if it is less than `bound`. With
+    /// the `parallel` feature the giant steps are split into segments that
+    /// are searched in parallel.
+    fn discrete_log_bounded(&self, v: &C, bound: u64) -> Option<u64> {
+        let giant_steps = num_giant_steps(bound, self.m);
+        #[cfg(feature = "parallel")]
+        {
+            // Use more segments than threads, so that the search stops soon
+            // after the value is found.
+            let num_segments = std::cmp::min(4 * rayon::current_num_threads() as u64, giant_steps);
+            if num_segments > 1 {
+                let segment = num_giant_steps(giant_steps, num_segments);
+                return (0..num_segments).into_par_iter().find_map_any(|s| {
+                    let start = s * segment;
+                    let end = std::cmp::min(start + segment, giant_steps);
+                    self.search(v, start, end, bound)
+                });
+            }
+        }
+        self.search(v, 0, giant_steps, bound)
+    }
+
+    /// Compute the discrete logarithms of the points that are less than
+    /// `bound`, searching for all of them together. With the `parallel`
+    /// feature the points are split among the threads.
+    fn discrete_log_batch(&self, vs: &[C], bound: u64) -> Vec<Option<u64>> {
+        if vs.len() == 1 {
+            return vec![self.discrete_log_bounded(&vs[0], bound)];
+        }
+        #[cfg(feature = "parallel")]
+        {
+            let threads = rayon::current_num_threads();
+            if threads > 1 && vs.len() > 1 {
+                let chunk_size = (vs.len() + threads - 1) / threads;
+                let results: Vec<Vec<Option<u64>>> = vs
+                    .par_chunks(chunk_size)
+                    .map(|chunk| self.search_batch(chunk, bound))
+                    .collect();
+                return results.concat();
+            }
+        }
+        self.search_batch(vs, bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve_arithmetic::Ristretto255;
+    use pairing::bls12_381::G1;
+    use rand::*;
+
+    // Check that values up to the bound are found, and values above it are not.
+    fn check_discrete_log<C: Curve>() {
+        let mut csprng = thread_rng();
+        let base = C::generate(&mut csprng);
+        let m = 1 << 8;
+        let bound = 50_001;
+        let table = CompactBabyStepGiantStep::new(&base, m);
+        let values = [0, 1, m - 1, m, 7 * m + 3, bound - 1]
+            .iter()
+            .copied()
+            .chain((0..5).map(|_| csprng.gen_range(0, bound)))
+            .collect::<Vec<_>>();
+        for x in values {
+            let v = base.mul_by_scalar(&C::scalar_from_u64(x));
+            assert_eq!(table.discrete_log_bounded(&v, bound), Some(x));
+        }
+        let v = base.mul_by_scalar(&C::scalar_from_u64(bound));
+        assert_eq!(table.discrete_log_bounded(&v, bound), None);
+    }
+
+    #[test]
+    fn test_compact_discrete_log_g1() { check_discrete_log::<G1>() }
+
+    #[test]
+    fn test_compact_discrete_log_ristretto255() { check_discrete_log::<Ristretto255>() }
+
+    // The compact table and the hash map table should agree.
+    #[test]
+    fn test_compact_agrees_with_bsgs() {
+        let mut csprng = thread_rng();
+        let base = G1::generate(&mut csprng);
+        let m = 1 << 10;
+        let compact = CompactBabyStepGiantStep::new(&base, m);
+        let bsgs = BabyStepGiantStep::new(&base, m);
+        for _ in 0..5 {
+            let x = csprng.gen_range(0, 1 << 16);
+            let v = base.mul_by_scalar(&G1::scalar_from_u64(x));
+            assert_eq!(compact.discrete_log_bounded(&v, 1 << 16), Some(x));
+            assert_eq!(bsgs.discrete_log_bounded(&v, 1 << 16), Some(x));
+            assert_eq!(bsgs.discrete_log(&v), x);
+        }
+    }
+
+    // Batch search should agree with searching for each point, including the
+    // points that are not found.
+    #[test]
+    fn test_compact_discrete_log_batch() {
+        let mut csprng = thread_rng();
+        let base = G1::generate(&mut csprng);
+        let m = 1 << 8;
+        let bound = 1 << 14;
+        let table = CompactBabyStepGiantStep::new(&base, m);
+        let values = [0, 5, bound - 1, bound, 3 * m + 1, 3 * m + 1];
+        let vs = values
+            .iter()
+            .map(|x| base.mul_by_scalar(&G1::scalar_from_u64(*x)))
+            .collect::<Vec<_>>();
+        let expected = values
+            .iter()
+            .map(|&x| if x < bound { Some(x) } else { None })
+            .collect::<Vec<_>>();
+        assert_eq!(table.discrete_log_batch(&vs, bound), expected);
+        assert_eq!(table.discrete_log_batch(&[], bound), Vec::new());
+    }
+
+    // Make two entries have the same key, and check that both values are still
+    // found, or not found, but never confused.
+    #[test]
+    fn test_compact_key_collision() {
+        let mut csprng = thread_rng();
+        let base = G1::generate(&mut csprng);
+        let m = 1 << 8;
+        let table = CompactBabyStepGiantStep::new(&base, m);
+        let (_, j0) = table.entry(0);
+        let (key1, j1) = table.entry(1);
+        let mut bytes = table.as_bytes().to_vec();
+        bytes[table.header_len..table.header_len + 4].copy_from_slice(&key1.to_be_bytes());
+        let tampered =
+            CompactBabyStepGiantStep::<G1, _>::from_bytes(bytes).expect("The header is unchanged.");
+        let x1 = 3 * m + u64::from(j1);
+        let v1 = base.mul_by_scalar(&G1::scalar_from_u64(x1));
+        assert_eq!(tampered.discrete_log_bounded(&v1, 1 << 16), Some(x1));
+        let x0 = 3 * m + u64::from(j0);
+        let v0 = base.mul_by_scalar(&G1::scalar_from_u64(x0));
+        assert_eq!(tampered.discrete_log_bounded(&v0, 1 << 16), None);
+    }
+
+    #[test]
+    fn test_compact_from_bytes() {
+        let mut csprng = thread_rng();
+        let base = G1::generate(&mut csprng);
+        let table = CompactBabyStepGiantStep::new(&base, 1 << 8);
+        let bytes = table.as_bytes();
+        let parsed = CompactBabyStepGiantStep::<G1, &[u8]>::from_bytes(bytes)
+            .expect("Parsing the serialization should succeed.");
+        assert_eq!(parsed.m(), table.m());
+        assert_eq!(parsed.base(), table.base());
+        let v = base.mul_by_scalar(&G1::scalar_from_u64(12345));
+        assert_eq!(parsed.discrete_log_bounded(&v, 1 << 16), Some(12345));
+        assert!(
+            CompactBabyStepGiantStep::<G1, &[u8]>::from_bytes(&bytes[..bytes.len() - 1]).is_err(),
+            "A truncated table should be rejected."
+        );
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_compact_from_file() {
+        let mut csprng = thread_rng();
+        let base = G1::generate(&mut csprng);
+        let table = CompactBabyStepGiantStep::new(&base, 1 << 8);
+        let path = std::env::temp_dir().join(format!("bsgs-{}.bin", csprng.gen::<u64>()));
+        std::fs::write(&path, table.as_bytes()).expect("Writing the table should succeed.");
+        let v = base.mul_by_scalar(&G1::scalar_from_u64(4321));
+        let result = CompactBabyStepGiantStep::<G1, _>::from_file(&path)
+            .map(|mapped| mapped.discrete_log_bounded(&v, 1 << 16));
+        std::fs::remove_file(&path).expect("Removing the table should succeed.");
+        assert_eq!(
+            result.expect("Mapping the table should succeed."),
+            Some(4321)
+        );
+    }
+}
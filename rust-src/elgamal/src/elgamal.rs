@@ -113,7 +113,7 @@ pub fn chunks_to_value<C: Curve>(chunks: &[Value<C>], chunk_size: ChunkSize) ->
     for chunk_section in chunks.chunks(64 / usize::from(u8::from(chunk_size))) {
         // get the u64 encoded in this chunk section
         let v = chunk_size.chunks_to_u64(chunk_section.iter().map(|chunk| {
-            let repr = chunk.into_repr();
+            let repr = chunk.as_ref().into_repr();
             repr.as_ref()[0]
         }));
         let mut val = C::scalar_from_u64(v);
@@ -142,7 +142,7 @@ pub fn encrypt_in_chunks_given_generator<C: Curve, R: Rng>(
     generator: &C,
     csprng: &mut R,
 ) -> Vec<(Cipher<C>, Randomness<C>)> {
-    let chunks = value_to_chunks::<C>(val, chunk_size);
+    let chunks = value_to_chunks::<C>(val.as_ref(), chunk_size);
     pk.encrypt_exponent_vec_given_generator(&chunks, generator, csprng)
 }
 
@@ -202,6 +202,7 @@ pub fn decrypt_from_chunks_given_table<C: Curve>(
 mod tests {
     use super::*;
     use crate::message::*;
+    use curve_arithmetic::Ristretto255;
     use ff::Field;
     use pairing::bls12_381::{G1, G2};
     use rand::{rngs::ThreadRng, Rng};
@@ -231,6 +232,11 @@ mod tests {
     #[test]
     fn encrypt_decrypt_success_g2() { test_encrypt_decrypt_success_generic::<G2>() }
 
+    #[test]
+    fn encrypt_decrypt_success_ristretto255() {
+        test_encrypt_decrypt_success_generic::<Ristretto255>()
+    }
+
     // This is a generic helper function that tests encryption/decryption in chunks.
     // It is parameterized by a curve, and the intention is that concrete tests are
     // going to use explicit curve instances.
@@ -243,7 +249,7 @@ mod tests {
             let mut e = <C as Curve>::Scalar::zero();
             let one_scalar = Value::<C>::new(<C as Curve>::Scalar::one());
             for _ in 0..n {
-                e.add_assign(&one_scalar);
+                e.add_assign(one_scalar.as_ref());
             }
             let c = pk.encrypt_exponent(&mut csprng, &Value::new(e));
             let e2 = sk.decrypt_exponent_slow(&c);
@@ -262,6 +268,11 @@ mod tests {
         test_encrypt_decrypt_exponent_success_generic::<G2>()
     }
 
+    #[test]
+    fn encrypt_decrypt_exponent_success_ristretto255() {
+        test_encrypt_decrypt_exponent_success_generic::<Ristretto255>()
+    }
+
     // This is a generic helper function that tests encryption/decryption in chunks.
     // It is parameterized by a curve, and the intention is that concrete tests are
     // going to use explicit curve instances.
@@ -275,7 +286,7 @@ mod tests {
             let scalar = Value::<C>::generate(&mut csprng);
             let chunk_size_index: usize = csprng.gen_range(0, possible_chunk_sizes.len());
             let chunk_size = possible_chunk_sizes[chunk_size_index];
-            let chunks = value_to_chunks::<C>(&scalar, chunk_size);
+            let chunks = value_to_chunks::<C>(scalar.as_ref(), chunk_size);
             let retrieved_scalar = chunks_to_value::<C>(&chunks, chunk_size);
             // assert!(true);
             assert_eq!(scalar, retrieved_scalar);
@@ -285,6 +296,9 @@ mod tests {
     #[test]
     fn chunking_test_g1() { test_chunking_generic::<G1>() }
 
+    #[test]
+    fn chunking_test_ristretto255() { test_chunking_generic::<Ristretto255>() }
+
     // This is a generic helper function that tests encryption/decryption in chunks.
     // It is parameterized by a curve, and the intention is that concrete tests are
     // going to use explicit curve instances.
@@ -313,4 +327,9 @@ mod tests {
 
     #[test]
     fn chunked_encrypt_decrypt_test_g1() { test_chunked_encrypt_decrypt_generic::<G1>() }
+
+    #[test]
+    fn chunked_encrypt_decrypt_test_ristretto255() {
+        test_chunked_encrypt_decrypt_generic::<Ristretto255>()
+    }
 }
@@ -1,13 +1,14 @@
 //! Implementation of elgamal public key encryption and decryption over a Curve.
 
 mod cipher;
+mod compact_table;
 mod elgamal;
 mod errors;
 mod message;
 mod public;
 mod secret;
 
-pub use crate::{cipher::*, elgamal::*, message::*, public::*, secret::*};
+pub use crate::{cipher::*, compact_table::*, elgamal::*, message::*, public::*, secret::*};
 
 #[macro_use]
 extern crate crypto_common_derive;
@@ -7,7 +7,7 @@ use crate::{cipher::*, message::*, secret::*};
 
 use crypto_common::*;
 use crypto_common_derive::*;
-use curve_arithmetic::{multiexp, Curve, Value};
+use curve_arithmetic::{Curve, Value};
 
 /// Elgamal public key .
 #[derive(Copy, Clone, Eq, PartialEq, Serialize, SerdeBase16Serialize)]
@@ -26,7 +26,7 @@ impl<'a, C: Curve> From<&'a SecretKey<C>> for PublicKey<C> {
     /// Derive this public key from its corresponding `SecretKey`.
     fn from(secret_key: &SecretKey<C>) -> PublicKey<C> {
         let generator: C = secret_key.generator;
-        let key = generator.mul_by_scalar(&secret_key.scalar);
+        let key = secret_key.mul_point(&generator);
         PublicKey { generator, key }
     }
 }
@@ -38,11 +38,7 @@ impl<C: Curve> PublicKey<C> {
     where
         T: Rng, {
         let k = Randomness::generate(csprng);
-        let g = self.generator.mul_by_scalar(&k.randomness);
-        // FIXME: Could use multiexponentiation when we are calling from
-        // encrypt_exponent_rand.
-        let s = self.key.mul_by_scalar(&k.randomness).plus_point(&m.value);
-        (Cipher(g, s), k)
+        (self.hide(&k, m), k)
     }
 
     #[inline]
@@ -53,9 +49,14 @@ impl<C: Curve> PublicKey<C> {
         self.encrypt_rand(csprng, m).0
     }
 
-    pub fn hide(&self, k: &C::Scalar, message: &Message<C>) -> Cipher<C> {
-        let t = self.generator.mul_by_scalar(k);
-        let s = self.key.mul_by_scalar(&k).plus_point(&message.value);
+    /// Encrypt the message with the given randomness. The randomness is secret,
+    /// so the points are multiplied by it in constant time.
+    pub fn hide(&self, k: &Randomness<C>, message: &Message<C>) -> Cipher<C> {
+        let t = self.generator.mul_by_secret(&k.randomness);
+        let s = self
+            .key
+            .mul_by_secret(&k.randomness)
+            .plus_point(&message.value);
         Cipher(t, s)
     }
 
@@ -84,10 +85,12 @@ impl<C: Curve> PublicKey<C> {
     ) -> (Cipher<C>, Randomness<C>)
     where
         T: Rng, {
-        let randomness = C::generate_scalar(csprng);
-        let g = self.generator.mul_by_scalar(&randomness);
-        let s = multiexp(&[self.key, *h], &[randomness, *e.as_ref()]);
-        let randomness = Randomness::new(randomness);
+        let randomness = Randomness::new(C::generate_scalar(csprng));
+        let g = self.generator.mul_by_secret(&randomness.randomness);
+        let s = self
+            .key
+            .mul_by_secret(&randomness.randomness)
+            .plus_point(&e.mul_point(h));
         (Cipher(g, s), randomness)
     }
 
@@ -148,6 +151,7 @@ impl<C: Curve> PublicKey<C> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use curve_arithmetic::Ristretto255;
     use pairing::bls12_381::{G1, G2};
 
     macro_rules! macro_test_key_to_byte_conversion {
@@ -169,4 +173,5 @@ mod tests {
 
     macro_test_key_to_byte_conversion!(key_to_byte_conversion_g1, G1);
     macro_test_key_to_byte_conversion!(key_to_byte_conversion_g2, G2);
+    macro_test_key_to_byte_conversion!(key_to_byte_conversion_ristretto255, Ristretto255);
 }
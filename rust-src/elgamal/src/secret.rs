@@ -1,10 +1,10 @@
 // -*- mode: rust; -*-
 
 //! Elgamal secret key types
-use crate::{cipher::*, message::*};
+use crate::{cipher::*, compact_table::DiscreteLog, message::*};
 use anyhow::{bail, Result};
 use crypto_common::*;
-use curve_arithmetic::{Curve, Value};
+use curve_arithmetic::{Curve, Secret, Value};
 use ff::Field;
 use rand::*;
 use std::collections::HashMap;
@@ -106,6 +106,17 @@ impl<C: Curve> BabyStepGiantStep<C> {
         unreachable!("It should not be feasible to do 2^64 group additions.")
     }
 
+    /// Look up a point among the baby steps.
+    pub(crate) fn lookup(&self, point: &C) -> Option<u64> {
+        self.table.get(&to_bytes(point)).copied()
+    }
+
+    /// Size of the table.
+    pub fn m(&self) -> u64 { self.m }
+
+    /// The giant step, `base^{-m}`.
+    pub fn inverse_point(&self) -> C { self.inverse_point }
+
     /// Composition of `new` nad `discrete_log` methods for convenience.
     ///
     /// Less efficient than reusing the table.
@@ -115,9 +126,13 @@ impl<C: Curve> BabyStepGiantStep<C> {
 }
 
 impl<C: Curve> SecretKey<C> {
+    /// Multiply the point by the secret key in constant time, see
+    /// [Curve::mul_by_secret].
+    pub fn mul_point(&self, point: &C) -> C { point.mul_by_secret(&Secret::new(self.scalar)) }
+
     pub fn decrypt(&self, c: &Cipher<C>) -> Message<C> {
         let x = c.0; // k * g
-        let kag = x.mul_by_scalar(&self.scalar); // k * a * g
+        let kag = self.mul_point(&x); // k * a * g
         let y = c.1; // m + k * a * g
         let value = y.minus_point(&kag); // m
         Message { value }
@@ -146,6 +161,21 @@ impl<C: Curve> SecretKey<C> {
         bsgs.discrete_log(&dec)
     }
 
+    /// Decrypt the value in the exponent, if it is less than `bound`, using
+    /// any table for discrete logarithms, e.g., a [CompactBabyStepGiantStep].
+    /// Unlike [SecretKey::decrypt_exponent] this function always terminates.
+    ///
+    /// [CompactBabyStepGiantStep]: crate::CompactBabyStepGiantStep
+    pub fn decrypt_exponent_bounded<T: DiscreteLog<C> + ?Sized>(
+        &self,
+        c: &Cipher<C>,
+        table: &T,
+        bound: u64,
+    ) -> Option<u64> {
+        let dec = self.decrypt(c).value;
+        table.discrete_log_bounded(&dec, bound)
+    }
+
     /// Generate a `SecretKey` from a `csprng`.
     pub fn generate<T: Rng>(generator: &C, csprng: &mut T) -> Self {
         SecretKey {
@@ -167,6 +197,7 @@ impl<C: Curve> SecretKey<C> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use curve_arithmetic::Ristretto255;
     use pairing::bls12_381::{G1, G2};
     macro_rules! macro_test_secret_key_to_byte_conversion {
         ($function_name:ident, $curve_type:path) => {
@@ -187,6 +218,10 @@ mod tests {
 
     macro_test_secret_key_to_byte_conversion!(secret_key_to_byte_conversion_g1, G1);
     macro_test_secret_key_to_byte_conversion!(secret_key_to_byte_conversion_g2, G2);
+    macro_test_secret_key_to_byte_conversion!(
+        secret_key_to_byte_conversion_ristretto255,
+        Ristretto255
+    );
 
     // Test serialiation of baby-step-giant-step since it is implemented manually.
     #[test]
@@ -368,6 +368,114 @@ unsafe extern "C" fn decrypt_amount(
     crate::decrypt_amount(from_ptr!(table_ptr), &sk, &amount).microgtu
 }
 
+/// Memory map a compact table, see [CompactBabyStepGiantStep], from the file
+/// whose path is given as `path_len` UTF8 bytes. The table is not read into
+/// memory, and only the parts of it used by decryptions are loaded from the
+/// file. Return a null pointer if the table cannot be loaded.
+///
+/// # Safety
+/// This function is safe if the path pointer points to `path_len` bytes.
+#[cfg(not(target_arch = "wasm32"))]
+#[no_mangle]
+unsafe extern "C" fn load_compact_table(
+    path_ptr: *const u8,
+    path_len: size_t,
+) -> *mut MappedBabyStepGiantStep<Group> {
+    let path = slice_from_c_bytes!(path_ptr, path_len);
+    let table = match std::str::from_utf8(path) {
+        Ok(path) => MappedBabyStepGiantStep::from_file(path),
+        Err(_) => return std::ptr::null_mut(),
+    };
+    match table {
+        Ok(table) => Box::into_raw(Box::new(table)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+macro_free_ffi!(Box free_compact_table, MappedBabyStepGiantStep<Group>);
+
+/// Decrypt an amount using a compact table. In case of failure to find the
+/// chunks the function will set the `success` flag to `0`, and the return
+/// value should not be used. Otherwise the flag is set to `1`.
+///
+/// # Safety
+/// This function is safe if the input pointers are all non-null, and produce by
+/// `Box::into_raw`.
+#[cfg(not(target_arch = "wasm32"))]
+#[no_mangle]
+unsafe extern "C" fn decrypt_amount_compact(
+    table_ptr: *const MappedBabyStepGiantStep<Group>,
+    sec_ptr: *const elgamal::SecretKey<Group>,
+    high_ptr: *const elgamal::Cipher<Group>,
+    low_ptr: *const elgamal::Cipher<Group>,
+    success: *mut u8,
+) -> u64 {
+    let sk = from_ptr!(sec_ptr);
+    let amount = EncryptedAmount {
+        encryptions: [*from_ptr!(low_ptr), *from_ptr!(high_ptr)],
+    };
+    match crate::try_decrypt_amount(from_ptr!(table_ptr), sk, &amount) {
+        Some(amount) => {
+            *success = 1;
+            amount.microgtu
+        }
+        None => {
+            *success = 0;
+            0
+        }
+    }
+}
+
+/// Decrypt `len` amounts using a compact table, see [decrypt_amounts_batch].
+/// The `i`-th amount consists of the ciphers pointed to by the `i`-th elements
+/// of `high_ptrs` and `low_ptrs`. Its decryption is written to the `i`-th
+/// element of `out_amounts`, and the `i`-th element of `out_success` is set
+/// to `1`, or to `0` if the amount cannot be decrypted, in which case the
+/// amount written should not be used.
+///
+/// # Safety
+/// This function is safe if the table and secret key pointers are non-null
+/// and produced by `Box::into_raw`, the input arrays contain `len` non-null
+/// pointers produced by `Box::into_raw`, and the output arrays have space for
+/// `len` elements.
+#[cfg(not(target_arch = "wasm32"))]
+#[no_mangle]
+unsafe extern "C" fn decrypt_amounts_batch_compact(
+    table_ptr: *const MappedBabyStepGiantStep<Group>,
+    sec_ptr: *const elgamal::SecretKey<Group>,
+    high_ptrs: *const *const elgamal::Cipher<Group>,
+    low_ptrs: *const *const elgamal::Cipher<Group>,
+    len: size_t,
+    out_amounts: *mut u64,
+    out_success: *mut u8,
+) {
+    let sk = from_ptr!(sec_ptr);
+    let highs = slice_from_c_bytes!(high_ptrs, len);
+    let lows = slice_from_c_bytes!(low_ptrs, len);
+    let amounts = highs
+        .iter()
+        .zip(lows.iter())
+        .map(|(&high_ptr, &low_ptr)| EncryptedAmount {
+            encryptions: [*from_ptr!(low_ptr), *from_ptr!(high_ptr)],
+        })
+        .collect::<Vec<_>>();
+    let out_amounts = mut_slice_from_c_bytes!(out_amounts, len);
+    let out_success = mut_slice_from_c_bytes!(out_success, len);
+    let decrypted = crate::decrypt_amounts_batch(from_ptr!(table_ptr), sk, &amounts);
+    for (i, amount) in decrypted.into_iter().enumerate() {
+        match amount {
+            Some(amount) => {
+                out_amounts[i] = amount.microgtu;
+                out_success[i] = 1;
+            }
+            None => {
+                out_amounts[i] = 0;
+                out_success[i] = 0;
+            }
+        }
+    }
+}
+
 /// # Safety
 /// This function is safe if the pointers to structures are all non-null, and
 /// produced by `Box::into_raw`.
@@ -16,6 +16,8 @@ use elgamal::*;
 use id::types::*;
 use rand::*;
 use random_oracle::*;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 /// # Internal helper functions.
 
@@ -28,15 +30,12 @@ fn encrypt_amount<C: Curve, R: Rng>(
     csprng: &mut R,
 ) -> (EncryptedAmount<C>, EncryptedAmountRandomness<C>) {
     // The generator for encryption in the exponent is the second component of the
-    // commitment key, the 'h'.
-    let h = context.encryption_in_exponent_generator();
-    let mut ciphers = encrypt_u64_in_chunks_given_generator(
-        pk,
-        u64::from(amount),
-        CHUNK_SIZE_ENC_TRANS,
-        h,
-        csprng,
-    );
+    // commitment key, the 'h'. The context encrypts with its precomputed tables.
+    let mut ciphers = CHUNK_SIZE_ENC_TRANS
+        .u64_to_chunks(u64::from(amount))
+        .into_iter()
+        .map(|x| context.encrypt_exponent_rand(pk, &Value::<C>::from(x), csprng))
+        .collect::<Vec<_>>();
     // these two are guaranteed to exist because we used `ChunkSize::ThirtyTwo`. The
     // encryptions are in little-endian limbs, so the last one is the encryption
     // of the high bits.
@@ -63,7 +62,8 @@ pub fn encrypt_amount_with_fixed_randomness<C: Curve>(
 ) -> EncryptedAmount<C> {
     // The generator for encryption in the exponent is the second component of the
     // commitment key, the 'h'.
-    let h = context.encryption_in_exponent_generator();
+    let tables = context.on_chain_commitment_key_table();
+    let h = tables.h();
     let val = u64::from(amount);
     let chunks = CHUNK_SIZE_ENC_TRANS
         .u64_to_chunks(val)
@@ -72,7 +72,7 @@ pub fn encrypt_amount_with_fixed_randomness<C: Curve>(
         .collect::<Vec<_>>();
     let mut ciphers = Vec::with_capacity(chunks.len());
     for x in chunks {
-        let cipher = Cipher(C::zero_point(), h.mul_by_scalar(&x));
+        let cipher = Cipher(C::zero_point(), h.mul_by_scalar(x.as_ref()));
         ciphers.push(cipher);
     }
     let encryption_hi = ciphers.pop().unwrap();
@@ -116,6 +116,59 @@ pub fn decrypt_amount<C: Curve>(
     Amount::from(CHUNK_SIZE_ENC_TRANS.chunks_to_u64([low_chunk, hi_chunk].iter().copied()))
 }
 
+/// Decrypt a single amount given any table for discrete logarithms, e.g., a
+/// [CompactBabyStepGiantStep].
+///
+/// Each chunk is only searched for among the values that fit in a chunk, so
+/// unlike [decrypt_amount] this function always terminates. It returns `None`
+/// if a chunk is not found, which means that the amount was not encrypted
+/// correctly, or with a different generator than the one of the table.
+pub fn try_decrypt_amount<C: Curve, T: DiscreteLog<C> + ?Sized>(
+    table: &T,
+    sk: &SecretKey<C>,
+    amount: &EncryptedAmount<C>,
+) -> Option<Amount> {
+    let bound = 1u64 << u8::from(CHUNK_SIZE_ENC_TRANS);
+    let low_chunk = sk.decrypt_exponent_bounded(&amount.encryptions[0], table, bound)?;
+    let hi_chunk = sk.decrypt_exponent_bounded(&amount.encryptions[1], table, bound)?;
+    Some(Amount::from(
+        CHUNK_SIZE_ENC_TRANS.chunks_to_u64([low_chunk, hi_chunk].iter().copied()),
+    ))
+}
+
+/// Decrypt many amounts with the same secret key and table, e.g., the
+/// incoming amounts of an account. The result contains the decryption of each
+/// amount, or `None` if it cannot be decrypted, see [try_decrypt_amount].
+///
+/// This is faster than decrypting the amounts one by one, since the giant
+/// steps of all the chunks are taken together, and normalized with a single
+/// simultaneous inversion, see [DiscreteLog::discrete_log_batch]. With the
+/// `parallel` feature the decryptions and the search are split among multiple
+/// threads.
+pub fn decrypt_amounts_batch<C: Curve, T: DiscreteLog<C> + ?Sized + Sync>(
+    table: &T,
+    sk: &SecretKey<C>,
+    amounts: &[EncryptedAmount<C>],
+) -> Vec<Option<Amount>> {
+    let bound = 1u64 << u8::from(CHUNK_SIZE_ENC_TRANS);
+    let ciphers: Vec<&Cipher<C>> = amounts.iter().flat_map(|a| a.encryptions.iter()).collect();
+    #[cfg(feature = "parallel")]
+    let decryptions: Vec<C> = ciphers.par_iter().map(|c| sk.decrypt(c).value).collect();
+    #[cfg(not(feature = "parallel"))]
+    let decryptions: Vec<C> = ciphers.iter().map(|c| sk.decrypt(c).value).collect();
+    table
+        .discrete_log_batch(&decryptions, bound)
+        .chunks(2)
+        .map(|chunks| {
+            let low_chunk = chunks[0]?;
+            let hi_chunk = chunks[1]?;
+            Some(Amount::from(
+                CHUNK_SIZE_ENC_TRANS.chunks_to_u64([low_chunk, hi_chunk].iter().copied()),
+            ))
+        })
+        .collect()
+}
+
 impl<C: Curve> EncryptedAmount<C> {
     /// Join chunks of an encrypted amount into a single ciphertext.
     /// The resulting ciphertext will in general not be easily decryptable.
@@ -310,6 +363,74 @@ mod tests {
         );
     }
 
+    // Test that decryption with the compact table is the inverse to encryption.
+    #[test]
+    fn test_encrypt_try_decrypt_compact() {
+        let mut csprng = thread_rng();
+        let context = GlobalContext::<G1>::generate(String::from("genesis_string"));
+
+        let sk = SecretKey::generate(context.elgamal_generator(), &mut csprng);
+        let pk = PublicKey::from(&sk);
+
+        let amount = Amount::from(csprng.gen::<u64>());
+
+        let (enc_amount, _) = encrypt_amount(&context, &pk, amount, &mut csprng);
+
+        let m = 1 << 16;
+        let table = CompactBabyStepGiantStep::new(context.encryption_in_exponent_generator(), m);
+
+        let decrypted = try_decrypt_amount(&table, &sk, &enc_amount);
+        assert_eq!(
+            Some(amount),
+            decrypted,
+            "Decrypted amount differs from the original."
+        );
+    }
+
+    // Test that batch decryption agrees with the amounts, and fails only for the
+    // amount whose chunks are too large.
+    #[test]
+    fn test_decrypt_amounts_batch() {
+        let mut csprng = thread_rng();
+        let context = GlobalContext::<G1>::generate(String::from("genesis_string"));
+
+        let sk = SecretKey::generate(context.elgamal_generator(), &mut csprng);
+        let pk = PublicKey::from(&sk);
+
+        let amounts = (0..5)
+            .map(|_| Amount::from(csprng.gen::<u64>()))
+            .collect::<Vec<_>>();
+        let mut encrypted_amounts = amounts
+            .iter()
+            .map(|&amount| encrypt_amount(&context, &pk, amount, &mut csprng).0)
+            .collect::<Vec<_>>();
+        // An amount that is too large to be decrypted, encrypted without chunks.
+        let too_large = 1u64 << u8::from(CHUNK_SIZE_ENC_TRANS);
+        let h = context.encryption_in_exponent_generator();
+        let cipher = pk.encrypt_exponent_given_generator(
+            &Value::new(G1::scalar_from_u64(too_large)),
+            h,
+            &mut csprng,
+        );
+        encrypted_amounts.push(EncryptedAmount {
+            encryptions: [cipher, cipher],
+        });
+
+        let m = 1 << 16;
+        let table = CompactBabyStepGiantStep::new(h, m);
+
+        let decrypted = decrypt_amounts_batch(&table, &sk, &encrypted_amounts);
+        let expected = amounts
+            .iter()
+            .map(|&amount| Some(amount))
+            .chain(std::iter::once(None))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decrypted, expected,
+            "Decrypted amounts differ from the original."
+        );
+    }
+
     #[test]
     fn test_scale() {
         let mut csprng = thread_rng();
@@ -203,22 +203,15 @@ pub fn gen_enc_trans<C: Curve, R: Rng>(
     let s_prime = u64::from(s) - u64::from(a);
     let s_prime_chunks = CHUNK_SIZE.u64_to_chunks(s_prime);
     let a_chunks = CHUNK_SIZE.u64_to_chunks(u64::from(a));
+    // The encryptions use the precomputed tables of the context.
     let A_enc_randomness = a_chunks
         .iter()
-        .map(|&x| {
-            pk_receiver.encrypt_exponent_rand_given_generator(
-                &Value::<C>::from(x),
-                generator,
-                csprng,
-            )
-        })
+        .map(|&x| context.encrypt_exponent_rand(pk_receiver, &Value::<C>::from(x), csprng))
         .collect::<Vec<_>>();
     let (A, A_rand): (Vec<_>, Vec<_>) = A_enc_randomness.iter().cloned().unzip();
     let S_prime_enc_randomness = s_prime_chunks
         .iter()
-        .map(|&x| {
-            pk_sender.encrypt_exponent_rand_given_generator(&Value::<C>::from(x), generator, csprng)
-        })
+        .map(|&x| context.encrypt_exponent_rand(pk_sender, &Value::<C>::from(x), csprng))
         .collect::<Vec<_>>();
     let (S_prime, S_prime_rand): (Vec<_>, Vec<_>) = S_prime_enc_randomness.iter().cloned().unzip();
 
@@ -382,10 +375,13 @@ pub fn gen_sec_to_pub_trans<C: Curve, R: Rng>(
     let s_prime_chunks = CHUNK_SIZE.u64_to_chunks(s_prime);
     let S_prime_enc_randomness = s_prime_chunks
         .iter()
-        .map(|&x| pk.encrypt_exponent_rand_given_generator(&Value::<C>::from(x), generator, csprng))
+        .map(|&x| context.encrypt_exponent_rand(pk, &Value::<C>::from(x), csprng))
         .collect::<Vec<_>>();
     let A_dummy_encryption = {
-        let ha = generator.mul_by_scalar(&C::scalar_from_u64(u64::from(a)));
+        let ha = context
+            .on_chain_commitment_key_table()
+            .h()
+            .mul_by_scalar(&C::scalar_from_u64(u64::from(a)));
         Cipher(C::zero_point(), ha)
     };
     let A = [A_dummy_encryption];
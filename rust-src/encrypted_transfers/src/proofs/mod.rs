@@ -1,9 +1,8 @@
 //! Construction and verification of proofs for encrypted transfer transactions.
 
-// the following two modules are only there for reference if we ever need them,
-// they are not used.
+// the following module is only there for reference if we ever need it,
+// it is not used.
 mod dlogaggequal;
-mod dlogeq;
 mod enc_trans;
 mod generate_proofs;
 
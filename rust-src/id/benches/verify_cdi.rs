@@ -157,6 +157,7 @@ fn bench_parts(c: &mut Criterion) {
             tree
         },
         _phantom: Default::default(),
+        statements: Vec::new(),
     };
 
     let mut keys = BTreeMap::new();
@@ -183,8 +184,10 @@ fn bench_parts(c: &mut Criterion) {
         &id_use_data,
         0,
         policy.clone(),
+        &[],
         &acc_data,
         &Left(EXPIRY),
+        None,
     )
     .expect("Should generate the credential successfully.");
 
@@ -222,7 +225,18 @@ fn bench_parts(c: &mut Criterion) {
     let bench_create_credential =
         move |b: &mut Bencher, x: &(_, _, _, _, Policy<ArCurve, AttributeKind>, _)| {
             b.iter(|| {
-                create_credential(x.0, x.1, x.2, x.3, x.4.clone(), x.5, &Left(EXPIRY)).unwrap()
+                create_credential(
+                    x.0,
+                    x.1,
+                    x.2,
+                    x.3,
+                    x.4.clone(),
+                    &[],
+                    x.5,
+                    &Left(EXPIRY),
+                    None,
+                )
+                .unwrap()
             })
         };
     c.bench_with_input(
@@ -239,6 +253,23 @@ fn bench_parts(c: &mut Criterion) {
         &(&global_context, &ip_info, &ars_infos, &cdi),
         bench_verify_cdi,
     );
+    let bench_verify_cdi_batch = move |b: &mut Bencher, x: &(_, _, _, _)| {
+        let mut csprng = thread_rng();
+        let new_account = Left(EXPIRY);
+        let instances = (0..10)
+            .map(|_| CdiInstance {
+                ip_info:         x.1,
+                cdi:             x.3,
+                new_or_existing: &new_account,
+            })
+            .collect::<Vec<_>>();
+        b.iter(|| verify_cdi_batch(&mut csprng, x.0, x.2, &instances).unwrap())
+    };
+    c.bench_with_input(
+        BenchmarkId::new("Verify CDI batch", "10"),
+        &(&global_context, &ip_info, &ars_infos, &cdi),
+        bench_verify_cdi_batch,
+    );
     let share_vec = vec![decrypted_share_ar2, decrypted_share_ar4];
     let bench_reveal_id_cred_pub = move |b: &mut Bencher| b.iter(|| reveal_id_cred_pub(&share_vec));
     let bench_verify_ip = move |b: &mut Bencher| {
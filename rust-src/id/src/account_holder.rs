@@ -1,6 +1,8 @@
 //! Functionality needed by the account holder, either when interacting with the
 //! identity provider, or when interacting with the chain.
 use crate::{
+    id_prover,
+    revocation::{self, CredentialNonRevocation, SignedStatusList},
     secret_sharing::*,
     sigma_protocols::{
         com_enc_eq, com_eq, com_eq_different_groups, com_eq_sig, com_mult, common::*, dlog,
@@ -19,7 +21,7 @@ use dodis_yampolskiy_prf as prf;
 use elgamal::{multicombine, Cipher};
 use ff::Field;
 use pedersen_scheme::{
-    Commitment, CommitmentKey as PedersenKey, Randomness as PedersenRandomness, Value,
+    Commitment, CommitmentKey as PedersenKey, Committer, Randomness as PedersenRandomness, Value,
 };
 use rand::*;
 use random_oracle::RandomOracle;
@@ -36,7 +38,7 @@ pub fn build_pub_info_for_ip<C: Curve>(
     prf_key: &prf::SecretKey<C>,
     initial_account: &impl PublicInitialAccountData,
 ) -> Option<PublicInformationForIp<C>> {
-    let id_cred_pub = gc.on_chain_commitment_key.g.mul_by_scalar(id_cred_sec);
+    let id_cred_pub = id_cred_sec.mul_point(&gc.on_chain_commitment_key.g);
 
     // From create_credential:
     // let id_cred_sec = &aci.cred_holder_info.id_cred.id_cred_sec;
@@ -325,6 +327,46 @@ pub fn generate_pio<P: Pairing, C: Curve<Scalar = P::ScalarField>>(
     ))
 }
 
+/// Generate a request to renew the identity object, i.e., to extend its
+/// validity until `valid_to`. The request proves knowledge of the IdCredSec
+/// and prf key the identity object was created with, so that only its holder
+/// can renew it. The renewed identity object has the same pre-identity
+/// object, so the signature retrieval randomness of the original object can be
+/// used with it, and its credentials can be revoked in the same way.
+pub fn generate_renewal_request<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+>(
+    global_context: &GlobalContext<C>,
+    ip_info: &IpInfo<P>,
+    id_object: &IdentityObject<P, C, AttributeType>,
+    aci: &AccCredentialInfo<C>,
+    valid_to: YearMonth,
+) -> Option<IdentityRenewalRequest<C>> {
+    let mut csprng = thread_rng();
+    let (mut transcript, prover) =
+        utils::renewal_statement(global_context, ip_info, id_object, valid_to);
+    let secret = (
+        dlog::DlogSecret {
+            secret: aci.cred_holder_info.id_cred.id_cred_sec.clone(),
+        },
+        dlog::DlogSecret {
+            secret: aci.prf_key.to_value(),
+        },
+    );
+    let proof = prove(&mut transcript, &prover, secret, &mut csprng)?;
+    Some(IdentityRenewalRequest {
+        id_cred_pub: prover.first.public,
+        valid_to,
+        proof: RenewalProof {
+            challenge:           proof.challenge,
+            id_cred_sec_witness: proof.witness.w1,
+            prf_key_witness:     proof.witness.w2,
+        },
+    })
+}
+
 /// Convenient data structure to collect data related to a single AR
 pub struct SingleArData<'a, C: Curve> {
     ar: &'a ArInfo<C>,
@@ -344,11 +386,11 @@ type SharingData<'a, C> = (
 );
 
 /// A function to compute sharing data for a single value.
-pub fn compute_sharing_data<'a, C: Curve>(
+pub fn compute_sharing_data<'a, C: Curve, K: Committer<C>>(
     shared_scalar: &Value<C>,                           // Value to be shared.
     ar_parameters: &'a BTreeMap<ArIdentity, ArInfo<C>>, // Chosen anonimity revokers.
     threshold: Threshold,                               // Anonymity revocation threshold.
-    commitment_key: &PedersenKey<C>,                    // commitment key
+    commitment_key: &K,                                 // commitment key, or its tables
 ) -> SharingData<'a, C> {
     let n = ar_parameters.len() as u32;
     let mut csprng = thread_rng();
@@ -357,7 +399,8 @@ pub fn compute_sharing_data<'a, C: Curve>(
     // We evaluate the polynomial at ar_identities.
     let share_points = ar_parameters.keys().copied();
     // share the scalar on ar_identity points.
-    let sharing_data = share::<C, _, _, _>(&shared_scalar, share_points, threshold, &mut csprng);
+    let sharing_data =
+        share::<C, _, _, _>(shared_scalar.as_ref(), share_points, threshold, &mut csprng);
     // commitments to the sharing coefficients
     let mut cmm_sharing_coefficients: Vec<Commitment<C>> = Vec::with_capacity(threshold.into());
     // first coefficient is the shared scalar
@@ -442,7 +485,8 @@ pub fn compute_sharing_data_prf<'a, C: Curve>(
     // We evaluate the polynomial at ar_identities.
     let share_points = ar_parameters.keys().copied();
     // share the scalar on ar_identity points.
-    let sharing_data = share::<C, _, _, _>(&shared_scalar, share_points, threshold, &mut csprng);
+    let sharing_data =
+        share::<C, _, _, _>(shared_scalar.as_ref(), share_points, threshold, &mut csprng);
     // commitments to the sharing coefficients
     let mut cmm_sharing_coefficients: Vec<Commitment<C>> = Vec::with_capacity(threshold.into());
     // first coefficient is the shared scalar
@@ -509,6 +553,12 @@ pub fn commitment_to_share_and_rand<C: Curve>(
 /// commitments later on. The information is meant to be valid in the context of
 /// a given identity provider, and global parameter.
 /// The 'cred_counter' is used to generate a new credential ID.
+/// The 'statements' are statements about attributes that are not revealed in
+/// the policy. A proof of each of them is produced and added to the policy.
+/// If a status list of the identity provider is given, a proof that the
+/// identity is not revoked is added to the credential, and credential creation
+/// fails if it is revoked.
+#[allow(clippy::too_many_arguments)]
 pub fn create_credential<
     'a,
     P: Pairing,
@@ -520,20 +570,24 @@ pub fn create_credential<
     id_object_use_data: &IdObjectUseData<P, C>,
     cred_counter: u8,
     policy: Policy<C, AttributeType>,
+    statements: &[AttributeStatement<AttributeType>],
     cred_data: &impl CredentialDataWithSigning,
     new_or_existing: &either::Either<TransactionTime, AccountAddress>,
+    status_list: Option<&SignedStatusList<C>>,
 ) -> anyhow::Result<(
     CredentialDeploymentInfo<P, C, AttributeType>,
     CommitmentsRandomness<C>,
 )>
 where
     AttributeType: Clone, {
+    let global_context = context.global_context;
     let (unsigned_credential_info, commitments_randomness) = create_unsigned_credential(
         context,
         id_object,
         id_object_use_data,
         cred_counter,
         policy,
+        statements,
         cred_data.get_cred_key_info(),
         new_or_existing.as_ref().right(),
     )?;
@@ -542,9 +596,35 @@ where
         sigs: cred_data.sign(&new_or_existing, &unsigned_credential_info),
     };
 
+    let proof_not_revoked = match status_list {
+        Some(status_list) => {
+            let transcript = revocation::credential_transcript(
+                global_context,
+                &unsigned_credential_info.values.cred_id,
+            );
+            let proof = id_prover::prove_credential_not_revoked(
+                &transcript,
+                global_context,
+                &status_list.status_list,
+                &id_object_use_data.aci.cred_holder_info.id_cred.id_cred_sec,
+                &unsigned_credential_info.proofs.commitments,
+                &commitments_randomness,
+            );
+            match proof {
+                Some(proof) => Some(Box::new(CredentialNonRevocation {
+                    status_list: status_list.clone(),
+                    proof,
+                })),
+                None => bail!("The identity is revoked."),
+            }
+        }
+        None => None,
+    };
+
     let cdp = CredDeploymentProofs {
         id_proofs: unsigned_credential_info.proofs,
         proof_acc_sk,
+        proof_not_revoked,
     };
 
     let info = CredentialDeploymentInfo {
@@ -561,6 +641,9 @@ where
 /// The 'cred_counter' is used to generate a new credential ID.
 /// It should be the case that using the output, one can construct an actual
 /// credential deployment info, by signing the unsigned challenge.
+/// The 'statements' are statements about attributes that are not revealed in
+/// the policy. A proof of each of them is produced and added to the policy.
+#[allow(clippy::too_many_arguments)]
 pub fn create_unsigned_credential<
     'a,
     P: Pairing,
@@ -571,7 +654,8 @@ pub fn create_unsigned_credential<
     id_object: &IdentityObject<P, C, AttributeType>,
     id_object_use_data: &IdObjectUseData<P, C>,
     cred_counter: u8,
-    policy: Policy<C, AttributeType>,
+    mut policy: Policy<C, AttributeType>,
+    statements: &[AttributeStatement<AttributeType>],
     cred_key_info: CredentialPublicKeys,
     addr: Option<&AccountAddress>,
 ) -> anyhow::Result<(
@@ -598,11 +682,12 @@ where
         ),
     };
 
+    // Commitments with the on-chain commitment key use its precomputed tables.
+    let cmm_key_table = &*context.global_context.on_chain_commitment_key_table();
+
     // RegId as well as Prf key commitments must be computed
     // with the same generators as in the commitment key.
-    let cred_id = context
-        .global_context
-        .on_chain_commitment_key
+    let cred_id = cmm_key_table
         .hide(
             &Value::<C>::new(cred_id_exponent),
             &PedersenRandomness::zero(),
@@ -638,7 +723,7 @@ where
         id_cred_sec,
         &chosen_ars,
         prio.choice_ar_parameters.threshold,
-        &context.global_context.on_chain_commitment_key,
+        cmm_key_table,
     );
 
     let number_of_ars = prio.choice_ar_parameters.ar_identities.len();
@@ -663,7 +748,7 @@ where
     // We now compute commitments to all the items in the attribute list.
     // We use the on-chain pedersen commitment key.
     let (commitments, commitment_rands) = compute_commitments(
-        &context.global_context.on_chain_commitment_key,
+        cmm_key_table,
         &alist,
         prf_key,
         cred_counter,
@@ -673,6 +758,41 @@ where
         &mut csprng,
     )?;
 
+    // Prove the statements about the committed attributes, and add them to the
+    // policy so that they become part of the credential.
+    let mut statements_transcript = utils::attribute_statements_transcript(
+        &cred_id,
+        &commitments,
+        addr,
+        &context.global_context,
+    );
+    for statement in statements {
+        let tag = statement.attribute_tag();
+        ensure!(
+            !policy.policy_vec.contains_key(&tag),
+            "Cannot prove a statement about revealed attribute {}.",
+            tag
+        );
+        let (attribute, rand) = match (
+            alist.alist.get(&tag),
+            commitment_rands.attributes_rand.get(&tag),
+        ) {
+            (Some(attribute), Some(rand)) => (attribute, rand),
+            _ => bail!("Cannot prove a statement about missing attribute {}.", tag),
+        };
+        match id_prover::prove_attribute_statement(
+            &mut statements_transcript,
+            context.global_context.bulletproof_generators(),
+            &context.global_context.on_chain_commitment_key,
+            statement,
+            attribute,
+            rand,
+        ) {
+            Some(proof) => policy.statements.push(proof),
+            None => bail!("Attribute {} does not satisfy the statement.", tag),
+        }
+    }
+
     // We have all the values now.
     let cred_values = CredentialDeploymentValues {
         cred_id,
@@ -956,8 +1076,13 @@ fn compute_pok_sig<
 /// For the other values the verifier (the chain) will compute commitments with
 /// randomness 0 in order to verify knowledge of the signature.
 #[allow(clippy::too_many_arguments)]
-pub fn compute_commitments<C: Curve, AttributeType: Attribute<C::Scalar>, R: Rng>(
-    commitment_key: &PedersenKey<C>,
+pub fn compute_commitments<
+    C: Curve,
+    K: Committer<C>,
+    AttributeType: Attribute<C::Scalar>,
+    R: Rng,
+>(
+    commitment_key: &K,
     alist: &AttributeList<C::Scalar, AttributeType>,
     prf_key: &prf::SecretKey<C>,
     cred_counter: u8,
@@ -1259,6 +1384,7 @@ mod tests {
                 tree
             },
             _phantom: Default::default(),
+            statements: Vec::new(),
         };
         let mut keys = BTreeMap::new();
         keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
@@ -1277,8 +1403,10 @@ mod tests {
             &id_use_data,
             cred_ctr,
             policy.clone(),
+            &[],
             &acc_data,
             &Left(EXPIRY),
+            None,
         )
         .expect("Could not generate CDI");
 
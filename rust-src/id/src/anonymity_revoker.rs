@@ -1,7 +1,15 @@
 //! Functionality needed by the anonymity revoker.
-use crate::{secret_sharing::*, types::*};
+use crate::{
+    ar_committee::{combine_partial_decryptions, verify_partial_decryption, CommitteeKey},
+    secret_sharing::*,
+    sigma_protocols::{common::*, dlog, dlogeq},
+    types::*,
+};
 use curve_arithmetic::*;
-use elgamal::Message;
+use elgamal::{chunks_to_value, value_to_chunks, BabyStepGiantStep, Cipher, Message, SecretKey};
+use rand::*;
+use random_oracle::RandomOracle;
+use std::collections::BTreeSet;
 
 /// Reveal the `idCredPub` based on the given shares.
 /// It is important to remember that this always succeeds in computing
@@ -23,3 +31,336 @@ pub fn reveal_id_cred_pub<C: Curve>(shares: &[(ArIdentity, Message<C>)]) -> C {
 /// value is meaningful must be ensured by the caller, e.g., by making sure that
 /// the threshold is compatible with the number of shares.
 pub fn reveal_prf_key<C: Curve>(shares: &[(ArIdentity, Value<C>)]) -> C::Scalar { reveal(&shares) }
+
+fn decryption_transcript<C: Curve>(
+    public_key: &ArPublicKey<C>,
+    cipher: &Cipher<C>,
+) -> RandomOracle {
+    let mut transcript = RandomOracle::domain("ArDecryptionProof");
+    transcript.append_message(b"public_key", public_key);
+    transcript.append_message(b"cipher", cipher);
+    transcript
+}
+
+/// The decryption is correct if the public key and `c_2 - m` have the same
+/// discrete logarithm, with respect to the generator and `c_1` respectively.
+fn decryption_statement<C: Curve>(
+    public_key: &ArPublicKey<C>,
+    cipher: &Cipher<C>,
+    message: &Message<C>,
+) -> dlogeq::DlogEqual<C> {
+    dlogeq::DlogEqual {
+        dlog1: dlog::Dlog {
+            public: public_key.key,
+            coeff:  public_key.generator,
+        },
+        dlog2: dlog::Dlog {
+            public: cipher.1.minus_point(&message.value),
+            coeff:  cipher.0,
+        },
+    }
+}
+
+/// Decrypt the ciphertext, and prove that the decryption is correct.
+pub fn decrypt_with_proof<C: Curve, R: Rng>(
+    secret_key: &SecretKey<C>,
+    cipher: &Cipher<C>,
+    csprng: &mut R,
+) -> (Message<C>, DecryptionProof<C>) {
+    let message = secret_key.decrypt(cipher);
+    let public_key = ArPublicKey::from(secret_key);
+    let prover = decryption_statement(&public_key, cipher, &message);
+    let secret = dlog::DlogSecret {
+        secret: Value::new(secret_key.scalar),
+    };
+    let proof = prove(
+        &mut decryption_transcript(&public_key, cipher),
+        &prover,
+        secret,
+        csprng,
+    )
+    .expect("Proving equality of discrete logarithms does not fail.");
+    (message, DecryptionProof::Single { proof })
+}
+
+/// Check that the message is the decryption of the ciphertext under the
+/// given public key of an anonymity revoker.
+///
+/// For an anonymity revoker that is a committee, the key of the committee must
+/// be given. The partial decryptions must be correct, and the verification
+/// keys of the members that made them must interpolate to the public key. The
+/// latter ensures that the combination of the partial decryptions is the
+/// decryption with the secret key of the committee, even if the rest of the
+/// committee key is not genuine.
+pub fn verify_decryption<C: Curve>(
+    public_key: &ArPublicKey<C>,
+    committee_key: Option<&CommitteeKey<C>>,
+    cipher: &Cipher<C>,
+    message: &Message<C>,
+    proof: &DecryptionProof<C>,
+) -> bool {
+    match proof {
+        DecryptionProof::Single { proof } => {
+            let verifier = decryption_statement(public_key, cipher, message);
+            verify(
+                &mut decryption_transcript(public_key, cipher),
+                &verifier,
+                proof,
+            )
+        }
+        DecryptionProof::Committee {
+            partial_decryptions,
+        } => {
+            let committee_key = match committee_key {
+                Some(committee_key) => committee_key,
+                None => return false,
+            };
+            if committee_key.public_key != *public_key || partial_decryptions.is_empty() {
+                return false;
+            }
+            let members = partial_decryptions
+                .iter()
+                .map(|pd| pd.member)
+                .collect::<BTreeSet<_>>();
+            if members.len() != partial_decryptions.len()
+                || !partial_decryptions
+                    .iter()
+                    .all(|pd| verify_partial_decryption(committee_key, cipher, pd))
+            {
+                return false;
+            }
+            let verification_keys = partial_decryptions
+                .iter()
+                .map(|pd| (pd.member, committee_key.verification_keys[&pd.member]))
+                .collect::<Vec<_>>();
+            reveal_in_group(&verification_keys) == public_key.key
+                && combine_partial_decryptions(committee_key.threshold, cipher, partial_decryptions)
+                    .map_or(false, |decrypted| decrypted == *message)
+        }
+    }
+}
+
+/// Decrypt the encrypted share of the PRF key, given as encryptions in the
+/// exponent of its chunks, and prove that the decryption of each chunk is
+/// correct. The table must be for the generator of the encryption in the
+/// exponent.
+pub fn decrypt_prf_key_share_with_proofs<C: Curve, R: Rng>(
+    secret_key: &SecretKey<C>,
+    ciphers: &[Cipher<C>],
+    table: &BabyStepGiantStep<C>,
+    csprng: &mut R,
+) -> (Value<C>, Vec<DecryptionProof<C>>) {
+    let mut chunks = Vec::with_capacity(ciphers.len());
+    let mut proofs = Vec::with_capacity(ciphers.len());
+    for cipher in ciphers {
+        let (message, proof) = decrypt_with_proof(secret_key, cipher, csprng);
+        chunks.push(Value::from(table.discrete_log(&message.value)));
+        proofs.push(proof);
+    }
+    (chunks_to_value(&chunks, CHUNK_SIZE), proofs)
+}
+
+/// Check that the share of the PRF key is the decryption of the encrypted
+/// share, given as encryptions in the exponent of its chunks with respect to
+/// the given generator. The key of the committee is needed as in
+/// [verify_decryption].
+pub fn verify_prf_key_share_decryption<C: Curve>(
+    public_key: &ArPublicKey<C>,
+    committee_key: Option<&CommitteeKey<C>>,
+    ciphers: &[Cipher<C>],
+    generator: &C,
+    prf_key_share: &Value<C>,
+    proofs: &[DecryptionProof<C>],
+) -> bool {
+    let chunks = value_to_chunks::<C>(prf_key_share.as_ref(), CHUNK_SIZE);
+    chunks.len() == ciphers.len()
+        && proofs.len() == ciphers.len()
+        && izip!(chunks.iter(), ciphers.iter(), proofs.iter()).all(|(chunk, cipher, proof)| {
+            let message = Message {
+                value: chunk.mul_point(generator),
+            };
+            verify_decryption(public_key, committee_key, cipher, &message, proof)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ar_committee::*;
+    use elgamal::{encrypt_in_chunks_given_generator, PublicKey};
+    use pairing::bls12_381::G1;
+
+    #[test]
+    fn test_decryption_proofs() {
+        let mut csprng = thread_rng();
+        let generator = G1::generate(&mut csprng);
+        let secret_key = SecretKey::generate(&generator, &mut csprng);
+        let public_key = PublicKey::from(&secret_key);
+        let message = Message::generate(&mut csprng);
+        let cipher = public_key.encrypt(&mut csprng, &message);
+
+        let (decrypted, proof) = decrypt_with_proof(&secret_key, &cipher, &mut csprng);
+        assert_eq!(decrypted, message);
+        assert!(verify_decryption(
+            &public_key,
+            None,
+            &cipher,
+            &message,
+            &proof
+        ));
+        let other = Message::generate(&mut csprng);
+        assert!(!verify_decryption(
+            &public_key,
+            None,
+            &cipher,
+            &other,
+            &proof
+        ));
+        let other_key = PublicKey::from(&SecretKey::generate(&generator, &mut csprng));
+        assert!(!verify_decryption(
+            &other_key, None, &cipher, &message, &proof
+        ));
+
+        let h = G1::generate(&mut csprng);
+        let share = Value::<G1>::generate(&mut csprng);
+        let ciphers =
+            encrypt_in_chunks_given_generator(&public_key, &share, CHUNK_SIZE, &h, &mut csprng)
+                .into_iter()
+                .map(|(c, _)| c)
+                .collect::<Vec<_>>();
+        let table = BabyStepGiantStep::new(&h, 1 << 16);
+        let (decrypted, proofs) =
+            decrypt_prf_key_share_with_proofs(&secret_key, &ciphers, &table, &mut csprng);
+        assert_eq!(decrypted, share);
+        assert!(verify_prf_key_share_decryption(
+            &public_key,
+            None,
+            &ciphers,
+            &h,
+            &share,
+            &proofs
+        ));
+        let other = Value::<G1>::generate(&mut csprng);
+        assert!(!verify_prf_key_share_decryption(
+            &public_key,
+            None,
+            &ciphers,
+            &h,
+            &other,
+            &proofs
+        ));
+    }
+
+    #[test]
+    fn test_decrypted_data_without_proofs() {
+        let mut csprng = thread_rng();
+        let share = ChainArDecryptedData::<G1> {
+            ar_identity:       ArIdentity::new(1),
+            id_cred_pub_share: Message::generate(&mut csprng),
+            proof:             None,
+            committee_key:     None,
+        };
+        let json = serde_json::to_value(&share).unwrap();
+        assert_eq!(
+            json.as_object().map(|o| o.len()),
+            Some(2),
+            "Absent proofs should be omitted."
+        );
+        let parsed: ChainArDecryptedData<G1> = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, share);
+
+        let json = serde_json::json!({
+            "arIdentity": 1,
+            "prfKeyShare": crypto_common::base16_encode_string(&Value::<G1>::generate(&mut csprng)),
+        });
+        let parsed: IpArDecryptedData<G1> = serde_json::from_value(json).unwrap();
+        assert!(parsed.proofs.is_empty() && parsed.committee_key.is_none());
+    }
+
+    #[test]
+    fn test_committee_decryption_proofs() {
+        let mut csprng = thread_rng();
+        let generator = G1::generate(&mut csprng);
+        let members = (1..=3u32).map(ArIdentity::new).collect::<BTreeSet<_>>();
+        let threshold = Threshold(2);
+        let dealings_and_shares = members
+            .iter()
+            .map(|&dealer| deal(&generator, dealer, &members, threshold, &mut csprng))
+            .collect::<Vec<_>>();
+        let dealings = dealings_and_shares
+            .iter()
+            .map(|(d, _)| d.clone())
+            .collect::<Vec<_>>();
+        let committee_key = committee_key(&generator, threshold, &members, &dealings)
+            .expect("The dealings are valid.");
+        let message = Message::generate(&mut csprng);
+        let cipher = committee_key.public_key.encrypt(&mut csprng, &message);
+        let partial_decryptions = members
+            .iter()
+            .take(2)
+            .map(|member| {
+                let secret_key = member_secret_key(
+                    &generator,
+                    dealings_and_shares
+                        .iter()
+                        .map(|(_, shares)| &shares[member]),
+                );
+                partial_decrypt(*member, &secret_key, &cipher, &mut csprng)
+            })
+            .collect::<Vec<_>>();
+        let proof = DecryptionProof::Committee {
+            partial_decryptions,
+        };
+        let public_key = committee_key.public_key;
+        assert!(verify_decryption(
+            &public_key,
+            Some(&committee_key),
+            &cipher,
+            &message,
+            &proof
+        ));
+        let other = Message::generate(&mut csprng);
+        assert!(!verify_decryption(
+            &public_key,
+            Some(&committee_key),
+            &cipher,
+            &other,
+            &proof
+        ));
+        // The partial decryptions cannot be checked without the committee key.
+        assert!(!verify_decryption(
+            &public_key,
+            None,
+            &cipher,
+            &message,
+            &proof
+        ));
+
+        // Verification keys that do not interpolate to the public key are
+        // rejected, even if a threshold of partial decryptions match them.
+        let mut fake_key = committee_key;
+        let partial_decryptions = members
+            .iter()
+            .take(2)
+            .map(|&fake_member| {
+                let fake_secret = SecretKey::generate(&generator, &mut csprng);
+                fake_key
+                    .verification_keys
+                    .insert(fake_member, generator.mul_by_scalar(&fake_secret.scalar));
+                partial_decrypt(fake_member, &fake_secret, &cipher, &mut csprng)
+            })
+            .collect::<Vec<_>>();
+        let claimed = combine_partial_decryptions(threshold, &cipher, &partial_decryptions)
+            .expect("There are enough partial decryptions.");
+        let proof = DecryptionProof::Committee {
+            partial_decryptions,
+        };
+        assert!(!verify_decryption(
+            &public_key,
+            Some(&fake_key),
+            &cipher,
+            &claimed,
+            &proof
+        ));
+    }
+}
@@ -0,0 +1,495 @@
+//! Anonymity revokers that are committees. The ElGamal key of such an
+//! anonymity revoker is generated by its members in a distributed key
+//! generation based on Feldman's verifiable secret sharing, so that no single
+//! member knows the secret key, and ciphertexts can only be decrypted by a
+//! threshold of members together.
+//!
+//! The key generation proceeds as follows.
+//! 1. Each member makes a [Dealing] of a random secret with [deal]. The dealing
+//!    is published to all members, and the share of each member is sent to that
+//!    member over a confidential channel.
+//! 2. Each member checks all dealings with [verify_dealing], and the shares it
+//!    received with [verify_share]. Dealers whose dealing or shares are invalid
+//!    are excluded. All members must agree on the dealings that are used.
+//! 3. The public [CommitteeKey] is computed from the dealings with
+//!    [committee_key], and each member computes its share of the secret key
+//!    from the shares it received with [member_secret_key].
+//!
+//! This is the Joint-Feldman protocol, which is known not to generate the key
+//! uniformly at random (Gennaro, Jarecki, Krawczyk and Rabin, "Secure
+//! Distributed Key Generation for Discrete-Log Based Cryptosystems"). A dealer
+//! that waits for the dealings of the others before publishing its own, and
+//! then causes complaints against some dealers so that they are excluded, can
+//! choose between several public keys and so bias the key. It cannot learn
+//! the secret key, though, so the key is still secure for ElGamal encryption
+//! and threshold decryption, which is how it is used here, but it must not be
+//! relied on to be uniform.
+//!
+//! Members decrypt a ciphertext with [partial_decrypt], which produces a
+//! [PartialDecryption] with a proof that it is correct. Anybody can check it
+//! with [verify_partial_decryption], and a threshold of correct partial
+//! decryptions is combined with [combine_partial_decryptions].
+//!
+//! Members are identified by an [ArIdentity], which defines their evaluation
+//! point in the secret sharing, as for anonymity revokers.
+use crate::{
+    secret_sharing::{reveal_in_group, share, Threshold},
+    sigma_protocols::{common::*, dlog, dlogeq},
+    types::ArIdentity,
+};
+use anyhow::ensure;
+use crypto_common::*;
+use crypto_common_derive::*;
+use curve_arithmetic::{Curve, Value};
+use elgamal::{Cipher, Message, PublicKey, SecretKey};
+use ff::Field;
+use rand::*;
+use random_oracle::RandomOracle;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The public part of the contribution of a member to the key generation.
+#[derive(Debug, Clone, Serialize, SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+pub struct Dealing<C: Curve> {
+    /// The member that made the dealing.
+    #[serde(rename = "dealer")]
+    pub dealer:      ArIdentity,
+    /// Commitments `g^a_k` to the coefficients `a_k` of the sharing
+    /// polynomial, starting with the constant coefficient, which is the secret
+    /// of the dealer.
+    #[serde(
+        rename = "commitments",
+        serialize_with = "base16_encode",
+        deserialize_with = "base16_decode"
+    )]
+    pub commitments: Vec<C>,
+    /// Proof of knowledge of the secret of the dealer.
+    #[serde(rename = "proof")]
+    pub proof:       dlog::Proof<C>,
+}
+
+/// The public key of a committee, together with the keys with which the
+/// partial decryptions of the members are verified.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, SerdeBase16Serialize)]
+pub struct CommitteeKey<C: Curve> {
+    /// The number of members needed to decrypt.
+    pub threshold:         Threshold,
+    /// The ElGamal public key of the committee, which is used as the public
+    /// key of the anonymity revoker.
+    pub public_key:        PublicKey<C>,
+    /// The keys `g^x_i` of the shares `x_i` of the secret key of the members.
+    #[map_size_length = 4]
+    pub verification_keys: BTreeMap<ArIdentity, C>,
+}
+
+/// The decryption of a ciphertext by one member of the committee.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+pub struct PartialDecryption<C: Curve> {
+    /// The member that decrypted.
+    #[serde(rename = "member")]
+    pub member: ArIdentity,
+    /// The value `c_1^x_i` where `c_1` is the first component of the
+    /// ciphertext, and `x_i` is the share of the secret key of the member.
+    #[serde(
+        rename = "value",
+        serialize_with = "base16_encode",
+        deserialize_with = "base16_decode"
+    )]
+    pub value:  C,
+    /// Proof that the value has the same discrete logarithm as the
+    /// verification key of the member.
+    #[serde(rename = "proof")]
+    pub proof:  dlogeq::Proof<C>,
+}
+
+fn dealing_transcript<C: Curve>(dealer: ArIdentity, commitments: &[C]) -> RandomOracle {
+    let mut transcript = RandomOracle::domain("ArCommitteeDealing");
+    transcript.append_message(b"dealer", &dealer);
+    transcript.append_message(b"commitments", &commitments.to_vec());
+    transcript
+}
+
+/// Evaluate the polynomial in the exponent given by the commitments to its
+/// coefficients at the evaluation point of the member.
+fn evaluate_commitments<C: Curve>(commitments: &[C], member: ArIdentity) -> C {
+    let x = member.to_scalar::<C>();
+    commitments.iter().rev().fold(C::zero_point(), |acc, c| {
+        acc.mul_by_scalar(&x).plus_point(c)
+    })
+}
+
+/// Make a dealing of a fresh random secret of the dealer for the given
+/// members, with the given threshold. This returns the dealing together with
+/// the shares of the members, which must only be sent to the respective
+/// members.
+pub fn deal<C: Curve, R: Rng>(
+    generator: &C,
+    dealer: ArIdentity,
+    members: &BTreeSet<ArIdentity>,
+    threshold: Threshold,
+    csprng: &mut R,
+) -> (Dealing<C>, BTreeMap<ArIdentity, Value<C>>) {
+    let secret = C::generate_scalar(csprng);
+    let sharing = share::<C, _, _, _>(&secret, members.iter().copied(), threshold, csprng);
+    let commitments = std::iter::once(&secret)
+        .chain(sharing.coefficients.iter().map(|c| c.as_ref()))
+        .map(|a| generator.mul_by_scalar(a))
+        .collect::<Vec<_>>();
+    let prover = dlog::Dlog {
+        public: commitments[0],
+        coeff:  *generator,
+    };
+    let secret = dlog::DlogSecret {
+        secret: Value::new(secret),
+    };
+    let proof = prove(
+        &mut dealing_transcript(dealer, &commitments),
+        &prover,
+        secret,
+        csprng,
+    )
+    .expect("Proving knowledge of a discrete logarithm does not fail.");
+    let shares = members.iter().copied().zip(sharing.shares).collect();
+    (
+        Dealing {
+            dealer,
+            commitments,
+            proof,
+        },
+        shares,
+    )
+}
+
+/// Check that the dealing is for the given threshold, and that the dealer
+/// knows the secret it dealt.
+pub fn verify_dealing<C: Curve>(generator: &C, dealing: &Dealing<C>, threshold: Threshold) -> bool {
+    if dealing.commitments.len() != usize::from(threshold) {
+        return false;
+    }
+    let verifier = dlog::Dlog {
+        public: dealing.commitments[0],
+        coeff:  *generator,
+    };
+    verify(
+        &mut dealing_transcript(dealing.dealer, &dealing.commitments),
+        &verifier,
+        &dealing.proof,
+    )
+}
+
+/// Check that the share the member received is consistent with the dealing.
+pub fn verify_share<C: Curve>(
+    generator: &C,
+    dealing: &Dealing<C>,
+    member: ArIdentity,
+    share: &Value<C>,
+) -> bool {
+    share.mul_point(generator) == evaluate_commitments(&dealing.commitments, member)
+}
+
+/// Compute the key of the committee from the dealings that were agreed on.
+/// The dealings must have been checked with [verify_dealing].
+pub fn committee_key<C: Curve>(
+    generator: &C,
+    threshold: Threshold,
+    members: &BTreeSet<ArIdentity>,
+    dealings: &[Dealing<C>],
+) -> anyhow::Result<CommitteeKey<C>> {
+    ensure!(!dealings.is_empty(), "At least one dealing is needed.");
+    ensure!(
+        usize::from(threshold) <= members.len(),
+        "The threshold must not exceed the number of members."
+    );
+    let dealers = dealings.iter().map(|d| d.dealer).collect::<BTreeSet<_>>();
+    ensure!(
+        dealers.len() == dealings.len(),
+        "Each member can only make one dealing."
+    );
+    ensure!(
+        dealers.is_subset(members),
+        "Dealings can only be made by members."
+    );
+    ensure!(
+        dealings
+            .iter()
+            .all(|d| d.commitments.len() == usize::from(threshold)),
+        "The dealings must be for the given threshold."
+    );
+    let key = dealings
+        .iter()
+        .fold(C::zero_point(), |acc, d| acc.plus_point(&d.commitments[0]));
+    let verification_keys = members
+        .iter()
+        .map(|&member| {
+            let vk = dealings.iter().fold(C::zero_point(), |acc, d| {
+                acc.plus_point(&evaluate_commitments(&d.commitments, member))
+            });
+            (member, vk)
+        })
+        .collect();
+    Ok(CommitteeKey {
+        threshold,
+        public_key: PublicKey {
+            generator: *generator,
+            key,
+        },
+        verification_keys,
+    })
+}
+
+/// Compute the share of the secret key of a member from the shares it
+/// received in the dealings that were agreed on. The shares must have been
+/// checked with [verify_share].
+pub fn member_secret_key<'a, C: Curve>(
+    generator: &C,
+    shares: impl IntoIterator<Item = &'a Value<C>>,
+) -> SecretKey<C> {
+    let mut scalar = C::Scalar::zero();
+    for share in shares {
+        scalar.add_assign(share.as_ref());
+    }
+    SecretKey {
+        generator: *generator,
+        scalar,
+    }
+}
+
+fn partial_decryption_transcript<C: Curve>(member: ArIdentity, cipher: &Cipher<C>) -> RandomOracle {
+    let mut transcript = RandomOracle::domain("ArCommitteePartialDecryption");
+    transcript.append_message(b"member", &member);
+    transcript.append_message(b"cipher", cipher);
+    transcript
+}
+
+fn partial_decryption_statement<C: Curve>(
+    generator: &C,
+    verification_key: &C,
+    cipher: &Cipher<C>,
+    value: &C,
+) -> dlogeq::DlogEqual<C> {
+    dlogeq::DlogEqual {
+        dlog1: dlog::Dlog {
+            public: *verification_key,
+            coeff:  *generator,
+        },
+        dlog2: dlog::Dlog {
+            public: *value,
+            coeff:  cipher.0,
+        },
+    }
+}
+
+/// Decrypt the ciphertext with the share of the secret key of the member,
+/// proving that this was done correctly.
+pub fn partial_decrypt<C: Curve, R: Rng>(
+    member: ArIdentity,
+    secret_key: &SecretKey<C>,
+    cipher: &Cipher<C>,
+    csprng: &mut R,
+) -> PartialDecryption<C> {
+    let value = secret_key.mul_point(&cipher.0);
+    let verification_key = secret_key.mul_point(&secret_key.generator);
+    let prover =
+        partial_decryption_statement(&secret_key.generator, &verification_key, cipher, &value);
+    let secret = dlog::DlogSecret {
+        secret: Value::new(secret_key.scalar),
+    };
+    let proof = prove(
+        &mut partial_decryption_transcript(member, cipher),
+        &prover,
+        secret,
+        csprng,
+    )
+    .expect("Proving equality of discrete logarithms does not fail.");
+    PartialDecryption {
+        member,
+        value,
+        proof,
+    }
+}
+
+/// Check that the partial decryption of the ciphertext is correct, i.e., that
+/// it was made with the share of the secret key of a member of the committee.
+pub fn verify_partial_decryption<C: Curve>(
+    committee_key: &CommitteeKey<C>,
+    cipher: &Cipher<C>,
+    partial_decryption: &PartialDecryption<C>,
+) -> bool {
+    let verification_key = match committee_key
+        .verification_keys
+        .get(&partial_decryption.member)
+    {
+        Some(vk) => vk,
+        None => return false,
+    };
+    let verifier = partial_decryption_statement(
+        &committee_key.public_key.generator,
+        verification_key,
+        cipher,
+        &partial_decryption.value,
+    );
+    verify(
+        &mut partial_decryption_transcript(partial_decryption.member, cipher),
+        &verifier,
+        &partial_decryption.proof,
+    )
+}
+
+/// Decrypt the ciphertext from the partial decryptions of the members. This
+/// fails if the partial decryptions are not by distinct members, or if there
+/// are fewer than the threshold of the committee, since the result would then
+/// not be the decryption. The caller must make sure that the partial
+/// decryptions are verified with [verify_partial_decryption].
+pub fn combine_partial_decryptions<C: Curve>(
+    threshold: Threshold,
+    cipher: &Cipher<C>,
+    partial_decryptions: &[PartialDecryption<C>],
+) -> anyhow::Result<Message<C>> {
+    let members = partial_decryptions
+        .iter()
+        .map(|pd| pd.member)
+        .collect::<BTreeSet<_>>();
+    ensure!(
+        members.len() == partial_decryptions.len(),
+        "Each member can only make one partial decryption."
+    );
+    ensure!(
+        members.len() >= usize::from(threshold),
+        "At least {} partial decryptions are needed, but there are only {}.",
+        threshold,
+        members.len()
+    );
+    let shares = partial_decryptions
+        .iter()
+        .map(|pd| (pd.member, pd.value))
+        .collect::<Vec<_>>();
+    let mask = reveal_in_group(&shares);
+    Ok(Message {
+        value: cipher.1.minus_point(&mask),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pairing::bls12_381::G1;
+
+    /// Run the key generation with all members dealing.
+    fn run_dkg(
+        generator: &G1,
+        members: &BTreeSet<ArIdentity>,
+        threshold: Threshold,
+    ) -> (CommitteeKey<G1>, BTreeMap<ArIdentity, SecretKey<G1>>) {
+        let mut csprng = thread_rng();
+        let mut dealings = Vec::new();
+        let mut received = BTreeMap::<ArIdentity, Vec<Value<G1>>>::new();
+        for &dealer in members.iter() {
+            let (dealing, shares) = deal(generator, dealer, members, threshold, &mut csprng);
+            assert!(verify_dealing(generator, &dealing, threshold));
+            for (member, share) in shares {
+                assert!(verify_share(generator, &dealing, member, &share));
+                received.entry(member).or_default().push(share);
+            }
+            dealings.push(dealing);
+        }
+        let key = committee_key(generator, threshold, members, &dealings)
+            .expect("The dealings are valid.");
+        let secret_keys = received
+            .iter()
+            .map(|(&member, shares)| (member, member_secret_key(generator, shares)))
+            .collect();
+        (key, secret_keys)
+    }
+
+    #[test]
+    fn test_threshold_decryption() {
+        let mut csprng = thread_rng();
+        let generator = G1::generate(&mut csprng);
+        let members = (1..=5u32).map(ArIdentity::new).collect::<BTreeSet<_>>();
+        let threshold = Threshold(3);
+        let (key, secret_keys) = run_dkg(&generator, &members, threshold);
+        for (member, secret_key) in secret_keys.iter() {
+            assert_eq!(
+                &generator.mul_by_scalar(&secret_key.scalar),
+                &key.verification_keys[member],
+                "The verification key of {} does not match its share.",
+                member
+            );
+        }
+
+        let message = Message::<G1>::generate(&mut csprng);
+        let cipher = key.public_key.encrypt(&mut csprng, &message);
+        let partial_decryptions = secret_keys
+            .iter()
+            .skip(1)
+            .take(3)
+            .map(|(&member, secret_key)| partial_decrypt(member, secret_key, &cipher, &mut csprng))
+            .collect::<Vec<_>>();
+        for pd in partial_decryptions.iter() {
+            assert!(verify_partial_decryption(&key, &cipher, pd));
+        }
+        assert_eq!(
+            combine_partial_decryptions(threshold, &cipher, &partial_decryptions)
+                .expect("There are enough partial decryptions."),
+            message
+        );
+        // Fewer than the threshold, or duplicate members, do not decrypt.
+        assert!(
+            combine_partial_decryptions(threshold, &cipher, &partial_decryptions[..2]).is_err()
+        );
+        let duplicates = [
+            partial_decryptions[0].clone(),
+            partial_decryptions[1].clone(),
+            partial_decryptions[1].clone(),
+        ];
+        assert!(combine_partial_decryptions(threshold, &cipher, &duplicates).is_err());
+
+        // A partial decryption of another ciphertext, or by another member, is
+        // rejected.
+        let other_cipher = key.public_key.encrypt(&mut csprng, &message);
+        assert!(!verify_partial_decryption(
+            &key,
+            &other_cipher,
+            &partial_decryptions[0]
+        ));
+        let mut wrong_member = partial_decryptions[0].clone();
+        wrong_member.member = partial_decryptions[1].member;
+        assert!(!verify_partial_decryption(&key, &cipher, &wrong_member));
+
+        let key = serialize_deserialize(&key).expect("Key should deserialize.");
+        assert!(verify_partial_decryption(
+            &key,
+            &cipher,
+            &partial_decryptions[1]
+        ));
+    }
+
+    #[test]
+    fn test_invalid_dealings() {
+        let mut csprng = thread_rng();
+        let generator = G1::generate(&mut csprng);
+        let members = (1..=3u32).map(ArIdentity::new).collect::<BTreeSet<_>>();
+        let member = ArIdentity::new(2);
+        let (dealing, shares) = deal(&generator, member, &members, Threshold(2), &mut csprng);
+        assert!(!verify_dealing(&generator, &dealing, Threshold(3)));
+
+        // The proof is bound to the dealer.
+        let mut other_dealer = dealing.clone();
+        other_dealer.dealer = ArIdentity::new(1);
+        assert!(!verify_dealing(&generator, &other_dealer, Threshold(2)));
+
+        // A share for another member is detected.
+        assert!(!verify_share(
+            &generator,
+            &dealing,
+            ArIdentity::new(1),
+            &shares[&member]
+        ));
+        assert!(committee_key(&generator, Threshold(2), &members, &[
+            dealing.clone(),
+            dealing
+        ])
+        .is_err());
+    }
+}
@@ -1,11 +1,14 @@
 //! Functionality needed by the chain to verify credential deployments.
 use crate::{
+    id_verifier, pvss, revocation,
     secret_sharing::Threshold,
     sigma_protocols::{com_enc_eq, com_eq_sig, com_mult, common::*},
     types::*,
     utils,
 };
-use bulletproofs::range_proof::verify_less_than_or_equal;
+use bulletproofs::range_proof::{
+    verify_less_than_or_equal, verify_less_than_or_equal_batch, Generators, LessThanOrEqualInstance,
+};
 use core::fmt::{self, Display};
 use crypto_common::{to_bytes, types::TransactionTime};
 use curve_arithmetic::{Curve, Pairing};
@@ -27,6 +30,7 @@ pub enum CdiVerificationError {
     Policy,
     Ar,
     Proof,
+    Revoked,
 }
 
 impl Display for CdiVerificationError {
@@ -40,11 +44,17 @@ impl Display for CdiVerificationError {
             CdiVerificationError::Policy => write!(f, "PolicyVerificationError"),
             CdiVerificationError::Ar => write!(f, "AnonymityRevokerVerificationError"),
             CdiVerificationError::Proof => write!(f, "ProofVerificationError"),
+            CdiVerificationError::Revoked => write!(f, "RevocationVerificationError"),
         }
     }
 }
+
 /// Verify credential deployment info. This checks that the data is consistent,
 /// and that the credential is signed by the specified identity provider.
+/// If the credential contains a proof that its identity is not revoked, the
+/// proof and the signature of the identity provider on the status list are
+/// checked as well. Whether the status list is recent enough must be checked
+/// by the caller.
 pub fn verify_cdi<
     P: Pairing,
     C: Curve<Scalar = P::ScalarField>,
@@ -59,23 +69,165 @@ pub fn verify_cdi<
     cdi: &CredentialDeploymentInfo<P, C, AttributeType>,
     new_or_existing: &Either<TransactionTime, AccountAddress>,
 ) -> Result<(), CdiVerificationError> {
+    let mut ro = verify_cdi_sigma_proof(global_context, ip_info, known_ars, cdi, new_or_existing)?;
+    if !verify_less_than_or_equal(
+        &mut ro,
+        8,
+        &cdi.proofs.id_proofs.commitments.cmm_cred_counter,
+        &cdi.proofs.id_proofs.commitments.cmm_max_accounts,
+        &cdi.proofs.id_proofs.cred_counter_less_than_max_accounts,
+        global_context.bulletproof_generators(),
+        &global_context.on_chain_commitment_key,
+    ) {
+        return Err(CdiVerificationError::Proof);
+    }
+    verify_cdi_ownership_and_policy(global_context, ip_info, cdi, new_or_existing)
+}
+
+/// A credential deployment together with the data it is verified against, to
+/// be verified as part of a batch by [verify_cdi_batch].
+pub struct CdiInstance<
+    'a,
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+> {
+    /// Public information of the identity provider that signed the identity
+    /// object the credential is derived from.
+    pub ip_info:         &'a IpInfo<P>,
+    /// The credential to verify.
+    pub cdi:             &'a CredentialDeploymentInfo<P, C, AttributeType>,
+    /// Either the expiry of the transaction creating a new account, or the
+    /// address of the existing account the credential is deployed to.
+    pub new_or_existing: &'a Either<TransactionTime, AccountAddress>,
+}
+
+/// Verify many credential deployments at once. The outcome for each credential
+/// is the same as that of [verify_cdi], including which error is reported,
+/// except with negligible probability. The range proofs of all the credentials
+/// are combined with random weights and checked with
+/// [verify_less_than_or_equal_batch], so that a single multiexponentiation is
+/// performed if they are all valid.
+///
+/// The sigma proofs, including the proof of knowledge of the identity
+/// provider's signature with its pairings, are checked one credential at a
+/// time. They consist of the challenge and the response only, so the verifier
+/// has to recompute the commit message of each proof, the pairing product
+/// included, to hash it and compare with the challenge. There is no equation
+/// between known values that a random linear combination could check instead.
+///
+/// If any of the credentials are invalid, the indices of all the invalid
+/// credentials in `instances`, in increasing order, are returned together with
+/// the reason they are invalid.
+pub fn verify_cdi_batch<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+    A: HasArPublicKey<C>,
+    R: rand::Rng,
+>(
+    csprng: &mut R,
+    global_context: &GlobalContext<C>,
+    // NB: The following map only needs to be a superset of the ars
+    // in all the cdis.
+    known_ars: &BTreeMap<ArIdentity, A>,
+    instances: &[CdiInstance<P, C, AttributeType>],
+) -> Result<(), Vec<(usize, CdiVerificationError)>> {
+    let mut outcomes = Vec::with_capacity(instances.len());
+    // The range proofs of the credentials that passed the checks preceding them,
+    // and the indices of those credentials in `instances`.
+    let mut range_proofs = Vec::with_capacity(instances.len());
+    let mut range_proof_indices = Vec::with_capacity(instances.len());
+    for (i, instance) in instances.iter().enumerate() {
+        let outcome = verify_cdi_sigma_proof(
+            global_context,
+            instance.ip_info,
+            known_ars,
+            instance.cdi,
+            instance.new_or_existing,
+        )
+        .map(|ro| {
+            let id_proofs = &instance.cdi.proofs.id_proofs;
+            range_proofs.push(LessThanOrEqualInstance {
+                transcript:   ro,
+                n:            8,
+                commitment_a: &id_proofs.commitments.cmm_cred_counter,
+                commitment_b: &id_proofs.commitments.cmm_max_accounts,
+                proof:        &id_proofs.cred_counter_less_than_max_accounts,
+            });
+            range_proof_indices.push(i);
+        });
+        outcomes.push(outcome);
+    }
+    // If the combined check fails, each range proof is checked once on its own
+    // to find the invalid ones.
+    if let Err(failures) = verify_less_than_or_equal_batch(
+        csprng,
+        &range_proofs,
+        global_context.bulletproof_generators(),
+        &global_context.on_chain_commitment_key,
+    ) {
+        for (j, _) in failures {
+            outcomes[range_proof_indices[j]] = Err(CdiVerificationError::Proof);
+        }
+    }
+    let failures = outcomes
+        .into_iter()
+        .zip(instances.iter())
+        .enumerate()
+        .filter_map(|(i, (outcome, instance))| {
+            outcome
+                .and_then(|_| {
+                    verify_cdi_ownership_and_policy(
+                        global_context,
+                        instance.ip_info,
+                        instance.cdi,
+                        instance.new_or_existing,
+                    )
+                })
+                .err()
+                .map(|e| (i, e))
+        })
+        .collect::<Vec<_>>();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures)
+    }
+}
+
+/// Do the checks of [verify_cdi] that precede the range proof that the
+/// credential counter is less than the maximum number of accounts, ending with
+/// the sigma proof. If the checks succeed the state of the transcript in which
+/// the range proof is to be checked is returned.
+fn verify_cdi_sigma_proof<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+    A: HasArPublicKey<C>,
+>(
+    global_context: &GlobalContext<C>,
+    ip_info: &IpInfo<P>,
+    known_ars: &BTreeMap<ArIdentity, A>,
+    cdi: &CredentialDeploymentInfo<P, C, AttributeType>,
+    new_or_existing: &Either<TransactionTime, AccountAddress>,
+) -> Result<RandomOracle, CdiVerificationError> {
     // We need to check that the threshold is actually equal to
     // the number of coefficients in the sharing polynomial
-    // (corresponding to the degree+1)
+    // (corresponding to the degree+1), and that there are enough anonymity
+    // revokers to reconstruct. The proofs below link the encrypted shares to
+    // the commitments to the shares.
     let addr = new_or_existing.as_ref().right();
-    let rt_usize: usize = cdi.values.threshold.into();
-    if rt_usize
-        != cdi
-            .proofs
+    let share_cmms = pvss::share_commitments(
+        cdi.values.threshold,
+        cdi.values.ar_data.keys(),
+        &cdi.proofs
             .id_proofs
             .commitments
-            .cmm_id_cred_sec_sharing_coeff
-            .len()
-    {
-        return Err(CdiVerificationError::Ar);
-    }
+            .cmm_id_cred_sec_sharing_coeff,
+    )
+    .map_err(|_| CdiVerificationError::Ar)?;
     let on_chain_commitment_key = global_context.on_chain_commitment_key;
-    let gens = global_context.bulletproof_generators();
     let ip_verify_key = &ip_info.ip_verify_key;
     // Compute the challenge prefix by hashing the values.
     let mut ro = RandomOracle::domain("credential");
@@ -99,9 +251,6 @@ pub fn verify_cdi<
     // FIXME: Figure out a pattern to get rid of these clone's.
     let witness_reg_id = cdi.proofs.id_proofs.proof_reg_id.clone();
 
-    let cdv = &cdi.values;
-    let proofs = &cdi.proofs;
-
     let verifier_sig = pok_sig_verifier(
         &on_chain_commitment_key,
         cdi.values.threshold,
@@ -127,7 +276,7 @@ pub fn verify_cdi<
         &on_chain_commitment_key,
         known_ars,
         &cdi.values.ar_data,
-        &commitments.cmm_id_cred_sec_sharing_coeff,
+        &share_cmms,
         &cdi.proofs.id_proofs.proof_id_cred_pub,
     )?;
 
@@ -151,18 +300,27 @@ pub fn verify_cdi<
     if !verify(&mut ro, &verifier, &proof) {
         return Err(CdiVerificationError::Proof);
     }
+    Ok(ro)
+}
+
+/// Do the checks of [verify_cdi] that follow the range proof, of the
+/// signatures proving ownership of the account keys, of the policy, and of
+/// the proof that the identity is not revoked, if any.
+fn verify_cdi_ownership_and_policy<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+>(
+    global_context: &GlobalContext<C>,
+    ip_info: &IpInfo<P>,
+    cdi: &CredentialDeploymentInfo<P, C, AttributeType>,
+    new_or_existing: &Either<TransactionTime, AccountAddress>,
+) -> Result<(), CdiVerificationError> {
+    let addr = new_or_existing.as_ref().right();
+    let cdv = &cdi.values;
+    let proofs = &cdi.proofs;
+    let commitments = &proofs.id_proofs.commitments;
 
-    if !verify_less_than_or_equal(
-        &mut ro,
-        8,
-        &cdi.proofs.id_proofs.commitments.cmm_cred_counter,
-        &cdi.proofs.id_proofs.commitments.cmm_max_accounts,
-        &cdi.proofs.id_proofs.cred_counter_less_than_max_accounts,
-        &gens,
-        &on_chain_commitment_key,
-    ) {
-        return Err(CdiVerificationError::Proof);
-    }
     let signed = utils::credential_hash_to_sign(&cdv, &proofs.id_proofs, new_or_existing);
     // Notice that here we provide all the verification keys, and the
     // function `verify_accunt_ownership_proof` assumes that
@@ -176,17 +334,45 @@ pub fn verify_cdi<
         return Err(CdiVerificationError::AccountOwnership);
     }
 
-    let check_policy = verify_policy(&on_chain_commitment_key, &commitments, &cdi.values.policy);
+    let mut statements_transcript = utils::attribute_statements_transcript(
+        &cdi.values.cred_id,
+        commitments,
+        addr,
+        global_context,
+    );
+    let check_policy = verify_policy(
+        &mut statements_transcript,
+        &global_context.on_chain_commitment_key,
+        global_context.bulletproof_generators(),
+        commitments,
+        &cdi.values.policy,
+    );
 
     if !check_policy {
         return Err(CdiVerificationError::Policy);
     }
 
+    if let Some(proof_not_revoked) = &proofs.proof_not_revoked {
+        let transcript = revocation::credential_transcript(global_context, &cdv.cred_id);
+        if !id_verifier::verify_credential_not_revoked(
+            &transcript,
+            global_context,
+            ip_info,
+            &proof_not_revoked.status_list,
+            commitments,
+            &proof_not_revoked.proof,
+        ) {
+            return Err(CdiVerificationError::Revoked);
+        }
+    }
+
     Ok(())
 }
 
 /// Verify initial account creation. This is essentially checking that the
-/// signature by the identity provider is correct.
+/// signature by the identity provider is correct. Initial credentials have no
+/// commitments to prove attribute statements against, so their policy must not
+/// contain any.
 pub fn verify_initial_cdi<
     P: Pairing,
     C: Curve<Scalar = P::ScalarField>,
@@ -196,6 +382,9 @@ pub fn verify_initial_cdi<
     cdi: &InitialCredentialDeploymentInfo<C, AttributeType>,
     expiry: TransactionTime,
 ) -> Result<(), CdiVerificationError> {
+    if !cdi.values.policy.statements.is_empty() {
+        return Err(CdiVerificationError::Policy);
+    }
     let mut hasher = Sha256::new();
     hasher.update(&to_bytes(&expiry));
     hasher.update(&to_bytes(&cdi.values));
@@ -211,7 +400,7 @@ fn id_cred_pub_verifier<C: Curve, A: HasArPublicKey<C>>(
     commitment_key: &CommitmentKey<C>,
     known_ars: &BTreeMap<ArIdentity, A>,
     chain_ar_data: &BTreeMap<ArIdentity, ChainArData<C>>,
-    cmm_sharing_coeff: &[Commitment<C>],
+    share_cmms: &BTreeMap<ArIdentity, Commitment<C>>,
     proof_id_cred_pub: &BTreeMap<ArIdentity, com_enc_eq::Witness<C>>,
 ) -> Result<IdCredPubVerifiers<C>, CdiVerificationError> {
     let mut provers = Vec::with_capacity(proof_id_cred_pub.len());
@@ -229,7 +418,9 @@ fn id_cred_pub_verifier<C: Curve, A: HasArPublicKey<C>>(
         if ar_id != ar_id_1 {
             return Err(CdiVerificationError::IdCredPub);
         }
-        let cmm_share = utils::commitment_to_share(&ar_id.to_scalar::<C>(), cmm_sharing_coeff);
+        let cmm_share = *share_cmms
+            .get(ar_id)
+            .ok_or(CdiVerificationError::IdCredPub)?;
 
         // finding the correct AR data.
         let ar_info = known_ars
@@ -250,15 +441,30 @@ fn id_cred_pub_verifier<C: Curve, A: HasArPublicKey<C>>(
     }))
 }
 
-/// Verify a policy. This currently does not do anything since
-/// the only check that is done is that the commitments are opened correctly,
-/// and that check is part of the signature check.
+/// Verify a policy. The revealed attributes are checked as part of the
+/// signature check, so the only thing left to check here is that each of the
+/// attribute statements refers to a committed attribute, and that its proof
+/// is valid with respect to that commitment. The proofs are checked in order
+/// in the given transcript, see [utils::attribute_statements_transcript].
 fn verify_policy<C: Curve, AttributeType: Attribute<C::Scalar>>(
-    _commitment_key: &CommitmentKey<C>,
-    _commitments: &CredentialDeploymentCommitments<C>,
-    _policy: &Policy<C, AttributeType>,
+    transcript: &mut RandomOracle,
+    commitment_key: &CommitmentKey<C>,
+    gens: &Generators<C>,
+    commitments: &CredentialDeploymentCommitments<C>,
+    policy: &Policy<C, AttributeType>,
 ) -> bool {
-    true
+    policy.statements.iter().all(|statement| {
+        match commitments.cmm_attributes.get(&statement.attribute_tag()) {
+            Some(cmm) => id_verifier::verify_attribute_statement(
+                transcript,
+                commitment_key,
+                gens,
+                statement,
+                cmm,
+            ),
+            None => false,
+        }
+    })
 }
 
 /// Verify the proof of knowledge of signature on the attribute list.
@@ -351,7 +557,7 @@ fn pok_sig_verifier<
 mod tests {
     use super::*;
 
-    use crate::{account_holder::*, constants::*, identity_provider::*, test::*};
+    use crate::{account_holder::*, constants::*, id_prover, identity_provider::*, test::*};
     use crypto_common::types::{KeyIndex, KeyPair};
     use pairing::bls12_381::G1;
     use rand::*;
@@ -426,6 +632,7 @@ mod tests {
                 tree
             },
             _phantom: Default::default(),
+            statements: Vec::new(),
         };
         let cred_data = CredentialData {
             keys:      {
@@ -444,13 +651,78 @@ mod tests {
             &id_use_data,
             0,
             policy.clone(),
+            &[],
             &cred_data,
             &Left(EXPIRY),
+            None,
         )
         .expect("Should generate the credential successfully.");
         let cdi_check = verify_cdi(&global_ctx, &ip_info, &ars_infos, &cdi, &Left(EXPIRY));
         assert_eq!(cdi_check, Ok(()));
 
+        // The sharing of the IdCredSec is checked publicly. A sharing polynomial of
+        // the wrong degree, fewer anonymity revokers than the threshold, and
+        // shares encrypted to the wrong anonymity revokers are rejected.
+        let mut wrong_degree = cdi.clone();
+        wrong_degree
+            .proofs
+            .id_proofs
+            .commitments
+            .cmm_id_cred_sec_sharing_coeff
+            .pop();
+        assert_eq!(
+            verify_cdi(
+                &global_ctx,
+                &ip_info,
+                &ars_infos,
+                &wrong_degree,
+                &Left(EXPIRY)
+            ),
+            Err(CdiVerificationError::Ar)
+        );
+        let mut too_few_ars = cdi.clone();
+        let removed = cdi
+            .values
+            .ar_data
+            .keys()
+            .skip(usize::from(cdi.values.threshold) - 1)
+            .copied()
+            .collect::<Vec<_>>();
+        for ar_id in removed.iter() {
+            too_few_ars.values.ar_data.remove(ar_id);
+            too_few_ars.proofs.id_proofs.proof_id_cred_pub.remove(ar_id);
+        }
+        assert_eq!(
+            verify_cdi(
+                &global_ctx,
+                &ip_info,
+                &ars_infos,
+                &too_few_ars,
+                &Left(EXPIRY)
+            ),
+            Err(CdiVerificationError::Ar)
+        );
+        let mut swapped_shares = cdi.clone();
+        let ar_ids = cdi.values.ar_data.keys().copied().collect::<Vec<_>>();
+        for (from, to) in ar_ids.iter().zip(ar_ids.iter().skip(1)) {
+            swapped_shares
+                .values
+                .ar_data
+                .get_mut(to)
+                .expect("The anonymity revoker exists.")
+                .enc_id_cred_pub_share = cdi.values.ar_data[from].enc_id_cred_pub_share;
+        }
+        assert_eq!(
+            verify_cdi(
+                &global_ctx,
+                &ip_info,
+                &ars_infos,
+                &swapped_shares,
+                &Left(EXPIRY)
+            ),
+            Err(CdiVerificationError::Proof)
+        );
+
         // Testing with an existing RegId (i.e. an existing account)
         let existing_reg_id = AccountAddress::new(&cdi.values.cred_id);
         let cred_data = CredentialData {
@@ -469,8 +741,10 @@ mod tests {
             &id_use_data,
             1,
             policy,
+            &[],
             &cred_data,
             &Right(existing_reg_id),
+            None,
         )
         .expect("Should generate the credential successfully.");
         let cdi_check = verify_cdi(
@@ -483,6 +757,356 @@ mod tests {
         assert_eq!(cdi_check, Ok(()));
     }
 
+    #[test]
+    fn test_verify_cdi_with_statements() {
+        let mut csprng = thread_rng();
+
+        // Generate PIO
+        let max_attrs = 10;
+        let num_ars = 5;
+        let IpData {
+            public_ip_info: ip_info,
+            ip_secret_key,
+            ip_cdi_secret_key,
+        } = test_create_ip_info(&mut csprng, num_ars, max_attrs);
+        let global_ctx = GlobalContext::<G1>::generate(String::from("genesis_string"));
+        let (ars_infos, _) =
+            test_create_ars(&global_ctx.on_chain_commitment_key.g, num_ars, &mut csprng);
+        let aci = test_create_aci(&mut csprng);
+        let initial_acc_data = InitialAccountData {
+            keys:      {
+                let mut keys = BTreeMap::new();
+                keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                keys
+            },
+            threshold: SignatureThreshold(1),
+        };
+        let (context, pio, randomness) = test_create_pio(
+            &aci,
+            &ip_info,
+            &ars_infos,
+            &global_ctx,
+            num_ars,
+            &initial_acc_data,
+        );
+        let alist = test_create_attributes();
+        let (ip_sig, _) = verify_credentials(
+            &pio,
+            context,
+            &alist,
+            EXPIRY,
+            &ip_secret_key,
+            &ip_cdi_secret_key,
+        )
+        .expect("Should verify the pre-identity object.");
+        let id_object = IdentityObject {
+            pre_identity_object: pio,
+            alist,
+            signature: ip_sig,
+        };
+        let id_use_data = IdObjectUseData { aci, randomness };
+        let policy = Policy {
+            valid_to:   id_object.alist.valid_to,
+            created_at: id_object.alist.created_at,
+            policy_vec: {
+                let mut tree = BTreeMap::new();
+                tree.insert(AttributeTag::from(8u8), AttributeKind::from(31));
+                tree
+            },
+            _phantom:   Default::default(),
+            statements: Vec::new(),
+        };
+        // Attribute 0 has value 55 and is not revealed.
+        let statements = vec![
+            AttributeStatement::InRange {
+                attribute_tag: AttributeTag::from(0u8),
+                lower:         AttributeKind::from(50),
+                upper:         AttributeKind::from(60),
+            },
+            AttributeStatement::InSet {
+                attribute_tag: AttributeTag::from(0u8),
+                set:           vec![AttributeKind::from(54), AttributeKind::from(55)],
+            },
+            AttributeStatement::NotInSet {
+                attribute_tag: AttributeTag::from(0u8),
+                set:           vec![AttributeKind::from(31), AttributeKind::from(56)],
+            },
+        ];
+        let cred_data = CredentialData {
+            keys:      {
+                let mut keys = BTreeMap::new();
+                keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                keys
+            },
+            threshold: SignatureThreshold(1),
+        };
+        let context = IpContext::new(&ip_info, &ars_infos, &global_ctx);
+        let (cdi, _) = create_credential(
+            context,
+            &id_object,
+            &id_use_data,
+            0,
+            policy.clone(),
+            &statements,
+            &cred_data,
+            &Left(EXPIRY),
+            None,
+        )
+        .expect("Should generate the credential successfully.");
+        assert_eq!(cdi.values.policy.statements.len(), statements.len());
+        let cdi_check = verify_cdi(&global_ctx, &ip_info, &ars_infos, &cdi, &Left(EXPIRY));
+        assert_eq!(cdi_check, Ok(()));
+
+        // The proofs of the statements are bound to the credential.
+        let commitments = &cdi.proofs.id_proofs.commitments;
+        let check_policy = |cred_id| {
+            verify_policy(
+                &mut utils::attribute_statements_transcript(
+                    cred_id,
+                    commitments,
+                    None,
+                    &global_ctx,
+                ),
+                &global_ctx.on_chain_commitment_key,
+                global_ctx.bulletproof_generators(),
+                commitments,
+                &cdi.values.policy,
+            )
+        };
+        assert!(check_policy(&cdi.values.cred_id));
+        assert!(
+            !check_policy(&global_ctx.on_chain_commitment_key.g),
+            "The statements verified for another credential."
+        );
+
+        // Statements that do not hold, or that are about revealed or missing
+        // attributes, cannot be proved.
+        let bad_statements = [
+            AttributeStatement::InSet {
+                attribute_tag: AttributeTag::from(0u8),
+                set:           vec![AttributeKind::from(31)],
+            },
+            AttributeStatement::NotInSet {
+                attribute_tag: AttributeTag::from(8u8),
+                set:           vec![AttributeKind::from(55)],
+            },
+            AttributeStatement::NotInSet {
+                attribute_tag: AttributeTag::from(3u8),
+                set:           vec![AttributeKind::from(55)],
+            },
+        ];
+        for statement in bad_statements.iter() {
+            assert!(
+                create_credential(
+                    context,
+                    &id_object,
+                    &id_use_data,
+                    0,
+                    policy.clone(),
+                    std::slice::from_ref(statement),
+                    &cred_data,
+                    &Left(EXPIRY),
+                    None,
+                )
+                .is_err(),
+                "Proved a statement that should not be provable."
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_cdi_batch() {
+        let mut csprng = thread_rng();
+
+        // Generate PIO
+        let max_attrs = 10;
+        let num_ars = 5;
+        let IpData {
+            public_ip_info: ip_info,
+            ip_secret_key,
+            ip_cdi_secret_key,
+        } = test_create_ip_info(&mut csprng, num_ars, max_attrs);
+        let global_ctx = GlobalContext::<G1>::generate(String::from("genesis_string"));
+        let (ars_infos, _) =
+            test_create_ars(&global_ctx.on_chain_commitment_key.g, num_ars, &mut csprng);
+        let aci = test_create_aci(&mut csprng);
+        let initial_acc_data = InitialAccountData {
+            keys:      {
+                let mut keys = BTreeMap::new();
+                keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                keys
+            },
+            threshold: SignatureThreshold(1),
+        };
+        let (context, pio, randomness) = test_create_pio(
+            &aci,
+            &ip_info,
+            &ars_infos,
+            &global_ctx,
+            num_ars,
+            &initial_acc_data,
+        );
+        let alist = test_create_attributes();
+        let (ip_sig, _) = verify_credentials(
+            &pio,
+            context,
+            &alist,
+            EXPIRY,
+            &ip_secret_key,
+            &ip_cdi_secret_key,
+        )
+        .expect("Identity object request should be valid.");
+        let id_object = IdentityObject {
+            pre_identity_object: pio,
+            alist,
+            signature: ip_sig,
+        };
+        let id_use_data = IdObjectUseData { aci, randomness };
+        let policy = Policy {
+            valid_to:   YearMonth::new(2022, 5).unwrap(),
+            created_at: YearMonth::new(2020, 5).unwrap(),
+            policy_vec: BTreeMap::new(),
+            _phantom:   Default::default(),
+            statements: Vec::new(),
+        };
+        let context = IpContext::new(&ip_info, &ars_infos, &global_ctx);
+
+        // Generate a few credentials from the same identity object.
+        let mut cdis = Vec::new();
+        for cred_counter in 0..3 {
+            let cred_data = CredentialData {
+                keys:      {
+                    let mut keys = BTreeMap::new();
+                    keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                    keys
+                },
+                threshold: SignatureThreshold(1),
+            };
+            let (cdi, _) = create_credential(
+                context,
+                &id_object,
+                &id_use_data,
+                cred_counter,
+                policy.clone(),
+                &[],
+                &cred_data,
+                &Left(EXPIRY),
+                None,
+            )
+            .expect("Should generate the credential successfully.");
+            cdis.push(cdi);
+        }
+        let new_account = Left(EXPIRY);
+        let instances = cdis
+            .iter()
+            .map(|cdi| CdiInstance {
+                ip_info: &ip_info,
+                cdi,
+                new_or_existing: &new_account,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            verify_cdi_batch(&mut csprng, &global_ctx, &ars_infos, &instances),
+            Ok(()),
+            "A batch of valid credentials should verify."
+        );
+
+        // Replace the range proof of the middle credential with the range proof of
+        // the last one. Only the middle credential should be reported.
+        let mut invalid = cdis[1].clone();
+        invalid.proofs.id_proofs.cred_counter_less_than_max_accounts = cdis[2]
+            .proofs
+            .id_proofs
+            .cred_counter_less_than_max_accounts
+            .clone();
+        let instances = vec![
+            CdiInstance {
+                ip_info:         &ip_info,
+                cdi:             &cdis[0],
+                new_or_existing: &new_account,
+            },
+            CdiInstance {
+                ip_info:         &ip_info,
+                cdi:             &invalid,
+                new_or_existing: &new_account,
+            },
+            CdiInstance {
+                ip_info:         &ip_info,
+                cdi:             &cdis[2],
+                new_or_existing: &new_account,
+            },
+        ];
+        let failures = verify_cdi_batch(&mut csprng, &global_ctx, &ars_infos, &instances)
+            .expect_err("The batch contains an invalid credential.");
+        assert_eq!(
+            failures.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![1],
+            "Only the invalid credential should be reported."
+        );
+
+        // All the credentials with invalid range proofs are reported.
+        let mut other_invalid = cdis[2].clone();
+        other_invalid
+            .proofs
+            .id_proofs
+            .cred_counter_less_than_max_accounts = cdis[0]
+            .proofs
+            .id_proofs
+            .cred_counter_less_than_max_accounts
+            .clone();
+        let invalid_cdis = [invalid.clone(), cdis[0].clone(), other_invalid];
+        let instances = invalid_cdis
+            .iter()
+            .map(|cdi| CdiInstance {
+                ip_info: &ip_info,
+                cdi,
+                new_or_existing: &new_account,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            verify_cdi_batch(&mut csprng, &global_ctx, &ars_infos, &instances),
+            Err(vec![
+                (0, CdiVerificationError::Proof),
+                (2, CdiVerificationError::Proof)
+            ]),
+            "All the invalid credentials should be reported."
+        );
+
+        // The errors are reported in the same order as by verify_cdi: an invalid
+        // range proof takes precedence over invalid account ownership proofs.
+        let mut invalid_ownership = cdis[0].clone();
+        invalid_ownership.proofs.proof_acc_sk = cdis[2].proofs.proof_acc_sk.clone();
+        let mut invalid_both = invalid.clone();
+        invalid_both.proofs.proof_acc_sk = cdis[2].proofs.proof_acc_sk.clone();
+        let invalid_cdis = [invalid_ownership, invalid_both];
+        let instances = invalid_cdis
+            .iter()
+            .map(|cdi| CdiInstance {
+                ip_info: &ip_info,
+                cdi,
+                new_or_existing: &new_account,
+            })
+            .collect::<Vec<_>>();
+        let expected = invalid_cdis
+            .iter()
+            .enumerate()
+            .map(|(i, cdi)| {
+                let e = verify_cdi(&global_ctx, &ip_info, &ars_infos, cdi, &new_account)
+                    .expect_err("The credential is invalid.");
+                (i, e)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(expected, vec![
+            (0, CdiVerificationError::AccountOwnership),
+            (1, CdiVerificationError::Proof)
+        ]);
+        assert_eq!(
+            verify_cdi_batch(&mut csprng, &global_ctx, &ars_infos, &instances),
+            Err(expected),
+            "The batch should report the same errors as verify_cdi."
+        );
+    }
+
     #[test]
     fn test_verify_initial_cdi() {
         let mut csprng = thread_rng();
@@ -526,5 +1150,28 @@ mod tests {
         let (_, initial_cdi) = ver_ok.unwrap();
         let cdi_check = verify_initial_cdi(&ip_info, &initial_cdi, EXPIRY);
         assert_eq!(cdi_check, Ok(()));
+
+        // Initial credentials cannot carry attribute statements, since there
+        // are no commitments to check them against.
+        let mut initial_cdi = initial_cdi;
+        let keys = CommitmentKey::<G1>::generate(&mut csprng);
+        let attribute = AttributeKind::from(55);
+        let randomness = Randomness::<G1>::generate(&mut csprng);
+        let statement = id_prover::prove_attribute_statement(
+            &mut RandomOracle::domain("test_statements"),
+            global_ctx.bulletproof_generators(),
+            &keys,
+            &AttributeStatement::InRange {
+                attribute_tag: AttributeTag::from(0u8),
+                lower:         AttributeKind::from(50),
+                upper:         AttributeKind::from(60),
+            },
+            &attribute,
+            &randomness,
+        )
+        .expect("Statement should be provable.");
+        initial_cdi.values.policy.statements.push(statement);
+        let cdi_check = verify_initial_cdi(&ip_info, &initial_cdi, EXPIRY);
+        assert_eq!(cdi_check, Err(CdiVerificationError::Policy));
     }
 }
@@ -103,6 +103,7 @@ extern "C" fn verify_cdi_ffi(
                 Err(CdiVerificationError::Ar) => -6,
                 Err(CdiVerificationError::AccountOwnership) => -7,
                 Err(CdiVerificationError::Proof) => -8,
+                Err(CdiVerificationError::Revoked) => -15,
             }
         }
     }
@@ -294,6 +295,7 @@ mod test {
                 tree
             },
             _phantom: Default::default(),
+            statements: Vec::new(),
         };
 
         let wrong_policy = Policy {
@@ -305,6 +307,7 @@ mod test {
                 tree
             },
             _phantom: Default::default(),
+            statements: Vec::new(),
         };
 
         let mut keys = BTreeMap::new();
@@ -331,8 +334,10 @@ mod test {
             &id_use_data,
             0,
             policy,
+            &[],
             &acc_data,
             &Left(EXPIRY),
+            None,
         )
         .expect("Should generate the credential successfully.");
 
@@ -342,8 +347,10 @@ mod test {
             &id_use_data,
             0,
             wrong_policy,
+            &[],
             &acc_data,
             &Left(EXPIRY),
+            None,
         )
         .expect("Should generate the credential successfully.");
 
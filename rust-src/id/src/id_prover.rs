@@ -1,9 +1,19 @@
-use crate::types::*;
-use bulletproofs::range_proof::{prove_given_scalars, Generators, RangeProof};
+use crate::{
+    id_verifier::verify_attribute_range_with_transcript,
+    revocation::{self, NonRevocationProof, StatusList},
+    types::*,
+    utils,
+};
+use bulletproofs::{
+    r1cs::{set_membership_gadget, set_non_membership_gadget, Prover, R1CSProof},
+    range_proof::{prove_given_scalars, Generators, RangeProof},
+};
 use curve_arithmetic::Curve;
 use ed25519_dalek as ed25519;
 use ff::Field;
-use pedersen_scheme::{CommitmentKey as PedersenKey, Randomness as PedersenRandomness};
+use pedersen_scheme::{
+    CommitmentKey as PedersenKey, Randomness as PedersenRandomness, Value as PedersenValue,
+};
 use random_oracle::RandomOracle;
 use sha2::{Digest, Sha256};
 
@@ -67,6 +77,29 @@ pub fn prove_attribute_in_range<C: Curve, AttributeType: Attribute<C::Scalar>>(
     r: &PedersenRandomness<C>,
 ) -> Option<RangeProof<C>> {
     let mut transcript = RandomOracle::domain("attribute_range_proof");
+    prove_attribute_in_range_with_transcript(
+        &mut transcript,
+        gens,
+        keys,
+        attribute,
+        lower,
+        upper,
+        r,
+    )
+}
+
+/// Like [prove_attribute_in_range], but the proof is produced using the given
+/// transcript. This allows the proof to be bound to additional context, such
+/// as a challenge from the verifier.
+pub fn prove_attribute_in_range_with_transcript<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    transcript: &mut RandomOracle,
+    gens: &Generators<C>,
+    keys: &PedersenKey<C>,
+    attribute: &AttributeType,
+    lower: &AttributeType,
+    upper: &AttributeType,
+    r: &PedersenRandomness<C>,
+) -> Option<RangeProof<C>> {
     let mut csprng = rand::thread_rng();
     let delta = attribute.to_field_element();
     let a = lower.to_field_element();
@@ -81,7 +114,7 @@ pub fn prove_attribute_in_range<C: Curve, AttributeType: Attribute<C::Scalar>>(
     let rand1 = r.clone();
     let rand2 = r.clone();
     prove_given_scalars(
-        &mut transcript,
+        transcript,
         &mut csprng,
         64,
         2,
@@ -91,3 +124,191 @@ pub fn prove_attribute_in_range<C: Curve, AttributeType: Attribute<C::Scalar>>(
         &[rand1, rand2],
     )
 }
+
+/// Function for proving that an attribute inside a commitment is one of the
+/// values in a set. The parameters are
+/// - gens - the bulletproof generators needed for the proof. There must be at
+///   least as many as the size of the set.
+/// - keys - the commitments keys used to commit to the attribute
+/// - attribute - the attribute inside the commitment
+/// - set - the set of values
+/// - r - the randomness used to commit
+///
+/// The function outputs `None` if the attribute is not in the set, or if there
+/// are not enough generators.
+/// This is done by proving that the product of attribute - v, for v in the set,
+/// is zero.
+pub fn prove_attribute_in_set<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    gens: &Generators<C>,
+    keys: &PedersenKey<C>,
+    attribute: &AttributeType,
+    set: &[AttributeType],
+    r: &PedersenRandomness<C>,
+) -> Option<R1CSProof<C>> {
+    let mut transcript = RandomOracle::domain("attribute_set_membership_proof");
+    prove_attribute_in_set_with_transcript(&mut transcript, gens, keys, attribute, set, r)
+}
+
+/// Like [prove_attribute_in_set], but the proof is produced using the given
+/// transcript.
+pub fn prove_attribute_in_set_with_transcript<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    transcript: &mut RandomOracle,
+    gens: &Generators<C>,
+    keys: &PedersenKey<C>,
+    attribute: &AttributeType,
+    set: &[AttributeType],
+    r: &PedersenRandomness<C>,
+) -> Option<R1CSProof<C>> {
+    let mut csprng = rand::thread_rng();
+    let set = utils::attribute_set_to_scalars::<C, AttributeType>(transcript, set);
+    let value = attribute.to_field_element();
+    if !set.contains(&value) {
+        return None;
+    }
+    let mut prover = Prover::new(keys, transcript);
+    let (_, var) = prover.commit(&PedersenValue::new(value), r);
+    set_membership_gadget(&mut prover, var, &set);
+    prover.prove(&mut csprng, gens).ok()
+}
+
+/// Function for proving that an attribute inside a commitment is none of the
+/// values in a set. The parameters are the same as for
+/// [prove_attribute_in_set], except that there must be at least as many
+/// generators as the size of the set plus one.
+///
+/// The function outputs `None` if the attribute is in the set, or if there are
+/// not enough generators.
+/// This is done by proving that the product of attribute - v, for v in the set,
+/// has an inverse.
+pub fn prove_attribute_not_in_set<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    gens: &Generators<C>,
+    keys: &PedersenKey<C>,
+    attribute: &AttributeType,
+    set: &[AttributeType],
+    r: &PedersenRandomness<C>,
+) -> Option<R1CSProof<C>> {
+    let mut transcript = RandomOracle::domain("attribute_set_non_membership_proof");
+    prove_attribute_not_in_set_with_transcript(&mut transcript, gens, keys, attribute, set, r)
+}
+
+/// Like [prove_attribute_not_in_set], but the proof is produced using the
+/// given transcript.
+pub fn prove_attribute_not_in_set_with_transcript<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    transcript: &mut RandomOracle,
+    gens: &Generators<C>,
+    keys: &PedersenKey<C>,
+    attribute: &AttributeType,
+    set: &[AttributeType],
+    r: &PedersenRandomness<C>,
+) -> Option<R1CSProof<C>> {
+    let mut csprng = rand::thread_rng();
+    let set = utils::attribute_set_to_scalars::<C, AttributeType>(transcript, set);
+    let value = attribute.to_field_element();
+    let mut prover = Prover::new(keys, transcript);
+    let (_, var) = prover.commit(&PedersenValue::new(value), r);
+    set_non_membership_gadget(&mut prover, var, Some(value), &set).ok()?;
+    prover.prove(&mut csprng, gens).ok()
+}
+
+/// Function for proving a statement about an attribute inside a commitment,
+/// using one of the functions above. The parameters are
+/// - transcript - the transcript the proof is bound to, see
+///   [utils::attribute_statements_transcript] for the statements of credentials
+/// - gens - the bulletproof generators needed for the proof
+/// - keys - the commitments keys used to commit to the attribute
+/// - statement - the statement to prove
+/// - attribute - the attribute inside the commitment
+/// - r - the randomness used to commit
+///
+/// The function outputs the statement together with the proof, or `None` if the
+/// attribute does not satisfy the statement.
+pub fn prove_attribute_statement<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    transcript: &mut RandomOracle,
+    gens: &Generators<C>,
+    keys: &PedersenKey<C>,
+    statement: &AttributeStatement<AttributeType>,
+    attribute: &AttributeType,
+    r: &PedersenRandomness<C>,
+) -> Option<AttributeStatementWithProof<C, AttributeType>> {
+    match statement {
+        AttributeStatement::InRange {
+            attribute_tag,
+            lower,
+            upper,
+        } => {
+            let mut check_transcript = transcript.split();
+            let proof = prove_attribute_in_range_with_transcript(
+                transcript, gens, keys, attribute, lower, upper, r,
+            )?;
+            // The range proof can be produced even if the attribute is not in the
+            // range, so we check it to not produce invalid proofs.
+            let commitment = keys.hide(&PedersenValue::<C>::new(attribute.to_field_element()), r);
+            verify_attribute_range_with_transcript(
+                &mut check_transcript,
+                keys,
+                gens,
+                lower,
+                upper,
+                &commitment,
+                &proof,
+            )
+            .ok()?;
+            Some(AttributeStatementWithProof::InRange {
+                attribute_tag: *attribute_tag,
+                lower: lower.clone(),
+                upper: upper.clone(),
+                proof,
+            })
+        }
+        AttributeStatement::InSet { attribute_tag, set } => {
+            let proof =
+                prove_attribute_in_set_with_transcript(transcript, gens, keys, attribute, set, r)?;
+            Some(AttributeStatementWithProof::InSet {
+                attribute_tag: *attribute_tag,
+                set: set.clone(),
+                proof,
+            })
+        }
+        AttributeStatement::NotInSet { attribute_tag, set } => {
+            let proof = prove_attribute_not_in_set_with_transcript(
+                transcript, gens, keys, attribute, set, r,
+            )?;
+            Some(AttributeStatementWithProof::NotInSet {
+                attribute_tag: *attribute_tag,
+                set: set.clone(),
+                proof,
+            })
+        }
+    }
+}
+
+/// Function for proving that the identity a credential is derived from is not
+/// revoked by the identity provider. The arguments are
+/// - transcript - the transcript the proof is bound to
+/// - global_context - the global context the credential was deployed with
+/// - status_list - the status list of the identity provider
+/// - id_cred_sec - the IdCredSec of the identity
+/// - commitments - the commitments of the credential
+/// - randomness - the randomness output when the credential was created
+///
+/// The function outputs `None` if the identity is revoked, or the commitments
+/// are malformed.
+pub fn prove_credential_not_revoked<C: Curve>(
+    transcript: &RandomOracle,
+    global_context: &GlobalContext<C>,
+    status_list: &StatusList<C>,
+    id_cred_sec: &PedersenValue<C>,
+    commitments: &CredentialDeploymentCommitments<C>,
+    randomness: &CommitmentsRandomness<C>,
+) -> Option<NonRevocationProof<C>> {
+    let mut csprng = rand::thread_rng();
+    revocation::prove_non_revocation(
+        transcript,
+        &global_context.on_chain_commitment_key,
+        status_list,
+        commitments.cmm_id_cred_sec_sharing_coeff.first()?,
+        id_cred_sec,
+        &randomness.id_cred_sec_rand,
+        &mut csprng,
+    )
+}
@@ -1,6 +1,13 @@
-use crate::{types::*, utils};
-use bulletproofs::range_proof::{verify_efficient, Generators, RangeProof, VerificationError};
-use curve_arithmetic::Curve;
+use crate::{
+    revocation::{self, NonRevocationProof, SignedStatusList},
+    types::*,
+    utils,
+};
+use bulletproofs::{
+    r1cs::{set_membership_gadget, set_non_membership_gadget, R1CSError, R1CSProof, Verifier},
+    range_proof::{verify_efficient, Generators, RangeProof, VerificationError},
+};
+use curve_arithmetic::{Curve, Pairing};
 use ff::Field;
 use pedersen_scheme::{
     Commitment, CommitmentKey as PedersenKey, Randomness as PedersenRandomness, Value,
@@ -49,6 +56,20 @@ pub fn verify_attribute_range<C: Curve, AttributeType: Attribute<C::Scalar>>(
     proof: &RangeProof<C>,
 ) -> Result<(), VerificationError> {
     let mut transcript = RandomOracle::domain("attribute_range_proof");
+    verify_attribute_range_with_transcript(&mut transcript, keys, gens, lower, upper, c, proof)
+}
+
+/// Like [verify_attribute_range], but the proof is verified using the given
+/// transcript, which must be the same as the one the prover used.
+pub fn verify_attribute_range_with_transcript<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    transcript: &mut RandomOracle,
+    keys: &PedersenKey<C>,
+    gens: &Generators<C>,
+    lower: &AttributeType,
+    upper: &AttributeType,
+    c: &Commitment<C>,
+    proof: &RangeProof<C>,
+) -> Result<(), VerificationError> {
     let a = lower.to_field_element();
     let b = upper.to_field_element();
     let zero_randomness = PedersenRandomness::<C>::zero();
@@ -61,7 +82,7 @@ pub fn verify_attribute_range<C: Curve, AttributeType: Attribute<C::Scalar>>(
     let com_delta_minus_a = Commitment(c.0.minus_point(&com_a.0));
 
     verify_efficient(
-        &mut transcript,
+        transcript,
         64,
         &[com_delta_minus_b_plus_2n, com_delta_minus_a],
         proof,
@@ -70,6 +91,118 @@ pub fn verify_attribute_range<C: Curve, AttributeType: Attribute<C::Scalar>>(
     )
 }
 
+/// Function for verifying a proof that an attribute inside a commitment is one
+/// of the values in a set. The arguments are
+/// - keys - the commitments keys used to commit to the attribute
+/// - gens - the bulletproof generators needed for the proof
+/// - set - the set of values
+/// - c - the commitment to the attribute
+/// - proof - the proof about the attribute inside the commitment
+///
+/// The function outputs `Ok(())` if the proof is correct, i.e., if the
+/// attribute inside the commitment is in the set.
+pub fn verify_attribute_in_set<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    keys: &PedersenKey<C>,
+    gens: &Generators<C>,
+    set: &[AttributeType],
+    c: &Commitment<C>,
+    proof: &R1CSProof<C>,
+) -> Result<(), R1CSError> {
+    let mut transcript = RandomOracle::domain("attribute_set_membership_proof");
+    verify_attribute_in_set_with_transcript(&mut transcript, keys, gens, set, c, proof)
+}
+
+/// Like [verify_attribute_in_set], but the proof is verified using the given
+/// transcript.
+pub fn verify_attribute_in_set_with_transcript<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    transcript: &mut RandomOracle,
+    keys: &PedersenKey<C>,
+    gens: &Generators<C>,
+    set: &[AttributeType],
+    c: &Commitment<C>,
+    proof: &R1CSProof<C>,
+) -> Result<(), R1CSError> {
+    let mut csprng = rand::thread_rng();
+    let set = utils::attribute_set_to_scalars::<C, AttributeType>(transcript, set);
+    let mut verifier = Verifier::new(transcript);
+    let var = verifier.commit(c);
+    set_membership_gadget(&mut verifier, var, &set);
+    verifier.verify(&mut csprng, proof, gens, keys)
+}
+
+/// Function for verifying a proof that an attribute inside a commitment is none
+/// of the values in a set. The arguments are the same as for
+/// [verify_attribute_in_set].
+///
+/// The function outputs `Ok(())` if the proof is correct, i.e., if the
+/// attribute inside the commitment is not in the set.
+pub fn verify_attribute_not_in_set<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    keys: &PedersenKey<C>,
+    gens: &Generators<C>,
+    set: &[AttributeType],
+    c: &Commitment<C>,
+    proof: &R1CSProof<C>,
+) -> Result<(), R1CSError> {
+    let mut transcript = RandomOracle::domain("attribute_set_non_membership_proof");
+    verify_attribute_not_in_set_with_transcript(&mut transcript, keys, gens, set, c, proof)
+}
+
+/// Like [verify_attribute_not_in_set], but the proof is verified using the
+/// given transcript.
+pub fn verify_attribute_not_in_set_with_transcript<
+    C: Curve,
+    AttributeType: Attribute<C::Scalar>,
+>(
+    transcript: &mut RandomOracle,
+    keys: &PedersenKey<C>,
+    gens: &Generators<C>,
+    set: &[AttributeType],
+    c: &Commitment<C>,
+    proof: &R1CSProof<C>,
+) -> Result<(), R1CSError> {
+    let mut csprng = rand::thread_rng();
+    let set = utils::attribute_set_to_scalars::<C, AttributeType>(transcript, set);
+    let mut verifier = Verifier::new(transcript);
+    let var = verifier.commit(c);
+    set_non_membership_gadget(&mut verifier, var, None, &set)?;
+    verifier.verify(&mut csprng, proof, gens, keys)
+}
+
+/// Function for verifying a statement, with proof, about an attribute inside a
+/// commitment. The arguments are
+/// - transcript - the transcript the proof is bound to, see
+///   [utils::attribute_statements_transcript] for the statements of credentials
+/// - keys - the commitments keys used to commit to the attribute
+/// - gens - the bulletproof generators needed for the proof
+/// - statement - the statement with its proof
+/// - c - the commitment to the attribute the statement is about
+///
+/// The function outputs a bool, indicating whether the proof is correct or not.
+pub fn verify_attribute_statement<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    transcript: &mut RandomOracle,
+    keys: &PedersenKey<C>,
+    gens: &Generators<C>,
+    statement: &AttributeStatementWithProof<C, AttributeType>,
+    c: &Commitment<C>,
+) -> bool {
+    match statement {
+        AttributeStatementWithProof::InRange {
+            lower,
+            upper,
+            proof,
+            ..
+        } => verify_attribute_range_with_transcript(transcript, keys, gens, lower, upper, c, proof)
+            .is_ok(),
+        AttributeStatementWithProof::InSet { set, proof, .. } => {
+            verify_attribute_in_set_with_transcript(transcript, keys, gens, set, c, proof).is_ok()
+        }
+        AttributeStatementWithProof::NotInSet { set, proof, .. } => {
+            verify_attribute_not_in_set_with_transcript(transcript, keys, gens, set, c, proof)
+                .is_ok()
+        }
+    }
+}
+
 /// Function for verifying account ownership. The arguments are
 /// - public_data - the public keys (and threshold) of the prover. These should
 ///   be read from chain by looking up the account. If they are not present on
@@ -96,6 +229,41 @@ pub fn verify_account_ownership(
     utils::verify_account_ownership_proof(&public_data.keys, public_data.threshold, &proof, to_sign)
 }
 
+/// Function for verifying that the identity a credential is derived from is
+/// not revoked by the identity provider. The arguments are
+/// - transcript - the transcript the proof is bound to
+/// - global_context - the global context the credential was deployed with
+/// - ip_info - the identity provider the credential is derived from
+/// - status_list - the status list of the identity provider
+/// - commitments - the commitments of the credential, as recorded on chain
+/// - proof - the prover's proof
+///
+/// The function outputs a bool, indicating whether the status list is signed
+/// by the identity provider, and the proof is correct with respect to it.
+pub fn verify_credential_not_revoked<P: Pairing, C: Curve<Scalar = P::ScalarField>>(
+    transcript: &RandomOracle,
+    global_context: &GlobalContext<C>,
+    ip_info: &IpInfo<P>,
+    status_list: &SignedStatusList<C>,
+    commitments: &CredentialDeploymentCommitments<C>,
+    proof: &NonRevocationProof<C>,
+) -> bool {
+    // The commitment to the constant coefficient of the sharing polynomial
+    // is the commitment to IdCredSec.
+    let cmm_id_cred_sec = match commitments.cmm_id_cred_sec_sharing_coeff.first() {
+        Some(cmm) => cmm,
+        None => return false,
+    };
+    revocation::verify_status_list(ip_info, status_list)
+        && revocation::verify_non_revocation(
+            transcript,
+            &global_context.on_chain_commitment_key,
+            &status_list.status_list,
+            cmm_id_cred_sec,
+            proof,
+        )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,4 +341,91 @@ mod tests {
             assert!(false, "Failed to produce proof.");
         };
     }
+
+    #[test]
+    fn test_verify_attribute_in_set() {
+        let mut csprng = thread_rng();
+        let global = GlobalContext::<G1>::generate(String::from("genesis_string"));
+        let keys = global.on_chain_commitment_key;
+        let gens = global.bulletproof_generators();
+        let set: Vec<_> = ["DK", "SE", "NO", "FI", "IS"]
+            .iter()
+            .map(|v| AttributeKind(v.to_string()))
+            .collect();
+        let attribute = AttributeKind("NO".to_string());
+        let value = Value::<G1>::new(attribute.to_field_element());
+        let (commitment, randomness) = keys.commit(&value, &mut csprng);
+        let proof = prove_attribute_in_set(&gens, &keys, &attribute, &set, &randomness)
+            .expect("Failed to produce proof.");
+        assert_eq!(
+            verify_attribute_in_set(&keys, &gens, &set, &commitment, &proof),
+            Ok(()),
+            "Incorrect set membership proof."
+        );
+        assert!(
+            verify_attribute_not_in_set(&keys, &gens, &set, &commitment, &proof).is_err(),
+            "Membership proof accepted as non-membership proof."
+        );
+        assert!(
+            verify_attribute_in_set(&keys, &gens, &set[1..], &commitment, &proof).is_err(),
+            "Proof accepted for a different set."
+        );
+        assert!(
+            prove_attribute_not_in_set(&gens, &keys, &attribute, &set, &randomness).is_none(),
+            "Produced a non-membership proof for a member."
+        );
+    }
+
+    #[test]
+    fn test_verify_attribute_not_in_set() {
+        let mut csprng = thread_rng();
+        let global = GlobalContext::<G1>::generate(String::from("genesis_string"));
+        let keys = global.on_chain_commitment_key;
+        let gens = global.bulletproof_generators();
+        let set: Vec<_> = ["DK", "SE", "NO", "FI", "IS"]
+            .iter()
+            .map(|v| AttributeKind(v.to_string()))
+            .collect();
+        let attribute = AttributeKind("DE".to_string());
+        let value = Value::<G1>::new(attribute.to_field_element());
+        let (commitment, randomness) = keys.commit(&value, &mut csprng);
+        let statement = AttributeStatement::NotInSet {
+            attribute_tag: AttributeTag(4),
+            set:           set.clone(),
+        };
+        let transcript = RandomOracle::domain("test_statement");
+        let statement = prove_attribute_statement(
+            &mut transcript.split(),
+            &gens,
+            &keys,
+            &statement,
+            &attribute,
+            &randomness,
+        )
+        .expect("Failed to produce proof.");
+        assert!(
+            verify_attribute_statement(
+                &mut transcript.split(),
+                &keys,
+                &gens,
+                &statement,
+                &commitment
+            ),
+            "Incorrect set non-membership proof."
+        );
+        assert!(
+            !verify_attribute_statement(
+                &mut RandomOracle::domain("other_statement"),
+                &keys,
+                &gens,
+                &statement,
+                &commitment
+            ),
+            "Proof verified in a different transcript."
+        );
+        assert!(
+            prove_attribute_in_set(&gens, &keys, &attribute, &set, &randomness).is_none(),
+            "Produced a membership proof for a non-member."
+        );
+    }
 }
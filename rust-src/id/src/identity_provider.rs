@@ -1,6 +1,7 @@
 //! Functionality needed by the identity provider. This gathers together the
 //! primitives from the rest of the library into a convenient package.
 use crate::{
+    pvss,
     secret_sharing::Threshold,
     sigma_protocols::{com_enc_eq, com_eq, com_eq_different_groups, common::*, dlog},
     types::*,
@@ -29,6 +30,7 @@ pub enum Reason {
     IllegalAttributeRequirements,
     TooManyAttributes,
     IncorrectProof,
+    InvalidRenewal,
 }
 
 impl std::fmt::Display for Reason {
@@ -44,12 +46,14 @@ impl std::fmt::Display for Reason {
             IllegalAttributeRequirements => write!(f, "Illegal attributes."),
             TooManyAttributes => write!(f, "Too many attributes for the given public key."),
             IncorrectProof => write!(f, "Zero knowledge proof does not verify."),
+            InvalidRenewal => write!(
+                f,
+                "Renewal is not for the identity object or does not extend its validity."
+            ),
         }
     }
 }
 
-/// FIXME: This function does not check that the anonymity revocation
-/// parameters make sense.
 /// Validate all the proofs in an identity object request.
 pub fn validate_request<P: Pairing, C: Curve<Scalar = P::ScalarField>>(
     pre_id_obj: &PreIdentityObject<P, C>,
@@ -141,21 +145,23 @@ pub fn validate_request<P: Pairing, C: Curve<Scalar = P::ScalarField>>(
 
     // We also need to check that the threshold is actually equal to
     // the number of coefficients in the sharing polynomial
-    // (corresponding to the degree+1)
-    if rt_usize != pre_id_obj.cmm_prf_sharing_coeff.len() {
+    // (corresponding to the degree+1). Since the threshold is at least 1 this
+    // also ensures that there is a commitment to the PRF key.
+    let share_cmms = pvss::share_commitments(
+        revocation_threshold,
+        pre_id_obj.ip_ar_data.keys(),
+        &pre_id_obj.cmm_prf_sharing_coeff,
+    )
+    .map_err(|_| Reason::WrongArParameters)?;
+
+    // Each share must be proved to be in range so that it can be decrypted.
+    if pre_id_obj.poks.bulletproofs.len() != pre_id_obj.ip_ar_data.len() {
         return Err(Reason::WrongArParameters);
     }
 
     // ar commitment key
     let ar_ck = &context.global_context.on_chain_commitment_key;
 
-    // The commitment to the PRF key to the identity providers
-    // must have at least one value.
-    // FIXME: Rework the choice of data-structure so that this is implicit.
-    if pre_id_obj.cmm_prf_sharing_coeff.is_empty() {
-        return Err(Reason::WrongArParameters);
-    }
-
     // Verify that the two commitments to the PRF key are the same.
     let verifier_prf_same = com_eq_different_groups::ComEqDiffGroups {
         commitment_1: pre_id_obj.cmm_prf,
@@ -171,7 +177,7 @@ pub fn validate_request<P: Pairing, C: Curve<Scalar = P::ScalarField>>(
     let h_in_exponent = *context.global_context.encryption_in_exponent_generator();
     let prf_verification = compute_prf_sharing_verifier(
         ar_ck,
-        &pre_id_obj.cmm_prf_sharing_coeff,
+        &share_cmms,
         &pre_id_obj.ip_ar_data,
         &context.ars_infos,
         &h_in_exponent,
@@ -246,6 +252,97 @@ pub fn validate_request<P: Pairing, C: Curve<Scalar = P::ScalarField>>(
     }
 }
 
+/// Signing with the identity provider's PS key, i.e., signing identity
+/// objects. Implementations can keep the key outside of the process, e.g., in
+/// an external key store.
+pub trait IpSigner<P: Pairing> {
+    type Error: std::fmt::Display + std::fmt::Debug;
+
+    /// Sign the message with the secret key matching `ip_verify_key` of the
+    /// identity provider.
+    fn sign_unknown_message(
+        &self,
+        message: &ps_sig::UnknownMessage<P>,
+    ) -> Result<ps_sig::Signature<P>, Self::Error>;
+}
+
+impl<P: Pairing> IpSigner<P> for ps_sig::SecretKey<P> {
+    type Error = std::convert::Infallible;
+
+    fn sign_unknown_message(
+        &self,
+        message: &ps_sig::UnknownMessage<P>,
+    ) -> Result<ps_sig::Signature<P>, Self::Error> {
+        let mut csprng = thread_rng();
+        Ok(self.sign_unknown_message(message, &mut csprng))
+    }
+}
+
+/// Signing with the identity provider's ed25519 key, i.e., signing initial
+/// credentials. Implementations can keep the key outside of the process,
+/// e.g., in an HSM.
+pub trait IpCdiSigner {
+    type Error: std::fmt::Display + std::fmt::Debug;
+
+    /// Sign the message with the secret key matching `ip_cdi_verify_key` of
+    /// the identity provider.
+    fn sign(&self, message: &[u8]) -> Result<ed25519_dalek::Signature, Self::Error>;
+}
+
+impl IpCdiSigner for ed25519_dalek::Keypair {
+    type Error = std::convert::Infallible;
+
+    fn sign(&self, message: &[u8]) -> Result<ed25519_dalek::Signature, Self::Error> {
+        let expanded_sk = ed25519_dalek::ExpandedSecretKey::from(&self.secret);
+        Ok(expanded_sk.sign(message, &self.public))
+    }
+}
+
+/// An ed25519 secret key of the identity provider together with its public
+/// key.
+struct CdiKeys<'a> {
+    secret: &'a ed25519_dalek::SecretKey,
+    public: &'a ed25519_dalek::PublicKey,
+}
+
+impl<'a> IpCdiSigner for CdiKeys<'a> {
+    type Error = std::convert::Infallible;
+
+    fn sign(&self, message: &[u8]) -> Result<ed25519_dalek::Signature, Self::Error> {
+        let expanded_sk = ed25519_dalek::ExpandedSecretKey::from(self.secret);
+        Ok(expanded_sk.sign(message, self.public))
+    }
+}
+
+#[derive(Debug)]
+/// Reasons why signing an identity object with an [IpSigner] can fail.
+pub enum SigningError<E> {
+    /// The request is invalid.
+    Invalid(Reason),
+    /// The signer failed.
+    Signer(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for SigningError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SigningError::Invalid(reason) => reason.fmt(f),
+            SigningError::Signer(e) => write!(f, "Signing failed: {}", e),
+        }
+    }
+}
+
+impl<E: std::fmt::Display + std::fmt::Debug> std::error::Error for SigningError<E> {}
+
+impl From<SigningError<std::convert::Infallible>> for Reason {
+    fn from(e: SigningError<std::convert::Infallible>) -> Self {
+        match e {
+            SigningError::Invalid(reason) => reason,
+            SigningError::Signer(e) => match e {},
+        }
+    }
+}
+
 /// Sign the given pre-identity-object to produce an identity object.
 pub fn sign_identity_object<
     P: Pairing,
@@ -257,6 +354,27 @@ pub fn sign_identity_object<
     alist: &AttributeList<C::Scalar, AttributeType>,
     ip_secret_key: &ps_sig::SecretKey<P>,
 ) -> Result<ps_sig::Signature<P>, Reason> {
+    Ok(sign_identity_object_with_signer(
+        pre_id_obj,
+        ip_info,
+        alist,
+        ip_secret_key,
+    )?)
+}
+
+/// Sign the given pre-identity-object to produce an identity object, using
+/// the given signer instead of the secret key.
+pub fn sign_identity_object_with_signer<
+    P: Pairing,
+    AttributeType: Attribute<P::ScalarField>,
+    C: Curve<Scalar = P::ScalarField>,
+    S: IpSigner<P> + ?Sized,
+>(
+    pre_id_obj: &PreIdentityObject<P, C>,
+    ip_info: &IpInfo<P>,
+    alist: &AttributeList<C::Scalar, AttributeType>,
+    signer: &S,
+) -> Result<ps_sig::Signature<P>, SigningError<S::Error>> {
     let choice_ar_handles = pre_id_obj.choice_ar_parameters.ar_identities.clone();
     let message: ps_sig::UnknownMessage<P> = compute_message(
         &pre_id_obj.cmm_prf,
@@ -265,15 +383,105 @@ pub fn sign_identity_object<
         &choice_ar_handles,
         &alist,
         &ip_info.ip_verify_key,
-    )?;
-    let mut csprng = thread_rng();
-    // FIXME: Pass in csprng here.
-    Ok(ip_secret_key.sign_unknown_message(&message, &mut csprng))
+    )
+    .map_err(SigningError::Invalid)?;
+    signer
+        .sign_unknown_message(&message)
+        .map_err(SigningError::Signer)
+}
+
+/// Validate a request to renew the identity object. The request must be for
+/// this identity object, extend its validity, and prove knowledge of its
+/// IdCredSec and prf key.
+pub fn validate_renewal_request<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+>(
+    request: &IdentityRenewalRequest<C>,
+    id_object: &IdentityObject<P, C, AttributeType>,
+    global_context: &GlobalContext<C>,
+    ip_info: &IpInfo<P>,
+) -> Result<(), Reason> {
+    if request.id_cred_pub != id_object.pre_identity_object.pub_info_for_ip.id_cred_pub
+        || request.valid_to <= id_object.alist.valid_to
+    {
+        return Err(Reason::InvalidRenewal);
+    }
+    let (mut transcript, verifier) =
+        utils::renewal_statement(global_context, ip_info, id_object, request.valid_to);
+    let proof = SigmaProof {
+        challenge: request.proof.challenge,
+        witness:   AndWitness {
+            w1: request.proof.id_cred_sec_witness,
+            w2: request.proof.prf_key_witness,
+        },
+    };
+    if verify(&mut transcript, &verifier, &proof) {
+        Ok(())
+    } else {
+        Err(Reason::IncorrectProof)
+    }
+}
+
+/// Validate the request to renew the identity object, and sign the renewed
+/// identity object. Only the `validTo` date of the attribute list changes,
+/// the attributes and the pre-identity object are kept. In particular the
+/// anonymity revocation data is the same, so credentials derived from the
+/// renewed identity object are linked to it in the same way as credentials
+/// derived from the original.
+pub fn renew_identity_object<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+>(
+    request: &IdentityRenewalRequest<C>,
+    id_object: IdentityObject<P, C, AttributeType>,
+    global_context: &GlobalContext<C>,
+    ip_info: &IpInfo<P>,
+    ip_secret_key: &ps_sig::SecretKey<P>,
+) -> Result<IdentityObject<P, C, AttributeType>, Reason> {
+    Ok(renew_identity_object_with_signer(
+        request,
+        id_object,
+        global_context,
+        ip_info,
+        ip_secret_key,
+    )?)
+}
+
+/// Renew the identity object, using the given signer instead of the secret
+/// key.
+pub fn renew_identity_object_with_signer<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+    S: IpSigner<P> + ?Sized,
+>(
+    request: &IdentityRenewalRequest<C>,
+    id_object: IdentityObject<P, C, AttributeType>,
+    global_context: &GlobalContext<C>,
+    ip_info: &IpInfo<P>,
+    signer: &S,
+) -> Result<IdentityObject<P, C, AttributeType>, SigningError<S::Error>> {
+    validate_renewal_request(request, &id_object, global_context, ip_info)
+        .map_err(SigningError::Invalid)?;
+    let alist = AttributeList {
+        valid_to: request.valid_to,
+        ..id_object.alist
+    };
+    let signature =
+        sign_identity_object_with_signer(&id_object.pre_identity_object, ip_info, &alist, signer)?;
+    Ok(IdentityObject {
+        pre_identity_object: id_object.pre_identity_object,
+        alist,
+        signature,
+    })
 }
 
 fn compute_prf_sharing_verifier<C: Curve>(
     ar_commitment_key: &CommitmentKey<C>,
-    cmm_sharing_coeff: &[Commitment<C>],
+    share_cmms: &BTreeMap<ArIdentity, Commitment<C>>,
     ip_ar_data: &BTreeMap<ArIdentity, IpArData<C>>,
     known_ars: &BTreeMap<ArIdentity, ArInfo<C>>,
     encryption_in_exponent_generator: &C,
@@ -282,7 +490,7 @@ fn compute_prf_sharing_verifier<C: Curve>(
     let mut witnesses = Vec::with_capacity(ip_ar_data.len());
 
     for (ar_id, ar_data) in ip_ar_data.iter() {
-        let cmm_share = utils::commitment_to_share(&ar_id.to_scalar::<C>(), cmm_sharing_coeff);
+        let cmm_share = *share_cmms.get(ar_id)?;
         // finding the right encryption key
 
         // Take linear combination of ciphers
@@ -361,12 +569,37 @@ pub fn create_initial_cdi<
     expiry: TransactionTime,
     ip_cdi_secret_key: &ed25519_dalek::SecretKey,
 ) -> InitialCredentialDeploymentInfo<C, AttributeType> {
+    let signer = CdiKeys {
+        secret: ip_cdi_secret_key,
+        public: &ip_info.ip_cdi_verify_key,
+    };
+    match create_initial_cdi_with_signer(ip_info, pub_info_for_ip, alist, expiry, &signer) {
+        Ok(icdi) => icdi,
+        Err(e) => match e {},
+    }
+}
+
+/// Create the initial credential, signing it with the given signer instead of
+/// the secret key.
+pub fn create_initial_cdi_with_signer<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+    S: IpCdiSigner + ?Sized,
+>(
+    ip_info: &IpInfo<P>,
+    pub_info_for_ip: PublicInformationForIp<C>,
+    alist: &AttributeList<C::Scalar, AttributeType>,
+    expiry: TransactionTime,
+    signer: &S,
+) -> Result<InitialCredentialDeploymentInfo<C, AttributeType>, S::Error> {
     // The initial policy is empty, apart from the expiry date of the credential.
     let policy: Policy<C, AttributeType> = Policy {
         valid_to:   alist.valid_to,
         created_at: alist.created_at,
         policy_vec: BTreeMap::new(),
         _phantom:   Default::default(),
+        statements: Vec::new(),
     };
     let cred_values = InitialCredentialDeploymentValues {
         reg_id: pub_info_for_ip.reg_id,
@@ -375,31 +608,27 @@ pub fn create_initial_cdi<
         cred_account: pub_info_for_ip.vk_acc,
     };
 
-    let sig = sign_initial_cred_values(&cred_values, expiry, ip_info, &ip_cdi_secret_key);
-    InitialCredentialDeploymentInfo {
+    let sig = sign_initial_cred_values(&cred_values, expiry, signer)?;
+    Ok(InitialCredentialDeploymentInfo {
         values: cred_values,
         sig,
-    }
+    })
 }
 
 fn sign_initial_cred_values<
-    P: Pairing,
-    C: Curve<Scalar = P::ScalarField>,
+    C: Curve,
     AttributeType: Attribute<C::Scalar>,
+    S: IpCdiSigner + ?Sized,
 >(
     initial_cred_values: &InitialCredentialDeploymentValues<C, AttributeType>,
     expiry: TransactionTime,
-    ip_info: &IpInfo<P>,
-    ip_cdi_secret_key: &ed25519_dalek::SecretKey,
-) -> IpCdiSignature {
+    signer: &S,
+) -> Result<IpCdiSignature, S::Error> {
     let mut hasher = Sha256::new();
     hasher.update(&to_bytes(&expiry));
     hasher.update(&to_bytes(&initial_cred_values));
     let to_sign = hasher.finalize();
-    let expanded_sk = ed25519_dalek::ExpandedSecretKey::from(ip_cdi_secret_key);
-    expanded_sk
-        .sign(to_sign.as_ref(), &ip_info.ip_cdi_verify_key)
-        .into()
+    Ok(signer.sign(to_sign.as_ref())?.into())
 }
 
 pub fn compute_message<P: Pairing, AttributeType: Attribute<P::ScalarField>>(
@@ -482,8 +711,17 @@ pub fn compute_message<P: Pairing, AttributeType: Attribute<P::ScalarField>>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{constants::ArCurve, test::*};
-    use crypto_common::types::{KeyIndex, KeyPair};
+    use crate::{
+        account_holder::{create_credential, generate_renewal_request},
+        anonymity_revoker::reveal_id_cred_pub,
+        chain::verify_cdi,
+        constants::{ArCurve, IpPairing},
+        test::*,
+    };
+    use crypto_common::{
+        serialize_deserialize,
+        types::{KeyIndex, KeyPair},
+    };
     use ff::Field;
     use pedersen_scheme::{CommitmentKey, Value as PedersenValue};
     use std::collections::btree_map::BTreeMap;
@@ -580,6 +818,77 @@ mod tests {
         assert!(ver_ok.is_ok());
     }
 
+    /// A signer that keeps the key elsewhere, and is unavailable.
+    struct UnavailableSigner;
+
+    impl IpSigner<IpPairing> for UnavailableSigner {
+        type Error = &'static str;
+
+        fn sign_unknown_message(
+            &self,
+            _message: &ps_sig::UnknownMessage<IpPairing>,
+        ) -> Result<ps_sig::Signature<IpPairing>, Self::Error> {
+            Err("Key store unavailable.")
+        }
+    }
+
+    /// Check that signing via the signer traits agrees with signing with the
+    /// keys, and that errors of the signer are reported.
+    #[test]
+    fn test_signers() {
+        let max_attrs = 10;
+        let num_ars = 4;
+        let mut csprng = thread_rng();
+        let IpData {
+            public_ip_info: ip_info,
+            ip_secret_key,
+            ip_cdi_secret_key,
+        } = test_create_ip_info(&mut csprng, num_ars, max_attrs);
+        let global_ctx = GlobalContext::<ArCurve>::generate(String::from("genesis_string"));
+        let (ars_infos, _) =
+            test_create_ars(&global_ctx.on_chain_commitment_key.g, num_ars, &mut csprng);
+        let aci = test_create_aci(&mut csprng);
+        let acc_data = InitialAccountData {
+            keys:      {
+                let mut keys = BTreeMap::new();
+                keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                keys
+            },
+            threshold: SignatureThreshold(1),
+        };
+        let (_, pio, _) =
+            test_create_pio(&aci, &ip_info, &ars_infos, &global_ctx, num_ars, &acc_data);
+        let attrs = test_create_attributes();
+
+        assert!(sign_identity_object_with_signer(&pio, &ip_info, &attrs, &ip_secret_key).is_ok());
+        match sign_identity_object_with_signer(&pio, &ip_info, &attrs, &UnavailableSigner) {
+            Err(SigningError::Signer(e)) => assert_eq!(e, "Key store unavailable."),
+            _ => panic!("Signing should fail with the signer's error."),
+        }
+
+        let keypair = ed25519_dalek::Keypair {
+            secret: ed25519_dalek::SecretKey::from_bytes(ip_cdi_secret_key.as_bytes())
+                .expect("Valid secret key."),
+            public: ip_info.ip_cdi_verify_key,
+        };
+        let icdi = create_initial_cdi(
+            &ip_info,
+            pio.pub_info_for_ip.clone(),
+            &attrs,
+            EXPIRY,
+            &ip_cdi_secret_key,
+        );
+        let icdi_signer = create_initial_cdi_with_signer(
+            &ip_info,
+            pio.pub_info_for_ip.clone(),
+            &attrs,
+            EXPIRY,
+            &keypair,
+        )
+        .expect("Signing with a keypair cannot fail.");
+        assert_eq!(to_bytes(&icdi), to_bytes(&icdi_signer));
+    }
+
     // /// Check IP's verify_credentials fail for wrong id_cred_sec
     // proof-of-knowledge.
     // #[test]
@@ -717,4 +1026,211 @@ mod tests {
             "Verify_credentials did not fail with invalid PRF commitment"
         );
     }
+
+    /// Test that validate_request checks the sharing of the PRF key publicly.
+    #[test]
+    fn test_validate_request_fail_sharing() {
+        let max_attrs = 10;
+        let num_ars = 4;
+        let mut csprng = thread_rng();
+        let IpData {
+            public_ip_info: ip_info,
+            ..
+        } = test_create_ip_info(&mut csprng, num_ars, max_attrs);
+        let global_ctx = GlobalContext::<ArCurve>::generate(String::from("genesis_string"));
+        let (ars_infos, _) =
+            test_create_ars(&global_ctx.on_chain_commitment_key.g, num_ars, &mut csprng);
+        let aci = test_create_aci(&mut csprng);
+        let acc_data = InitialAccountData {
+            keys:      {
+                let mut keys = BTreeMap::new();
+                keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                keys
+            },
+            threshold: SignatureThreshold(1),
+        };
+        let (context, pio, _) =
+            test_create_pio(&aci, &ip_info, &ars_infos, &global_ctx, num_ars, &acc_data);
+        assert_eq!(validate_request(&pio, context), Ok(()));
+        let copy_pio = || serialize_deserialize(&pio).expect("Serialization roundtrip succeeds.");
+
+        // A sharing polynomial of the wrong degree.
+        let mut wrong_degree = copy_pio();
+        wrong_degree.cmm_prf_sharing_coeff.pop();
+        assert_eq!(
+            validate_request(&wrong_degree, context),
+            Err(Reason::WrongArParameters)
+        );
+
+        // Fewer anonymity revokers than the threshold.
+        let mut too_few_ars = copy_pio();
+        let threshold = usize::from(pio.choice_ar_parameters.threshold);
+        let removed = pio
+            .ip_ar_data
+            .keys()
+            .skip(threshold - 1)
+            .copied()
+            .collect::<Vec<_>>();
+        for ar_id in removed.iter() {
+            too_few_ars.choice_ar_parameters.ar_identities.remove(ar_id);
+            too_few_ars.ip_ar_data.remove(ar_id);
+        }
+        too_few_ars.poks.bulletproofs.truncate(threshold - 1);
+        assert_eq!(
+            validate_request(&too_few_ars, context),
+            Err(Reason::WrongArParameters)
+        );
+
+        // A missing range proof for an encrypted share.
+        let mut missing_range_proof = copy_pio();
+        missing_range_proof.poks.bulletproofs.pop();
+        assert_eq!(
+            validate_request(&missing_range_proof, context),
+            Err(Reason::WrongArParameters)
+        );
+
+        // Shares encrypted to the wrong anonymity revokers.
+        let mut swapped_shares = copy_pio();
+        let ar_ids = pio.ip_ar_data.keys().copied().collect::<Vec<_>>();
+        for (from, to) in ar_ids.iter().zip(ar_ids.iter().skip(1)) {
+            swapped_shares
+                .ip_ar_data
+                .get_mut(to)
+                .expect("The anonymity revoker exists.")
+                .enc_prf_key_share = pio.ip_ar_data[from].enc_prf_key_share;
+        }
+        assert_eq!(
+            validate_request(&swapped_shares, context),
+            Err(Reason::IncorrectProof)
+        );
+    }
+
+    /// Check that a renewed identity object has the new validity, that
+    /// credentials can be created from it, and that they are revoked to the
+    /// same idCredPub as the original identity object.
+    #[test]
+    fn test_renewal() {
+        let max_attrs = 10;
+        let num_ars = 4;
+        let mut csprng = thread_rng();
+        let IpData {
+            public_ip_info: ip_info,
+            ip_secret_key,
+            ip_cdi_secret_key,
+        } = test_create_ip_info(&mut csprng, num_ars, max_attrs);
+        let global_ctx = GlobalContext::<ArCurve>::generate(String::from("genesis_string"));
+        let (ars_infos, ars_secret) =
+            test_create_ars(&global_ctx.on_chain_commitment_key.g, num_ars, &mut csprng);
+        let aci = test_create_aci(&mut csprng);
+        let acc_data = InitialAccountData {
+            keys:      {
+                let mut keys = BTreeMap::new();
+                keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                keys
+            },
+            threshold: SignatureThreshold(1),
+        };
+        let (context, pio, randomness) =
+            test_create_pio(&aci, &ip_info, &ars_infos, &global_ctx, num_ars, &acc_data);
+        let alist = test_create_attributes();
+        let (signature, _) = verify_credentials(
+            &pio,
+            context,
+            &alist,
+            EXPIRY,
+            &ip_secret_key,
+            &ip_cdi_secret_key,
+        )
+        .expect("The request is valid.");
+        let id_object = IdentityObject {
+            pre_identity_object: pio,
+            alist,
+            signature,
+        };
+
+        // Renewals must extend the validity.
+        let request = generate_renewal_request(
+            &global_ctx,
+            &ip_info,
+            &id_object,
+            &aci,
+            id_object.alist.valid_to,
+        )
+        .expect("Generating the renewal request should succeed.");
+        assert_eq!(
+            validate_renewal_request(&request, &id_object, &global_ctx, &ip_info),
+            Err(Reason::InvalidRenewal)
+        );
+
+        // Only the holder of the secrets can renew.
+        let valid_to = YearMonth::new(2024, 5).unwrap();
+        let other_aci = test_create_aci(&mut csprng);
+        let mut request =
+            generate_renewal_request(&global_ctx, &ip_info, &id_object, &other_aci, valid_to)
+                .expect("Generating the renewal request should succeed.");
+        request.id_cred_pub = id_object.pre_identity_object.pub_info_for_ip.id_cred_pub;
+        assert_eq!(
+            validate_renewal_request(&request, &id_object, &global_ctx, &ip_info),
+            Err(Reason::IncorrectProof)
+        );
+
+        let request = generate_renewal_request(&global_ctx, &ip_info, &id_object, &aci, valid_to)
+            .expect("Generating the renewal request should succeed.");
+        let request = serialize_deserialize(&request).expect("Request should deserialize.");
+        let created_at = id_object.alist.created_at;
+        let renewed =
+            renew_identity_object(&request, id_object, &global_ctx, &ip_info, &ip_secret_key)
+                .expect("Renewal should succeed.");
+        assert_eq!(renewed.alist.valid_to, valid_to);
+        assert_eq!(renewed.alist.created_at, created_at);
+
+        // The signature retrieval randomness of the original object is used.
+        let id_use_data = IdObjectUseData { aci, randomness };
+        let policy = Policy {
+            valid_to,
+            created_at,
+            policy_vec: BTreeMap::new(),
+            _phantom: Default::default(),
+            statements: Vec::new(),
+        };
+        let cred_data = CredentialData {
+            keys:      {
+                let mut keys = BTreeMap::new();
+                keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                keys
+            },
+            threshold: SignatureThreshold(1),
+        };
+        let new_or_existing = either::Either::Left(EXPIRY);
+        let (cdi, _) = create_credential(
+            context,
+            &renewed,
+            &id_use_data,
+            1,
+            policy,
+            &[],
+            &cred_data,
+            &new_or_existing,
+            None,
+        )
+        .expect("Should generate the credential successfully.");
+        assert_eq!(
+            verify_cdi(&global_ctx, &ip_info, &ars_infos, &cdi, &new_or_existing),
+            Ok(())
+        );
+
+        let shares = ars_secret
+            .iter()
+            .map(|(ar_id, key)| {
+                (
+                    *ar_id,
+                    key.decrypt(&cdi.values.ar_data[ar_id].enc_id_cred_pub_share),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reveal_id_cred_pub(&shares),
+            renewed.pre_identity_object.pub_info_for_ip.id_cred_pub
+        );
+    }
 }
@@ -3,6 +3,7 @@ extern crate itertools;
 
 pub mod account_holder;
 pub mod anonymity_revoker;
+pub mod ar_committee;
 pub mod chain;
 pub mod constants;
 #[cfg(feature = "ffi")]
@@ -10,6 +11,9 @@ mod ffi;
 pub mod id_prover;
 pub mod id_verifier;
 pub mod identity_provider;
+pub mod presentation;
+pub mod pvss;
+pub mod revocation;
 pub mod secret_sharing;
 pub mod sigma_protocols;
 pub mod types;
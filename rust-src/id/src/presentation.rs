@@ -0,0 +1,1122 @@
+//! A protocol for presenting properties of an on-chain credential to a
+//! verifier.
+//!
+//! The verifier sends a [PresentationRequest], which contains a nonce, the
+//! credential registration id of the credential the request is about, and a
+//! list of statements. The holder of the credential responds with a
+//! [Presentation] that contains one proof per statement. All proofs are
+//! produced with a transcript that includes the whole request, so a
+//! presentation cannot be replayed for a different nonce, a different
+//! credential, or a different set of statements.
+use crate::{
+    id_prover, id_verifier,
+    revocation::{NonRevocationProof, SignedStatusList},
+    sigma_protocols::{
+        common::{prove, verify},
+        dlog,
+    },
+    types::*,
+};
+use anyhow::{anyhow, bail, ensure};
+use bulletproofs::{r1cs::R1CSProof, range_proof::RangeProof};
+use byteorder::ReadBytesExt;
+use core::fmt::{self, Display};
+use crypto_common::{types::Timestamp, *};
+use curve_arithmetic::{Curve, Pairing, Value};
+use pedersen_scheme::{Commitment, CommitmentKey as PedersenKey};
+use random_oracle::RandomOracle;
+use std::collections::btree_map::BTreeMap;
+
+/// Maximum length of the nonce in a presentation request, in bytes.
+pub const MAX_NONCE_SIZE: usize = 256;
+
+/// A nonce chosen by the verifier. It should be fresh for each request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresentationNonce {
+    pub nonce: Vec<u8>,
+}
+
+impl Serial for PresentationNonce {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        (self.nonce.len() as u16).serial(out);
+        out.write_all(&self.nonce)
+            .expect("Writing to buffer should succeed.");
+    }
+}
+
+impl Deserial for PresentationNonce {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let len: u16 = source.get()?;
+        ensure!(
+            usize::from(len) <= MAX_NONCE_SIZE,
+            "Nonce size of {} is too big. Maximum size is {}.",
+            len,
+            MAX_NONCE_SIZE
+        );
+        let mut nonce = vec![0; usize::from(len)];
+        source.read_exact(&mut nonce)?;
+        Ok(PresentationNonce { nonce })
+    }
+}
+
+impl SerdeSerialize for PresentationNonce {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer, {
+        serializer.serialize_str(&hex::encode(&self.nonce))
+    }
+}
+
+impl<'de> SerdeDeserialize<'de> for PresentationNonce {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>, {
+        let s = String::deserialize(deserializer)?;
+        let nonce = hex::decode(s).map_err(|e| serde::de::Error::custom(format!("{}", e)))?;
+        if nonce.len() <= MAX_NONCE_SIZE {
+            Ok(PresentationNonce { nonce })
+        } else {
+            Err(serde::de::Error::custom("Nonce length out of bounds."))
+        }
+    }
+}
+
+/// A statement about a credential that the verifier asks the holder to prove.
+/// Statements about attributes can only be made about attributes that are
+/// committed to in the credential, i.e., that are not revealed in its policy.
+#[derive(Debug, PartialEq, Eq, Clone, SerdeSerialize, SerdeDeserialize)]
+#[serde(tag = "type", content = "contents")]
+pub enum Statement<AttributeType> {
+    /// Reveal the value of the attribute.
+    #[serde(rename = "revealAttribute")]
+    RevealAttribute {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+    },
+    /// The attribute lies in the interval [lower, upper).
+    #[serde(rename = "attributeInRange")]
+    AttributeInRange {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "lower")]
+        lower:         AttributeType,
+        #[serde(rename = "upper")]
+        upper:         AttributeType,
+    },
+    /// The attribute is one of the values in the set.
+    #[serde(rename = "attributeInSet")]
+    AttributeInSet {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "set")]
+        set:           Vec<AttributeType>,
+    },
+    /// The attribute is none of the values in the set.
+    #[serde(rename = "attributeNotInSet")]
+    AttributeNotInSet {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "set")]
+        set:           Vec<AttributeType>,
+    },
+    /// The holder controls the keys of the credential.
+    #[serde(rename = "accountOwnership")]
+    AccountOwnership,
+    /// The identity the credential is derived from is not revoked, according
+    /// to a status list of the identity provider issued no earlier than the
+    /// given time.
+    #[serde(rename = "notRevoked")]
+    NotRevoked {
+        #[serde(rename = "issuedAfter")]
+        issued_after: Timestamp,
+    },
+}
+
+impl<AttributeType: Serial> Serial for Statement<AttributeType> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        match self {
+            Statement::RevealAttribute { attribute_tag } => {
+                out.write_u8(0).expect("Writing to buffer should succeed.");
+                out.put(attribute_tag);
+            }
+            Statement::AttributeInRange {
+                attribute_tag,
+                lower,
+                upper,
+            } => {
+                out.write_u8(1).expect("Writing to buffer should succeed.");
+                out.put(attribute_tag);
+                out.put(lower);
+                out.put(upper);
+            }
+            Statement::AttributeInSet { attribute_tag, set } => {
+                out.write_u8(2).expect("Writing to buffer should succeed.");
+                out.put(attribute_tag);
+                out.put(&(set.len() as u16));
+                serial_vector_no_length(set, out);
+            }
+            Statement::AttributeNotInSet { attribute_tag, set } => {
+                out.write_u8(3).expect("Writing to buffer should succeed.");
+                out.put(attribute_tag);
+                out.put(&(set.len() as u16));
+                serial_vector_no_length(set, out);
+            }
+            Statement::AccountOwnership => {
+                out.write_u8(4).expect("Writing to buffer should succeed.");
+            }
+            Statement::NotRevoked { issued_after } => {
+                out.write_u8(5).expect("Writing to buffer should succeed.");
+                out.put(issued_after);
+            }
+        }
+    }
+}
+
+impl<AttributeType: Deserial> Deserial for Statement<AttributeType> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        match source.read_u8()? {
+            0 => Ok(Statement::RevealAttribute {
+                attribute_tag: source.get()?,
+            }),
+            1 => Ok(Statement::AttributeInRange {
+                attribute_tag: source.get()?,
+                lower:         source.get()?,
+                upper:         source.get()?,
+            }),
+            2 => {
+                let attribute_tag = source.get()?;
+                let len: u16 = source.get()?;
+                let set = deserial_vector_no_length(source, usize::from(len))?;
+                Ok(Statement::AttributeInSet { attribute_tag, set })
+            }
+            3 => {
+                let attribute_tag = source.get()?;
+                let len: u16 = source.get()?;
+                let set = deserial_vector_no_length(source, usize::from(len))?;
+                Ok(Statement::AttributeNotInSet { attribute_tag, set })
+            }
+            4 => Ok(Statement::AccountOwnership),
+            5 => Ok(Statement::NotRevoked {
+                issued_after: source.get()?,
+            }),
+            n => bail!("Statement::deserial: Unsupported tag {}.", n),
+        }
+    }
+}
+
+/// A request from a verifier for a presentation of properties of an on-chain
+/// credential.
+#[derive(Debug, PartialEq, Eq, Clone, SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(
+    serialize = "C: Curve, AttributeType: SerdeSerialize",
+    deserialize = "C: Curve, AttributeType: SerdeDeserialize<'de>"
+))]
+pub struct PresentationRequest<C: Curve, AttributeType> {
+    /// Nonce chosen by the verifier.
+    #[serde(rename = "nonce")]
+    pub nonce:      PresentationNonce,
+    /// Credential registration id of the credential the statements are about.
+    #[serde(
+        rename = "credId",
+        serialize_with = "base16_encode",
+        deserialize_with = "base16_decode"
+    )]
+    pub cred_id:    C,
+    /// The statements the verifier wants proved.
+    #[serde(rename = "statements")]
+    pub statements: Vec<Statement<AttributeType>>,
+}
+
+impl<C: Curve, AttributeType: Serial> Serial for PresentationRequest<C, AttributeType> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        out.put(&self.nonce);
+        out.put(&self.cred_id);
+        out.put(&(self.statements.len() as u16));
+        serial_vector_no_length(&self.statements, out)
+    }
+}
+
+impl<C: Curve, AttributeType: Deserial> Deserial for PresentationRequest<C, AttributeType> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let nonce = source.get()?;
+        let cred_id = source.get()?;
+        let len: u16 = source.get()?;
+        let statements = deserial_vector_no_length(source, usize::from(len))?;
+        Ok(PresentationRequest {
+            nonce,
+            cred_id,
+            statements,
+        })
+    }
+}
+
+/// A proof of a single [Statement]. The variants correspond to the variants
+/// of [Statement].
+#[derive(Debug, PartialEq, Eq, Clone, SerdeSerialize, SerdeDeserialize)]
+#[serde(tag = "type", content = "contents")]
+#[serde(bound(
+    serialize = "C: Curve, AttributeType: SerdeSerialize",
+    deserialize = "C: Curve, AttributeType: SerdeDeserialize<'de>"
+))]
+pub enum StatementProof<C: Curve, AttributeType> {
+    /// The value of the attribute together with a proof of knowledge of the
+    /// randomness used to commit to it, see [reveal_attribute_dlog].
+    #[serde(rename = "revealAttribute")]
+    RevealAttribute {
+        #[serde(rename = "attribute")]
+        attribute: AttributeType,
+        #[serde(rename = "proof")]
+        proof:     dlog::Proof<C>,
+    },
+    #[serde(rename = "attributeInRange")]
+    AttributeInRange {
+        #[serde(
+            rename = "proof",
+            serialize_with = "base16_encode",
+            deserialize_with = "base16_decode"
+        )]
+        proof: RangeProof<C>,
+    },
+    #[serde(rename = "attributeInSet")]
+    AttributeInSet {
+        #[serde(
+            rename = "proof",
+            serialize_with = "base16_encode",
+            deserialize_with = "base16_decode"
+        )]
+        proof: R1CSProof<C>,
+    },
+    #[serde(rename = "attributeNotInSet")]
+    AttributeNotInSet {
+        #[serde(
+            rename = "proof",
+            serialize_with = "base16_encode",
+            deserialize_with = "base16_decode"
+        )]
+        proof: R1CSProof<C>,
+    },
+    #[serde(rename = "accountOwnership")]
+    AccountOwnership {
+        #[serde(rename = "proof")]
+        proof: AccountOwnershipProof,
+    },
+    /// The status list the proof is with respect to, and the proof.
+    #[serde(rename = "notRevoked")]
+    NotRevoked {
+        #[serde(rename = "statusList")]
+        status_list: SignedStatusList<C>,
+        #[serde(rename = "proof")]
+        proof:       NonRevocationProof<C>,
+    },
+}
+
+impl<C: Curve, AttributeType: Serial> Serial for StatementProof<C, AttributeType> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        match self {
+            StatementProof::RevealAttribute { attribute, proof } => {
+                out.write_u8(0).expect("Writing to buffer should succeed.");
+                out.put(attribute);
+                out.put(proof);
+            }
+            StatementProof::AttributeInRange { proof } => {
+                out.write_u8(1).expect("Writing to buffer should succeed.");
+                out.put(proof);
+            }
+            StatementProof::AttributeInSet { proof } => {
+                out.write_u8(2).expect("Writing to buffer should succeed.");
+                out.put(proof);
+            }
+            StatementProof::AttributeNotInSet { proof } => {
+                out.write_u8(3).expect("Writing to buffer should succeed.");
+                out.put(proof);
+            }
+            StatementProof::AccountOwnership { proof } => {
+                out.write_u8(4).expect("Writing to buffer should succeed.");
+                out.put(proof);
+            }
+            StatementProof::NotRevoked { status_list, proof } => {
+                out.write_u8(5).expect("Writing to buffer should succeed.");
+                out.put(status_list);
+                out.put(proof);
+            }
+        }
+    }
+}
+
+impl<C: Curve, AttributeType: Deserial> Deserial for StatementProof<C, AttributeType> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        match source.read_u8()? {
+            0 => Ok(StatementProof::RevealAttribute {
+                attribute: source.get()?,
+                proof:     source.get()?,
+            }),
+            1 => Ok(StatementProof::AttributeInRange {
+                proof: source.get()?,
+            }),
+            2 => Ok(StatementProof::AttributeInSet {
+                proof: source.get()?,
+            }),
+            3 => Ok(StatementProof::AttributeNotInSet {
+                proof: source.get()?,
+            }),
+            4 => Ok(StatementProof::AccountOwnership {
+                proof: source.get()?,
+            }),
+            5 => Ok(StatementProof::NotRevoked {
+                status_list: source.get()?,
+                proof:       source.get()?,
+            }),
+            n => bail!("StatementProof::deserial: Unsupported tag {}.", n),
+        }
+    }
+}
+
+/// The response to a [PresentationRequest]. It contains a proof of each of
+/// the requested statements, in the order of the request.
+#[derive(Debug, PartialEq, Eq, Clone, SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(
+    serialize = "C: Curve, AttributeType: SerdeSerialize",
+    deserialize = "C: Curve, AttributeType: SerdeDeserialize<'de>"
+))]
+pub struct Presentation<C: Curve, AttributeType> {
+    #[serde(rename = "proofs")]
+    pub proofs: Vec<StatementProof<C, AttributeType>>,
+}
+
+impl<C: Curve, AttributeType: Serial> Serial for Presentation<C, AttributeType> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        out.put(&(self.proofs.len() as u16));
+        serial_vector_no_length(&self.proofs, out)
+    }
+}
+
+impl<C: Curve, AttributeType: Deserial> Deserial for Presentation<C, AttributeType> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let len: u16 = source.get()?;
+        let proofs = deserial_vector_no_length(source, usize::from(len))?;
+        Ok(Presentation { proofs })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Reason why verification of a presentation failed.
+pub enum PresentationVerificationError {
+    /// The credential is not the one with the registration id of the request,
+    /// it is not issued by the given identity provider, or it is an initial
+    /// credential, which has no commitments.
+    CredentialMismatch,
+    /// The number of proofs does not match the number of statements.
+    ProofCount,
+    /// The statement with the given index is about an attribute that is not
+    /// committed to in the credential.
+    MissingCommitment(usize),
+    /// The proof with the given index is not a valid proof of the statement.
+    InvalidProof(usize),
+}
+
+impl Display for PresentationVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PresentationVerificationError::CredentialMismatch => write!(f, "CredentialMismatch"),
+            PresentationVerificationError::ProofCount => write!(f, "ProofCountMismatch"),
+            PresentationVerificationError::MissingCommitment(i) => {
+                write!(f, "MissingCommitment({})", i)
+            }
+            PresentationVerificationError::InvalidProof(i) => write!(f, "InvalidProof({})", i),
+        }
+    }
+}
+
+/// Construct the transcript shared by the prover and the verifier. It is bound
+/// to the global context and the whole request, in particular the nonce and
+/// the credential registration id.
+fn presentation_transcript<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    global_context: &GlobalContext<C>,
+    request: &PresentationRequest<C, AttributeType>,
+) -> RandomOracle {
+    let mut transcript = RandomOracle::domain("presentation");
+    transcript.append_message(b"global_context", global_context);
+    transcript.append_message(b"request", request);
+    transcript
+}
+
+/// The transcript used for the proof of the statement with the given index.
+fn statement_transcript(transcript: &RandomOracle, index: usize) -> RandomOracle {
+    let mut ro = transcript.split();
+    ro.append_message(b"statement_index", &(index as u64));
+    ro
+}
+
+/// The statement that the holder knows the randomness of the commitment `c` to
+/// the revealed attribute, i.e., the discrete logarithm of `c g^{-a}` with
+/// respect to `h`, where `a` is the attribute and `g`, `h` the commitment key.
+/// Unlike the randomness itself the proof does not allow the verifier to
+/// convince anybody else of the value of the attribute.
+fn reveal_attribute_dlog<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    keys: &PedersenKey<C>,
+    attribute: &AttributeType,
+    c: &Commitment<C>,
+) -> dlog::Dlog<C> {
+    dlog::Dlog {
+        public: c
+            .0
+            .minus_point(&keys.g.mul_by_scalar(&attribute.to_field_element())),
+        coeff:  keys.h,
+    }
+}
+
+/// Produce a presentation in response to a request. The arguments are
+/// - global_context - the global context the credential was deployed with
+/// - request - the request from the verifier
+/// - account - the address of the account the credential is on
+/// - attributes - the attribute list of the identity object the credential was
+///   created from
+/// - randomness - the randomness output when the credential was created
+/// - cred_data - the keys of the credential, used to prove account ownership
+/// - id_cred_sec - the IdCredSec of the identity the credential is derived
+///   from, used to prove that it is not revoked
+/// - commitments - the commitments of the credential
+/// - status_list - the latest status list of the identity provider, if any
+///
+/// The function fails if any of the statements is about an attribute that is
+/// not committed to in the credential, or if the attribute does not satisfy
+/// the statement. Statements that the identity is not revoked require a
+/// status list that is recent enough, and fail if the identity is revoked.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_presentation<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    global_context: &GlobalContext<C>,
+    request: &PresentationRequest<C, AttributeType>,
+    account: AccountAddress,
+    attributes: &BTreeMap<AttributeTag, AttributeType>,
+    randomness: &CommitmentsRandomness<C>,
+    cred_data: &CredentialData,
+    id_cred_sec: &Value<C>,
+    commitments: &CredentialDeploymentCommitments<C>,
+    status_list: Option<&SignedStatusList<C>>,
+) -> anyhow::Result<Presentation<C, AttributeType>> {
+    let keys = &global_context.on_chain_commitment_key;
+    let gens = global_context.bulletproof_generators();
+    let transcript = presentation_transcript(global_context, request);
+    let lookup =
+        |tag: &AttributeTag| match (attributes.get(tag), randomness.attributes_rand.get(tag)) {
+            (Some(attribute), Some(r)) => Ok((attribute, r)),
+            _ => Err(anyhow!(
+                "Attribute {} is not committed to in the credential.",
+                tag
+            )),
+        };
+    let mut csprng = rand::thread_rng();
+    let mut proofs = Vec::with_capacity(request.statements.len());
+    for (i, statement) in request.statements.iter().enumerate() {
+        let mut ro = statement_transcript(&transcript, i);
+        let proof = match statement {
+            Statement::RevealAttribute { attribute_tag } => {
+                let (attribute, r) = lookup(attribute_tag)?;
+                let commitment = keys.hide(&Value::<C>::new(attribute.to_field_element()), r);
+                let prover = reveal_attribute_dlog(keys, attribute, &commitment);
+                let secret = dlog::DlogSecret {
+                    secret: Value::new(*r.as_ref()),
+                };
+                let proof = prove(&mut ro, &prover, secret, &mut csprng)
+                    .expect("Proving knowledge of a discrete logarithm does not fail.");
+                StatementProof::RevealAttribute {
+                    attribute: attribute.clone(),
+                    proof,
+                }
+            }
+            Statement::AttributeInRange {
+                attribute_tag,
+                lower,
+                upper,
+            } => {
+                let (attribute, r) = lookup(attribute_tag)?;
+                let proof = id_prover::prove_attribute_in_range_with_transcript(
+                    &mut ro.split(),
+                    gens,
+                    keys,
+                    attribute,
+                    lower,
+                    upper,
+                    r,
+                );
+                // The range proof can be produced even if the attribute is not
+                // in the range, so we check it to not produce invalid proofs.
+                let commitment = keys.hide(&Value::<C>::new(attribute.to_field_element()), r);
+                match proof {
+                    Some(proof)
+                        if id_verifier::verify_attribute_range_with_transcript(
+                            &mut ro,
+                            keys,
+                            gens,
+                            lower,
+                            upper,
+                            &commitment,
+                            &proof,
+                        )
+                        .is_ok() =>
+                    {
+                        StatementProof::AttributeInRange { proof }
+                    }
+                    _ => bail!("Attribute {} is not in the range.", attribute_tag),
+                }
+            }
+            Statement::AttributeInSet { attribute_tag, set } => {
+                let (attribute, r) = lookup(attribute_tag)?;
+                match id_prover::prove_attribute_in_set_with_transcript(
+                    &mut ro, gens, keys, attribute, set, r,
+                ) {
+                    Some(proof) => StatementProof::AttributeInSet { proof },
+                    None => bail!("Attribute {} is not in the set.", attribute_tag),
+                }
+            }
+            Statement::AttributeNotInSet { attribute_tag, set } => {
+                let (attribute, r) = lookup(attribute_tag)?;
+                match id_prover::prove_attribute_not_in_set_with_transcript(
+                    &mut ro, gens, keys, attribute, set, r,
+                ) {
+                    Some(proof) => StatementProof::AttributeNotInSet { proof },
+                    None => bail!("Attribute {} is in the set.", attribute_tag),
+                }
+            }
+            Statement::AccountOwnership => {
+                let challenge = ro.get_challenge();
+                StatementProof::AccountOwnership {
+                    proof: id_prover::prove_ownership_of_account(
+                        cred_data,
+                        account,
+                        challenge.as_ref(),
+                    ),
+                }
+            }
+            Statement::NotRevoked { issued_after } => {
+                let status_list = match status_list {
+                    Some(status_list) if status_list.status_list.issued_at >= *issued_after => {
+                        status_list
+                    }
+                    _ => bail!("No status list issued after {}.", issued_after.millis),
+                };
+                match id_prover::prove_credential_not_revoked(
+                    &ro,
+                    global_context,
+                    &status_list.status_list,
+                    id_cred_sec,
+                    commitments,
+                    randomness,
+                ) {
+                    Some(proof) => StatementProof::NotRevoked {
+                        status_list: status_list.clone(),
+                        proof,
+                    },
+                    None => bail!("The identity is revoked."),
+                }
+            }
+        };
+        proofs.push(proof);
+    }
+    Ok(Presentation { proofs })
+}
+
+/// Verify a presentation with respect to a request. The arguments are
+/// - global_context - the global context of the chain
+/// - request - the request that was sent to the holder
+/// - ip_info - the identity provider the credential is derived from
+/// - account - the address of the account the credential is on
+/// - credential - the credential, as recorded on chain
+/// - presentation - the response from the holder
+///
+/// The identity provider, account and credential must be looked up on chain by
+/// the verifier, using the credential registration id in the request. The
+/// function checks that the credential has that registration id and is issued
+/// by the given identity provider, and fails for initial credentials since
+/// they have no commitments.
+pub fn verify_presentation<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+>(
+    global_context: &GlobalContext<C>,
+    request: &PresentationRequest<C, AttributeType>,
+    ip_info: &IpInfo<P>,
+    account: AccountAddress,
+    credential: &AccountCredentialWithoutProofs<C, AttributeType>,
+    presentation: &Presentation<C, AttributeType>,
+) -> Result<(), PresentationVerificationError> {
+    let (cred_key_info, commitments) = match credential {
+        AccountCredentialWithoutProofs::Normal { cdv, commitments }
+            if cdv.cred_id == request.cred_id && cdv.ip_identity == ip_info.ip_identity =>
+        {
+            (&cdv.cred_key_info, commitments)
+        }
+        _ => return Err(PresentationVerificationError::CredentialMismatch),
+    };
+    if request.statements.len() != presentation.proofs.len() {
+        return Err(PresentationVerificationError::ProofCount);
+    }
+    let keys = &global_context.on_chain_commitment_key;
+    let gens = global_context.bulletproof_generators();
+    let transcript = presentation_transcript(global_context, request);
+    let lookup = |i: usize, tag: &AttributeTag| {
+        commitments
+            .cmm_attributes
+            .get(tag)
+            .ok_or(PresentationVerificationError::MissingCommitment(i))
+    };
+    for (i, (statement, proof)) in request
+        .statements
+        .iter()
+        .zip(presentation.proofs.iter())
+        .enumerate()
+    {
+        let mut ro = statement_transcript(&transcript, i);
+        let valid = match (statement, proof) {
+            (
+                Statement::RevealAttribute { attribute_tag },
+                StatementProof::RevealAttribute { attribute, proof },
+            ) => {
+                let c = lookup(i, attribute_tag)?;
+                verify(&mut ro, &reveal_attribute_dlog(keys, attribute, c), proof)
+            }
+            (
+                Statement::AttributeInRange {
+                    attribute_tag,
+                    lower,
+                    upper,
+                },
+                StatementProof::AttributeInRange { proof },
+            ) => {
+                let c = lookup(i, attribute_tag)?;
+                id_verifier::verify_attribute_range_with_transcript(
+                    &mut ro, keys, gens, lower, upper, c, proof,
+                )
+                .is_ok()
+            }
+            (
+                Statement::AttributeInSet { attribute_tag, set },
+                StatementProof::AttributeInSet { proof },
+            ) => {
+                let c = lookup(i, attribute_tag)?;
+                id_verifier::verify_attribute_in_set_with_transcript(
+                    &mut ro, keys, gens, set, c, proof,
+                )
+                .is_ok()
+            }
+            (
+                Statement::AttributeNotInSet { attribute_tag, set },
+                StatementProof::AttributeNotInSet { proof },
+            ) => {
+                let c = lookup(i, attribute_tag)?;
+                id_verifier::verify_attribute_not_in_set_with_transcript(
+                    &mut ro, keys, gens, set, c, proof,
+                )
+                .is_ok()
+            }
+            (Statement::AccountOwnership, StatementProof::AccountOwnership { proof }) => {
+                let challenge = ro.get_challenge();
+                id_verifier::verify_account_ownership(
+                    cred_key_info,
+                    account,
+                    challenge.as_ref(),
+                    proof,
+                )
+            }
+            (
+                Statement::NotRevoked { issued_after },
+                StatementProof::NotRevoked { status_list, proof },
+            ) => {
+                status_list.status_list.issued_at >= *issued_after
+                    && id_verifier::verify_credential_not_revoked(
+                        &ro,
+                        global_context,
+                        ip_info,
+                        status_list,
+                        commitments,
+                        proof,
+                    )
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(PresentationVerificationError::InvalidProof(i));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::AttributeKind,
+        revocation::{sign_status_list, StatusList},
+        secret_sharing::Threshold,
+        test::test_create_ip_info,
+    };
+    use crypto_common::types::{KeyIndex, KeyPair};
+    use pairing::bls12_381::G1;
+    use rand::*;
+    use std::collections::hash_map::HashMap;
+
+    type ExampleRequest = PresentationRequest<G1, AttributeKind>;
+
+    struct Holder {
+        cred_id:     G1,
+        account:     AccountAddress,
+        attributes:  BTreeMap<AttributeTag, AttributeKind>,
+        randomness:  CommitmentsRandomness<G1>,
+        cred_data:   CredentialData,
+        id_cred_sec: Value<G1>,
+        commitments: CredentialDeploymentCommitments<G1>,
+    }
+
+    impl Holder {
+        /// The credential of the holder as recorded on chain.
+        fn credential(&self) -> AccountCredentialWithoutProofs<G1, AttributeKind> {
+            AccountCredentialWithoutProofs::Normal {
+                cdv:         CredentialDeploymentValues {
+                    cred_key_info: self.cred_data.get_cred_key_info(),
+                    cred_id:       self.cred_id,
+                    ip_identity:   IpIdentity(0),
+                    threshold:     Threshold(1),
+                    ar_data:       BTreeMap::new(),
+                    policy:        Policy {
+                        valid_to:   YearMonth::new(2022, 5).unwrap(),
+                        created_at: YearMonth::new(2020, 5).unwrap(),
+                        policy_vec: BTreeMap::new(),
+                        statements: Vec::new(),
+                        _phantom:   Default::default(),
+                    },
+                },
+                commitments: self.commitments.clone(),
+            }
+        }
+    }
+
+    /// Create the data of a holder of a credential with attributes 0 and 1
+    /// committed to.
+    fn create_holder(global_context: &GlobalContext<G1>) -> Holder {
+        let mut csprng = thread_rng();
+        let keys = &global_context.on_chain_commitment_key;
+        let mut attributes = BTreeMap::new();
+        attributes.insert(AttributeTag::from(0u8), AttributeKind::from(55));
+        attributes.insert(AttributeTag::from(1u8), AttributeKind::from(1990));
+        let mut attributes_rand = HashMap::new();
+        let mut cmm_attributes = BTreeMap::new();
+        for (tag, attribute) in attributes.iter() {
+            let (c, r) = keys.commit(&Value::<G1>::new(attribute.to_field_element()), &mut csprng);
+            attributes_rand.insert(*tag, r);
+            cmm_attributes.insert(*tag, c);
+        }
+        let (cmm_prf, prf_rand) = keys.commit(&Value::<G1>::generate(&mut csprng), &mut csprng);
+        let (cmm_cred_counter, cred_counter_rand) =
+            keys.commit(&Value::<G1>::generate(&mut csprng), &mut csprng);
+        let (cmm_max_accounts, max_accounts_rand) =
+            keys.commit(&Value::<G1>::generate(&mut csprng), &mut csprng);
+        let id_cred_sec = Value::<G1>::generate(&mut csprng);
+        let (cmm_id_cred_sec, id_cred_sec_rand) = keys.commit(&id_cred_sec, &mut csprng);
+        let cred_data = CredentialData {
+            keys:      {
+                let mut keys = BTreeMap::new();
+                keys.insert(KeyIndex(0), KeyPair::generate(&mut csprng));
+                keys.insert(KeyIndex(1), KeyPair::generate(&mut csprng));
+                keys
+            },
+            threshold: SignatureThreshold(2),
+        };
+        Holder {
+            cred_id: G1::generate(&mut csprng),
+            account: AccountAddress([7u8; ACCOUNT_ADDRESS_SIZE]),
+            attributes,
+            randomness: CommitmentsRandomness {
+                id_cred_sec_rand,
+                prf_rand,
+                cred_counter_rand,
+                max_accounts_rand,
+                attributes_rand,
+            },
+            cred_data,
+            id_cred_sec,
+            commitments: CredentialDeploymentCommitments {
+                cmm_prf,
+                cmm_cred_counter,
+                cmm_max_accounts,
+                cmm_attributes,
+                cmm_id_cred_sec_sharing_coeff: vec![cmm_id_cred_sec],
+            },
+        }
+    }
+
+    fn create_request(nonce: &[u8], cred_id: G1) -> ExampleRequest {
+        ExampleRequest {
+            nonce: PresentationNonce {
+                nonce: nonce.to_vec(),
+            },
+            cred_id,
+            statements: vec![
+                Statement::RevealAttribute {
+                    attribute_tag: AttributeTag::from(0u8),
+                },
+                Statement::AttributeInRange {
+                    attribute_tag: AttributeTag::from(1u8),
+                    lower:         AttributeKind::from(1900),
+                    upper:         AttributeKind::from(2000),
+                },
+                Statement::AttributeInSet {
+                    attribute_tag: AttributeTag::from(0u8),
+                    set:           vec![AttributeKind::from(55), AttributeKind::from(56)],
+                },
+                Statement::AttributeNotInSet {
+                    attribute_tag: AttributeTag::from(1u8),
+                    set:           vec![AttributeKind::from(2000), AttributeKind::from(2001)],
+                },
+                Statement::AccountOwnership,
+            ],
+        }
+    }
+
+    #[test]
+    fn test_presentation() {
+        let global = GlobalContext::<G1>::generate(String::from("genesis_string"));
+        let holder = create_holder(&global);
+        let ip_info = test_create_ip_info(&mut thread_rng(), 1, 1).public_ip_info;
+        let request = create_request(b"nonce", holder.cred_id);
+        let presentation = prove_presentation(
+            &global,
+            &request,
+            holder.account,
+            &holder.attributes,
+            &holder.randomness,
+            &holder.cred_data,
+            &holder.id_cred_sec,
+            &holder.commitments,
+            None,
+        )
+        .expect("Should produce a presentation.");
+        let credential = holder.credential();
+        assert_eq!(
+            verify_presentation(
+                &global,
+                &request,
+                &ip_info,
+                holder.account,
+                &credential,
+                &presentation
+            ),
+            Ok(())
+        );
+
+        // The revealed attribute must be the committed one.
+        let mut other_presentation = presentation.clone();
+        other_presentation.proofs[0] = match &presentation.proofs[0] {
+            StatementProof::RevealAttribute { proof, .. } => StatementProof::RevealAttribute {
+                attribute: AttributeKind::from(56),
+                proof:     proof.clone(),
+            },
+            _ => panic!("The first proof reveals an attribute."),
+        };
+        assert_eq!(
+            verify_presentation(
+                &global,
+                &request,
+                &ip_info,
+                holder.account,
+                &credential,
+                &other_presentation
+            ),
+            Err(PresentationVerificationError::InvalidProof(0))
+        );
+
+        // The presentation is bound to the nonce.
+        let other_request = ExampleRequest {
+            nonce: PresentationNonce {
+                nonce: b"other nonce".to_vec(),
+            },
+            ..request.clone()
+        };
+        assert!(verify_presentation(
+            &global,
+            &other_request,
+            &ip_info,
+            holder.account,
+            &credential,
+            &presentation
+        )
+        .is_err());
+
+        // The credential must be the one the request is about.
+        let other_cred_id = G1::generate(&mut thread_rng());
+        let other_request = ExampleRequest {
+            cred_id: other_cred_id,
+            ..request.clone()
+        };
+        assert_eq!(
+            verify_presentation(
+                &global,
+                &other_request,
+                &ip_info,
+                holder.account,
+                &credential,
+                &presentation
+            ),
+            Err(PresentationVerificationError::CredentialMismatch)
+        );
+
+        // The credential must be issued by the given identity provider.
+        let mut other_ip_info = ip_info.clone();
+        other_ip_info.ip_identity = IpIdentity(1);
+        assert_eq!(
+            verify_presentation(
+                &global,
+                &request,
+                &other_ip_info,
+                holder.account,
+                &credential,
+                &presentation
+            ),
+            Err(PresentationVerificationError::CredentialMismatch)
+        );
+
+        // The presentation is bound to the credential.
+        let mut other_credential = holder.credential();
+        if let AccountCredentialWithoutProofs::Normal { cdv, .. } = &mut other_credential {
+            cdv.cred_id = other_cred_id;
+        }
+        assert!(verify_presentation(
+            &global,
+            &other_request,
+            &ip_info,
+            holder.account,
+            &other_credential,
+            &presentation
+        )
+        .is_err());
+
+        // Binary and JSON encodings round trip.
+        let parsed = serialize_deserialize(&request).expect("Request should deserialize.");
+        assert_eq!(request, parsed);
+        let parsed = serialize_deserialize(&presentation).expect("Proof should deserialize.");
+        assert_eq!(presentation, parsed);
+        let json = serde_json::to_string(&request).unwrap();
+        let parsed: ExampleRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(request, parsed);
+        let json = serde_json::to_string(&presentation).unwrap();
+        let parsed: Presentation<G1, AttributeKind> = serde_json::from_str(&json).unwrap();
+        assert_eq!(presentation, parsed);
+    }
+
+    #[test]
+    fn test_presentation_false_statement() {
+        let global = GlobalContext::<G1>::generate(String::from("genesis_string"));
+        let holder = create_holder(&global);
+        let mut request = create_request(b"nonce", holder.cred_id);
+        request.statements = vec![Statement::AttributeInRange {
+            attribute_tag: AttributeTag::from(1u8),
+            lower:         AttributeKind::from(2000),
+            upper:         AttributeKind::from(2010),
+        }];
+        assert!(prove_presentation(
+            &global,
+            &request,
+            holder.account,
+            &holder.attributes,
+            &holder.randomness,
+            &holder.cred_data,
+            &holder.id_cred_sec,
+            &holder.commitments,
+            None,
+        )
+        .is_err());
+        request.statements = vec![Statement::RevealAttribute {
+            attribute_tag: AttributeTag::from(5u8),
+        }];
+        assert!(prove_presentation(
+            &global,
+            &request,
+            holder.account,
+            &holder.attributes,
+            &holder.randomness,
+            &holder.cred_data,
+            &holder.id_cred_sec,
+            &holder.commitments,
+            None,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_presentation_not_revoked() {
+        let mut csprng = thread_rng();
+        let global = GlobalContext::<G1>::generate(String::from("genesis_string"));
+        let holder = create_holder(&global);
+        let ip_data = test_create_ip_info(&mut csprng, 1, 1);
+        let ip_info = ip_data.public_ip_info;
+        let keypair = ed25519_dalek::Keypair {
+            secret: ip_data.ip_cdi_secret_key,
+            public: ip_info.ip_cdi_verify_key,
+        };
+        let mut request = create_request(b"nonce", holder.cred_id);
+        request.statements = vec![Statement::NotRevoked {
+            issued_after: Timestamp::from(1000),
+        }];
+        let mut status_list = StatusList::new(ip_info.ip_identity, Timestamp::from(2000));
+        status_list.revoke(G1::generate(&mut csprng));
+        let signed = sign_status_list(status_list.clone(), &keypair).expect("Signing succeeds.");
+        let prove = |status_list: Option<&SignedStatusList<G1>>| {
+            prove_presentation(
+                &global,
+                &request,
+                holder.account,
+                &holder.attributes,
+                &holder.randomness,
+                &holder.cred_data,
+                &holder.id_cred_sec,
+                &holder.commitments,
+                status_list,
+            )
+        };
+        let presentation = prove(Some(&signed)).expect("Should produce a presentation.");
+        let credential = holder.credential();
+        assert_eq!(
+            verify_presentation(
+                &global,
+                &request,
+                &ip_info,
+                holder.account,
+                &credential,
+                &presentation
+            ),
+            Ok(())
+        );
+        let parsed = serialize_deserialize(&presentation).expect("Proof should deserialize.");
+        assert_eq!(presentation, parsed);
+
+        // The status list must be recent enough.
+        assert!(prove(None).is_err());
+        let mut old_list = status_list.clone();
+        old_list.issued_at = Timestamp::from(500);
+        let old_signed = sign_status_list(old_list, &keypair).expect("Signing succeeds.");
+        assert!(prove(Some(&old_signed)).is_err());
+
+        // The status list must be signed by the identity provider.
+        let other_ip = test_create_ip_info(&mut csprng, 1, 1).public_ip_info;
+        assert_eq!(
+            verify_presentation(
+                &global,
+                &request,
+                &other_ip,
+                holder.account,
+                &credential,
+                &presentation
+            ),
+            Err(PresentationVerificationError::InvalidProof(0))
+        );
+
+        // Revoked identities cannot prove the statement.
+        let id_cred_pub = global
+            .on_chain_commitment_key
+            .g
+            .mul_by_scalar(holder.id_cred_sec.as_ref());
+        status_list.revoke(id_cred_pub);
+        let signed = sign_status_list(status_list, &keypair).expect("Signing succeeds.");
+        assert!(prove(Some(&signed)).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Public verification of the sharing of the IdCredSec and the PRF key among
+//! the anonymity revokers.
+//!
+//! The account holder shares a value with a polynomial of degree `t - 1`,
+//! where `t` is the revocation threshold, commits to each of its coefficients,
+//! and encrypts the share of each anonymity revoker to it. Anybody can then
+//! check from public data alone that any `t` of the anonymity revokers
+//! reconstruct the same value, namely the one committed to by the constant
+//! coefficient. This requires that
+//! - there are exactly `t` commitments to coefficients, so the polynomial has
+//!   degree at most `t - 1`, and every set of `t` shares determines it,
+//! - there are at least `t` anonymity revokers, so that the value can be
+//!   reconstructed at all, and they have distinct non-zero evaluation points,
+//!   which is guaranteed by [ArIdentity] and the use of maps,
+//! - each encrypted share is proved to be the value committed to by the
+//!   commitment to the share computed from the commitments to the coefficients,
+//!   see [share_commitments]. These proofs are part of the proofs in the
+//!   credential and the pre-identity object, and are checked by
+//!   [verify_cdi](crate::chain::verify_cdi) and
+//!   [validate_request](crate::identity_provider::validate_request).
+//!
+//! These checks together with the proofs make up the complete publicly
+//! verifiable argument, and no further checks are needed. The commitments fix
+//! a unique polynomial of degree at most `t - 1`, and the proofs fix the value
+//! encrypted to each anonymity revoker to its evaluation at the evaluation
+//! point of that anonymity revoker, so any `t` decrypted shares interpolate to
+//! the constant coefficient. In credentials the commitment to the constant
+//! coefficient is bound to the IdCredSec by the proof of knowledge of the
+//! signature of the identity provider, and in pre-identity objects to the PRF
+//! key by the proof that it is committed to the same value as `cmm_prf`. The
+//! shares of the PRF key are encrypted in chunks in the exponent, and the
+//! range proofs checked by `validate_request` additionally ensure that every
+//! chunk can be decrypted.
+use crate::{secret_sharing::Threshold, types::ArIdentity, utils};
+use curve_arithmetic::Curve;
+use pedersen_scheme::Commitment;
+use std::{collections::BTreeMap, fmt};
+
+/// Reason why the public sharing data is not consistent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PvssError {
+    /// The number of commitments to coefficients does not match the
+    /// threshold, so the degree of the sharing polynomial is wrong.
+    Degree {
+        threshold:    Threshold,
+        coefficients: usize,
+    },
+    /// There are fewer anonymity revokers than the threshold.
+    TooFewShares {
+        threshold: Threshold,
+        shares:    usize,
+    },
+}
+
+impl fmt::Display for PvssError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PvssError::Degree {
+                threshold,
+                coefficients,
+            } => write!(
+                f,
+                "Sharing polynomial has {} coefficients, but the threshold is {}.",
+                coefficients, threshold
+            ),
+            PvssError::TooFewShares { threshold, shares } => write!(
+                f,
+                "There are {} shares, but the threshold is {}.",
+                shares, threshold
+            ),
+        }
+    }
+}
+
+/// Check the degree of the committed sharing polynomial against the threshold,
+/// and that there are enough shares to reconstruct, and compute the commitments
+/// to the shares of the given anonymity revokers. Each encrypted share must be
+/// proved to be the value committed to by the respective commitment.
+pub fn share_commitments<'a, C: Curve>(
+    threshold: Threshold,
+    ar_identities: impl ExactSizeIterator<Item = &'a ArIdentity>,
+    cmm_sharing_coeff: &[Commitment<C>],
+) -> Result<BTreeMap<ArIdentity, Commitment<C>>, PvssError> {
+    if cmm_sharing_coeff.len() != usize::from(threshold) {
+        return Err(PvssError::Degree {
+            threshold,
+            coefficients: cmm_sharing_coeff.len(),
+        });
+    }
+    if ar_identities.len() < usize::from(threshold) {
+        return Err(PvssError::TooFewShares {
+            threshold,
+            shares: ar_identities.len(),
+        });
+    }
+    Ok(ar_identities
+        .map(|&ar_id| {
+            let cmm = utils::commitment_to_share(&ar_id.to_scalar::<C>(), cmm_sharing_coeff);
+            (ar_id, cmm)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret_sharing::*;
+    use curve_arithmetic::Value;
+    use pairing::bls12_381::G1;
+    use pedersen_scheme::{CommitmentKey, Randomness};
+    use rand::*;
+
+    #[test]
+    fn test_share_commitments() {
+        let mut csprng = thread_rng();
+        let ck = CommitmentKey::<G1>::generate(&mut csprng);
+        let ar_ids = (1..=5u32).map(ArIdentity::new).collect::<Vec<_>>();
+        let threshold = Threshold(3);
+        let secret = Value::<G1>::generate(&mut csprng);
+        let sharing = share::<G1, _, _, _>(
+            secret.as_ref(),
+            ar_ids.iter().copied(),
+            threshold,
+            &mut csprng,
+        );
+        let coefficients = std::iter::once(&secret)
+            .chain(sharing.coefficients.iter())
+            .collect::<Vec<_>>();
+        let (cmms, rands): (Vec<_>, Vec<_>) = coefficients
+            .iter()
+            .map(|c| ck.commit(*c, &mut csprng))
+            .unzip();
+
+        let share_cmms = share_commitments(threshold, ar_ids.iter(), &cmms)
+            .expect("Sharing data is consistent.");
+        for (ar_id, share) in ar_ids.iter().zip(sharing.shares.iter()) {
+            let rand =
+                Randomness::<G1>::new(utils::evaluate_poly(&rands, &ar_id.to_scalar::<G1>()));
+            assert!(
+                ck.open(share, &rand, &share_cmms[ar_id]),
+                "Commitment to the share of {} does not open to the share.",
+                ar_id
+            );
+        }
+        // Every set of threshold many shares reconstructs the same secret.
+        for subset in [[0, 1, 2], [1, 3, 4], [0, 2, 4]].iter() {
+            let shares = subset
+                .iter()
+                .map(|&i| (ar_ids[i], sharing.shares[i].clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(reveal::<_, G1>(&shares), *secret.as_ref());
+        }
+
+        assert_eq!(
+            share_commitments(Threshold(2), ar_ids.iter(), &cmms),
+            Err(PvssError::Degree {
+                threshold:    Threshold(2),
+                coefficients: 3,
+            })
+        );
+        assert_eq!(
+            share_commitments(threshold, ar_ids[..2].iter(), &cmms),
+            Err(PvssError::TooFewShares {
+                threshold,
+                shares: 2,
+            })
+        );
+    }
+}
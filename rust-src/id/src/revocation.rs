@@ -0,0 +1,357 @@
+//! Revocation of identities by the identity provider, and proofs by holders
+//! of credentials that their identity is not revoked.
+//!
+//! An identity is identified by its `IdCredPub = g^idCredSec`, which the
+//! identity provider learns from the pre-identity object. The identity
+//! provider maintains a [StatusList] of the `IdCredPub` of all the identities
+//! it has revoked, and publishes it signed with its ed25519 key, i.e., the key
+//! matching `ip_cdi_verify_key`, see [sign_status_list].
+//!
+//! Every credential contains a commitment `C = g^idCredSec h^r` to the
+//! `IdCredSec` of the identity it is derived from, namely the commitment to
+//! the constant coefficient of the sharing polynomial of `IdCredSec`. The
+//! holder proves that the committed value is not the discrete logarithm of
+//! any `IdCredPub` in the list without revealing it, see
+//! [prove_non_revocation]. The proof is linear in the size of the list, and it
+//! is bound to a specific version of the list, so a holder has to produce a
+//! new proof when the list changes. How recent the list must be is up to the
+//! verifier, which can check the version and the time it was issued.
+use crate::{
+    identity_provider::IpCdiSigner,
+    sigma_protocols::{com_ineq, common::*},
+    types::*,
+};
+use crypto_common::{types::Timestamp, *};
+use crypto_common_derive::*;
+use curve_arithmetic::{Curve, Pairing, Value};
+use ed25519_dalek::Verifier;
+use pedersen_scheme::{Commitment, CommitmentKey, Randomness};
+use rand::*;
+use random_oracle::RandomOracle;
+use sha2::{Digest, Sha256};
+
+/// The identities revoked by an identity provider.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+pub struct StatusList<C: Curve> {
+    /// The identity provider that revoked the identities.
+    #[serde(rename = "ipIdentity")]
+    pub ip_identity: IpIdentity,
+    /// The version of the list. It is incremented every time an identity is
+    /// revoked.
+    #[serde(rename = "version")]
+    pub version:     u64,
+    /// The time the list was issued.
+    #[serde(rename = "issuedAt")]
+    pub issued_at:   Timestamp,
+    /// The `IdCredPub` of the revoked identities.
+    #[serde(
+        rename = "revoked",
+        serialize_with = "base16_encode",
+        deserialize_with = "base16_decode"
+    )]
+    pub revoked:     Vec<C>,
+}
+
+impl<C: Curve> StatusList<C> {
+    /// The list of the identity provider in which no identities are revoked.
+    pub fn new(ip_identity: IpIdentity, issued_at: Timestamp) -> Self {
+        StatusList {
+            ip_identity,
+            version: 0,
+            issued_at,
+            revoked: Vec::new(),
+        }
+    }
+
+    /// Whether the identity with the given `IdCredPub` is revoked.
+    pub fn is_revoked(&self, id_cred_pub: &C) -> bool { self.revoked.contains(id_cred_pub) }
+
+    /// Revoke the identity with the given `IdCredPub`, and increment the
+    /// version. Returns `false`, and leaves the list unchanged, if the identity
+    /// is already revoked.
+    pub fn revoke(&mut self, id_cred_pub: C) -> bool {
+        if self.is_revoked(&id_cred_pub) {
+            return false;
+        }
+        self.revoked.push(id_cred_pub);
+        self.version += 1;
+        true
+    }
+}
+
+/// A status list together with the signature of the identity provider on it.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+pub struct SignedStatusList<C: Curve> {
+    #[serde(rename = "statusList")]
+    pub status_list: StatusList<C>,
+    /// Signature with the key matching `ip_cdi_verify_key` of the identity
+    /// provider.
+    #[serde(rename = "signature")]
+    pub signature:   IpCdiSignature,
+}
+
+/// The message that is signed by the identity provider. It is separated from
+/// the messages that are signed when creating initial credentials.
+fn status_list_message<C: Curve>(status_list: &StatusList<C>) -> impl AsRef<[u8]> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"StatusList");
+    hasher.update(to_bytes(status_list));
+    hasher.finalize()
+}
+
+/// Sign the status list with the given signer, which must sign with the key
+/// matching `ip_cdi_verify_key` of the identity provider.
+pub fn sign_status_list<C: Curve, S: IpCdiSigner + ?Sized>(
+    status_list: StatusList<C>,
+    signer: &S,
+) -> Result<SignedStatusList<C>, S::Error> {
+    let signature = signer.sign(status_list_message(&status_list).as_ref())?;
+    Ok(SignedStatusList {
+        status_list,
+        signature: signature.into(),
+    })
+}
+
+/// Check that the status list is signed by the given identity provider.
+pub fn verify_status_list<P: Pairing, C: Curve<Scalar = P::ScalarField>>(
+    ip_info: &IpInfo<P>,
+    signed: &SignedStatusList<C>,
+) -> bool {
+    signed.status_list.ip_identity == ip_info.ip_identity
+        && ip_info
+            .ip_cdi_verify_key
+            .verify(
+                status_list_message(&signed.status_list).as_ref(),
+                &signed.signature,
+            )
+            .is_ok()
+}
+
+/// Proof that the value committed to in a commitment is not the `IdCredSec`
+/// of any of the identities in a status list.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, SerdeBase16Serialize)]
+pub struct NonRevocationProof<C: Curve> {
+    /// For each revoked identity, in the order of the list, the blinded point
+    /// `T` and the proof that the committed value is not its `IdCredSec`.
+    pub proofs: Vec<(C, com_ineq::Proof<C>)>,
+}
+
+/// Proof that the identity a credential is derived from is not revoked,
+/// included in the credential when it is created. The proof is made with the
+/// transcript [credential_transcript].
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct CredentialNonRevocation<C: Curve> {
+    /// The status list the proof is made with respect to.
+    pub status_list: SignedStatusList<C>,
+    /// The proof that the identity is not in the list.
+    pub proof:       NonRevocationProof<C>,
+}
+
+/// The transcripts of the proofs for the individual identities in the list
+/// are derived from this.
+fn non_revocation_transcript<C: Curve>(
+    transcript: &RandomOracle,
+    status_list: &StatusList<C>,
+) -> RandomOracle {
+    let mut ro = transcript.split();
+    ro.append_message(b"NonRevocationProof", status_list);
+    ro
+}
+
+/// Prove that the value committed to in `cmm` is not the `IdCredSec` of any
+/// identity in the status list. The arguments are
+/// - transcript - the transcript the proof is bound to, e.g., containing the
+///   credential the commitment is part of
+/// - cmm_key - the commitment key, whose first generator must be the one the
+///   `IdCredPub` are computed with, i.e., the on-chain commitment key
+/// - id_cred_sec, randomness - the opening of the commitment `cmm`
+///
+/// Returns `None` if the identity is revoked.
+pub fn prove_non_revocation<C: Curve, R: Rng>(
+    transcript: &RandomOracle,
+    cmm_key: &CommitmentKey<C>,
+    status_list: &StatusList<C>,
+    cmm: &Commitment<C>,
+    id_cred_sec: &Value<C>,
+    randomness: &Randomness<C>,
+    csprng: &mut R,
+) -> Option<NonRevocationProof<C>> {
+    let ro = non_revocation_transcript(transcript, status_list);
+    let mut proofs = Vec::with_capacity(status_list.revoked.len());
+    for (i, point) in status_list.revoked.iter().enumerate() {
+        let secret =
+            com_ineq::ComIneqSecret::generate(id_cred_sec.clone(), randomness.clone(), csprng);
+        let blinded = secret.blinded(cmm_key, point);
+        if blinded.is_zero_point() {
+            return None;
+        }
+        let prover = com_ineq::ComIneq {
+            cmm: *cmm,
+            point: *point,
+            blinded,
+            cmm_key: *cmm_key,
+        };
+        let mut ro = ro.split();
+        ro.append_message(b"index", &(i as u64));
+        proofs.push((blinded, prove(&mut ro, &prover, secret, csprng)?));
+    }
+    Some(NonRevocationProof { proofs })
+}
+
+/// Verify a proof produced by [prove_non_revocation] with the same
+/// transcript, commitment key and status list. The status list must be checked
+/// separately, e.g., with [verify_status_list].
+pub fn verify_non_revocation<C: Curve>(
+    transcript: &RandomOracle,
+    cmm_key: &CommitmentKey<C>,
+    status_list: &StatusList<C>,
+    cmm: &Commitment<C>,
+    proof: &NonRevocationProof<C>,
+) -> bool {
+    if proof.proofs.len() != status_list.revoked.len() {
+        return false;
+    }
+    let ro = non_revocation_transcript(transcript, status_list);
+    status_list
+        .revoked
+        .iter()
+        .zip(proof.proofs.iter())
+        .enumerate()
+        .all(|(i, (point, (blinded, proof)))| {
+            let verifier = com_ineq::ComIneq {
+                cmm:     *cmm,
+                point:   *point,
+                blinded: *blinded,
+                cmm_key: *cmm_key,
+            };
+            let mut ro = ro.split();
+            ro.append_message(b"index", &(i as u64));
+            verify(&mut ro, &verifier, proof)
+        })
+}
+
+/// The transcript of the proof that the identity a credential is derived from
+/// is not revoked. It is bound to the registration id of the credential.
+pub fn credential_transcript<C: Curve>(
+    global_context: &GlobalContext<C>,
+    cred_id: &C,
+) -> RandomOracle {
+    let mut ro = RandomOracle::domain("CredentialNonRevocation");
+    ro.append_message(b"global_context", global_context);
+    ro.append_message(b"cred_id", cred_id);
+    ro
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Keypair;
+    use pairing::bls12_381::G1;
+
+    #[test]
+    fn test_status_list() {
+        let mut csprng = thread_rng();
+        let keypair = Keypair::generate(&mut csprng);
+        let mut status_list = StatusList::<G1>::new(IpIdentity(0), Timestamp::from(0));
+        assert!(status_list.revoke(G1::generate(&mut csprng)));
+        let revoked = G1::generate(&mut csprng);
+        assert!(status_list.revoke(revoked));
+        assert!(!status_list.revoke(revoked));
+        assert_eq!(status_list.version, 2);
+        assert!(status_list.is_revoked(&revoked));
+
+        let signed = sign_status_list(status_list, &keypair).expect("Signing does not fail.");
+        let message = status_list_message(&signed.status_list);
+        assert!(keypair
+            .public
+            .verify(message.as_ref(), &signed.signature)
+            .is_ok());
+        let mut tampered = signed;
+        tampered.status_list.revoked.pop();
+        let message = status_list_message(&tampered.status_list);
+        assert!(keypair
+            .public
+            .verify(message.as_ref(), &tampered.signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_non_revocation() {
+        let mut csprng = thread_rng();
+        let cmm_key = CommitmentKey::<G1>::generate(&mut csprng);
+        let id_cred_sec = Value::<G1>::generate(&mut csprng);
+        let (cmm, randomness) = cmm_key.commit(&id_cred_sec, &mut csprng);
+        let id_cred_pub = id_cred_sec.mul_point(&cmm_key.g);
+        let transcript = RandomOracle::domain("test_non_revocation");
+
+        let mut status_list = StatusList::<G1>::new(IpIdentity(0), Timestamp::from(0));
+        let proof = prove_non_revocation(
+            &transcript,
+            &cmm_key,
+            &status_list,
+            &cmm,
+            &id_cred_sec,
+            &randomness,
+            &mut csprng,
+        )
+        .expect("Nothing is revoked.");
+        assert!(verify_non_revocation(
+            &transcript,
+            &cmm_key,
+            &status_list,
+            &cmm,
+            &proof
+        ));
+
+        for _ in 0..5 {
+            status_list.revoke(G1::generate(&mut csprng));
+        }
+        // The proof is bound to the version of the list.
+        assert!(!verify_non_revocation(
+            &transcript,
+            &cmm_key,
+            &status_list,
+            &cmm,
+            &proof
+        ));
+        let proof = prove_non_revocation(
+            &transcript,
+            &cmm_key,
+            &status_list,
+            &cmm,
+            &id_cred_sec,
+            &randomness,
+            &mut csprng,
+        )
+        .expect("The identity is not revoked.");
+        assert!(verify_non_revocation(
+            &transcript,
+            &cmm_key,
+            &status_list,
+            &cmm,
+            &proof
+        ));
+        let other = RandomOracle::domain("other");
+        assert!(!verify_non_revocation(
+            &other,
+            &cmm_key,
+            &status_list,
+            &cmm,
+            &proof
+        ));
+
+        status_list.revoke(id_cred_pub);
+        assert!(prove_non_revocation(
+            &transcript,
+            &cmm_key,
+            &status_list,
+            &cmm,
+            &id_cred_sec,
+            &randomness,
+            &mut csprng,
+        )
+        .is_none());
+    }
+}
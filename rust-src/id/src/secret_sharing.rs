@@ -135,7 +135,7 @@ pub fn share<C: Curve, P: Into<u64>, I: IntoIterator<Item = P> + ExactSizeIterat
         // evaluate the polynomial at point 'xs'
         for coeff in coefficients.iter().rev() {
             share.mul_assign(&x);
-            share.add_assign(coeff);
+            share.add_assign(coeff.as_ref());
         }
         // since the zeroth coefficient is not in the list of coefficients we do one
         // final step here
@@ -177,7 +177,7 @@ pub fn reveal<P: Into<u64> + Copy, C: Curve>(shares: &[(P, PedersenValue<C>)]) -
     let kxs = shares.iter().map(|(fst, _)| *fst).collect::<Vec<_>>();
     shares.iter().fold(C::Scalar::zero(), |accum, (i, v)| {
         let mut s = lagrange::<P, C>(&kxs, *i);
-        s.mul_assign(&v);
+        s.mul_assign(v.as_ref());
         s.add_assign(&accum);
         s
     })
@@ -277,7 +277,7 @@ mod test {
             let sufficient_sample = &shares[0..(threshold as usize)];
             let sufficient_sample_points = sufficient_sample
                 .iter()
-                .map(|(n, s)| (*n, generator.mul_by_scalar(&s)))
+                .map(|(n, s)| (*n, s.mul_point(&generator)))
                 .collect::<Vec<(u8, G1)>>();
             let revealed_data: Fr = reveal::<_, G1>(&sufficient_sample);
             assert_eq!(revealed_data, secret);
@@ -305,7 +305,7 @@ mod test {
             assert_ne!(revealed_data, secret);
             let sufficient_points_err = shares
                 .iter()
-                .map(|(n, s)| (*n, generator.mul_by_scalar(&s)))
+                .map(|(n, s)| (*n, s.mul_point(&generator)))
                 .collect::<Vec<(u8, G1)>>();
             let revealed_data_point: G1 = reveal_in_group::<_, G1>(&sufficient_points_err);
             assert_ne!(revealed_data_point, secret_point);
@@ -326,7 +326,7 @@ mod test {
             let insufficient_sample = &insufficient_shares[0..((threshold - 1) as usize)];
             let insufficient_sample_points = insufficient_sample
                 .iter()
-                .map(|(n, s)| (*n, generator.mul_by_scalar(&s)))
+                .map(|(n, s)| (*n, s.mul_point(&generator)))
                 .collect::<Vec<(u8, G1)>>();
             let revealed_data: Fr = reveal::<_, G1>(&insufficient_sample);
             assert_ne!(revealed_data, secret);
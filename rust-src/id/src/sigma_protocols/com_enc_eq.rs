@@ -103,8 +103,8 @@ impl<C: Curve> SigmaProtocol for ComEncEq<C> {
 
         let mut z_2 = *challenge;
         z_2.negate();
-        z_2.mul_assign(x);
-        z_2.add_assign(&beta);
+        z_2.mul_assign(x.as_ref());
+        z_2.add_assign(beta.as_ref());
 
         let mut z_3 = *challenge;
         z_3.negate();
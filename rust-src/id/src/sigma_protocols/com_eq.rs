@@ -72,7 +72,7 @@ impl<C: Curve, D: Curve<Scalar = C::Scalar>> SigmaProtocol for ComEq<C, D> {
         let alpha = Value::<D>::generate_non_zero(csprng);
         // This cR_i is R_i from the specification.
         let (v, cR) = self.cmm_key.commit(&alpha, csprng);
-        u = u.plus_point(&self.g.mul_by_scalar(&alpha));
+        u = u.plus_point(&self.g.mul_by_secret(&alpha.value));
         Some((CommittedPoints { u, v }, (alpha, cR)))
     }
 
@@ -89,9 +89,9 @@ impl<C: Curve, D: Curve<Scalar = C::Scalar>> SigmaProtocol for ComEq<C, D> {
         let (ref alpha, ref cR) = state;
         // compute alpha_i - a_i * c
         let mut s = *challenge;
-        s.mul_assign(&secret.a);
+        s.mul_assign(secret.a.as_ref());
         s.negate();
-        s.add_assign(alpha);
+        s.add_assign(alpha.as_ref());
         // compute R_i - r_i * c
         let mut t: C::Scalar = *challenge;
         t.mul_assign(&secret.r);
@@ -134,7 +134,7 @@ impl<C: Curve, D: Curve<Scalar = C::Scalar>> SigmaProtocol for ComEq<C, D> {
         let (c, randomness) = comm_key.commit(&a, csprng);
         let g = C::generate(csprng);
         let mut y = C::zero_point();
-        y = y.plus_point(&g.mul_by_scalar(&a));
+        y = y.plus_point(&g.mul_by_secret(&a.value));
         let com_eq = ComEq {
             commitment: c,
             y,
@@ -146,6 +146,18 @@ impl<C: Curve, D: Curve<Scalar = C::Scalar>> SigmaProtocol for ComEq<C, D> {
     }
 }
 
+impl<C: Curve, D: Curve<Scalar = C::Scalar>> SimulatableSigmaProtocol for ComEq<C, D> {
+    fn simulate_witness<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+        _challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        Some(Witness {
+            witness: (C::generate_scalar(csprng), C::generate_scalar(csprng)),
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
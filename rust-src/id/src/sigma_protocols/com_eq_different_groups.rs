@@ -76,9 +76,9 @@ impl<C1: Curve, C2: Curve<Scalar = C1::Scalar>> SigmaProtocol for ComEqDiffGroup
         challenge: &Self::ProtocolChallenge,
     ) -> Option<Self::ProverWitness> {
         let mut s_1 = *challenge;
-        s_1.mul_assign(&secret.value);
+        s_1.mul_assign(secret.value.as_ref());
         s_1.negate();
-        s_1.add_assign(&state.0);
+        s_1.add_assign(state.0.as_ref());
 
         let mut s_2 = *challenge;
         s_2.mul_assign(&secret.rand_cmm_1);
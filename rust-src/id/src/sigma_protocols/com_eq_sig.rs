@@ -107,7 +107,7 @@ impl<'a, P: Pairing, C: Curve<Scalar = P::ScalarField>> SigmaProtocol for ComEqS
             let mu_i = Value::generate_non_zero(csprng);
 
             // And a point in G2 computed from it.
-            let cU_i = cY_tilda(i).mul_by_scalar(&mu_i);
+            let cU_i = cY_tilda(i).mul_by_secret(&mu_i.value);
             // A commitment to the value v_i, and a randomness
             let (c_i, cR_i) = cmm_key.commit(&mu_i, csprng);
 
@@ -146,7 +146,7 @@ impl<'a, P: Pairing, C: Curve<Scalar = P::ScalarField>> SigmaProtocol for ComEqS
         // If challange = 0 the proof is not going to be valid.
         // However this is an exceedingly unlikely case
         let mut wit_r_prime = *challenge;
-        wit_r_prime.mul_assign(&r_prime);
+        wit_r_prime.mul_assign(r_prime.as_ref());
         wit_r_prime.negate();
         wit_r_prime.add_assign(&state.rho_prime);
 
@@ -154,9 +154,9 @@ impl<'a, P: Pairing, C: Curve<Scalar = P::ScalarField>> SigmaProtocol for ComEqS
         for ((ref m, ref r), (ref mu, ref rho)) in izip!(secret.values_and_rands, state.mus_and_rs)
         {
             let mut wit_m = *challenge;
-            wit_m.mul_assign(m);
+            wit_m.mul_assign(m.as_ref());
             wit_m.negate();
-            wit_m.add_assign(mu);
+            wit_m.add_assign(mu.as_ref());
 
             let mut wit_r = *challenge;
             wit_r.mul_assign(r);
@@ -261,7 +261,7 @@ impl<'a, P: Pairing, C: Curve<Scalar = P::ScalarField>> SigmaProtocol for ComEqS
         for cY_j in ps_pk.ys.iter().take(csprng.gen_range(0, data_size)) {
             let v_j = Value::generate(csprng);
             let (c_j, r_j) = cmm_key.commit(&v_j, csprng);
-            comm_to_signer = comm_to_signer.plus_point(&cY_j.mul_by_scalar(&v_j));
+            comm_to_signer = comm_to_signer.plus_point(&cY_j.mul_by_secret(&v_j.value));
             secrets.push((v_j, r_j));
             commitments.push(c_j);
         }
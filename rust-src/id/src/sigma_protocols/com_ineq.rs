@@ -0,0 +1,242 @@
+//! This module implements the com-ineq sigma protocol, which is used to prove
+//! that the value `x` committed to in a commitment `C = g^x h^r` is not the
+//! discrete logarithm of a public point `Y` with respect to `g`.
+//!
+//! The prover chooses a random non-zero `b`, and publishes the point
+//! `T = (g^x Y^{-1})^b`, which is the zero point if and only if `g^x = Y`. It
+//! then proves knowledge of `a = x * b`, `b` and `c = r * b` such that
+//! `T = g^a Y^{-b}` and `g^a h^c C^{-b}` is the zero point. The latter ensures
+//! that `a = x * b`, by the binding property of the commitment, and hence that
+//! `T` is computed correctly. The verifier in addition checks that `T` is not
+//! the zero point.
+use crate::sigma_protocols::common::*;
+use crypto_common::*;
+use crypto_common_derive::*;
+use curve_arithmetic::{Curve, Value};
+use ff::Field;
+use pedersen_scheme::{Commitment, CommitmentKey, Randomness};
+use random_oracle::{Challenge, RandomOracle};
+
+pub struct ComIneq<C: Curve> {
+    /// The commitment `C` to the value.
+    pub cmm:     Commitment<C>,
+    /// The point `Y` the committed value is claimed to not be the discrete
+    /// logarithm of.
+    pub point:   C,
+    /// The point `T` computed by the prover.
+    pub blinded: C,
+    /// The commitment key used to generate the commitment.
+    pub cmm_key: CommitmentKey<C>,
+}
+
+pub struct ComIneqSecret<C: Curve> {
+    /// The committed value `x`.
+    pub value:      Value<C>,
+    /// The randomness `r` of the commitment.
+    pub randomness: Randomness<C>,
+    /// The blinding factor `b` used to compute `T`.
+    pub blinding:   C::Scalar,
+}
+
+impl<C: Curve> ComIneqSecret<C> {
+    /// Choose a new random blinding factor.
+    pub fn generate<R: rand::Rng>(
+        value: Value<C>,
+        randomness: Randomness<C>,
+        csprng: &mut R,
+    ) -> Self {
+        ComIneqSecret {
+            value,
+            randomness,
+            blinding: C::generate_non_zero_scalar(csprng),
+        }
+    }
+
+    /// Compute the point `T = (g^x Y^{-1})^b` that the prover publishes for
+    /// the given point `Y`.
+    pub fn blinded(&self, cmm_key: &CommitmentKey<C>, point: &C) -> C {
+        self.value
+            .mul_point(&cmm_key.g)
+            .minus_point(point)
+            .mul_by_scalar(&self.blinding)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+pub struct Witness<C: Curve> {
+    /// Randomized `a`.
+    za: C::Scalar,
+    /// Randomized `b`.
+    zb: C::Scalar,
+    /// Randomized `c`.
+    zc: C::Scalar,
+}
+
+/// Convenient alias for com-ineq proof
+pub type Proof<C> = SigmaProof<Witness<C>>;
+
+impl<C: Curve> SigmaProtocol for ComIneq<C> {
+    type CommitMessage = (C, C);
+    type ProtocolChallenge = C::Scalar;
+    type ProverState = (C::Scalar, C::Scalar, C::Scalar);
+    type ProverWitness = Witness<C>;
+    type SecretData = ComIneqSecret<C>;
+
+    fn public(&self, ro: &mut RandomOracle) {
+        ro.append_message(b"cmm", &self.cmm);
+        ro.append_message(b"point", &self.point);
+        ro.append_message(b"blinded", &self.blinded);
+        ro.append_message(b"cmm_key", &self.cmm_key)
+    }
+
+    fn get_challenge(&self, challenge: &Challenge) -> Self::ProtocolChallenge {
+        C::scalar_from_bytes(challenge)
+    }
+
+    fn commit_point<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+    ) -> Option<(Self::CommitMessage, Self::ProverState)> {
+        let alpha = C::generate_non_zero_scalar(csprng);
+        let beta = C::generate_non_zero_scalar(csprng);
+        let gamma = C::generate_non_zero_scalar(csprng);
+        let g_alpha = self.cmm_key.g.mul_by_scalar(&alpha);
+        let u = g_alpha.minus_point(&self.point.mul_by_scalar(&beta));
+        let v = g_alpha
+            .plus_point(&self.cmm_key.h.mul_by_scalar(&gamma))
+            .minus_point(&self.cmm.mul_by_scalar(&beta));
+        Some(((u, v), (alpha, beta, gamma)))
+    }
+
+    fn generate_witness(
+        &self,
+        secret: Self::SecretData,
+        state: Self::ProverState,
+        challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        let (alpha, beta, gamma) = state;
+        let b = secret.blinding;
+        let mut a = *secret.value.as_ref();
+        a.mul_assign(&b);
+        let mut c = *secret.randomness;
+        c.mul_assign(&b);
+        let respond = |s: C::Scalar, rand: C::Scalar| {
+            let mut z = *challenge;
+            z.mul_assign(&s);
+            z.add_assign(&rand);
+            z
+        };
+        Some(Witness {
+            za: respond(a, alpha),
+            zb: respond(b, beta),
+            zc: respond(c, gamma),
+        })
+    }
+
+    fn extract_point(
+        &self,
+        challenge: &Self::ProtocolChallenge,
+        witness: &Self::ProverWitness,
+    ) -> Option<Self::CommitMessage> {
+        // The blinded point is zero if the committed value is the discrete
+        // logarithm of the point, so the proof is rejected.
+        if self.blinded.is_zero_point() {
+            return None;
+        }
+        let g_za = self.cmm_key.g.mul_by_scalar(&witness.za);
+        let u = g_za
+            .minus_point(&self.point.mul_by_scalar(&witness.zb))
+            .minus_point(&self.blinded.mul_by_scalar(challenge));
+        let v = g_za
+            .plus_point(&self.cmm_key.h.mul_by_scalar(&witness.zc))
+            .minus_point(&self.cmm.mul_by_scalar(&witness.zb));
+        Some((u, v))
+    }
+
+    #[cfg(test)]
+    fn with_valid_data<R: rand::Rng>(
+        _data_size: usize,
+        csprng: &mut R,
+        f: impl FnOnce(Self, Self::SecretData, &mut R) -> (),
+    ) {
+        let cmm_key = CommitmentKey::generate(csprng);
+        let value = Value::generate(csprng);
+        let (cmm, randomness) = cmm_key.commit(&value, csprng);
+        let point = C::generate(csprng);
+        let secret = ComIneqSecret::generate(value, randomness, csprng);
+        let com_ineq = ComIneq {
+            cmm,
+            point,
+            blinded: secret.blinded(&cmm_key, &point),
+            cmm_key,
+        };
+        f(com_ineq, secret, csprng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pairing::bls12_381::G1;
+
+    #[test]
+    pub fn test_com_ineq_correctness() {
+        let mut csprng = rand::thread_rng();
+        for _ in 0..100 {
+            ComIneq::<G1>::with_valid_data(0, &mut csprng, |com_ineq, secret, csprng| {
+                let challenge_prefix = generate_challenge_prefix(csprng);
+                let mut ro = RandomOracle::domain(&challenge_prefix);
+                let proof = prove(&mut ro.split(), &com_ineq, secret, csprng)
+                    .expect("Proving should succeed.");
+                assert!(verify(&mut ro, &com_ineq, &proof));
+            })
+        }
+    }
+
+    #[test]
+    pub fn test_com_ineq_soundness() {
+        let mut csprng = rand::thread_rng();
+        for _ in 0..100 {
+            ComIneq::<G1>::with_valid_data(0, &mut csprng, |com_ineq, secret, csprng| {
+                let challenge_prefix = generate_challenge_prefix(csprng);
+                let mut ro = RandomOracle::domain(&challenge_prefix);
+                let proof = prove(&mut ro.split(), &com_ineq, secret, csprng)
+                    .expect("Proving should succeed.");
+
+                let wrong_point = ComIneq {
+                    point: G1::generate(csprng),
+                    ..com_ineq
+                };
+                assert!(!verify(&mut ro.split(), &wrong_point, &proof));
+                let wrong_blinded = ComIneq {
+                    blinded: G1::generate(csprng),
+                    ..wrong_point
+                };
+                assert!(!verify(&mut ro.split(), &wrong_blinded, &proof));
+            })
+        }
+    }
+
+    #[test]
+    pub fn test_com_ineq_equal() {
+        // If the committed value is the discrete logarithm of the point the
+        // blinded point is zero, and no proof is accepted.
+        let mut csprng = rand::thread_rng();
+        let cmm_key = CommitmentKey::<G1>::generate(&mut csprng);
+        let value = Value::generate(&mut csprng);
+        let (cmm, randomness) = cmm_key.commit(&value, &mut csprng);
+        let point = value.mul_point(&cmm_key.g);
+        let secret = ComIneqSecret::generate(value, randomness, &mut csprng);
+        let com_ineq = ComIneq {
+            cmm,
+            point,
+            blinded: secret.blinded(&cmm_key, &point),
+            cmm_key,
+        };
+        assert!(com_ineq.blinded.is_zero_point());
+        let mut ro = RandomOracle::domain("com_ineq_equal");
+        let proof = prove(&mut ro.split(), &com_ineq, secret, &mut csprng)
+            .expect("Proving should succeed.");
+        assert!(!verify(&mut ro, &com_ineq, &proof));
+    }
+}
@@ -89,7 +89,7 @@ impl<C: Curve> SigmaProtocol for ComLin<C> {
         let mut sum_ui_alphai = C::Scalar::zero();
         for (ualpha, alpha) in izip!(&self.us, &alphas) {
             let mut ualpha = *ualpha;
-            ualpha.mul_assign(&alpha);
+            ualpha.mul_assign(alpha.as_ref());
             sum_ui_alphai.add_assign(&ualpha);
         }
         let sum_ui_alphai: Value<C> = Value::new(sum_ui_alphai);
@@ -121,9 +121,9 @@ impl<C: Curve> SigmaProtocol for ComLin<C> {
         let c = *challenge;
         for (s, alpha, r, r_i_tilda) in izip!(&secret.xs, &alphas, &secret.rs, &r_i_tildes) {
             let mut zi = c;
-            zi.mul_assign(s);
+            zi.mul_assign(s.as_ref());
             zi.negate();
-            zi.add_assign(alpha);
+            zi.add_assign(alpha.as_ref());
             zs.push(zi);
 
             let mut si = c;
@@ -192,7 +192,7 @@ impl<C: Curve> SigmaProtocol for ComLin<C> {
             let ri = Randomness::<C>::generate(csprng);
             let ui = C::generate_scalar(csprng);
             let mut uixi = ui;
-            uixi.mul_assign(&xi);
+            uixi.mul_assign(xi.as_ref());
             sum.add_assign(&uixi);
             cmms.push(cmm_key.hide(&xi, &ri));
             xs.push(xi);
@@ -404,7 +404,7 @@ mod tests {
             let h = G1::generate(rng);
             G_H.push((g, h));
         }
-        let gens = bulletproofs::range_proof::Generators { G_H };
+        let gens = bulletproofs::range_proof::Generators::new(G_H);
         let proof = bulletproofs::range_proof::prove_given_scalars(
             &mut ro.split(),
             rng,
@@ -85,21 +85,19 @@ impl<'a, C: Curve> SigmaProtocol for ComMult<C> {
         let rands = state.1;
         let cR = state.2;
         for i in 0..2 {
-            ss[i].mul_assign(&secret.values[i]); // c * x_i
-            ss[i].negate(); // 
-                            // - c * x_i
-            ss[i].add_assign(&alphas[i]); // alpha - c * x_i
+            ss[i].mul_assign(secret.values[i].as_ref()); // c * x_i
+            ss[i].negate(); // - c * x_i
+            ss[i].add_assign(alphas[i].as_ref()); // alpha - c * x_i
 
             ts[i].mul_assign(&secret.rands[i]); // c * r_i
-            ts[i].negate(); // 
-                            // - c * r_i
+            ts[i].negate(); // - c * r_i
             ts[i].add_assign(&rands[i]); // rTilde_i - c * r_i
         }
 
         // compute r_3 - r_1 * x_2
         let mut r = C::Scalar::one();
         r.mul_assign(&secret.rands[0]); // r_1
-        r.mul_assign(&secret.values[1]); // r_1 * x_2
+        r.mul_assign(secret.values[1].as_ref()); // r_1 * x_2
         r.negate();
         r.add_assign(&secret.rands[2]); // r_3 - r_1 * x_2
 
@@ -148,8 +146,8 @@ impl<'a, C: Curve> SigmaProtocol for ComMult<C> {
         let a_1 = Value::<C>::generate_non_zero(csprng);
         let a_2 = Value::<C>::generate_non_zero(csprng);
         let mut a_3 = C::Scalar::one();
-        a_3.mul_assign(&a_1);
-        a_3.mul_assign(&a_2);
+        a_3.mul_assign(a_1.as_ref());
+        a_3.mul_assign(a_2.as_ref());
         let a_3: Value<C> = Value::new(a_3);
 
         let (cmm_1, r_1) = cmm_key.commit(&a_1, csprng);
@@ -1,5 +1,9 @@
 use crypto_common::*;
+use curve_arithmetic::Curve;
+use either::Either;
+use ff::Field;
 use random_oracle::*;
+use std::marker::PhantomData;
 
 /// The common data known to the prover and verifier, i.e., public values and
 /// coefficients.
@@ -286,6 +290,519 @@ impl<P: SigmaProtocol> ReplicateAdapter<P> {
     }
 }
 
+/// Sigma protocols for which the verifier's view can be simulated without
+/// knowledge of the secret, given the challenge in advance. This is what is
+/// needed to compose protocols disjunctively, see [OrAdapter] and
+/// [ThresholdAdapter].
+pub trait SimulatableSigmaProtocol: SigmaProtocol {
+    /// Generate a witness such that the witness, the given challenge, and the
+    /// commit message computed by `extract_point` from them, are distributed
+    /// in the same way as in an honest run of the protocol.
+    fn simulate_witness<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+        challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness>;
+}
+
+impl<P1: SimulatableSigmaProtocol, P2: SimulatableSigmaProtocol> SimulatableSigmaProtocol
+    for AndAdapter<P1, P2>
+{
+    fn simulate_witness<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+        challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        let w1 = self.first.simulate_witness(csprng, &challenge.0)?;
+        let w2 = self.second.simulate_witness(csprng, &challenge.1)?;
+        Some(AndWitness { w1, w2 })
+    }
+}
+
+impl<P: SimulatableSigmaProtocol> SimulatableSigmaProtocol for ReplicateAdapter<P> {
+    fn simulate_witness<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+        challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        let mut witnesses = Vec::with_capacity(self.protocols.len());
+        for p in self.protocols.iter() {
+            witnesses.push(p.simulate_witness(csprng, challenge)?);
+        }
+        Some(ReplicateWitness { witnesses })
+    }
+}
+
+// ## This section provides disjunctive composition of sigma protocols, using
+// the technique of Cramer, Damgård and Schoenmakers. The prover simulates the
+// protocols whose secrets they do not know, and the challenges of the
+// individual protocols are constrained so that the prover can choose only
+// some of them freely.
+
+/// Which of the two statements of an [OrAdapter] the prover knows the secret
+/// for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrSide {
+    First,
+    Second,
+}
+
+#[derive(Serialize)]
+pub struct OrWitness<C: Curve, W1: Serialize, W2: Serialize> {
+    /// The challenge of the first protocol. The challenge of the second
+    /// protocol is the overall challenge minus this one.
+    pub challenge_first: C::Scalar,
+    pub w1:              W1,
+    pub w2:              W2,
+}
+
+/// An adapter for proving that the prover knows the secret of at least one of
+/// two statements, without revealing which one. Both protocols must use
+/// scalars of the curve `C` as challenges.
+pub struct OrAdapter<C: Curve, P1, P2> {
+    pub first:    P1,
+    pub second:   P2,
+    /// The statement the prover knows the secret for. This is only needed when
+    /// proving, and proving fails if it is `None`. It is ignored by the
+    /// verifier.
+    pub known:    Option<OrSide>,
+    pub _phantom: PhantomData<C>,
+}
+
+impl<C: Curve, P1, P2> OrAdapter<C, P1, P2> {
+    /// Construct the adapter for the verifier, or for the prover if `known`
+    /// is set.
+    pub fn new(first: P1, second: P2, known: Option<OrSide>) -> Self {
+        OrAdapter {
+            first,
+            second,
+            known,
+            _phantom: Default::default(),
+        }
+    }
+}
+
+impl<
+        C: Curve,
+        P1: SimulatableSigmaProtocol<ProtocolChallenge = C::Scalar>,
+        P2: SimulatableSigmaProtocol<ProtocolChallenge = C::Scalar>,
+    > SigmaProtocol for OrAdapter<C, P1, P2>
+{
+    type CommitMessage = (P1::CommitMessage, P2::CommitMessage);
+    type ProtocolChallenge = C::Scalar;
+    /// The state of the protocol the prover knows the secret for, together
+    /// with the challenge and witness of the simulated protocol.
+    type ProverState = Either<
+        (P1::ProverState, C::Scalar, P2::ProverWitness),
+        (C::Scalar, P1::ProverWitness, P2::ProverState),
+    >;
+    type ProverWitness = OrWitness<C, P1::ProverWitness, P2::ProverWitness>;
+    /// The secret of the statement indicated by the `known` field.
+    type SecretData = Either<P1::SecretData, P2::SecretData>;
+
+    fn public(&self, ro: &mut RandomOracle) {
+        self.first.public(ro);
+        self.second.public(ro)
+    }
+
+    fn get_challenge(&self, challenge: &Challenge) -> Self::ProtocolChallenge {
+        C::scalar_from_bytes(challenge)
+    }
+
+    fn commit_point<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+    ) -> Option<(Self::CommitMessage, Self::ProverState)> {
+        match self.known? {
+            OrSide::First => {
+                let (m1, s1) = self.first.commit_point(csprng)?;
+                let c2 = C::generate_scalar(csprng);
+                let w2 = self.second.simulate_witness(csprng, &c2)?;
+                let m2 = self.second.extract_point(&c2, &w2)?;
+                Some(((m1, m2), Either::Left((s1, c2, w2))))
+            }
+            OrSide::Second => {
+                let (m2, s2) = self.second.commit_point(csprng)?;
+                let c1 = C::generate_scalar(csprng);
+                let w1 = self.first.simulate_witness(csprng, &c1)?;
+                let m1 = self.first.extract_point(&c1, &w1)?;
+                Some(((m1, m2), Either::Right((c1, w1, s2))))
+            }
+        }
+    }
+
+    fn generate_witness(
+        &self,
+        secret: Self::SecretData,
+        state: Self::ProverState,
+        challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        match (secret, state) {
+            (Either::Left(secret), Either::Left((s1, c2, w2))) => {
+                let mut c1 = *challenge;
+                c1.sub_assign(&c2);
+                let w1 = self.first.generate_witness(secret, s1, &c1)?;
+                Some(OrWitness {
+                    challenge_first: c1,
+                    w1,
+                    w2,
+                })
+            }
+            (Either::Right(secret), Either::Right((c1, w1, s2))) => {
+                let mut c2 = *challenge;
+                c2.sub_assign(&c1);
+                let w2 = self.second.generate_witness(secret, s2, &c2)?;
+                Some(OrWitness {
+                    challenge_first: c1,
+                    w1,
+                    w2,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn extract_point(
+        &self,
+        challenge: &Self::ProtocolChallenge,
+        witness: &Self::ProverWitness,
+    ) -> Option<Self::CommitMessage> {
+        let c1 = witness.challenge_first;
+        let mut c2 = *challenge;
+        c2.sub_assign(&c1);
+        let p1 = self.first.extract_point(&c1, &witness.w1)?;
+        let p2 = self.second.extract_point(&c2, &witness.w2)?;
+        Some((p1, p2))
+    }
+
+    #[cfg(test)]
+    fn with_valid_data<R: rand::Rng>(
+        data_size: usize,
+        csprng: &mut R,
+        f: impl FnOnce(Self, Self::SecretData, &mut R) -> (),
+    ) {
+        // Valid data for both statements is generated, but only the secret of
+        // one of them is given to the prover.
+        P1::with_valid_data(data_size, csprng, |first, s1, csprng| {
+            P2::with_valid_data(data_size, csprng, |second, s2, csprng| {
+                if csprng.gen::<bool>() {
+                    f(
+                        OrAdapter::new(first, second, Some(OrSide::First)),
+                        Either::Left(s1),
+                        csprng,
+                    )
+                } else {
+                    f(
+                        OrAdapter::new(first, second, Some(OrSide::Second)),
+                        Either::Right(s2),
+                        csprng,
+                    )
+                }
+            })
+        })
+    }
+}
+
+impl<
+        C: Curve,
+        P1: SimulatableSigmaProtocol<ProtocolChallenge = C::Scalar>,
+        P2: SimulatableSigmaProtocol<ProtocolChallenge = C::Scalar>,
+    > SimulatableSigmaProtocol for OrAdapter<C, P1, P2>
+{
+    fn simulate_witness<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+        challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        let c1 = C::generate_scalar(csprng);
+        let mut c2 = *challenge;
+        c2.sub_assign(&c1);
+        let w1 = self.first.simulate_witness(csprng, &c1)?;
+        let w2 = self.second.simulate_witness(csprng, &c2)?;
+        Some(OrWitness {
+            challenge_first: c1,
+            w1,
+            w2,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct ThresholdWitness<C: Curve, W: Serialize> {
+    /// The challenges of the first n-k protocols, where n is the number of
+    /// protocols and k the threshold. Together with the overall challenge
+    /// these determine the challenges of the remaining protocols.
+    #[size_length = 4]
+    pub challenges: Vec<C::Scalar>,
+    #[size_length = 4]
+    pub witnesses:  Vec<W>,
+}
+
+/// An adapter for proving that the prover knows the secrets of at least `k`
+/// out of `n` statements of the same kind, without revealing which ones.
+///
+/// The challenge of the i'th protocol (counting from 0) is f(i+1), where f is
+/// a polynomial of degree at most n-k with f(0) the overall challenge. The
+/// prover can thus choose the challenges of n-k protocols freely, and simulate
+/// those, but must answer the remaining k challenges honestly.
+pub struct ThresholdAdapter<C: Curve, P> {
+    /// The statements. This must be non-empty.
+    pub protocols: Vec<P>,
+    /// The number of statements the prover must know the secrets of. This must
+    /// be between 1 and the number of protocols.
+    pub threshold: usize,
+    /// Indices of the statements the prover knows the secrets of. The prover
+    /// must set exactly `threshold` distinct indices, the verifier should leave
+    /// this empty since it is ignored.
+    pub known:     Vec<usize>,
+    pub _phantom:  PhantomData<C>,
+}
+
+impl<C: Curve, P> ThresholdAdapter<C, P> {
+    /// Construct the adapter for the verifier, or for the prover if `known`
+    /// is non-empty.
+    pub fn new(protocols: Vec<P>, threshold: usize, known: Vec<usize>) -> Self {
+        ThresholdAdapter {
+            protocols,
+            threshold,
+            known,
+            _phantom: Default::default(),
+        }
+    }
+
+    /// Check that the threshold is meaningful for the number of protocols.
+    fn threshold_ok(&self) -> bool { self.threshold >= 1 && self.threshold <= self.protocols.len() }
+}
+
+/// Given points (x_j, y_j) with distinct x_j, evaluate at x the polynomial of
+/// the least degree that passes through them. Returns `None` if the points
+/// are not distinct.
+fn interpolate_at<F: Field>(points: &[(F, F)], x: &F) -> Option<F> {
+    let mut result = F::zero();
+    for (j, (x_j, y_j)) in points.iter().enumerate() {
+        let mut numerator = *y_j;
+        let mut denominator = F::one();
+        for (m, (x_m, _)) in points.iter().enumerate() {
+            if m != j {
+                let mut x_minus_x_m = *x;
+                x_minus_x_m.sub_assign(x_m);
+                numerator.mul_assign(&x_minus_x_m);
+                let mut x_j_minus_x_m = *x_j;
+                x_j_minus_x_m.sub_assign(x_m);
+                denominator.mul_assign(&x_j_minus_x_m);
+            }
+        }
+        numerator.mul_assign(&denominator.inverse()?);
+        result.add_assign(&numerator);
+    }
+    Some(result)
+}
+
+impl<C: Curve, P: SimulatableSigmaProtocol<ProtocolChallenge = C::Scalar>> ThresholdAdapter<C, P> {
+    /// Given the overall challenge and the challenges of some of the
+    /// protocols, as pairs of index and challenge, compute the challenges of
+    /// all the protocols. There must be exactly n-k given challenges.
+    fn complete_challenges(
+        &self,
+        challenge: &C::Scalar,
+        given: &[(usize, C::Scalar)],
+    ) -> Option<Vec<C::Scalar>> {
+        let n = self.protocols.len();
+        if !self.threshold_ok() || given.len() != n - self.threshold {
+            return None;
+        }
+        let point = |i: usize| C::scalar_from_u64(i as u64 + 1);
+        let mut points = Vec::with_capacity(given.len() + 1);
+        points.push((C::Scalar::zero(), *challenge));
+        let mut challenges = vec![None; n];
+        for &(i, c_i) in given {
+            *challenges.get_mut(i)? = Some(c_i);
+            points.push((point(i), c_i));
+        }
+        challenges
+            .into_iter()
+            .enumerate()
+            .map(|(i, c_i)| match c_i {
+                Some(c_i) => Some(c_i),
+                None => interpolate_at(&points, &point(i)),
+            })
+            .collect()
+    }
+}
+
+impl<C: Curve, P: SimulatableSigmaProtocol<ProtocolChallenge = C::Scalar>> SigmaProtocol
+    for ThresholdAdapter<C, P>
+{
+    type CommitMessage = ReplicatePoints<P::CommitMessage>;
+    type ProtocolChallenge = C::Scalar;
+    /// For each protocol, either the prover's state if the prover knows the
+    /// secret, or the challenge and witness of the simulation.
+    type ProverState = Vec<Either<P::ProverState, (C::Scalar, P::ProverWitness)>>;
+    type ProverWitness = ThresholdWitness<C, P::ProverWitness>;
+    /// The secrets of the statements indicated by the `known` field, in the
+    /// same order.
+    type SecretData = Vec<P::SecretData>;
+
+    fn public(&self, ro: &mut RandomOracle) {
+        ro.append_message("threshold", &(self.threshold as u64));
+        self.protocols.iter().for_each(|p| p.public(ro))
+    }
+
+    fn get_challenge(&self, challenge: &Challenge) -> Self::ProtocolChallenge {
+        C::scalar_from_bytes(challenge)
+    }
+
+    fn commit_point<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+    ) -> Option<(Self::CommitMessage, Self::ProverState)> {
+        let n = self.protocols.len();
+        if !self.threshold_ok() || self.known.len() != self.threshold {
+            return None;
+        }
+        let mut is_known = vec![false; n];
+        for &i in self.known.iter() {
+            let known = is_known.get_mut(i)?;
+            if *known {
+                return None;
+            }
+            *known = true;
+        }
+        let mut points = Vec::with_capacity(n);
+        let mut states = Vec::with_capacity(n);
+        for (p, known) in izip!(self.protocols.iter(), is_known) {
+            if known {
+                let (m, s) = p.commit_point(csprng)?;
+                points.push(m);
+                states.push(Either::Left(s));
+            } else {
+                let c = C::generate_scalar(csprng);
+                let w = p.simulate_witness(csprng, &c)?;
+                points.push(p.extract_point(&c, &w)?);
+                states.push(Either::Right((c, w)));
+            }
+        }
+        Some((ReplicatePoints { points }, states))
+    }
+
+    fn generate_witness(
+        &self,
+        secret: Self::SecretData,
+        state: Self::ProverState,
+        challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        let n = self.protocols.len();
+        if state.len() != n || secret.len() != self.known.len() {
+            return None;
+        }
+        let given: Vec<(usize, C::Scalar)> = state
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| s.as_ref().right().map(|(c, _)| (i, *c)))
+            .collect();
+        let challenges = self.complete_challenges(challenge, &given)?;
+        // Pair the secrets with the indices they belong to.
+        let mut secrets: Vec<Option<P::SecretData>> = (0..n).map(|_| None).collect();
+        for (&i, sec) in izip!(self.known.iter(), secret) {
+            *secrets.get_mut(i)? = Some(sec);
+        }
+        let mut witnesses = Vec::with_capacity(n);
+        for (p, s, sec, c) in izip!(self.protocols.iter(), state, secrets, challenges.iter()) {
+            match s {
+                Either::Left(s) => witnesses.push(p.generate_witness(sec?, s, c)?),
+                Either::Right((_, w)) => witnesses.push(w),
+            }
+        }
+        Some(ThresholdWitness {
+            challenges: challenges[0..n - self.threshold].to_vec(),
+            witnesses,
+        })
+    }
+
+    fn extract_point(
+        &self,
+        challenge: &Self::ProtocolChallenge,
+        witness: &Self::ProverWitness,
+    ) -> Option<Self::CommitMessage> {
+        let n = self.protocols.len();
+        if witness.witnesses.len() != n {
+            return None;
+        }
+        let given: Vec<(usize, C::Scalar)> =
+            witness.challenges.iter().copied().enumerate().collect();
+        let challenges = self.complete_challenges(challenge, &given)?;
+        let mut points = Vec::with_capacity(n);
+        for (p, c, w) in izip!(
+            self.protocols.iter(),
+            challenges.iter(),
+            witness.witnesses.iter()
+        ) {
+            points.push(p.extract_point(c, w)?);
+        }
+        Some(ReplicatePoints { points })
+    }
+
+    #[cfg(test)]
+    fn with_valid_data<R: rand::Rng>(
+        data_size: usize,
+        csprng: &mut R,
+        f: impl FnOnce(Self, Self::SecretData, &mut R) -> (),
+    ) {
+        // Generate n = data_size + 1 statements, and give the prover the secrets
+        // of a random subset of them, of random size k.
+        use rand::seq::SliceRandom;
+        let n = data_size + 1;
+        let threshold = csprng.gen_range(1, n + 1);
+        let mut known: Vec<usize> = (0..n).collect();
+        known.shuffle(csprng);
+        known.truncate(threshold);
+        let mut protocols = Vec::with_capacity(n);
+        let mut secrets = Vec::with_capacity(n);
+        for _ in 0..n {
+            P::with_valid_data(data_size, csprng, |p, s, _| {
+                protocols.push(p);
+                secrets.push(Some(s));
+            });
+        }
+        let secret = known
+            .iter()
+            .map(|&i| secrets[i].take().expect("Indices are distinct."))
+            .collect();
+        f(
+            ThresholdAdapter::new(protocols, threshold, known),
+            secret,
+            csprng,
+        )
+    }
+}
+
+impl<C: Curve, P: SimulatableSigmaProtocol<ProtocolChallenge = C::Scalar>> SimulatableSigmaProtocol
+    for ThresholdAdapter<C, P>
+{
+    fn simulate_witness<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+        challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        let n = self.protocols.len();
+        if !self.threshold_ok() {
+            return None;
+        }
+        let given: Vec<(usize, C::Scalar)> = (0..n - self.threshold)
+            .map(|i| (i, C::generate_scalar(csprng)))
+            .collect();
+        let challenges = self.complete_challenges(challenge, &given)?;
+        let mut witnesses = Vec::with_capacity(n);
+        for (p, c) in izip!(self.protocols.iter(), challenges.iter()) {
+            witnesses.push(p.simulate_witness(csprng, c)?);
+        }
+        Some(ThresholdWitness {
+            challenges: challenges[0..n - self.threshold].to_vec(),
+            witnesses,
+        })
+    }
+}
+
 /// Given a sigma protocol prover and a context (in the form of the random
 /// oracle), produce a sigma proof and update the context. This function can
 /// return 'None' if the input data is inconsistent.
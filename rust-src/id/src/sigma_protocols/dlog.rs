@@ -63,7 +63,7 @@ impl<C: Curve> SigmaProtocol for Dlog<C> {
         // If the challenge is zero, the proof is not going to be valid unless alpha
         // (randomised point) is also zero.
         let mut witness = *challenge;
-        witness.mul_assign(&secret.secret);
+        witness.mul_assign(secret.secret.as_ref());
         witness.add_assign(&state);
         Some(Witness { witness })
     }
@@ -88,7 +88,7 @@ impl<C: Curve> SigmaProtocol for Dlog<C> {
     ) {
         let secret = Value::generate(csprng);
         let base = C::generate(csprng);
-        let public = base.mul_by_scalar(&secret);
+        let public = secret.mul_point(&base);
         let dlog = Dlog {
             public,
             coeff: base,
@@ -97,6 +97,18 @@ impl<C: Curve> SigmaProtocol for Dlog<C> {
     }
 }
 
+impl<C: Curve> SimulatableSigmaProtocol for Dlog<C> {
+    fn simulate_witness<R: rand::Rng>(
+        &self,
+        csprng: &mut R,
+        _challenge: &Self::ProtocolChallenge,
+    ) -> Option<Self::ProverWitness> {
+        Some(Witness {
+            witness: C::generate_scalar(csprng),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,21 +1,22 @@
 //! This sigma protocol can be used to prove knowledge of x such that y_1 =
 //! g_1^x and y_2=g_2^x
-//!
-//! NB: This module is not used by the rest of the project and is only here for
-//! demonstration purposes.
-//! If it becomes necessary to use it, the code needs to be thoroughly reviewed.
-use curve_arithmetic::Curve;
-use id::sigma_protocols::{
+use crate::sigma_protocols::{
     common::*,
     dlog::{Witness as DlogWitness, *},
 };
+use curve_arithmetic::Curve;
+#[cfg(test)]
+use curve_arithmetic::Value;
 use random_oracle::{Challenge, RandomOracle};
 
-struct DlogEqual<C: Curve> {
-    dlog1: Dlog<C>,
-    dlog2: Dlog<C>,
+pub struct DlogEqual<C: Curve> {
+    pub dlog1: Dlog<C>,
+    pub dlog2: Dlog<C>,
 }
 
+/// Convenient alias for the proof of equality of discrete logarithms.
+pub type Proof<C> = SigmaProof<DlogWitness<C>>;
+
 impl<C: Curve> SigmaProtocol for DlogEqual<C> {
     type CommitMessage = (C, C);
     type ProtocolChallenge = C::Scalar;
@@ -49,7 +50,7 @@ impl<C: Curve> SigmaProtocol for DlogEqual<C> {
         state: Self::ProverState,
         challenge: &Self::ProtocolChallenge,
     ) -> Option<Self::ProverWitness> {
-        let w1 = self.dlog1.generate_witness(secret, state, &challenge)?;
+        let w1 = self.dlog1.generate_witness(secret, state, challenge)?;
         Some(w1)
     }
 
@@ -58,16 +59,35 @@ impl<C: Curve> SigmaProtocol for DlogEqual<C> {
         challenge: &Self::ProtocolChallenge,
         witness: &Self::ProverWitness,
     ) -> Option<Self::CommitMessage> {
-        let p1 = self.dlog1.extract_point(&challenge, &witness)?;
-        let p2 = self.dlog2.extract_point(&challenge, &witness)?;
+        let p1 = self.dlog1.extract_point(challenge, witness)?;
+        let p2 = self.dlog2.extract_point(challenge, witness)?;
         Some((p1, p2))
     }
+
+    #[cfg(test)]
+    fn with_valid_data<R: rand::Rng>(
+        _data_size: usize,
+        csprng: &mut R,
+        f: impl FnOnce(Self, Self::SecretData, &mut R) -> (),
+    ) {
+        let secret = Value::generate(csprng);
+        let coeff1 = C::generate(csprng);
+        let coeff2 = C::generate(csprng);
+        let dlog1 = Dlog {
+            public: secret.mul_point(&coeff1),
+            coeff:  coeff1,
+        };
+        let dlog2 = Dlog {
+            public: secret.mul_point(&coeff2),
+            coeff:  coeff2,
+        };
+        f(DlogEqual { dlog1, dlog2 }, DlogSecret { secret }, csprng);
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use curve_arithmetic::Value;
     use ff::PrimeField;
     use pairing::bls12_381::{Fr, G1};
     use rand::*;
@@ -5,10 +5,12 @@ pub mod com_enc_eq;
 pub mod com_eq;
 pub mod com_eq_different_groups;
 pub mod com_eq_sig;
+pub mod com_ineq;
 pub mod com_lin;
 pub mod com_mult;
 pub mod common;
 pub mod dlog;
+pub mod dlogeq;
 
 #[cfg(test)]
 pub mod sigma_test;
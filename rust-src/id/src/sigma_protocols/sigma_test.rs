@@ -1,5 +1,8 @@
-use crate::sigma_protocols::{com_enc_eq, com_eq_sig, common::*, dlog};
+use crate::sigma_protocols::{com_enc_eq, com_eq, com_eq_sig, common::*, dlog};
+use crypto_common::*;
+use curve_arithmetic::{Curve, Value};
 use pairing::bls12_381::{Bls12, G1, G2};
+use rand::Rng;
 use random_oracle::RandomOracle;
 
 #[test]
@@ -14,3 +17,114 @@ pub fn test_and() {
         assert!(verify(&mut RandomOracle::domain("test"), &prover, &proof))
     })
 }
+
+#[test]
+pub fn test_or() {
+    let mut csprng = rand::thread_rng();
+    for _ in 0..20 {
+        OrAdapter::<G1, dlog::Dlog<G1>, com_eq::ComEq<G1, G1>>::with_valid_data(
+            0,
+            &mut csprng,
+            |prover, secret, csprng| {
+                let proof = prove(&mut RandomOracle::domain("test"), &prover, secret, csprng)
+                    .expect("Proving should succeed.");
+                assert!(verify(&mut RandomOracle::domain("test"), &prover, &proof));
+                let proof = serialize_deserialize(&proof).expect("Proof should deserialize.");
+                assert!(verify(&mut RandomOracle::domain("test"), &prover, &proof));
+
+                // Changing either of the statements must make verification fail.
+                let wrong_public = G1::generate(csprng);
+                let verifier = OrAdapter::new(
+                    dlog::Dlog {
+                        public: wrong_public,
+                        coeff:  prover.first.coeff,
+                    },
+                    prover.second,
+                    None,
+                );
+                assert!(!verify(
+                    &mut RandomOracle::domain("test"),
+                    &verifier,
+                    &proof
+                ));
+                let verifier = OrAdapter::new(
+                    prover.first,
+                    com_eq::ComEq {
+                        y: wrong_public,
+                        ..verifier.second
+                    },
+                    None,
+                );
+                assert!(!verify(
+                    &mut RandomOracle::domain("test"),
+                    &verifier,
+                    &proof
+                ));
+            },
+        )
+    }
+}
+
+#[test]
+pub fn test_threshold() {
+    let mut csprng = rand::thread_rng();
+    for data_size in 0..10 {
+        ThresholdAdapter::<G1, dlog::Dlog<G1>>::with_valid_data(
+            data_size,
+            &mut csprng,
+            |prover, secret, csprng| {
+                let proof = prove(&mut RandomOracle::domain("test"), &prover, secret, csprng)
+                    .expect("Proving should succeed.");
+                let proof = serialize_deserialize(&proof).expect("Proof should deserialize.");
+                let mut verifier =
+                    ThresholdAdapter::new(prover.protocols, prover.threshold, Vec::new());
+                assert!(verify(&mut RandomOracle::domain("test"), &verifier, &proof));
+
+                // Lowering the threshold must make verification fail.
+                if verifier.threshold > 1 {
+                    verifier.threshold -= 1;
+                    assert!(!verify(
+                        &mut RandomOracle::domain("test"),
+                        &verifier,
+                        &proof
+                    ));
+                    verifier.threshold += 1;
+                }
+                // Changing any of the statements must make verification fail.
+                let i = csprng.gen_range(0, verifier.protocols.len());
+                verifier.protocols[i].public = G1::generate(csprng);
+                assert!(!verify(
+                    &mut RandomOracle::domain("test"),
+                    &verifier,
+                    &proof
+                ));
+            },
+        )
+    }
+}
+
+#[test]
+pub fn test_threshold_wrong_secret() {
+    // A prover who knows only one of the secrets cannot prove knowledge of two.
+    let mut csprng = rand::thread_rng();
+    let mut protocols = Vec::new();
+    let mut secrets = Vec::new();
+    for _ in 0..3 {
+        dlog::Dlog::<G1>::with_valid_data(0, &mut csprng, |p, s, _| {
+            protocols.push(p);
+            secrets.push(s);
+        });
+    }
+    let wrong_secret = dlog::DlogSecret {
+        secret: Value::generate(&mut csprng),
+    };
+    let prover = ThresholdAdapter::<G1, _>::new(protocols, 2, vec![0, 2]);
+    let proof = prove(
+        &mut RandomOracle::domain("test"),
+        &prover,
+        vec![secrets.remove(0), wrong_secret],
+        &mut csprng,
+    )
+    .expect("Proving should succeed.");
+    assert!(!verify(&mut RandomOracle::domain("test"), &prover, &proof));
+}
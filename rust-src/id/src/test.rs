@@ -4,11 +4,12 @@ use crate::{
     chain::*,
     constants::{ArCurve, BaseField, IpPairing, *},
     identity_provider::*,
+    revocation::*,
     secret_sharing::Threshold,
     types::*,
 };
 use crypto_common::{
-    types::{KeyIndex, KeyPair, TransactionTime},
+    types::{KeyIndex, KeyPair, Timestamp, TransactionTime},
     *,
 };
 use curve_arithmetic::Curve;
@@ -204,6 +205,7 @@ pub fn test_pipeline() {
     let des_initial = serialize_deserialize(&initial_cdi);
     assert!(des_initial.is_ok(), "Deserialization must be successful.");
 
+    let pio_id_cred_pub = pio.pub_info_for_ip.id_cred_pub;
     let id_object = IdentityObject {
         pre_identity_object: pio,
         alist,
@@ -221,6 +223,7 @@ pub fn test_pipeline() {
             tree
         },
         _phantom: Default::default(),
+        statements: Vec::new(),
     };
     let acc_data = CredentialData {
         keys:      {
@@ -232,19 +235,65 @@ pub fn test_pipeline() {
         },
         threshold: SignatureThreshold(2),
     };
+    // The identity provider publishes a status list in which another identity
+    // is revoked, and the credential is created with a proof that its identity
+    // is not in it.
+    let ip_cdi_keypair = ed25519::Keypair {
+        secret: ed25519::SecretKey::from_bytes(ip_cdi_secret_key.as_bytes())
+            .expect("Valid secret key."),
+        public: ip_info.ip_cdi_verify_key,
+    };
+    let mut status_list = StatusList::new(ip_info.ip_identity, Timestamp::from(0));
+    status_list.revoke(ArCurve::generate(&mut csprng));
+    let signed_status_list =
+        sign_status_list(status_list.clone(), &ip_cdi_keypair).expect("Signing does not fail.");
     let (cdi, _) = create_credential(
-        context,
+        context.clone(),
         &id_object,
         &id_use_data,
         0,
         policy.clone(),
+        &[],
         &acc_data,
         &Left(EXPIRY),
+        Some(&signed_status_list),
     )
     .expect("Should generate the credential successfully.");
+    assert!(cdi.proofs.proof_not_revoked.is_some());
     let cdi_check = verify_cdi(&global_ctx, &ip_info, &ars_infos, &cdi, &Left(EXPIRY));
     assert_eq!(cdi_check, Ok(()));
 
+    // Once the identity provider revokes the identity no credential can be
+    // created with respect to the new list, and the proof is not valid with
+    // respect to it.
+    status_list.revoke(pio_id_cred_pub);
+    let signed_status_list =
+        sign_status_list(status_list, &ip_cdi_keypair).expect("Signing does not fail.");
+    assert!(create_credential(
+        context.clone(),
+        &id_object,
+        &id_use_data,
+        0,
+        policy.clone(),
+        &[],
+        &acc_data,
+        &Left(EXPIRY),
+        Some(&signed_status_list),
+    )
+    .is_err());
+    let mut revoked_cdi = cdi.clone();
+    if let Some(proof_not_revoked) = revoked_cdi.proofs.proof_not_revoked.as_mut() {
+        proof_not_revoked.status_list = signed_status_list;
+    }
+    let cdi_check = verify_cdi(
+        &global_ctx,
+        &ip_info,
+        &ars_infos,
+        &revoked_cdi,
+        &Left(EXPIRY),
+    );
+    assert_eq!(cdi_check, Err(CdiVerificationError::Revoked));
+
     // Verify serialization
     let cdi_values = serialize_deserialize(&cdi.values);
     assert!(
@@ -271,10 +320,15 @@ pub fn test_pipeline() {
     // For now we just check that the last item in the proofs deserialized
     // correctly.
     assert_eq!(
-        des.unwrap().proofs.id_proofs.proof_reg_id,
+        des.as_ref().unwrap().proofs.id_proofs.proof_reg_id,
         cdi.proofs.id_proofs.proof_reg_id,
         "It should deserialize back to what we started with."
     );
+    assert_eq!(
+        des.unwrap().proofs.proof_not_revoked,
+        cdi.proofs.proof_not_revoked,
+        "The proof that the identity is not revoked should deserialize back."
+    );
 
     // Revoking anonymity using all but one AR
     let mut shares = Vec::new();
@@ -290,10 +344,14 @@ pub fn test_pipeline() {
     let revealed_id_cred_pub = reveal_id_cred_pub(&shares);
     assert_eq!(
         revealed_id_cred_pub,
-        global_ctx
-            .on_chain_commitment_key
-            .g
-            .mul_by_scalar(&id_use_data.aci.cred_holder_info.id_cred.id_cred_sec)
+        global_ctx.on_chain_commitment_key.g.mul_by_scalar(
+            id_use_data
+                .aci
+                .cred_holder_info
+                .id_cred
+                .id_cred_sec
+                .as_ref()
+        )
     );
 
     // generate a new cdi from a modified pre-identity object in which we swapped
@@ -305,8 +363,10 @@ pub fn test_pipeline() {
         &id_use_data,
         0,
         policy,
+        &[],
         &acc_data,
         &Left(EXPIRY),
+        None,
     )
     .expect("Should generate the credential successfully.");
     // Swap two ar_data values for two anonymity revokers.
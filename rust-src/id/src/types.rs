@@ -1,16 +1,21 @@
 //! Main types used in the identity layer, and their serialization
 //! implementations.
 use crate::{
+    ar_committee::{CommitteeKey, PartialDecryption},
+    revocation::CredentialNonRevocation,
     secret_sharing::Threshold,
     sigma_protocols::{
         com_enc_eq, com_eq, com_eq_different_groups, com_eq_sig, com_mult,
         common::{ReplicateAdapter, ReplicateWitness},
-        dlog,
+        dlog, dlogeq,
     },
 };
-use anyhow::{anyhow, bail};
+use anyhow::{anyhow, bail, ensure};
 use base58check::*; // only for account addresses
-use bulletproofs::range_proof::{Generators, RangeProof};
+use bulletproofs::{
+    r1cs::R1CSProof,
+    range_proof::{Generators, GeneratorsTable, RangeProof},
+};
 use byteorder::ReadBytesExt;
 use crypto_common::{
     types::{CredentialIndex, KeyIndex, KeyPair},
@@ -23,11 +28,13 @@ use dodis_yampolskiy_prf as prf;
 use ed25519_dalek as ed25519;
 use ed25519_dalek::Verifier;
 use either::Either;
-use elgamal::{ChunkSize, Cipher, Message, SecretKey as ElgamalSecretKey};
+use elgamal::{
+    ChunkSize, Cipher, Message, Randomness as ElgamalRandomness, SecretKey as ElgamalSecretKey,
+};
 use ff::Field;
 use hex::{decode, encode};
 use pedersen_scheme::{
-    Commitment as PedersenCommitment, CommitmentKey as PedersenKey,
+    Commitment as PedersenCommitment, CommitmentKey as PedersenKey, CommitmentKeyTable,
     Randomness as PedersenRandomness, Value as PedersenValue,
 };
 use random_oracle::Challenge;
@@ -43,6 +50,7 @@ use std::{
     fmt,
     io::{Cursor, Read},
     str::FromStr,
+    sync::Arc,
 };
 
 /// NB: This includes digits of PI (starting with 314...) as ASCII characters
@@ -479,7 +487,7 @@ pub trait Attribute<F: Field>: Clone + Sized + Send + Sync + fmt::Display + Seri
 /// The year is in Gregorian calendar and months are numbered from 1, i.e.,
 /// 1 is January, ..., 12 is December.
 /// Year must be a 4 digit year, i.e., between 1000 and 9999.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct YearMonth {
     pub year:  u16,
     pub month: u8,
@@ -710,6 +718,65 @@ pub struct IpArData<C: Curve> {
     pub proof_com_enc_eq:  com_enc_eq::Witness<C>,
 }
 
+/// Proof that a message is the decryption of a ciphertext under the public key
+/// of an anonymity revoker, see
+/// [verify_decryption](crate::anonymity_revoker::verify_decryption).
+#[derive(Debug, PartialEq, Eq, Clone, SerdeSerialize, SerdeDeserialize)]
+#[serde(tag = "type", content = "contents")]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+pub enum DecryptionProof<C: Curve> {
+    /// Proof that the anonymity revoker decrypted with the secret key
+    /// matching its public key.
+    #[serde(rename = "single")]
+    Single {
+        #[serde(rename = "proof")]
+        proof: dlogeq::Proof<C>,
+    },
+    /// The partial decryptions of the members of an anonymity revoker that is
+    /// a committee. They are checked against the key of the committee, which
+    /// is given once alongside the proofs.
+    #[serde(rename = "committee")]
+    Committee {
+        #[serde(rename = "partialDecryptions")]
+        partial_decryptions: Vec<PartialDecryption<C>>,
+    },
+}
+
+impl<C: Curve> Serial for DecryptionProof<C> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        match self {
+            DecryptionProof::Single { proof } => {
+                out.write_u8(0).expect("Writing to buffer is safe.");
+                out.put(proof);
+            }
+            DecryptionProof::Committee {
+                partial_decryptions,
+            } => {
+                out.write_u8(1).expect("Writing to buffer is safe.");
+                out.put(partial_decryptions);
+            }
+        }
+    }
+}
+
+impl<C: Curve> Deserial for DecryptionProof<C> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        match source.read_u8()? {
+            0 => {
+                let proof = source.get()?;
+                Ok(DecryptionProof::Single { proof })
+            }
+            1 => {
+                let partial_decryptions = source.get()?;
+                Ok(DecryptionProof::Committee {
+                    partial_decryptions,
+                })
+            }
+            n => bail!("Unknown decryption proof tag {}.", n),
+        }
+    }
+}
+
 /// Data structure for when a anonymity revoker decrypts its encrypted share
 /// This is the decrypted counterpart of IpArData.
 #[derive(Serialize, SerdeSerialize, SerdeDeserialize)]
@@ -721,6 +788,18 @@ pub struct IpArDecryptedData<C: Curve> {
     /// share of prf key
     #[serde(rename = "prfKeyShare")]
     pub prf_key_share: Value<C>,
+    /// proofs of correct decryption of each chunk of the encrypted share. They
+    /// are absent in shares decrypted by older tools.
+    #[serde(rename = "proofs", default, skip_serializing_if = "Vec::is_empty")]
+    pub proofs:        Vec<DecryptionProof<C>>,
+    /// the key of the committee if the anonymity revoker is a committee, used
+    /// to check the proofs
+    #[serde(
+        rename = "committeeKey",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub committee_key: Option<CommitteeKey<C>>,
 }
 
 /// Data relating to a single anonymity revoker sent by the account holder to
@@ -751,6 +830,18 @@ pub struct ChainArDecryptedData<C: Curve> {
     /// share of id cred pub
     #[serde(rename = "idCredPubShare")]
     pub id_cred_pub_share: Message<C>,
+    /// proof of correct decryption of the encrypted share. It is absent in
+    /// shares decrypted by older tools.
+    #[serde(rename = "proof", default, skip_serializing_if = "Option::is_none")]
+    pub proof:             Option<DecryptionProof<C>>,
+    /// the key of the committee if the anonymity revoker is a committee, used
+    /// to check the proof
+    #[serde(
+        rename = "committeeKey",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub committee_key:     Option<CommitteeKey<C>>,
 }
 
 // NOTE: This struct is redundant, but we will
@@ -871,6 +962,43 @@ pub struct IdentityObject<
     pub signature:           ps_sig::Signature<P>,
 }
 
+/// Proof that the holder of an identity object knows its IdCredSec and PRF
+/// key. This is part of the request to renew the identity object.
+#[derive(Serialize)]
+pub struct RenewalProof<C: Curve> {
+    /// Challenge for the combined proof.
+    pub challenge:           Challenge,
+    /// Witness to the proof of knowledge of IdCredSec, i.e., the discrete
+    /// logarithm of idCredPub.
+    pub id_cred_sec_witness: dlog::Witness<C>,
+    /// Witness to the proof of knowledge of the prf key, i.e., that
+    /// regId = PRF(prf_key, 0).
+    pub prf_key_witness:     dlog::Witness<C>,
+}
+
+/// Request of the account holder to renew an identity object, i.e., to get a
+/// new signature on it with a later `validTo` date.
+#[derive(Serialize, SerdeSerialize, SerdeDeserialize)]
+#[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
+pub struct IdentityRenewalRequest<C: Curve> {
+    /// The idCredPub of the identity object to renew.
+    #[serde(
+        rename = "idCredPub",
+        serialize_with = "base16_encode",
+        deserialize_with = "base16_decode"
+    )]
+    pub id_cred_pub: C,
+    /// The requested new end of validity of the identity object.
+    #[serde(rename = "validTo")]
+    pub valid_to:    YearMonth,
+    #[serde(
+        rename = "proof",
+        serialize_with = "base16_encode",
+        deserialize_with = "base16_decode"
+    )]
+    pub proof:       RenewalProof<C>,
+}
+
 /// Anonymity revokers associated with a single identity provider
 #[derive(Debug, Clone, Serialize, SerdeSerialize, SerdeDeserialize)]
 #[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
@@ -1050,11 +1178,17 @@ pub struct CommitmentsRandomness<C: Curve> {
 pub struct CredDeploymentProofs<P: Pairing, C: Curve<Scalar = P::ScalarField>> {
     /// Proofs that ensure that the credential is derived in a valid way from a
     /// valid identity object.
-    pub id_proofs:    IdOwnershipProofs<P, C>,
+    pub id_proofs:         IdOwnershipProofs<P, C>,
     /// Proof of knowledge of acc secret keys (signing keys corresponding to the
     /// verification keys either on the account already, or the ones which are
     /// part of this credential.
-    pub proof_acc_sk: AccountOwnershipProof,
+    pub proof_acc_sk:      AccountOwnershipProof,
+    /// Proof that the identity the credential is derived from is not revoked,
+    /// if the credential was created with respect to a status list. It is
+    /// serialized after the other proofs only if present, so that credentials
+    /// without it are serialized as before. It is boxed since it is usually
+    /// absent.
+    pub proof_not_revoked: Option<Box<CredentialNonRevocation<C>>>,
 }
 
 // This is an unfortunate situation, but we need to manually write a
@@ -1076,6 +1210,9 @@ impl<P: Pairing, C: Curve<Scalar = P::ScalarField>> Serial for CredDeploymentPro
         tmp_out.put(&self.id_proofs.proof_reg_id);
         tmp_out.put(&self.proof_acc_sk);
         tmp_out.put(&self.id_proofs.cred_counter_less_than_max_accounts);
+        if let Some(proof_not_revoked) = &self.proof_not_revoked {
+            tmp_out.put(proof_not_revoked);
+        }
         let len: u32 = tmp_out.len() as u32; // safe
         out.put(&len);
         out.write_all(&tmp_out).expect("Writing to buffer is safe.");
@@ -1097,6 +1234,11 @@ impl<P: Pairing, C: Curve<Scalar = P::ScalarField>> Deserial for CredDeploymentP
         let proof_reg_id = limited.get()?;
         let proof_acc_sk = limited.get()?;
         let cred_counter_less_than_max_accounts = limited.get()?;
+        let proof_not_revoked = if limited.limit() > 0 {
+            Some(limited.get()?)
+        } else {
+            None
+        };
         if limited.limit() == 0 {
             Ok(CredDeploymentProofs {
                 id_proofs: IdOwnershipProofs {
@@ -1109,6 +1251,7 @@ impl<P: Pairing, C: Curve<Scalar = P::ScalarField>> Deserial for CredDeploymentP
                     cred_counter_less_than_max_accounts,
                 },
                 proof_acc_sk,
+                proof_not_revoked,
             })
         } else {
             bail!("Length information is inaccurate. Credential proofs not valid.")
@@ -1180,32 +1323,292 @@ pub struct IdOwnershipProofs<P: Pairing, C: Curve<Scalar = P::ScalarField>> {
     pub cred_counter_less_than_max_accounts: RangeProof<C>,
 }
 
+/// A statement about an attribute that is committed to, but not revealed, in a
+/// credential. Statements are requested by the account holder when creating a
+/// credential, and are then included in the policy together with their proofs,
+/// see [AttributeStatementWithProof].
+#[derive(Debug, PartialEq, Eq, Clone, SerdeSerialize, SerdeDeserialize)]
+#[serde(tag = "type", content = "contents")]
+pub enum AttributeStatement<AttributeType> {
+    /// The attribute lies in the interval [lower, upper).
+    #[serde(rename = "attributeInRange")]
+    InRange {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "lower")]
+        lower:         AttributeType,
+        #[serde(rename = "upper")]
+        upper:         AttributeType,
+    },
+    /// The attribute is one of the values in the set.
+    #[serde(rename = "attributeInSet")]
+    InSet {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "set")]
+        set:           Vec<AttributeType>,
+    },
+    /// The attribute is none of the values in the set.
+    #[serde(rename = "attributeNotInSet")]
+    NotInSet {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "set")]
+        set:           Vec<AttributeType>,
+    },
+}
+
+impl<AttributeType> AttributeStatement<AttributeType> {
+    /// The attribute the statement is about.
+    pub fn attribute_tag(&self) -> AttributeTag {
+        match self {
+            AttributeStatement::InRange { attribute_tag, .. } => *attribute_tag,
+            AttributeStatement::InSet { attribute_tag, .. } => *attribute_tag,
+            AttributeStatement::NotInSet { attribute_tag, .. } => *attribute_tag,
+        }
+    }
+}
+
+/// A statement about a committed attribute together with its proof. The
+/// proof is with respect to the commitment to the attribute in the
+/// credential.
+#[derive(Debug, PartialEq, Eq, Clone, SerdeSerialize, SerdeDeserialize)]
+#[serde(tag = "type", content = "contents")]
+#[serde(bound(
+    serialize = "C: Curve, AttributeType: SerdeSerialize",
+    deserialize = "C: Curve, AttributeType: SerdeDeserialize<'de>"
+))]
+pub enum AttributeStatementWithProof<C: Curve, AttributeType> {
+    /// The attribute lies in the interval [lower, upper).
+    #[serde(rename = "attributeInRange")]
+    InRange {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "lower")]
+        lower:         AttributeType,
+        #[serde(rename = "upper")]
+        upper:         AttributeType,
+        #[serde(
+            rename = "proof",
+            serialize_with = "base16_encode",
+            deserialize_with = "base16_decode"
+        )]
+        proof:         RangeProof<C>,
+    },
+    /// The attribute is one of the values in the set.
+    #[serde(rename = "attributeInSet")]
+    InSet {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "set")]
+        set:           Vec<AttributeType>,
+        #[serde(
+            rename = "proof",
+            serialize_with = "base16_encode",
+            deserialize_with = "base16_decode"
+        )]
+        proof:         R1CSProof<C>,
+    },
+    /// The attribute is none of the values in the set.
+    #[serde(rename = "attributeNotInSet")]
+    NotInSet {
+        #[serde(rename = "attributeTag")]
+        attribute_tag: AttributeTag,
+        #[serde(rename = "set")]
+        set:           Vec<AttributeType>,
+        #[serde(
+            rename = "proof",
+            serialize_with = "base16_encode",
+            deserialize_with = "base16_decode"
+        )]
+        proof:         R1CSProof<C>,
+    },
+}
+
+impl<C: Curve, AttributeType> AttributeStatementWithProof<C, AttributeType> {
+    /// The attribute the statement is about.
+    pub fn attribute_tag(&self) -> AttributeTag {
+        match self {
+            AttributeStatementWithProof::InRange { attribute_tag, .. } => *attribute_tag,
+            AttributeStatementWithProof::InSet { attribute_tag, .. } => *attribute_tag,
+            AttributeStatementWithProof::NotInSet { attribute_tag, .. } => *attribute_tag,
+        }
+    }
+}
+
+impl<C: Curve, AttributeType: Attribute<C::Scalar>> Serial
+    for AttributeStatementWithProof<C, AttributeType>
+{
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        match self {
+            AttributeStatementWithProof::InRange {
+                attribute_tag,
+                lower,
+                upper,
+                proof,
+            } => {
+                0u8.serial(out);
+                out.put(attribute_tag);
+                out.put(lower);
+                out.put(upper);
+                out.put(proof);
+            }
+            AttributeStatementWithProof::InSet {
+                attribute_tag,
+                set,
+                proof,
+            } => {
+                1u8.serial(out);
+                out.put(attribute_tag);
+                out.put(&(set.len() as u16));
+                serial_vector_no_length(set, out);
+                out.put(proof);
+            }
+            AttributeStatementWithProof::NotInSet {
+                attribute_tag,
+                set,
+                proof,
+            } => {
+                2u8.serial(out);
+                out.put(attribute_tag);
+                out.put(&(set.len() as u16));
+                serial_vector_no_length(set, out);
+                out.put(proof);
+            }
+        }
+    }
+}
+
+impl<C: Curve, AttributeType: Attribute<C::Scalar>> Deserial
+    for AttributeStatementWithProof<C, AttributeType>
+{
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        match source.get()? {
+            0u8 => {
+                let attribute_tag = source.get()?;
+                let lower = source.get()?;
+                let upper = source.get()?;
+                let proof = source.get()?;
+                Ok(AttributeStatementWithProof::InRange {
+                    attribute_tag,
+                    lower,
+                    upper,
+                    proof,
+                })
+            }
+            1u8 => {
+                let attribute_tag = source.get()?;
+                let len: u16 = source.get()?;
+                let set = deserial_vector_no_length(source, usize::from(len))?;
+                let proof = source.get()?;
+                Ok(AttributeStatementWithProof::InSet {
+                    attribute_tag,
+                    set,
+                    proof,
+                })
+            }
+            2u8 => {
+                let attribute_tag = source.get()?;
+                let len: u16 = source.get()?;
+                let set = deserial_vector_no_length(source, usize::from(len))?;
+                let proof = source.get()?;
+                Ok(AttributeStatementWithProof::NotInSet {
+                    attribute_tag,
+                    set,
+                    proof,
+                })
+            }
+            n => bail!(
+                "AttributeStatementWithProof::deserial: Unsupported tag {}.",
+                n
+            ),
+        }
+    }
+}
+
+/// Version of the format of attribute statements in policies. It is part of
+/// both the binary and the JSON serialization of policies that contain
+/// statements.
+pub const ATTRIBUTE_STATEMENTS_VERSION: Version = Version { value: 1 };
+
+/// Bit set in the number of revealed attributes in the binary serialization of
+/// a policy to indicate that it is followed by attribute statements. Policies
+/// without statements are serialized in the original format, so that their
+/// serialization is unchanged. The number of attributes is less than 256 so
+/// the bit is never set otherwise.
+const POLICY_STATEMENTS_FLAG: u16 = 0x8000;
+
 #[derive(Debug, PartialEq, Eq, Clone, SerdeSerialize, SerdeDeserialize)]
 #[serde(bound(
     serialize = "C: Curve, AttributeType: Attribute<C::Scalar> + SerdeSerialize",
     deserialize = "C: Curve, AttributeType: Attribute<C::Scalar> + SerdeDeserialize<'de>"
 ))]
-/// A policy is (currently) revealed values of attributes that are part of the
-/// identity object. Policies are part of credentials.
+/// A policy consists of revealed values of attributes that are part of the
+/// identity object, and statements, with proofs, about attributes that are not
+/// revealed. Policies are part of credentials.
 pub struct Policy<C: Curve, AttributeType: Attribute<C::Scalar>> {
     #[serde(rename = "validTo")]
     pub valid_to:   YearMonth,
     #[serde(rename = "createdAt")]
     pub created_at: YearMonth,
-    /// Revealed attributes for now. In the future we might have
-    /// additional items with (Tag, Property, Proof).
+    /// Revealed attributes.
     #[serde(rename = "revealedAttributes")]
     pub policy_vec: BTreeMap<AttributeTag, AttributeType>,
+    /// Statements about attributes that are not revealed. In JSON these are
+    /// versioned, and omitted if there are none.
+    #[serde(
+        rename = "attributeStatements",
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_attribute_statements",
+        deserialize_with = "deserialize_attribute_statements"
+    )]
+    pub statements: Vec<AttributeStatementWithProof<C, AttributeType>>,
     #[serde(skip)]
     pub _phantom:   std::marker::PhantomData<C>,
 }
 
+fn serialize_attribute_statements<S: Serializer, C: Curve, AttributeType: SerdeSerialize>(
+    statements: &[AttributeStatementWithProof<C, AttributeType>],
+    ser: S,
+) -> Result<S::Ok, S::Error> {
+    Versioned::new(ATTRIBUTE_STATEMENTS_VERSION, statements).serialize(ser)
+}
+
+fn deserialize_attribute_statements<
+    'de,
+    D: Deserializer<'de>,
+    C: Curve,
+    AttributeType: SerdeDeserialize<'de>,
+>(
+    des: D,
+) -> Result<Vec<AttributeStatementWithProof<C, AttributeType>>, D::Error> {
+    let versioned: Versioned<Vec<AttributeStatementWithProof<C, AttributeType>>> =
+        Versioned::deserialize(des)?;
+    if versioned.version != ATTRIBUTE_STATEMENTS_VERSION {
+        return Err(de::Error::custom(format!(
+            "Unsupported version of attribute statements {}.",
+            versioned.version
+        )));
+    }
+    Ok(versioned.value)
+}
+
 impl<C: Curve, AttributeType: Attribute<C::Scalar>> Serial for Policy<C, AttributeType> {
     fn serial<B: Buffer>(&self, out: &mut B) {
         out.put(&self.valid_to);
         out.put(&self.created_at);
-        out.put(&(self.policy_vec.len() as u16));
-        serial_map_no_length(&self.policy_vec, out)
+        let len = self.policy_vec.len() as u16;
+        if self.statements.is_empty() {
+            out.put(&len);
+            serial_map_no_length(&self.policy_vec, out)
+        } else {
+            out.put(&(len | POLICY_STATEMENTS_FLAG));
+            serial_map_no_length(&self.policy_vec, out);
+            out.put(&ATTRIBUTE_STATEMENTS_VERSION);
+            out.put(&(self.statements.len() as u16));
+            serial_vector_no_length(&self.statements, out)
+        }
     }
 }
 
@@ -1214,11 +1617,29 @@ impl<C: Curve, AttributeType: Attribute<C::Scalar>> Deserial for Policy<C, Attri
         let valid_to = source.get()?;
         let created_at = source.get()?;
         let len: u16 = source.get()?;
-        let policy_vec = deserial_map_no_length(source, usize::from(len))?;
+        let has_statements = len & POLICY_STATEMENTS_FLAG != 0;
+        let policy_vec =
+            deserial_map_no_length(source, usize::from(len & !POLICY_STATEMENTS_FLAG))?;
+        let statements = if has_statements {
+            let version: Version = source.get()?;
+            ensure!(
+                version == ATTRIBUTE_STATEMENTS_VERSION,
+                "Unsupported version of attribute statements {}.",
+                version
+            );
+            let num_statements: u16 = source.get()?;
+            // The serialization must be canonical, so an empty list of statements
+            // must use the original format.
+            ensure!(num_statements > 0, "Policy has no attribute statements.");
+            deserial_vector_no_length(source, usize::from(num_statements))?
+        } else {
+            Vec::new()
+        };
         Ok(Policy {
             valid_to,
             created_at,
             policy_vec,
+            statements,
             _phantom: Default::default(),
         })
     }
@@ -1595,7 +2016,7 @@ pub struct IpContext<'a, P: Pairing, C: Curve<Scalar = P::ScalarField>> {
 
 impl<'a, P: Pairing, C: Curve<Scalar = P::ScalarField>> Copy for IpContext<'a, P, C> {}
 
-#[derive(Clone, Serialize, SerdeSerialize, SerdeDeserialize)]
+#[derive(Clone, SerdeSerialize, SerdeDeserialize)]
 #[serde(bound(serialize = "C: Curve", deserialize = "C: Curve"))]
 /// A set of cryptographic parameters that are particular to the chain and
 /// shared by everybody that interacts with the chain.
@@ -1604,17 +2025,68 @@ pub struct GlobalContext<C: Curve> {
     /// therefore it is public). The account holder uses this commitment key to
     /// generate commitments to values in the attribute list.
     #[serde(rename = "onChainCommitmentKey")]
-    pub on_chain_commitment_key: PedersenKey<C>,
+    pub on_chain_commitment_key:   PedersenKey<C>,
     /// Generators for the bulletproofs.
     /// It is unclear what length we will require here, or whether we'll allow
     /// dynamic generation.
     #[serde(rename = "bulletproofGenerators")]
-    bulletproof_generators:      Generators<C>,
-    #[string_size_length = 4]
+    bulletproof_generators:        Generators<C>,
     #[serde(rename = "genesisString")]
     /// A free-form string used to distinguish between different chains even if
     /// they share other parameters.
-    pub genesis_string:          String,
+    pub genesis_string:            String,
+    /// Tables of the on-chain commitment key, built when they are first
+    /// needed. They are not part of the serialization of the context, see
+    /// [GlobalContext::tables] for shipping them precomputed.
+    #[serde(skip)]
+    on_chain_commitment_key_table: TableCache<CommitmentKeyTable<C>>,
+}
+
+impl<C: Curve> Serial for GlobalContext<C> {
+    fn serial<B: Buffer>(&self, out: &mut B) {
+        out.put(&self.on_chain_commitment_key);
+        out.put(&self.bulletproof_generators);
+        out.put(&(self.genesis_string.len() as u32));
+        serial_string(self.genesis_string.as_str(), out);
+    }
+}
+
+impl<C: Curve> Deserial for GlobalContext<C> {
+    fn deserial<R: ReadBytesExt>(source: &mut R) -> ParseResult<Self> {
+        let on_chain_commitment_key = source.get()?;
+        let bulletproof_generators = source.get()?;
+        let len: u32 = source.get()?;
+        let genesis_string = deserial_string(source, usize::try_from(len)?)?;
+        Ok(GlobalContext {
+            on_chain_commitment_key,
+            bulletproof_generators,
+            genesis_string,
+            on_chain_commitment_key_table: Default::default(),
+        })
+    }
+}
+
+/// Precomputed tables of the generators of a [GlobalContext]. Building them is
+/// expensive, so wallets can ship them and install them with
+/// [GlobalContext::set_tables] instead. The points are stored uncompressed and
+/// are not checked when deserializing, so the tables must only be loaded from
+/// a trusted source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GlobalContextTables<C: Curve> {
+    /// Tables of the on-chain commitment key, whose generators are also the
+    /// ones used for encryption of amounts.
+    pub on_chain_commitment_key: CommitmentKeyTable<C>,
+    /// Tables of the bulletproof generators.
+    pub bulletproof_generators:  GeneratorsTable<C>,
+}
+
+impl<C: Curve> GlobalContextTables<C> {
+    /// The SHA256 digest of the serialization of the tables. Checking all the
+    /// points of the tables is as expensive as building them, so instead the
+    /// digest of tables that are known to be correct is recorded when they are
+    /// built, and [GlobalContext::set_tables] only installs tables with that
+    /// digest.
+    pub fn digest(&self) -> [u8; 32] { Sha256::digest(&to_bytes(self)).into() }
 }
 
 impl<C: Curve> GlobalContext<C> {
@@ -1650,8 +2122,9 @@ impl<C: Curve> GlobalContext<C> {
 
         GlobalContext {
             on_chain_commitment_key: cmm_key,
-            bulletproof_generators: Generators { G_H: generators },
+            bulletproof_generators: Generators::new(generators),
             genesis_string,
+            on_chain_commitment_key_table: Default::default(),
         }
     }
 
@@ -1675,6 +2148,76 @@ impl<C: Curve> GlobalContext<C> {
     /// A wrapper function to support changes in internal structure of the
     /// context in the future, e.g., lazy generation of generators.
     pub fn bulletproof_generators(&self) -> &Generators<C> { &self.bulletproof_generators }
+
+    /// Tables of the on-chain commitment key. Commitments computed with these
+    /// are the same as those computed with the key, but faster. The tables
+    /// are built the first time they are needed, unless they were installed
+    /// with [GlobalContext::set_tables], and built again if the key has
+    /// changed since.
+    pub fn on_chain_commitment_key_table(&self) -> Arc<CommitmentKeyTable<C>> {
+        self.on_chain_commitment_key_table.get_or_build(
+            |table| table.matches(&self.on_chain_commitment_key),
+            || CommitmentKeyTable::new(&self.on_chain_commitment_key),
+        )
+    }
+
+    /// Encrypt the value in the exponent of the generator for encryption in
+    /// the exponent, and return the randomness used. This is the same as
+    /// [elgamal::PublicKey::encrypt_exponent_rand_given_generator] with that
+    /// generator, but uses the tables of the context for the multiplications
+    /// of its generators.
+    pub fn encrypt_exponent_rand<R: rand::Rng>(
+        &self,
+        pk: &elgamal::PublicKey<C>,
+        value: &Value<C>,
+        csprng: &mut R,
+    ) -> (Cipher<C>, ElgamalRandomness<C>) {
+        let tables = self.on_chain_commitment_key_table();
+        let randomness = ElgamalRandomness::new(C::generate_scalar(csprng));
+        let c1 = if pk.generator == *self.elgamal_generator() {
+            tables.g().mul_by_secret(&randomness.randomness)
+        } else {
+            pk.generator.mul_by_secret(&randomness.randomness)
+        };
+        let c2 = pk
+            .key
+            .mul_by_secret(&randomness.randomness)
+            .plus_point(&tables.h().mul_by_secret(&value.value));
+        (Cipher(c1, c2), randomness)
+    }
+
+    /// All the precomputed tables of the context, building the ones that were
+    /// not built yet. These can be serialized and later installed with
+    /// [GlobalContext::set_tables].
+    pub fn tables(&self) -> GlobalContextTables<C> {
+        GlobalContextTables {
+            on_chain_commitment_key: (*self.on_chain_commitment_key_table()).clone(),
+            bulletproof_generators:  (*self.bulletproof_generators.table()).clone(),
+        }
+    }
+
+    /// Install precomputed tables, e.g., ones shipped with a wallet. The
+    /// expected digest of the tables, see [GlobalContextTables::digest], must
+    /// be obtained from a trusted source, e.g., compiled into the wallet,
+    /// while the tables themselves can be loaded from a file. Returns `false`
+    /// if the tables do not have the expected digest or are not for the
+    /// generators of this context, in which case they are not installed.
+    pub fn set_tables(&self, tables: GlobalContextTables<C>, digest: &[u8; 32]) -> bool {
+        if tables.digest() != *digest
+            || !tables
+                .on_chain_commitment_key
+                .matches(&self.on_chain_commitment_key)
+            || !tables
+                .bulletproof_generators
+                .matches(&self.bulletproof_generators)
+        {
+            return false;
+        }
+        self.on_chain_commitment_key_table
+            .set(tables.on_chain_commitment_key);
+        self.bulletproof_generators
+            .set_table(tables.bulletproof_generators)
+    }
 }
 
 /// Make a context in which the account holder can produce a pre-identity object
@@ -2178,4 +2721,152 @@ mod tests {
         let ym1_parsed = YearMonth::try_from(num).unwrap();
         assert_eq!(ym1, ym1_parsed);
     }
+
+    #[test]
+    fn test_policy_statements_serialization() {
+        use crate::{constants::AttributeKind, id_prover::prove_attribute_statement};
+        use pairing::bls12_381::G1;
+        use rand::thread_rng;
+
+        let mut csprng = thread_rng();
+        let global = GlobalContext::<G1>::generate(String::from("genesis_string"));
+        let keys = global.on_chain_commitment_key;
+        let attribute = AttributeKind::from(55);
+        let (_, randomness) = keys.commit(
+            &PedersenValue::<G1>::new(attribute.to_field_element()),
+            &mut csprng,
+        );
+        let mut policy_vec = BTreeMap::new();
+        policy_vec.insert(AttributeTag::from(8u8), AttributeKind::from(31));
+        let mut policy = Policy::<G1, AttributeKind> {
+            valid_to: YearMonth::new(2022, 5).unwrap(),
+            created_at: YearMonth::new(2020, 5).unwrap(),
+            policy_vec,
+            _phantom: Default::default(),
+            statements: Vec::new(),
+        };
+
+        // Policies without statements keep the original serialization.
+        let mut buf = Vec::new();
+        buf.put(&policy);
+        assert_eq!(&buf[6..8], &[0, 1], "Unexpected length of the policy.");
+        let json = serde_json::to_value(&policy).unwrap();
+        assert!(json.get("attributeStatements").is_none());
+
+        for statement in [
+            AttributeStatement::InRange {
+                attribute_tag: AttributeTag::from(0u8),
+                lower:         AttributeKind::from(50),
+                upper:         AttributeKind::from(60),
+            },
+            AttributeStatement::InSet {
+                attribute_tag: AttributeTag::from(0u8),
+                set:           vec![AttributeKind::from(54), AttributeKind::from(55)],
+            },
+            AttributeStatement::NotInSet {
+                attribute_tag: AttributeTag::from(0u8),
+                set:           vec![AttributeKind::from(56)],
+            },
+        ]
+        .iter()
+        {
+            policy.statements.push(
+                prove_attribute_statement(
+                    &mut random_oracle::RandomOracle::domain("test_statements"),
+                    global.bulletproof_generators(),
+                    &keys,
+                    statement,
+                    &attribute,
+                    &randomness,
+                )
+                .expect("Statement should be provable."),
+            );
+        }
+
+        let parsed = serialize_deserialize(&policy).expect("Policy should deserialize.");
+        assert_eq!(policy, parsed);
+        let json = serde_json::to_string(&policy).unwrap();
+        let parsed: Policy<G1, AttributeKind> = serde_json::from_str(&json).unwrap();
+        assert_eq!(policy, parsed);
+    }
+
+    #[test]
+    fn test_policy_statements_testdata() {
+        use crate::constants::{ArCurve, AttributeKind, IpPairing};
+
+        // The credential with attribute statements that the haskell tests check
+        // their serialization against.
+        let json = include_str!("../../../testdata/cdi-statements.json");
+        let bin = &include_bytes!("../../../testdata/cdi-statements.bin")[..];
+        let cdi: Versioned<CredentialDeploymentInfo<IpPairing, ArCurve, AttributeKind>> =
+            serde_json::from_str(json).expect("Credential should parse from JSON.");
+        assert_eq!(cdi.value.values.policy.statements.len(), 3);
+        assert_eq!(to_bytes(&cdi), bin);
+        let parsed: Versioned<CredentialDeploymentInfo<IpPairing, ArCurve, AttributeKind>> =
+            from_bytes(&mut std::io::Cursor::new(bin)).expect("Credential should deserialize.");
+        assert_eq!(parsed.value.values.policy, cdi.value.values.policy);
+    }
+
+    #[test]
+    fn test_global_context_tables() {
+        use pairing::bls12_381::G1;
+        use rand::thread_rng;
+
+        let mut csprng = thread_rng();
+        let global = GlobalContext::<G1>::generate_size(String::from("genesis_string"), 16);
+        // The tables are not part of the serialization of the context.
+        let mut expected = Vec::new();
+        expected.put(&global.on_chain_commitment_key);
+        expected.put(global.bulletproof_generators());
+        expected.put(&(global.genesis_string.len() as u32));
+        expected.extend_from_slice(global.genesis_string.as_bytes());
+        assert_eq!(to_bytes(&global), expected);
+
+        let tables = global.tables();
+        let digest = tables.digest();
+        let tables = serialize_deserialize(&tables).expect("Tables should deserialize.");
+        let parsed: GlobalContext<G1> =
+            serialize_deserialize(&global).expect("Context should deserialize.");
+        assert!(parsed.set_tables(tables.clone(), &digest));
+        assert_eq!(parsed.tables(), tables);
+        let other = GlobalContext::<G1>::generate_from_seed(String::from("other"), 16, b"other");
+        assert!(!other.set_tables(tables.clone(), &digest));
+        assert!(!other.set_tables(other.tables(), &digest));
+
+        // Tables that are for the right generators, but are wrong in any other
+        // place, are rejected.
+        let mut bytes = to_bytes(&tables);
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        let corrupted: GlobalContextTables<G1> = from_bytes(&mut std::io::Cursor::new(&bytes))
+            .expect("Corrupted tables should deserialize.");
+        let fresh: GlobalContext<G1> =
+            serialize_deserialize(&global).expect("Context should deserialize.");
+        assert!(!fresh.set_tables(corrupted, &digest));
+        assert!(fresh.set_tables(tables.clone(), &digest));
+
+        let sk = ElgamalSecretKey::generate(global.elgamal_generator(), &mut csprng);
+        let pk = elgamal::PublicKey::from(&sk);
+        let value = Value::<G1>::generate(&mut csprng);
+        let (cipher, randomness) = global.encrypt_exponent_rand(&pk, &value, &mut csprng);
+        assert_eq!(
+            cipher,
+            Cipher(
+                pk.generator.mul_by_scalar(&randomness),
+                pk.key.mul_by_scalar(&randomness).plus_point(
+                    &global
+                        .encryption_in_exponent_generator()
+                        .mul_by_scalar(value.as_ref())
+                )
+            )
+        );
+
+        // The tables follow changes of the public generators, also in clones
+        // that share them.
+        let mut changed = global.clone();
+        changed.on_chain_commitment_key = other.on_chain_commitment_key;
+        changed.bulletproof_generators.G_H = other.bulletproof_generators().G_H.clone();
+        assert_eq!(changed.tables(), other.tables());
+        assert_eq!(global.tables(), tables);
+    }
 }
@@ -1,6 +1,10 @@
 //! A collection of auxiliary functions that don't belong anywhere else.
 
-use crate::{secret_sharing::Threshold, types::*};
+use crate::{
+    secret_sharing::Threshold,
+    sigma_protocols::{common::AndAdapter, dlog},
+    types::*,
+};
 use anyhow::bail;
 use crypto_common::{
     to_bytes,
@@ -14,9 +18,48 @@ use elgamal::*;
 use ff::{Field, PrimeField};
 use pedersen_scheme::Commitment;
 use rand::*;
+use random_oracle::RandomOracle;
 use sha2::{Digest, Sha256};
 use std::collections::{btree_map::BTreeMap, BTreeSet};
 
+/// Convert the set of attribute values in a set membership or non-membership
+/// statement to field elements, and add them to the transcript. This is used by
+/// both the prover and the verifier to make sure that the proof is bound to the
+/// set.
+pub fn attribute_set_to_scalars<C: Curve, AttributeType: Attribute<C::Scalar>>(
+    transcript: &mut RandomOracle,
+    set: &[AttributeType],
+) -> Vec<C::Scalar> {
+    transcript.append_message(b"set_size", &(set.len() as u64));
+    set.iter()
+        .map(|v| {
+            let scalar = v.to_field_element();
+            transcript.append_message(b"set_element", &scalar);
+            scalar
+        })
+        .collect()
+}
+
+/// The transcript in which the attribute statements of a credential are proved
+/// and verified. The statements are part of the values of the credential, so
+/// the transcript cannot contain them. Instead it is bound to the credential by
+/// its registration id and the commitments to its attributes, so that the
+/// proofs cannot be reused in another credential. The statements themselves
+/// are bound to the proofs of the credential, whose transcript contains them.
+pub fn attribute_statements_transcript<C: Curve>(
+    cred_id: &C,
+    commitments: &CredentialDeploymentCommitments<C>,
+    addr: Option<&AccountAddress>,
+    global_context: &GlobalContext<C>,
+) -> RandomOracle {
+    let mut transcript = RandomOracle::domain("credential_attribute_statements");
+    transcript.append_message(b"cred_id", cred_id);
+    transcript.append_message(b"commitments", commitments);
+    transcript.append_message(b"address", &addr);
+    transcript.append_message(b"global_context", global_context);
+    transcript
+}
+
 /// Given a list of commitments g^{a_i}h^{r_i}
 /// and a point x (the share number), compute
 /// g^p(x)h^r(x) where
@@ -74,7 +117,7 @@ pub fn encrypt_prf_share<C: Curve, R: Rng>(
     let h = context.encryption_in_exponent_generator();
     // let mut ciphers = encrypt_in_chunks_given_generator(pk, share, CHUNK_SIZE, h,
     // csprng);
-    let chunks = value_to_chunks::<C>(share, CHUNK_SIZE);
+    let chunks = value_to_chunks::<C>(share.as_ref(), CHUNK_SIZE);
     let mut ciphers = pk.encrypt_exponent_vec_given_generator(&chunks, h, csprng);
     // these are guaranteed to exist because we used `ChunkSize::ThirtyTwo`. The
     // encryptions are in little-endian limbs, so the last one is the encryption
@@ -109,8 +152,14 @@ pub fn encrypt_prf_share<C: Curve, R: Rng>(
         randomness_8,
     ];
     let chunks = [
-        *chunks[0], *chunks[1], *chunks[2], *chunks[3], *chunks[4], *chunks[5], *chunks[6],
-        *chunks[7],
+        *chunks[0].as_ref(),
+        *chunks[1].as_ref(),
+        *chunks[2].as_ref(),
+        *chunks[3].as_ref(),
+        *chunks[4].as_ref(),
+        *chunks[5].as_ref(),
+        *chunks[6].as_ref(),
+        *chunks[7].as_ref(),
     ];
     (enc, rand, chunks)
 }
@@ -288,6 +337,50 @@ pub fn credential_hash_to_sign<
     to_sign.to_vec()
 }
 
+/// The statement proved in a request to renew the given identity object,
+/// together with the transcript in which it is proved. The statement is
+/// knowledge of IdCredSec, i.e., the discrete logarithm of idCredPub, and
+/// knowledge of the prf key, which is the discrete logarithm of
+/// `g - x * regId` with respect to `regId`, where `x` is the index of the
+/// initial credential, since `regId = PRF(prf_key, x) = g^(1/(prf_key + x))`.
+/// The transcript binds the proof to the identity object, as well as to the
+/// requested end of validity.
+pub fn renewal_statement<
+    P: Pairing,
+    C: Curve<Scalar = P::ScalarField>,
+    AttributeType: Attribute<C::Scalar>,
+>(
+    global_context: &GlobalContext<C>,
+    ip_info: &IpInfo<P>,
+    id_object: &IdentityObject<P, C, AttributeType>,
+    valid_to: YearMonth,
+) -> (RandomOracle, AndAdapter<dlog::Dlog<C>, dlog::Dlog<C>>) {
+    let pub_info_for_ip = &id_object.pre_identity_object.pub_info_for_ip;
+    let g = global_context.on_chain_commitment_key.g;
+    let reg_id = pub_info_for_ip.reg_id;
+    let x = C::scalar_from_u64(u64::from(crate::constants::INITIAL_CREDENTIAL_INDEX));
+
+    let mut transcript = RandomOracle::domain("IdentityRenewalProof");
+    transcript.append_message(b"ctx", &global_context);
+    transcript.append_message(b"ip_identity", &ip_info.ip_identity);
+    transcript.append_message(b"pub_info_for_ip", &pub_info_for_ip);
+    transcript.append_message(b"alist", &id_object.alist);
+    transcript.append_message(b"signature", &id_object.signature);
+    transcript.append_message(b"valid_to", &valid_to);
+
+    let statement = AndAdapter {
+        first:  dlog::Dlog {
+            public: pub_info_for_ip.id_cred_pub,
+            coeff:  g,
+        },
+        second: dlog::Dlog {
+            public: g.minus_point(&reg_id.mul_by_scalar(&x)),
+            coeff:  reg_id,
+        },
+    };
+    (transcript, statement)
+}
+
 /// Given two ordered iterators call the corresponding functions in the
 /// increasing order of keys. That is, essentially merge the two iterators into
 /// an ordered iterator and then map, but this is all done inline.
@@ -43,6 +43,7 @@ impl<C: Curve> std::borrow::Borrow<C> for Commitment<C> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use curve_arithmetic::Ristretto255;
     use pairing::bls12_381::{G1, G2};
     use rand::*;
     impl<C: Curve> Commitment<C> {
@@ -66,4 +67,9 @@ mod tests {
     macro_test_commitment_to_byte_conversion!(commitment_to_byte_conversion_bls12_381_g1, G1);
 
     macro_test_commitment_to_byte_conversion!(commitment_to_byte_conversion_bls12_381_g2, G2);
+
+    macro_test_commitment_to_byte_conversion!(
+        commitment_to_byte_conversion_ristretto255,
+        Ristretto255
+    );
 }
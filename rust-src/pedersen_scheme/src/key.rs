@@ -36,7 +36,9 @@ impl<C: Curve> CommitmentKey<C> {
 
     /// The low-level worker function that actually does the commitment.
     /// The interface is not very type-safe, hence the availability of other
-    /// functions.
+    /// functions. The time it takes depends on the value and the randomness,
+    /// so it must only be used with public ones, see
+    /// [CommitmentKey::hide_secret].
     pub fn hide_worker(&self, value: &C::Scalar, randomness: &C::Scalar) -> Commitment<C> {
         let h = self.h;
         let g = self.g;
@@ -46,10 +48,25 @@ impl<C: Curve> CommitmentKey<C> {
         Commitment(cmm) // hr.plus_point(&gm))
     }
 
+    /// Compute the commitment to the secret value with the secret randomness
+    /// in constant time, see [Curve::mul_by_secret].
+    pub fn hide_secret(
+        &self,
+        value: &Secret<C::Scalar>,
+        randomness: &Secret<C::Scalar>,
+    ) -> Commitment<C> {
+        Commitment(
+            self.g
+                .mul_by_secret(value)
+                .plus_point(&self.h.mul_by_secret(randomness)),
+        )
+    }
+
     #[inline(always)]
-    /// Hide the value inside a commitment using the given randomness.
+    /// Hide the value inside a commitment using the given randomness. This
+    /// takes constant time, see [CommitmentKey::hide_secret].
     pub fn hide<V: AsRef<C::Scalar>>(&self, s: &V, r: &Randomness<C>) -> Commitment<C> {
-        self.hide_worker(s.as_ref(), r.as_ref())
+        self.hide_secret(&Secret::new(*s.as_ref()), &r.randomness)
     }
 
     /// Prove that the commitment `self` contains the given value and
@@ -67,9 +84,127 @@ impl<C: Curve> CommitmentKey<C> {
     }
 }
 
+/// Computation of commitments with a fixed commitment key. This is implemented
+/// by the key itself, and by [CommitmentKeyTable], which computes the same
+/// commitments using precomputed tables.
+pub trait Committer<C: Curve> {
+    /// The key the commitments are computed with.
+    fn commitment_key(&self) -> CommitmentKey<C>;
+
+    /// Compute the commitment to the value with the given randomness, see
+    /// [CommitmentKey::hide_worker]. This must only be used with a public
+    /// value and randomness.
+    fn hide_worker(&self, value: &C::Scalar, randomness: &C::Scalar) -> Commitment<C>;
+
+    /// Compute the commitment to the secret value with the secret randomness
+    /// in constant time, see [CommitmentKey::hide_secret].
+    fn hide_secret(
+        &self,
+        value: &Secret<C::Scalar>,
+        randomness: &Secret<C::Scalar>,
+    ) -> Commitment<C>;
+
+    /// Hide the value inside a commitment using the given randomness. This
+    /// takes constant time, see [Committer::hide_secret].
+    fn hide<V: AsRef<C::Scalar>>(&self, s: &V, r: &Randomness<C>) -> Commitment<C> {
+        self.hide_secret(&Secret::new(*s.as_ref()), &r.randomness)
+    }
+
+    /// Commit to the given value using a freshly generated randomness, and
+    /// return the randomness that was generated.
+    fn commit<T: Rng, V: AsRef<C::Scalar>>(
+        &self,
+        s: &V,
+        csprng: &mut T,
+    ) -> (Commitment<C>, Randomness<C>) {
+        let r = Randomness::<C>::generate(csprng);
+        (self.hide(s, &r), r)
+    }
+}
+
+impl<C: Curve> Committer<C> for CommitmentKey<C> {
+    fn commitment_key(&self) -> CommitmentKey<C> { *self }
+
+    fn hide_worker(&self, value: &C::Scalar, randomness: &C::Scalar) -> Commitment<C> {
+        CommitmentKey::hide_worker(self, value, randomness)
+    }
+
+    fn hide_secret(
+        &self,
+        value: &Secret<C::Scalar>,
+        randomness: &Secret<C::Scalar>,
+    ) -> Commitment<C> {
+        CommitmentKey::hide_secret(self, value, randomness)
+    }
+}
+
+/// Precomputed tables of the generators of a commitment key. Commitments are
+/// computed with two table lookups instead of a multiexponentiation, which is
+/// worthwhile for keys that are used for many commitments, such as the
+/// on-chain commitment key.
+///
+/// Like [FixedBaseTable] the tables must only be deserialized from a trusted
+/// source.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct CommitmentKeyTable<C: Curve> {
+    g: FixedBaseTable<C>,
+    h: FixedBaseTable<C>,
+}
+
+impl<C: Curve> CommitmentKeyTable<C> {
+    /// Build the tables for the given key.
+    pub fn new(key: &CommitmentKey<C>) -> Self {
+        CommitmentKeyTable {
+            g: FixedBaseTable::new(&key.g, DEFAULT_FIXED_BASE_WINDOW),
+            h: FixedBaseTable::new(&key.h, DEFAULT_FIXED_BASE_WINDOW),
+        }
+    }
+
+    /// The table of the base the value is raised to.
+    pub fn g(&self) -> &FixedBaseTable<C> { &self.g }
+
+    /// The table of the base the randomness is raised to.
+    pub fn h(&self) -> &FixedBaseTable<C> { &self.h }
+
+    /// Whether these are the tables of the given key.
+    pub fn matches(&self, key: &CommitmentKey<C>) -> bool {
+        *self.g.base() == key.g && *self.h.base() == key.h
+    }
+}
+
+impl<C: Curve> Committer<C> for CommitmentKeyTable<C> {
+    fn commitment_key(&self) -> CommitmentKey<C> {
+        CommitmentKey {
+            g: *self.g.base(),
+            h: *self.h.base(),
+        }
+    }
+
+    fn hide_worker(&self, value: &C::Scalar, randomness: &C::Scalar) -> Commitment<C> {
+        Commitment(
+            self.g
+                .mul_by_scalar(value)
+                .plus_point(&self.h.mul_by_scalar(randomness)),
+        )
+    }
+
+    fn hide_secret(
+        &self,
+        value: &Secret<C::Scalar>,
+        randomness: &Secret<C::Scalar>,
+    ) -> Commitment<C> {
+        Commitment(
+            self.g
+                .mul_by_secret(value)
+                .plus_point(&self.h.mul_by_secret(randomness)),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use curve_arithmetic::Ristretto255;
     use pairing::bls12_381::{G1Affine, G2Affine, G1, G2};
 
     macro_rules! macro_test_key_byte_conversion {
@@ -92,6 +227,8 @@ mod tests {
 
     macro_test_key_byte_conversion!(key_byte_conversion_bls12_381_g2_affine, G2Affine);
 
+    macro_test_key_byte_conversion!(key_byte_conversion_ristretto255, Ristretto255);
+
     macro_rules! macro_test_commit_open {
         ($function_name:ident, $curve_type:path) => {
             #[test]
@@ -114,4 +251,25 @@ mod tests {
 
     macro_test_commit_open!(commit_open_bls12_381_g2_affine, G2Affine);
     macro_test_commit_open!(commit_open_bls12_381_g2_projective, G2);
+
+    macro_test_commit_open!(commit_open_ristretto255, Ristretto255);
+
+    #[test]
+    pub fn test_commitment_key_table() {
+        let mut csprng = thread_rng();
+        let key = CommitmentKey::<G1>::generate(&mut csprng);
+        let table = CommitmentKeyTable::new(&key);
+        assert!(table.matches(&key));
+        assert_eq!(table.commitment_key(), key);
+        for _i in 1..20 {
+            let value = Value::<G1>::generate(&mut csprng);
+            let (c, r) = table.commit(&value, &mut csprng);
+            assert_eq!(c, key.hide(&value, &r));
+            assert_eq!(c, key.hide_worker(value.as_ref(), r.as_ref()));
+            assert_eq!(c, table.hide_worker(value.as_ref(), r.as_ref()));
+        }
+        let table2 = serialize_deserialize(&table).expect("Deserialization should succeed.");
+        assert_eq!(table, table2);
+        assert!(!table.matches(&CommitmentKey::generate(&mut csprng)));
+    }
 }
@@ -25,11 +25,11 @@ pub struct Randomness<C: Curve> {
 impl<C: Curve> Deref for Randomness<C> {
     type Target = C::Scalar;
 
-    fn deref(&self) -> &C::Scalar { &self.randomness }
+    fn deref(&self) -> &C::Scalar { (*self.randomness).as_ref() }
 }
 
 impl<C: Curve> AsRef<C::Scalar> for Randomness<C> {
-    fn as_ref(&self) -> &C::Scalar { &self.randomness }
+    fn as_ref(&self) -> &C::Scalar { (*self.randomness).as_ref() }
 }
 
 impl<C: Curve> Randomness<C> {
@@ -75,6 +75,7 @@ impl<C: Curve> Randomness<C> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use curve_arithmetic::Ristretto255;
     use pairing::bls12_381::{G1Affine, G2Affine};
     macro_rules! macro_test_randomness_to_byte_conversion {
         ($function_name:ident, $curve_type:path) => {
@@ -101,4 +102,9 @@ mod tests {
         randomness_to_byte_conversion_bls12_381_g2_affine,
         G2Affine
     );
+
+    macro_test_randomness_to_byte_conversion!(
+        randomness_to_byte_conversion_ristretto255,
+        Ristretto255
+    );
 }
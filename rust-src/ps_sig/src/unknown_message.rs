@@ -45,11 +45,11 @@ pub struct SigRetrievalRandomness<P: Pairing> {
 impl<C: Pairing> std::ops::Deref for SigRetrievalRandomness<C> {
     type Target = C::ScalarField;
 
-    fn deref(&self) -> &C::ScalarField { &self.randomness }
+    fn deref(&self) -> &C::ScalarField { (*self.randomness).as_ref() }
 }
 
 impl<C: Pairing> AsRef<C::ScalarField> for SigRetrievalRandomness<C> {
-    fn as_ref(&self) -> &C::ScalarField { &self.randomness }
+    fn as_ref(&self) -> &C::ScalarField { (*self.randomness).as_ref() }
 }
 
 impl<C: Pairing> SigRetrievalRandomness<C> {